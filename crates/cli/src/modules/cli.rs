@@ -423,6 +423,12 @@ pub enum ServerCommands {
         /// Prefix to filter configuration entries by
         prefix: Option<String>,
     },
+
+    /// Show the effective configuration along with the source of each entry
+    EffectiveConfig {
+        /// Prefix to filter configuration entries by
+        prefix: Option<String>,
+    },
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -33,6 +33,16 @@ pub enum UpdateSettings {
     },
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct EffectiveValue {
+    #[serde(rename = "_id")]
+    pub key: String,
+    #[serde(rename = "_value")]
+    pub value: String,
+    #[serde(rename = "_source")]
+    pub source: String,
+}
+
 impl ServerCommands {
     pub async fn exec(self, client: Client) {
         match self {
@@ -106,6 +116,46 @@ impl ServerCommands {
                     eprintln!();
                 }
 
+                eprintln!(
+                    "\n\n{} key{} found.\n",
+                    results.len(),
+                    if results.len() == 1 { "" } else { "s" }
+                );
+            }
+            ServerCommands::EffectiveConfig { prefix } => {
+                let results = client
+                    .http_request::<Response<Vec<EffectiveValue>>, String>(
+                        Method::GET,
+                        &format!(
+                            "/api/settings/effective?prefix={}",
+                            prefix.unwrap_or_default()
+                        ),
+                        None,
+                    )
+                    .await
+                    .items;
+
+                if !results.is_empty() {
+                    let mut table = Table::new();
+                    table.add_row(Row::new(vec![
+                        Cell::new("Key").with_style(Attr::Bold),
+                        Cell::new("Value").with_style(Attr::Bold),
+                        Cell::new("Source").with_style(Attr::Bold),
+                    ]));
+
+                    for result in &results {
+                        table.add_row(Row::new(vec![
+                            Cell::new(&result.key),
+                            Cell::new(&result.value),
+                            Cell::new(&result.source),
+                        ]));
+                    }
+
+                    eprintln!();
+                    table.printstd();
+                    eprintln!();
+                }
+
                 eprintln!(
                     "\n\n{} key{} found.\n",
                     results.len(),
@@ -24,6 +24,19 @@ impl Server {
         email: &str,
         session_id: u64,
     ) -> trc::Result<Option<u32>> {
+        self.email_to_id_or_catch_all(directory, email, session_id)
+            .await
+            .map(|result| result.map(|(id, _)| id))
+    }
+
+    /// Resolves an address to an account id, also returning whether the
+    /// account was obtained from a catch-all address.
+    pub async fn email_to_id_or_catch_all(
+        &self,
+        directory: &Directory,
+        email: &str,
+        session_id: u64,
+    ) -> trc::Result<Option<(u32, bool)>> {
         let mut address = self
             .core
             .smtp
@@ -33,19 +46,18 @@ impl Server {
             .to_subaddress(self, email, session_id)
             .await;
 
-        for _ in 0..2 {
-            let result = directory.email_to_id(address.as_ref()).await?;
-
-            if result.is_some() {
-                return Ok(result);
-            } else if let Some(catch_all) = self
-                .core
-                .smtp
-                .session
-                .rcpt
-                .catch_all
-                .to_catch_all(self, email, session_id)
-                .await
+        for is_catch_all in [false, true] {
+            if let Some(id) = directory.email_to_id(address.as_ref()).await? {
+                return Ok(Some((id, is_catch_all)));
+            } else if !is_catch_all
+                && let Some(catch_all) = self
+                    .core
+                    .smtp
+                    .session
+                    .rcpt
+                    .catch_all
+                    .to_catch_all(self, email, session_id)
+                    .await
             {
                 address = catch_all;
             } else {
@@ -11,10 +11,10 @@ use directory::{
         manage::{ChangedPrincipals, ManageDirectory},
         PrincipalField,
     },
-    Permission, Principal, QueryBy, Type,
+    Permission, Permissions, Principal, QueryBy, Type,
 };
 use jmap_proto::{
-    request::RequestMethod,
+    request::{capability::Capability, RequestMethod},
     types::{acl::Acl, collection::Collection, id::Id},
 };
 use std::{
@@ -29,6 +29,7 @@ use utils::map::{
 };
 
 use crate::{
+    expr::{functions::ResolveVariable, if_block::IfBlock, Variable, V_AUTHENTICATED_AS},
     listener::limiter::{ConcurrencyLimiter, LimiterResult},
     Server, KV_TOKEN_REVISION,
 };
@@ -83,13 +84,18 @@ impl Server {
                 .map(|v| v as u32)
                 .collect(),
             access_to: VecMap::new(),
-            tenant: None,
+            tenant: match principal.tenant() {
+                Some(tenant_id) => self.get_tenant_info(tenant_id).await?,
+                None => None,
+            },
             name: principal.take_str(PrincipalField::Name).unwrap_or_default(),
             description: principal.take_str(PrincipalField::Description),
             emails: principal
                 .take_str_array(PrincipalField::Emails)
                 .unwrap_or_default(),
             quota: principal.quota(),
+            legal_hold: principal.has_field(PrincipalField::LegalHold),
+            locale: principal.take_str(PrincipalField::Locale),
             permissions,
             concurrent_imap_requests: self.core.imap.rate_concurrent.map(ConcurrencyLimiter::new),
             concurrent_http_requests: self
@@ -102,10 +108,20 @@ impl Server {
                 .jmap
                 .upload_max_concurrent
                 .map(ConcurrencyLimiter::new),
+            upload_max_bandwidth: None,
+            get_max_objects: None,
+            set_max_objects: None,
+            mailbox_max_count: None,
+            mailbox_max_depth: None,
+            keyword_events: true,
+            impersonator: None,
             obj_size: 0,
             revision,
         };
 
+        // Apply per-account limits
+        self.apply_account_limits(&mut access_token).await;
+
         for grant_account_id in [access_token.primary_id]
             .into_iter()
             .chain(access_token.member_of.iter().copied())
@@ -150,6 +166,73 @@ impl Server {
         Ok(access_token.update_size())
     }
 
+    async fn apply_account_limits(&self, access_token: &mut AccessToken) {
+        let limits = &self.core.jmap.account_limits;
+        let account = AccountVariables {
+            name: &access_token.name,
+        };
+
+        let request_max_concurrent = self
+            .eval_account_limit::<u64>(&limits.request_max_concurrent, &account)
+            .await;
+        let upload_max_concurrent = self
+            .eval_account_limit::<u64>(&limits.upload_max_concurrent, &account)
+            .await;
+        let upload_max_bandwidth = self
+            .eval_account_limit::<u64>(&limits.upload_max_bandwidth, &account)
+            .await;
+        let get_max_objects = self
+            .eval_account_limit::<usize>(&limits.get_max_objects, &account)
+            .await;
+        let set_max_objects = self
+            .eval_account_limit::<usize>(&limits.set_max_objects, &account)
+            .await;
+        let mailbox_max_count = self
+            .eval_account_limit::<usize>(&limits.mailbox_max_count, &account)
+            .await;
+        let mailbox_max_depth = self
+            .eval_account_limit::<usize>(&limits.mailbox_max_depth, &account)
+            .await;
+        let keyword_events = self
+            .eval_account_limit::<bool>(&limits.keyword_events, &account)
+            .await;
+
+        if let Some(max_concurrent) = request_max_concurrent {
+            access_token.concurrent_http_requests =
+                (max_concurrent > 0).then(|| ConcurrencyLimiter::new(max_concurrent));
+        }
+        if let Some(max_concurrent) = upload_max_concurrent {
+            access_token.concurrent_uploads =
+                (max_concurrent > 0).then(|| ConcurrencyLimiter::new(max_concurrent));
+        }
+        access_token.upload_max_bandwidth = upload_max_bandwidth.filter(|bytes| *bytes > 0);
+
+        // Per-account object limits can only tighten the global ones
+        access_token.get_max_objects =
+            get_max_objects.filter(|max| *max > 0 && *max < self.core.jmap.get_max_objects);
+        access_token.set_max_objects =
+            set_max_objects.filter(|max| *max > 0 && *max < self.core.jmap.set_max_objects);
+        access_token.mailbox_max_count = mailbox_max_count.filter(|max| {
+            *max > 0
+                && (self.core.jmap.mailbox_max_count == 0
+                    || *max < self.core.jmap.mailbox_max_count)
+        });
+        access_token.mailbox_max_depth =
+            mailbox_max_depth.filter(|max| *max > 0 && *max < self.core.jmap.mailbox_max_depth);
+        access_token.keyword_events = keyword_events.unwrap_or(true);
+    }
+
+    async fn eval_account_limit<T: for<'x> TryFrom<Variable<'x>>>(
+        &self,
+        if_block: &Option<IfBlock>,
+        account: &AccountVariables<'_>,
+    ) -> Option<T> {
+        match if_block {
+            Some(if_block) => self.eval_if(if_block, account, 0).await,
+            None => None,
+        }
+    }
+
     async fn build_access_token(&self, account_id: u32, revision: u64) -> trc::Result<AccessToken> {
         let err = match self.directory().query(QueryBy::Id(account_id), true).await {
             Ok(Some(principal)) => {
@@ -173,6 +256,41 @@ impl Server {
         }
     }
 
+    /// Builds a read-only access token for an account opened by an
+    /// administrator, these tokens are never cached.
+    pub async fn impersonation_access_token(
+        &self,
+        principal: Principal,
+        impersonator: u32,
+    ) -> trc::Result<Arc<AccessToken>> {
+        let revision = self
+            .fetch_token_revision(principal.id())
+            .await
+            .unwrap_or(u64::MAX);
+        let mut access_token = self
+            .build_access_token_from_principal(principal, revision)
+            .await?;
+
+        // Accounts with administrative permissions cannot be impersonated
+        if access_token.is_administrator() {
+            return Err(trc::SecurityEvent::Unauthorized
+                .into_err()
+                .details("Administrator accounts cannot be impersonated.")
+                .account_id(access_token.primary_id()));
+        }
+
+        let mut permissions = Permissions::new();
+        for permission in access_token.permissions() {
+            if permission.is_read_only_permission() {
+                permissions.set(permission.id());
+            }
+        }
+        access_token.permissions = permissions;
+        access_token.impersonator = Some(impersonator);
+
+        Ok(Arc::new(access_token))
+    }
+
     pub async fn get_access_token(
         &self,
         principal: impl Into<PrincipalOrId>,
@@ -358,6 +476,23 @@ impl From<Principal> for PrincipalOrId {
     }
 }
 
+struct AccountVariables<'x> {
+    name: &'x str,
+}
+
+impl ResolveVariable for AccountVariables<'_> {
+    fn resolve_variable(&self, variable: u32) -> Variable<'_> {
+        match variable {
+            V_AUTHENTICATED_AS => self.name.into(),
+            _ => Variable::default(),
+        }
+    }
+
+    fn resolve_global(&self, _: &str) -> Variable<'_> {
+        Variable::Integer(0)
+    }
+}
+
 impl PrincipalOrId {
     pub fn id(&self) -> u32 {
         match self {
@@ -412,6 +547,16 @@ impl AccessToken {
         self.primary_id == account_id
     }
 
+    pub fn is_impersonated(&self) -> bool {
+        self.impersonator.is_some()
+    }
+
+    pub fn is_administrator(&self) -> bool {
+        self.permissions()
+            .into_iter()
+            .any(|permission| !permission.is_user_permission())
+    }
+
     #[inline(always)]
     pub fn has_permission(&self, permission: Permission) -> bool {
         self.permissions.get(permission.id())
@@ -585,6 +730,7 @@ impl AccessToken {
                 jmap_proto::method::query::RequestArguments::Quota => {
                     Permission::JmapQuotaQueryChanges
                 }
+                jmap_proto::method::query::RequestArguments::Thread => Permission::JmapThreadQuery,
             },
             RequestMethod::Query(m) => match m.arguments {
                 jmap_proto::method::query::RequestArguments::Email(_) => Permission::JmapEmailQuery,
@@ -601,16 +747,20 @@ impl AccessToken {
                     Permission::JmapPrincipalQuery
                 }
                 jmap_proto::method::query::RequestArguments::Quota => Permission::JmapQuotaQuery,
+                jmap_proto::method::query::RequestArguments::Thread => Permission::JmapThreadQuery,
             },
             RequestMethod::SearchSnippet(_) => Permission::JmapSearchSnippet,
             RequestMethod::ValidateScript(_) => Permission::JmapSieveScriptValidate,
             RequestMethod::LookupBlob(_) => Permission::JmapBlobLookup,
+            RequestMethod::RecallEmail(_) => Permission::JmapEmailRecall,
             RequestMethod::UploadBlob(_) => Permission::JmapBlobUpload,
             RequestMethod::Echo(_) => Permission::JmapEcho,
             RequestMethod::Error(_) => return Ok(()),
         };
 
-        if self.has_permission(permission) {
+        if self.has_permission(permission)
+            && jmap_capability(permission).is_none_or(|c| self.has_jmap_capability(c))
+        {
             Ok(())
         } else {
             Err(trc::JmapEvent::Forbidden
@@ -619,6 +769,23 @@ impl AccessToken {
         }
     }
 
+    pub fn has_jmap_capability(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Submission => self.has_permission(Permission::JmapCapabilitySubmission),
+            Capability::VacationResponse => {
+                self.has_permission(Permission::JmapCapabilityVacationResponse)
+            }
+            Capability::Sieve => self.has_permission(Permission::JmapCapabilitySieve),
+            Capability::WebSocket => self.has_permission(Permission::JmapCapabilityWebSocket),
+            Capability::Blob => self.has_permission(Permission::JmapCapabilityBlob),
+            Capability::Core
+            | Capability::Mail
+            | Capability::Contacts
+            | Capability::Calendars
+            | Capability::Quota => true,
+        }
+    }
+
     pub fn as_resource_token(&self) -> ResourceToken {
         ResourceToken {
             account_id: self.primary_id,
@@ -651,7 +818,32 @@ impl AccessToken {
             + (self.access_to.len() * (std::mem::size_of::<u32>() + std::mem::size_of::<u64>()))
             + self.name.len()
             + self.description.as_ref().map_or(0, |v| v.len())
+            + self.locale.as_ref().map_or(0, |v| v.len())
             + self.emails.iter().map(|v| v.len()).sum::<usize>()) as u64;
         self
     }
 }
+
+// Capabilities that can be disabled per principal or role
+fn jmap_capability(permission: Permission) -> Option<Capability> {
+    match permission {
+        Permission::JmapEmailSubmissionGet
+        | Permission::JmapEmailSubmissionSet
+        | Permission::JmapEmailSubmissionChanges
+        | Permission::JmapEmailSubmissionQuery
+        | Permission::JmapEmailSubmissionQueryChanges => Some(Capability::Submission),
+        Permission::JmapVacationResponseGet | Permission::JmapVacationResponseSet => {
+            Some(Capability::VacationResponse)
+        }
+        Permission::JmapSieveScriptGet
+        | Permission::JmapSieveScriptSet
+        | Permission::JmapSieveScriptQuery
+        | Permission::JmapSieveScriptQueryChanges
+        | Permission::JmapSieveScriptValidate => Some(Capability::Sieve),
+        Permission::JmapBlobGet
+        | Permission::JmapBlobCopy
+        | Permission::JmapBlobLookup
+        | Permission::JmapBlobUpload => Some(Capability::Blob),
+        _ => None,
+    }
+}
@@ -38,6 +38,15 @@ pub struct AccessToken {
     pub concurrent_http_requests: Option<ConcurrencyLimiter>,
     pub concurrent_imap_requests: Option<ConcurrencyLimiter>,
     pub concurrent_uploads: Option<ConcurrencyLimiter>,
+    pub upload_max_bandwidth: Option<u64>,
+    pub get_max_objects: Option<usize>,
+    pub set_max_objects: Option<usize>,
+    pub mailbox_max_count: Option<usize>,
+    pub mailbox_max_depth: Option<usize>,
+    pub keyword_events: bool,
+    pub legal_hold: bool,
+    pub locale: Option<String>,
+    pub impersonator: Option<u32>,
     pub revision: u64,
     pub obj_size: u64,
 }
@@ -46,6 +55,7 @@ pub struct AccessToken {
 pub struct TenantInfo {
     pub id: u32,
     pub quota: u64,
+    pub max_outbound: u64,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -79,6 +89,17 @@ impl Server {
                     Err(err) => Err(err),
                 }
             }
+            Credentials::Plain { username, secret }
+                if self
+                    .core
+                    .jmap
+                    .impersonation_separator
+                    .as_ref()
+                    .is_some_and(|separator| username.contains(separator.as_str())) =>
+            {
+                self.authenticate_impersonation(req, directory, username, secret)
+                    .await
+            }
             _ => match self.authenticate_credentials(req, directory).await {
                 Ok(principal) => self.get_access_token(principal).await,
                 Err(err) => Err(err),
@@ -89,6 +110,94 @@ impl Server {
                 .assert_has_permission(Permission::Authenticate)
                 .map(|_| token)
         })
+        .inspect(|token| {
+            self.inner
+                .data
+                .active_sessions
+                .set_account_id(req.session_id, token.primary_id());
+        })
+    }
+
+    async fn authenticate_impersonation(
+        &self,
+        req: &AuthRequest<'_>,
+        directory: &Directory,
+        username: &str,
+        secret: &str,
+    ) -> trc::Result<Arc<AccessToken>> {
+        // Logins have the form <account><separator><administrator>
+        let (account_name, admin_name) = self
+            .core
+            .jmap
+            .impersonation_separator
+            .as_deref()
+            .and_then(|separator| username.rsplit_once(separator))
+            .filter(|(account_name, admin_name)| !account_name.is_empty() && !admin_name.is_empty())
+            .ok_or_else(|| {
+                trc::AuthEvent::Failed
+                    .into_err()
+                    .ctx(trc::Key::AccountName, username.to_string())
+                    .ctx(trc::Key::RemoteIp, req.remote_ip)
+            })?;
+
+        // Authenticate the administrator using their own credentials
+        let admin = self
+            .get_access_token(
+                self.authenticate_credentials(
+                    &AuthRequest {
+                        credentials: Credentials::Plain {
+                            username: admin_name.to_string(),
+                            secret: secret.to_string(),
+                        },
+                        session_id: req.session_id,
+                        remote_ip: req.remote_ip,
+                        return_member_of: true,
+                        directory: req.directory,
+                    },
+                    directory,
+                )
+                .await?,
+            )
+            .await?;
+        admin.assert_has_permission(Permission::ImpersonateReadOnly)?;
+
+        // Obtain the account to open
+        let principal = directory
+            .query(QueryBy::Name(account_name), req.return_member_of)
+            .await?
+            .filter(|principal| principal.id() != admin.primary_id())
+            .ok_or_else(|| {
+                trc::AuthEvent::Failed
+                    .into_err()
+                    .details("Account not found.")
+                    .ctx(trc::Key::AccountName, account_name.to_string())
+                    .ctx(trc::Key::RemoteIp, req.remote_ip)
+            })?;
+        let access_token = self
+            .impersonation_access_token(principal, admin.primary_id())
+            .await?;
+
+        // Tenant administrators can only open accounts within their tenant
+        if let Some(tenant) = admin.tenant {
+            if access_token.tenant.is_none_or(|t| t.id != tenant.id) {
+                return Err(trc::SecurityEvent::Unauthorized
+                    .into_err()
+                    .details("Account belongs to a different tenant.")
+                    .ctx(trc::Key::AccountName, account_name.to_string()));
+            }
+        }
+
+        trc::event!(
+            Auth(trc::AuthEvent::Impersonation),
+            AccountName = access_token.name.clone(),
+            AccountId = access_token.primary_id(),
+            Id = admin.primary_id(),
+            Details = admin.name.clone(),
+            RemoteIp = req.remote_ip,
+            SpanId = req.session_id,
+        );
+
+        Ok(access_token)
     }
 
     async fn authenticate_credentials(
@@ -0,0 +1,124 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use ahash::AHashMap;
+use utils::{config::Config, sanitize_language};
+
+#[derive(Debug, Clone)]
+pub struct Localization {
+    pub default_language: String,
+    pub catalog: AHashMap<String, AHashMap<String, String>>,
+    pub learn_expiry: Option<Duration>,
+}
+
+impl Localization {
+    pub fn parse(config: &mut Config) -> Self {
+        let default_language = match config.value("i18n.default-language") {
+            Some(value) => match sanitize_language(value) {
+                Some(language) => language,
+                None => {
+                    let err = format!("Invalid language tag {value:?}");
+                    config.new_parse_error("i18n.default-language", err);
+                    "en".to_string()
+                }
+            },
+            None => "en".to_string(),
+        };
+
+        // Translations are stored as i18n.catalog.<language>.<key>
+        let mut catalog: AHashMap<String, AHashMap<String, String>> = AHashMap::new();
+        let mut errors = vec![];
+        for (key, value) in config.iterate_prefix("i18n.catalog") {
+            if let Some((language, key)) = key
+                .split_once('.')
+                .filter(|(language, key)| !language.is_empty() && !key.is_empty())
+            {
+                if let Some(language) = sanitize_language(language) {
+                    catalog
+                        .entry(language)
+                        .or_default()
+                        .insert(key.to_string(), value.to_string());
+                } else {
+                    errors.push((
+                        format!("i18n.catalog.{language}"),
+                        format!("Invalid language tag {language:?}"),
+                    ));
+                }
+            }
+        }
+        for (key, error) in errors {
+            config.new_parse_error(key, error);
+        }
+
+        Localization {
+            default_language,
+            catalog,
+            learn_expiry: config
+                .property_or_default::<Option<Duration>>("i18n.learn.expiry", "30d")
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn has_translations(&self) -> bool {
+        !self.catalog.is_empty()
+    }
+
+    /// Returns the translation of a key in the requested language, falling back
+    /// to its primary subtag, the default language and the built-in catalog.
+    pub fn get(&self, language: &str, key: &str) -> Option<&str> {
+        let primary = language.split_once('-').map(|(primary, _)| primary);
+        [
+            Some(language),
+            primary,
+            Some(self.default_language.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|language| self.catalog.get(language)?.get(key))
+        .map(|value| value.as_str())
+        .or_else(|| builtin(key))
+    }
+
+    pub fn text<'x>(&'x self, language: &str, key: &'x str) -> &'x str {
+        self.get(language, key).unwrap_or(key)
+    }
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Localization {
+            default_language: "en".to_string(),
+            catalog: AHashMap::new(),
+            learn_expiry: Some(Duration::from_secs(30 * 86400)),
+        }
+    }
+}
+
+fn builtin(key: &str) -> Option<&'static str> {
+    match key {
+        "dsn.subject.success" => "Successfully delivered message",
+        "dsn.subject.delay" => "Warning: Delay in message delivery",
+        "dsn.subject.failure" => "Failed to deliver message",
+        "dsn.subject.partial" => "Partially delivered message",
+        "dsn.subject.mixed" => "Warning: Temporary and permanent failures during message delivery",
+        "dsn.body.success" => {
+            "Your message has been successfully delivered to the following recipients:"
+        }
+        "dsn.body.delay" => {
+            "There was a temporary problem delivering your message to the following recipients:"
+        }
+        "dsn.body.failure" => "Your message could not be delivered to the following recipients:",
+        "dsn.body.partial" => "Your message has been partially delivered:",
+        "dsn.body.mixed" => "Your message could not be delivered to some recipients:",
+        "dsn.section.success" => "Delivery to the following addresses was successful",
+        "dsn.section.delay" => "There was a temporary problem delivering to these addresses",
+        "dsn.section.failure" => "Delivery to the following addresses failed",
+        _ => return None,
+    }
+    .into()
+}
@@ -8,6 +8,10 @@ use std::time::Duration;
 
 use utils::config::{Config, Rate};
 
+use crate::expr::{if_block::IfBlock, tokenizer::TokenMap, V_AUTHENTICATED_AS};
+
+use super::CONNECTION_VARS;
+
 #[derive(Default, Clone)]
 pub struct ImapConfig {
     pub max_request_size: usize,
@@ -20,10 +24,19 @@ pub struct ImapConfig {
 
     pub rate_requests: Option<Rate>,
     pub rate_concurrent: Option<u64>,
+
+    pub labels_folder: Option<String>,
+
+    pub extension_idle: Option<IfBlock>,
+    pub extension_pop3_top: Option<IfBlock>,
 }
 
 impl ImapConfig {
     pub fn parse(config: &mut Config) -> Self {
+        let token_map = &TokenMap::default()
+            .with_variables(CONNECTION_VARS)
+            .with_variables(&[V_AUTHENTICATED_AS]);
+
         ImapConfig {
             max_request_size: config
                 .property_or_default("imap.request.max-size", "52428800")
@@ -49,6 +62,19 @@ impl ImapConfig {
             allow_plain_auth: config
                 .property_or_default("imap.auth.allow-plain-text", "false")
                 .unwrap_or(false),
+            labels_folder: config
+                .property_or_default::<bool>("imap.folders.labels.enable", "false")
+                .unwrap_or(false)
+                .then(|| {
+                    config
+                        .value("imap.folders.labels.name")
+                        .unwrap_or("Labels")
+                        .trim_matches('/')
+                        .to_string()
+                })
+                .filter(|name| !name.is_empty()),
+            extension_idle: IfBlock::try_parse(config, "imap.extensions.idle", token_map),
+            extension_pop3_top: IfBlock::try_parse(config, "pop3.extensions.top", token_map),
         }
     }
 }
@@ -24,10 +24,10 @@ use utils::{
 use crate::{
     auth::{roles::RolePermissions, AccessToken},
     config::smtp::resolver::{Policy, Tlsa},
-    listener::blocked::BlockedIps,
+    listener::blocked::IpList,
     manager::webadmin::WebAdminManager,
-    Account, AccountId, Caches, Data, Mailbox, MailboxId, MailboxState, NextMailboxState, Threads,
-    TlsConnectors,
+    Account, AccountId, Caches, Conversation, Conversations, Data, Mailbox, MailboxId,
+    MailboxState, NextMailboxState, Threads, TlsConnectors,
 };
 
 use super::server::tls::{build_self_signed_cert, parse_certificates};
@@ -59,7 +59,8 @@ impl Data {
             })
             .ok()
             .map(Arc::new),
-            blocked_ips: RwLock::new(BlockedIps::parse(config).blocked_ip_addresses),
+            blocked_ips: RwLock::new(IpList::parse_blocked(config)),
+            allowed_ips: RwLock::new(IpList::parse_allowed(config)),
             blocked_ips_version: 0.into(),
             jmap_id_gen: id_generator.clone(),
             queue_id_gen: id_generator.clone(),
@@ -73,6 +74,14 @@ impl Data {
             logos: Default::default(),
             smtp_connectors: TlsConnectors::default(),
             asn_geo_data: Default::default(),
+            spam_tag_hits: Default::default(),
+            active_sessions: Default::default(),
+            bootstrap_token: Default::default(),
+            migration: Default::default(),
+            restart: Default::default(),
+            queue_admission: Default::default(),
+            queue_shaping: Default::default(),
+            sandbox_stats: Default::default(),
         }
     }
 }
@@ -125,6 +134,13 @@ impl Caches {
                 MB_10,
                 (std::mem::size_of::<Threads>() + (500 * std::mem::size_of::<u64>())) as u64,
             ),
+            conversations: Cache::from_config(
+                config,
+                "conversation",
+                MB_10,
+                (std::mem::size_of::<Conversations>()
+                    + (500 * (std::mem::size_of::<Conversation>() + 64))) as u64,
+            ),
             bayes: CacheWithTtl::from_config(
                 config,
                 "bayes",
@@ -179,6 +195,7 @@ impl Caches {
                 MB_5,
                 ((std::mem::size_of::<Ipv4Addr>() + 255) * 2) as u64,
             ),
+            lookup_queries: CacheWithTtl::from_config(config, "lookup-query", MB_5, 512),
         }
     }
 
@@ -213,6 +230,7 @@ impl Default for Data {
             tls_certificates: Default::default(),
             tls_self_signed_cert: Default::default(),
             blocked_ips: Default::default(),
+            allowed_ips: Default::default(),
             blocked_ips_version: 0.into(),
             jmap_id_gen: Default::default(),
             queue_id_gen: Default::default(),
@@ -223,6 +241,14 @@ impl Default for Data {
             logos: Default::default(),
             smtp_connectors: Default::default(),
             asn_geo_data: Default::default(),
+            spam_tag_hits: Default::default(),
+            active_sessions: Default::default(),
+            bootstrap_token: Default::default(),
+            migration: Default::default(),
+            restart: Default::default(),
+            queue_admission: Default::default(),
+            queue_shaping: Default::default(),
+            sandbox_stats: Default::default(),
         }
     }
 }
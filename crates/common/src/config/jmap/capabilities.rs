@@ -15,6 +15,8 @@ use jmap_proto::{
 };
 use utils::{config::Config, map::vec_map::VecMap};
 
+use crate::config::scripts::UntrustedExtensions;
+
 use super::settings::JmapConfig;
 
 impl JmapConfig {
@@ -116,6 +118,9 @@ impl JmapConfig {
         for (_, capability) in config.values("sieve.untrusted.disabled-capabilities") {
             capabilities.remove(&sieve::compiler::grammar::Capability::parse(capability));
         }
+        for capability in UntrustedExtensions::parse(config).disabled {
+            capabilities.remove(&capability);
+        }
 
         let mut extensions = capabilities
             .into_iter()
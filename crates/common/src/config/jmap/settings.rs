@@ -4,11 +4,34 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{str::FromStr, time::Duration};
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
 
-use jmap_proto::request::capability::BaseCapabilities;
+use ahash::AHashMap;
+use hyper::HeaderMap;
+use jmap_proto::{request::capability::BaseCapabilities, types::keyword::Keyword};
 use nlp::language::Language;
-use utils::config::{cron::SimpleCron, utils::ParseValue, Config, Rate};
+use regex::Regex;
+use utils::{
+    config::{cron::SimpleCron, utils::ParseValue, Config, Rate},
+    sanitize_language,
+};
+
+use crate::{
+    config::parse_http_headers,
+    expr::{if_block::IfBlock, tokenizer::TokenMap, V_AUTHENTICATED_AS},
+};
+
+#[derive(Default, Clone)]
+pub struct AccountLimits {
+    pub request_max_concurrent: Option<IfBlock>,
+    pub upload_max_concurrent: Option<IfBlock>,
+    pub upload_max_bandwidth: Option<IfBlock>,
+    pub get_max_objects: Option<IfBlock>,
+    pub set_max_objects: Option<IfBlock>,
+    pub mailbox_max_count: Option<IfBlock>,
+    pub mailbox_max_depth: Option<IfBlock>,
+    pub keyword_events: Option<IfBlock>,
+}
 
 #[derive(Default, Clone)]
 pub struct JmapConfig {
@@ -22,6 +45,8 @@ pub struct JmapConfig {
     pub request_max_size: usize,
     pub request_max_calls: usize,
     pub request_max_concurrent: Option<u64>,
+    pub manage_max_body_size: AHashMap<String, usize>,
+    pub idempotency_ttl: Option<Duration>,
 
     pub get_max_objects: usize,
     pub set_max_objects: usize,
@@ -33,12 +58,19 @@ pub struct JmapConfig {
     pub upload_tmp_quota_amount: usize,
     pub upload_tmp_ttl: u64,
 
+    pub account_limits: AccountLimits,
+
     pub mailbox_max_depth: usize,
+    pub mailbox_max_count: usize,
     pub mailbox_name_max_len: usize,
+    pub mailbox_deny_names: Vec<Regex>,
     pub mail_attachments_max_size: usize,
     pub mail_parse_max_items: usize,
     pub mail_max_size: usize,
+    pub mail_max_messages: u64,
     pub mail_autoexpunge_after: Option<Duration>,
+    pub mail_recall_max_age: Option<Duration>,
+    pub mail_keyword_events: Vec<Keyword>,
 
     pub sieve_max_script_name: usize,
     pub sieve_max_scripts: usize,
@@ -61,9 +93,13 @@ pub struct JmapConfig {
 
     pub fallback_admin: Option<(String, String)>,
     pub master_user: Option<(String, String)>,
+    pub impersonation_separator: Option<String>,
 
     pub default_folders: Vec<DefaultFolder>,
+    pub folder_mappings: Vec<FolderMapping>,
     pub shared_folder: String,
+    pub public_folder: String,
+    pub public_account: Option<String>,
 
     pub http_headers: Vec<(hyper::header::HeaderName, hyper::header::HeaderValue)>,
     pub http_use_forwarded: bool,
@@ -73,6 +109,104 @@ pub struct JmapConfig {
 
     pub capabilities: BaseCapabilities,
     pub account_purge_frequency: SimpleCron,
+
+    pub fetch_max_accounts: usize,
+    pub fetch_frequency: Duration,
+    pub fetch_max_messages: usize,
+    pub fetch_timeout: Duration,
+    pub fetch_allow_invalid_certs: bool,
+    pub fetch_oauth_clients: AHashMap<String, FetchOAuthClient>,
+
+    pub text_extractor: TextExtractorConfig,
+    pub index_workers: usize,
+
+    pub submission_templates: AHashMap<String, MessageTemplate>,
+    pub account_templates: Vec<AccountTemplate>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MessageTemplate {
+    pub from_name: Option<String>,
+    pub from_address: Option<String>,
+    pub subject: String,
+    pub text_body: Option<String>,
+    pub html_body: Option<String>,
+    pub translations: AHashMap<String, MessageTemplateTranslation>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MessageTemplateTranslation {
+    pub subject: Option<String>,
+    pub text_body: Option<String>,
+    pub html_body: Option<String>,
+}
+
+impl MessageTemplate {
+    pub fn subject(&self, language: &str) -> &str {
+        self.translation(language)
+            .and_then(|t| t.subject.as_deref())
+            .unwrap_or(&self.subject)
+    }
+
+    /// Returns the text and HTML bodies for a language, the bodies of a
+    /// translation are never mixed with the untranslated ones.
+    pub fn bodies(&self, language: &str) -> (Option<&str>, Option<&str>) {
+        match self
+            .translation(language)
+            .filter(|t| t.text_body.is_some() || t.html_body.is_some())
+        {
+            Some(t) => (t.text_body.as_deref(), t.html_body.as_deref()),
+            None => (self.text_body.as_deref(), self.html_body.as_deref()),
+        }
+    }
+
+    fn translation(&self, language: &str) -> Option<&MessageTemplateTranslation> {
+        self.translations.get(language).or_else(|| {
+            language
+                .split_once('-')
+                .and_then(|(primary, _)| self.translations.get(primary))
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AccountTemplate {
+    pub domains: Vec<String>,
+    pub folders: Vec<String>,
+    pub welcome: Option<MessageTemplate>,
+    pub sieve: Option<(String, String)>,
+    pub signature_text: Option<String>,
+    pub signature_html: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FetchOAuthClient {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct TextExtractorConfig {
+    pub method: TextExtractorMethod,
+    pub timeout: Duration,
+    pub max_size: usize,
+    pub max_parts: usize,
+}
+
+#[derive(Clone, Default)]
+pub enum TextExtractorMethod {
+    Http {
+        url: String,
+        headers: HeaderMap,
+        tls_allow_invalid_certs: bool,
+    },
+    Command {
+        command: String,
+        arguments: Vec<String>,
+    },
+    #[default]
+    Disabled,
 }
 
 #[derive(Clone, Debug)]
@@ -84,6 +218,12 @@ pub struct DefaultFolder {
     pub create: bool,
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct FolderMapping {
+    pub domains: Vec<String>,
+    pub names: AHashMap<String, SpecialUse>,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum SpecialUse {
     Inbox,
@@ -136,6 +276,9 @@ impl JmapConfig {
             .map(|v| v.to_string())
             .collect::<Vec<_>>()
         {
+            if key == "public" {
+                continue;
+            }
             match SpecialUse::parse_value(&key) {
                 Ok(SpecialUse::Shared) => {
                     if let Some(value) = config.value(&key) {
@@ -255,6 +398,17 @@ impl JmapConfig {
             request_max_concurrent: config
                 .property_or_default::<Option<u64>>("jmap.protocol.request.max-concurrent", "4")
                 .unwrap_or(Some(4)),
+            manage_max_body_size: config
+                .properties::<usize>("server.http.manage.max-body-size")
+                .into_iter()
+                .filter_map(|(key, size)| {
+                    key.strip_prefix("server.http.manage.max-body-size.")
+                        .map(|route| (route.to_string(), size))
+                })
+                .collect(),
+            idempotency_ttl: config
+                .property_or_default::<Option<Duration>>("server.http.idempotency.ttl", "1d")
+                .unwrap_or_default(),
             get_max_objects: config
                 .property("jmap.protocol.get.max-objects")
                 .unwrap_or(500),
@@ -277,18 +431,29 @@ impl JmapConfig {
                 .property_or_default::<Duration>("jmap.protocol.upload.ttl", "1h")
                 .unwrap_or_else(|| Duration::from_secs(3600))
                 .as_secs(),
+            account_limits: AccountLimits::parse(config),
             mailbox_max_depth: config.property("jmap.mailbox.max-depth").unwrap_or(10),
+            mailbox_max_count: config.property("jmap.mailbox.max-count").unwrap_or(0),
             mailbox_name_max_len: config
                 .property("jmap.mailbox.max-name-length")
                 .unwrap_or(255),
+            mailbox_deny_names: parse_mailbox_deny_names(config),
             mail_attachments_max_size: config
                 .property("jmap.email.max-attachment-size")
                 .unwrap_or(50000000),
             mail_max_size: config.property("jmap.email.max-size").unwrap_or(75000000),
             mail_parse_max_items: config.property("jmap.email.parse.max-items").unwrap_or(10),
+            mail_max_messages: config.property("jmap.email.max-messages").unwrap_or(0),
             mail_autoexpunge_after: config
                 .property_or_default::<Option<Duration>>("jmap.email.auto-expunge", "30d")
                 .unwrap_or_default(),
+            mail_recall_max_age: config
+                .property_or_default::<Option<Duration>>("jmap.email.recall.max-age", "1d")
+                .unwrap_or_default(),
+            mail_keyword_events: config
+                .values("jmap.email.keyword-events.keywords")
+                .map(|(_, keyword)| Keyword::from(keyword.trim().to_string()))
+                .collect(),
             sieve_max_script_name: config
                 .property("sieve.untrusted.limits.name-length")
                 .unwrap_or(512),
@@ -348,6 +513,22 @@ impl JmapConfig {
             account_purge_frequency: config
                 .property_or_default::<SimpleCron>("jmap.account.purge.frequency", "0 0 *")
                 .unwrap_or_else(|| SimpleCron::parse_value("0 0 *").unwrap()),
+            fetch_max_accounts: config
+                .property_or_default("jmap.fetch.max-accounts", "5")
+                .unwrap_or(5),
+            fetch_frequency: config
+                .property_or_default("jmap.fetch.frequency", "15m")
+                .unwrap_or_else(|| Duration::from_secs(15 * 60)),
+            fetch_max_messages: config
+                .property_or_default("jmap.fetch.max-messages", "500")
+                .unwrap_or(500),
+            fetch_timeout: config
+                .property_or_default("jmap.fetch.timeout", "1m")
+                .unwrap_or_else(|| Duration::from_secs(60)),
+            fetch_allow_invalid_certs: config
+                .property_or_default("jmap.fetch.allow-invalid-certs", "false")
+                .unwrap_or_default(),
+            fetch_oauth_clients: parse_fetch_oauth_clients(config),
             fallback_admin: config
                 .value("authentication.fallback-admin.user")
                 .and_then(|u| {
@@ -360,14 +541,586 @@ impl JmapConfig {
                     .value("authentication.master.secret")
                     .map(|p| (u.to_string(), p.to_string()))
             }),
+            impersonation_separator: config
+                .property_or_default::<bool>("authentication.impersonation.enable", "false")
+                .unwrap_or(false)
+                .then(|| {
+                    config
+                        .value("authentication.impersonation.separator")
+                        .unwrap_or("*")
+                        .to_string()
+                })
+                .filter(|separator| !separator.is_empty()),
             default_folders,
             shared_folder,
+            public_folder: config
+                .value("jmap.folders.public.name")
+                .map(|name| name.trim().trim_end_matches('/'))
+                .filter(|name| !name.is_empty() && !name.contains('/'))
+                .unwrap_or("Public")
+                .to_string(),
+            public_account: config
+                .value("jmap.folders.public.account")
+                .map(|name| name.trim().to_lowercase())
+                .filter(|name| !name.is_empty()),
+            text_extractor: TextExtractorConfig::parse(config),
+            index_workers: config
+                .property::<usize>("storage.full-text.workers")
+                .filter(|workers| *workers > 0)
+                .unwrap_or_else(num_cpus::get),
+            submission_templates: parse_message_templates(config),
+            account_templates: parse_account_templates(config),
+            folder_mappings: parse_folder_mappings(config),
         };
 
         // Add capabilities
         jmap.add_capabilities(config);
         jmap
     }
+
+    /// Returns the special-use role for a folder name, using the domain's mapping
+    /// first, then the default folder names and finally well-known names.
+    pub fn folder_special_use(&self, domain: Option<&str>, name: &str) -> Option<SpecialUse> {
+        let name = name.trim().to_lowercase();
+        let domain_mapping = domain.and_then(|domain| {
+            self.folder_mappings
+                .iter()
+                .find(|m| m.domains.iter().any(|d| d.eq_ignore_ascii_case(domain)))
+        });
+        let default_mapping = self.folder_mappings.iter().find(|m| m.domains.is_empty());
+
+        [domain_mapping, default_mapping]
+            .into_iter()
+            .flatten()
+            .find_map(|mapping| mapping.names.get(&name).copied())
+            .or_else(|| {
+                self.default_folders.iter().find_map(|folder| {
+                    (folder.name.to_lowercase() == name
+                        || folder
+                            .aliases
+                            .iter()
+                            .any(|alias| alias.to_lowercase() == name))
+                    .then_some(folder.special_use)
+                })
+            })
+            .or_else(|| {
+                WELL_KNOWN_FOLDERS
+                    .iter()
+                    .find_map(|(folder, special_use)| (*folder == name).then_some(*special_use))
+            })
+            .filter(|special_use| !matches!(special_use, SpecialUse::Shared | SpecialUse::None))
+    }
+
+    /// Returns the provisioning template for a domain, falling back to the
+    /// template that does not list any domains.
+    pub fn account_template(&self, domain: &str) -> Option<&AccountTemplate> {
+        self.account_templates
+            .iter()
+            .find(|t| t.domains.iter().any(|d| d.eq_ignore_ascii_case(domain)))
+            .or_else(|| self.account_templates.iter().find(|t| t.domains.is_empty()))
+    }
+
+    /// Returns whether a mailbox name is not rejected by any of the
+    /// configured name patterns.
+    pub fn is_mailbox_name_allowed(&self, name: &str) -> bool {
+        !self
+            .mailbox_deny_names
+            .iter()
+            .any(|regex| regex.is_match(name))
+    }
+}
+
+impl AccountLimits {
+    pub fn parse(config: &mut Config) -> Self {
+        let token_map = &TokenMap::default().with_variables(&[V_AUTHENTICATED_AS]);
+
+        AccountLimits {
+            request_max_concurrent: IfBlock::try_parse(
+                config,
+                "jmap.account.limit.request.max-concurrent",
+                token_map,
+            ),
+            upload_max_concurrent: IfBlock::try_parse(
+                config,
+                "jmap.account.limit.upload.max-concurrent",
+                token_map,
+            ),
+            upload_max_bandwidth: IfBlock::try_parse(
+                config,
+                "jmap.account.limit.upload.max-bandwidth",
+                token_map,
+            ),
+            get_max_objects: IfBlock::try_parse(
+                config,
+                "jmap.account.limit.get.max-objects",
+                token_map,
+            ),
+            set_max_objects: IfBlock::try_parse(
+                config,
+                "jmap.account.limit.set.max-objects",
+                token_map,
+            ),
+            mailbox_max_count: IfBlock::try_parse(
+                config,
+                "jmap.account.limit.mailbox.max-count",
+                token_map,
+            ),
+            mailbox_max_depth: IfBlock::try_parse(
+                config,
+                "jmap.account.limit.mailbox.max-depth",
+                token_map,
+            ),
+            keyword_events: IfBlock::try_parse(
+                config,
+                "jmap.email.keyword-events.enable",
+                token_map,
+            ),
+        }
+    }
+}
+
+fn parse_message_templates(config: &mut Config) -> AHashMap<String, MessageTemplate> {
+    let mut templates = AHashMap::new();
+    for id in config
+        .sub_keys("submission.template", ".subject")
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+    {
+        let mut template = MessageTemplate {
+            from_name: config
+                .value(("submission.template", id.as_str(), "from.name"))
+                .map(|v| v.to_string()),
+            from_address: config
+                .value(("submission.template", id.as_str(), "from.address"))
+                .map(|v| v.to_string()),
+            subject: config
+                .value(("submission.template", id.as_str(), "subject"))
+                .unwrap_or_default()
+                .to_string(),
+            text_body: config
+                .value(("submission.template", id.as_str(), "body.text"))
+                .map(|v| v.to_string()),
+            html_body: config
+                .value(("submission.template", id.as_str(), "body.html"))
+                .map(|v| v.to_string()),
+            translations: AHashMap::new(),
+        };
+
+        // Translations are stored as subject.<language>, body.text.<language>, etc.
+        let mut errors = vec![];
+        for (property, value) in config.iterate_prefix(("submission.template", id.as_str())) {
+            let (part, language) = if let Some(language) = property.strip_prefix("subject.") {
+                (0, language)
+            } else if let Some(language) = property.strip_prefix("body.text.") {
+                (1, language)
+            } else if let Some(language) = property.strip_prefix("body.html.") {
+                (2, language)
+            } else {
+                continue;
+            };
+            let Some(language) = sanitize_language(language) else {
+                errors.push(property.to_string());
+                continue;
+            };
+            let translation = template.translations.entry(language).or_default();
+            let value = Some(value.to_string());
+            match part {
+                0 => translation.subject = value,
+                1 => translation.text_body = value,
+                _ => translation.html_body = value,
+            }
+        }
+        for property in errors {
+            config.new_parse_error(
+                ("submission.template", id.as_str(), property.as_str()),
+                "Invalid language tag",
+            );
+        }
+
+        if template.text_body.is_some() || template.html_body.is_some() {
+            templates.insert(id, template);
+        } else {
+            config.new_build_error(
+                ("submission.template", id.as_str()),
+                "Message template has no body",
+            );
+        }
+    }
+
+    templates
+}
+
+fn parse_mailbox_deny_names(config: &mut Config) -> Vec<Regex> {
+    let mut patterns = Vec::new();
+    for (key, value) in config
+        .values("jmap.mailbox.deny-names")
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<_>>()
+    {
+        match Regex::new(&value) {
+            Ok(regex) => patterns.push(regex),
+            Err(err) => config.new_parse_error(key, format!("Invalid mailbox name pattern: {err}")),
+        }
+    }
+
+    patterns
+}
+
+fn parse_fetch_oauth_clients(config: &mut Config) -> AHashMap<String, FetchOAuthClient> {
+    let mut clients = AHashMap::new();
+    for id in config
+        .sub_keys("jmap.fetch.oauth", ".client-id")
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+    {
+        let id = id.as_str();
+        let token_url = config
+            .value(("jmap.fetch.oauth", id, "token-url"))
+            .or(match id {
+                "google" => Some("https://oauth2.googleapis.com/token"),
+                "microsoft" => Some("https://login.microsoftonline.com/common/oauth2/v2.0/token"),
+                _ => None,
+            })
+            .map(|v| v.to_string());
+        let Some(token_url) = token_url else {
+            config.new_build_error(
+                ("jmap.fetch.oauth", id, "token-url"),
+                "Missing token endpoint for OAuth client",
+            );
+            continue;
+        };
+
+        clients.insert(
+            id.to_string(),
+            FetchOAuthClient {
+                token_url,
+                client_id: config
+                    .value(("jmap.fetch.oauth", id, "client-id"))
+                    .unwrap_or_default()
+                    .to_string(),
+                client_secret: config
+                    .value(("jmap.fetch.oauth", id, "client-secret"))
+                    .map(|v| v.to_string()),
+            },
+        );
+    }
+
+    clients
+}
+
+fn parse_account_templates(config: &mut Config) -> Vec<AccountTemplate> {
+    let mut templates = Vec::new();
+    for id in config
+        .sub_keys("account.provision", "")
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+    {
+        let id = id.as_str();
+        let welcome = config
+            .value(("account.provision", id, "welcome.subject"))
+            .map(|subject| MessageTemplate {
+                from_name: config
+                    .value(("account.provision", id, "welcome.from.name"))
+                    .map(|v| v.to_string()),
+                from_address: config
+                    .value(("account.provision", id, "welcome.from.address"))
+                    .map(|v| v.to_string()),
+                subject: subject.to_string(),
+                text_body: config
+                    .value(("account.provision", id, "welcome.body.text"))
+                    .map(|v| v.to_string()),
+                html_body: config
+                    .value(("account.provision", id, "welcome.body.html"))
+                    .map(|v| v.to_string()),
+            });
+        if welcome.as_ref().is_some_and(|w| {
+            w.from_address.is_none() || (w.text_body.is_none() && w.html_body.is_none())
+        }) {
+            config.new_build_error(
+                ("account.provision", id, "welcome"),
+                "Welcome message requires a sender address and a body",
+            );
+            continue;
+        }
+
+        templates.push(AccountTemplate {
+            domains: config
+                .values(("account.provision", id, "domains"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            folders: config
+                .values(("account.provision", id, "folders"))
+                .map(|(_, v)| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            welcome,
+            sieve: config
+                .value(("account.provision", id, "sieve.script"))
+                .map(|script| {
+                    (
+                        config
+                            .value(("account.provision", id, "sieve.name"))
+                            .unwrap_or("default")
+                            .to_string(),
+                        script.to_string(),
+                    )
+                }),
+            signature_text: config
+                .value(("account.provision", id, "signature.text"))
+                .map(|v| v.to_string()),
+            signature_html: config
+                .value(("account.provision", id, "signature.html"))
+                .map(|v| v.to_string()),
+        });
+    }
+
+    templates
+}
+
+fn parse_folder_mappings(config: &mut Config) -> Vec<FolderMapping> {
+    let mut mappings = Vec::new();
+
+    for id in config
+        .sub_keys("jmap.folder-mapping", "")
+        .map(|k| k.to_string())
+        .collect::<Vec<_>>()
+    {
+        let id = id.as_str();
+        let mut mapping = FolderMapping {
+            domains: config
+                .values(("jmap.folder-mapping", id, "domains"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            names: AHashMap::new(),
+        };
+        for (role, special_use) in [
+            ("inbox", SpecialUse::Inbox),
+            ("trash", SpecialUse::Trash),
+            ("junk", SpecialUse::Junk),
+            ("drafts", SpecialUse::Drafts),
+            ("archive", SpecialUse::Archive),
+            ("sent", SpecialUse::Sent),
+        ] {
+            for (_, name) in config.values(("jmap.folder-mapping", id, role)) {
+                let name = name.trim().to_lowercase();
+                if !name.is_empty() {
+                    mapping.names.insert(name, special_use);
+                }
+            }
+        }
+        mappings.push(mapping);
+    }
+
+    mappings
+}
+
+static WELL_KNOWN_FOLDERS: &[(&str, SpecialUse)] = &[
+    ("sent", SpecialUse::Sent),
+    ("sent items", SpecialUse::Sent),
+    ("sent mail", SpecialUse::Sent),
+    ("sent messages", SpecialUse::Sent),
+    ("gesendet", SpecialUse::Sent),
+    ("gesendete elemente", SpecialUse::Sent),
+    ("gesendete objekte", SpecialUse::Sent),
+    ("envoyés", SpecialUse::Sent),
+    ("éléments envoyés", SpecialUse::Sent),
+    ("messages envoyés", SpecialUse::Sent),
+    ("enviados", SpecialUse::Sent),
+    ("elementos enviados", SpecialUse::Sent),
+    ("itens enviados", SpecialUse::Sent),
+    ("posta inviata", SpecialUse::Sent),
+    ("inviati", SpecialUse::Sent),
+    ("verzonden", SpecialUse::Sent),
+    ("verzonden items", SpecialUse::Sent),
+    ("skickat", SpecialUse::Sent),
+    ("skickade objekt", SpecialUse::Sent),
+    ("sendt", SpecialUse::Sent),
+    ("sendte elementer", SpecialUse::Sent),
+    ("wysłane", SpecialUse::Sent),
+    ("отправленные", SpecialUse::Sent),
+    ("trash", SpecialUse::Trash),
+    ("bin", SpecialUse::Trash),
+    ("deleted", SpecialUse::Trash),
+    ("deleted items", SpecialUse::Trash),
+    ("deleted messages", SpecialUse::Trash),
+    ("papierkorb", SpecialUse::Trash),
+    ("gelöschte elemente", SpecialUse::Trash),
+    ("gelöschte objekte", SpecialUse::Trash),
+    ("corbeille", SpecialUse::Trash),
+    ("éléments supprimés", SpecialUse::Trash),
+    ("papelera", SpecialUse::Trash),
+    ("elementos eliminados", SpecialUse::Trash),
+    ("lixeira", SpecialUse::Trash),
+    ("itens excluídos", SpecialUse::Trash),
+    ("cestino", SpecialUse::Trash),
+    ("posta eliminata", SpecialUse::Trash),
+    ("prullenbak", SpecialUse::Trash),
+    ("verwijderde items", SpecialUse::Trash),
+    ("papperskorgen", SpecialUse::Trash),
+    ("borttagna objekt", SpecialUse::Trash),
+    ("kosz", SpecialUse::Trash),
+    ("корзина", SpecialUse::Trash),
+    ("удаленные", SpecialUse::Trash),
+    ("junk", SpecialUse::Junk),
+    ("junk mail", SpecialUse::Junk),
+    ("junk e-mail", SpecialUse::Junk),
+    ("junk email", SpecialUse::Junk),
+    ("spam", SpecialUse::Junk),
+    ("bulk mail", SpecialUse::Junk),
+    ("junk-e-mail", SpecialUse::Junk),
+    ("spamverdacht", SpecialUse::Junk),
+    ("courrier indésirable", SpecialUse::Junk),
+    ("indésirables", SpecialUse::Junk),
+    ("correo no deseado", SpecialUse::Junk),
+    ("lixo eletrônico", SpecialUse::Junk),
+    ("posta indesiderata", SpecialUse::Junk),
+    ("ongewenste e-mail", SpecialUse::Junk),
+    ("skräppost", SpecialUse::Junk),
+    ("wiadomości-śmieci", SpecialUse::Junk),
+    ("спам", SpecialUse::Junk),
+    ("drafts", SpecialUse::Drafts),
+    ("draft", SpecialUse::Drafts),
+    ("entwürfe", SpecialUse::Drafts),
+    ("brouillons", SpecialUse::Drafts),
+    ("borradores", SpecialUse::Drafts),
+    ("rascunhos", SpecialUse::Drafts),
+    ("bozze", SpecialUse::Drafts),
+    ("concepten", SpecialUse::Drafts),
+    ("utkast", SpecialUse::Drafts),
+    ("kladde", SpecialUse::Drafts),
+    ("wersje robocze", SpecialUse::Drafts),
+    ("черновики", SpecialUse::Drafts),
+    ("archive", SpecialUse::Archive),
+    ("archives", SpecialUse::Archive),
+    ("archiv", SpecialUse::Archive),
+    ("archivo", SpecialUse::Archive),
+    ("arquivo", SpecialUse::Archive),
+    ("archivio", SpecialUse::Archive),
+    ("archief", SpecialUse::Archive),
+    ("arkiv", SpecialUse::Archive),
+    ("archiwum", SpecialUse::Archive),
+    ("архив", SpecialUse::Archive),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escape {
+    None,
+    Header,
+    Html,
+}
+
+/// Replaces `{{name}}` placeholders with the value of each variable, failing
+/// when a placeholder has no matching variable.
+pub fn render_template(
+    template: &str,
+    variables: &BTreeMap<String, serde_json::Value>,
+    escape: Escape,
+) -> Result<String, String> {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start + 2..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        let value = match variables.get(name) {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(serde_json::Value::Null) => String::new(),
+            Some(value) => value.to_string(),
+            None => return Err(format!("Missing template variable {name:?}.")),
+        };
+
+        match escape {
+            Escape::None => result.push_str(&value),
+            Escape::Header => {
+                result.extend(
+                    value
+                        .chars()
+                        .map(|ch| if ch.is_control() { ' ' } else { ch }),
+                )
+            }
+            Escape::Html => {
+                for ch in value.chars() {
+                    match ch {
+                        '&' => result.push_str("&amp;"),
+                        '<' => result.push_str("&lt;"),
+                        '>' => result.push_str("&gt;"),
+                        '"' => result.push_str("&quot;"),
+                        '\'' => result.push_str("&#39;"),
+                        _ => result.push(ch),
+                    }
+                }
+            }
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+impl TextExtractorConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let method = match config.value("storage.full-text.extractor.type") {
+            Some("http") => TextExtractorMethod::Http {
+                url: config
+                    .value_require_non_empty("storage.full-text.extractor.url")
+                    .unwrap_or_default()
+                    .to_string(),
+                headers: parse_http_headers(config, "storage.full-text.extractor"),
+                tls_allow_invalid_certs: config
+                    .property_or_default("storage.full-text.extractor.allow-invalid-certs", "false")
+                    .unwrap_or(false),
+            },
+            Some("command") => TextExtractorMethod::Command {
+                command: config
+                    .value_require_non_empty("storage.full-text.extractor.command")
+                    .unwrap_or_default()
+                    .to_string(),
+                arguments: config
+                    .values("storage.full-text.extractor.arguments")
+                    .map(|(_, v)| v.to_string())
+                    .collect(),
+            },
+            None | Some("disable" | "disabled" | "none" | "false") => TextExtractorMethod::Disabled,
+            Some(_) => {
+                config.new_build_error("storage.full-text.extractor.type", "Invalid value");
+                TextExtractorMethod::Disabled
+            }
+        };
+
+        TextExtractorConfig {
+            method,
+            timeout: config
+                .property_or_default("storage.full-text.extractor.timeout", "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+            max_size: config
+                .property_or_default("storage.full-text.extractor.max-size", "10485760")
+                .unwrap_or(10 * 1024 * 1024),
+            max_parts: config
+                .property_or_default("storage.full-text.extractor.max-parts", "5")
+                .unwrap_or(5),
+        }
+    }
+}
+
+impl SpecialUse {
+    pub fn as_role(&self) -> Option<&'static str> {
+        match self {
+            SpecialUse::Inbox => Some("inbox"),
+            SpecialUse::Trash => Some("trash"),
+            SpecialUse::Junk => Some("junk"),
+            SpecialUse::Drafts => Some("drafts"),
+            SpecialUse::Archive => Some("archive"),
+            SpecialUse::Sent => Some("sent"),
+            SpecialUse::Shared | SpecialUse::None => None,
+        }
+    }
 }
 
 impl ParseValue for SpecialUse {
@@ -25,10 +25,15 @@ use crate::{
 };
 
 use self::{
-    imap::ImapConfig, jmap::settings::JmapConfig, scripts::Scripting, smtp::SmtpConfig,
-    storage::Storage,
+    i18n::Localization,
+    imap::ImapConfig,
+    jmap::settings::JmapConfig,
+    scripts::Scripting,
+    smtp::SmtpConfig,
+    storage::{LookupQuery, Storage},
 };
 
+pub mod i18n;
 pub mod imap;
 pub mod inner;
 pub mod jmap;
@@ -152,16 +157,29 @@ impl Core {
             )
         }
 
+        let queries = LookupQuery::parse_all(config, &stores.stores, &data);
+
+        let smtp = SmtpConfig::parse(config).await;
+        for policy in smtp.archive.domains.values() {
+            if !stores.blob_stores.contains_key(&policy.blob_store) {
+                config.new_build_error(
+                    ("archive", policy.id.as_str(), "blob-store"),
+                    format!("Blob store {:?} not found", policy.blob_store),
+                );
+            }
+        }
+
         Self {
             sieve: Scripting::parse(config, &stores).await,
             network: Network::parse(config),
-            smtp: SmtpConfig::parse(config).await,
+            smtp,
             jmap: JmapConfig::parse(config),
             imap: ImapConfig::parse(config),
             oauth: OAuthConfig::parse(config),
             acme: AcmeProviders::parse(config),
             metrics: Metrics::parse(config),
             spam: SpamFilterConfig::parse(config).await,
+            i18n: Localization::parse(config),
             storage: Storage {
                 data,
                 blob,
@@ -169,6 +187,7 @@ impl Core {
                 lookup,
                 directory,
                 directories: directories.directories,
+                queries,
                 purge_schedules: stores.purge_schedules,
                 config: config_manager,
                 stores: stores.stores,
@@ -44,6 +44,7 @@ pub struct ClusterRoles {
     pub renew_acme: bool,
     pub calculate_metrics: bool,
     pub push_metrics: bool,
+    pub fetch_accounts: bool,
 }
 
 #[derive(Clone, Default)]
@@ -55,6 +56,7 @@ pub enum AsnGeoLookupConfig {
         headers: HeaderMap,
         asn_resources: Vec<String>,
         geo_resources: Vec<String>,
+        format: AsnGeoFormat,
     },
     Dns {
         zone_ipv4: String,
@@ -68,6 +70,14 @@ pub enum AsnGeoLookupConfig {
     Disabled,
 }
 
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum AsnGeoFormat {
+    #[default]
+    Csv,
+    Ip2Location,
+    MaxMind,
+}
+
 #[derive(Clone)]
 pub struct FieldOrDefault {
     pub field: Option<String>,
@@ -109,6 +119,7 @@ impl Default for Network {
                 renew_acme: true,
                 calculate_metrics: true,
                 push_metrics: true,
+                fetch_accounts: true,
             },
         }
     }
@@ -227,6 +238,10 @@ impl Network {
                 &mut network.roles.push_metrics,
                 "cluster.roles.metrics.push",
             ),
+            (
+                &mut network.roles.fetch_accounts,
+                "cluster.roles.fetch.accounts",
+            ),
         ] {
             let node_ids = config
                 .properties::<u64>(key)
@@ -288,6 +303,15 @@ impl AsnGeoLookupConfig {
                     max_size: config.property("asn.max-size").unwrap_or(100 * 1024 * 1024),
                     asn_resources,
                     geo_resources,
+                    format: match config.value("asn.format").unwrap_or("csv") {
+                        "csv" => AsnGeoFormat::Csv,
+                        "ip2location" => AsnGeoFormat::Ip2Location,
+                        "maxmind" | "mmdb" => AsnGeoFormat::MaxMind,
+                        _ => {
+                            config.new_build_error("asn.format", "Invalid value");
+                            return None;
+                        }
+                    },
                 }
                 .into()
             }
@@ -9,7 +9,7 @@ use std::{sync::Arc, time::Duration};
 use ahash::AHashMap;
 use sieve::{compiler::grammar::Capability, Compiler, Runtime, Sieve};
 use store::Stores;
-use utils::config::Config;
+use utils::config::{utils::ParseValue, Config, Rate};
 
 use crate::scripts::{
     functions::{register_functions_trusted, register_functions_untrusted},
@@ -28,6 +28,67 @@ pub struct Scripting {
     pub sign: IfBlock,
     pub trusted_scripts: AHashMap<String, Arc<Sieve>>,
     pub untrusted_scripts: AHashMap<String, Arc<Sieve>>,
+    pub imapsieve: Vec<ImapSieveRule>,
+    pub untrusted_extensions: UntrustedExtensions,
+    pub sieve_library: AHashMap<String, Arc<Sieve>>,
+    pub trusted_limits: SandboxLimits,
+    pub notify: AHashMap<String, Arc<NotifyBackend>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SandboxLimits {
+    pub max_time: Option<Duration>,
+    pub max_memory: Option<usize>,
+    pub max_external_calls: Option<usize>,
+    pub policy: SandboxPolicy,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SandboxPolicy {
+    pub max_violations: Option<u32>,
+    pub disable_for: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct UntrustedExtensions {
+    pub disabled: Vec<Capability>,
+    pub virustest_header: String,
+}
+
+/// An external service that delivers the notifications of user scripts
+/// using a method other than mailto.
+#[derive(Debug, Clone)]
+pub struct NotifyBackend {
+    pub id: String,
+    pub method: NotifyMethod,
+    pub url: String,
+    pub body: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub timeout: Duration,
+    pub rate: Option<Rate>,
+    pub max_message_length: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyMethod {
+    Webhook,
+    Sms,
+    Xmpp,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImapSieveRule {
+    pub id: String,
+    pub mailbox: String,
+    pub causes: Vec<ImapSieveCause>,
+    pub script: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImapSieveCause {
+    Append,
+    Copy,
+    Flag,
 }
 
 impl Scripting {
@@ -87,6 +148,12 @@ impl Scripting {
             )
             .register_functions(&mut fnc_map_untrusted);
 
+        // Parse untrusted extensions
+        let untrusted_extensions = UntrustedExtensions::parse(config);
+
+        // Parse notification backends
+        let notify = NotifyBackend::parse(config);
+
         // Parse untrusted runtime
         let untrusted_runtime = Runtime::new()
             .with_functions(&mut fnc_map_untrusted)
@@ -138,21 +205,21 @@ impl Scripting {
                     .as_secs(),
             )
             .with_capability(Capability::Expressions)
-            .without_capabilities(
-                config
-                    .values("sieve.untrusted.disable-capabilities")
-                    .map(|(_, v)| v),
-            )
+            .without_capabilities(untrusted_extensions.disabled.iter().cloned())
             .with_valid_notification_uris({
-                let values = config
+                let mut values = config
                     .values("sieve.untrusted.notification-uris")
                     .map(|(_, v)| v.to_string())
                     .collect::<Vec<_>>();
-                if !values.is_empty() {
-                    values
-                } else {
-                    vec!["mailto".to_string()]
+                if values.is_empty() {
+                    values.push("mailto".to_string());
                 }
+                for scheme in notify.keys() {
+                    if !values.contains(scheme) {
+                        values.push(scheme.clone());
+                    }
+                }
+                values
             })
             .with_protected_headers({
                 let values = config
@@ -217,7 +284,6 @@ impl Scripting {
                 Capability::MailboxId,
                 Capability::MboxMetadata,
                 Capability::ServerMetadata,
-                Capability::ImapSieve,
                 Capability::Duplicate,
             ])
             .with_capability(Capability::Expressions)
@@ -316,6 +382,21 @@ impl Scripting {
             }
         }
 
+        // Parse shared library scripts
+        let sieve_library = Scripting::parse_library(config, &untrusted_compiler);
+
+        // Parse IMAPSIEVE rules
+        let mut imapsieve = Vec::new();
+        for id in config
+            .sub_keys("sieve.trusted.imapsieve", ".script")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            if let Some(rule) = ImapSieveRule::parse(config, &id, &trusted_scripts) {
+                imapsieve.push(rule);
+            }
+        }
+
         let token_map = TokenMap::default().with_variables(SMTP_RCPT_TO_VARS);
 
         Scripting {
@@ -350,6 +431,300 @@ impl Scripting {
             ),
             untrusted_scripts,
             trusted_scripts,
+            imapsieve,
+            untrusted_extensions,
+            sieve_library,
+            trusted_limits: SandboxLimits {
+                max_time: config.property("sieve.trusted.limits.time"),
+                max_memory: config.property("sieve.trusted.limits.memory"),
+                max_external_calls: config.property("sieve.trusted.limits.external-calls"),
+                policy: SandboxPolicy::parse(config, "sieve.trusted.sandbox"),
+            },
+            notify,
+        }
+    }
+
+    pub fn parse_library(config: &mut Config, compiler: &Compiler) -> AHashMap<String, Arc<Sieve>> {
+        let mut sieve_library = AHashMap::new();
+
+        // Only the published version of each library script is compiled
+        for id in config
+            .sub_keys("sieve.untrusted.library", ".version")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            let Some(version) =
+                config.property_require::<u32>(("sieve.untrusted.library", id.as_str(), "version"))
+            else {
+                continue;
+            };
+            let key = format!("sieve.untrusted.library.{id}.versions.{version}.contents");
+            let Some(contents) = config.value_require(&key).map(|v| v.to_string()) else {
+                continue;
+            };
+
+            match compiler.compile(contents.as_bytes()) {
+                Ok(compiled) => {
+                    sieve_library.insert(id.to_lowercase(), compiled.into());
+                }
+                Err(err) => config.new_build_error(
+                    key.as_str(),
+                    format!("Failed to compile Sieve library script: {err}"),
+                ),
+            }
+        }
+
+        sieve_library
+    }
+}
+
+impl SandboxLimits {
+    pub fn exceeded(
+        &self,
+        elapsed: Duration,
+        memory: usize,
+        external_calls: usize,
+    ) -> Option<&'static str> {
+        if self.max_time.is_some_and(|max_time| elapsed > max_time) {
+            Some("Execution time limit exceeded")
+        } else if self
+            .max_memory
+            .is_some_and(|max_memory| memory > max_memory)
+        {
+            Some("Memory limit exceeded")
+        } else if self
+            .max_external_calls
+            .is_some_and(|max_calls| external_calls > max_calls)
+        {
+            Some("External call limit exceeded")
+        } else {
+            None
+        }
+    }
+}
+
+impl SandboxPolicy {
+    pub fn parse(config: &mut Config, prefix: &str) -> Self {
+        SandboxPolicy {
+            max_violations: config.property(format!("{prefix}.max-violations")),
+            disable_for: config
+                .property_or_default(format!("{prefix}.disable-for"), "1h")
+                .unwrap_or_else(|| Duration::from_secs(3600)),
+        }
+    }
+}
+
+impl UntrustedExtensions {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut disabled = config
+            .values("sieve.untrusted.disable-capabilities")
+            .map(|(_, v)| Capability::parse(v))
+            .collect::<Vec<_>>();
+
+        // Extensions commonly used by migrated scripts can be toggled individually
+        for (extension, capabilities) in [
+            ("editheader", &[Capability::EditHeader][..]),
+            ("regex", &[Capability::Regex][..]),
+            (
+                "spamtest",
+                &[Capability::SpamTest, Capability::SpamTestPlus][..],
+            ),
+            ("virustest", &[Capability::VirusTest][..]),
+        ] {
+            if !config
+                .property_or_default::<bool>(
+                    ("sieve.untrusted.extensions", extension, "enable"),
+                    "true",
+                )
+                .unwrap_or(true)
+            {
+                for capability in capabilities {
+                    if !disabled.contains(capability) {
+                        disabled.push(capability.clone());
+                    }
+                }
+            }
+        }
+
+        UntrustedExtensions {
+            disabled,
+            virustest_header: config
+                .value("sieve.untrusted.extensions.virustest.header")
+                .unwrap_or("X-Virus-Status")
+                .to_string(),
+        }
+    }
+
+    pub fn is_enabled(&self, capability: &Capability) -> bool {
+        !self.disabled.contains(capability)
+    }
+}
+
+impl NotifyBackend {
+    /// Parses the notification backends, keyed by the URI scheme scripts use
+    /// to address them.
+    pub fn parse(config: &mut Config) -> AHashMap<String, Arc<NotifyBackend>> {
+        let mut backends = AHashMap::new();
+
+        for id in config
+            .sub_keys("sieve.untrusted.notify", ".type")
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+        {
+            let id = id.as_str();
+            let prefix = "sieve.untrusted.notify";
+            if !config
+                .property_or_default((prefix, id, "enable"), "true")
+                .unwrap_or(true)
+            {
+                continue;
+            }
+            let Some(method) = config.property_require::<NotifyMethod>((prefix, id, "type")) else {
+                continue;
+            };
+            let Some(url) = config
+                .value_require((prefix, id, "url"))
+                .map(|v| v.trim().to_string())
+            else {
+                continue;
+            };
+            let scheme = config
+                .value((prefix, id, "scheme"))
+                .unwrap_or(method.as_str())
+                .trim()
+                .to_lowercase();
+            if scheme == "mailto" {
+                config.new_parse_error(
+                    (prefix, id, "scheme"),
+                    "The mailto scheme is reserved for e-mail notifications",
+                );
+                continue;
+            }
+
+            let mut headers = Vec::new();
+            for (_, header) in config.values((prefix, id, "headers")) {
+                if let Some((name, value)) = header.split_once(':') {
+                    headers.push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+
+            let backend = Arc::new(NotifyBackend {
+                id: id.to_string(),
+                method,
+                url,
+                body: config.value((prefix, id, "body")).map(|v| v.to_string()),
+                headers,
+                timeout: config
+                    .property_or_default((prefix, id, "timeout"), "10s")
+                    .unwrap_or_else(|| Duration::from_secs(10)),
+                rate: config
+                    .property_or_default::<Option<Rate>>((prefix, id, "rate-limit"), "10/1h")
+                    .unwrap_or_default(),
+                max_message_length: config.property((prefix, id, "max-length")).unwrap_or(
+                    match method {
+                        NotifyMethod::Sms => 160,
+                        NotifyMethod::Webhook | NotifyMethod::Xmpp => 1024,
+                    },
+                ),
+            });
+            if backends.contains_key(&scheme) {
+                config.new_build_error(
+                    (prefix, id, "scheme"),
+                    format!("Duplicate notification backend for scheme {scheme:?}."),
+                );
+            } else {
+                backends.insert(scheme, backend);
+            }
+        }
+
+        backends
+    }
+}
+
+impl NotifyMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotifyMethod::Webhook => "webhook",
+            NotifyMethod::Sms => "sms",
+            NotifyMethod::Xmpp => "xmpp",
+        }
+    }
+}
+
+impl ParseValue for NotifyMethod {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "webhook" => Ok(NotifyMethod::Webhook),
+            "sms" => Ok(NotifyMethod::Sms),
+            "xmpp" => Ok(NotifyMethod::Xmpp),
+            other => Err(format!("Invalid notification backend type {other:?}.",)),
+        }
+    }
+}
+
+impl Default for UntrustedExtensions {
+    fn default() -> Self {
+        UntrustedExtensions {
+            disabled: Vec::new(),
+            virustest_header: "X-Virus-Status".to_string(),
+        }
+    }
+}
+
+impl ImapSieveRule {
+    fn parse(
+        config: &mut Config,
+        id: &str,
+        trusted_scripts: &AHashMap<String, Arc<Sieve>>,
+    ) -> Option<Self> {
+        let script = config
+            .value_require(("sieve.trusted.imapsieve", id, "script"))?
+            .to_string();
+        if !trusted_scripts.contains_key(&script) {
+            config.new_build_error(
+                ("sieve.trusted.imapsieve", id, "script"),
+                format!("Trusted Sieve script {script:?} does not exist"),
+            );
+            return None;
+        }
+        let mailbox = config
+            .value_require(("sieve.trusted.imapsieve", id, "mailbox"))?
+            .to_string();
+        let mut causes = config
+            .properties::<ImapSieveCause>(("sieve.trusted.imapsieve", id, "causes"))
+            .into_iter()
+            .map(|(_, cause)| cause)
+            .collect::<Vec<_>>();
+        if causes.is_empty() {
+            causes = vec![ImapSieveCause::Append, ImapSieveCause::Copy];
+        }
+
+        Some(ImapSieveRule {
+            id: id.to_string(),
+            mailbox,
+            causes,
+            script,
+        })
+    }
+}
+
+impl ImapSieveCause {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImapSieveCause::Append => "APPEND",
+            ImapSieveCause::Copy => "COPY",
+            ImapSieveCause::Flag => "FLAG",
+        }
+    }
+}
+
+impl ParseValue for ImapSieveCause {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "append" => Ok(ImapSieveCause::Append),
+            "copy" => Ok(ImapSieveCause::Copy),
+            "flag" => Ok(ImapSieveCause::Flag),
+            _ => Err(format!("Invalid IMAPSIEVE cause {value:?}.")),
         }
     }
 }
@@ -377,6 +752,11 @@ impl Default for Scripting {
             ),
             untrusted_scripts: AHashMap::new(),
             trusted_scripts: AHashMap::new(),
+            imapsieve: Vec::new(),
+            untrusted_extensions: UntrustedExtensions::default(),
+            sieve_library: AHashMap::new(),
+            trusted_limits: SandboxLimits::default(),
+            notify: AHashMap::new(),
         }
     }
 }
@@ -393,6 +773,11 @@ impl Clone for Scripting {
             sign: self.sign.clone(),
             trusted_scripts: self.trusted_scripts.clone(),
             untrusted_scripts: self.untrusted_scripts.clone(),
+            imapsieve: self.imapsieve.clone(),
+            untrusted_extensions: self.untrusted_extensions.clone(),
+            sieve_library: self.sieve_library.clone(),
+            trusted_limits: self.trusted_limits.clone(),
+            notify: self.notify.clone(),
         }
     }
 }
@@ -167,6 +167,11 @@ impl AcmeProviders {
                 .property::<bool>(("acme", acme_id, "default"))
                 .unwrap_or_default();
 
+            // Request certificates for the MTA-STS policy host of each local domain
+            let mta_sts = config
+                .property::<bool>(("acme", acme_id, "mta-sts"))
+                .unwrap_or_default();
+
             if !domains.is_empty() {
                 match AcmeProvider::new(
                     acme_id.to_string(),
@@ -177,6 +182,7 @@ impl AcmeProviders {
                     eab,
                     renew_before,
                     default,
+                    mta_sts,
                 ) {
                     Ok(acme_provider) => {
                         providers.insert(acme_id.to_string(), acme_provider);
@@ -0,0 +1,100 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use utils::config::Config;
+
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveConfig {
+    pub domains: AHashMap<String, Arc<ArchivePolicy>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArchivePolicy {
+    pub id: String,
+    pub blob_store: String,
+    pub prefix: String,
+    pub manifest: bool,
+}
+
+impl ArchiveConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut domains = AHashMap::new();
+
+        for id in config
+            .sub_keys("archive", "")
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+        {
+            let id = id.as_str();
+            if !config
+                .property_or_default(("archive", id, "enable"), "true")
+                .unwrap_or(true)
+            {
+                continue;
+            }
+
+            let Some(blob_store) = config
+                .value_require(("archive", id, "blob-store"))
+                .map(|v| v.trim().to_string())
+            else {
+                continue;
+            };
+
+            // Object names are always relative to the bucket root
+            let prefix = config
+                .value(("archive", id, "prefix"))
+                .map(|v| v.trim().trim_matches('/'))
+                .unwrap_or_default();
+            if prefix
+                .split('/')
+                .any(|part| part == "." || part == ".." || (part.is_empty() && !prefix.is_empty()))
+            {
+                config.new_parse_error(
+                    ("archive", id, "prefix"),
+                    format!("Invalid object prefix {prefix:?}"),
+                );
+                continue;
+            }
+            let prefix = if !prefix.is_empty() {
+                format!("{prefix}/")
+            } else {
+                String::new()
+            };
+
+            let policy = Arc::new(ArchivePolicy {
+                id: id.to_string(),
+                blob_store,
+                prefix,
+                manifest: config
+                    .property_or_default(("archive", id, "manifest"), "true")
+                    .unwrap_or(true),
+            });
+            let matches = config
+                .values(("archive", id, "domains"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                config.new_parse_error(("archive", id, "domains"), "Missing sender domains");
+            }
+            for domain in matches {
+                if domains.contains_key(&domain) {
+                    config.new_build_error(
+                        ("archive", id, "domains"),
+                        format!("Duplicate archive policy for domain {domain:?}."),
+                    );
+                } else {
+                    domains.insert(domain, policy.clone());
+                }
+            }
+        }
+
+        ArchiveConfig { domains }
+    }
+}
@@ -13,13 +13,13 @@ use mail_auth::{
 };
 use mail_parser::decoders::base64::base64_decode;
 use utils::config::{
-    Config,
     utils::{AsKey, ParseValue},
+    Config,
 };
 
 use crate::{
     config::CONNECTION_VARS,
-    expr::{self, Constant, ConstantValue, if_block::IfBlock, tokenizer::TokenMap},
+    expr::{self, if_block::IfBlock, tokenizer::TokenMap, Constant, ConstantValue},
 };
 
 use super::*;
@@ -31,6 +31,9 @@ pub struct MailAuthConfig {
     pub spf: SpfAuthConfig,
     pub dmarc: DmarcAuthConfig,
     pub iprev: IpRevAuthConfig,
+    pub resign: ResignAuthConfig,
+    pub srs: Option<SrsConfig>,
+    pub dns_cache: Option<DnsCacheConfig>,
     pub signatures: AHashMap<String, Arc<ArcSwap<LazySignature>>>,
 }
 
@@ -76,6 +79,37 @@ pub struct IpRevAuthConfig {
     pub verify: IfBlock,
 }
 
+/// Signing policies applied after the server modifies a message, as the
+/// original signatures are unlikely to verify afterwards.
+#[derive(Clone)]
+pub struct ResignAuthConfig {
+    pub milter: ResignPolicy,
+    pub script: ResignPolicy,
+    pub disclaimer: ResignPolicy,
+    pub list: ResignPolicy,
+}
+
+#[derive(Clone)]
+pub struct ResignPolicy {
+    pub dkim: IfBlock,
+    pub arc: IfBlock,
+}
+
+#[derive(Clone)]
+pub struct DnsCacheConfig {
+    pub min_ttl: u64,
+    pub max_ttl: u64,
+    pub negative_ttl: u64,
+    pub dnssec: bool,
+}
+
+#[derive(Clone)]
+pub struct SrsConfig {
+    pub domain: String,
+    pub secrets: Vec<String>,
+    pub max_age: u64,
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub enum VerifyStrategy {
     #[default]
@@ -161,6 +195,37 @@ impl Default for MailAuthConfig {
                     "relaxed",
                 ),
             },
+            resign: ResignAuthConfig {
+                milter: ResignPolicy {
+                    dkim: IfBlock::new::<()>("auth.resign.milter.dkim", [], "false"),
+                    arc: IfBlock::new::<()>("auth.resign.milter.arc", [], "false"),
+                },
+                script: ResignPolicy {
+                    dkim: IfBlock::new::<()>("auth.resign.script.dkim", [], "false"),
+                    arc: IfBlock::new::<()>("auth.resign.script.arc", [], "false"),
+                },
+                disclaimer: ResignPolicy {
+                    dkim: IfBlock::new::<()>("auth.resign.disclaimer.dkim", [], "false"),
+                    arc: IfBlock::new::<()>("auth.resign.disclaimer.arc", [], "false"),
+                },
+                list: ResignPolicy {
+                    dkim: IfBlock::new::<()>(
+                        "auth.resign.list.dkim",
+                        [(
+                            "is_local_domain('*', rcpt_domain)",
+                            "['rsa-' + rcpt_domain, 'ed25519-' + rcpt_domain]",
+                        )],
+                        "false",
+                    ),
+                    arc: IfBlock::new::<()>(
+                        "auth.resign.list.arc",
+                        [],
+                        "'rsa-' + config_get('report.domain')",
+                    ),
+                },
+            },
+            srs: None,
+            dns_cache: None,
             signatures: Default::default(),
         }
     }
@@ -174,6 +239,7 @@ impl MailAuthConfig {
         let conn_vars = TokenMap::default()
             .with_variables(CONNECTION_VARS)
             .with_constants::<VerifyStrategy>();
+        let list_vars = TokenMap::default().with_variables(RCPT_DOMAIN_VARS);
         let mut mail_auth = Self::default();
 
         for (value, key, token_map) in [
@@ -193,6 +259,46 @@ impl MailAuthConfig {
             ),
             (&mut mail_auth.dmarc.verify, "auth.dmarc.verify", &rcpt_vars),
             (&mut mail_auth.iprev.verify, "auth.iprev.verify", &conn_vars),
+            (
+                &mut mail_auth.resign.milter.dkim,
+                "auth.resign.milter.dkim",
+                &rcpt_vars,
+            ),
+            (
+                &mut mail_auth.resign.milter.arc,
+                "auth.resign.milter.arc",
+                &rcpt_vars,
+            ),
+            (
+                &mut mail_auth.resign.script.dkim,
+                "auth.resign.script.dkim",
+                &rcpt_vars,
+            ),
+            (
+                &mut mail_auth.resign.script.arc,
+                "auth.resign.script.arc",
+                &rcpt_vars,
+            ),
+            (
+                &mut mail_auth.resign.disclaimer.dkim,
+                "auth.resign.disclaimer.dkim",
+                &rcpt_vars,
+            ),
+            (
+                &mut mail_auth.resign.disclaimer.arc,
+                "auth.resign.disclaimer.arc",
+                &rcpt_vars,
+            ),
+            (
+                &mut mail_auth.resign.list.dkim,
+                "auth.resign.list.dkim",
+                &list_vars,
+            ),
+            (
+                &mut mail_auth.resign.list.arc,
+                "auth.resign.list.arc",
+                &list_vars,
+            ),
         ] {
             if let Some(if_block) = IfBlock::try_parse(config, key, token_map) {
                 *value = if_block;
@@ -202,6 +308,59 @@ impl MailAuthConfig {
             .property_or_default("auth.dkim.strict", "true")
             .unwrap_or(true);
 
+        // Sender Rewriting Scheme, the first secret is used for signing and
+        // the remaining ones are only accepted to allow key rotation.
+        let srs_secrets = config
+            .values("auth.srs.secret")
+            .map(|(_, v)| v.to_string())
+            .collect::<Vec<_>>();
+        if !srs_secrets.is_empty() {
+            if let Some(domain) = config
+                .value("auth.srs.domain")
+                .or_else(|| config.value("report.domain"))
+                .or_else(|| config.value("lookup.default.domain"))
+                .map(|v| v.to_lowercase())
+            {
+                mail_auth.srs = Some(SrsConfig {
+                    domain,
+                    secrets: srs_secrets,
+                    max_age: config
+                        .property_or_default::<Duration>("auth.srs.max-age", "21d")
+                        .map(|d| d.as_secs() / 86400)
+                        .unwrap_or(21)
+                        .clamp(1, 1023),
+                });
+            } else {
+                config.new_build_error("auth.srs.domain", "Missing SRS domain");
+            }
+        }
+
+        // Shared cache for DKIM, SPF and DMARC records
+        if config
+            .property_or_default("auth.dns-cache.enable", "false")
+            .unwrap_or(false)
+        {
+            let min_ttl = config
+                .property_or_default::<Duration>("auth.dns-cache.min-ttl", "1m")
+                .map(|d| d.as_secs())
+                .unwrap_or(60);
+            mail_auth.dns_cache = Some(DnsCacheConfig {
+                min_ttl,
+                max_ttl: config
+                    .property_or_default::<Duration>("auth.dns-cache.max-ttl", "1d")
+                    .map(|d| d.as_secs())
+                    .unwrap_or(86400)
+                    .max(min_ttl),
+                negative_ttl: config
+                    .property_or_default::<Duration>("auth.dns-cache.negative-ttl", "5m")
+                    .map(|d| d.as_secs())
+                    .unwrap_or(300),
+                dnssec: config
+                    .property_or_default("auth.dns-cache.dnssec", "false")
+                    .unwrap_or(false),
+            });
+        }
+
         // Parse signatures
         let mut signatures: AHashMap<&str, Config> = AHashMap::new();
         let mut current_id = None;
@@ -0,0 +1,143 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use utils::{config::Config, glob::GlobSet};
+
+#[derive(Debug, Clone, Default)]
+pub struct DisclaimerConfig {
+    pub domains: AHashMap<String, Arc<Disclaimer>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Disclaimer {
+    pub id: String,
+    pub text: String,
+    pub html: String,
+    pub exempt_senders: GlobSet,
+    pub exempt_recipients: GlobSet,
+}
+
+impl DisclaimerConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut domains = AHashMap::new();
+
+        for id in config
+            .sub_keys("disclaimer", "")
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+        {
+            let id = id.as_str();
+            if !config
+                .property_or_default(("disclaimer", id, "enable"), "true")
+                .unwrap_or(true)
+            {
+                continue;
+            }
+
+            let Some(text) = config
+                .value(("disclaimer", id, "text"))
+                .map(|v| v.trim_end().to_string())
+                .filter(|v| !v.is_empty())
+            else {
+                config.new_parse_error(("disclaimer", id, "text"), "Missing disclaimer text");
+                continue;
+            };
+            let html = config
+                .value(("disclaimer", id, "html"))
+                .map(|v| v.trim_end().to_string())
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| text_to_html(&text));
+            let mut disclaimer = Disclaimer {
+                id: id.to_string(),
+                text,
+                html,
+                exempt_senders: GlobSet::default(),
+                exempt_recipients: GlobSet::default(),
+            };
+            for (list, set) in [
+                ("exempt.senders", &mut disclaimer.exempt_senders),
+                ("exempt.recipients", &mut disclaimer.exempt_recipients),
+            ] {
+                for (_, entry) in config.values(("disclaimer", id, list)) {
+                    let entry = entry.trim().to_lowercase();
+                    if entry.contains('@') {
+                        set.insert(&entry);
+                    } else if !entry.is_empty() {
+                        set.insert(&format!("*@{entry}"));
+                    }
+                }
+            }
+
+            let disclaimer = Arc::new(disclaimer);
+            let matches = config
+                .values(("disclaimer", id, "domains"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                config.new_parse_error(("disclaimer", id, "domains"), "Missing sender domains");
+            }
+            for domain in matches {
+                if domains.contains_key(&domain) {
+                    config.new_build_error(
+                        ("disclaimer", id, "domains"),
+                        format!("Duplicate disclaimer for domain {domain:?}."),
+                    );
+                } else {
+                    domains.insert(domain, disclaimer.clone());
+                }
+            }
+        }
+
+        DisclaimerConfig { domains }
+    }
+
+    /// Returns the disclaimer to append to a message sent by `sender`, unless
+    /// the sender or all recipients are exempt.
+    pub fn disclaimer_for<'x>(
+        &self,
+        sender: &str,
+        recipients: impl IntoIterator<Item = &'x str>,
+    ) -> Option<&Arc<Disclaimer>> {
+        let sender = sender.to_lowercase();
+        let (_, domain) = sender.rsplit_once('@')?;
+        let disclaimer = self.domains.get(domain)?;
+        let mut recipients = recipients.into_iter().peekable();
+
+        if disclaimer.exempt_senders.contains(&sender)
+            || (recipients.peek().is_some()
+                && recipients.all(|rcpt| disclaimer.exempt_recipients.contains(rcpt)))
+        {
+            None
+        } else {
+            Some(disclaimer)
+        }
+    }
+}
+
+fn text_to_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len() + 32);
+    html.push_str("<p>");
+    for (idx, line) in text.lines().enumerate() {
+        if idx > 0 {
+            html.push_str("<br>");
+        }
+        for ch in line.chars() {
+            match ch {
+                '&' => html.push_str("&amp;"),
+                '<' => html.push_str("&lt;"),
+                '>' => html.push_str("&gt;"),
+                '"' => html.push_str("&quot;"),
+                _ => html.push(ch),
+            }
+        }
+    }
+    html.push_str("</p>");
+    html
+}
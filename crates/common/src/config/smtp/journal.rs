@@ -0,0 +1,136 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use utils::config::Config;
+
+use super::smime::{build_smime_key_pair, SmimeKeyPair};
+
+#[derive(Debug, Clone, Default)]
+pub struct JournalConfig {
+    pub domains: AHashMap<String, Arc<JournalPolicy>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalPolicy {
+    pub id: String,
+    pub address: String,
+    pub decrypt: bool,
+    pub escrow_keys: Vec<EscrowKey>,
+}
+
+/// A domain key held in escrow, used to decrypt the journal copy of
+/// encrypted messages.
+#[derive(Debug, Clone)]
+pub enum EscrowKey {
+    Smime { id: String, key_pair: SmimeKeyPair },
+    Pgp { id: String, private_key: String },
+}
+
+impl JournalConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut domains = AHashMap::new();
+
+        for id in config
+            .sub_keys("journal", "")
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+        {
+            let id = id.as_str();
+            if !config
+                .property_or_default(("journal", id, "enable"), "true")
+                .unwrap_or(true)
+            {
+                continue;
+            }
+
+            let Some(address) = config
+                .value_require(("journal", id, "address"))
+                .map(|v| v.trim().to_lowercase())
+            else {
+                continue;
+            };
+            let decrypt = config
+                .property_or_default(("journal", id, "decrypt"), "false")
+                .unwrap_or(false);
+            let mut escrow_keys = Vec::new();
+            for key_id in config
+                .sub_keys(("journal", id, "escrow"), ".type")
+                .map(|k| k.to_string())
+                .collect::<Vec<_>>()
+            {
+                let prefix = format!("journal.{id}.escrow.{key_id}");
+                let key = match config.value((prefix.as_str(), "type")) {
+                    Some("smime") => config
+                        .value((prefix.as_str(), "certificate"))
+                        .zip(config.value((prefix.as_str(), "private-key")))
+                        .ok_or_else(|| "Missing certificate or private key.".to_string())
+                        .and_then(|(cert, pk)| build_smime_key_pair(cert, pk))
+                        .map(|key_pair| EscrowKey::Smime {
+                            id: key_id.clone(),
+                            key_pair,
+                        }),
+                    Some("pgp") => config
+                        .value((prefix.as_str(), "private-key"))
+                        .map(|pk| EscrowKey::Pgp {
+                            id: key_id.clone(),
+                            private_key: pk.to_string(),
+                        })
+                        .ok_or_else(|| "Missing private key.".to_string()),
+                    Some(other) => Err(format!("Invalid escrow key type {other:?}.")),
+                    None => continue,
+                };
+                match key {
+                    Ok(key) => escrow_keys.push(key),
+                    Err(err) => config.new_build_error(prefix.as_str(), err),
+                }
+            }
+            if decrypt && escrow_keys.is_empty() {
+                config.new_build_error(
+                    ("journal", id, "decrypt"),
+                    "Decryption requires at least one escrow key.",
+                );
+            }
+
+            let policy = Arc::new(JournalPolicy {
+                id: id.to_string(),
+                address,
+                decrypt,
+                escrow_keys,
+            });
+            let matches = config
+                .values(("journal", id, "domains"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                config.new_parse_error(("journal", id, "domains"), "Missing recipient domains");
+            }
+            for domain in matches {
+                if domains.contains_key(&domain) {
+                    config.new_build_error(
+                        ("journal", id, "domains"),
+                        format!("Duplicate journal policy for domain {domain:?}."),
+                    );
+                } else {
+                    domains.insert(domain, policy.clone());
+                }
+            }
+        }
+
+        JournalConfig { domains }
+    }
+}
+
+impl EscrowKey {
+    pub fn id(&self) -> &str {
+        match self {
+            EscrowKey::Smime { id, .. } | EscrowKey::Pgp { id, .. } => id,
+        }
+    }
+}
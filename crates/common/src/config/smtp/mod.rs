@@ -6,18 +6,24 @@
 
 use utils::config::{Config, Rate};
 
+pub mod archive;
 pub mod auth;
+pub mod disclaimer;
+pub mod journal;
 pub mod queue;
 pub mod report;
 pub mod resolver;
+pub mod responder;
 pub mod session;
+pub mod smime;
 pub mod throttle;
 
 use crate::expr::{tokenizer::TokenMap, Expression};
 
 use self::{
-    auth::MailAuthConfig, queue::QueueConfig, report::ReportConfig, resolver::Resolvers,
-    session::SessionConfig,
+    archive::ArchiveConfig, auth::MailAuthConfig, disclaimer::DisclaimerConfig,
+    journal::JournalConfig, queue::QueueConfig, report::ReportConfig, resolver::Resolvers,
+    responder::AutoResponderConfig, session::SessionConfig, smime::SmimeConfig,
 };
 
 use super::*;
@@ -29,6 +35,11 @@ pub struct SmtpConfig {
     pub resolvers: Resolvers,
     pub mail_auth: MailAuthConfig,
     pub report: ReportConfig,
+    pub auto_responder: AutoResponderConfig,
+    pub disclaimers: DisclaimerConfig,
+    pub smime: SmimeConfig,
+    pub journal: JournalConfig,
+    pub archive: ArchiveConfig,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -50,10 +61,13 @@ pub const THROTTLE_MX: u16 = 1 << 6;
 pub const THROTTLE_REMOTE_IP: u16 = 1 << 7;
 pub const THROTTLE_LOCAL_IP: u16 = 1 << 8;
 pub const THROTTLE_HELO_DOMAIN: u16 = 1 << 9;
+pub const THROTTLE_ASN: u16 = 1 << 10;
+pub const THROTTLE_COUNTRY: u16 = 1 << 11;
 
 pub(crate) const RCPT_DOMAIN_VARS: &[u32; 1] = &[V_RECIPIENT_DOMAIN];
+pub(crate) const RCPT_VARS: &[u32; 2] = &[V_RECIPIENT, V_RECIPIENT_DOMAIN];
 
-pub(crate) const SMTP_EHLO_VARS: &[u32; 10] = &[
+pub(crate) const SMTP_EHLO_VARS: &[u32; 11] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -64,8 +78,9 @@ pub(crate) const SMTP_EHLO_VARS: &[u32; 10] = &[
     V_HELO_DOMAIN,
     V_ASN,
     V_COUNTRY,
+    V_REPUTATION,
 ];
-pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 12] = &[
+pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 13] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -78,8 +93,9 @@ pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 12] = &[
     V_AUTHENTICATED_AS,
     V_ASN,
     V_COUNTRY,
+    V_REPUTATION,
 ];
-pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 17] = &[
+pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 18] = &[
     V_SENDER,
     V_SENDER_DOMAIN,
     V_RECIPIENTS,
@@ -97,6 +113,7 @@ pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 17] = &[
     V_HELO_DOMAIN,
     V_ASN,
     V_COUNTRY,
+    V_REPUTATION,
 ];
 pub(crate) const SMTP_QUEUE_HOST_VARS: &[u32; 14] = &[
     V_SENDER,
@@ -158,6 +175,11 @@ impl SmtpConfig {
             resolvers: Resolvers::parse(config).await,
             mail_auth: MailAuthConfig::parse(config),
             report: ReportConfig::parse(config),
+            auto_responder: AutoResponderConfig::parse(config),
+            disclaimers: DisclaimerConfig::parse(config),
+            smime: SmimeConfig::parse(config),
+            journal: JournalConfig::parse(config),
+            archive: ArchiveConfig::parse(config),
         }
     }
 }
@@ -4,11 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::{net::IpAddr, time::Duration};
+
 use ahash::AHashMap;
 use mail_auth::IpLookupStrategy;
 use mail_send::Credentials;
 use throttle::parse_queue_rate_limiter_key;
-use utils::config::{utils::ParseValue, Config};
+use utils::config::{utils::ParseValue, Config, Rate};
 
 use crate::{
     config::server::ServerProtocol,
@@ -45,8 +47,26 @@ pub struct QueueConfig {
     pub quota: QueueQuotas,
     pub max_threads: usize,
 
+    // Suppression list
+    pub suppression: QueueSuppression,
+
+    // Admission control
+    pub admission: QueueAdmission,
+
+    // Cluster coordination
+    pub coordinator: QueueCoordinator,
+
+    // IP warm-up
+    pub warmup: QueueWarmup,
+
+    // Rate shaping
+    pub shaping: Vec<QueueShaper>,
+
     // Relay hosts
     pub relay_hosts: AHashMap<String, RelayHost>,
+
+    // Internationalized addresses
+    pub smtputf8_downgrade: bool,
 }
 
 #[derive(Clone)]
@@ -60,6 +80,14 @@ pub struct Dsn {
     pub name: IfBlock,
     pub address: IfBlock,
     pub sign: IfBlock,
+    pub delay: DelayDsn,
+}
+
+#[derive(Clone)]
+pub struct DelayDsn {
+    pub enable: IfBlock,
+    pub max_count: IfBlock,
+    pub coalesce: IfBlock,
 }
 
 #[derive(Clone)]
@@ -105,6 +133,62 @@ pub struct QueueQuota {
     pub messages: Option<usize>,
 }
 
+#[derive(Clone, Default)]
+pub struct QueueSuppression {
+    pub enable: bool,
+    pub expiry: Option<Duration>,
+}
+
+#[derive(Clone, Default)]
+pub struct QueueAdmission {
+    pub enable: bool,
+    pub max_messages: Option<u64>,
+    pub max_latency: Option<Duration>,
+    pub check_interval: Duration,
+}
+
+#[derive(Clone, Default)]
+pub struct QueueCoordinator {
+    pub enable: bool,
+    pub heartbeat: Duration,
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Default)]
+pub struct QueueWarmup {
+    pub providers: Vec<String>,
+    pub ips: AHashMap<IpAddr, WarmupSchedule>,
+}
+
+#[derive(Clone)]
+pub struct WarmupSchedule {
+    pub start: u64,
+    pub limits: Vec<u64>,
+}
+
+#[derive(Clone)]
+pub struct QueueShaper {
+    pub id: String,
+    pub target: ShapingTarget,
+    pub hosts: Vec<String>,
+    pub rate: Rate,
+    pub burst: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapingTarget {
+    Domain,
+    Mx,
+}
+
+/// Token bucket state, with tokens stored in thousandths so that partial
+/// refills are not lost between deliveries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenBucket {
+    pub tokens: u64,
+    pub updated: u64,
+}
+
 #[derive(Clone)]
 pub struct RelayHost {
     pub address: String,
@@ -189,6 +273,11 @@ impl Default for QueueConfig {
                     [],
                     "['rsa-' + config_get('report.domain'), 'ed25519-' + config_get('report.domain')]",
                 ),
+                delay: DelayDsn {
+                    enable: IfBlock::new::<()>("report.dsn.delay.enable", [], "true"),
+                    max_count: IfBlock::empty("report.dsn.delay.max-count"),
+                    coalesce: IfBlock::new::<()>("report.dsn.delay.coalesce", [], "false"),
+                },
             },
             timeout: QueueOutboundTimeout {
                 connect: IfBlock::new::<()>("queue.outbound.timeouts.connect", [], "5m"),
@@ -204,7 +293,13 @@ impl Default for QueueConfig {
             inbound_limiters: QueueRateLimiters::default(),
             outbound_limiters: QueueRateLimiters::default(),
             quota: QueueQuotas::default(),
+            suppression: QueueSuppression::default(),
+            admission: QueueAdmission::default(),
+            coordinator: QueueCoordinator::default(),
+            warmup: QueueWarmup::default(),
+            shaping: Vec::new(),
             relay_hosts: Default::default(),
+            smtputf8_downgrade: false,
         }
     }
 }
@@ -310,6 +405,21 @@ impl QueueConfig {
                 &sender_vars,
             ),
             (&mut queue.dsn.sign, "report.dsn.sign", &sender_vars),
+            (
+                &mut queue.dsn.delay.enable,
+                "report.dsn.delay.enable",
+                &sender_vars,
+            ),
+            (
+                &mut queue.dsn.delay.max_count,
+                "report.dsn.delay.max-count",
+                &sender_vars,
+            ),
+            (
+                &mut queue.dsn.delay.coalesce,
+                "report.dsn.delay.coalesce",
+                &sender_vars,
+            ),
         ] {
             if let Some(if_block) = IfBlock::try_parse(config, key, token_map) {
                 *value = if_block;
@@ -325,6 +435,64 @@ impl QueueConfig {
         queue.outbound_limiters = parse_outbound_rate_limiters(config);
         queue.quota = parse_queue_quota(config);
 
+        // Parse suppression list
+        queue.suppression = QueueSuppression {
+            enable: config
+                .property_or_default("queue.suppression.enable", "true")
+                .unwrap_or(true),
+            expiry: config
+                .property_or_default::<Option<Duration>>("queue.suppression.expiry", "90d")
+                .unwrap_or_default(),
+        };
+
+        // Parse admission control
+        queue.admission = QueueAdmission {
+            enable: config
+                .property_or_default("queue.admission.enable", "false")
+                .unwrap_or(false),
+            max_messages: config
+                .property::<Option<u64>>("queue.admission.max-messages")
+                .unwrap_or_default(),
+            max_latency: config
+                .property_or_default::<Option<Duration>>("queue.admission.max-latency", "5s")
+                .unwrap_or_default(),
+            check_interval: config
+                .property_or_default("queue.admission.check-interval", "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+        };
+
+        // Parse cluster coordination
+        queue.coordinator = QueueCoordinator {
+            enable: config
+                .property_or_default("queue.coordinator.enable", "false")
+                .unwrap_or(false),
+            heartbeat: config
+                .property_or_default("queue.coordinator.heartbeat", "10s")
+                .unwrap_or_else(|| Duration::from_secs(10)),
+            timeout: config
+                .property_or_default("queue.coordinator.timeout", "60s")
+                .unwrap_or_else(|| Duration::from_secs(60)),
+        };
+        if queue.coordinator.timeout <= queue.coordinator.heartbeat {
+            config.new_parse_error(
+                "queue.coordinator.timeout",
+                "Timeout must be longer than the heartbeat interval",
+            );
+            queue.coordinator.timeout = queue.coordinator.heartbeat * 3;
+        }
+
+        // Parse IP warm-up schedules
+        queue.warmup = parse_warmup(config);
+
+        // Parse rate shaping buckets
+        queue.shaping = parse_shaping(config);
+
+        // Internationalized addresses are bounced by default when the remote
+        // host does not support SMTPUTF8
+        queue.smtputf8_downgrade = config
+            .property_or_default("queue.outbound.smtputf8.downgrade", "false")
+            .unwrap_or(false);
+
         // Parse relay hosts
         queue.relay_hosts = config
             .sub_keys("remote", ".address")
@@ -377,6 +545,236 @@ fn parse_relay_host(config: &mut Config, id: &str) -> Option<RelayHost> {
     })
 }
 
+fn parse_warmup(config: &mut Config) -> QueueWarmup {
+    let mut warmup = QueueWarmup {
+        providers: config
+            .values("queue.warmup.providers")
+            .map(|(_, provider)| provider.trim().trim_end_matches('.').to_lowercase())
+            .filter(|provider| !provider.is_empty())
+            .collect(),
+        ips: AHashMap::new(),
+    };
+    if warmup.providers.is_empty() {
+        // MX host suffixes of the major mailbox providers
+        warmup.providers = ["google.com", "outlook.com", "yahoodns.net", "icloud.com"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+    }
+
+    for id in config
+        .sub_keys("queue.warmup", ".address")
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+    {
+        let Some(address) =
+            config.property_require::<IpAddr>(("queue.warmup", id.as_str(), "address"))
+        else {
+            continue;
+        };
+        let start = match config
+            .value_require(("queue.warmup", id.as_str(), "start"))
+            .map(|value| value.trim().to_string())
+        {
+            Some(value) => {
+                let date = if value.len() == 10 {
+                    format!("{value}T00:00:00Z")
+                } else {
+                    value
+                };
+                match mail_parser::DateTime::parse_rfc3339(&date) {
+                    Some(date) if date.is_valid() => date.to_timestamp() as u64,
+                    _ => {
+                        config.new_parse_error(
+                            ("queue.warmup", id.as_str(), "start"),
+                            format!("Invalid date {date:?}, expected YYYY-MM-DD"),
+                        );
+                        continue;
+                    }
+                }
+            }
+            None => continue,
+        };
+        let limits = config
+            .properties::<u64>(("queue.warmup", id.as_str(), "schedule"))
+            .into_iter()
+            .map(|(_, limit)| limit)
+            .collect::<Vec<_>>();
+        if limits.is_empty() {
+            config.new_parse_error(
+                ("queue.warmup", id.as_str(), "schedule"),
+                "Warm-up schedule must contain at least one daily limit",
+            );
+            continue;
+        }
+
+        warmup.ips.insert(address, WarmupSchedule { start, limits });
+    }
+
+    warmup
+}
+
+impl QueueWarmup {
+    pub fn is_provider(&self, hostname: &str) -> bool {
+        self.providers
+            .iter()
+            .any(|provider| is_host_suffix(hostname, provider))
+    }
+}
+
+fn parse_shaping(config: &mut Config) -> Vec<QueueShaper> {
+    let mut shapers = Vec::new();
+
+    for id in config
+        .sub_keys("queue.shaping", ".rate")
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+    {
+        if !config
+            .property_or_default::<bool>(("queue.shaping", id.as_str(), "enable"), "true")
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        let Some(rate) = config
+            .property_require::<Rate>(("queue.shaping", id.as_str(), "rate"))
+            .filter(|rate| rate.requests > 0 && !rate.period.is_zero())
+        else {
+            continue;
+        };
+
+        let mut targets = [ShapingTarget::Domain, ShapingTarget::Mx]
+            .into_iter()
+            .filter_map(|target| {
+                let hosts = config
+                    .values(("queue.shaping", id.as_str(), target.as_str()))
+                    .map(|(_, host)| host.trim().trim_end_matches('.').to_lowercase())
+                    .filter(|host| !host.is_empty())
+                    .collect::<Vec<_>>();
+                (!hosts.is_empty()).then_some((target, hosts))
+            })
+            .collect::<Vec<_>>();
+        let (target, hosts) = match (targets.pop(), targets.is_empty()) {
+            (Some(target), true) => target,
+            _ => {
+                config.new_parse_error(
+                    ("queue.shaping", id.as_str()),
+                    "Rate shaping bucket must define either 'domain' or 'mx' hosts",
+                );
+                continue;
+            }
+        };
+        let burst = config
+            .property::<u64>(("queue.shaping", id.as_str(), "burst"))
+            .filter(|burst| *burst > 0)
+            .unwrap_or(rate.requests);
+
+        shapers.push(QueueShaper {
+            id,
+            target,
+            hosts,
+            rate,
+            burst,
+        });
+    }
+
+    shapers
+}
+
+const TOKEN_SCALE: u64 = 1000;
+
+impl QueueShaper {
+    /// Returns whether deliveries to a recipient domain or MX host draw from
+    /// this bucket. MX hosts match on their suffix, as providers use many.
+    pub fn matches(&self, target: ShapingTarget, host: &str) -> bool {
+        self.target == target
+            && self.hosts.iter().any(|pattern| match target {
+                ShapingTarget::Domain => pattern.eq_ignore_ascii_case(host.trim_end_matches('.')),
+                ShapingTarget::Mx => is_host_suffix(host, pattern),
+            })
+    }
+
+    pub fn new_bucket(&self, now: u64) -> TokenBucket {
+        TokenBucket {
+            tokens: self.burst * TOKEN_SCALE,
+            updated: now,
+        }
+    }
+
+    /// Adds the tokens accrued since the last update, up to the burst size.
+    pub fn refill(&self, bucket: &mut TokenBucket, now: u64) {
+        let accrued = now
+            .saturating_sub(bucket.updated)
+            .saturating_mul(self.rate.requests * TOKEN_SCALE)
+            / self.period();
+        bucket.tokens = bucket
+            .tokens
+            .saturating_add(accrued)
+            .min(self.burst * TOKEN_SCALE);
+        bucket.updated = now;
+    }
+
+    /// Takes a token from the bucket, returning `false` if it is exhausted.
+    pub fn take(&self, bucket: &mut TokenBucket) -> bool {
+        if bucket.tokens >= TOKEN_SCALE {
+            bucket.tokens -= TOKEN_SCALE;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remaining(&self, bucket: &TokenBucket) -> u64 {
+        bucket.tokens / TOKEN_SCALE
+    }
+
+    /// Returns the number of seconds until the next whole token is added.
+    pub fn next_token(&self, bucket: &TokenBucket) -> u64 {
+        ((TOKEN_SCALE - bucket.tokens % TOKEN_SCALE) * self.period())
+            .div_ceil(self.rate.requests * TOKEN_SCALE)
+    }
+
+    /// Returns the number of seconds an empty bucket takes to fill up, after
+    /// which its persisted state is no longer needed.
+    pub fn fill_time(&self) -> u64 {
+        (self.burst * self.period()).div_ceil(self.rate.requests)
+    }
+
+    fn period(&self) -> u64 {
+        self.rate.period.as_secs().max(1)
+    }
+}
+
+impl ShapingTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShapingTarget::Domain => "domain",
+            ShapingTarget::Mx => "mx",
+        }
+    }
+}
+
+fn is_host_suffix(hostname: &str, suffix: &str) -> bool {
+    let hostname = hostname.trim_end_matches('.');
+    hostname.len() >= suffix.len()
+        && hostname[hostname.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+        && (hostname.len() == suffix.len()
+            || hostname.as_bytes()[hostname.len() - suffix.len() - 1] == b'.')
+}
+
+impl WarmupSchedule {
+    /// Returns the number of days elapsed since the warm-up started.
+    pub fn day(&self, now: u64) -> u64 {
+        now.saturating_sub(self.start) / 86400
+    }
+
+    /// Returns the daily limit for the given day, or `None` once the
+    /// schedule is complete and the IP is considered established.
+    pub fn limit(&self, day: u64) -> Option<u64> {
+        self.limits.get(day as usize).copied()
+    }
+}
+
 fn parse_inbound_rate_limters(config: &mut Config) -> QueueRateLimiters {
     let mut throttle = QueueRateLimiters::default();
     let all_throttles = parse_queue_rate_limiter(
@@ -391,7 +789,9 @@ fn parse_inbound_rate_limters(config: &mut Config) -> QueueRateLimiters {
             | THROTTLE_RCPT
             | THROTTLE_RCPT_DOMAIN
             | THROTTLE_SENDER
-            | THROTTLE_SENDER_DOMAIN,
+            | THROTTLE_SENDER_DOMAIN
+            | THROTTLE_ASN
+            | THROTTLE_COUNTRY,
     );
     for t in all_throttles {
         if (t.keys & (THROTTLE_RCPT | THROTTLE_RCPT_DOMAIN)) != 0
@@ -27,6 +27,7 @@ pub struct ReportConfig {
 #[derive(Clone)]
 pub struct ReportAnalysis {
     pub addresses: Vec<AddressMatch>,
+    pub feedback_loop: Vec<AddressMatch>,
     pub forward: bool,
     pub store: Option<Duration>,
 }
@@ -58,7 +59,8 @@ pub struct Report {
     pub send: IfBlock,
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AggregateFrequency {
     Hourly,
     Daily,
@@ -87,6 +89,11 @@ impl ReportConfig {
                     .into_iter()
                     .map(|(_, m)| m)
                     .collect(),
+                feedback_loop: config
+                    .properties::<AddressMatch>("report.analysis.feedback-loop")
+                    .into_iter()
+                    .map(|(_, m)| m)
+                    .collect(),
                 forward: config.property("report.analysis.forward").unwrap_or(true),
                 store: config
                     .property_or_default::<Option<Duration>>("report.analysis.store", "30d")
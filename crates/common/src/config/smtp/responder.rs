@@ -0,0 +1,204 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use mail_parser::{HeaderName, Message};
+use utils::{config::Config, glob::GlobPattern};
+
+#[derive(Debug, Clone)]
+pub struct AutoResponderConfig {
+    pub null_sender: bool,
+    pub auto_submitted: bool,
+    pub list_headers: bool,
+    pub verp: bool,
+    pub precedence: Vec<String>,
+    pub senders: Vec<GlobPattern>,
+    pub dsn_senders: Vec<GlobPattern>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoGenerated {
+    NullSender,
+    AutomatedSender,
+    Verp,
+    AutoSubmitted,
+    Precedence,
+    MailingList,
+    ResponseSuppressed,
+}
+
+impl AutoResponderConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        AutoResponderConfig {
+            null_sender: config
+                .property_or_default("auto-responder.detect.null-sender", "true")
+                .unwrap_or(true),
+            auto_submitted: config
+                .property_or_default("auto-responder.detect.auto-submitted", "true")
+                .unwrap_or(true),
+            list_headers: config
+                .property_or_default("auto-responder.detect.list-headers", "true")
+                .unwrap_or(true),
+            verp: config
+                .property_or_default("auto-responder.detect.verp", "true")
+                .unwrap_or(true),
+            precedence: parse_list(
+                config,
+                "auto-responder.detect.precedence",
+                &["bulk", "list", "junk"],
+            )
+            .into_iter()
+            .map(|value| value.to_lowercase())
+            .collect(),
+            senders: parse_list(
+                config,
+                "auto-responder.detect.senders",
+                &[
+                    "mailer-daemon",
+                    "owner-*",
+                    "*-request",
+                    "*-bounce",
+                    "*-bounces",
+                    "listserv",
+                    "majordomo",
+                ],
+            )
+            .into_iter()
+            .map(|pattern| GlobPattern::compile(&pattern, true))
+            .collect(),
+            dsn_senders: parse_list(
+                config,
+                "auto-responder.dsn.ignore-senders",
+                &["mailer-daemon"],
+            )
+            .into_iter()
+            .map(|pattern| GlobPattern::compile(&pattern, true))
+            .collect(),
+        }
+    }
+
+    /// Returns the reason why a message looks automatically generated, in which case
+    /// auto-responders such as vacation replies and notifications should not answer it.
+    pub fn detect(&self, return_path: &str, message: &Message<'_>) -> Option<AutoGenerated> {
+        let return_path = return_path.trim();
+        if return_path.is_empty() || return_path == "<>" {
+            return self.null_sender.then_some(AutoGenerated::NullSender);
+        }
+
+        let local_part = return_path
+            .rsplit_once('@')
+            .map_or(return_path, |(local_part, _)| local_part)
+            .to_lowercase();
+        if self
+            .senders
+            .iter()
+            .any(|sender| sender.matches(&local_part))
+        {
+            return Some(AutoGenerated::AutomatedSender);
+        } else if self.verp && is_verp(&local_part) {
+            return Some(AutoGenerated::Verp);
+        }
+
+        for header in message.root_part().headers() {
+            match &header.name {
+                HeaderName::ListArchive
+                | HeaderName::ListHelp
+                | HeaderName::ListId
+                | HeaderName::ListOwner
+                | HeaderName::ListPost
+                | HeaderName::ListSubscribe
+                | HeaderName::ListUnsubscribe
+                    if self.list_headers =>
+                {
+                    return Some(AutoGenerated::MailingList);
+                }
+                HeaderName::Other(name) => {
+                    let value = header.value.as_text().unwrap_or_default().trim();
+                    if name.eq_ignore_ascii_case("Auto-Submitted") {
+                        if self.auto_submitted && !value.eq_ignore_ascii_case("no") {
+                            return Some(AutoGenerated::AutoSubmitted);
+                        }
+                    } else if name.eq_ignore_ascii_case("Precedence") {
+                        if self
+                            .precedence
+                            .iter()
+                            .any(|precedence| value.eq_ignore_ascii_case(precedence))
+                        {
+                            return Some(AutoGenerated::Precedence);
+                        }
+                    } else if name.eq_ignore_ascii_case("X-Auto-Response-Suppress")
+                        && value.split(',').any(|value| {
+                            ["all", "oof", "autoreply"]
+                                .iter()
+                                .any(|v| value.trim().eq_ignore_ascii_case(v))
+                        })
+                    {
+                        return Some(AutoGenerated::ResponseSuppressed);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// Whether delivery status notifications addressed to this return path
+    /// should be dropped, as the sender is known to be an automated system.
+    pub fn is_dsn_suppressed(&self, return_path: &str) -> bool {
+        let local_part = return_path
+            .rsplit_once('@')
+            .map_or(return_path, |(local_part, _)| local_part)
+            .to_lowercase();
+        self.dsn_senders
+            .iter()
+            .any(|sender| sender.matches(&local_part))
+    }
+}
+
+impl AutoGenerated {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutoGenerated::NullSender => "null-sender",
+            AutoGenerated::AutomatedSender => "automated-sender",
+            AutoGenerated::Verp => "verp",
+            AutoGenerated::AutoSubmitted => "auto-submitted",
+            AutoGenerated::Precedence => "precedence",
+            AutoGenerated::MailingList => "mailing-list",
+            AutoGenerated::ResponseSuppressed => "response-suppressed",
+        }
+    }
+}
+
+// VERP return paths encode the recipient in the local part, such as
+// "list-bounces+jdoe=example.com" or "bounce-1234-jdoe=example.com"
+fn is_verp(local_part: &str) -> bool {
+    local_part.rsplit_once('=').is_some_and(|(prefix, domain)| {
+        domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && prefix
+                .rfind(['+', '-'])
+                .is_some_and(|pos| pos > 0 && pos + 1 < prefix.len())
+    })
+}
+
+fn parse_list(config: &mut Config, key: &str, default: &[&str]) -> Vec<String> {
+    let values = config
+        .values(key)
+        .map(|(_, v)| v.to_string())
+        .collect::<Vec<_>>();
+    if !values.is_empty() {
+        values
+    } else {
+        default.iter().map(|v| v.to_string()).collect()
+    }
+}
+
+impl Default for AutoResponderConfig {
+    fn default() -> Self {
+        Self::parse(&mut Config::default())
+    }
+}
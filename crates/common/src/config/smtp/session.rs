@@ -20,7 +20,7 @@ use smtp_proto::*;
 use utils::config::{utils::ParseValue, Config};
 
 use crate::{
-    config::CONNECTION_VARS,
+    config::{scripts::SandboxPolicy, CONNECTION_VARS},
     expr::{if_block::IfBlock, tokenizer::TokenMap, *},
 };
 
@@ -42,6 +42,7 @@ pub struct SessionConfig {
     pub data: Data,
     pub extensions: Extensions,
     pub mta_sts_policy: Option<Policy>,
+    pub reputation: Option<ConnectionReputation>,
 
     pub milters: Vec<Milter>,
     pub hooks: Vec<MTAHook>,
@@ -64,11 +65,16 @@ pub struct Ehlo {
 #[derive(Clone)]
 pub struct Extensions {
     pub pipelining: IfBlock,
+    pub pipelining_strict: IfBlock,
     pub chunking: IfBlock,
     pub requiretls: IfBlock,
     pub dsn: IfBlock,
     pub vrfy: IfBlock,
     pub expn: IfBlock,
+    pub vrfy_rate: IfBlock,
+    pub expn_rate: IfBlock,
+    pub expn_max_size: IfBlock,
+    pub expn_opaque_size: IfBlock,
     pub no_soliciting: IfBlock,
     pub future_release: IfBlock,
     pub deliver_by: IfBlock,
@@ -78,6 +84,8 @@ pub struct Extensions {
 #[derive(Clone)]
 pub struct Auth {
     pub directory: IfBlock,
+    pub allow: IfBlock,
+    pub alert: IfBlock,
     pub mechanisms: IfBlock,
     pub require: IfBlock,
     pub must_match_sender: IfBlock,
@@ -103,11 +111,19 @@ pub struct Rcpt {
     pub errors_max: IfBlock,
     pub errors_wait: IfBlock,
 
+    // Directory harvesting protection
+    pub harvest_enable: IfBlock,
+    pub harvest_delay: IfBlock,
+    pub harvest_max_delay: IfBlock,
+    pub harvest_disconnect: IfBlock,
+    pub harvest_block: IfBlock,
+
     // Limits
     pub max_recipients: IfBlock,
 
     // Catch-all and sub-addressing
     pub catch_all: AddressMapping,
+    pub catch_all_folder: IfBlock,
     pub subaddressing: AddressMapping,
 }
 
@@ -128,6 +144,7 @@ pub struct Data {
     pub max_messages: IfBlock,
     pub max_message_size: IfBlock,
     pub max_received_headers: IfBlock,
+    pub spool_chunk_size: Option<usize>,
 
     // Headers
     pub add_received: IfBlock,
@@ -136,7 +153,38 @@ pub struct Data {
     pub add_auth_results: IfBlock,
     pub add_message_id: IfBlock,
     pub add_date: IfBlock,
+    pub add_trace_id: IfBlock,
     pub add_delivered_to: bool,
+
+    // Privacy
+    pub privacy: IfBlock,
+    pub privacy_headers: Vec<String>,
+
+    // Local delivery
+    pub deduplicate: IfBlock,
+    pub expire: IfBlock,
+
+    // Outbound moderation
+    pub moderation: DataModeration,
+}
+
+/// Holds messages submitted by flagged accounts until an administrator approves them.
+#[derive(Clone)]
+pub struct DataModeration {
+    pub enable: IfBlock,
+    pub expiry: Duration,
+    pub max_held: usize,
+}
+
+/// Controls how originating client details are handled on authenticated submissions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeaderPrivacy {
+    #[default]
+    Disable,
+    /// Omit the client address from the Received header and remove identifying headers.
+    Anonymize,
+    /// Do not add a Received header and remove existing ones along with identifying headers.
+    Strip,
 }
 
 #[derive(Clone)]
@@ -176,6 +224,18 @@ pub struct MTAHook {
     pub tempfail_on_error: bool,
     pub run_on_stage: AHashSet<Stage>,
     pub max_response_size: usize,
+    pub sandbox: SandboxPolicy,
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectionReputation {
+    pub expiry: u64,
+    pub half_life: u64,
+    pub penalty_spam: f64,
+    pub penalty_ham: f64,
+    pub penalty_auth_failure: f64,
+    pub penalty_invalid_rcpt: f64,
+    pub penalty_protocol_violation: f64,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -215,6 +275,7 @@ impl SessionConfig {
             .filter_map(|id| parse_hooks(config, &id, &has_rcpt_vars))
             .collect();
         session.mta_sts_policy = Policy::try_parse(config);
+        session.reputation = ConnectionReputation::parse(config);
 
         for (value, key, token_map) in [
             (&mut session.duration, "session.duration", &has_conn_vars),
@@ -244,6 +305,11 @@ impl SessionConfig {
                 "session.extensions.pipelining",
                 &has_sender_vars,
             ),
+            (
+                &mut session.extensions.pipelining_strict,
+                "session.extensions.pipelining-strict",
+                &has_conn_vars,
+            ),
             (
                 &mut session.extensions.dsn,
                 "session.extensions.dsn",
@@ -259,6 +325,26 @@ impl SessionConfig {
                 "session.extensions.expn",
                 &has_sender_vars,
             ),
+            (
+                &mut session.extensions.vrfy_rate,
+                "session.extensions.vrfy-rate",
+                &has_sender_vars,
+            ),
+            (
+                &mut session.extensions.expn_rate,
+                "session.extensions.expn-rate",
+                &has_sender_vars,
+            ),
+            (
+                &mut session.extensions.expn_max_size,
+                "session.extensions.expn-max-size",
+                &has_sender_vars,
+            ),
+            (
+                &mut session.extensions.expn_opaque_size,
+                "session.extensions.expn-opaque-size",
+                &has_sender_vars,
+            ),
             (
                 &mut session.extensions.chunking,
                 "session.extensions.chunking",
@@ -309,6 +395,16 @@ impl SessionConfig {
                 "session.auth.directory",
                 &has_ehlo_hars,
             ),
+            (
+                &mut session.auth.allow,
+                "session.auth.allow",
+                &has_ehlo_hars,
+            ),
+            (
+                &mut session.auth.alert,
+                "session.auth.alert",
+                &has_ehlo_hars,
+            ),
             (
                 &mut session.auth.mechanisms,
                 "session.auth.mechanisms",
@@ -374,6 +470,31 @@ impl SessionConfig {
                 "session.rcpt.errors.wait",
                 &has_sender_vars,
             ),
+            (
+                &mut session.rcpt.harvest_enable,
+                "session.rcpt.harvest.enable",
+                &has_sender_vars,
+            ),
+            (
+                &mut session.rcpt.harvest_delay,
+                "session.rcpt.harvest.delay",
+                &has_sender_vars,
+            ),
+            (
+                &mut session.rcpt.harvest_max_delay,
+                "session.rcpt.harvest.max-delay",
+                &has_sender_vars,
+            ),
+            (
+                &mut session.rcpt.harvest_disconnect,
+                "session.rcpt.harvest.disconnect",
+                &has_sender_vars,
+            ),
+            (
+                &mut session.rcpt.harvest_block,
+                "session.rcpt.harvest.block",
+                &has_sender_vars,
+            ),
             (
                 &mut session.rcpt.max_recipients,
                 "session.rcpt.max-recipients",
@@ -439,14 +560,73 @@ impl SessionConfig {
                 "session.data.add-headers.date",
                 &has_rcpt_vars,
             ),
+            (
+                &mut session.data.add_trace_id,
+                "session.data.add-headers.trace-id",
+                &has_rcpt_vars,
+            ),
         ] {
             if let Some(if_block) = IfBlock::try_parse(config, key, token_map) {
                 *value = if_block;
             }
         }
+        if let Some(if_block) = IfBlock::try_parse(
+            config,
+            "session.rcpt.catch-all-folder",
+            &TokenMap::default().with_variables(RCPT_VARS),
+        ) {
+            session.rcpt.catch_all_folder = if_block;
+        }
+        if let Some(if_block) = IfBlock::try_parse(
+            config,
+            "session.data.deduplicate",
+            &TokenMap::default().with_variables(RCPT_VARS),
+        ) {
+            session.data.deduplicate = if_block;
+        }
+        if let Some(if_block) = IfBlock::try_parse(
+            config,
+            "session.data.expire",
+            &TokenMap::default().with_variables(RCPT_VARS),
+        ) {
+            session.data.expire = if_block;
+        }
+        if let Some(if_block) = IfBlock::try_parse(
+            config,
+            "session.data.privacy.mode",
+            &TokenMap::default()
+                .with_variables(SMTP_RCPT_TO_VARS)
+                .with_constants::<HeaderPrivacy>(),
+        ) {
+            session.data.privacy = if_block;
+        }
+        let privacy_headers = config
+            .values("session.data.privacy.headers")
+            .map(|(_, v)| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>();
+        if !privacy_headers.is_empty() {
+            session.data.privacy_headers = privacy_headers;
+        }
         session.data.add_delivered_to = config
             .property_or_default("session.data.add-headers.delivered-to", "true")
             .unwrap_or(true);
+        session.data.spool_chunk_size = config
+            .property_or_default::<Option<usize>>("session.data.spool.chunk-size", "8388608")
+            .unwrap_or_default();
+        if let Some(if_block) = IfBlock::try_parse(
+            config,
+            "session.data.moderation.enable",
+            &TokenMap::default().with_variables(SMTP_RCPT_TO_VARS),
+        ) {
+            session.data.moderation.enable = if_block;
+        }
+        session.data.moderation.expiry = config
+            .property_or_default::<Duration>("session.data.moderation.expiry", "3d")
+            .unwrap_or(Duration::from_secs(3 * 86400));
+        session.data.moderation.max_held = config
+            .property_or_default::<usize>("session.data.moderation.max-held", "1000")
+            .unwrap_or(1000);
         session
     }
 }
@@ -588,10 +768,50 @@ fn parse_hooks(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<MT
                 "52428800",
             )
             .unwrap_or(52428800),
+        sandbox: SandboxPolicy::parse(config, &format!("session.hook.{id}.sandbox")),
         headers,
     })
 }
 
+impl ConnectionReputation {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("session.reputation.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        ConnectionReputation {
+            expiry: config
+                .property_or_default::<Duration>("session.reputation.expiry", "30d")
+                .map(|d| d.as_secs())
+                .unwrap_or(2592000),
+            half_life: config
+                .property_or_default::<Duration>("session.reputation.half-life", "7d")
+                .map(|d| d.as_secs())
+                .unwrap_or(604800)
+                .max(1),
+            penalty_spam: config
+                .property_or_default("session.reputation.penalty.spam", "1.0")
+                .unwrap_or(1.0),
+            penalty_ham: config
+                .property_or_default("session.reputation.penalty.ham", "-0.25")
+                .unwrap_or(-0.25),
+            penalty_auth_failure: config
+                .property_or_default("session.reputation.penalty.auth-failure", "0.5")
+                .unwrap_or(0.5),
+            penalty_invalid_rcpt: config
+                .property_or_default("session.reputation.penalty.invalid-rcpt", "0.25")
+                .unwrap_or(0.25),
+            penalty_protocol_violation: config
+                .property_or_default("session.reputation.penalty.protocol-violation", "0.5")
+                .unwrap_or(0.5),
+        }
+        .into()
+    }
+}
+
 fn parse_stages(config: &mut Config, prefix: &str, id: &str) -> AHashSet<Stage> {
     let mut stages = AHashSet::default();
     let mut invalid = Vec::new();
@@ -663,6 +883,12 @@ impl Default for SessionConfig {
                     [("local_port != 25", "'*'")],
                     "false",
                 ),
+                allow: IfBlock::new::<()>("session.auth.allow", [], "true"),
+                alert: IfBlock::new::<()>(
+                    "session.auth.alert",
+                    [("local_port == 25", "true")],
+                    "false",
+                ),
                 mechanisms: IfBlock::new::<Mechanism>(
                     "session.auth.mechanisms",
                     [
@@ -710,8 +936,14 @@ impl Default for SessionConfig {
                 rewrite: IfBlock::empty("session.rcpt.rewrite"),
                 errors_max: IfBlock::new::<()>("session.rcpt.errors.total", [], "5"),
                 errors_wait: IfBlock::new::<()>("session.rcpt.errors.wait", [], "5s"),
+                harvest_enable: IfBlock::new::<()>("session.rcpt.harvest.enable", [], "false"),
+                harvest_delay: IfBlock::new::<()>("session.rcpt.harvest.delay", [], "1s"),
+                harvest_max_delay: IfBlock::new::<()>("session.rcpt.harvest.max-delay", [], "30s"),
+                harvest_disconnect: IfBlock::new::<()>("session.rcpt.harvest.disconnect", [], "10"),
+                harvest_block: IfBlock::new::<()>("session.rcpt.harvest.block", [], "false"),
                 max_recipients: IfBlock::new::<()>("session.rcpt.max-recipients", [], "100"),
                 catch_all: AddressMapping::Enable,
+                catch_all_folder: IfBlock::empty("session.rcpt.catch-all-folder"),
                 subaddressing: AddressMapping::Enable,
             },
             data: Data {
@@ -754,10 +986,39 @@ impl Default for SessionConfig {
                     [("local_port == 25", "true")],
                     "false",
                 ),
+                add_trace_id: IfBlock::new::<()>(
+                    "session.data.add-headers.trace-id",
+                    [("local_port == 25", "true")],
+                    "false",
+                ),
                 add_delivered_to: false,
+                spool_chunk_size: None,
+                privacy: IfBlock::new::<HeaderPrivacy>("session.data.privacy.mode", [], "disable"),
+                privacy_headers: [
+                    "User-Agent",
+                    "X-Mailer",
+                    "X-Originating-IP",
+                    "X-MimeOLE",
+                    "X-Newsreader",
+                ]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+                deduplicate: IfBlock::new::<()>("session.data.deduplicate", [], "7d"),
+                expire: IfBlock::empty("session.data.expire"),
+                moderation: DataModeration {
+                    enable: IfBlock::new::<()>("session.data.moderation.enable", [], "false"),
+                    expiry: Duration::from_secs(3 * 86400),
+                    max_held: 1000,
+                },
             },
             extensions: Extensions {
                 pipelining: IfBlock::new::<()>("session.extensions.pipelining", [], "true"),
+                pipelining_strict: IfBlock::new::<()>(
+                    "session.extensions.pipelining-strict",
+                    [],
+                    "false",
+                ),
                 chunking: IfBlock::new::<()>("session.extensions.chunking", [], "true"),
                 requiretls: IfBlock::new::<()>("session.extensions.requiretls", [], "true"),
                 dsn: IfBlock::new::<()>(
@@ -775,6 +1036,22 @@ impl Default for SessionConfig {
                     [("!is_empty(authenticated_as)", "true")],
                     "false",
                 ),
+                vrfy_rate: IfBlock::new::<()>(
+                    "session.extensions.vrfy-rate",
+                    [("!is_empty(authenticated_as)", "false")],
+                    "[10, 1m]",
+                ),
+                expn_rate: IfBlock::new::<()>(
+                    "session.extensions.expn-rate",
+                    [("!is_empty(authenticated_as)", "false")],
+                    "[5, 1m]",
+                ),
+                expn_max_size: IfBlock::new::<()>("session.extensions.expn-max-size", [], "100"),
+                expn_opaque_size: IfBlock::new::<()>(
+                    "session.extensions.expn-opaque-size",
+                    [("!is_empty(authenticated_as)", "0")],
+                    "50",
+                ),
                 no_soliciting: IfBlock::new::<()>("session.extensions.no-soliciting", [], "''"),
                 future_release: IfBlock::new::<()>(
                     "session.extensions.future-release",
@@ -793,6 +1070,7 @@ impl Default for SessionConfig {
                 ),
             },
             mta_sts_policy: None,
+            reputation: None,
             milters: Default::default(),
             hooks: Default::default(),
         }
@@ -938,3 +1216,49 @@ impl ConstantValue for MtPriority {
             .add_constant("nsep", MtPriority::Nsep);
     }
 }
+
+impl<'x> TryFrom<Variable<'x>> for HeaderPrivacy {
+    type Error = ();
+
+    fn try_from(value: Variable<'x>) -> Result<Self, Self::Error> {
+        match value {
+            Variable::Integer(0) => Ok(HeaderPrivacy::Disable),
+            Variable::Integer(1) => Ok(HeaderPrivacy::Anonymize),
+            Variable::Integer(2) => Ok(HeaderPrivacy::Strip),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<HeaderPrivacy> for Constant {
+    fn from(value: HeaderPrivacy) -> Self {
+        Constant::Integer(match value {
+            HeaderPrivacy::Disable => 0,
+            HeaderPrivacy::Anonymize => 1,
+            HeaderPrivacy::Strip => 2,
+        })
+    }
+}
+
+impl ParseValue for HeaderPrivacy {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "anonymize" => Ok(HeaderPrivacy::Anonymize),
+            "strip" => Ok(HeaderPrivacy::Strip),
+            "disable" | "disabled" | "none" | "false" => Ok(HeaderPrivacy::Disable),
+            _ => Err(format!("Invalid header privacy value {:?}.", value)),
+        }
+    }
+}
+
+impl ConstantValue for HeaderPrivacy {
+    fn add_constants(token_map: &mut TokenMap) {
+        token_map
+            .add_constant("anonymize", HeaderPrivacy::Anonymize)
+            .add_constant("strip", HeaderPrivacy::Strip)
+            .add_constant("disable", HeaderPrivacy::Disable)
+            .add_constant("disabled", HeaderPrivacy::Disable)
+            .add_constant("none", HeaderPrivacy::Disable)
+            .add_constant("false", HeaderPrivacy::Disable);
+    }
+}
@@ -0,0 +1,194 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use ahash::AHashMap;
+use rsa::{
+    pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey},
+    pkcs8::DecodePrivateKey,
+    RsaPrivateKey, RsaPublicKey,
+};
+use rustls_pemfile::{certs, read_one, Item};
+use utils::config::Config;
+use x509_parser::{certificate::X509Certificate, der_parser::asn1_rs::FromDer};
+
+#[derive(Debug, Clone)]
+pub struct SmimeConfig {
+    pub domains: AHashMap<String, Arc<SmimeDomain>>,
+    pub harvest: bool,
+    pub harvest_expiry: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmimeDomain {
+    pub id: String,
+    pub signer: Option<SmimeKeyPair>,
+    pub encrypt: bool,
+    pub cipher: SmimeCipher,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmimeKeyPair {
+    /// DER encoded certificate chain, starting with the certificate of the key.
+    pub certificates: Vec<Vec<u8>>,
+    pub private_key: Arc<RsaPrivateKey>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SmimeCipher {
+    Aes128,
+    #[default]
+    Aes256,
+}
+
+impl SmimeConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut domains = AHashMap::new();
+
+        for id in config
+            .sub_keys("smime.domain", "")
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+        {
+            let id = id.as_str();
+            if !config
+                .property_or_default(("smime.domain", id, "enable"), "true")
+                .unwrap_or(true)
+            {
+                continue;
+            }
+
+            let signer = if config
+                .property_or_default(("smime.domain", id, "sign"), "true")
+                .unwrap_or(true)
+            {
+                match parse_signer(config, id) {
+                    Ok(signer) => Some(signer),
+                    Err(err) => {
+                        config.new_build_error(("smime.domain", id), err);
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+            let encrypt = config
+                .property_or_default(("smime.domain", id, "encrypt"), "true")
+                .unwrap_or(true);
+            if signer.is_none() && !encrypt {
+                continue;
+            }
+            let cipher = match config.value(("smime.domain", id, "cipher")) {
+                Some("aes128") => SmimeCipher::Aes128,
+                Some("aes256") | None => SmimeCipher::Aes256,
+                Some(other) => {
+                    let err = format!("Invalid cipher {other:?}.");
+                    config.new_parse_error(("smime.domain", id, "cipher"), err);
+                    continue;
+                }
+            };
+
+            let domain = Arc::new(SmimeDomain {
+                id: id.to_string(),
+                signer,
+                encrypt,
+                cipher,
+            });
+            let matches = config
+                .values(("smime.domain", id, "domains"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                config.new_parse_error(("smime.domain", id, "domains"), "Missing sender domains");
+            }
+            for name in matches {
+                if domains.contains_key(&name) {
+                    config.new_build_error(
+                        ("smime.domain", id, "domains"),
+                        format!("Duplicate S/MIME policy for domain {name:?}."),
+                    );
+                } else {
+                    domains.insert(name, domain.clone());
+                }
+            }
+        }
+
+        SmimeConfig {
+            domains,
+            harvest: config
+                .property_or_default("smime.harvest.enable", "false")
+                .unwrap_or(false),
+            harvest_expiry: config
+                .property_or_default("smime.harvest.expiry", "365d")
+                .unwrap_or(Duration::from_secs(365 * 86400)),
+        }
+    }
+
+    /// Returns the S/MIME policy that applies to messages sent by `sender`.
+    pub fn policy_for(&self, sender: &str) -> Option<&Arc<SmimeDomain>> {
+        let (_, domain) = sender.rsplit_once('@')?;
+        self.domains.get(&domain.to_lowercase())
+    }
+}
+
+fn parse_signer(config: &Config, id: &str) -> Result<SmimeKeyPair, String> {
+    build_smime_key_pair(
+        config
+            .value(("smime.domain", id, "certificate"))
+            .ok_or("Missing signing certificate.")?,
+        config
+            .value(("smime.domain", id, "private-key"))
+            .ok_or("Missing private key.")?,
+    )
+}
+
+/// Builds an S/MIME key pair from a PEM encoded certificate chain and RSA
+/// private key, verifying that the key matches the first certificate.
+pub fn build_smime_key_pair(certificate: &str, private_key: &str) -> Result<SmimeKeyPair, String> {
+    let certificates = certs(&mut Cursor::new(certificate.as_bytes()))
+        .map(|cert| cert.map(|cert| cert.to_vec()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("Failed to read certificates: {err}"))?;
+    let Some(leaf_cert) = certificates.first() else {
+        return Err("No certificates found.".to_string());
+    };
+
+    let private_key = match read_one(&mut Cursor::new(private_key.as_bytes())) {
+        Ok(Some(Item::Pkcs1Key(key))) => RsaPrivateKey::from_pkcs1_der(key.secret_pkcs1_der())
+            .map_err(|err| format!("Failed to parse PKCS1 RSA key: {err}"))?,
+        Ok(Some(Item::Pkcs8Key(key))) => RsaPrivateKey::from_pkcs8_der(key.secret_pkcs8_der())
+            .map_err(|err| format!("Failed to parse PKCS8 RSA key: {err}"))?,
+        Err(err) => return Err(format!("Failed to read PEM: {err}")),
+        Ok(Some(key)) => return Err(format!("Unsupported key type: {key:?}")),
+        Ok(None) => return Err("No RSA key found in PEM".to_string()),
+    };
+
+    let (_, cert) = X509Certificate::from_der(leaf_cert)
+        .map_err(|err| format!("Failed to parse certificate: {err}"))?;
+    if RsaPublicKey::from_pkcs1_der(&cert.public_key().subject_public_key.data)
+        .ok()
+        .is_none_or(|public_key| public_key != private_key.to_public_key())
+    {
+        return Err("Private key does not match the certificate.".to_string());
+    }
+
+    Ok(SmimeKeyPair {
+        certificates,
+        private_key: Arc::new(private_key),
+    })
+}
+
+impl Default for SmimeConfig {
+    fn default() -> Self {
+        Self {
+            domains: AHashMap::new(),
+            harvest: false,
+            harvest_expiry: Duration::from_secs(365 * 86400),
+        }
+    }
+}
@@ -100,6 +100,8 @@ pub(crate) fn parse_queue_rate_limiter_key(value: &str) -> Result<u16, String> {
         "remote_ip" => Ok(THROTTLE_REMOTE_IP),
         "local_ip" => Ok(THROTTLE_LOCAL_IP),
         "helo_domain" => Ok(THROTTLE_HELO_DOMAIN),
+        "asn" => Ok(THROTTLE_ASN),
+        "country" => Ok(THROTTLE_COUNTRY),
         _ => Err(format!("Invalid THROTTLE key {value:?}")),
     }
 }
@@ -9,14 +9,14 @@ use std::{
     time::Duration,
 };
 
-use ahash::AHashSet;
+use ahash::{AHashMap, AHashSet};
 use mail_auth::common::resolver::ToReverseName;
 use nlp::bayes::BayesClassifier;
 use tokio::net::lookup_host;
 use utils::{
     cache::CacheItemWeight,
-    config::{utils::ParseValue, Config},
-    glob::GlobMap,
+    config::{utils::ParseValue, Config, Rate},
+    glob::{GlobMap, GlobSet},
 };
 
 use super::{functions::ResolveVariable, if_block::IfBlock, tokenizer::TokenMap, Variable};
@@ -28,11 +28,90 @@ pub struct SpamFilterConfig {
     pub rules: SpamFilterRules,
     pub lists: SpamFilterLists,
     pub pyzor: Option<PyzorConfig>,
+    pub rspamd: Option<RspamdConfig>,
     pub reputation: Option<ReputationConfig>,
     pub bayes: Option<BayesConfig>,
     pub scores: SpamFilterScoreConfig,
     pub expiry: SpamFilterExpiryConfig,
     pub headers: SpamFilterHeaderConfig,
+    pub policies: AHashMap<String, SpamPolicy>,
+    pub known_senders: Option<KnownSenderConfig>,
+    pub max_user_list_entries: usize,
+    pub link_protection: Option<LinkProtectionConfig>,
+    pub reputation_apis: AHashMap<String, ReputationApiConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReputationApiConfig {
+    pub url: String,
+    pub body: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub hash: bool,
+    pub timeout: Duration,
+    pub rate: Option<Rate>,
+    pub ttl_malicious: u64,
+    pub ttl_clean: u64,
+    pub field: String,
+    pub malicious: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkProtectionConfig {
+    pub mode: LinkProtectionMode,
+    pub spam_only: bool,
+    pub domains: AHashSet<String>,
+    pub exclude: GlobSet,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkProtectionMode {
+    Rewrite { redirector: String },
+    Tag { tag: String },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpamPolicy {
+    pub spam_threshold: Option<f64>,
+    pub discard_threshold: Option<f64>,
+    pub reject_threshold: Option<f64>,
+    pub action: Option<SpamPolicyAction>,
+    pub subject_tag: Option<String>,
+    pub allow: GlobSet,
+    pub block: GlobSet,
+    pub known_sender: Option<KnownSenderAction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpamPolicyAction {
+    #[default]
+    Junk,
+    Header,
+}
+
+/// Senders the recipient has previously written to, which are trusted more
+/// than unknown senders.
+#[derive(Debug, Clone)]
+pub struct KnownSenderConfig {
+    pub action: KnownSenderAction,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownSenderAction {
+    Skip,
+    Score,
+    Ignore,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpamVerdict<'x> {
+    Ham,
+    Spam {
+        action: SpamPolicyAction,
+        subject_tag: Option<&'x str>,
+    },
+    Discard,
+    Reject,
 }
 
 #[derive(Debug, Clone)]
@@ -41,6 +120,7 @@ pub struct SpamFilterHeaderConfig {
     pub result: Option<String>,
     pub bayes_result: Option<String>,
     pub llm: Option<String>,
+    pub exemption: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -54,6 +134,7 @@ pub struct SpamFilterScoreConfig {
 pub struct SpamFilterExpiryConfig {
     pub grey_list: Option<u64>,
     pub trusted_reply: Option<u64>,
+    pub known_sender: Option<u64>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -112,6 +193,15 @@ pub struct PyzorConfig {
     pub ratio: f64,
 }
 
+#[derive(Debug, Clone)]
+pub struct RspamdConfig {
+    pub url: String,
+    pub controller_url: String,
+    pub password: Option<String>,
+    pub timeout: Duration,
+    pub learn: bool,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct SpamFilterRules {
     pub url: Vec<IfBlock>,
@@ -182,12 +272,435 @@ impl SpamFilterConfig {
             rules: SpamFilterRules::parse(config),
             lists: SpamFilterLists::parse(config),
             pyzor: PyzorConfig::parse(config).await,
+            rspamd: RspamdConfig::parse(config),
             reputation: ReputationConfig::parse(config),
             bayes: BayesConfig::parse(config),
             scores: SpamFilterScoreConfig::parse(config),
             expiry: SpamFilterExpiryConfig::parse(config),
             headers: SpamFilterHeaderConfig::parse(config),
+            policies: SpamPolicy::parse(config),
+            known_senders: KnownSenderConfig::parse(config),
+            max_user_list_entries: config
+                .property_or_default("spam-filter.user-list.max-entries", "500")
+                .unwrap_or(500),
+            link_protection: LinkProtectionConfig::parse(config),
+            reputation_apis: ReputationApiConfig::parse(config),
+        }
+    }
+
+    fn policies_for(&self, rcpt: &str) -> impl Iterator<Item = &SpamPolicy> {
+        let rcpt = rcpt.to_lowercase();
+        let domain = rcpt.rsplit_once('@').map(|(_, domain)| domain.to_string());
+
+        // User policies take precedence over domain policies
+        [
+            self.policies.get(&rcpt),
+            domain.and_then(|domain| self.policies.get(&domain)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Returns `Some(true)` if the sender is allowed and `Some(false)` if it is
+    /// blocked by the recipient's user or domain policy.
+    pub fn sender_policy(&self, rcpt: &str, sender: &str) -> Option<bool> {
+        if self.policies.is_empty() || sender.is_empty() {
+            return None;
+        }
+
+        let sender = sender.to_lowercase();
+        self.policies_for(rcpt).find_map(|policy| {
+            if policy.allow.contains(&sender) {
+                Some(true)
+            } else if policy.block.contains(&sender) {
+                Some(false)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the action to take on spam messages delivered to a recipient.
+    pub fn recipient_action(&self, rcpt: &str) -> SpamPolicyAction {
+        if self.policies.is_empty() {
+            SpamPolicyAction::Junk
+        } else {
+            self.policies_for(rcpt)
+                .find_map(|policy| policy.action)
+                .unwrap_or_default()
+        }
+    }
+
+    /// Returns how messages from senders the recipient has written to are
+    /// filtered, as set by the recipient's user or domain policy.
+    pub fn known_sender_action(&self, rcpt: &str) -> KnownSenderAction {
+        match &self.known_senders {
+            Some(config) => self
+                .policies_for(rcpt)
+                .find_map(|policy| policy.known_sender)
+                .unwrap_or(config.action),
+            None => KnownSenderAction::Ignore,
+        }
+    }
+
+    /// Rescores the value of the spam status header, comparing the adjusted
+    /// score against the recipient's spam threshold.
+    pub fn adjust_status(&self, rcpt: &str, status: &str, adjustment: f64) -> Option<String> {
+        let score = status
+            .split_once("score=")
+            .and_then(|(_, score)| score.trim().parse::<f64>().ok())?
+            + adjustment;
+        let threshold = self
+            .policies_for(rcpt)
+            .find_map(|policy| policy.spam_threshold)
+            .unwrap_or(self.scores.spam_threshold);
+
+        Some(format!(
+            "{}, score={:.2}",
+            if score >= threshold { "Yes" } else { "No" },
+            score
+        ))
+    }
+
+    /// Resolves the verdict for a recipient from the value of the spam status header.
+    pub fn recipient_verdict(&self, rcpt: &str, status: Option<&str>) -> SpamVerdict<'_> {
+        let Some(status) = status else {
+            return SpamVerdict::Ham;
+        };
+        let is_spam = status.contains("Yes");
+        let score = status
+            .split_once("score=")
+            .and_then(|(_, score)| score.trim().parse::<f64>().ok());
+
+        if self.policies.is_empty() {
+            return if is_spam {
+                SpamVerdict::Spam {
+                    action: SpamPolicyAction::Junk,
+                    subject_tag: None,
+                }
+            } else {
+                SpamVerdict::Ham
+            };
+        }
+
+        let policies = self.policies_for(rcpt).collect::<Vec<_>>();
+        let resolve = |f: fn(&SpamPolicy) -> Option<f64>| policies.iter().find_map(|p| f(p));
+
+        let is_spam = if let Some(score) = score {
+            if resolve(|p| p.reject_threshold).is_some_and(|t| score >= t) {
+                return SpamVerdict::Reject;
+            } else if resolve(|p| p.discard_threshold).is_some_and(|t| score >= t) {
+                return SpamVerdict::Discard;
+            }
+
+            resolve(|p| p.spam_threshold).map_or(is_spam, |t| score >= t)
+        } else {
+            is_spam
+        };
+
+        if is_spam {
+            SpamVerdict::Spam {
+                action: policies.iter().find_map(|p| p.action).unwrap_or_default(),
+                subject_tag: policies.iter().find_map(|p| p.subject_tag.as_deref()),
+            }
+        } else {
+            SpamVerdict::Ham
+        }
+    }
+}
+
+impl LinkProtectionConfig {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("spam-filter.link-protection.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        let mut exclude = GlobSet::default();
+        let mode = match config
+            .value("spam-filter.link-protection.mode")
+            .unwrap_or("rewrite")
+        {
+            "rewrite" => {
+                let redirector = config
+                    .value_require("spam-filter.link-protection.redirector")?
+                    .trim()
+                    .to_string();
+                if !redirector.contains("{url}") {
+                    config.new_parse_error(
+                        "spam-filter.link-protection.redirector",
+                        "Redirector URL must contain the {url} placeholder",
+                    );
+                    return None;
+                }
+
+                // Links pointing to the redirector itself are never rewritten
+                if let Some(host) = url_host(&redirector) {
+                    exclude.insert(&host);
+                }
+
+                LinkProtectionMode::Rewrite { redirector }
+            }
+            "tag" => LinkProtectionMode::Tag {
+                tag: config
+                    .value("spam-filter.link-protection.tag")
+                    .unwrap_or("[Suspicious link]")
+                    .to_string(),
+            },
+            other => {
+                let err = format!("Invalid link protection mode {other:?}");
+                config.new_parse_error("spam-filter.link-protection.mode", err);
+                return None;
+            }
+        };
+
+        for (_, host) in config.values("spam-filter.link-protection.exclude") {
+            let host = host.trim().to_lowercase();
+            if !host.is_empty() {
+                exclude.insert(&host);
+            }
         }
+
+        Some(LinkProtectionConfig {
+            spam_only: config
+                .property_or_default(
+                    "spam-filter.link-protection.spam-only",
+                    if matches!(mode, LinkProtectionMode::Tag { .. }) {
+                        "true"
+                    } else {
+                        "false"
+                    },
+                )
+                .unwrap_or_default(),
+            mode,
+            domains: config
+                .values("spam-filter.link-protection.domains")
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            exclude,
+        })
+    }
+
+    /// Returns whether link protection is enabled for the recipient's domain.
+    pub fn is_enabled_for(&self, rcpt: &str) -> bool {
+        self.domains.is_empty()
+            || rcpt
+                .rsplit_once('@')
+                .is_some_and(|(_, domain)| self.domains.contains(&domain.to_lowercase()))
+    }
+
+    /// Returns whether a link pointing to this URL is excluded from protection.
+    pub fn is_excluded(&self, url: &str) -> bool {
+        url_host(url).is_none_or(|host| self.exclude.contains(&host))
+    }
+}
+
+/// Extracts the lowercase host name from an http(s) URL.
+pub fn url_host(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host)
+        .split(':')
+        .next()?;
+
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+impl ReputationApiConfig {
+    pub fn parse(config: &mut Config) -> AHashMap<String, ReputationApiConfig> {
+        let mut apis = AHashMap::new();
+
+        for id in config
+            .sub_keys("spam-filter.reputation-api", "")
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+        {
+            if let Some(api) = ReputationApiConfig::parse_api(config, &id) {
+                apis.insert(id, api);
+            }
+        }
+
+        apis
+    }
+
+    fn parse_api(config: &mut Config, id: &str) -> Option<Self> {
+        let prefix = ("spam-filter.reputation-api", id);
+        if !config
+            .property_or_default((prefix.0, id, "enable"), "true")
+            .unwrap_or(true)
+        {
+            return None;
+        }
+
+        let api_key = config
+            .value((prefix.0, id, "api-key"))
+            .map(|v| v.to_string());
+        let mut headers = Vec::new();
+
+        // Defaults for well-known services
+        let (url, body, hash, field, malicious) =
+            match config.value((prefix.0, id, "type")).unwrap_or("custom") {
+                "urlhaus" => {
+                    if let Some(api_key) = &api_key {
+                        headers.push(("Auth-Key".to_string(), api_key.clone()));
+                    }
+                    (
+                        "https://urlhaus-api.abuse.ch/v1/url/",
+                        Some("url={value}"),
+                        false,
+                        "/query_status",
+                        vec!["ok".to_string()],
+                    )
+                }
+                "phishtank" => (
+                    "https://checkurl.phishtank.com/checkurl/",
+                    Some("url={value}&format=json"),
+                    false,
+                    "/results/valid",
+                    vec![],
+                ),
+                "virustotal" => {
+                    if let Some(api_key) = &api_key {
+                        headers.push(("x-apikey".to_string(), api_key.clone()));
+                    }
+                    (
+                        "https://www.virustotal.com/api/v3/urls/{value}",
+                        None,
+                        true,
+                        "/data/attributes/last_analysis_stats/malicious",
+                        vec![],
+                    )
+                }
+                "custom" => ("", None, false, "", vec![]),
+                other => {
+                    let err = format!("Invalid reputation API type {other:?}");
+                    config.new_parse_error((prefix.0, id, "type"), err);
+                    return None;
+                }
+            };
+
+        for (_, header) in config.values((prefix.0, id, "headers")) {
+            if let Some((name, value)) = header.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        let url = config
+            .value((prefix.0, id, "url"))
+            .unwrap_or(url)
+            .trim()
+            .to_string();
+        if url.is_empty() {
+            config.new_parse_error((prefix.0, id, "url"), "Missing reputation API URL");
+            return None;
+        } else if !url.contains("{value}")
+            && body.is_none()
+            && !config.contains_key((prefix.0, id, "body"))
+        {
+            config.new_parse_error(
+                (prefix.0, id, "url"),
+                "Reputation API URL or body must contain the {value} placeholder",
+            );
+            return None;
+        }
+
+        Some(ReputationApiConfig {
+            url,
+            body: config
+                .value((prefix.0, id, "body"))
+                .or(body)
+                .map(|v| v.to_string()),
+            headers,
+            hash: config.property((prefix.0, id, "hash")).unwrap_or(hash),
+            timeout: config
+                .property_or_default((prefix.0, id, "timeout"), "5s")
+                .unwrap_or_else(|| Duration::from_secs(5)),
+            rate: config
+                .property_or_default::<Option<Rate>>((prefix.0, id, "rate-limit"), "false")
+                .unwrap_or_default(),
+            ttl_malicious: config
+                .property_or_default::<Duration>((prefix.0, id, "cache.ttl.malicious"), "1d")
+                .unwrap_or_else(|| Duration::from_secs(86400))
+                .as_secs(),
+            ttl_clean: config
+                .property_or_default::<Duration>((prefix.0, id, "cache.ttl.clean"), "1h")
+                .unwrap_or_else(|| Duration::from_secs(3600))
+                .as_secs(),
+            field: config
+                .value((prefix.0, id, "response.field"))
+                .unwrap_or(field)
+                .to_string(),
+            malicious: Some(
+                config
+                    .values((prefix.0, id, "response.malicious"))
+                    .map(|(_, v)| v.to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .filter(|values| !values.is_empty())
+            .unwrap_or(malicious),
+        })
+    }
+}
+
+impl SpamPolicy {
+    pub fn parse(config: &mut Config) -> AHashMap<String, SpamPolicy> {
+        let mut policies = AHashMap::new();
+
+        for id in config
+            .sub_keys("spam-filter.policy", "")
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+        {
+            let id = id.as_str();
+            let mut policy = SpamPolicy {
+                spam_threshold: config.property(("spam-filter.policy", id, "score.spam")),
+                discard_threshold: config.property(("spam-filter.policy", id, "score.discard")),
+                reject_threshold: config.property(("spam-filter.policy", id, "score.reject")),
+                action: config.property(("spam-filter.policy", id, "action")),
+                subject_tag: config
+                    .value(("spam-filter.policy", id, "subject-tag"))
+                    .map(|v| v.to_string()),
+                allow: GlobSet::default(),
+                block: GlobSet::default(),
+                known_sender: config.property(("spam-filter.policy", id, "known-sender")),
+            };
+            for (list, set) in [("allow", &mut policy.allow), ("block", &mut policy.block)] {
+                for (_, entry) in config.values(("spam-filter.policy", id, list)) {
+                    let entry = entry.trim().to_lowercase();
+                    if entry.contains('@') {
+                        set.insert(&entry);
+                    } else if !entry.is_empty() {
+                        set.insert(&format!("*@{entry}"));
+                    }
+                }
+            }
+
+            let matches = config
+                .values(("spam-filter.policy", id, "match"))
+                .map(|(_, v)| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect::<Vec<_>>();
+            for address in matches {
+                if policies.contains_key(&address) {
+                    config.new_build_error(
+                        ("spam-filter.policy", id, "match"),
+                        format!("Duplicate spam policy for {address:?}."),
+                    );
+                } else {
+                    policies.insert(address, policy.clone());
+                }
+            }
+        }
+
+        policies
     }
 }
 
@@ -330,6 +843,7 @@ impl SpamFilterHeaderConfig {
             ("result", &mut header.result),
             ("llm", &mut header.llm),
             ("bayes", &mut header.bayes_result),
+            ("exemption", &mut header.exemption),
         ] {
             if config
                 .property_or_default(("spam-filter.header", typ, "enable"), "true")
@@ -423,6 +937,40 @@ impl SpamFilterLists {
     }
 }
 
+impl RspamdConfig {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("spam-filter.rspamd.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        RspamdConfig {
+            url: config
+                .value("spam-filter.rspamd.url")
+                .unwrap_or("http://127.0.0.1:11333")
+                .trim_end_matches('/')
+                .to_string(),
+            controller_url: config
+                .value("spam-filter.rspamd.controller-url")
+                .unwrap_or("http://127.0.0.1:11334")
+                .trim_end_matches('/')
+                .to_string(),
+            password: config
+                .value("spam-filter.rspamd.password")
+                .map(|v| v.to_string()),
+            timeout: config
+                .property_or_default::<Duration>("spam-filter.rspamd.timeout", "10s")
+                .unwrap_or(Duration::from_secs(10)),
+            learn: config
+                .property_or_default("spam-filter.rspamd.learn", "true")
+                .unwrap_or(true),
+        }
+        .into()
+    }
+}
+
 impl PyzorConfig {
     pub async fn parse(config: &mut Config) -> Option<Self> {
         if !config
@@ -604,6 +1152,54 @@ impl SpamFilterExpiryConfig {
                 )
                 .unwrap_or_default()
                 .map(|d| d.as_secs()),
+            known_sender: config
+                .property_or_default::<Option<Duration>>(
+                    "spam-filter.known-sender.duration",
+                    "180d",
+                )
+                .unwrap_or_default()
+                .map(|d| d.as_secs()),
+        }
+    }
+}
+
+impl KnownSenderConfig {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("spam-filter.known-sender.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        Some(KnownSenderConfig {
+            action: config
+                .property_or_default("spam-filter.known-sender.action", "score")
+                .unwrap_or(KnownSenderAction::Score),
+            score: config
+                .property_or_default("spam-filter.known-sender.score", "-5.0")
+                .unwrap_or(-5.0),
+        })
+    }
+}
+
+impl ParseValue for SpamPolicyAction {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "junk" | "move" => Ok(SpamPolicyAction::Junk),
+            "header" => Ok(SpamPolicyAction::Header),
+            other => Err(format!("Invalid spam policy action {other:?}.",)),
+        }
+    }
+}
+
+impl ParseValue for KnownSenderAction {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "skip" => Ok(KnownSenderAction::Skip),
+            "score" => Ok(KnownSenderAction::Score),
+            "ignore" | "none" => Ok(KnownSenderAction::Ignore),
+            other => Err(format!("Invalid known sender action {other:?}.",)),
         }
     }
 }
@@ -654,6 +1250,7 @@ impl Default for SpamFilterHeaderConfig {
             result: "X-Spam-Result".to_string().into(),
             bayes_result: "X-Spam-Bayes".to_string().into(),
             llm: "X-Spam-LLM".to_string().into(),
+            exemption: "X-Spam-Exemption".to_string().into(),
         }
     }
 }
@@ -4,11 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use ahash::AHashMap;
 use directory::Directory;
-use store::{BlobStore, FtsStore, InMemoryStore, PurgeSchedule, Store};
+use store::{BlobStore, FtsStore, InMemoryStore, PurgeSchedule, Rows, Store, Value};
+use utils::{cache::CacheItemWeight, config::Config};
 
 use crate::manager::config::ConfigManager;
 
@@ -27,4 +28,97 @@ pub struct Storage {
     pub blobs: AHashMap<String, BlobStore>,
     pub lookups: AHashMap<String, InMemoryStore>,
     pub ftss: AHashMap<String, FtsStore>,
+
+    pub queries: AHashMap<String, Arc<LookupQuery>>,
+}
+
+#[derive(Clone)]
+pub struct LookupQuery {
+    pub store: Store,
+    pub query: String,
+    pub cache_ttl: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LookupRows(pub Arc<Rows>);
+
+impl LookupQuery {
+    pub fn parse_all(
+        config: &mut Config,
+        stores: &AHashMap<String, Store>,
+        default_store: &Store,
+    ) -> AHashMap<String, Arc<LookupQuery>> {
+        let mut queries = AHashMap::new();
+
+        for id in config
+            .sub_keys("lookup.query", ".query")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            let query = config
+                .value(("lookup.query", id.as_str(), "query"))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            if !query
+                .as_bytes()
+                .get(..6)
+                .is_some_and(|q| q.eq_ignore_ascii_case(b"SELECT"))
+            {
+                config.new_build_error(
+                    ("lookup.query", id.as_str(), "query"),
+                    "Only SELECT statements are allowed in lookup queries",
+                );
+                continue;
+            }
+
+            let store = match config
+                .value(("lookup.query", id.as_str(), "store"))
+                .map(|s| s.to_string())
+            {
+                Some(store_id) => match stores.get(&store_id) {
+                    Some(store) => store.clone(),
+                    None => {
+                        config.new_build_error(
+                            ("lookup.query", id.as_str(), "store"),
+                            format!("Store {store_id:?} not found"),
+                        );
+                        continue;
+                    }
+                },
+                None => default_store.clone(),
+            };
+
+            queries.insert(
+                id.clone(),
+                Arc::new(LookupQuery {
+                    store,
+                    query,
+                    cache_ttl: config.property(("lookup.query", id.as_str(), "cache.ttl")),
+                }),
+            );
+        }
+
+        queries
+    }
+
+    pub fn cache_key(id: &str, params: &[Value<'_>]) -> String {
+        format!("{id}:{params:?}")
+    }
+}
+
+impl CacheItemWeight for LookupRows {
+    fn weight(&self) -> u64 {
+        self.0
+            .rows
+            .iter()
+            .flat_map(|row| row.values.iter())
+            .map(|value| match value {
+                Value::Text(text) => text.len() as u64,
+                Value::Blob(blob) => blob.len() as u64,
+                _ => std::mem::size_of::<i64>() as u64,
+            })
+            .sum::<u64>()
+            + std::mem::size_of::<Rows>() as u64
+    }
 }
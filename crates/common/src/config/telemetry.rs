@@ -42,6 +42,8 @@ pub enum TelemetrySubscriberType {
     Webhook(WebhookTracer),
     #[cfg(unix)]
     JournalTracer(crate::telemetry::tracers::journald::Subscriber),
+    #[cfg(windows)]
+    EventLogTracer(crate::telemetry::tracers::eventlog::Subscriber),
 }
 
 #[derive(Debug)]
@@ -114,6 +116,15 @@ pub struct Metrics {
     pub prometheus: Option<PrometheusMetrics>,
     pub otel: Option<Arc<OtelMetrics>>,
     pub log_path: Option<String>,
+    pub storage_forecast: Option<StorageForecast>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageForecast {
+    pub history: usize,
+    pub min_samples: usize,
+    pub alert_threshold: Duration,
+    pub capacity: Option<u64>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -432,6 +443,51 @@ impl Tracers {
                         continue;
                     }
                 }
+                "eventlog" => {
+                    #[cfg(windows)]
+                    {
+                        if !tracers
+                            .iter()
+                            .any(|t| matches!(t.typ, TelemetrySubscriberType::EventLogTracer(_)))
+                        {
+                            use crate::telemetry::tracers::eventlog::{
+                                DEFAULT_SOURCE_NAME, Subscriber,
+                            };
+
+                            match Subscriber::new(
+                                config
+                                    .value(("tracer", id, "source"))
+                                    .unwrap_or(DEFAULT_SOURCE_NAME),
+                            ) {
+                                Ok(subscriber) => {
+                                    TelemetrySubscriberType::EventLogTracer(subscriber)
+                                }
+                                Err(e) => {
+                                    config.new_build_error(
+                                        ("tracer", id, "type"),
+                                        format!("Failed to register event log source: {e}"),
+                                    );
+                                    continue;
+                                }
+                            }
+                        } else {
+                            config.new_build_error(
+                                ("tracer", id, "type"),
+                                "Only one event log tracer is allowed".to_string(),
+                            );
+                            continue;
+                        }
+                    }
+
+                    #[cfg(not(windows))]
+                    {
+                        config.new_build_error(
+                            ("tracer", id, "type"),
+                            "The event log is only available on Windows systems.",
+                        );
+                        continue;
+                    }
+                }
                 unknown => {
                     config.new_parse_error(
                         ("tracer", id, "type"),
@@ -477,6 +533,10 @@ impl Tracers {
                 TelemetrySubscriberType::JournalTracer(_) => {
                     EventType::Telemetry(TelemetryEvent::JournalError).into()
                 }
+                #[cfg(windows)]
+                TelemetrySubscriberType::EventLogTracer(_) => {
+                    EventType::Telemetry(TelemetryEvent::EventLogError).into()
+                }
             };
 
             // Parse disabled events
@@ -557,6 +617,7 @@ impl Metrics {
             prometheus: None,
             otel: None,
             log_path: None,
+            storage_forecast: None,
         };
 
         // Obtain log path
@@ -702,6 +763,36 @@ impl Metrics {
             }
         }
 
+        // Daily storage usage samples used to forecast quota and disk exhaustion
+        if config
+            .property_or_default("metrics.storage-forecast.enable", "false")
+            .unwrap_or(false)
+        {
+            let history = config
+                .property_or_default::<usize>("metrics.storage-forecast.history", "90")
+                .unwrap_or(90);
+            let min_samples = config
+                .property_or_default::<usize>("metrics.storage-forecast.min-samples", "7")
+                .unwrap_or(7);
+            if min_samples < 2 || min_samples > history {
+                config.new_build_error(
+                    "metrics.storage-forecast.min-samples",
+                    "The minimum number of samples must be between 2 and the history length",
+                );
+            } else {
+                metrics.storage_forecast = Some(StorageForecast {
+                    history,
+                    min_samples,
+                    alert_threshold: config
+                        .property_or_default("metrics.storage-forecast.alert-threshold", "30d")
+                        .unwrap_or_else(|| Duration::from_secs(30 * 86400)),
+                    capacity: config
+                        .property::<u64>("metrics.storage-forecast.capacity")
+                        .filter(|capacity| *capacity > 0),
+                });
+            }
+        }
+
         metrics
     }
 }
@@ -6,15 +6,18 @@
 
 use std::{sync::Arc, time::Duration};
 
-use directory::{Directory, QueryBy, Type, backend::internal::manage::ManageDirectory};
+use directory::{
+    Directory, QueryBy, TENANT_QUOTA_OUTBOUND, TENANT_QUOTA_STORAGE, Type,
+    backend::internal::manage::ManageDirectory,
+};
 use jmap_proto::types::{
     blob::BlobId, collection::Collection, property::Property, state::StateChange,
 };
 use sieve::Sieve;
 use store::{
     BitmapKey, BlobClass, BlobStore, Deserialize, FtsStore, InMemoryStore, IndexKey, IterateParams,
-    LogKey, Serialize, Store, U32_LEN, ValueKey,
-    dispatch::DocumentSet,
+    LogKey, Rows, Serialize, Store, U32_LEN, Value, ValueKey,
+    dispatch::{DocumentSet, lookup::KeyValue},
     roaring::RoaringBitmap,
     write::{
         BatchBuilder, BitmapClass, BlobOp, DirectoryClass, QueueClass, TagValue, ValueClass,
@@ -25,13 +28,16 @@ use trc::AddContext;
 use utils::BlobHash;
 
 use crate::{
-    ImapId, Inner, MailboxState, Server,
+    ImapId, Inner, KV_TENANT_OUTBOUND, MailboxState, Server,
     auth::{AccessToken, ResourceToken, TenantInfo},
-    config::smtp::{
-        auth::{ArcSealer, DkimSigner, LazySignature, ResolvedSignature, build_signature},
-        queue::RelayHost,
+    config::{
+        smtp::{
+            auth::{ArcSealer, DkimSigner, LazySignature, ResolvedSignature, build_signature},
+            queue::RelayHost,
+        },
+        storage::{LookupQuery, LookupRows},
     },
-    ipc::StateEvent,
+    ipc::{BackPressureSender, StateEvent},
 };
 
 impl Server {
@@ -110,6 +116,39 @@ impl Server {
         })
     }
 
+    pub async fn lookup_query(&self, id: &str, params: Vec<Value<'_>>) -> trc::Result<Arc<Rows>> {
+        let query = self.core.storage.queries.get(id).ok_or_else(|| {
+            trc::EvalEvent::Error
+                .into_err()
+                .ctx(trc::Key::Id, id.to_string())
+                .details("Unknown lookup query")
+        })?;
+
+        // Results are only cached when a TTL is configured
+        let cache_key = query.cache_ttl.map(|_| LookupQuery::cache_key(id, &params));
+        if let Some(rows) = cache_key
+            .as_ref()
+            .and_then(|key| self.inner.cache.lookup_queries.get(key))
+        {
+            return Ok(rows.0);
+        }
+
+        let rows = query
+            .store
+            .sql_query::<Rows>(&query.query, params)
+            .await
+            .map(Arc::new)
+            .caused_by(trc::location!())?;
+        if let (Some(cache_key), Some(cache_ttl)) = (cache_key, query.cache_ttl) {
+            self.inner
+                .cache
+                .lookup_queries
+                .insert(cache_key, LookupRows(rows.clone()), cache_ttl);
+        }
+
+        Ok(rows)
+    }
+
     pub fn get_arc_sealer(&self, name: &str, session_id: u64) -> Option<Arc<ArcSealer>> {
         self.resolve_signature(name).map(|s| s.sealer).or_else(|| {
             trc::event!(
@@ -170,15 +209,20 @@ impl Server {
     }
 
     pub fn get_untrusted_sieve_script(&self, name: &str, session_id: u64) -> Option<&Arc<Sieve>> {
-        self.core.sieve.untrusted_scripts.get(name).or_else(|| {
-            trc::event!(
-                Sieve(trc::SieveEvent::ScriptNotFound),
-                Id = name.to_string(),
-                SpanId = session_id,
-            );
+        self.core
+            .sieve
+            .untrusted_scripts
+            .get(name)
+            .or_else(|| self.core.sieve.sieve_library.get(name))
+            .or_else(|| {
+                trc::event!(
+                    Sieve(trc::SieveEvent::ScriptNotFound),
+                    Id = name.to_string(),
+                    SpanId = session_id,
+                );
 
-            None
-        })
+                None
+            })
     }
 
     pub fn get_relay_host(&self, name: &str, session_id: u64) -> Option<&RelayHost> {
@@ -266,9 +310,56 @@ impl Server {
             }
         }
 
+        // Enforce the tenant's aggregate quota
+        if let Some(tenant) = quotas.tenant.filter(|tenant| tenant.quota != 0) {
+            let used_quota = self.get_used_quota(tenant.id).await? as u64;
+
+            if used_quota + item_size > tenant.quota {
+                return Err(trc::LimitEvent::TenantQuota
+                    .into_err()
+                    .ctx(trc::Key::Limit, tenant.quota)
+                    .ctx(trc::Key::Size, used_quota));
+            }
+        }
+
         Ok(())
     }
 
+    pub async fn get_tenant_info(&self, tenant_id: u32) -> trc::Result<Option<TenantInfo>> {
+        self.store()
+            .get_principal(tenant_id)
+            .await
+            .caused_by(trc::location!())
+            .map(|tenant| {
+                tenant.map(|tenant| TenantInfo {
+                    id: tenant_id,
+                    quota: tenant.tenant_quota(TENANT_QUOTA_STORAGE),
+                    max_outbound: tenant.tenant_quota(TENANT_QUOTA_OUTBOUND),
+                })
+            })
+    }
+
+    pub async fn is_tenant_outbound_allowed(&self, tenant: &TenantInfo) -> trc::Result<bool> {
+        if tenant.max_outbound == 0 {
+            return Ok(true);
+        }
+
+        let (key, expires_in) = tenant_outbound_key(tenant.id);
+        self.in_memory_store()
+            .counter_incr(KeyValue::new(key, 1).expires(expires_in), true)
+            .await
+            .caused_by(trc::location!())
+            .map(|sent| sent <= tenant.max_outbound as i64)
+    }
+
+    pub async fn get_tenant_outbound_count(&self, tenant_id: u32) -> trc::Result<u64> {
+        self.in_memory_store()
+            .counter_get(tenant_outbound_key(tenant_id).0)
+            .await
+            .caused_by(trc::location!())
+            .map(|sent| sent.max(0) as u64)
+    }
+
     pub async fn get_resource_token(
         &self,
         access_token: &AccessToken,
@@ -295,6 +386,9 @@ impl Server {
                 .add_context(|err| err.caused_by(trc::location!()).account_id(account_id))?
             {
                 quotas.quota = principal.quota();
+                if let Some(tenant_id) = principal.tenant() {
+                    quotas.tenant = self.get_tenant_info(tenant_id).await?;
+                }
             }
 
             quotas
@@ -533,24 +627,22 @@ impl Server {
     }
 
     pub async fn broadcast_state_change(&self, state_change: StateChange) -> bool {
-        match self
+        if self
             .inner
             .ipc
             .state_tx
-            .clone()
-            .send(StateEvent::Publish { state_change })
+            .send_or_wait("state", StateEvent::Publish { state_change })
             .await
         {
-            Ok(_) => true,
-            Err(_) => {
-                trc::event!(
-                    Server(trc::ServerEvent::ThreadError),
-                    Details = "Error sending state change.",
-                    CausedBy = trc::location!()
-                );
+            true
+        } else {
+            trc::event!(
+                Server(trc::ServerEvent::ThreadError),
+                Details = "Error sending state change.",
+                CausedBy = trc::location!()
+            );
 
-                false
-            }
+            false
         }
     }
 
@@ -661,3 +753,16 @@ impl MailboxState {
         }
     }
 }
+
+fn tenant_outbound_key(tenant_id: u32) -> (Vec<u8>, u64) {
+    const DAY: u64 = 86400;
+    let now = now();
+
+    (
+        KeyValue::<()>::build_key(
+            KV_TENANT_OUTBOUND,
+            [tenant_id.to_be_bytes(), ((now / DAY) as u32).to_be_bytes()].concat(),
+        ),
+        DAY - (now % DAY),
+    )
+}
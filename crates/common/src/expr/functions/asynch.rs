@@ -1,4 +1,4 @@
-use std::{cmp::Ordering, net::IpAddr, vec::IntoIter};
+use std::{cmp::Ordering, net::IpAddr, sync::Arc, vec::IntoIter};
 
 use directory::backend::RcptType;
 use mail_auth::IpLookupStrategy;
@@ -97,6 +97,25 @@ impl Server {
             }
             F_DNS_QUERY => self.dns_query(params).await,
             F_SQL_QUERY => self.sql_query(params, session_id).await,
+            F_LOOKUP_QUERY => {
+                let id = params.next_as_string();
+                let arguments = match params.next() {
+                    Variable::Array(l) => l.into_iter().map(to_store_value).collect(),
+                    v => vec![to_store_value(v)],
+                };
+
+                self.lookup_query(id.as_ref(), arguments)
+                    .await
+                    .map(|rows| rows_to_variable(Arc::unwrap_or_clone(rows)))
+            }
+            F_REPUTATION_LOOKUP => {
+                let api_id = params.next_as_string();
+                let value = params.next_as_string();
+
+                self.reputation_lookup(api_id.as_ref(), value.as_ref(), session_id)
+                    .await
+                    .map(Variable::from)
+            }
             _ => Ok(Variable::default()),
         }
     }
@@ -127,33 +146,11 @@ impl Server {
             .get(..6)
             .is_some_and(|q| q.eq_ignore_ascii_case(b"SELECT"))
         {
-            let mut rows = store
+            store
                 .sql_query::<Rows>(&query, arguments)
                 .await
-                .caused_by(trc::location!())?;
-            Ok(match rows.rows.len().cmp(&1) {
-                Ordering::Equal => {
-                    let mut row = rows.rows.pop().unwrap().values;
-                    match row.len().cmp(&1) {
-                        Ordering::Equal if !matches!(row.first(), Some(Value::Null)) => {
-                            row.pop().map(into_variable).unwrap()
-                        }
-                        Ordering::Less => Variable::default(),
-                        _ => {
-                            Variable::Array(row.into_iter().map(into_variable).collect::<Vec<_>>())
-                        }
-                    }
-                }
-                Ordering::Less => Variable::default(),
-                Ordering::Greater => rows
-                    .rows
-                    .into_iter()
-                    .map(|r| {
-                        Variable::Array(r.values.into_iter().map(into_variable).collect::<Vec<_>>())
-                    })
-                    .collect::<Vec<_>>()
-                    .into(),
-            })
+                .caused_by(trc::location!())
+                .map(rows_to_variable)
         } else {
             store
                 .sql_query::<usize>(&query, arguments)
@@ -350,6 +347,28 @@ fn to_store_value(value: Variable) -> Value {
     }
 }
 
+fn rows_to_variable<'x>(mut rows: Rows) -> Variable<'x> {
+    match rows.rows.len().cmp(&1) {
+        Ordering::Equal => {
+            let mut row = rows.rows.pop().unwrap().values;
+            match row.len().cmp(&1) {
+                Ordering::Equal if !matches!(row.first(), Some(Value::Null)) => {
+                    row.pop().map(into_variable).unwrap()
+                }
+                Ordering::Less => Variable::default(),
+                _ => Variable::Array(row.into_iter().map(into_variable).collect::<Vec<_>>()),
+            }
+        }
+        Ordering::Less => Variable::default(),
+        Ordering::Greater => rows
+            .rows
+            .into_iter()
+            .map(|r| Variable::Array(r.values.into_iter().map(into_variable).collect::<Vec<_>>()))
+            .collect::<Vec<_>>()
+            .into(),
+    }
+}
+
 fn into_variable(value: Value) -> Variable {
     match value {
         Value::Integer(v) => Variable::Integer(v),
@@ -12,6 +12,7 @@ pub mod array;
 pub mod asynch;
 pub mod email;
 pub mod misc;
+pub mod reputation;
 pub mod text;
 
 pub trait ResolveVariable: Sync + Send {
@@ -92,6 +93,8 @@ pub const F_COUNTER_INCR: u32 = 5;
 pub const F_COUNTER_GET: u32 = 6;
 pub const F_SQL_QUERY: u32 = 7;
 pub const F_DNS_QUERY: u32 = 8;
+pub const F_REPUTATION_LOOKUP: u32 = 9;
+pub const F_LOOKUP_QUERY: u32 = 10;
 
 pub const ASYNC_FUNCTIONS: &[(&str, u32, u32)] = &[
     ("is_local_domain", F_IS_LOCAL_DOMAIN, 2),
@@ -103,4 +106,6 @@ pub const ASYNC_FUNCTIONS: &[(&str, u32, u32)] = &[
     ("counter_get", F_COUNTER_GET, 2),
     ("dns_query", F_DNS_QUERY, 2),
     ("sql_query", F_SQL_QUERY, 3),
+    ("reputation_lookup", F_REPUTATION_LOOKUP, 2),
+    ("lookup_query", F_LOOKUP_QUERY, 2),
 ];
@@ -0,0 +1,186 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use sha2::{Digest, Sha256};
+use store::dispatch::lookup::KeyValue;
+use trc::AddContext;
+
+use crate::{
+    config::spamfilter::ReputationApiConfig, Server, KV_RATE_LIMIT_REPUTATION_API,
+    KV_REPUTATION_API,
+};
+
+pub const REPUTATION_MALICIOUS: &str = "malicious";
+pub const REPUTATION_CLEAN: &str = "clean";
+pub const REPUTATION_UNKNOWN: &str = "unknown";
+
+impl Server {
+    /// Looks up the reputation of a URL, domain or file hash using an external API,
+    /// returning `malicious`, `clean` or `unknown`.
+    pub async fn reputation_lookup(
+        &self,
+        api_id: &str,
+        value: &str,
+        session_id: u64,
+    ) -> trc::Result<&'static str> {
+        let Some(api) = self.core.spam.reputation_apis.get(api_id) else {
+            return Err(trc::EventType::Eval(trc::EvalEvent::Error)
+                .into_err()
+                .details("Unknown reputation API")
+                .ctx(trc::Key::Id, api_id.to_string()));
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(REPUTATION_UNKNOWN);
+        }
+
+        // Values are only stored hashed
+        let digest = Sha256::digest(value.as_bytes());
+        let mut cache_key = Vec::with_capacity(api_id.len() + 1 + digest.len());
+        cache_key.extend_from_slice(api_id.as_bytes());
+        cache_key.push(0);
+        cache_key.extend_from_slice(&digest);
+        if let Some(result) = self
+            .in_memory_store()
+            .key_get::<String>(KeyValue::<()>::build_key(KV_REPUTATION_API, &cache_key))
+            .await
+            .caused_by(trc::location!())?
+        {
+            return Ok(if result == REPUTATION_MALICIOUS {
+                REPUTATION_MALICIOUS
+            } else {
+                REPUTATION_CLEAN
+            });
+        }
+
+        // Enforce the API rate limit
+        if let Some(rate) = &api.rate
+            && self
+                .in_memory_store()
+                .is_rate_allowed(KV_RATE_LIMIT_REPUTATION_API, api_id.as_bytes(), rate, false)
+                .await
+                .caused_by(trc::location!())?
+                .is_some()
+        {
+            trc::event!(
+                Eval(trc::EvalEvent::Error),
+                SpanId = session_id,
+                Id = api_id.to_string(),
+                Details = "Reputation API rate limit exceeded",
+            );
+            return Ok(REPUTATION_UNKNOWN);
+        }
+
+        let value = if api.hash {
+            digest
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>()
+        } else {
+            form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>()
+        };
+        let result = match api.query(&value).await {
+            Ok(result) => result,
+            Err(err) => {
+                trc::error!(err
+                    .span_id(session_id)
+                    .ctx(trc::Key::Id, api_id.to_string())
+                    .caused_by(trc::location!()));
+                return Ok(REPUTATION_UNKNOWN);
+            }
+        };
+
+        self.in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(KV_REPUTATION_API, &cache_key, result.as_bytes().to_vec())
+                    .expires(if result == REPUTATION_MALICIOUS {
+                        api.ttl_malicious
+                    } else {
+                        api.ttl_clean
+                    }),
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(result)
+    }
+}
+
+impl ReputationApiConfig {
+    async fn query(&self, value: &str) -> trc::Result<&'static str> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|err| {
+                trc::EventType::Eval(trc::EvalEvent::Error)
+                    .into_err()
+                    .reason(err)
+                    .details("Failed to build request")
+            })?;
+        let url = self.url.replace("{value}", value);
+        let mut request = if let Some(body) = &self.body {
+            client
+                .post(url)
+                .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(body.replace("{value}", value))
+        } else {
+            client.get(url)
+        };
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let response = request.send().await.map_err(|err| {
+            trc::EventType::Eval(trc::EvalEvent::Error)
+                .into_err()
+                .reason(err)
+                .details("Failed to send request")
+        })?;
+        match response.status() {
+            StatusCode::NOT_FOUND => return Ok(REPUTATION_CLEAN),
+            status if !status.is_success() => {
+                return Err(trc::EventType::Eval(trc::EvalEvent::Error)
+                    .into_err()
+                    .ctx(trc::Key::Code, status.as_u16())
+                    .details("Reputation API returned an error"));
+            }
+            _ => (),
+        }
+
+        let response = response
+            .bytes()
+            .await
+            .map_err(|err| {
+                trc::EventType::Eval(trc::EvalEvent::Error)
+                    .into_err()
+                    .reason(err)
+                    .details("Failed to read response")
+            })
+            .and_then(|bytes| {
+                serde_json::from_slice::<serde_json::Value>(&bytes).map_err(|err| {
+                    trc::EventType::Eval(trc::EvalEvent::Error)
+                        .into_err()
+                        .reason(err)
+                        .details("Failed to parse response")
+                })
+            })?;
+        let is_malicious = match response.pointer(&self.field) {
+            Some(serde_json::Value::Bool(value)) => *value,
+            Some(serde_json::Value::Number(value)) => value.as_f64().is_some_and(|v| v > 0.0),
+            Some(serde_json::Value::String(value)) => {
+                self.malicious.iter().any(|v| v.eq_ignore_ascii_case(value))
+            }
+            _ => false,
+        };
+
+        Ok(if is_malicious {
+            REPUTATION_MALICIOUS
+        } else {
+            REPUTATION_CLEAN
+        })
+    }
+}
@@ -37,6 +37,7 @@ pub const V_HEADERS: u32 = 23;
 pub const V_METHOD: u32 = 24;
 pub const V_ASN: u32 = 25;
 pub const V_COUNTRY: u32 = 26;
+pub const V_REPUTATION: u32 = 27;
 
 pub const VARIABLES_MAP: &[(&str, u32)] = &[
     ("rcpt", V_RECIPIENT),
@@ -66,6 +67,7 @@ pub const VARIABLES_MAP: &[(&str, u32)] = &[
     ("method", V_METHOD),
     ("asn", V_ASN),
     ("country", V_COUNTRY),
+    ("reputation", V_REPUTATION),
 ];
 
 use regex::Regex;
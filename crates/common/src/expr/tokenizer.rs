@@ -369,6 +369,7 @@ impl TokenMap {
             V_QUEUE_LAST_ERROR,
             V_ASN,
             V_COUNTRY,
+            V_REPUTATION,
         ])
     }
 
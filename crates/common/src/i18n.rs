@@ -0,0 +1,157 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use mail_parser::{HeaderName, HeaderValue, Message};
+use nlp::language::{
+    detect::{LanguageDetector, MIN_LANGUAGE_SCORE},
+    Language,
+};
+use store::dispatch::lookup::KeyValue;
+use utils::sanitize_language;
+
+use crate::{Server, KV_LANGUAGE_HINT};
+
+// Only the beginning of the body is used to detect its language
+const MAX_DETECT_LEN: usize = 2048;
+
+impl Server {
+    /// Resolves the preferred language of a recipient, using the default
+    /// language for addresses that do not belong to a local account.
+    pub async fn recipient_language(&self, address: &str, session_id: u64) -> String {
+        if !self.core.i18n.has_translations() {
+            return self.core.i18n.default_language.clone();
+        }
+
+        match self
+            .email_to_id(self.directory(), address, session_id)
+            .await
+        {
+            Ok(Some(account_id)) => self.account_language(account_id).await,
+            Ok(None) => self.core.i18n.default_language.clone(),
+            Err(err) => {
+                trc::error!(err
+                    .span_id(session_id)
+                    .details("Failed to resolve recipient language")
+                    .caused_by(trc::location!()));
+                self.core.i18n.default_language.clone()
+            }
+        }
+    }
+
+    /// Obtains an account's language from its directory locale, or otherwise
+    /// from the language detected in the messages it has recently sent.
+    pub async fn account_language(&self, account_id: u32) -> String {
+        if !self.core.i18n.has_translations() {
+            return self.core.i18n.default_language.clone();
+        }
+
+        match self.get_access_token(account_id).await {
+            Ok(access_token) => {
+                if let Some(locale) = &access_token.locale {
+                    return locale.clone();
+                }
+            }
+            Err(err) => {
+                trc::error!(err
+                    .account_id(account_id)
+                    .details("Failed to obtain access token")
+                    .caused_by(trc::location!()));
+            }
+        }
+
+        if self.core.i18n.learn_expiry.is_some() {
+            match self
+                .in_memory_store()
+                .key_get::<String>(KeyValue::<()>::build_key(
+                    KV_LANGUAGE_HINT,
+                    account_id.to_be_bytes(),
+                ))
+                .await
+            {
+                Ok(Some(language)) => return language,
+                Ok(None) => (),
+                Err(err) => {
+                    trc::error!(err
+                        .account_id(account_id)
+                        .details("Failed to obtain language hint")
+                        .caused_by(trc::location!()));
+                }
+            }
+        }
+
+        self.core.i18n.default_language.clone()
+    }
+
+    /// Records the language of a message sent by an account, which is used
+    /// when the account has no locale set in the directory.
+    pub async fn learn_account_language(&self, account_id: u32, message: &Message<'_>) {
+        let Some(expiry) = self
+            .core
+            .i18n
+            .learn_expiry
+            .filter(|_| self.core.i18n.has_translations())
+        else {
+            return;
+        };
+        let Some(language) = self.message_language(message) else {
+            return;
+        };
+
+        if let Err(err) = self
+            .in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_LANGUAGE_HINT,
+                    account_id.to_be_bytes(),
+                    language.into_bytes(),
+                )
+                .expires(expiry.as_secs()),
+            )
+            .await
+        {
+            trc::error!(err
+                .account_id(account_id)
+                .details("Failed to store language hint")
+                .caused_by(trc::location!()));
+        }
+    }
+
+    fn message_language(&self, message: &Message<'_>) -> Option<String> {
+        // Prefer the language declared by the sender's client
+        let declared = match message
+            .root_part()
+            .headers
+            .header_value(&HeaderName::ContentLanguage)
+        {
+            Some(HeaderValue::Text(value)) => sanitize_language(value),
+            Some(HeaderValue::TextList(values)) => {
+                values.first().and_then(|value| sanitize_language(value))
+            }
+            _ => None,
+        };
+        if declared.is_some() {
+            return declared;
+        }
+
+        // Otherwise detect it, only learning languages with translations
+        let text = message.body_text(0)?;
+        let text = text
+            .char_indices()
+            .nth(MAX_DETECT_LEN)
+            .map_or(text.as_ref(), |(pos, _)| &text[..pos]);
+        let (detected, score) = LanguageDetector::detect_single(text)?;
+        if score < MIN_LANGUAGE_SCORE {
+            return None;
+        }
+
+        self.core
+            .i18n
+            .catalog
+            .keys()
+            .find(|language| Language::from_iso_639(language) == Some(detected))
+            .cloned()
+    }
+}
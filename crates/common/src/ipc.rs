@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{sync::Arc, time::Instant};
+use std::{future::Future, sync::Arc, time::Instant};
 
 use ahash::RandomState;
 use jmap_proto::types::{state::StateChange, type_state::DataType};
@@ -14,12 +14,16 @@ use mail_auth::{
     report::{tlsrpt::FailureDetails, Record},
 };
 use store::{BlobStore, InMemoryStore, Store};
-use tokio::sync::mpsc;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use trc::{Collector, MetricType, ServerEvent};
 use utils::map::bitmap::Bitmap;
 
-use crate::config::smtp::{
-    report::AggregateFrequency,
-    resolver::{Policy, Tlsa},
+use crate::{
+    config::smtp::{
+        report::AggregateFrequency,
+        resolver::{Policy, Tlsa},
+    },
+    Ipc,
 };
 
 pub enum HousekeeperEvent {
@@ -140,6 +144,58 @@ pub enum PolicyType {
     None,
 }
 
+pub trait BackPressureSender<T>: Sync + Send {
+    // Waits for the receiver to drain when the channel is full, returns false if it was closed
+    fn send_or_wait(&self, channel: &'static str, event: T) -> impl Future<Output = bool> + Send;
+}
+
+impl<T: Send> BackPressureSender<T> for mpsc::Sender<T> {
+    async fn send_or_wait(&self, channel: &'static str, event: T) -> bool {
+        match self.try_send(event) {
+            Ok(_) => true,
+            Err(TrySendError::Full(event)) => {
+                trc::event!(
+                    Server(ServerEvent::ChannelFull),
+                    Id = channel,
+                    Limit = self.max_capacity(),
+                );
+
+                self.send(event).await.is_ok()
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+impl Ipc {
+    pub fn update_channel_metrics(&self) {
+        for (metric, max_capacity, capacity) in [
+            (
+                MetricType::StateChannelUsage,
+                self.state_tx.max_capacity(),
+                self.state_tx.capacity(),
+            ),
+            (
+                MetricType::HousekeeperChannelUsage,
+                self.housekeeper_tx.max_capacity(),
+                self.housekeeper_tx.capacity(),
+            ),
+            (
+                MetricType::QueueChannelUsage,
+                self.queue_tx.max_capacity(),
+                self.queue_tx.capacity(),
+            ),
+            (
+                MetricType::ReportChannelUsage,
+                self.report_tx.max_capacity(),
+                self.report_tx.capacity(),
+            ),
+        ] {
+            Collector::update_gauge(metric, max_capacity.saturating_sub(capacity) as u64);
+        }
+    }
+}
+
 pub trait ToHash {
     fn to_hash(&self) -> u64;
 }
@@ -5,41 +5,54 @@
  */
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, VecDeque},
     hash::{BuildHasher, Hasher},
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::{
-        atomic::{AtomicBool, AtomicU8},
+        atomic::{AtomicBool, AtomicU64, AtomicU8},
         Arc,
     },
+    time::Duration,
 };
 
-use ahash::{AHashMap, AHashSet};
+use ahash::AHashMap;
 use arc_swap::ArcSwap;
 use auth::{oauth::config::OAuthConfig, roles::RolePermissions, AccessToken};
 use config::{
+    i18n::Localization,
     imap::ImapConfig,
     jmap::settings::JmapConfig,
     network::Network,
     scripts::Scripting,
     smtp::{
+        queue::TokenBucket,
         resolver::{Policy, Tlsa},
         SmtpConfig,
     },
     spamfilter::{IpResolver, SpamFilterConfig},
-    storage::Storage,
+    storage::{LookupRows, Storage},
     telemetry::Metrics,
 };
 
 use imap_proto::protocol::list::Attribute;
 use ipc::{HousekeeperEvent, QueueEvent, ReportingEvent, StateEvent};
-use listener::{asn::AsnGeoLookupData, blocked::Security, tls::AcmeProviders};
+use listener::{
+    asn::AsnGeoLookupData,
+    blocked::{IpList, Security},
+    sessions::ActiveSessions,
+    tls::AcmeProviders,
+};
 
 use mail_auth::{Txt, MX};
-use manager::webadmin::{Resource, WebAdminManager};
+use manager::{
+    migrate::MigrationStatus,
+    restart::RestartStatus,
+    webadmin::{Resource, WebAdminManager},
+};
 use nlp::bayes::{TokenHash, Weights};
 use parking_lot::{Mutex, RwLock};
 use rustls::sign::CertifiedKey;
+use store::roaring::RoaringBitmap;
 use tokio::sync::{mpsc, Notify, Semaphore};
 use tokio_rustls::TlsConnector;
 use utils::{
@@ -53,6 +66,7 @@ pub mod config;
 pub mod core;
 pub mod dns;
 pub mod expr;
+pub mod i18n;
 pub mod ipc;
 pub mod listener;
 pub mod manager;
@@ -91,6 +105,34 @@ pub const KV_LOCK_QUEUE_MESSAGE: u8 = 21;
 pub const KV_LOCK_QUEUE_REPORT: u8 = 22;
 pub const KV_LOCK_EMAIL_TASK: u8 = 23;
 pub const KV_LOCK_HOUSEKEEPER: u8 = 24;
+pub const KV_LIST_MODERATION: u8 = 25;
+pub const KV_LOCK_LIST_MODERATION: u8 = 26;
+pub const KV_DELIVERY_DEDUP: u8 = 27;
+pub const KV_SUPPRESSION: u8 = 28;
+pub const KV_REPORT_INTERVAL: u8 = 29;
+pub const KV_REPUTATION_CONNECTION: u8 = 30;
+pub const KV_WARMUP: u8 = 31;
+pub const KV_AUTORESPONDER_SUPPRESSED: u8 = 32;
+pub const KV_LOCK_MAILBOX_COUNTERS: u8 = 33;
+pub const KV_RATE_LIMIT_UPLOAD: u8 = 34;
+pub const KV_IDEMPOTENCY: u8 = 35;
+pub const KV_LOCK_IDEMPOTENCY: u8 = 36;
+pub const KV_TENANT_OUTBOUND: u8 = 37;
+pub const KV_LOCK_PROVISION: u8 = 38;
+pub const KV_REPUTATION_API: u8 = 39;
+pub const KV_RATE_LIMIT_REPUTATION_API: u8 = 40;
+pub const KV_LOCK_FETCH_ACCOUNT: u8 = 41;
+pub const KV_OUTBOUND_MODERATION: u8 = 42;
+pub const KV_LOCK_OUTBOUND_MODERATION: u8 = 43;
+pub const KV_SMIME_CERTIFICATE: u8 = 44;
+pub const KV_DNS_AUTH_CACHE: u8 = 45;
+pub const KV_STORE_BENCHMARK: u8 = 46;
+pub const KV_LANGUAGE_HINT: u8 = 47;
+pub const KV_KNOWN_SENDER: u8 = 48;
+pub const KV_RATE_LIMIT_NOTIFY: u8 = 49;
+pub const KV_STORAGE_HISTORY: u8 = 50;
+pub const KV_QUEUE_MEMBER: u8 = 51;
+pub const KV_QUEUE_SHAPING: u8 = 52;
 
 #[derive(Clone)]
 pub struct Server {
@@ -109,7 +151,8 @@ pub struct Data {
     pub tls_certificates: ArcSwap<AHashMap<String, Arc<CertifiedKey>>>,
     pub tls_self_signed_cert: Option<Arc<CertifiedKey>>,
 
-    pub blocked_ips: RwLock<AHashSet<IpAddr>>,
+    pub blocked_ips: RwLock<IpList>,
+    pub allowed_ips: RwLock<IpList>,
     pub blocked_ips_version: AtomicU8,
 
     pub asn_geo_data: AsnGeoLookupData,
@@ -124,6 +167,44 @@ pub struct Data {
     pub config_version: AtomicU8,
 
     pub smtp_connectors: TlsConnectors,
+
+    pub spam_tag_hits: Mutex<VecDeque<SpamTagHits>>,
+
+    pub active_sessions: ActiveSessions,
+
+    pub bootstrap_token: RwLock<Option<[u8; 32]>>,
+    pub migration: MigrationStatus,
+    pub restart: RestartStatus,
+    pub queue_admission: QueueAdmissionStatus,
+    pub queue_shaping: Mutex<AHashMap<String, TokenBucket>>,
+    pub sandbox_stats: Mutex<AHashMap<SandboxId, SandboxStats>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SandboxId {
+    Sieve(String),
+    Hook(String),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SandboxStats {
+    pub runs: u64,
+    pub violations: u64,
+    pub consecutive_violations: u32,
+    pub elapsed: Duration,
+    pub disabled_until: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct QueueAdmissionStatus {
+    pub last_check: AtomicU64,
+    pub is_paused: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+pub struct SpamTagHits {
+    pub hour: u64,
+    pub tags: AHashMap<String, u64>,
 }
 
 pub struct Caches {
@@ -134,6 +215,7 @@ pub struct Caches {
     pub account: Cache<AccountId, Arc<Account>>,
     pub mailbox: Cache<MailboxId, Arc<MailboxState>>,
     pub threads: Cache<u32, Arc<Threads>>,
+    pub conversations: Cache<u32, Arc<Conversations>>,
 
     pub bayes: CacheWithTtl<TokenHash, Weights>,
 
@@ -145,6 +227,8 @@ pub struct Caches {
     pub dns_tlsa: CacheWithTtl<String, Arc<Tlsa>>,
     pub dbs_mta_sts: CacheWithTtl<String, Arc<Policy>>,
     pub dns_rbl: CacheWithTtl<String, Option<Arc<IpResolver>>>,
+
+    pub lookup_queries: CacheWithTtl<String, LookupRows>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -160,6 +244,7 @@ pub struct Ipc {
     pub queue_tx: mpsc::Sender<QueueEvent>,
     pub report_tx: mpsc::Sender<ReportingEvent>,
     pub local_delivery_sm: Arc<Semaphore>,
+    pub report_analysis_sm: Arc<Semaphore>,
 }
 
 pub struct TlsConnectors {
@@ -235,6 +320,21 @@ pub struct Threads {
     pub modseq: Option<u64>,
 }
 
+#[derive(Debug, Default, Clone)]
+pub struct Conversations {
+    pub threads: AHashMap<u32, Arc<Conversation>>,
+    pub message_threads: AHashMap<u32, u32>,
+    pub modseq: Option<u64>,
+    pub obj_size: u64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Conversation {
+    pub message_ids: Vec<u32>,
+    pub participants: Vec<String>,
+    pub last_activity: u64,
+}
+
 #[derive(Clone, Default)]
 pub struct Core {
     pub storage: Storage,
@@ -247,6 +347,7 @@ pub struct Core {
     pub spam: SpamFilterConfig,
     pub imap: ImapConfig,
     pub metrics: Metrics,
+    pub i18n: Localization,
 }
 
 impl CacheItemWeight for AccountId {
@@ -267,6 +368,55 @@ impl CacheItemWeight for Threads {
     }
 }
 
+impl Conversations {
+    pub fn threads_of(&self, message_ids: &RoaringBitmap) -> RoaringBitmap {
+        message_ids
+            .iter()
+            .filter_map(|message_id| self.message_threads.get(&message_id).copied())
+            .collect()
+    }
+
+    pub fn filter(
+        &self,
+        thread_ids: RoaringBitmap,
+        f: impl Fn(&Conversation) -> bool,
+    ) -> RoaringBitmap {
+        thread_ids
+            .into_iter()
+            .filter(|thread_id| {
+                self.threads
+                    .get(thread_id)
+                    .is_some_and(|conversation| f(conversation))
+            })
+            .collect()
+    }
+
+    pub fn update_size(mut self) -> Self {
+        self.obj_size = (std::mem::size_of::<Conversations>()
+            + (self.message_threads.len() * 3 * std::mem::size_of::<u32>())
+            + self
+                .threads
+                .values()
+                .map(|conversation| {
+                    std::mem::size_of::<Conversation>()
+                        + std::mem::size_of::<u32>()
+                        + conversation
+                            .participants
+                            .iter()
+                            .map(|participant| participant.len())
+                            .sum::<usize>()
+                })
+                .sum::<usize>()) as u64;
+        self
+    }
+}
+
+impl CacheItemWeight for Conversations {
+    fn weight(&self) -> u64 {
+        self.obj_size
+    }
+}
+
 impl CacheItemWeight for MailboxState {
     fn weight(&self) -> u64 {
         self.obj_size
@@ -400,8 +550,10 @@ impl Default for Caches {
             account: Cache::new(1024, 10 * 1024 * 1024),
             mailbox: Cache::new(1024, 10 * 1024 * 1024),
             threads: Cache::new(1024, 10 * 1024 * 1024),
+            conversations: Cache::new(1024, 10 * 1024 * 1024),
             bayes: CacheWithTtl::new(1024, 10 * 1024 * 1024),
             dns_rbl: CacheWithTtl::new(1024, 10 * 1024 * 1024),
+            lookup_queries: CacheWithTtl::new(1024, 10 * 1024 * 1024),
             dns_txt: CacheWithTtl::new(1024, 10 * 1024 * 1024),
             dns_mx: CacheWithTtl::new(1024, 10 * 1024 * 1024),
             dns_ptr: CacheWithTtl::new(1024, 10 * 1024 * 1024),
@@ -423,6 +575,7 @@ impl Default for Ipc {
             queue_tx: mpsc::channel(IPC_CHANNEL_BUFFER).0,
             report_tx: mpsc::channel(IPC_CHANNEL_BUFFER).0,
             local_delivery_sm: Arc::new(Semaphore::new(10)),
+            report_analysis_sm: Arc::new(Semaphore::new(10)),
         }
     }
 }
@@ -30,6 +30,7 @@ pub struct AcmeProvider {
     renew_before: chrono::Duration,
     account_key: ArcSwap<Vec<u8>>,
     default: bool,
+    mta_sts: bool,
 }
 
 #[derive(Clone)]
@@ -66,6 +67,7 @@ impl AcmeProvider {
         eab: Option<EabSettings>,
         renew_before: Duration,
         default: bool,
+        mta_sts: bool,
     ) -> trc::Result<Self> {
         Ok(AcmeProvider {
             id,
@@ -86,6 +88,7 @@ impl AcmeProvider {
             challenge,
             eab,
             default,
+            mta_sts,
         })
     }
 }
@@ -154,6 +157,7 @@ impl Clone for AcmeProvider {
             account_key: ArcSwap::from_pointee(self.account_key.load().as_ref().clone()),
             eab: self.eab.clone(),
             default: self.default,
+            mta_sts: self.mta_sts,
         }
     }
 }
@@ -11,7 +11,11 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use store::dispatch::lookup::KeyValue;
 use trc::{AcmeEvent, EventType};
-use x509_parser::parse_x509_certificate;
+use x509_parser::{extensions::GeneralName, parse_x509_certificate};
+
+use directory::backend::internal::manage::ManageDirectory;
+use directory::backend::internal::PrincipalField;
+use directory::Type;
 
 use crate::listener::acme::directory::Identifier;
 use crate::listener::acme::ChallengeSettings;
@@ -27,14 +31,26 @@ impl Server {
         pem: Vec<u8>,
         cached: bool,
     ) -> trc::Result<Duration> {
-        let (cert, validity) = parse_cert(&pem)?;
+        let (cert, validity, names) = parse_cert(&pem)?;
 
-        self.set_cert(provider, Arc::new(cert));
+        self.set_cert(provider, Arc::new(cert), &names);
 
-        let renew_at = (validity[1] - provider.renew_before - Utc::now())
-            .max(chrono::Duration::zero())
-            .to_std()
-            .unwrap_or_default();
+        // Renew right away if a local domain is missing its MTA-STS policy host
+        let renew_at = if cached
+            && provider.mta_sts
+            && self
+                .order_domains(provider)
+                .await
+                .iter()
+                .any(|domain| !names.contains(domain))
+        {
+            Duration::ZERO
+        } else {
+            (validity[1] - provider.renew_before - Utc::now())
+                .max(chrono::Duration::zero())
+                .to_std()
+                .unwrap_or_default()
+        };
         let renewal_date = validity[1] - provider.renew_before;
 
         trc::event!(
@@ -86,7 +102,8 @@ impl Server {
         let directory = Directory::discover(&provider.directory_url).await?;
         let account = Account::create_with_keypair(directory, provider).await?;
 
-        let mut params = CertificateParams::new(provider.domains.clone());
+        let domains = self.order_domains(provider).await;
+        let mut params = CertificateParams::new(domains.clone());
         params.distinguished_name = DistinguishedName::new();
         params.alg = &PKCS_ECDSA_P256_SHA256;
         let cert = rcgen::Certificate::from_params(params).map_err(|err| {
@@ -95,7 +112,7 @@ impl Server {
                 .reason(err)
         })?;
 
-        let (order_url, mut order) = account.new_order(provider.domains.clone()).await?;
+        let (order_url, mut order) = account.new_order(domains).await?;
         loop {
             match order.status {
                 OrderStatus::Pending => {
@@ -168,6 +185,54 @@ impl Server {
         }
     }
 
+    /// Domains to include in the certificate order. When MTA-STS is enabled,
+    /// the policy host of each local domain is added as long as it resolves.
+    async fn order_domains(&self, provider: &AcmeProvider) -> Vec<String> {
+        let mut domains = provider.domains.clone();
+        if !provider.mta_sts {
+            return domains;
+        }
+
+        let local_domains = match self
+            .store()
+            .list_principals(None, None, &[Type::Domain], &[PrincipalField::Name], 0, 0)
+            .await
+        {
+            Ok(principals) => principals.items,
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to list local domains.")
+                    .ctx(trc::Key::Id, provider.id.to_string())
+                    .caused_by(trc::location!()));
+                return domains;
+            }
+        };
+
+        for domain in local_domains {
+            let name = format!("mta-sts.{}", domain.name());
+            if domains.contains(&name) {
+                continue;
+            }
+
+            // HTTP and TLS-ALPN challenges fail for hosts that do not resolve
+            if !matches!(provider.challenge, ChallengeSettings::Dns01 { .. })
+                && !self.dns_exists_ip(&name).await.unwrap_or_default()
+            {
+                trc::event!(
+                    Acme(AcmeEvent::DnsRecordLookupFailed),
+                    Id = provider.id.to_string(),
+                    Hostname = name,
+                    Details = "Skipping MTA-STS policy host",
+                );
+                continue;
+            }
+
+            domains.push(name);
+        }
+
+        domains
+    }
+
     async fn authorize(
         &self,
         provider: &AcmeProvider,
@@ -388,7 +453,7 @@ impl Server {
     }
 }
 
-fn parse_cert(pem: &[u8]) -> trc::Result<(CertifiedKey, [DateTime<Utc>; 2])> {
+fn parse_cert(pem: &[u8]) -> trc::Result<(CertifiedKey, [DateTime<Utc>; 2], Vec<String>)> {
     let mut pems = pem::parse_many(pem).map_err(|err| {
         EventType::Acme(AcmeEvent::Error)
             .reason(err)
@@ -414,14 +479,33 @@ fn parse_cert(pem: &[u8]) -> trc::Result<(CertifiedKey, [DateTime<Utc>; 2])> {
         .into_iter()
         .map(|p| CertificateDer::from(p.into_contents()))
         .collect();
-    let validity = match parse_x509_certificate(&cert_chain[0]) {
+    let (validity, names) = match parse_x509_certificate(&cert_chain[0]) {
         Ok((_, cert)) => {
             let validity = cert.validity();
-            [validity.not_before, validity.not_after].map(|t| {
-                Utc.timestamp_opt(t.timestamp(), 0)
-                    .earliest()
-                    .unwrap_or_default()
-            })
+            let names = cert
+                .subject_alternative_name()
+                .ok()
+                .flatten()
+                .map(|san| {
+                    san.value
+                        .general_names
+                        .iter()
+                        .filter_map(|name| match name {
+                            GeneralName::DNSName(name) => Some(name.to_string()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+
+            (
+                [validity.not_before, validity.not_after].map(|t| {
+                    Utc.timestamp_opt(t.timestamp(), 0)
+                        .earliest()
+                        .unwrap_or_default()
+                }),
+                names,
+            )
         }
         Err(err) => {
             return Err(EventType::Acme(AcmeEvent::Error)
@@ -430,5 +514,5 @@ fn parse_cert(pem: &[u8]) -> trc::Result<(CertifiedKey, [DateTime<Utc>; 2])> {
         }
     };
     let cert = CertifiedKey::new(cert_chain, pk);
-    Ok((cert, validity))
+    Ok((cert, validity, names))
 }
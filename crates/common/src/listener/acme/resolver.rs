@@ -21,10 +21,15 @@ use crate::{listener::acme::directory::SerializedCert, Server, KV_ACME};
 use super::{directory::ACME_TLS_ALPN_NAME, AcmeProvider, StaticResolver};
 
 impl Server {
-    pub(crate) fn set_cert(&self, provider: &AcmeProvider, cert: Arc<CertifiedKey>) {
+    pub(crate) fn set_cert(
+        &self,
+        provider: &AcmeProvider,
+        cert: Arc<CertifiedKey>,
+        names: &[String],
+    ) {
         // Add certificates
         let mut certificates = self.inner.data.tls_certificates.load().as_ref().clone();
-        for domain in provider.domains.iter() {
+        for domain in provider.domains.iter().chain(names.iter()) {
             certificates.insert(
                 domain
                     .strip_prefix("*.")
@@ -5,7 +5,7 @@
  */
 
 use std::{
-    net::IpAddr,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::{atomic::AtomicU64, Arc},
     time::{Duration, Instant},
 };
@@ -16,7 +16,13 @@ use mail_auth::common::resolver::ToReverseName;
 use store::write::now;
 use tokio::sync::Semaphore;
 
-use crate::{config::network::AsnGeoLookupConfig, manager::fetch_resource, Server};
+use crate::{
+    config::network::{AsnGeoFormat, AsnGeoLookupConfig},
+    manager::fetch_resource,
+    Server,
+};
+
+use super::mmdb::MmdbReader;
 
 pub struct AsnGeoLookupData {
     pub lock: Semaphore,
@@ -62,8 +68,7 @@ impl Server {
                     self.refresh_asn_geo_tables();
                 }
 
-                result.asn = asn_geo.asn.load().lookup(ip).cloned();
-                result.country = asn_geo.country.load().lookup(ip).cloned();
+                result = asn_geo.lookup_cached(ip);
             }
             AsnGeoLookupConfig::Dns {
                 zone_ipv4,
@@ -152,6 +157,7 @@ impl Server {
                 asn_resources,
                 geo_resources,
                 headers,
+                format,
             } = &server.core.network.asn_geo_lookup
             {
                 let mut asn_data = Data::new();
@@ -163,121 +169,46 @@ impl Server {
                     .chain(geo_resources.iter().map(|url| (false, url)))
                 {
                     let time = Instant::now();
-                    match fetch_resource(url, headers.clone().into(), *timeout, *max_size)
-                        .await
-                        .map(String::from_utf8)
-                    {
-                        Ok(Ok(data)) => {
-                            let mut has_errors = false;
-                            let mut asn_mappings = AHashMap::new();
-                            let mut geo_mappings = AHashMap::new();
-
-                            let mut from_ip = None;
-                            let mut to_ip = None;
-                            let mut asn = None;
-                            let mut details = None;
-
-                            let mut in_quote = false;
-                            let mut col_num = 0;
-                            let mut col_start = 0;
-                            let mut line_start = 0;
-
-                            for (idx, ch) in data.char_indices() {
-                                match ch {
-                                    '"' => in_quote = !in_quote,
-                                    ',' | '\n' if !in_quote => {
-                                        let column =
-                                            data.get(col_start..idx).unwrap_or_default().trim();
-                                        match col_num {
-                                            0 => from_ip = column.parse::<IpAddr>().ok(),
-                                            1 => to_ip = column.parse::<IpAddr>().ok(),
-                                            2 if is_asn => asn = column.parse::<u32>().ok(),
-                                            2 | 3 => {
-                                                let column = column
-                                                    .strip_prefix('"')
-                                                    .and_then(|s| s.strip_suffix('"'))
-                                                    .unwrap_or(column);
-                                                if !column.is_empty() || details.is_none() {
-                                                    details = Some(column);
-                                                }
-                                            }
-                                            _ => break,
-                                        }
-
-                                        if ch == '\n' {
-                                            let is_success = match (from_ip, to_ip, asn, details) {
-                                                (
-                                                    Some(from_ip),
-                                                    Some(to_ip),
-                                                    Some(asn),
-                                                    asn_name,
-                                                ) if is_asn => {
-                                                    let data = asn_mappings
-                                                        .entry(asn)
-                                                        .or_insert_with(|| {
-                                                            Arc::new(AsnData {
-                                                                id: asn,
-                                                                name: asn_name.map(String::from),
-                                                            })
-                                                        })
-                                                        .clone();
-                                                    asn_data.insert(from_ip, to_ip, data)
-                                                }
-                                                (Some(from_ip), Some(to_ip), _, Some(code))
-                                                    if !is_asn && [2, 3].contains(&code.len()) =>
-                                                {
-                                                    let code = code.to_uppercase();
-                                                    let data = geo_mappings
-                                                        .entry(code.clone())
-                                                        .or_insert_with(|| Arc::new(code))
-                                                        .clone();
-                                                    country_data.insert(from_ip, to_ip, data)
-                                                }
-                                                (None, None, _, _) => true, // Ignore empty rows
-                                                _ => false,
-                                            };
-
-                                            if !is_success && !has_errors {
-                                                trc::event!(
-                                                    Resource(trc::ResourceEvent::Error),
-                                                    Details = "Invalid ASN/Geo data",
-                                                    Url = url.clone(),
-                                                    Details = data
-                                                        .get(line_start..idx)
-                                                        .unwrap_or_default()
-                                                        .to_string(),
-                                                );
-                                                has_errors = true;
-                                            }
-
-                                            col_num = 0;
-                                            from_ip = None;
-                                            to_ip = None;
-                                            asn = None;
-                                            details = None;
-                                            line_start = idx + 1;
-                                        } else {
-                                            col_num += 1;
-                                        }
-                                        col_start = idx + 1;
-                                    }
-                                    _ => {}
+                    match fetch_resource(url, headers.clone().into(), *timeout, *max_size).await {
+                        Ok(data) => {
+                            let result = match format {
+                                AsnGeoFormat::MaxMind => {
+                                    parse_mmdb(&data, is_asn, &mut asn_data, &mut country_data)
+                                }
+                                AsnGeoFormat::Csv | AsnGeoFormat::Ip2Location => {
+                                    String::from_utf8(data)
+                                        .map_err(|_| "Invalid UTF-8 data".to_string())
+                                        .map(|data| {
+                                            parse_csv(
+                                                &data,
+                                                url,
+                                                is_asn,
+                                                *format,
+                                                &mut asn_data,
+                                                &mut country_data,
+                                            )
+                                        })
+                                }
+                            };
+
+                            match result {
+                                Ok(_) => {
+                                    trc::event!(
+                                        Resource(trc::ResourceEvent::DownloadExternal),
+                                        Details = "Downloaded ASN/Geo data",
+                                        Url = url.clone(),
+                                        Elapsed = time.elapsed()
+                                    );
+                                }
+                                Err(err) => {
+                                    trc::event!(
+                                        Resource(trc::ResourceEvent::Error),
+                                        Details = "Failed to parse ASN/Geo data",
+                                        Url = url.clone(),
+                                        Reason = err
+                                    );
                                 }
                             }
-
-                            trc::event!(
-                                Resource(trc::ResourceEvent::DownloadExternal),
-                                Details = "Downloaded ASN/Geo data",
-                                Url = url.clone(),
-                                Elapsed = time.elapsed()
-                            );
-                        }
-                        Ok(Err(_)) => {
-                            trc::event!(
-                                Resource(trc::ResourceEvent::Error),
-                                Details = "Failed to UTF-8 decode ASN/Geo data",
-                                Url = url.clone(),
-                            );
                         }
                         Err(err) => {
                             trc::event!(
@@ -312,6 +243,223 @@ impl Server {
     }
 }
 
+impl AsnGeoLookupData {
+    /// Looks up an IP address in the downloaded ASN/Geo tables without
+    /// refreshing them.
+    pub fn lookup_cached(&self, ip: IpAddr) -> AsnGeoLookupResult {
+        AsnGeoLookupResult {
+            asn: self.asn.load().lookup(ip).cloned(),
+            country: self.country.load().lookup(ip).cloned(),
+        }
+    }
+}
+
+fn parse_csv(
+    data: &str,
+    url: &str,
+    is_asn: bool,
+    format: AsnGeoFormat,
+    asn_data: &mut Data<Arc<AsnData>>,
+    country_data: &mut Data<Arc<String>>,
+) {
+    let mut has_errors = false;
+    let mut asn_mappings = AHashMap::new();
+    let mut geo_mappings = AHashMap::new();
+
+    let mut from_ip = None;
+    let mut to_ip = None;
+    let mut asn = None;
+    let mut details = None;
+
+    let mut in_quote = false;
+    let mut col_num = 0;
+    let mut col_start = 0;
+    let mut line_start = 0;
+
+    for (idx, ch) in data.char_indices() {
+        match ch {
+            '"' => in_quote = !in_quote,
+            ',' | '\n' if !in_quote => {
+                let column = data.get(col_start..idx).unwrap_or_default().trim();
+                let column = column
+                    .strip_prefix('"')
+                    .and_then(|s| s.strip_suffix('"'))
+                    .unwrap_or(column);
+                match (format, col_num) {
+                    (_, 0) => from_ip = parse_ip(column, format),
+                    (_, 1) => to_ip = parse_ip(column, format),
+                    (AsnGeoFormat::Ip2Location, 2) if !is_asn => details = Some(column),
+                    (AsnGeoFormat::Ip2Location, 3) if is_asn => asn = column.parse::<u32>().ok(),
+                    (AsnGeoFormat::Ip2Location, 4) if is_asn => details = Some(column),
+                    (AsnGeoFormat::Ip2Location, _) => {}
+                    (_, 2) if is_asn => asn = column.parse::<u32>().ok(),
+                    (_, 2 | 3) => {
+                        if !column.is_empty() || details.is_none() {
+                            details = Some(column);
+                        }
+                    }
+                    _ => break,
+                }
+
+                if ch == '\n' {
+                    let is_success = match (from_ip, to_ip, asn, details) {
+                        (Some(from_ip), Some(to_ip), Some(asn), asn_name) if is_asn => {
+                            let data = asn_mappings
+                                .entry(asn)
+                                .or_insert_with(|| {
+                                    Arc::new(AsnData {
+                                        id: asn,
+                                        name: asn_name.map(String::from),
+                                    })
+                                })
+                                .clone();
+                            asn_data.insert(from_ip, to_ip, data)
+                        }
+                        (Some(from_ip), Some(to_ip), _, Some(code))
+                            if !is_asn && [2, 3].contains(&code.len()) =>
+                        {
+                            let code = code.to_uppercase();
+                            let data = geo_mappings
+                                .entry(code.clone())
+                                .or_insert_with(|| Arc::new(code))
+                                .clone();
+                            country_data.insert(from_ip, to_ip, data)
+                        }
+                        (Some(_), Some(_), _, Some("-")) => true, // Ignore unassigned ranges
+                        (None, None, _, _) => true,               // Ignore empty rows
+                        _ => false,
+                    };
+
+                    if !is_success && !has_errors {
+                        trc::event!(
+                            Resource(trc::ResourceEvent::Error),
+                            Details = "Invalid ASN/Geo data",
+                            Url = url.to_string(),
+                            Details = data.get(line_start..idx).unwrap_or_default().to_string(),
+                        );
+                        has_errors = true;
+                    }
+
+                    col_num = 0;
+                    from_ip = None;
+                    to_ip = None;
+                    asn = None;
+                    details = None;
+                    line_start = idx + 1;
+                } else {
+                    col_num += 1;
+                }
+                col_start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_ip(column: &str, format: AsnGeoFormat) -> Option<IpAddr> {
+    if format == AsnGeoFormat::Ip2Location {
+        // IP2Location stores addresses as integers, with IPv4 addresses
+        // mapped to IPv6 in the IPv6 databases
+        let ip = column.parse::<u128>().ok()?;
+        Some(match u32::try_from(ip) {
+            Ok(ip) => IpAddr::V4(Ipv4Addr::from(ip)),
+            Err(_) => {
+                let ip = Ipv6Addr::from(ip);
+                ip.to_ipv4_mapped()
+                    .map(IpAddr::V4)
+                    .unwrap_or(IpAddr::V6(ip))
+            }
+        })
+    } else {
+        column.parse::<IpAddr>().ok()
+    }
+}
+
+fn parse_mmdb(
+    data: &[u8],
+    is_asn: bool,
+    asn_data: &mut Data<Arc<AsnData>>,
+    country_data: &mut Data<Arc<String>>,
+) -> Result<(), String> {
+    let reader = MmdbReader::new(data)?;
+    let mut asn_records: AHashMap<usize, Option<Arc<AsnData>>> = AHashMap::new();
+    let mut geo_records: AHashMap<usize, Option<Arc<String>>> = AHashMap::new();
+    let mut geo_mappings: AHashMap<String, Arc<String>> = AHashMap::new();
+    let mut result = Ok(());
+
+    reader.networks(|from_ip, to_ip, offset| {
+        if result.is_err() {
+            return;
+        }
+
+        // Records are shared by many networks, decode each one only once
+        if is_asn {
+            let asn = match asn_records.get(&offset) {
+                Some(asn) => asn.clone(),
+                None => match reader.record(offset) {
+                    Ok(record) => {
+                        let asn = record
+                            .get("autonomous_system_number")
+                            .and_then(|v| v.as_uint())
+                            .and_then(|id| u32::try_from(id).ok())
+                            .map(|id| {
+                                Arc::new(AsnData {
+                                    id,
+                                    name: record
+                                        .get("autonomous_system_organization")
+                                        .and_then(|v| v.as_str())
+                                        .map(String::from),
+                                })
+                            });
+                        asn_records.insert(offset, asn.clone());
+                        asn
+                    }
+                    Err(err) => {
+                        result = Err(err);
+                        return;
+                    }
+                },
+            };
+
+            if let Some(asn) = asn {
+                asn_data.insert(from_ip, to_ip, asn);
+            }
+        } else {
+            let country = match geo_records.get(&offset) {
+                Some(country) => country.clone(),
+                None => match reader.record(offset) {
+                    Ok(record) => {
+                        let country = record
+                            .get("country")
+                            .or_else(|| record.get("registered_country"))
+                            .and_then(|v| v.get("iso_code"))
+                            .and_then(|v| v.as_str())
+                            .map(|code| {
+                                let code = code.to_uppercase();
+                                geo_mappings
+                                    .entry(code.clone())
+                                    .or_insert_with(|| Arc::new(code))
+                                    .clone()
+                            });
+                        geo_records.insert(offset, country.clone());
+                        country
+                    }
+                    Err(err) => {
+                        result = Err(err);
+                        return;
+                    }
+                },
+            };
+
+            if let Some(country) = country {
+                country_data.insert(from_ip, to_ip, country);
+            }
+        }
+    })?;
+
+    result
+}
+
 impl<T> Data<T> {
     fn new() -> Self {
         Self {
@@ -6,7 +6,8 @@
 
 use std::{fmt::Debug, net::IpAddr};
 
-use ahash::AHashSet;
+use ahash::AHashMap;
+use store::write::now;
 use utils::{
     config::{
         ipmask::{IpAddrMask, IpAddrOrMask},
@@ -21,15 +22,8 @@ use crate::{
     KV_RATE_LIMIT_RCPT, KV_RATE_LIMIT_SCAN,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Security {
-    blocked_ip_networks: Vec<IpAddrMask>,
-    has_blocked_networks: bool,
-
-    allowed_ip_addresses: AHashSet<IpAddr>,
-    allowed_ip_networks: Vec<IpAddrMask>,
-    has_allowed_networks: bool,
-
     http_banned_paths: Vec<MatchType>,
     scanner_fail_rate: Option<Rate>,
 
@@ -43,43 +37,30 @@ pub const BLOCKED_IP_PREFIX: &str = "server.blocked-ip.";
 pub const ALLOWED_IP_KEY: &str = "server.allowed-ip";
 pub const ALLOWED_IP_PREFIX: &str = "server.allowed-ip.";
 
-pub struct BlockedIps {
-    pub blocked_ip_addresses: AHashSet<IpAddr>,
-    pub blocked_ip_networks: Vec<IpAddrMask>,
+/// IP addresses and networks of an allow or block list, along with
+/// the time at which each entry expires.
+#[derive(Debug, Default)]
+pub struct IpList {
+    addresses: AHashMap<IpAddr, u64>,
+    networks: Vec<(IpAddrMask, u64)>,
+}
+
+/// Metadata stored as the value of an allow or block list entry.
+/// Entries with an empty value never expire.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IpListEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
 }
 
 impl Security {
     pub fn parse(config: &mut Config) -> Self {
-        let mut allowed_ip_addresses = AHashSet::new();
-        let mut allowed_ip_networks = Vec::new();
-
-        for ip in config
-            .set_values(ALLOWED_IP_KEY)
-            .map(IpAddrOrMask::parse_value)
-            .collect::<Vec<_>>()
-        {
-            match ip {
-                Ok(IpAddrOrMask::Ip(ip)) => {
-                    allowed_ip_addresses.insert(ip);
-                }
-                Ok(IpAddrOrMask::Mask(ip)) => {
-                    allowed_ip_networks.push(ip);
-                }
-                Err(err) => {
-                    config.new_parse_error(ALLOWED_IP_KEY, err);
-                }
-            }
-        }
-
-        #[cfg(not(feature = "test_mode"))]
-        {
-            // Add loopback addresses
-            allowed_ip_addresses.insert(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
-            allowed_ip_addresses.insert(IpAddr::V6(std::net::Ipv6Addr::LOCALHOST));
-        }
-
-        let blocked = BlockedIps::parse(config);
-
         // Parse blocked HTTP paths
         let mut http_banned_paths = config
             .values("server.auto-ban.scan.paths")
@@ -114,11 +95,6 @@ impl Security {
         }
 
         Security {
-            has_blocked_networks: !blocked.blocked_ip_networks.is_empty(),
-            blocked_ip_networks: blocked.blocked_ip_networks,
-            has_allowed_networks: !allowed_ip_networks.is_empty(),
-            allowed_ip_addresses,
-            allowed_ip_networks,
             auth_fail_rate: config
                 .property_or_default::<Option<Rate>>("server.auto-ban.auth.rate", "100/1d")
                 .unwrap_or_default(),
@@ -152,13 +128,21 @@ impl Server {
                         .is_none());
 
             if !is_allowed {
-                return self.block_ip(ip).await.map(|_| true);
+                return self.block_ip(ip, "auto-ban.abuse").await.map(|_| true);
             }
         }
 
         Ok(false)
     }
 
+    pub async fn is_harvest_fail2banned(&self, ip: IpAddr) -> trc::Result<bool> {
+        if !self.is_ip_allowed(&ip) {
+            self.block_ip(ip, "auto-ban.abuse").await.map(|_| true)
+        } else {
+            Ok(false)
+        }
+    }
+
     pub async fn is_scanner_fail2banned(&self, ip: IpAddr) -> trc::Result<bool> {
         if let Some(rate) = &self.core.network.security.scanner_fail_rate {
             let is_allowed = self.is_ip_allowed(&ip)
@@ -169,7 +153,7 @@ impl Server {
                     .is_none();
 
             if !is_allowed {
-                return self.block_ip(ip).await.map(|_| true);
+                return self.block_ip(ip, "auto-ban.scan").await.map(|_| true);
             }
         }
 
@@ -180,7 +164,7 @@ impl Server {
         let paths = &self.core.network.security.http_banned_paths;
 
         if !paths.is_empty() && paths.iter().any(|p| p.matches(path)) && !self.is_ip_allowed(&ip) {
-            self.block_ip(ip).await.map(|_| true)
+            self.block_ip(ip, "auto-ban.scan").await.map(|_| true)
         } else {
             Ok(false)
         }
@@ -196,7 +180,7 @@ impl Server {
                     .is_none();
 
             if !is_allowed {
-                return self.block_ip(ip).await.map(|_| true);
+                return self.block_ip(ip, "auto-ban.loiter").await.map(|_| true);
             }
         }
 
@@ -219,16 +203,20 @@ impl Server {
                             .await?
                             .is_none()));
             if !is_allowed {
-                return self.block_ip(ip).await.map(|_| true);
+                return self.block_ip(ip, "auto-ban.auth").await.map(|_| true);
             }
         }
 
         Ok(false)
     }
 
-    async fn block_ip(&self, ip: IpAddr) -> trc::Result<()> {
+    async fn block_ip(&self, ip: IpAddr, source: &str) -> trc::Result<()> {
         // Add IP to blocked list
-        self.inner.data.blocked_ips.write().insert(ip);
+        self.inner
+            .data
+            .blocked_ips
+            .write()
+            .insert(IpAddrOrMask::Ip(ip), None);
 
         // Write blocked IP to config
         self.core
@@ -237,7 +225,12 @@ impl Server {
             .set(
                 [ConfigKey {
                     key: format!("{}.{}", BLOCKED_IP_KEY, ip),
-                    value: String::new(),
+                    value: IpListEntry {
+                        created: now().into(),
+                        source: source.to_string().into(),
+                        ..Default::default()
+                    }
+                    .to_value(),
                 }],
                 true,
             )
@@ -255,26 +248,18 @@ impl Server {
 
     pub fn is_ip_blocked(&self, ip: &IpAddr) -> bool {
         self.inner.data.blocked_ips.read().contains(ip)
-            || (self.core.network.security.has_blocked_networks
-                && self
-                    .core
-                    .network
-                    .security
-                    .blocked_ip_networks
-                    .iter()
-                    .any(|network| network.matches(ip)))
     }
 
     pub fn is_ip_allowed(&self, ip: &IpAddr) -> bool {
-        self.core.network.security.allowed_ip_addresses.contains(ip)
-            || (self.core.network.security.has_allowed_networks
-                && self
-                    .core
-                    .network
-                    .security
-                    .allowed_ip_networks
-                    .iter()
-                    .any(|network| network.matches(ip)))
+        // Loopback addresses are always allowed
+        #[cfg(not(feature = "test_mode"))]
+        if matches!(ip, IpAddr::V4(ip) if *ip == std::net::Ipv4Addr::LOCALHOST)
+            || matches!(ip, IpAddr::V6(ip) if *ip == std::net::Ipv6Addr::LOCALHOST)
+        {
+            return true;
+        }
+
+        self.inner.data.allowed_ips.read().contains(ip)
     }
 
     pub fn increment_blocked_version(&self) {
@@ -285,57 +270,103 @@ impl Server {
     }
 }
 
-impl BlockedIps {
-    pub fn parse(config: &mut Config) -> Self {
-        let mut blocked_ip_addresses = AHashSet::new();
-        let mut blocked_ip_networks = Vec::new();
+impl IpList {
+    pub fn parse_blocked(config: &mut Config) -> Self {
+        Self::parse(config, BLOCKED_IP_KEY)
+    }
+
+    pub fn parse_allowed(config: &mut Config) -> Self {
+        Self::parse(config, ALLOWED_IP_KEY)
+    }
+
+    fn parse(config: &mut Config, key: &str) -> Self {
+        let mut list = IpList::default();
+        let now = now();
 
-        for ip in config
-            .set_values(BLOCKED_IP_KEY)
-            .map(IpAddrOrMask::parse_value)
+        for (ip, entry) in config
+            .iterate_prefix(key)
+            .map(|(ip, value)| (IpAddrOrMask::parse_value(ip), IpListEntry::parse(value)))
             .collect::<Vec<_>>()
         {
             match ip {
-                Ok(IpAddrOrMask::Ip(ip)) => {
-                    blocked_ip_addresses.insert(ip);
-                }
-                Ok(IpAddrOrMask::Mask(ip)) => {
-                    blocked_ip_networks.push(ip);
+                Ok(ip) => {
+                    if !entry.is_expired(now) {
+                        list.insert(ip, entry.expires);
+                    }
                 }
                 Err(err) => {
-                    config.new_parse_error(BLOCKED_IP_KEY, err);
+                    config.new_parse_error(key, err);
+                }
+            }
+        }
+
+        list
+    }
+
+    pub fn insert(&mut self, ip: IpAddrOrMask, expires: Option<u64>) {
+        let expires = expires.unwrap_or(u64::MAX);
+        match ip {
+            IpAddrOrMask::Ip(ip) => {
+                self.addresses.insert(ip, expires);
+            }
+            IpAddrOrMask::Mask(network) => {
+                if let Some(entry) = self.networks.iter_mut().find(|(n, _)| n == &network) {
+                    entry.1 = expires;
+                } else {
+                    self.networks.push((network, expires));
                 }
             }
         }
+    }
+
+    pub fn remove(&mut self, ip: &IpAddrOrMask) {
+        match ip {
+            IpAddrOrMask::Ip(ip) => {
+                self.addresses.remove(ip);
+            }
+            IpAddrOrMask::Mask(network) => {
+                self.networks.retain(|(n, _)| n != network);
+            }
+        }
+    }
 
-        Self {
-            blocked_ip_addresses,
-            blocked_ip_networks,
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        if self.addresses.is_empty() && self.networks.is_empty() {
+            return false;
         }
+
+        let now = now();
+        self.addresses.get(ip).is_some_and(|expires| *expires > now)
+            || self
+                .networks
+                .iter()
+                .any(|(network, expires)| *expires > now && network.matches(ip))
     }
 }
 
-#[allow(clippy::derivable_impls)]
-impl Default for Security {
-    fn default() -> Self {
-        // Add IPv4 and IPv6 loopback addresses
-        Self {
-            #[cfg(not(feature = "test_mode"))]
-            allowed_ip_addresses: AHashSet::from_iter([
-                IpAddr::V4(std::net::Ipv4Addr::LOCALHOST),
-                IpAddr::V6(std::net::Ipv6Addr::LOCALHOST),
-            ]),
-            #[cfg(feature = "test_mode")]
-            allowed_ip_addresses: Default::default(),
-            allowed_ip_networks: Default::default(),
-            has_allowed_networks: Default::default(),
-            blocked_ip_networks: Default::default(),
-            has_blocked_networks: Default::default(),
-            auth_fail_rate: Default::default(),
-            rcpt_fail_rate: Default::default(),
-            loiter_fail_rate: Default::default(),
-            scanner_fail_rate: Default::default(),
-            http_banned_paths: Default::default(),
+impl IpListEntry {
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            IpListEntry::default()
+        } else {
+            // Values that were entered manually are kept as comments
+            serde_json::from_str(value).unwrap_or_else(|_| IpListEntry {
+                comment: value.to_string().into(),
+                ..Default::default()
+            })
         }
     }
+
+    pub fn to_value(&self) -> String {
+        if self != &IpListEntry::default() {
+            serde_json::to_string(self).unwrap_or_default()
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
 }
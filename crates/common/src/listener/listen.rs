@@ -141,7 +141,7 @@ impl Listener {
                                                                             .unwrap_or(remote_addr);
                                                     if let Some(session) = instance.build_session(stream, local_addr, remote_addr, &server) {
                                                         // Spawn session
-                                                        manager.spawn(session, is_tls, enable_acme, span_start, span_end);
+                                                        manager.spawn(session, server.inner.clone(), is_tls, enable_acme, span_start, span_end);
                                                     }
                                                 }
                                                 Err(err) => {
@@ -161,7 +161,7 @@ impl Listener {
                                         opts.apply(&session.stream);
 
                                         // Spawn session
-                                        manager.spawn(session, is_tls, enable_acme, span_start, span_end);
+                                        manager.spawn(session, server.inner.clone(), is_tls, enable_acme, span_start, span_end);
                                     }
                                 }
                                 Err(err) => {
@@ -0,0 +1,311 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+// Minimal reader for MaxMind DB files (https://maxmind.github.io/MaxMind-DB/)
+
+const METADATA_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+const DATA_SEPARATOR_SIZE: usize = 16;
+const MAX_NESTING: usize = 32;
+
+const TYPE_POINTER: u8 = 1;
+const TYPE_STRING: u8 = 2;
+const TYPE_DOUBLE: u8 = 3;
+const TYPE_BYTES: u8 = 4;
+const TYPE_UINT16: u8 = 5;
+const TYPE_UINT32: u8 = 6;
+const TYPE_MAP: u8 = 7;
+const TYPE_INT32: u8 = 8;
+const TYPE_UINT64: u8 = 9;
+const TYPE_UINT128: u8 = 10;
+const TYPE_ARRAY: u8 = 11;
+const TYPE_BOOLEAN: u8 = 14;
+const TYPE_FLOAT: u8 = 15;
+
+pub struct MmdbReader<'x> {
+    tree: &'x [u8],
+    data: &'x [u8],
+    node_count: usize,
+    record_size: usize,
+    ip_version: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MmdbValue<'x> {
+    String(&'x str),
+    Uint(u128),
+    Int(i32),
+    Float(f64),
+    Bool(bool),
+    Bytes(&'x [u8]),
+    Map(Vec<(&'x str, MmdbValue<'x>)>),
+    Array(Vec<MmdbValue<'x>>),
+}
+
+impl<'x> MmdbReader<'x> {
+    pub fn new(bytes: &'x [u8]) -> Result<Self, String> {
+        let metadata_start = bytes
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .ok_or("MaxMind DB metadata not found")?
+            + METADATA_MARKER.len();
+        let metadata = decode(&bytes[metadata_start..], 0, 0)?.0;
+
+        let node_count = metadata
+            .get("node_count")
+            .and_then(|v| v.as_uint())
+            .ok_or("Missing node_count in MaxMind DB metadata")? as usize;
+        let record_size = metadata
+            .get("record_size")
+            .and_then(|v| v.as_uint())
+            .filter(|size| [24, 28, 32].contains(size))
+            .ok_or("Invalid record_size in MaxMind DB metadata")?
+            as usize;
+        let ip_version = metadata
+            .get("ip_version")
+            .and_then(|v| v.as_uint())
+            .filter(|version| [4, 6].contains(version))
+            .ok_or("Invalid ip_version in MaxMind DB metadata")? as u8;
+
+        let tree_size = node_count * record_size / 4;
+        let data_start = tree_size + DATA_SEPARATOR_SIZE;
+        let data_end = metadata_start - METADATA_MARKER.len();
+        if data_start > data_end {
+            return Err("Truncated MaxMind DB search tree".to_string());
+        }
+
+        Ok(MmdbReader {
+            tree: &bytes[..tree_size],
+            data: &bytes[data_start..data_end],
+            node_count,
+            record_size,
+            ip_version,
+        })
+    }
+
+    /// Walks the search tree invoking `f` with the first and last address of
+    /// each network and the offset of its record in the data section.
+    pub fn networks(&self, mut f: impl FnMut(IpAddr, IpAddr, usize)) -> Result<(), String> {
+        let bits: u32 = if self.ip_version == 4 { 32 } else { 128 };
+        let mut stack = vec![(0usize, 0u128, 0u32)];
+
+        while let Some((node, prefix, depth)) = stack.pop() {
+            if depth >= bits {
+                return Err("Invalid MaxMind DB search tree depth".to_string());
+            }
+
+            let (left, right) = self.read_node(node)?;
+            for (bit, record) in [(0u128, left), (1u128, right)] {
+                let depth = depth + 1;
+                let prefix = prefix | (bit << (bits - depth));
+
+                if record < self.node_count {
+                    // Skip IPv4 aliases in IPv6 trees, they point to the IPv4 subtree
+                    if bits == 128
+                        && ((depth == 96 && prefix == 0xffff_u128 << 32)
+                            || (depth == 16 && prefix == 0x2002_u128 << 112))
+                    {
+                        continue;
+                    }
+                    stack.push((record, prefix, depth));
+                } else if record > self.node_count {
+                    let offset = (record - self.node_count)
+                        .checked_sub(DATA_SEPARATOR_SIZE)
+                        .ok_or("Invalid MaxMind DB record pointer")?;
+                    let host_bits = bits - depth;
+                    let last = if host_bits > 0 {
+                        prefix | (u128::MAX >> (128 - host_bits))
+                    } else {
+                        prefix
+                    };
+
+                    if bits == 32 || (depth >= 96 && prefix >> 32 == 0) {
+                        f(
+                            IpAddr::V4(Ipv4Addr::from(prefix as u32)),
+                            IpAddr::V4(Ipv4Addr::from(last as u32)),
+                            offset,
+                        );
+                    } else {
+                        f(
+                            IpAddr::V6(Ipv6Addr::from(prefix)),
+                            IpAddr::V6(Ipv6Addr::from(last)),
+                            offset,
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn record(&self, offset: usize) -> Result<MmdbValue<'x>, String> {
+        decode(self.data, offset, 0).map(|(value, _)| value)
+    }
+
+    fn read_node(&self, node: usize) -> Result<(usize, usize), String> {
+        let size = self.record_size / 4;
+        let bytes = self
+            .tree
+            .get(node * size..(node + 1) * size)
+            .ok_or("Invalid MaxMind DB node")?;
+
+        Ok(match self.record_size {
+            24 => (be_uint(&bytes[0..3]), be_uint(&bytes[3..6])),
+            28 => (
+                ((bytes[3] as usize & 0xf0) << 20) | be_uint(&bytes[0..3]),
+                ((bytes[3] as usize & 0x0f) << 24) | be_uint(&bytes[4..7]),
+            ),
+            _ => (be_uint(&bytes[0..4]), be_uint(&bytes[4..8])),
+        })
+    }
+}
+
+impl<'x> MmdbValue<'x> {
+    pub fn get(&self, key: &str) -> Option<&MmdbValue<'x>> {
+        match self {
+            MmdbValue::Map(entries) => entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'x str> {
+        match self {
+            MmdbValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_uint(&self) -> Option<u128> {
+        match self {
+            MmdbValue::Uint(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+fn decode(data: &[u8], offset: usize, depth: usize) -> Result<(MmdbValue<'_>, usize), String> {
+    if depth > MAX_NESTING {
+        return Err("MaxMind DB data nested too deeply".to_string());
+    }
+    let (data_type, size, offset) = decode_control(data, offset)?;
+
+    match data_type {
+        TYPE_POINTER => {
+            // Pointers are resolved without advancing past the referenced value
+            let (value, _) = decode(data, size, depth + 1)?;
+            Ok((value, offset))
+        }
+        TYPE_STRING => {
+            let bytes = read(data, offset, size)?;
+            std::str::from_utf8(bytes)
+                .map(|value| (MmdbValue::String(value), offset + size))
+                .map_err(|_| "Invalid UTF-8 string in MaxMind DB".to_string())
+        }
+        TYPE_DOUBLE => {
+            let bytes = read(data, offset, 8)?;
+            Ok((
+                MmdbValue::Float(f64::from_be_bytes(bytes.try_into().unwrap_or_default())),
+                offset + 8,
+            ))
+        }
+        TYPE_FLOAT => {
+            let bytes = read(data, offset, 4)?;
+            Ok((
+                MmdbValue::Float(f32::from_be_bytes(bytes.try_into().unwrap_or_default()) as f64),
+                offset + 4,
+            ))
+        }
+        TYPE_BYTES => Ok((MmdbValue::Bytes(read(data, offset, size)?), offset + size)),
+        TYPE_UINT16 | TYPE_UINT32 | TYPE_UINT64 | TYPE_UINT128 => {
+            let bytes = read(data, offset, size)?;
+            if size > 16 {
+                return Err("Invalid unsigned integer size in MaxMind DB".to_string());
+            }
+            Ok((
+                MmdbValue::Uint(bytes.iter().fold(0u128, |acc, b| (acc << 8) | *b as u128)),
+                offset + size,
+            ))
+        }
+        TYPE_INT32 => {
+            let bytes = read(data, offset, size)?;
+            if size > 4 {
+                return Err("Invalid signed integer size in MaxMind DB".to_string());
+            }
+            Ok((
+                MmdbValue::Int(bytes.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32) as i32),
+                offset + size,
+            ))
+        }
+        TYPE_MAP => {
+            let mut entries = Vec::with_capacity(size.min(64));
+            let mut offset = offset;
+            for _ in 0..size {
+                let (key, next) = decode(data, offset, depth + 1)?;
+                let key = key.as_str().ok_or("Invalid map key in MaxMind DB")?;
+                let (value, next) = decode(data, next, depth + 1)?;
+                entries.push((key, value));
+                offset = next;
+            }
+            Ok((MmdbValue::Map(entries), offset))
+        }
+        TYPE_ARRAY => {
+            let mut items = Vec::with_capacity(size.min(64));
+            let mut offset = offset;
+            for _ in 0..size {
+                let (value, next) = decode(data, offset, depth + 1)?;
+                items.push(value);
+                offset = next;
+            }
+            Ok((MmdbValue::Array(items), offset))
+        }
+        TYPE_BOOLEAN => Ok((MmdbValue::Bool(size != 0), offset)),
+        _ => Err(format!("Unsupported MaxMind DB data type {data_type}")),
+    }
+}
+
+fn decode_control(data: &[u8], offset: usize) -> Result<(u8, usize, usize), String> {
+    let control = *data.get(offset).ok_or("Truncated MaxMind DB data")?;
+    let mut offset = offset + 1;
+    let mut data_type = control >> 5;
+
+    if data_type == TYPE_POINTER {
+        let size = ((control >> 3) & 0x03) as usize;
+        let value = (control & 0x07) as usize;
+        let bytes = read(data, offset, size + 1)?;
+        let pointer = match size {
+            0 => (value << 8) | be_uint(bytes),
+            1 => ((value << 16) | be_uint(bytes)) + 2048,
+            2 => ((value << 24) | be_uint(bytes)) + 526336,
+            _ => be_uint(bytes),
+        };
+        return Ok((TYPE_POINTER, pointer, offset + size + 1));
+    } else if data_type == 0 {
+        // Extended type
+        data_type = 7 + *data.get(offset).ok_or("Truncated MaxMind DB data")?;
+        offset += 1;
+    }
+
+    let size = (control & 0x1f) as usize;
+    let (size, offset) = match size {
+        0..=28 => (size, offset),
+        29 => (29 + be_uint(read(data, offset, 1)?), offset + 1),
+        30 => (285 + be_uint(read(data, offset, 2)?), offset + 2),
+        _ => (65821 + be_uint(read(data, offset, 3)?), offset + 3),
+    };
+
+    Ok((data_type, size, offset))
+}
+
+fn read(data: &[u8], offset: usize, len: usize) -> Result<&[u8], String> {
+    data.get(offset..offset + len)
+        .ok_or_else(|| "Truncated MaxMind DB data".to_string())
+}
+
+fn be_uint(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize)
+}
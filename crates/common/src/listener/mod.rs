@@ -13,22 +13,27 @@ use tokio::{
     sync::watch,
 };
 use tokio_rustls::{Accept, TlsAcceptor};
-use trc::{Event, EventType, Key};
+use trc::{Event, EventType, Key, Value};
 use utils::{config::ipmask::IpAddrMask, snowflake::SnowflakeIdGenerator};
 
 use crate::{
     config::server::ServerProtocol,
-    expr::{functions::ResolveVariable, *},
-    Server,
+    expr::{functions::ResolveVariable, if_block::IfBlock, *},
+    Inner, Server,
 };
 
-use self::limiter::{ConcurrencyLimiter, InFlight};
+use self::{
+    limiter::{ConcurrencyLimiter, InFlight},
+    sessions::TrackedStream,
+};
 
 pub mod acme;
 pub mod asn;
 pub mod blocked;
 pub mod limiter;
 pub mod listen;
+pub mod mmdb;
+pub mod sessions;
 pub mod stream;
 pub mod tls;
 
@@ -75,6 +80,22 @@ pub struct SessionData<T: SessionStream> {
     pub instance: Arc<ServerInstance>,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionInfo {
+    pub local_ip: IpAddr,
+    pub local_port: u16,
+    pub remote_ip: IpAddr,
+    pub remote_port: u16,
+}
+
+// Variables available when evaluating protocol extensions outside of SMTP
+pub struct SessionVariables<'x> {
+    pub instance: &'x ServerInstance,
+    pub connection: &'x ConnectionInfo,
+    pub is_tls: bool,
+    pub authenticated_as: &'x str,
+}
+
 pub trait SessionStream: AsyncRead + AsyncWrite + Unpin + 'static + Sync + Send {
     fn is_tls(&self) -> bool;
     fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>);
@@ -91,6 +112,7 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
     fn spawn<T: SessionStream>(
         &self,
         mut session: SessionData<T>,
+        inner: Arc<Inner>,
         is_tls: bool,
         acme_core: Option<Server>,
         span_start: EventType,
@@ -120,28 +142,32 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
                             // Send span
                             Event::with_keys(
                                 span_start,
-                                vec![
-                                    (Key::ListenerId, session.instance.id.clone().into()),
-                                    (Key::LocalPort, session.local_port.into()),
-                                    (Key::RemoteIp, session.remote_ip.into()),
-                                    (Key::RemotePort, session.remote_port.into()),
-                                    (Key::SpanId, session.session_id.into()),
-                                ],
+                                span_keys(
+                                    &inner,
+                                    &session.instance,
+                                    session.local_port,
+                                    session.remote_ip,
+                                    session.remote_port,
+                                    session.session_id,
+                                ),
                             )
                             .send_with_metrics();
 
                             manager
-                                .handle(SessionData {
-                                    stream,
-                                    local_ip: session.local_ip,
-                                    local_port: session.local_port,
-                                    remote_ip: session.remote_ip,
-                                    remote_port: session.remote_port,
-                                    protocol: session.protocol,
-                                    session_id: session.session_id,
-                                    in_flight: session.in_flight,
-                                    instance: session.instance,
-                                })
+                                .handle_tracked(
+                                    inner,
+                                    SessionData {
+                                        stream,
+                                        local_ip: session.local_ip,
+                                        local_port: session.local_port,
+                                        remote_ip: session.remote_ip,
+                                        remote_port: session.remote_port,
+                                        protocol: session.protocol,
+                                        session_id: session.session_id,
+                                        in_flight: session.in_flight,
+                                        instance: session.instance,
+                                    },
+                                )
                                 .await;
                         }
                         Err(err) => {
@@ -166,18 +192,19 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
                         // Send span
                         Event::with_keys(
                             span_start,
-                            vec![
-                                (Key::ListenerId, session.instance.id.clone().into()),
-                                (Key::LocalPort, session.local_port.into()),
-                                (Key::RemoteIp, session.remote_ip.into()),
-                                (Key::RemotePort, session.remote_port.into()),
-                                (Key::SpanId, session.session_id.into()),
-                            ],
+                            span_keys(
+                                &inner,
+                                &session.instance,
+                                session.local_port,
+                                session.remote_ip,
+                                session.remote_port,
+                                session.session_id,
+                            ),
                         )
                         .send_with_metrics();
 
                         session.stream = stream;
-                        manager.handle(session).await;
+                        manager.handle_tracked(inner, session).await;
                     }
                     TcpAcceptorResult::Close => return,
                 }
@@ -189,17 +216,18 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
                 // Send span
                 Event::with_keys(
                     span_start,
-                    vec![
-                        (Key::ListenerId, session.instance.id.clone().into()),
-                        (Key::LocalPort, session.local_port.into()),
-                        (Key::RemoteIp, session.remote_ip.into()),
-                        (Key::RemotePort, session.remote_port.into()),
-                        (Key::SpanId, session.session_id.into()),
-                    ],
+                    span_keys(
+                        &inner,
+                        &session.instance,
+                        session.local_port,
+                        session.remote_ip,
+                        session.remote_port,
+                        session.session_id,
+                    ),
                 )
                 .send_with_metrics();
 
-                manager.handle(session).await;
+                manager.handle_tracked(inner, session).await;
             }
 
             // End span
@@ -214,6 +242,39 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
         });
     }
 
+    fn handle_tracked<T: SessionStream>(
+        self,
+        inner: Arc<Inner>,
+        session: SessionData<T>,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        async move {
+            // Register the session so it can be listed and terminated by administrators
+            let sessions = &inner.data.active_sessions;
+            let active = sessions.register(
+                &session,
+                inner.data.asn_geo_data.lookup_cached(session.remote_ip),
+            );
+            let session = SessionData {
+                stream: TrackedStream::new(session.stream, active.clone()),
+                local_ip: session.local_ip,
+                local_port: session.local_port,
+                remote_ip: session.remote_ip,
+                remote_port: session.remote_port,
+                protocol: session.protocol,
+                session_id: session.session_id,
+                in_flight: session.in_flight,
+                instance: session.instance,
+            };
+
+            tokio::select! {
+                _ = self.handle(session) => {}
+                _ = active.terminated() => {}
+            }
+
+            sessions.unregister(active.session_id);
+        }
+    }
+
     fn handle<T: SessionStream>(
         self,
         session: SessionData<T>,
@@ -222,6 +283,34 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
     fn shutdown(&self) -> impl std::future::Future<Output = ()> + Send;
 }
 
+fn span_keys(
+    inner: &Inner,
+    instance: &ServerInstance,
+    local_port: u16,
+    remote_ip: IpAddr,
+    remote_port: u16,
+    session_id: u64,
+) -> Vec<(Key, Value)> {
+    let mut keys = vec![
+        (Key::ListenerId, instance.id.clone().into()),
+        (Key::LocalPort, local_port.into()),
+        (Key::RemoteIp, remote_ip.into()),
+        (Key::RemotePort, remote_port.into()),
+        (Key::SpanId, session_id.into()),
+    ];
+
+    // Add the ASN and country if the lookup tables have been downloaded
+    let asn_geo = inner.data.asn_geo_data.lookup_cached(remote_ip);
+    if let Some(asn) = asn_geo.asn {
+        keys.push((Key::Asn, asn.id.into()));
+    }
+    if let Some(country) = asn_geo.country {
+        keys.push((Key::Country, country.as_str().to_string().into()));
+    }
+
+    keys
+}
+
 impl<T: SessionStream> ResolveVariable for SessionData<T> {
     fn resolve_variable(&self, variable: u32) -> crate::expr::Variable<'_> {
         match variable {
@@ -241,6 +330,54 @@ impl<T: SessionStream> ResolveVariable for SessionData<T> {
     }
 }
 
+impl<T: SessionStream> SessionData<T> {
+    pub fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            local_ip: self.local_ip,
+            local_port: self.local_port,
+            remote_ip: self.remote_ip,
+            remote_port: self.remote_port,
+        }
+    }
+}
+
+impl ResolveVariable for SessionVariables<'_> {
+    fn resolve_variable(&self, variable: u32) -> crate::expr::Variable<'_> {
+        match variable {
+            V_REMOTE_IP => self.connection.remote_ip.to_string().into(),
+            V_REMOTE_PORT => self.connection.remote_port.into(),
+            V_LOCAL_IP => self.connection.local_ip.to_string().into(),
+            V_LOCAL_PORT => self.connection.local_port.into(),
+            V_LISTENER => self.instance.id.as_str().into(),
+            V_PROTOCOL => self.instance.protocol.as_str().into(),
+            V_TLS => self.is_tls.into(),
+            V_AUTHENTICATED_AS => self.authenticated_as.into(),
+            _ => crate::expr::Variable::default(),
+        }
+    }
+
+    fn resolve_global(&self, _: &str) -> Variable<'_> {
+        Variable::Integer(0)
+    }
+}
+
+impl Server {
+    pub async fn is_extension_enabled(
+        &self,
+        extension: &Option<IfBlock>,
+        variables: &SessionVariables<'_>,
+        session_id: u64,
+    ) -> bool {
+        match extension {
+            Some(extension) => self
+                .eval_if::<bool, _>(extension, variables, session_id)
+                .await
+                .unwrap_or(true),
+            None => true,
+        }
+    }
+}
+
 impl Debug for TcpAcceptor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
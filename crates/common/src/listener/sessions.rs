@@ -0,0 +1,206 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    borrow::Cow,
+    net::IpAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+use store::write::now;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::Notify,
+};
+
+use crate::config::server::ServerProtocol;
+
+use super::{asn::AsnGeoLookupResult, SessionData, SessionStream};
+
+/// Registry of the client sessions currently open on this node.
+#[derive(Default)]
+pub struct ActiveSessions {
+    sessions: Mutex<AHashMap<u64, Arc<ActiveSession>>>,
+}
+
+pub struct ActiveSession {
+    pub session_id: u64,
+    pub protocol: ServerProtocol,
+    pub listener_id: String,
+    pub remote_ip: IpAddr,
+    pub remote_port: u16,
+    pub asn: Option<u32>,
+    pub country: Option<Arc<String>>,
+    pub started: u64,
+    account_id: AtomicU32,
+    last_activity: AtomicU64,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    terminate: Notify,
+}
+
+pub struct TrackedStream<T: SessionStream> {
+    stream: T,
+    session: Arc<ActiveSession>,
+}
+
+const NO_ACCOUNT: u32 = u32::MAX;
+
+impl ActiveSessions {
+    pub fn register<T: SessionStream>(
+        &self,
+        session: &SessionData<T>,
+        asn_geo: AsnGeoLookupResult,
+    ) -> Arc<ActiveSession> {
+        let now = now();
+        let active = Arc::new(ActiveSession {
+            session_id: session.session_id,
+            protocol: session.protocol,
+            listener_id: session.instance.id.clone(),
+            remote_ip: session.remote_ip,
+            remote_port: session.remote_port,
+            asn: asn_geo.asn.map(|asn| asn.id),
+            country: asn_geo.country,
+            started: now,
+            account_id: AtomicU32::new(NO_ACCOUNT),
+            last_activity: AtomicU64::new(now),
+            bytes_received: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            terminate: Notify::new(),
+        });
+        self.sessions
+            .lock()
+            .insert(session.session_id, active.clone());
+        active
+    }
+
+    pub fn unregister(&self, session_id: u64) {
+        self.sessions.lock().remove(&session_id);
+    }
+
+    pub fn get(&self, session_id: u64) -> Option<Arc<ActiveSession>> {
+        self.sessions.lock().get(&session_id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<Arc<ActiveSession>> {
+        self.sessions.lock().values().cloned().collect()
+    }
+
+    pub fn set_account_id(&self, session_id: u64, account_id: u32) {
+        if let Some(session) = self.sessions.lock().get(&session_id) {
+            session.account_id.store(account_id, Ordering::Relaxed);
+        }
+    }
+
+    /// Terminates all sessions authenticated as the given account, returning
+    /// the sessions that were terminated.
+    pub fn terminate_account(&self, account_id: u32) -> Vec<Arc<ActiveSession>> {
+        let sessions = self
+            .sessions
+            .lock()
+            .values()
+            .filter(|session| session.account_id() == Some(account_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        for session in &sessions {
+            session.terminate();
+        }
+        sessions
+    }
+}
+
+impl ActiveSession {
+    pub fn account_id(&self) -> Option<u32> {
+        Some(self.account_id.load(Ordering::Relaxed)).filter(|id| *id != NO_ACCOUNT)
+    }
+
+    pub fn last_activity(&self) -> u64 {
+        self.last_activity.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    pub fn terminate(&self) {
+        self.terminate.notify_one();
+    }
+
+    pub async fn terminated(&self) {
+        self.terminate.notified().await
+    }
+}
+
+impl<T: SessionStream> TrackedStream<T> {
+    pub fn new(stream: T, session: Arc<ActiveSession>) -> Self {
+        Self { stream, session }
+    }
+}
+
+impl<T: SessionStream> AsyncRead for TrackedStream<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.stream).poll_read(cx, buf);
+        let bytes = buf.filled().len().saturating_sub(filled);
+        if bytes > 0 {
+            self.session
+                .bytes_received
+                .fetch_add(bytes as u64, Ordering::Relaxed);
+            self.session.last_activity.store(now(), Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl<T: SessionStream> AsyncWrite for TrackedStream<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let result = Pin::new(&mut self.stream).poll_write(cx, buf);
+        if let Poll::Ready(Ok(bytes)) = &result {
+            self.session
+                .bytes_sent
+                .fetch_add(*bytes as u64, Ordering::Relaxed);
+            self.session.last_activity.store(now(), Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl<T: SessionStream> SessionStream for TrackedStream<T> {
+    fn is_tls(&self) -> bool {
+        self.stream.is_tls()
+    }
+
+    fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
+        self.stream.tls_version_and_cipher()
+    }
+}
@@ -33,6 +33,7 @@ use crate::{
 use super::{
     WEBADMIN_KEY,
     backup::BackupParams,
+    bootstrap::bootstrap_token,
     config::{ConfigManager, Patterns},
     console::store_console,
 };
@@ -42,6 +43,14 @@ pub struct BootManager {
     pub inner: Arc<Inner>,
     pub servers: Listeners,
     pub ipc_rxs: IpcReceivers,
+    pub mode: BootMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootMode {
+    Server,
+    QueueConsole,
+    Migrate,
 }
 
 pub struct IpcReceivers {
@@ -63,17 +72,32 @@ Options:
   -e, --export <PATH>              Export all store data to a specific path
   -i, --import <PATH>              Import store data from a specific path
   -o, --console                    Open the store console
+  -q, --queue                      Open the offline queue console
+  -m, --migrate                    Run pending store migrations and exit
   -I, --init <PATH>                Initialize a new server at a specific path
   -h, --help                       Print help
   -V, --version                    Print version
 "#
 );
 
+#[cfg(windows)]
+const SERVICE_HELP: &str = r#"
+Windows service options:
+      --install-service            Install the server as a Windows service
+      --uninstall-service          Remove the Windows service
+      --service                    Run under the Windows Service Control Manager
+"#;
+
+#[cfg(not(windows))]
+const SERVICE_HELP: &str = "";
+
 #[derive(PartialEq, Eq)]
 enum StoreOp {
     Export(BackupParams),
     Import(PathBuf),
     Console,
+    QueueConsole,
+    Migrate,
     None,
 }
 
@@ -81,6 +105,8 @@ impl BootManager {
     pub async fn init() -> Self {
         let mut config_path = std::env::var("CONFIG_PATH").ok();
         let mut import_export = StoreOp::None;
+        #[cfg(windows)]
+        let mut install_service = false;
 
         if config_path.is_none() {
             let mut args = std::env::args().skip(1);
@@ -98,7 +124,7 @@ impl BootManager {
 
                 match (key.as_str(), value) {
                     ("help" | "h", _) => {
-                        eprintln!("{HELP}");
+                        eprintln!("{HELP}{SERVICE_HELP}");
                         std::process::exit(0);
                     }
                     ("version" | "V", _) => {
@@ -121,6 +147,25 @@ impl BootManager {
                     ("console" | "o", None) => {
                         import_export = StoreOp::Console;
                     }
+                    ("queue" | "q", None) => {
+                        import_export = StoreOp::QueueConsole;
+                    }
+                    ("migrate" | "m", None) => {
+                        import_export = StoreOp::Migrate;
+                    }
+                    #[cfg(windows)]
+                    ("service", None) => {
+                        super::service::start_dispatcher();
+                    }
+                    #[cfg(windows)]
+                    ("install-service", None) => {
+                        install_service = true;
+                    }
+                    #[cfg(windows)]
+                    ("uninstall-service", None) => {
+                        super::service::uninstall();
+                        std::process::exit(0);
+                    }
                     (_, None) => {
                         failed(&format!("Unrecognized command '{key}', try '--help'."));
                     }
@@ -130,14 +175,26 @@ impl BootManager {
                 }
             }
 
+            #[cfg(windows)]
+            if config_path.is_none() {
+                config_path = super::service::default_config_path()
+                    .map(|path| path.to_string_lossy().into_owned());
+            }
+
             if config_path.is_none() {
                 if import_export == StoreOp::None {
-                    eprintln!("{HELP}");
+                    eprintln!("{HELP}{SERVICE_HELP}");
                 } else {
                     eprintln!("Missing '--config' argument for import/export.")
                 }
                 std::process::exit(0);
             }
+
+            #[cfg(windows)]
+            if install_service {
+                super::service::install(std::path::Path::new(config_path.as_ref().unwrap()));
+                std::process::exit(0);
+            }
         }
 
         // Read main configuration file
@@ -156,6 +213,9 @@ impl BootManager {
         // Resolve environment macros
         config.resolve_macros(&["env"]).await;
 
+        // Apply STALWART__* environment overrides
+        config.apply_env_overrides();
+
         // Parser servers
         let mut servers = Listeners::parse(&mut config);
 
@@ -321,14 +381,15 @@ impl BootManager {
                     }
                 }
 
-                // Download webadmin if missing
+                // Download webadmin if missing or if it does not match the pinned checksum
                 if let Some(blob_store) = config
                     .value("storage.blob")
                     .and_then(|id| stores.blob_stores.get(id))
                 {
                     match blob_store.get_blob(WEBADMIN_KEY, 0..usize::MAX).await {
-                        Ok(Some(_)) => (),
-                        Ok(None) => match manager.fetch_resource("webadmin").await {
+                        Ok(Some(bytes))
+                            if manager.verify_resource("webadmin", &bytes).await.is_ok() => {}
+                        Ok(_) => match manager.fetch_resource("webadmin").await {
                             Ok(bytes) => match blob_store.put_blob(WEBADMIN_KEY, &bytes).await {
                                 Ok(_) => {
                                     trc::event!(
@@ -402,6 +463,9 @@ impl BootManager {
                     }
                 }
 
+                // Enable bootstrap mode on first start
+                *data.bootstrap_token.write() = bootstrap_token(&core).await;
+
                 // Spam filter auto-update
                 if config
                     .property_or_default::<bool>("spam-filter.auto-update", "false")
@@ -445,6 +509,7 @@ impl BootManager {
                     config,
                     servers,
                     ipc_rxs,
+                    mode: BootMode::Server,
                 }
             }
             StoreOp::Export(path) => {
@@ -474,16 +539,68 @@ impl BootManager {
                 store_console(Core::parse(&mut config, stores, manager).await.storage.data).await;
                 std::process::exit(0);
             }
+            StoreOp::Migrate => {
+                // Enable telemetry
+                telemetry.enable(false);
+
+                // Parse settings, listeners are not started in this mode
+                let core = Core::parse(&mut config, stores, manager).await;
+                let data = Data::parse(&mut config);
+                let cache = Caches::parse(&mut config);
+                let (ipc, ipc_rxs) = build_ipc(&mut config);
+
+                BootManager {
+                    inner: Arc::new(Inner {
+                        shared_core: ArcSwap::from_pointee(core),
+                        data,
+                        ipc,
+                        cache,
+                    }),
+                    config,
+                    servers: Listeners::default(),
+                    ipc_rxs,
+                    mode: BootMode::Migrate,
+                }
+            }
+            StoreOp::QueueConsole => {
+                // Parse in-memory stores
+                stores.parse_in_memory(&mut config, false).await;
+
+                // Parse settings, listeners are not started in this mode
+                let core = Core::parse(&mut config, stores, manager).await;
+                let data = Data::parse(&mut config);
+                let cache = Caches::parse(&mut config);
+                let (ipc, ipc_rxs) = build_ipc(&mut config);
+
+                BootManager {
+                    inner: Arc::new(Inner {
+                        shared_core: ArcSwap::from_pointee(core),
+                        data,
+                        ipc,
+                        cache,
+                    }),
+                    config,
+                    servers: Listeners::default(),
+                    ipc_rxs,
+                    mode: BootMode::QueueConsole,
+                }
+            }
         }
     }
 }
 
 pub fn build_ipc(config: &mut Config) -> (Ipc, IpcReceivers) {
     // Build ipc receivers
-    let (state_tx, state_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
-    let (housekeeper_tx, housekeeper_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
-    let (queue_tx, queue_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
-    let (report_tx, report_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
+    let mut buffer_size = |channel: &str| {
+        config
+            .property_or_default::<usize>(("server.ipc.buffer", channel), "1024")
+            .unwrap_or(IPC_CHANNEL_BUFFER)
+            .max(1)
+    };
+    let (state_tx, state_rx) = mpsc::channel(buffer_size("state"));
+    let (housekeeper_tx, housekeeper_rx) = mpsc::channel(buffer_size("housekeeper"));
+    let (queue_tx, queue_rx) = mpsc::channel(buffer_size("queue"));
+    let (report_tx, report_rx) = mpsc::channel(buffer_size("report"));
     (
         Ipc {
             state_tx,
@@ -497,6 +614,12 @@ pub fn build_ipc(config: &mut Config) -> (Ipc, IpcReceivers) {
                     .unwrap_or(10)
                     .max(1),
             )),
+            report_analysis_sm: Arc::new(Semaphore::new(
+                config
+                    .property_or_default::<usize>("report.analysis.threads", "10")
+                    .unwrap_or(10)
+                    .max(1),
+            )),
         },
         IpcReceivers {
             state_rx: Some(state_rx),
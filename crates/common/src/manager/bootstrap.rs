@@ -0,0 +1,110 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use directory::{backend::internal::manage::ManageDirectory, Principal};
+use sha2::{Digest, Sha256};
+use trc::AddContext;
+use utils::config::ConfigKey;
+
+use crate::{auth::AccessToken, Core, Server};
+
+pub const BOOTSTRAP_COMPLETED_KEY: &str = "server.bootstrap.completed";
+
+// Bootstrap mode allows an automation tool to provision a new server using
+// a one-time token until the bootstrap process is marked as completed.
+pub async fn bootstrap_token(core: &Core) -> Option<[u8; 32]> {
+    let token = if let Ok(token) = std::env::var("STALWART_BOOTSTRAP_TOKEN") {
+        token
+    } else if let Ok(path) = std::env::var("STALWART_BOOTSTRAP_TOKEN_FILE") {
+        match tokio::fs::read_to_string(&path).await {
+            Ok(token) => token,
+            Err(err) => {
+                trc::event!(
+                    Resource(trc::ResourceEvent::Error),
+                    Details = "Failed to read bootstrap token file",
+                    Path = path,
+                    Reason = err.to_string(),
+                );
+                return None;
+            }
+        }
+    } else {
+        return None;
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        return None;
+    }
+
+    // Bootstrap is only available once, on a server without any principals
+    let is_empty = match core.storage.config.get(BOOTSTRAP_COMPLETED_KEY).await {
+        Ok(None) => match core.storage.data.count_principals(None, None, None).await {
+            Ok(total) => total == 0,
+            Err(err) => {
+                trc::error!(err.details("Failed to count principals"));
+                false
+            }
+        },
+        Ok(Some(_)) => false,
+        Err(err) => {
+            trc::error!(err.details("Failed to obtain bootstrap status"));
+            false
+        }
+    };
+
+    if is_empty {
+        trc::event!(Security(trc::SecurityEvent::BootstrapEnabled));
+
+        Some(Sha256::digest(token.as_bytes()).into())
+    } else {
+        None
+    }
+}
+
+impl Server {
+    pub fn is_bootstrap_token(&self, token: &str) -> bool {
+        self.inner
+            .data
+            .bootstrap_token
+            .read()
+            .as_ref()
+            .is_some_and(|hash| {
+                let digest: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+                hash.iter()
+                    .zip(digest.iter())
+                    .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                    == 0
+            })
+    }
+
+    pub fn is_bootstrap_active(&self) -> bool {
+        self.inner.data.bootstrap_token.read().is_some()
+    }
+
+    pub async fn bootstrap_access_token(&self) -> trc::Result<Arc<AccessToken>> {
+        self.get_access_token(Principal::fallback_admin(""))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    pub async fn complete_bootstrap(&self) -> trc::Result<bool> {
+        let token = self.inner.data.bootstrap_token.write().take();
+        if token.is_some() {
+            self.core
+                .storage
+                .config
+                .set([ConfigKey::from((BOOTSTRAP_COMPLETED_KEY, "true"))], true)
+                .await
+                .caused_by(trc::location!())?;
+
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
@@ -19,7 +19,7 @@ use store::{
 use trc::AddContext;
 use utils::{
     Semver,
-    config::{Config, ConfigKey},
+    config::{Config, ConfigKey, env_override_key},
     glob::GlobPattern,
 };
 
@@ -51,6 +51,22 @@ pub enum MatchType {
     All,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigSource {
+    Local,
+    Macro,
+    Environment,
+    Database,
+    Default,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct EffectiveValue {
+    pub value: String,
+    pub source: ConfigSource,
+}
+
 pub(crate) struct ExternalSpamRules {
     pub version: Semver,
     pub keys: Vec<ConfigKey>,
@@ -63,11 +79,66 @@ impl ConfigManager {
             ..Default::default()
         };
         config.resolve_all_macros().await;
+        config.apply_env_overrides();
         self.extend_config(&mut config, prefix)
             .await
             .map(|_| config)
     }
 
+    pub async fn effective_config(
+        &self,
+        defaults: BTreeMap<String, String>,
+    ) -> trc::Result<BTreeMap<String, EffectiveValue>> {
+        let local = self.cfg_local.load();
+        let mut config = Config {
+            keys: local.as_ref().clone(),
+            ..Default::default()
+        };
+        config.resolve_all_macros().await;
+
+        // Local keys, flagging the ones obtained from a macro expansion
+        let mut results = BTreeMap::new();
+        for (key, value) in config.keys {
+            let source = if local.get(&key).is_some_and(|raw| raw != &value) {
+                ConfigSource::Macro
+            } else {
+                ConfigSource::Local
+            };
+            results.insert(key, EffectiveValue { value, source });
+        }
+
+        // Environment overrides
+        for (name, value) in std::env::vars() {
+            if let Some(key) = env_override_key(&name) {
+                results.insert(
+                    key,
+                    EffectiveValue {
+                        value,
+                        source: ConfigSource::Environment,
+                    },
+                );
+            }
+        }
+
+        // Settings stored in the database
+        for (key, value) in self.db_list("", false).await? {
+            results.entry(key).or_insert(EffectiveValue {
+                value,
+                source: ConfigSource::Database,
+            });
+        }
+
+        // Defaults applied while parsing
+        for (key, value) in defaults {
+            results.entry(key).or_insert(EffectiveValue {
+                value,
+                source: ConfigSource::Default,
+            });
+        }
+
+        Ok(results)
+    }
+
     pub(crate) async fn extend_config(&self, config: &mut Config, prefix: &str) -> trc::Result<()> {
         for (key, value) in self.db_list(prefix, false).await? {
             config.keys.entry(key).or_insert(value);
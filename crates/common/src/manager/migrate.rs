@@ -0,0 +1,140 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+    time::Duration,
+};
+
+use directory::backend::internal::MigrateDirectory;
+use trc::AddContext;
+
+use crate::Server;
+
+const MIGRATION_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Tracks the progress of the store migrations executed at startup,
+/// the server is not reported as ready until they complete.
+#[derive(Debug, Default)]
+pub struct MigrationStatus {
+    state: AtomicU8,
+    processed: AtomicU64,
+    total: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MigrationState {
+    Completed = 0,
+    Pending = 1,
+    Running = 2,
+    Required = 3,
+    Failed = 4,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct MigrationProgress {
+    pub state: &'static str,
+    pub processed: u64,
+    pub total: u64,
+}
+
+impl MigrationStatus {
+    pub fn state(&self) -> MigrationState {
+        match self.state.load(Ordering::Relaxed) {
+            1 => MigrationState::Pending,
+            2 => MigrationState::Running,
+            3 => MigrationState::Required,
+            4 => MigrationState::Failed,
+            _ => MigrationState::Completed,
+        }
+    }
+
+    pub fn set_state(&self, state: MigrationState) {
+        self.state.store(state as u8, Ordering::Relaxed);
+    }
+
+    pub fn set_progress(&self, processed: u64, total: u64) {
+        self.processed.store(processed, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state() == MigrationState::Completed
+    }
+
+    pub fn progress(&self) -> MigrationProgress {
+        MigrationProgress {
+            state: self.state().as_str(),
+            processed: self.processed.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MigrationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationState::Completed => "completed",
+            MigrationState::Pending => "pending",
+            MigrationState::Running => "running",
+            MigrationState::Required => "required",
+            MigrationState::Failed => "failed",
+        }
+    }
+}
+
+impl Server {
+    pub async fn run_migrations(&self) -> trc::Result<()> {
+        let status = &self.inner.data.migration;
+        status.set_state(MigrationState::Running);
+
+        match self
+            .store()
+            .migrate_directory(|processed, total| status.set_progress(processed, total))
+            .await
+            .caused_by(trc::location!())
+        {
+            Ok(_) => {
+                status.set_state(MigrationState::Completed);
+                Ok(())
+            }
+            Err(err) => {
+                status.set_state(MigrationState::Failed);
+                Err(err)
+            }
+        }
+    }
+
+    /// Waits until the store has been migrated by a separate process
+    /// started with `--migrate`.
+    pub async fn wait_for_migrations(&self) {
+        let status = &self.inner.data.migration;
+
+        loop {
+            match self.store().needs_directory_migration().await {
+                Ok(false) => {
+                    status.set_state(MigrationState::Completed);
+                    break;
+                }
+                Ok(true) => {
+                    if status.state() != MigrationState::Required {
+                        status.set_state(MigrationState::Required);
+                        trc::event!(
+                            Server(trc::ServerEvent::StartupError),
+                            Details = "Store migration required, waiting for it to complete"
+                        );
+                    }
+                }
+                Err(err) => {
+                    trc::error!(err.details("Failed to check store migration status"));
+                }
+            }
+
+            tokio::time::sleep(MIGRATION_POLL_INTERVAL).await;
+        }
+    }
+}
@@ -6,7 +6,10 @@
 
 use std::time::Duration;
 
+use base64::{engine::general_purpose::STANDARD, Engine};
 use hyper::HeaderMap;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use sha2::{Digest, Sha256};
 use utils::HttpLimitResponse;
 
 use crate::USER_AGENT;
@@ -15,10 +18,15 @@ use self::config::ConfigManager;
 
 pub mod backup;
 pub mod boot;
+pub mod bootstrap;
 pub mod config;
 pub mod console;
+pub mod migrate;
 pub mod reload;
+pub mod restart;
 pub mod restore;
+#[cfg(windows)]
+pub mod service;
 pub mod webadmin;
 
 const DEFAULT_SPAMFILTER_URL: &str =
@@ -27,42 +35,156 @@ const DEFAULT_WEBADMIN_URL: &str =
     "https://github.com/Bixilon/stalwart-webadmin/releases/latest/download/webadmin.zip";
 pub const WEBADMIN_KEY: &[u8] = "STALWART_WEBADMIN".as_bytes();
 
+// Webadmin bundle embedded at compile time, used instead of downloading
+// the default bundle on air-gapped deployments.
+#[cfg(feature = "webadmin_bundle")]
+const EMBEDDED_WEBADMIN: &[u8] = include_bytes!(env!("STALWART_WEBADMIN_BUNDLE"));
+
 impl ConfigManager {
     pub async fn fetch_resource(&self, resource_id: &str) -> Result<Vec<u8>, String> {
-        if let Some(url) = self
-            .get(&format!("{resource_id}.resource"))
-            .await
-            .map_err(|err| {
-                format!("Failed to fetch configuration key '{resource_id}.resource': {err}",)
-            })?
-        {
-            fetch_resource(&url, None, Duration::from_secs(60), MAX_SIZE).await
-        } else {
+        // Build the list of locations to try, the primary resource URL followed by any mirrors
+        let mut urls = Vec::new();
+        if let Some(url) = self.resource_setting(resource_id, "resource").await? {
+            urls.push(url);
+        }
+        urls.extend(
+            self.list(&format!("{resource_id}.mirrors."), true)
+                .await
+                .map_err(|err| {
+                    format!("Failed to list configuration key '{resource_id}.mirrors': {err}")
+                })?
+                .into_iter()
+                .map(|(_, url)| url)
+                .filter(|url| !url.is_empty()),
+        );
+        if urls.is_empty() {
             match resource_id {
-                "spam-filter" => {
-                    fetch_resource(
-                        DEFAULT_SPAMFILTER_URL,
-                        None,
-                        Duration::from_secs(60),
-                        MAX_SIZE,
-                    )
-                    .await
-                }
+                "spam-filter" => urls.push(DEFAULT_SPAMFILTER_URL.to_string()),
+                #[cfg(feature = "webadmin_bundle")]
                 "webadmin" => {
-                    fetch_resource(
-                        DEFAULT_WEBADMIN_URL,
+                    let bytes = EMBEDDED_WEBADMIN.to_vec();
+                    self.verify_resource(resource_id, &bytes).await?;
+                    return Ok(bytes);
+                }
+                #[cfg(not(feature = "webadmin_bundle"))]
+                "webadmin" => urls.push(DEFAULT_WEBADMIN_URL.to_string()),
+                _ => return Err(format!("Unknown resource: {resource_id}")),
+            }
+        }
+
+        let proxy = match self.resource_setting(resource_id, "proxy").await? {
+            Some(proxy) => Some(proxy),
+            None => self.resource_setting("resource", "proxy").await?,
+        };
+        let public_key = self
+            .resource_setting(resource_id, "signature.public-key")
+            .await?
+            .map(|key| {
+                STANDARD.decode(key.trim()).map_err(|err| {
+                    format!("Invalid public key in '{resource_id}.signature.public-key': {err}")
+                })
+            })
+            .transpose()?;
+
+        let mut last_err = String::new();
+        for url in urls {
+            let result = async {
+                let bytes = fetch_resource_with_proxy(
+                    &url,
+                    None,
+                    Duration::from_secs(60),
+                    MAX_SIZE,
+                    proxy.as_deref(),
+                )
+                .await?;
+                self.verify_resource(resource_id, &bytes).await?;
+                if let Some(public_key) = &public_key {
+                    let signature = fetch_resource_with_proxy(
+                        &format!("{url}.sig"),
                         None,
                         Duration::from_secs(60),
-                        MAX_SIZE,
+                        1024,
+                        proxy.as_deref(),
                     )
-                    .await
+                    .await?;
+                    verify_signature(resource_id, public_key, &bytes, &signature)?;
+                }
+                Ok::<_, String>(bytes)
+            }
+            .await;
+
+            match result {
+                Ok(bytes) => return Ok(bytes),
+                Err(err) => {
+                    trc::event!(
+                        Resource(trc::ResourceEvent::Error),
+                        Id = resource_id.to_string(),
+                        Url = url,
+                        Details = err.clone(),
+                    );
+                    last_err = err;
                 }
-                _ => Err(format!("Unknown resource: {resource_id}")),
             }
         }
+
+        Err(last_err)
+    }
+
+    /// Verifies the contents of a resource against the SHA-256 digest
+    /// pinned in `{resource_id}.checksum`, if any.
+    pub async fn verify_resource(&self, resource_id: &str, bytes: &[u8]) -> Result<(), String> {
+        if let Some(expected) = self.resource_setting(resource_id, "checksum").await? {
+            let expected = expected
+                .trim()
+                .strip_prefix("sha256:")
+                .unwrap_or(expected.trim())
+                .to_ascii_lowercase();
+            let digest = format!("{:x}", Sha256::digest(bytes));
+
+            if digest != expected {
+                return Err(format!(
+                    "Checksum mismatch for resource '{resource_id}': expected {expected}, got {digest}"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn resource_setting(
+        &self,
+        resource_id: &str,
+        setting: &str,
+    ) -> Result<Option<String>, String> {
+        let key = format!("{resource_id}.{setting}");
+        self.get(&key)
+            .await
+            .map(|value| value.filter(|value| !value.is_empty()))
+            .map_err(|err| format!("Failed to fetch configuration key '{key}': {err}"))
     }
 }
 
+// Resources can be signed with an Ed25519 key, the signature is
+// published next to the resource with a `.sig` extension.
+fn verify_signature(
+    resource_id: &str,
+    public_key: &[u8],
+    bytes: &[u8],
+    signature: &[u8],
+) -> Result<(), String> {
+    let signature = if signature.len() == 64 {
+        signature.to_vec()
+    } else {
+        STANDARD
+            .decode(signature.trim_ascii())
+            .map_err(|err| format!("Invalid signature for resource '{resource_id}': {err}"))?
+    };
+
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(bytes, &signature)
+        .map_err(|_| format!("Signature verification failed for resource '{resource_id}'"))
+}
+
 const MAX_SIZE: usize = 100 * 1024 * 1024;
 
 pub async fn fetch_resource(
@@ -70,16 +192,34 @@ pub async fn fetch_resource(
     headers: Option<HeaderMap>,
     timeout: Duration,
     max_size: usize,
+) -> Result<Vec<u8>, String> {
+    fetch_resource_with_proxy(url, headers, timeout, max_size, None).await
+}
+
+pub async fn fetch_resource_with_proxy(
+    url: &str,
+    headers: Option<HeaderMap>,
+    timeout: Duration,
+    max_size: usize,
+    proxy: Option<&str>,
 ) -> Result<Vec<u8>, String> {
     if let Some(path) = url.strip_prefix("file://") {
         tokio::fs::read(path)
             .await
             .map_err(|err| format!("Failed to read {path}: {err}"))
     } else {
-        let response = reqwest::Client::builder()
+        let mut client = reqwest::Client::builder()
             .timeout(timeout)
             .danger_accept_invalid_certs(is_localhost_url(url))
-            .user_agent(USER_AGENT)
+            .user_agent(USER_AGENT);
+        if let Some(proxy) = proxy {
+            client = client.proxy(
+                reqwest::Proxy::all(proxy)
+                    .map_err(|err| format!("Invalid proxy URL {proxy}: {err}"))?,
+            );
+        }
+
+        let response = client
             .build()
             .unwrap_or_default()
             .get(url)
@@ -11,10 +11,12 @@ use utils::config::Config;
 
 use crate::{
     config::{
+        scripts::Scripting,
         server::{tls::parse_certificates, Listeners},
+        spamfilter::SpamFilterConfig,
         telemetry::Telemetry,
     },
-    listener::blocked::{BlockedIps, BLOCKED_IP_KEY},
+    listener::blocked::{IpList, ALLOWED_IP_KEY, BLOCKED_IP_KEY},
     Core, Server,
 };
 
@@ -34,7 +36,13 @@ impl Server {
             .config
             .build_config(BLOCKED_IP_KEY)
             .await?;
-        *self.inner.data.blocked_ips.write() = BlockedIps::parse(&mut config).blocked_ip_addresses;
+        self.core
+            .storage
+            .config
+            .extend_config(&mut config, ALLOWED_IP_KEY)
+            .await?;
+        *self.inner.data.blocked_ips.write() = IpList::parse_blocked(&mut config);
+        *self.inner.data.allowed_ips.write() = IpList::parse_allowed(&mut config);
 
         Ok(config.into())
     }
@@ -67,6 +75,36 @@ impl Server {
         })
     }
 
+    pub async fn reload_sieve_library(&self) -> trc::Result<ReloadResult> {
+        let mut config = self
+            .core
+            .storage
+            .config
+            .build_config("sieve.untrusted.library")
+            .await?;
+        let mut core = self.core.as_ref().clone();
+        core.sieve.sieve_library =
+            Scripting::parse_library(&mut config, &core.sieve.untrusted_compiler);
+
+        Ok(ReloadResult {
+            config,
+            new_core: core.into(),
+            tracers: None,
+        })
+    }
+
+    pub async fn reload_spam_filter(&self) -> trc::Result<ReloadResult> {
+        let mut config = self.core.storage.config.build_config("spam-filter").await?;
+        let mut core = self.core.as_ref().clone();
+        core.spam = SpamFilterConfig::parse(&mut config).await;
+
+        Ok(ReloadResult {
+            config,
+            new_core: core.into(),
+            tracers: None,
+        })
+    }
+
     pub async fn reload(&self) -> trc::Result<ReloadResult> {
         let mut config = self.core.storage.config.build_config("").await?;
 
@@ -120,8 +158,9 @@ impl Server {
             .tls_certificates
             .store(current_certificates.into());
 
-        // Update blocked IPs
-        *self.inner.data.blocked_ips.write() = BlockedIps::parse(&mut config).blocked_ip_addresses;
+        // Update allowed and blocked IPs
+        *self.inner.data.blocked_ips.write() = IpList::parse_blocked(&mut config);
+        *self.inner.data.allowed_ips.write() = IpList::parse_allowed(&mut config);
 
         // Parser servers
         let mut servers = Listeners::parse(&mut config);
@@ -0,0 +1,119 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use store::write::now;
+use tokio::sync::Notify;
+
+use crate::Server;
+
+/// Tracks a restart requested through the management API, which is carried
+/// out by the main task once sessions and deliveries have been drained.
+#[derive(Debug)]
+pub struct RestartStatus {
+    state: AtomicU8,
+    requested_at: AtomicU64,
+    started_at: u64,
+    notify: Notify,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RestartState {
+    Idle = 0,
+    Requested = 1,
+    Draining = 2,
+    Flushing = 3,
+    Restarting = 4,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartProgress {
+    pub state: &'static str,
+    pub requested_at: u64,
+    pub started_at: u64,
+    pub active_sessions: usize,
+}
+
+impl Default for RestartStatus {
+    fn default() -> Self {
+        RestartStatus {
+            state: AtomicU8::new(RestartState::Idle as u8),
+            requested_at: AtomicU64::new(0),
+            started_at: now(),
+            notify: Notify::new(),
+        }
+    }
+}
+
+impl RestartStatus {
+    pub fn state(&self) -> RestartState {
+        match self.state.load(Ordering::Relaxed) {
+            1 => RestartState::Requested,
+            2 => RestartState::Draining,
+            3 => RestartState::Flushing,
+            4 => RestartState::Restarting,
+            _ => RestartState::Idle,
+        }
+    }
+
+    pub fn set_state(&self, state: RestartState) {
+        self.state.store(state as u8, Ordering::Relaxed);
+    }
+
+    /// Requests a restart, returning `false` if one is already in progress.
+    pub fn request(&self) -> bool {
+        if self
+            .state
+            .compare_exchange(
+                RestartState::Idle as u8,
+                RestartState::Requested as u8,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            self.requested_at.store(now(), Ordering::Relaxed);
+            self.notify.notify_one();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Waits until a restart is requested.
+    pub async fn requested(&self) {
+        self.notify.notified().await
+    }
+}
+
+impl RestartState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RestartState::Idle => "idle",
+            RestartState::Requested => "requested",
+            RestartState::Draining => "draining",
+            RestartState::Flushing => "flushing",
+            RestartState::Restarting => "restarting",
+        }
+    }
+}
+
+impl Server {
+    /// Returns the progress of a pending restart, the `startedAt` timestamp
+    /// changes once the server is back up.
+    pub fn restart_progress(&self) -> RestartProgress {
+        let status = &self.inner.data.restart;
+        RestartProgress {
+            state: status.state().as_str(),
+            requested_at: status.requested_at.load(Ordering::Relaxed),
+            started_at: status.started_at,
+            active_sessions: self.inner.data.active_sessions.list().len(),
+        }
+    }
+}
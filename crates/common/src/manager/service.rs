@@ -0,0 +1,183 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::sync::Notify;
+use utils::{UnwrapFailure, failed};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+pub const SERVICE_NAME: &str = "StalwartMail";
+const SERVICE_DISPLAY_NAME: &str = "Stalwart Mail Server";
+const SERVICE_DESCRIPTION: &str = "Stalwart JMAP, IMAP, POP3 and SMTP server";
+
+static IS_SERVICE: AtomicBool = AtomicBool::new(false);
+static STATUS_HANDLE: OnceLock<ServiceStatusHandle> = OnceLock::new();
+static STOP_REQUEST: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Connects the process to the Service Control Manager. The dispatcher
+/// blocks until the service is stopped, so it runs on its own thread.
+pub fn start_dispatcher() {
+    IS_SERVICE.store(true, Ordering::Relaxed);
+    std::thread::Builder::new()
+        .name("service-dispatcher".into())
+        .spawn(|| {
+            if let Err(err) = service_dispatcher::start(SERVICE_NAME, ffi_service_main) {
+                failed(&format!("Failed to start service dispatcher: {err}"));
+            }
+        })
+        .failed("Failed to spawn service dispatcher thread");
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    let status_handle =
+        match service_control_handler::register(SERVICE_NAME, |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                set_service_state(ServiceState::StopPending, Duration::from_secs(30));
+                STOP_REQUEST.notify_one();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        }) {
+            Ok(status_handle) => status_handle,
+            Err(err) => {
+                failed(&format!("Failed to register service control handler: {err}"));
+            }
+        };
+
+    let _ = STATUS_HANDLE.set(status_handle);
+    set_service_state(ServiceState::Running, Duration::default());
+}
+
+fn set_service_state(state: ServiceState, wait_hint: Duration) {
+    if let Some(status_handle) = STATUS_HANDLE.get() {
+        let _ = status_handle.set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted: if state == ServiceState::Running {
+                ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+            } else {
+                ServiceControlAccept::empty()
+            },
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            wait_hint,
+            process_id: None,
+        });
+    }
+}
+
+/// Waits for either a console interrupt or a stop request from the
+/// Service Control Manager.
+pub async fn wait_for_shutdown() {
+    if IS_SERVICE.load(Ordering::Relaxed) {
+        tokio::select! {
+            _ = utils::wait_for_shutdown() => {}
+            _ = STOP_REQUEST.notified() => {
+                trc::event!(
+                    Server(trc::ServerEvent::Shutdown),
+                    CausedBy = "Service Control Manager"
+                );
+            }
+        }
+    } else {
+        utils::wait_for_shutdown().await;
+    }
+}
+
+/// Reports to the Service Control Manager that the server has stopped.
+pub fn report_stopped() {
+    set_service_state(ServiceState::Stopped, Duration::default());
+}
+
+/// Default configuration path following the Windows conventions,
+/// `%ProgramData%\Stalwart\etc\config.toml`.
+pub fn default_config_path() -> Option<PathBuf> {
+    std::env::var_os("ProgramData")
+        .map(|path| PathBuf::from(path).join("Stalwart").join("etc").join("config.toml"))
+        .filter(|path| path.exists())
+}
+
+pub fn install(config_path: &Path) {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .failed("Failed to connect to the Service Control Manager");
+
+    let config_path = config_path
+        .canonicalize()
+        .failed("Failed to resolve configuration file path");
+    let service = manager
+        .create_service(
+            &ServiceInfo {
+                name: SERVICE_NAME.into(),
+                display_name: SERVICE_DISPLAY_NAME.into(),
+                service_type: ServiceType::OWN_PROCESS,
+                start_type: ServiceStartType::AutoStart,
+                error_control: ServiceErrorControl::Normal,
+                executable_path: std::env::current_exe()
+                    .failed("Failed to obtain executable path"),
+                launch_arguments: vec![
+                    "--config".into(),
+                    config_path.into_os_string(),
+                    "--service".into(),
+                ],
+                dependencies: vec![],
+                account_name: None,
+                account_password: None,
+            },
+            ServiceAccess::CHANGE_CONFIG,
+        )
+        .failed("Failed to create service");
+    service
+        .set_description(SERVICE_DESCRIPTION)
+        .failed("Failed to set service description");
+
+    eprintln!("✅ Service '{SERVICE_NAME}' installed.");
+}
+
+pub fn uninstall() {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .failed("Failed to connect to the Service Control Manager");
+    let service = manager
+        .open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .failed("Failed to open service");
+
+    if service
+        .query_status()
+        .is_ok_and(|status| status.current_state != ServiceState::Stopped)
+    {
+        let _ = service.stop();
+    }
+
+    service.delete().failed("Failed to delete service");
+
+    eprintln!("✅ Service '{SERVICE_NAME}' removed.");
+}
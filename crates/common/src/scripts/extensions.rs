@@ -0,0 +1,179 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use mail_parser::Message;
+use sieve::{compiler::grammar::Capability, SpamStatus, VirusStatus};
+
+use crate::config::scripts::UntrustedExtensions;
+
+impl UntrustedExtensions {
+    /// Returns the first extension listed in the script's `require` commands
+    /// that has been disabled for untrusted scripts.
+    pub fn find_disabled(&self, script: &[u8]) -> Option<Capability> {
+        if self.disabled.is_empty() {
+            return None;
+        }
+
+        required_extensions(script)
+            .into_iter()
+            .map(|extension| Capability::parse(&extension))
+            .find(|capability| self.disabled.contains(capability))
+    }
+
+    /// Obtains the spam status of a message from the header added by the spam filter.
+    pub fn spam_status(&self, message: &Message<'_>, header: &str, threshold: f64) -> SpamStatus {
+        let Some(value) = message.header_raw(header).map(str::trim) else {
+            return SpamStatus::Unknown;
+        };
+        let (verdict, params) = value.split_once(',').unwrap_or((value, ""));
+        let score = params.split(',').find_map(|param| {
+            param
+                .trim()
+                .strip_prefix("score=")
+                .and_then(|score| score.trim().parse::<f64>().ok())
+        });
+
+        match (verdict.trim().to_ascii_lowercase().as_str(), score) {
+            (_, Some(score)) if threshold > 0.0 => {
+                if score >= threshold {
+                    SpamStatus::Spam
+                } else if score <= 0.0 {
+                    SpamStatus::Ham
+                } else {
+                    SpamStatus::MaybeSpam(score / threshold)
+                }
+            }
+            ("yes", _) => SpamStatus::Spam,
+            ("no", _) => SpamStatus::Ham,
+            _ => SpamStatus::Unknown,
+        }
+    }
+
+    /// Obtains the virus status of a message from the header added by an external scanner.
+    pub fn virus_status(&self, message: &Message<'_>) -> VirusStatus {
+        let Some(value) = message.header_raw(self.virustest_header.as_str()) else {
+            return VirusStatus::Unknown;
+        };
+
+        match value
+            .trim()
+            .split(|ch: char| ch.is_ascii_whitespace() || ch == ',' || ch == ';')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "clean" | "no" => VirusStatus::Clean,
+            "replaced" => VirusStatus::Replaced,
+            "cured" => VirusStatus::Cured,
+            "suspicious" | "maybe" => VirusStatus::MaybeVirus,
+            "infected" | "virus" | "yes" => VirusStatus::Virus,
+            _ => VirusStatus::Unknown,
+        }
+    }
+}
+
+/// Lists the extensions named in the `require` commands at the top of a script.
+fn required_extensions(script: &[u8]) -> Vec<String> {
+    let mut extensions = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        skip_whitespace(script, &mut pos);
+        if !script
+            .get(pos..pos + 7)
+            .is_some_and(|word| word.eq_ignore_ascii_case(b"require"))
+        {
+            break;
+        }
+        pos += 7;
+        skip_whitespace(script, &mut pos);
+
+        match script.get(pos) {
+            Some(b'[') => {
+                pos += 1;
+                loop {
+                    skip_whitespace(script, &mut pos);
+                    let Some(extension) = parse_string(script, &mut pos) else {
+                        return extensions;
+                    };
+                    extensions.push(extension);
+                    skip_whitespace(script, &mut pos);
+                    match script.get(pos) {
+                        Some(b',') => pos += 1,
+                        Some(b']') => {
+                            pos += 1;
+                            break;
+                        }
+                        _ => return extensions,
+                    }
+                }
+            }
+            Some(b'"') => {
+                let Some(extension) = parse_string(script, &mut pos) else {
+                    return extensions;
+                };
+                extensions.push(extension);
+            }
+            _ => break,
+        }
+
+        skip_whitespace(script, &mut pos);
+        if script.get(pos) == Some(&b';') {
+            pos += 1;
+        } else {
+            break;
+        }
+    }
+
+    extensions
+}
+
+fn skip_whitespace(script: &[u8], pos: &mut usize) {
+    while let Some(ch) = script.get(*pos) {
+        match ch {
+            b' ' | b'\t' | b'\r' | b'\n' => *pos += 1,
+            b'#' => {
+                while script.get(*pos).is_some_and(|ch| *ch != b'\n') {
+                    *pos += 1;
+                }
+            }
+            b'/' if script.get(*pos + 1) == Some(&b'*') => {
+                *pos += 2;
+                while *pos < script.len() && !script[*pos..].starts_with(b"*/") {
+                    *pos += 1;
+                }
+                *pos += 2;
+            }
+            _ => break,
+        }
+    }
+}
+
+fn parse_string(script: &[u8], pos: &mut usize) -> Option<String> {
+    if script.get(*pos) != Some(&b'"') {
+        return None;
+    }
+    *pos += 1;
+
+    let mut value = Vec::new();
+    loop {
+        match script.get(*pos)? {
+            b'"' => {
+                *pos += 1;
+                return String::from_utf8(value).ok();
+            }
+            b'\\' => {
+                value.push(*script.get(*pos + 1)?);
+                *pos += 2;
+            }
+            ch => {
+                value.push(*ch);
+                *pos += 1;
+            }
+        }
+    }
+}
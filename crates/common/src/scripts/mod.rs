@@ -12,7 +12,9 @@ use unicode_security::mixed_script::AugmentedScriptSet;
 
 use crate::IntoString;
 
+pub mod extensions;
 pub mod functions;
+pub mod notify;
 pub mod plugins;
 
 #[derive(Debug, serde::Serialize)]
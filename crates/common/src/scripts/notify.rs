@@ -0,0 +1,256 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use reqwest::header::CONTENT_TYPE;
+use trc::{AddContext, SieveEvent};
+
+use crate::{
+    config::scripts::{NotifyBackend, NotifyMethod},
+    Server, KV_RATE_LIMIT_NOTIFY,
+};
+
+/// A notification requested by a user script with the Sieve notify action.
+pub struct SieveNotification {
+    pub method: String,
+    pub from: Option<String>,
+    pub importance: &'static str,
+    pub options: Vec<String>,
+    pub message: String,
+}
+
+impl Server {
+    /// Hands a notification to the backend registered for its URI scheme,
+    /// returning whether it was accepted.
+    pub async fn sieve_notify(
+        &self,
+        account_id: u32,
+        account: &str,
+        notification: SieveNotification,
+        session_id: u64,
+    ) -> bool {
+        let (scheme, target) = notification.method.split_once(':').unwrap_or_default();
+        let Some(backend) = self.core.sieve.notify.get(&scheme.to_lowercase()).cloned() else {
+            trc::event!(
+                Sieve(SieveEvent::NotSupported),
+                SpanId = session_id,
+                AccountId = account_id,
+                Details = notification.method.clone(),
+                Reason = "No backend is configured for this notification method",
+            );
+            return false;
+        };
+        let Some(target) = backend.method.parse_target(target) else {
+            trc::event!(
+                Sieve(SieveEvent::NotifyFailed),
+                SpanId = session_id,
+                AccountId = account_id,
+                Id = backend.id.clone(),
+                Details = notification.method.clone(),
+                Reason = "Invalid notification target",
+            );
+            return false;
+        };
+
+        // Enforce the per-user rate limit
+        if let Some(rate) = &backend.rate {
+            let mut key = Vec::with_capacity(std::mem::size_of::<u32>() + backend.id.len());
+            key.extend_from_slice(&account_id.to_be_bytes());
+            key.extend_from_slice(backend.id.as_bytes());
+            match self
+                .in_memory_store()
+                .is_rate_allowed(KV_RATE_LIMIT_NOTIFY, &key, rate, false)
+                .await
+            {
+                Ok(None) => (),
+                Ok(Some(_)) => {
+                    trc::event!(
+                        Sieve(SieveEvent::LimitExceeded),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        Id = backend.id.clone(),
+                        Details = "Notification rate limit exceeded",
+                    );
+                    return false;
+                }
+                Err(err) => {
+                    trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+                    return false;
+                }
+            }
+        }
+
+        let message = match notification
+            .message
+            .char_indices()
+            .nth(backend.max_message_length)
+        {
+            Some((pos, _)) => format!("{}…", &notification.message[..pos]),
+            None => notification.message,
+        };
+        let payload = NotifyPayload {
+            target,
+            account: account.to_string(),
+            from: notification.from,
+            importance: notification.importance,
+            options: notification.options,
+            message,
+        };
+
+        // Notifications are sent in the background to avoid delaying delivery
+        tokio::spawn(async move {
+            match backend.send(&payload).await {
+                Ok(_) => {
+                    trc::event!(
+                        Sieve(SieveEvent::Notify),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        Id = backend.id.clone(),
+                        To = payload.target,
+                    );
+                }
+                Err(err) => {
+                    trc::event!(
+                        Sieve(SieveEvent::NotifyFailed),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        Id = backend.id.clone(),
+                        To = payload.target,
+                        CausedBy = err,
+                    );
+                }
+            }
+        });
+
+        true
+    }
+}
+
+struct NotifyPayload {
+    target: String,
+    account: String,
+    from: Option<String>,
+    importance: &'static str,
+    options: Vec<String>,
+    message: String,
+}
+
+impl NotifyMethod {
+    /// Extracts the recipient from the part of the notification URI that
+    /// follows the scheme.
+    fn parse_target(&self, target: &str) -> Option<String> {
+        let target = target.split_once('?').map_or(target, |(target, _)| target);
+        let target = percent_decode(target.trim_start_matches("//"))?;
+
+        match self {
+            NotifyMethod::Sms => {
+                // Visual separators are allowed in phone numbers (RFC 3966)
+                let number = target
+                    .chars()
+                    .filter(|ch| !matches!(ch, '-' | '.' | ' ' | '(' | ')'))
+                    .collect::<String>();
+                let digits = number.strip_prefix('+').unwrap_or(&number);
+                (digits.len() >= 3
+                    && digits.len() <= 15
+                    && digits.chars().all(|ch| ch.is_ascii_digit()))
+                .then_some(number)
+            }
+            NotifyMethod::Xmpp => target
+                .split_once('@')
+                .is_some_and(|(local, domain)| {
+                    !local.is_empty()
+                        && !domain.is_empty()
+                        && !target.contains(|ch: char| ch.is_whitespace())
+                })
+                .then_some(target),
+            NotifyMethod::Webhook => Some(target),
+        }
+    }
+}
+
+impl NotifyBackend {
+    async fn send(&self, payload: &NotifyPayload) -> trc::Result<()> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|err| {
+                trc::EventType::Sieve(SieveEvent::NotifyFailed)
+                    .into_err()
+                    .reason(err)
+                    .details("Failed to build request")
+            })?;
+        let url = self.url.replace("{to}", &url_encode(&payload.target));
+
+        // Use the configured form template, or otherwise post the notification as JSON
+        let mut request = if let Some(body) = &self.body {
+            client
+                .post(url)
+                .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(
+                    body.replace("{to}", &url_encode(&payload.target))
+                        .replace("{account}", &url_encode(&payload.account))
+                        .replace(
+                            "{from}",
+                            &url_encode(payload.from.as_deref().unwrap_or_default()),
+                        )
+                        .replace("{importance}", payload.importance)
+                        .replace("{message}", &url_encode(&payload.message)),
+                )
+        } else {
+            client
+                .post(url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(
+                    serde_json::json!({
+                        "type": self.method.as_str(),
+                        "to": payload.target,
+                        "account": payload.account,
+                        "from": payload.from,
+                        "importance": payload.importance,
+                        "options": payload.options,
+                        "message": payload.message,
+                    })
+                    .to_string(),
+                )
+        };
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let response = request.send().await.map_err(|err| {
+            trc::EventType::Sieve(SieveEvent::NotifyFailed)
+                .into_err()
+                .reason(err)
+                .details("Failed to send request")
+        })?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(trc::EventType::Sieve(SieveEvent::NotifyFailed)
+                .into_err()
+                .ctx(trc::Key::Code, response.status().as_u16())
+                .details("Notification backend returned an error"))
+        }
+        .caused_by(trc::location!())
+    }
+}
+
+fn url_encode(value: &str) -> String {
+    form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -31,7 +31,7 @@ pub struct PluginContext<'x> {
     pub arguments: Vec<Variable>,
 }
 
-const PLUGINS_REGISTER: [RegisterPluginFnc; 13] = [
+const PLUGINS_REGISTER: [RegisterPluginFnc; 14] = [
     query::register,
     exec::register,
     lookup::register,
@@ -45,6 +45,7 @@ const PLUGINS_REGISTER: [RegisterPluginFnc; 13] = [
     text::register_tokenize,
     text::register_domain_part,
     llm_prompt::register,
+    query::register_lookup,
 ];
 
 pub trait RegisterSievePlugins {
@@ -93,6 +94,7 @@ impl Core {
             10 => text::exec_tokenize(ctx),
             11 => text::exec_domain_part(ctx),
             12 => llm_prompt::exec(ctx).await,
+            13 => query::exec_lookup(ctx).await,
             _ => unreachable!(),
         };
 
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::cmp::Ordering;
+use std::{cmp::Ordering, sync::Arc};
 
 use crate::scripts::{into_sieve_value, to_store_value};
 use sieve::{runtime::Variable, FunctionMap};
@@ -16,6 +16,10 @@ pub fn register(plugin_id: u32, fnc_map: &mut FunctionMap) {
     fnc_map.set_external_function("query", plugin_id, 3);
 }
 
+pub fn register_lookup(plugin_id: u32, fnc_map: &mut FunctionMap) {
+    fnc_map.set_external_function("lookup_query", plugin_id, 2);
+}
+
 pub async fn exec(ctx: PluginContext<'_>) -> trc::Result<Variable> {
     // Obtain store name
     let store = match &ctx.arguments[0] {
@@ -48,39 +52,10 @@ pub async fn exec(ctx: PluginContext<'_>) -> trc::Result<Variable> {
         .get(..6)
         .is_some_and(|q| q.eq_ignore_ascii_case(b"SELECT"))
     {
-        let mut rows = store.sql_query::<Rows>(&query, arguments).await?;
-        Ok(match rows.rows.len().cmp(&1) {
-            Ordering::Equal => {
-                let mut row = rows.rows.pop().unwrap().values;
-                match row.len().cmp(&1) {
-                    Ordering::Equal if !matches!(row.first(), Some(Value::Null)) => {
-                        row.pop().map(into_sieve_value).unwrap()
-                    }
-                    Ordering::Less => Variable::default(),
-                    _ => Variable::Array(
-                        row.into_iter()
-                            .map(into_sieve_value)
-                            .collect::<Vec<_>>()
-                            .into(),
-                    ),
-                }
-            }
-            Ordering::Less => Variable::default(),
-            Ordering::Greater => rows
-                .rows
-                .into_iter()
-                .map(|r| {
-                    Variable::Array(
-                        r.values
-                            .into_iter()
-                            .map(into_sieve_value)
-                            .collect::<Vec<_>>()
-                            .into(),
-                    )
-                })
-                .collect::<Vec<_>>()
-                .into(),
-        })
+        store
+            .sql_query::<Rows>(&query, arguments)
+            .await
+            .map(rows_into_sieve_value)
     } else {
         Ok(store
             .sql_query::<usize>(&query, arguments)
@@ -89,3 +64,50 @@ pub async fn exec(ctx: PluginContext<'_>) -> trc::Result<Variable> {
             .into())
     }
 }
+
+pub async fn exec_lookup(ctx: PluginContext<'_>) -> trc::Result<Variable> {
+    let arguments = match &ctx.arguments[1] {
+        Variable::Array(l) => l.iter().map(to_store_value).collect(),
+        v => vec![to_store_value(v)],
+    };
+
+    ctx.server
+        .lookup_query(ctx.arguments[0].to_string().as_ref(), arguments)
+        .await
+        .map(|rows| rows_into_sieve_value(Arc::unwrap_or_clone(rows)))
+}
+
+fn rows_into_sieve_value(mut rows: Rows) -> Variable {
+    match rows.rows.len().cmp(&1) {
+        Ordering::Equal => {
+            let mut row = rows.rows.pop().unwrap().values;
+            match row.len().cmp(&1) {
+                Ordering::Equal if !matches!(row.first(), Some(Value::Null)) => {
+                    row.pop().map(into_sieve_value).unwrap()
+                }
+                Ordering::Less => Variable::default(),
+                _ => Variable::Array(
+                    row.into_iter()
+                        .map(into_sieve_value)
+                        .collect::<Vec<_>>()
+                        .into(),
+                ),
+            }
+        }
+        Ordering::Less => Variable::default(),
+        Ordering::Greater => rows
+            .rows
+            .into_iter()
+            .map(|r| {
+                Variable::Array(
+                    r.values
+                        .into_iter()
+                        .map(into_sieve_value)
+                        .collect::<Vec<_>>()
+                        .into(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .into(),
+    }
+}
@@ -6,4 +6,5 @@
 
 pub mod otel;
 pub mod prometheus;
+pub mod storage;
 
@@ -0,0 +1,344 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use directory::{
+    backend::internal::{manage::ManageDirectory, PrincipalField},
+    Principal, Type,
+};
+use serde::{Deserialize, Serialize};
+use store::{
+    dispatch::lookup::KeyValue,
+    write::{now, Bincode},
+    Serialize as _,
+};
+use trc::{AddContext, StoreEvent};
+
+use crate::{config::telemetry::StorageForecast, Server, KV_STORAGE_HISTORY};
+
+const DAY: u64 = 86400;
+
+#[derive(Debug, Clone, Copy)]
+pub enum StorageScope<'x> {
+    Account(u32),
+    Domain(&'x str),
+    Server,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StorageHistory {
+    pub limit: Option<u64>,
+    pub samples: Vec<StorageSample>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StorageSample {
+    pub date: u64,
+    pub used: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageTrend {
+    pub used: u64,
+    pub limit: Option<u64>,
+    pub daily_growth: Option<i64>,
+    pub days_until_full: Option<u64>,
+    pub full_at: Option<u64>,
+    pub history: Vec<StorageSample>,
+}
+
+impl Server {
+    /// Samples the storage used by every account, domain and the whole server,
+    /// alerting about those projected to reach their limit soon.
+    pub async fn record_storage_usage(&self) -> trc::Result<()> {
+        let Some(config) = &self.core.metrics.storage_forecast else {
+            return Ok(());
+        };
+        let principals = self
+            .store()
+            .list_principals(
+                None,
+                None,
+                &[Type::Individual, Type::Group],
+                &[
+                    PrincipalField::Name,
+                    PrincipalField::Quota,
+                    PrincipalField::Emails,
+                ],
+                0,
+                0,
+            )
+            .await
+            .caused_by(trc::location!())?
+            .items;
+
+        // Domains are limited by the sum of their account quotas, as long as
+        // none of their accounts is unlimited
+        let mut domains: AHashMap<String, (u64, Option<u64>)> = AHashMap::new();
+        let mut total = 0;
+        for principal in principals {
+            let account_id = principal.id();
+            let used = match self.get_used_quota(account_id).await {
+                Ok(used) => used.max(0) as u64,
+                Err(err) => {
+                    trc::error!(err
+                        .account_id(account_id)
+                        .details("Failed to obtain used quota")
+                        .caused_by(trc::location!()));
+                    continue;
+                }
+            };
+            let quota = Some(principal.quota()).filter(|quota| *quota > 0);
+            total += used;
+
+            self.update_storage_history(
+                config,
+                StorageScope::Account(account_id),
+                principal.name(),
+                used,
+                quota,
+            )
+            .await;
+
+            if let Some(domain) = principal_domain(&principal) {
+                let (domain_used, domain_quota) = domains.entry(domain).or_insert((0, Some(0)));
+                *domain_used += used;
+                *domain_quota = domain_quota.zip(quota).map(|(a, b)| a + b);
+            }
+        }
+
+        for (domain, (used, quota)) in &domains {
+            self.update_storage_history(
+                config,
+                StorageScope::Domain(domain),
+                domain,
+                *used,
+                *quota,
+            )
+            .await;
+        }
+        self.update_storage_history(
+            config,
+            StorageScope::Server,
+            "server",
+            total,
+            config.capacity,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    pub async fn storage_history(&self, scope: StorageScope<'_>) -> trc::Result<StorageHistory> {
+        self.in_memory_store()
+            .key_get::<Bincode<StorageHistory>>(KeyValue::<()>::build_key(
+                KV_STORAGE_HISTORY,
+                scope.key(),
+            ))
+            .await
+            .map(|history| history.map(|history| history.inner).unwrap_or_default())
+            .caused_by(trc::location!())
+    }
+
+    pub async fn storage_trend(
+        &self,
+        scope: StorageScope<'_>,
+    ) -> trc::Result<Option<StorageTrend>> {
+        match &self.core.metrics.storage_forecast {
+            Some(config) => self
+                .storage_history(scope)
+                .await
+                .map(|history| history.trend(config.min_samples)),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_storage_history(
+        &self,
+        config: &StorageForecast,
+        scope: StorageScope<'_>,
+        name: &str,
+        used: u64,
+        limit: Option<u64>,
+    ) {
+        let mut history = match self.storage_history(scope).await {
+            Ok(history) => history,
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to obtain storage history")
+                    .ctx(trc::Key::Id, name.to_string()));
+                return;
+            }
+        };
+
+        // Keep a single sample per day, replacing today's if it was already taken
+        let today = now() / DAY * DAY;
+        history.samples.retain(|sample| sample.date < today);
+        history.samples.push(StorageSample { date: today, used });
+        if history.samples.len() > config.history {
+            history
+                .samples
+                .drain(..history.samples.len() - config.history);
+        }
+        history.limit = limit;
+
+        if let Some(trend) = history.trend(config.min_samples).filter(|trend| {
+            trend
+                .days_until_full
+                .is_some_and(|days| days * DAY <= config.alert_threshold.as_secs())
+        }) {
+            trc::event!(
+                Store(StoreEvent::CapacityForecast),
+                Type = scope.as_str(),
+                Id = name.to_string(),
+                Size = used,
+                Limit = limit,
+                Total = trend.days_until_full,
+                Expires = trend.full_at.map(trc::Value::Timestamp),
+            );
+        }
+
+        if let Err(err) = self
+            .in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_STORAGE_HISTORY,
+                    scope.key(),
+                    Bincode::new(history).serialize(),
+                )
+                .expires(config.history as u64 * DAY),
+            )
+            .await
+        {
+            trc::error!(err
+                .details("Failed to store storage history")
+                .ctx(trc::Key::Id, name.to_string())
+                .caused_by(trc::location!()));
+        }
+    }
+}
+
+impl StorageScope<'_> {
+    fn key(&self) -> Vec<u8> {
+        match self {
+            StorageScope::Account(account_id) => {
+                [0u8].into_iter().chain(account_id.to_be_bytes()).collect()
+            }
+            StorageScope::Domain(domain) => [1u8]
+                .into_iter()
+                .chain(domain.to_lowercase().into_bytes())
+                .collect(),
+            StorageScope::Server => vec![2u8],
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageScope::Account(_) => "account",
+            StorageScope::Domain(_) => "domain",
+            StorageScope::Server => "server",
+        }
+    }
+}
+
+impl StorageHistory {
+    /// Fits a linear trend to the daily samples and projects when usage
+    /// will reach the limit, returning `None` when there are no samples.
+    pub fn trend(&self, min_samples: usize) -> Option<StorageTrend> {
+        let last = *self.samples.last()?;
+        let daily_growth = (self.samples.len() >= min_samples)
+            .then(|| {
+                let n = self.samples.len() as f64;
+                let (sum_x, sum_y) = self.samples.iter().fold((0.0, 0.0), |(x, y), sample| {
+                    (x + (sample.date / DAY) as f64, y + sample.used as f64)
+                });
+                let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+                let (cov, var) = self.samples.iter().fold((0.0, 0.0), |(cov, var), sample| {
+                    let dx = (sample.date / DAY) as f64 - mean_x;
+                    (cov + dx * (sample.used as f64 - mean_y), var + dx * dx)
+                });
+                (var > 0.0).then(|| (cov / var).round() as i64)
+            })
+            .flatten();
+        let days_until_full = self.limit.and_then(|limit| {
+            if last.used >= limit {
+                Some(0)
+            } else {
+                daily_growth
+                    .filter(|growth| *growth > 0)
+                    .map(|growth| (limit - last.used).div_ceil(growth as u64))
+            }
+        });
+
+        Some(StorageTrend {
+            used: last.used,
+            limit: self.limit,
+            daily_growth,
+            days_until_full,
+            full_at: days_until_full.map(|days| last.date + days * DAY),
+            history: self.samples.clone(),
+        })
+    }
+}
+
+fn principal_domain(principal: &Principal) -> Option<String> {
+    principal
+        .get_str_array(PrincipalField::Emails)
+        .and_then(|emails| emails.first())
+        .map(|email| email.as_str())
+        .or(Some(principal.name()))
+        .and_then(|address| address.rsplit_once('@'))
+        .map(|(_, domain)| domain.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StorageHistory, StorageSample, DAY};
+
+    #[test]
+    fn storage_trend() {
+        let history = |used: &[u64], limit: Option<u64>| StorageHistory {
+            limit,
+            samples: used
+                .iter()
+                .enumerate()
+                .map(|(day, used)| StorageSample {
+                    date: (day as u64 + 100) * DAY,
+                    used: *used,
+                })
+                .collect(),
+        };
+
+        // Steady growth of 100 bytes per day
+        let trend = history(&[1000, 1100, 1200, 1300], Some(2000))
+            .trend(3)
+            .unwrap();
+        assert_eq!(trend.used, 1300);
+        assert_eq!(trend.daily_growth, Some(100));
+        assert_eq!(trend.days_until_full, Some(7));
+        assert_eq!(trend.full_at, Some(110 * DAY));
+
+        // Not enough samples to estimate a trend
+        let trend = history(&[1000, 1100], Some(2000)).trend(3).unwrap();
+        assert_eq!(trend.daily_growth, None);
+        assert_eq!(trend.days_until_full, None);
+
+        // Shrinking usage never reaches the limit
+        let trend = history(&[1300, 1200, 1100], Some(2000)).trend(3).unwrap();
+        assert_eq!(trend.daily_growth, Some(-100));
+        assert_eq!(trend.days_until_full, None);
+
+        // Usage over the limit is already full
+        let trend = history(&[1000, 2500], Some(2000)).trend(3).unwrap();
+        assert_eq!(trend.days_until_full, Some(0));
+
+        // Unlimited usage has no exhaustion date
+        let trend = history(&[1000, 1100, 1200], None).trend(3).unwrap();
+        assert_eq!(trend.days_until_full, None);
+        assert!(history(&[], Some(2000)).trend(3).is_none());
+    }
+}
@@ -112,6 +112,10 @@ impl TelemetrySubscriberType {
             TelemetrySubscriberType::JournalTracer(subscriber) => {
                 tracers::journald::spawn_journald_tracer(builder, subscriber)
             }
+            #[cfg(windows)]
+            TelemetrySubscriberType::EventLogTracer(subscriber) => {
+                tracers::eventlog::spawn_eventlog_tracer(builder, subscriber)
+            }
         }
     }
 }
@@ -0,0 +1,105 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fmt::Write, io, ptr};
+
+use ahash::AHashSet;
+use trc::{Event, EventDetails, Level, TelemetryEvent, ipc::subscriber::SubscriberBuilder};
+use windows_sys::Win32::{
+    Foundation::HANDLE,
+    System::EventLog::{
+        DeregisterEventSource, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE,
+        EVENTLOG_WARNING_TYPE, REPORT_EVENT_TYPE, RegisterEventSourceW, ReportEventW,
+    },
+};
+
+pub const DEFAULT_SOURCE_NAME: &str = "Stalwart Mail Server";
+
+pub(crate) fn spawn_eventlog_tracer(builder: SubscriberBuilder, subscriber: Subscriber) {
+    let (_, mut rx) = builder.register();
+    tokio::spawn(async move {
+        while let Some(events) = rx.recv().await {
+            for event in events {
+                subscriber.send_event(&event);
+            }
+        }
+    });
+}
+
+#[derive(Debug)]
+pub struct Subscriber {
+    handle: HANDLE,
+}
+
+// SAFETY: Event source handles can be used concurrently from any thread.
+unsafe impl Send for Subscriber {}
+unsafe impl Sync for Subscriber {}
+
+impl Subscriber {
+    pub fn new(source_name: &str) -> io::Result<Self> {
+        let source_name = to_wide(source_name);
+        let handle = unsafe { RegisterEventSourceW(ptr::null(), source_name.as_ptr()) };
+
+        if !handle.is_null() {
+            Ok(Subscriber { handle })
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    fn send_event(&self, event: &Event<EventDetails>) {
+        let event_type: REPORT_EVENT_TYPE = match event.inner.level {
+            Level::Error => EVENTLOG_ERROR_TYPE,
+            Level::Warn => EVENTLOG_WARNING_TYPE,
+            _ => EVENTLOG_INFORMATION_TYPE,
+        };
+
+        let mut message = String::with_capacity(256);
+        let _ = write!(message, "{}", event.inner.typ.description());
+        let mut seen_keys = AHashSet::new();
+        for (key, value) in &event.keys {
+            if seen_keys.insert(*key) {
+                let _ = write!(message, "\r\n{}: {value}", key.name());
+            }
+        }
+
+        let message = to_wide(&message);
+        let strings = [message.as_ptr()];
+
+        if unsafe {
+            ReportEventW(
+                self.handle,
+                event_type,
+                0,
+                event.inner.typ.id() as u32,
+                ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                ptr::null(),
+            )
+        } == 0
+        {
+            trc::event!(
+                Telemetry(TelemetryEvent::EventLogError),
+                Details = "Failed to write event to the Windows event log",
+                Reason = io::Error::last_os_error().to_string()
+            );
+        }
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        unsafe {
+            DeregisterEventSource(self.handle);
+        }
+    }
+}
+
+fn to_wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+#[cfg(windows)]
+pub mod eventlog;
 #[cfg(unix)]
 pub mod journald;
 pub mod log;
@@ -0,0 +1,160 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use store::Store;
+use trc::AddContext;
+
+use crate::{Directory, DirectoryInner, QueryBy, Type};
+
+use super::{PrincipalField, lookup::DirectoryStore};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BookingPolicy {
+    #[default]
+    AutoAccept,
+    AutoDecline,
+    Manual,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResourceBooking {
+    pub id: u32,
+    pub name: String,
+    pub address: Option<String>,
+    pub policy: BookingPolicy,
+    pub managers: Vec<String>,
+    pub max_duration: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookingDecision {
+    Accept,
+    Decline,
+    Delegate,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait ManageResourceBooking: Sync + Send {
+    /// Returns the booking settings of a room or equipment principal, only
+    /// resources with a booking policy are scheduled automatically.
+    async fn resource_booking_by_id(
+        &self,
+        resource_id: u32,
+    ) -> trc::Result<Option<ResourceBooking>>;
+}
+
+impl ManageResourceBooking for Store {
+    async fn resource_booking_by_id(
+        &self,
+        resource_id: u32,
+    ) -> trc::Result<Option<ResourceBooking>> {
+        let Some(mut principal) = self
+            .query(QueryBy::Id(resource_id), false)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|p| matches!(p.typ, Type::Resource | Type::Location))
+        else {
+            return Ok(None);
+        };
+        let Some(policy) = principal
+            .get_str(PrincipalField::BookingPolicy)
+            .and_then(BookingPolicy::parse)
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(ResourceBooking {
+            id: resource_id,
+            address: principal.take_str(PrincipalField::Emails),
+            policy,
+            managers: principal
+                .take_str_array(PrincipalField::BookingManagers)
+                .unwrap_or_default(),
+            max_duration: principal
+                .get_int(PrincipalField::BookingMaxDuration)
+                .filter(|duration| *duration > 0),
+            name: principal.take_str(PrincipalField::Name).unwrap_or_default(),
+        }))
+    }
+}
+
+impl Directory {
+    /// Resource booking is only available for principals stored in the internal directory.
+    pub async fn resource_booking(&self, resource_id: u32) -> trc::Result<Option<ResourceBooking>> {
+        match &self.store {
+            DirectoryInner::Internal(store) => store.resource_booking_by_id(resource_id).await,
+            _ => Ok(None),
+        }
+    }
+}
+
+impl ResourceBooking {
+    /// Decides how to answer an invitation given the resource availability
+    /// and the requested duration in seconds.
+    pub fn decide(&self, is_available: bool, duration: u64) -> BookingDecision {
+        if !is_available || self.max_duration.is_some_and(|max| duration > max) {
+            return BookingDecision::Decline;
+        }
+
+        match self.policy {
+            BookingPolicy::AutoAccept => BookingDecision::Accept,
+            BookingPolicy::AutoDecline => BookingDecision::Decline,
+            BookingPolicy::Manual if !self.managers.is_empty() => BookingDecision::Delegate,
+            BookingPolicy::Manual => BookingDecision::Decline,
+        }
+    }
+
+    pub fn is_manager(&self, address: &str) -> bool {
+        self.managers.iter().any(|manager| manager == address)
+    }
+}
+
+impl BookingPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto-accept" => Some(BookingPolicy::AutoAccept),
+            "auto-decline" => Some(BookingPolicy::AutoDecline),
+            "manual" => Some(BookingPolicy::Manual),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookingPolicy::AutoAccept => "auto-accept",
+            BookingPolicy::AutoDecline => "auto-decline",
+            BookingPolicy::Manual => "manual",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BookingDecision, BookingPolicy, ResourceBooking};
+
+    #[test]
+    fn booking_decisions() {
+        let mut resource = ResourceBooking {
+            id: 0,
+            name: "room-1".to_string(),
+            address: None,
+            policy: BookingPolicy::AutoAccept,
+            managers: vec![],
+            max_duration: Some(3600),
+        };
+        assert_eq!(resource.decide(true, 1800), BookingDecision::Accept);
+        assert_eq!(resource.decide(false, 1800), BookingDecision::Decline);
+        assert_eq!(resource.decide(true, 7200), BookingDecision::Decline);
+
+        resource.policy = BookingPolicy::Manual;
+        assert_eq!(resource.decide(true, 1800), BookingDecision::Decline);
+        resource.managers.push("facilities@example.org".to_string());
+        assert_eq!(resource.decide(true, 1800), BookingDecision::Delegate);
+
+        resource.policy = BookingPolicy::AutoDecline;
+        assert_eq!(resource.decide(true, 1800), BookingDecision::Decline);
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use store::Store;
+use trc::AddContext;
+
+use crate::QueryBy;
+
+use super::{PrincipalField, lookup::DirectoryStore};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Forwarding {
+    pub forward_to: Vec<String>,
+    pub keep_copy: bool,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait ManageForwarding: Sync + Send {
+    /// Returns the forwarding settings of an account, if any forwarding
+    /// addresses have been configured.
+    async fn forwarding_by_id(&self, account_id: u32) -> trc::Result<Option<Forwarding>>;
+}
+
+impl ManageForwarding for Store {
+    async fn forwarding_by_id(&self, account_id: u32) -> trc::Result<Option<Forwarding>> {
+        let Some(mut principal) = self
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(None);
+        };
+
+        Ok(principal
+            .take_str_array(PrincipalField::ForwardTo)
+            .filter(|forward_to| !forward_to.is_empty())
+            .map(|forward_to| Forwarding {
+                forward_to,
+                keep_copy: principal
+                    .get_int(PrincipalField::ForwardKeepCopy)
+                    .is_some_and(|v| v != 0),
+            }))
+    }
+}
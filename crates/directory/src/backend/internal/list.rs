@@ -0,0 +1,241 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use store::Store;
+use trc::AddContext;
+
+use crate::{Directory, DirectoryInner, QueryBy, Type};
+
+use super::{
+    PrincipalField, PrincipalUpdate, PrincipalValue,
+    lookup::DirectoryStore,
+    manage::{ManageDirectory, UpdatePrincipal},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListPostPolicy {
+    #[default]
+    Anyone,
+    Members,
+    Moderated,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailingList {
+    pub id: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub address: String,
+    pub post_policy: ListPostPolicy,
+    pub open_subscription: bool,
+    pub moderators: Vec<String>,
+    pub archive: Option<String>,
+    pub external_members: Vec<String>,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait ManageMailingList: Sync + Send {
+    /// Returns the list settings for an address, only lists with a posting
+    /// policy are managed by the list subsystem.
+    async fn mailing_list(&self, address: &str) -> trc::Result<Option<MailingList>>;
+    async fn mailing_list_by_id(&self, list_id: u32) -> trc::Result<Option<MailingList>>;
+    async fn mailing_list_subscribe(&self, list: &MailingList, address: &str) -> trc::Result<bool>;
+    async fn mailing_list_unsubscribe(
+        &self,
+        list: &MailingList,
+        address: &str,
+    ) -> trc::Result<bool>;
+}
+
+impl ManageMailingList for Store {
+    async fn mailing_list(&self, address: &str) -> trc::Result<Option<MailingList>> {
+        if let Some(list_id) = self
+            .email_to_id(address)
+            .await
+            .caused_by(trc::location!())?
+        {
+            self.mailing_list_by_id(list_id).await
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn mailing_list_by_id(&self, list_id: u32) -> trc::Result<Option<MailingList>> {
+        let Some(mut principal) = self
+            .query(QueryBy::Id(list_id), false)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|p| p.typ == Type::List)
+        else {
+            return Ok(None);
+        };
+        let Some(post_policy) = principal
+            .get_str(PrincipalField::ListPostPolicy)
+            .and_then(ListPostPolicy::parse)
+        else {
+            return Ok(None);
+        };
+        let Some(address) = principal.take_str(PrincipalField::Emails) else {
+            return Ok(None);
+        };
+
+        Ok(Some(MailingList {
+            id: list_id,
+            description: principal.take_str(PrincipalField::Description),
+            address,
+            post_policy,
+            open_subscription: principal.get_str(PrincipalField::ListSubscription) == Some("open"),
+            moderators: principal
+                .take_str_array(PrincipalField::ListModerators)
+                .unwrap_or_default(),
+            archive: principal.take_str(PrincipalField::ListArchive),
+            external_members: principal
+                .take_str_array(PrincipalField::ExternalMembers)
+                .unwrap_or_default(),
+            name: principal.take_str(PrincipalField::Name).unwrap_or_default(),
+        }))
+    }
+
+    async fn mailing_list_subscribe(&self, list: &MailingList, address: &str) -> trc::Result<bool> {
+        if list.external_members.iter().any(|member| member == address) {
+            return Ok(false);
+        }
+
+        self.update_principal(UpdatePrincipal::by_id(list.id).with_updates(vec![
+            PrincipalUpdate::add_item(
+                PrincipalField::ExternalMembers,
+                PrincipalValue::String(address.to_string()),
+            ),
+        ]))
+        .await
+        .caused_by(trc::location!())
+        .map(|_| true)
+    }
+
+    async fn mailing_list_unsubscribe(
+        &self,
+        list: &MailingList,
+        address: &str,
+    ) -> trc::Result<bool> {
+        if !list.external_members.iter().any(|member| member == address) {
+            return Ok(false);
+        }
+
+        self.update_principal(UpdatePrincipal::by_id(list.id).with_updates(vec![
+            PrincipalUpdate::remove_item(
+                PrincipalField::ExternalMembers,
+                PrincipalValue::String(address.to_string()),
+            ),
+        ]))
+        .await
+        .caused_by(trc::location!())
+        .map(|_| true)
+    }
+}
+
+impl Directory {
+    /// Mailing lists are only managed when they are stored in the internal directory.
+    pub async fn mailing_list(&self, address: &str) -> trc::Result<Option<MailingList>> {
+        match &self.store {
+            DirectoryInner::Internal(store) => store.mailing_list(address).await,
+            _ => Ok(None),
+        }
+    }
+}
+
+impl MailingList {
+    pub fn is_moderator(&self, address: &str) -> bool {
+        self.moderators.iter().any(|moderator| moderator == address)
+    }
+
+    /// List identifier as defined in RFC 2919.
+    pub fn list_id(&self) -> String {
+        self.address.replace('@', ".")
+    }
+
+    /// Address used to issue list commands, such as `list-unsubscribe@domain`.
+    pub fn command_address(&self, command: &str) -> String {
+        let (local, domain) = self
+            .address
+            .rsplit_once('@')
+            .unwrap_or((self.address.as_str(), ""));
+        format!("{local}-{command}@{domain}")
+    }
+}
+
+impl ListPostPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "anyone" => Some(ListPostPolicy::Anyone),
+            "members" => Some(ListPostPolicy::Members),
+            "moderated" => Some(ListPostPolicy::Moderated),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListPostPolicy::Anyone => "anyone",
+            ListPostPolicy::Members => "members",
+            ListPostPolicy::Moderated => "moderated",
+        }
+    }
+}
+
+/// Splits a list command address such as `list-subscribe@domain` into the
+/// list address and the command.
+pub fn parse_list_command(address: &str) -> Option<(String, ListCommand)> {
+    let (local, domain) = address.rsplit_once('@')?;
+    [
+        ("-subscribe", ListCommand::Subscribe),
+        ("-unsubscribe", ListCommand::Unsubscribe),
+    ]
+    .into_iter()
+    .find_map(|(suffix, command)| {
+        local
+            .strip_suffix(suffix)
+            .filter(|local| !local.is_empty())
+            .map(|local| (format!("{local}@{domain}"), command))
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListCommand {
+    Subscribe,
+    Unsubscribe,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListCommand, parse_list_command};
+
+    #[test]
+    fn list_commands() {
+        for (address, expected) in [
+            (
+                "dev-subscribe@example.org",
+                Some(("dev@example.org", ListCommand::Subscribe)),
+            ),
+            (
+                "dev-unsubscribe@example.org",
+                Some(("dev@example.org", ListCommand::Unsubscribe)),
+            ),
+            (
+                "rust-dev-subscribe@example.org",
+                Some(("rust-dev@example.org", ListCommand::Subscribe)),
+            ),
+            ("-subscribe@example.org", None),
+            ("dev@example.org", None),
+            ("dev-subscribe", None),
+        ] {
+            assert_eq!(
+                parse_list_command(address),
+                expected.map(|(list, command)| (list.to_string(), command)),
+                "failed for {address}"
+            );
+        }
+    }
+}
@@ -14,16 +14,16 @@ use store::{
     },
 };
 use trc::AddContext;
-use utils::sanitize_email;
+use utils::{sanitize_email, sanitize_language};
 
 use crate::{
-    MAX_TYPE_ID, Permission, Permissions, Principal, QueryBy, ROLE_ADMIN, ROLE_TENANT_ADMIN,
-    ROLE_USER, Type, backend::RcptType,
+    Permission, Permissions, Principal, QueryBy, ROLE_ADMIN, ROLE_TENANT_ADMIN, ROLE_USER,
+    TENANT_QUOTA_OUTBOUND, Type, backend::RcptType, tenant_quota_principals,
 };
 
 use super::{
     PrincipalAction, PrincipalField, PrincipalInfo, PrincipalUpdate, PrincipalValue,
-    SpecialSecrets, lookup::DirectoryStore,
+    SpecialSecrets, booking::BookingPolicy, list::ListPostPolicy, lookup::DirectoryStore,
 };
 
 pub struct MemberOf {
@@ -234,6 +234,43 @@ impl ManageDirectory for Store {
 
         principal.set(PrincipalField::Name, name);
 
+        // Assign the principal to a tenant
+        if let Some(tenant_name) = principal.take_str(PrincipalField::Tenant) {
+            if tenant_id.is_none() {
+                tenant_id = self
+                    .get_principal_info(&tenant_name)
+                    .await
+                    .caused_by(trc::location!())?
+                    .filter(|v| v.typ == Type::Tenant)
+                    .ok_or_else(|| not_found(tenant_name))?
+                    .id
+                    .into();
+            }
+        }
+        if let Some(tenant_id) = tenant_id.filter(|_| principal.typ != Type::Tenant) {
+            // Enforce the tenant's principal limits
+            let max_principals = self
+                .get_principal(tenant_id)
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| not_found(tenant_id.to_string()))?
+                .tenant_quota(tenant_quota_principals(principal.typ));
+            if max_principals > 0
+                && self
+                    .count_principals(None, principal.typ.into(), tenant_id.into())
+                    .await
+                    .caused_by(trc::location!())?
+                    >= max_principals
+            {
+                return Err(trc::LimitEvent::TenantQuota
+                    .into_err()
+                    .details("Tenant principal limit reached")
+                    .ctx(trc::Key::Limit, max_principals));
+            }
+
+            principal.set(PrincipalField::Tenant, tenant_id as u64);
+        }
+
         // Map member names
         let mut members = Vec::new();
         let mut member_of = Vec::new();
@@ -323,6 +360,17 @@ impl ManageDirectory for Store {
             }
         }
 
+        // Normalize locale
+        if let Some(locale) = principal.take_str(PrincipalField::Locale) {
+            let locale = sanitize_language(&locale).ok_or_else(|| {
+                error(
+                    "Invalid locale",
+                    format!("Invalid value {:?} for locale", locale).into(),
+                )
+            })?;
+            principal.set(PrincipalField::Locale, locale);
+        }
+
         // Make sure the e-mail is not taken and validate domain
         if principal.typ != Type::OauthClient {
             for email in principal.iter_mut_str(PrincipalField::Emails) {
@@ -442,6 +490,15 @@ impl ManageDirectory for Store {
             .await
             .caused_by(trc::location!())?
             .ok_or_else(|| not_found(principal_id.to_string()))?;
+
+        // Accounts on legal hold must keep their data
+        if principal.has_field(PrincipalField::LegalHold) {
+            return Err(error(
+                "Account is on legal hold",
+                Some("The legal hold must be lifted before deleting this account"),
+            ));
+        }
+
         let mut batch = BatchBuilder::new();
 
         // Unlink all principal's blobs
@@ -772,6 +829,19 @@ impl ManageDirectory for Store {
                         principal.inner.remove(change.field);
                     }
                 }
+                (PrincipalAction::Set, PrincipalField::Locale, PrincipalValue::String(value)) => {
+                    if value.is_empty() {
+                        principal.inner.remove(change.field);
+                    } else if let Some(locale) = sanitize_language(&value) {
+                        principal.inner.set(change.field, locale);
+                    } else {
+                        return Err(error(
+                            "Invalid locale",
+                            format!("Invalid value {:?} for {}", value, change.field.as_str())
+                                .into(),
+                        ));
+                    }
+                }
                 (PrincipalAction::Set, PrincipalField::Quota, PrincipalValue::Integer(quota))
                     if matches!(
                         principal_type,
@@ -798,7 +868,7 @@ impl ManageDirectory for Store {
                     PrincipalField::Quota,
                     PrincipalValue::IntegerList(quotas),
                 ) if matches!(principal_type, Type::Tenant)
-                    && quotas.len() <= (MAX_TYPE_ID + 2) =>
+                    && quotas.len() <= (TENANT_QUOTA_OUTBOUND + 1) =>
                 {
                     principal.inner.set(PrincipalField::Quota, quotas);
                 }
@@ -1343,10 +1413,20 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::Set,
-                    PrincipalField::Urls | PrincipalField::ExternalMembers,
+                    PrincipalField::Urls
+                    | PrincipalField::ExternalMembers
+                    | PrincipalField::ListModerators
+                    | PrincipalField::ForwardTo
+                    | PrincipalField::BookingManagers,
                     PrincipalValue::StringList(mut items),
                 ) => {
-                    if matches!(change.field, PrincipalField::ExternalMembers) {
+                    if matches!(
+                        change.field,
+                        PrincipalField::ExternalMembers
+                            | PrincipalField::ListModerators
+                            | PrincipalField::ForwardTo
+                            | PrincipalField::BookingManagers
+                    ) {
                         items = items
                             .into_iter()
                             .map(|item| {
@@ -1373,10 +1453,20 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::AddItem,
-                    PrincipalField::Urls | PrincipalField::ExternalMembers,
+                    PrincipalField::Urls
+                    | PrincipalField::ExternalMembers
+                    | PrincipalField::ListModerators
+                    | PrincipalField::ForwardTo
+                    | PrincipalField::BookingManagers,
                     PrincipalValue::String(mut item),
                 ) => {
-                    if matches!(change.field, PrincipalField::ExternalMembers) {
+                    if matches!(
+                        change.field,
+                        PrincipalField::ExternalMembers
+                            | PrincipalField::ListModerators
+                            | PrincipalField::ForwardTo
+                            | PrincipalField::BookingManagers
+                    ) {
                         item = sanitize_email(&item).ok_or_else(|| {
                             error(
                                 "Invalid email address",
@@ -1392,13 +1482,115 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::RemoveItem,
-                    PrincipalField::Urls | PrincipalField::ExternalMembers,
+                    PrincipalField::Urls
+                    | PrincipalField::ExternalMembers
+                    | PrincipalField::ListModerators
+                    | PrincipalField::ForwardTo
+                    | PrincipalField::BookingManagers,
                     PrincipalValue::String(item),
                 ) => {
                     if principal.inner.has_str_value(change.field, &item) {
                         principal.inner.retain_str(change.field, |v| *v != item);
                     }
                 }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::ListPostPolicy
+                    | PrincipalField::ListSubscription
+                    | PrincipalField::ListArchive,
+                    PrincipalValue::String(value),
+                ) if principal_type == Type::List => {
+                    let value = match change.field {
+                        PrincipalField::ListPostPolicy
+                            if !value.is_empty() && ListPostPolicy::parse(&value).is_none() =>
+                        {
+                            return Err(error(
+                                "Invalid list posting policy",
+                                format!("Invalid value {:?} for {}", value, change.field.as_str())
+                                    .into(),
+                            ));
+                        }
+                        PrincipalField::ListSubscription
+                            if !value.is_empty() && !matches!(value.as_str(), "open" | "closed") =>
+                        {
+                            return Err(error(
+                                "Invalid list subscription policy",
+                                format!("Invalid value {:?} for {}", value, change.field.as_str())
+                                    .into(),
+                            ));
+                        }
+                        PrincipalField::ListArchive if !value.is_empty() => {
+                            sanitize_email(&value).ok_or_else(|| {
+                                error(
+                                    "Invalid email address",
+                                    format!(
+                                        "Invalid value {:?} for {}",
+                                        value,
+                                        change.field.as_str()
+                                    )
+                                    .into(),
+                                )
+                            })?
+                        }
+                        _ => value,
+                    };
+
+                    if !value.is_empty() {
+                        principal.inner.set(change.field, value);
+                    } else {
+                        principal.inner.remove(change.field);
+                    }
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::BookingPolicy,
+                    PrincipalValue::String(value),
+                ) if matches!(principal_type, Type::Resource | Type::Location) => {
+                    if value.is_empty() {
+                        principal.inner.remove(change.field);
+                    } else if BookingPolicy::parse(&value).is_some() {
+                        principal.inner.set(change.field, value);
+                    } else {
+                        return Err(error(
+                            "Invalid booking policy",
+                            format!("Invalid value {:?} for {}", value, change.field.as_str())
+                                .into(),
+                        ));
+                    }
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::BookingMaxDuration,
+                    PrincipalValue::Integer(value),
+                ) if matches!(principal_type, Type::Resource | Type::Location) => {
+                    if value != 0 {
+                        principal.inner.set(change.field, value);
+                    } else {
+                        principal.inner.remove(change.field);
+                    }
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::ForwardKeepCopy,
+                    PrincipalValue::Integer(value),
+                ) => {
+                    if value != 0 {
+                        principal.inner.set(PrincipalField::ForwardKeepCopy, 1u64);
+                    } else {
+                        principal.inner.remove(PrincipalField::ForwardKeepCopy);
+                    }
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::LegalHold,
+                    PrincipalValue::Integer(value),
+                ) if matches!(principal_type, Type::Individual | Type::Group) => {
+                    if value != 0 {
+                        principal.inner.set(PrincipalField::LegalHold, 1u64);
+                    } else {
+                        principal.inner.remove(PrincipalField::LegalHold);
+                    }
+                }
 
                 (_, field, value) => {
                     return Err(error(
@@ -1665,6 +1857,21 @@ impl ManageDirectory for Store {
             }
         }
 
+        // Map tenant
+        if let Some(tenant_id) = principal
+            .tenant()
+            .filter(|_| fields.is_empty() || fields.contains(&PrincipalField::Tenant))
+        {
+            if let Some(name) = self
+                .get_principal(tenant_id)
+                .await
+                .caused_by(trc::location!())?
+                .and_then(|mut p| p.take_str(PrincipalField::Name))
+            {
+                principal.set(PrincipalField::Tenant, name);
+            }
+        }
+
         // Obtain member names
         if fields.is_empty() || fields.contains(&PrincipalField::Members) {
             match principal.typ {
@@ -4,6 +4,9 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod booking;
+pub mod forward;
+pub mod list;
 pub mod lookup;
 pub mod manage;
 
@@ -99,8 +102,10 @@ impl Deserialize for Principal {
 }
 
 impl PrincipalInfo {
-    pub fn has_tenant_access(&self, _tenant_id: Option<u32>) -> bool {
-        true
+    pub fn has_tenant_access(&self, tenant_id: Option<u32>) -> bool {
+        tenant_id.is_none_or(|tenant_id| {
+            self.tenant == Some(tenant_id) || (self.typ == Type::Tenant && self.id == tenant_id)
+        })
     }
 }
 
@@ -230,62 +235,73 @@ fn deserialize(bytes: &[u8]) -> Option<Principal> {
 }
 
 pub trait MigrateDirectory: Sync + Send {
-    fn migrate_directory(&self) -> impl std::future::Future<Output = trc::Result<()>> + Send;
+    fn needs_directory_migration(
+        &self,
+    ) -> impl std::future::Future<Output = trc::Result<bool>> + Send;
+
+    fn migrate_directory(
+        &self,
+        progress: impl Fn(u64, u64) + Sync + Send,
+    ) -> impl std::future::Future<Output = trc::Result<()>> + Send;
 }
 
 impl MigrateDirectory for Store {
-    async fn migrate_directory(&self) -> trc::Result<()> {
+    async fn needs_directory_migration(&self) -> trc::Result<bool> {
+        let mut needs_migration = false;
+
+        self.iterate(legacy_directory_range(), |key, value| {
+            needs_migration = matches!(
+                (key.first(), value.first()),
+                (Some(2), Some(1)) | (Some(3), _)
+            );
+
+            Ok(!needs_migration)
+        })
+        .await
+        .caused_by(trc::location!())
+        .map(|_| needs_migration)
+    }
+
+    async fn migrate_directory(
+        &self,
+        progress: impl Fn(u64, u64) + Sync + Send,
+    ) -> trc::Result<()> {
         let mut principals = Vec::new();
         let mut domains = Vec::new();
 
-        self.iterate(
-            IterateParams::new(
-                ValueKey {
-                    account_id: 0,
-                    collection: 0,
-                    document_id: 0,
-                    class: ValueClass::Directory(DirectoryClass::Principal(0)),
-                },
-                ValueKey {
-                    account_id: u32::MAX,
-                    collection: u8::MAX,
-                    document_id: u32::MAX,
-                    class: ValueClass::Any(AnyClass {
-                        subspace: SUBSPACE_DIRECTORY,
-                        key: vec![4u8],
-                    }),
-                },
-            ),
-            |key, value| {
-                match (key.first(), value.first()) {
-                    (Some(2), Some(1)) => {
-                        principals.push((
-                            key.get(1..)
-                                .and_then(|b| b.read_leb128::<u32>().map(|(v, _)| v))
-                                .ok_or_else(|| {
-                                    trc::StoreEvent::DataCorruption
-                                        .caused_by(trc::location!())
-                                        .ctx(trc::Key::Value, key)
-                                })?,
-                            Principal::deserialize(value)?,
-                        ));
-                    }
-                    (Some(3), _) => {
-                        let domain = std::str::from_utf8(&key[1..]).unwrap_or_default();
-                        if !domain.is_empty() {
-                            domains.push(domain.to_string());
-                        }
+        self.iterate(legacy_directory_range(), |key, value| {
+            match (key.first(), value.first()) {
+                (Some(2), Some(1)) => {
+                    principals.push((
+                        key.get(1..)
+                            .and_then(|b| b.read_leb128::<u32>().map(|(v, _)| v))
+                            .ok_or_else(|| {
+                                trc::StoreEvent::DataCorruption
+                                    .caused_by(trc::location!())
+                                    .ctx(trc::Key::Value, key)
+                            })?,
+                        Principal::deserialize(value)?,
+                    ));
+                }
+                (Some(3), _) => {
+                    let domain = std::str::from_utf8(&key[1..]).unwrap_or_default();
+                    if !domain.is_empty() {
+                        domains.push(domain.to_string());
                     }
-                    _ => {}
                 }
+                _ => {}
+            }
 
-                Ok(true)
-            },
-        )
+            Ok(true)
+        })
         .await
         .caused_by(trc::location!())?;
 
         let total_principal_count = principals.len();
+        let total = (principals.len() + domains.len()) as u64;
+        let mut processed = 0;
+        progress(processed, total);
+
         for (account_id, mut principal) in principals {
             let role = principal.take_int(PrincipalField::Roles).unwrap() as u32;
 
@@ -321,6 +337,9 @@ impl MigrateDirectory for Store {
             self.write(batch.build())
                 .await
                 .caused_by(trc::location!())?;
+
+            processed += 1;
+            progress(processed, total);
         }
 
         let total_domain_count = domains.len();
@@ -360,6 +379,9 @@ impl MigrateDirectory for Store {
                         .ctx(trc::Key::Domain, domain)
                 );
             }
+
+            processed += 1;
+            progress(processed, total);
         }
 
         if total_domain_count > 0 || total_principal_count > 0 {
@@ -375,6 +397,27 @@ impl MigrateDirectory for Store {
     }
 }
 
+// Principals and domains stored using the pre-0.11 directory layout
+fn legacy_directory_range() -> IterateParams<ValueKey<ValueClass<u32>>> {
+    IterateParams::new(
+        ValueKey {
+            account_id: 0,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Directory(DirectoryClass::Principal(0)),
+        },
+        ValueKey {
+            account_id: u32::MAX,
+            collection: u8::MAX,
+            document_id: u32::MAX,
+            class: ValueClass::Any(AnyClass {
+                subspace: SUBSPACE_DIRECTORY,
+                key: vec![4u8],
+            }),
+        },
+    )
+}
+
 #[derive(
     Debug, Clone, Copy, PartialEq, Hash, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
 )]
@@ -397,6 +440,17 @@ pub enum PrincipalField {
     Picture,
     Urls,
     ExternalMembers,
+    ListPostPolicy,
+    ListModerators,
+    ListArchive,
+    ListSubscription,
+    ForwardTo,
+    ForwardKeepCopy,
+    BookingPolicy,
+    BookingManagers,
+    BookingMaxDuration,
+    LegalHold,
+    Locale,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -477,6 +531,17 @@ impl PrincipalField {
             PrincipalField::Picture => 14,
             PrincipalField::Urls => 15,
             PrincipalField::ExternalMembers => 16,
+            PrincipalField::ListPostPolicy => 17,
+            PrincipalField::ListModerators => 18,
+            PrincipalField::ListArchive => 19,
+            PrincipalField::ListSubscription => 20,
+            PrincipalField::ForwardTo => 21,
+            PrincipalField::ForwardKeepCopy => 22,
+            PrincipalField::BookingPolicy => 23,
+            PrincipalField::BookingManagers => 24,
+            PrincipalField::BookingMaxDuration => 25,
+            PrincipalField::LegalHold => 26,
+            PrincipalField::Locale => 27,
         }
     }
 
@@ -499,6 +564,17 @@ impl PrincipalField {
             14 => Some(PrincipalField::Picture),
             15 => Some(PrincipalField::Urls),
             16 => Some(PrincipalField::ExternalMembers),
+            17 => Some(PrincipalField::ListPostPolicy),
+            18 => Some(PrincipalField::ListModerators),
+            19 => Some(PrincipalField::ListArchive),
+            20 => Some(PrincipalField::ListSubscription),
+            21 => Some(PrincipalField::ForwardTo),
+            22 => Some(PrincipalField::ForwardKeepCopy),
+            23 => Some(PrincipalField::BookingPolicy),
+            24 => Some(PrincipalField::BookingManagers),
+            25 => Some(PrincipalField::BookingMaxDuration),
+            26 => Some(PrincipalField::LegalHold),
+            27 => Some(PrincipalField::Locale),
             _ => None,
         }
     }
@@ -522,6 +598,17 @@ impl PrincipalField {
             PrincipalField::Picture => "picture",
             PrincipalField::Urls => "urls",
             PrincipalField::ExternalMembers => "externalMembers",
+            PrincipalField::ListPostPolicy => "listPostPolicy",
+            PrincipalField::ListModerators => "listModerators",
+            PrincipalField::ListArchive => "listArchive",
+            PrincipalField::ListSubscription => "listSubscription",
+            PrincipalField::ForwardTo => "forwardTo",
+            PrincipalField::ForwardKeepCopy => "forwardKeepCopy",
+            PrincipalField::BookingPolicy => "bookingPolicy",
+            PrincipalField::BookingManagers => "bookingManagers",
+            PrincipalField::BookingMaxDuration => "bookingMaxDuration",
+            PrincipalField::LegalHold => "legalHold",
+            PrincipalField::Locale => "locale",
         }
     }
 
@@ -544,6 +631,17 @@ impl PrincipalField {
             "picture" => Some(PrincipalField::Picture),
             "urls" => Some(PrincipalField::Urls),
             "externalMembers" => Some(PrincipalField::ExternalMembers),
+            "listPostPolicy" => Some(PrincipalField::ListPostPolicy),
+            "listModerators" => Some(PrincipalField::ListModerators),
+            "listArchive" => Some(PrincipalField::ListArchive),
+            "listSubscription" => Some(PrincipalField::ListSubscription),
+            "forwardTo" => Some(PrincipalField::ForwardTo),
+            "forwardKeepCopy" => Some(PrincipalField::ForwardKeepCopy),
+            "bookingPolicy" => Some(PrincipalField::BookingPolicy),
+            "bookingManagers" => Some(PrincipalField::BookingManagers),
+            "bookingMaxDuration" => Some(PrincipalField::BookingMaxDuration),
+            "legalHold" => Some(PrincipalField::LegalHold),
+            "locale" => Some(PrincipalField::Locale),
             _ => None,
         }
     }
@@ -8,6 +8,7 @@ use ldap3::{Ldap, LdapConnAsync, ResultEntry, Scope, SearchEntry};
 use mail_send::Credentials;
 use store::xxhash_rust;
 use trc::AddContext;
+use utils::sanitize_language;
 
 use crate::{
     backend::{
@@ -406,6 +407,10 @@ impl LdapMappings {
                 if let Ok(quota) = value.into_iter().next().unwrap_or_default().parse::<u64>() {
                     principal.set(PrincipalField::Quota, quota);
                 }
+            } else if self.attr_locale.contains(&attr) {
+                if let Some(locale) = value.first().and_then(|v| sanitize_language(v)) {
+                    principal.set(PrincipalField::Locale, locale);
+                }
             } else if self.attr_type.contains(&attr) {
                 for value in value {
                     match value.to_ascii_lowercase().as_str() {
@@ -5,6 +5,7 @@
  */
 
 use trc::AddContext;
+use utils::idna_alternate;
 
 use crate::{
     backend::{internal::lookup::DirectoryStore, RcptType},
@@ -30,6 +31,17 @@ impl Directory {
     }
 
     pub async fn email_to_id(&self, address: &str) -> trc::Result<Option<u32>> {
+        // Internationalized domains may be stored in either IDNA form
+        match self.store_email_to_id(address).await? {
+            None => match idna_alternate(address) {
+                Some(address) => self.store_email_to_id(&address).await,
+                None => Ok(None),
+            },
+            result => Ok(result),
+        }
+    }
+
+    async fn store_email_to_id(&self, address: &str) -> trc::Result<Option<u32>> {
         match &self.store {
             DirectoryInner::Internal(store) => store.email_to_id(address).await,
             DirectoryInner::Ldap(store) => store.email_to_id(address).await,
@@ -50,16 +62,10 @@ impl Directory {
             }
         }
 
-        let result = match &self.store {
-            DirectoryInner::Internal(store) => store.is_local_domain(domain).await,
-            DirectoryInner::Ldap(store) => store.is_local_domain(domain).await,
-            DirectoryInner::Sql(store) => store.is_local_domain(domain).await,
-            DirectoryInner::Imap(store) => store.is_local_domain(domain).await,
-            DirectoryInner::Smtp(store) => store.is_local_domain(domain).await,
-            DirectoryInner::Memory(store) => store.is_local_domain(domain).await,
-            DirectoryInner::OpenId(store) => store.is_local_domain(domain).await,
+        let mut result = self.store_is_local_domain(domain).await?;
+        if !result && let Some(domain) = idna_alternate(domain) {
+            result = self.store_is_local_domain(&domain).await?;
         }
-        .caused_by(trc::location!())?;
 
         // Update cache
         if let Some(cache) = &self.cache {
@@ -69,6 +75,19 @@ impl Directory {
         Ok(result)
     }
 
+    async fn store_is_local_domain(&self, domain: &str) -> trc::Result<bool> {
+        match &self.store {
+            DirectoryInner::Internal(store) => store.is_local_domain(domain).await,
+            DirectoryInner::Ldap(store) => store.is_local_domain(domain).await,
+            DirectoryInner::Sql(store) => store.is_local_domain(domain).await,
+            DirectoryInner::Imap(store) => store.is_local_domain(domain).await,
+            DirectoryInner::Smtp(store) => store.is_local_domain(domain).await,
+            DirectoryInner::Memory(store) => store.is_local_domain(domain).await,
+            DirectoryInner::OpenId(store) => store.is_local_domain(domain).await,
+        }
+        .caused_by(trc::location!())
+    }
+
     pub async fn rcpt(&self, email: &str) -> trc::Result<RcptType> {
         // Check cache
         if let Some(cache) = &self.cache {
@@ -77,16 +96,12 @@ impl Directory {
             }
         }
 
-        let result = match &self.store {
-            DirectoryInner::Internal(store) => store.rcpt(email).await,
-            DirectoryInner::Ldap(store) => store.rcpt(email).await,
-            DirectoryInner::Sql(store) => store.rcpt(email).await,
-            DirectoryInner::Imap(store) => store.rcpt(email).await,
-            DirectoryInner::Smtp(store) => store.rcpt(email).await,
-            DirectoryInner::Memory(store) => store.rcpt(email).await,
-            DirectoryInner::OpenId(store) => store.rcpt(email).await,
+        let mut result = self.store_rcpt(email).await?;
+        if result == RcptType::Invalid
+            && let Some(email) = idna_alternate(email)
+        {
+            result = self.store_rcpt(&email).await?;
         }
-        .caused_by(trc::location!())?;
 
         // Update cache
         if let Some(cache) = &self.cache {
@@ -96,6 +111,19 @@ impl Directory {
         Ok(result)
     }
 
+    async fn store_rcpt(&self, email: &str) -> trc::Result<RcptType> {
+        match &self.store {
+            DirectoryInner::Internal(store) => store.rcpt(email).await,
+            DirectoryInner::Ldap(store) => store.rcpt(email).await,
+            DirectoryInner::Sql(store) => store.rcpt(email).await,
+            DirectoryInner::Imap(store) => store.rcpt(email).await,
+            DirectoryInner::Smtp(store) => store.rcpt(email).await,
+            DirectoryInner::Memory(store) => store.rcpt(email).await,
+            DirectoryInner::OpenId(store) => store.rcpt(email).await,
+        }
+        .caused_by(trc::location!())
+    }
+
     pub async fn vrfy(&self, address: &str) -> trc::Result<Vec<String>> {
         match &self.store {
             DirectoryInner::Internal(store) => store.vrfy(address).await,
@@ -76,6 +76,25 @@ impl Permission {
             Permission::PurgeInMemoryStore => "Purge the in-memory storage",
             Permission::PurgeAccount => "Purge user accounts",
             Permission::FtsReindex => "Rebuild the full-text search index",
+            Permission::MailboxRoleMapping => "Map migrated folders to special-use roles",
+            Permission::PublicFolderManage => "Manage public folders and their ACLs",
+            Permission::JmapThreadQuery => "Query conversations via JMAP",
+            Permission::JmapEmailRecall => "Recall unread messages sent to local recipients",
+            Permission::LegalHoldManage => "Place accounts on legal hold and export held data",
+            Permission::EDiscovery => "Search and export messages across accounts",
+            Permission::SmimeManage => "Manage S/MIME signing and recipient certificates",
+            Permission::SessionManage => "List and terminate active client sessions",
+            Permission::IpListManage => "Manage the IP allow and block lists",
+            Permission::JmapCapabilitySubmission => "Use the JMAP submission capability",
+            Permission::JmapCapabilityVacationResponse => {
+                "Use the JMAP vacation response capability"
+            }
+            Permission::JmapCapabilitySieve => "Use the JMAP Sieve capability",
+            Permission::JmapCapabilityWebSocket => "Use the JMAP WebSocket capability",
+            Permission::JmapCapabilityBlob => "Use the JMAP blob capability",
+            Permission::ImpersonateReadOnly => "Open other users' mailboxes in read-only mode",
+            Permission::SieveScriptManage => "Manage the Sieve scripts of any account",
+            Permission::SieveScriptExport => "Export the Sieve scripts of all accounts",
             Permission::Undelete => "Restore deleted items",
             Permission::DkimSignatureCreate => "Create DKIM signatures for email authentication",
             Permission::DkimSignatureGet => "Retrieve DKIM signature information",
@@ -84,6 +103,8 @@ impl Permission {
             Permission::LogsView => "Access system logs",
             Permission::SpamFilterTrain => "Train the spam filter",
             Permission::SpamFilterClassify => "Classify emails with the spam filter",
+            Permission::ManageSenderLists => "Manage personal sender allow and block lists",
+            Permission::ManageExternalAccounts => "Manage external accounts to fetch mail from",
             Permission::Restart => "Restart the email server",
             Permission::TracingList => "View stored traces",
             Permission::TracingGet => "Retrieve specific trace information",
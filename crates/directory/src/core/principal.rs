@@ -52,7 +52,13 @@ impl Principal {
     }
 
     pub fn tenant(&self) -> Option<u32> {
-        None
+        self.get_int(PrincipalField::Tenant).map(|v| v as u32)
+    }
+
+    pub fn tenant_quota(&self, index: usize) -> u64 {
+        self.iter_int(PrincipalField::Quota)
+            .nth(index)
+            .unwrap_or_default()
     }
 
     pub fn get_str(&self, key: PrincipalField) -> Option<&str> {
@@ -365,16 +371,15 @@ impl Principal {
 
     pub fn update_external(&mut self, mut external: Principal) -> Vec<PrincipalUpdate> {
         let mut updates = Vec::new();
-        if let Some(name) = external
-            .take_str(PrincipalField::Description)
-            .filter(|s| !s.is_empty())
-        {
-            if self.get_str(PrincipalField::Description) != Some(name.as_str()) {
-                updates.push(PrincipalUpdate::set(
-                    PrincipalField::Description,
-                    PrincipalValue::String(name.clone()),
-                ));
-                self.set(PrincipalField::Description, name);
+        for field in [PrincipalField::Description, PrincipalField::Locale] {
+            if let Some(value) = external.take_str(field).filter(|s| !s.is_empty()) {
+                if self.get_str(field) != Some(value.as_str()) {
+                    updates.push(PrincipalUpdate::set(
+                        field,
+                        PrincipalValue::String(value.clone()),
+                    ));
+                    self.set(field, value);
+                }
             }
         }
 
@@ -715,6 +720,13 @@ impl<'de> serde::Deserialize<'de> for PrincipalValue {
                 Ok(PrincipalValue::Integer(value))
             }
 
+            fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(PrincipalValue::Integer(value as u64))
+            }
+
             fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
             where
                 E: de::Error,
@@ -817,7 +829,12 @@ impl<'de> serde::Deserialize<'de> for Principal {
                         }
                         PrincipalField::Description
                         | PrincipalField::Tenant
-                        | PrincipalField::Picture => {
+                        | PrincipalField::Picture
+                        | PrincipalField::ListPostPolicy
+                        | PrincipalField::ListArchive
+                        | PrincipalField::ListSubscription
+                        | PrincipalField::BookingPolicy
+                        | PrincipalField::Locale => {
                             if let Some(v) = map.next_value::<Option<String>>()? {
                                 if v.len() <= MAX_STRING_LEN {
                                     PrincipalValue::String(v)
@@ -834,7 +851,10 @@ impl<'de> serde::Deserialize<'de> for Principal {
                             })?;
                             continue;
                         }
-                        PrincipalField::Quota => map.next_value::<PrincipalValue>()?,
+                        PrincipalField::Quota
+                        | PrincipalField::ForwardKeepCopy
+                        | PrincipalField::BookingMaxDuration
+                        | PrincipalField::LegalHold => map.next_value::<PrincipalValue>()?,
                         PrincipalField::Secrets
                         | PrincipalField::Emails
                         | PrincipalField::MemberOf
@@ -844,7 +864,10 @@ impl<'de> serde::Deserialize<'de> for Principal {
                         | PrincipalField::EnabledPermissions
                         | PrincipalField::DisabledPermissions
                         | PrincipalField::Urls
-                        | PrincipalField::ExternalMembers => {
+                        | PrincipalField::ExternalMembers
+                        | PrincipalField::ListModerators
+                        | PrincipalField::ForwardTo
+                        | PrincipalField::BookingManagers => {
                             match map.next_value::<StringOrMany>()? {
                                 StringOrMany::One(v) => PrincipalValue::StringList(vec![v]),
                                 StringOrMany::Many(v) => {
@@ -1039,6 +1062,8 @@ impl Permission {
                 | Permission::JmapEmailSubmissionQuery
                 | Permission::JmapSieveScriptQuery
                 | Permission::JmapQuotaQuery
+                | Permission::JmapThreadQuery
+                | Permission::JmapEmailRecall
                 | Permission::JmapSearchSnippet
                 | Permission::JmapSieveScriptValidate
                 | Permission::JmapBlobLookup
@@ -1089,6 +1114,83 @@ impl Permission {
                 | Permission::SieveHaveSpace
                 | Permission::SpamFilterClassify
                 | Permission::SpamFilterTrain
+                | Permission::ManageSenderLists
+                | Permission::ManageExternalAccounts
+                | Permission::JmapCapabilitySubmission
+                | Permission::JmapCapabilityVacationResponse
+                | Permission::JmapCapabilitySieve
+                | Permission::JmapCapabilityWebSocket
+                | Permission::JmapCapabilityBlob
+        )
+    }
+
+    /// Permissions retained when an account is opened by an impersonating
+    /// administrator, none of them can modify the account.
+    pub const fn is_read_only_permission(&self) -> bool {
+        matches!(
+            self,
+            Permission::Authenticate
+                | Permission::JmapEmailGet
+                | Permission::JmapMailboxGet
+                | Permission::JmapThreadGet
+                | Permission::JmapIdentityGet
+                | Permission::JmapEmailSubmissionGet
+                | Permission::JmapSieveScriptGet
+                | Permission::JmapVacationResponseGet
+                | Permission::JmapQuotaGet
+                | Permission::JmapBlobGet
+                | Permission::JmapEmailChanges
+                | Permission::JmapMailboxChanges
+                | Permission::JmapThreadChanges
+                | Permission::JmapIdentityChanges
+                | Permission::JmapEmailSubmissionChanges
+                | Permission::JmapQuotaChanges
+                | Permission::JmapEmailParse
+                | Permission::JmapEmailQueryChanges
+                | Permission::JmapMailboxQueryChanges
+                | Permission::JmapEmailSubmissionQueryChanges
+                | Permission::JmapSieveScriptQueryChanges
+                | Permission::JmapQuotaQueryChanges
+                | Permission::JmapEmailQuery
+                | Permission::JmapMailboxQuery
+                | Permission::JmapEmailSubmissionQuery
+                | Permission::JmapSieveScriptQuery
+                | Permission::JmapQuotaQuery
+                | Permission::JmapThreadQuery
+                | Permission::JmapSearchSnippet
+                | Permission::JmapBlobLookup
+                | Permission::JmapEcho
+                | Permission::ImapAuthenticate
+                | Permission::ImapAclGet
+                | Permission::ImapMyRights
+                | Permission::ImapListRights
+                | Permission::ImapCapability
+                | Permission::ImapId
+                | Permission::ImapEnable
+                | Permission::ImapFetch
+                | Permission::ImapIdle
+                | Permission::ImapList
+                | Permission::ImapLsub
+                | Permission::ImapNamespace
+                | Permission::ImapSearch
+                | Permission::ImapSort
+                | Permission::ImapSelect
+                | Permission::ImapExamine
+                | Permission::ImapStatus
+                | Permission::ImapThread
+                | Permission::Pop3Authenticate
+                | Permission::Pop3List
+                | Permission::Pop3Uidl
+                | Permission::Pop3Stat
+                | Permission::Pop3Retr
+                | Permission::SieveAuthenticate
+                | Permission::SieveListScripts
+                | Permission::SieveGetScript
+                | Permission::SieveHaveSpace
+                | Permission::JmapCapabilitySubmission
+                | Permission::JmapCapabilityVacationResponse
+                | Permission::JmapCapabilitySieve
+                | Permission::JmapCapabilityBlob
         )
     }
 }
@@ -58,6 +58,15 @@ pub enum Type {
 
 pub const MAX_TYPE_ID: usize = 11;
 
+// Tenant quotas are stored as a list: total storage, followed by the maximum
+// number of principals for each type id and the daily outbound message limit.
+pub const TENANT_QUOTA_STORAGE: usize = 0;
+pub const TENANT_QUOTA_OUTBOUND: usize = MAX_TYPE_ID + 2;
+
+pub const fn tenant_quota_principals(typ: Type) -> usize {
+    typ as usize + 1
+}
+
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, EnumMethods,
 )]
@@ -266,6 +275,25 @@ pub enum Permission {
     AiModelInteract,
     Troubleshoot,
     SpamFilterClassify,
+    ManageSenderLists,
+    MailboxRoleMapping,
+    PublicFolderManage,
+    JmapThreadQuery,
+    ManageExternalAccounts,
+    JmapEmailRecall,
+    LegalHoldManage,
+    EDiscovery,
+    SmimeManage,
+    SessionManage,
+    IpListManage,
+    JmapCapabilitySubmission,
+    JmapCapabilityVacationResponse,
+    JmapCapabilitySieve,
+    JmapCapabilityWebSocket,
+    JmapCapabilityBlob,
+    ImpersonateReadOnly,
+    SieveScriptManage,
+    SieveScriptExport,
     // WARNING: add new ids at the end (TODO: use static ids)
 }
 
@@ -0,0 +1,414 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::BTreeMap;
+
+use common::config::spamfilter::SpamFilterHeaderConfig;
+use jmap_proto::types::{property::Property, value::Value};
+use mail_parser::MessageParser;
+use serde::Serialize;
+
+/// Authentication and spam filter evaluation performed at delivery time,
+/// as recorded in the headers added by this server.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthReport {
+    pub authserv_id: Option<String>,
+    pub spf: Vec<AuthMethodResult>,
+    pub dkim: Vec<AuthMethodResult>,
+    pub arc: Vec<AuthMethodResult>,
+    pub dmarc: Vec<AuthMethodResult>,
+    pub iprev: Vec<AuthMethodResult>,
+    pub arc_chain: Option<ArcChain>,
+    pub spam: Option<SpamReport>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthMethodResult {
+    pub result: String,
+    pub comment: Option<String>,
+    pub properties: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArcChain {
+    pub instances: u32,
+    pub validation: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpamReport {
+    pub is_spam: bool,
+    pub score: Option<f64>,
+    pub tags: Vec<SpamTagScore>,
+    pub exemption: Option<SpamExemption>,
+}
+
+/// Filtering relaxed at delivery time, such as for senders known to the recipient.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpamExemption {
+    pub reason: String,
+    pub action: Option<String>,
+    pub adjustment: Option<f64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpamTagScore {
+    pub name: String,
+    pub score: f64,
+}
+
+impl AuthReport {
+    pub fn parse(raw_headers: &[u8], server_name: &str, spam: &SpamFilterHeaderConfig) -> Self {
+        let mut report = AuthReport::default();
+        let Some(message) = MessageParser::new().parse_headers(raw_headers) else {
+            return report;
+        };
+
+        let mut auth_results = None;
+        let mut spam_status = None;
+        let mut spam_result = None;
+        let mut spam_exemption = None;
+        for header in message.headers() {
+            let name = header.name.as_str();
+            let value = raw_headers
+                .get(header.offset_start..header.offset_end)
+                .map(unfold)
+                .unwrap_or_default();
+
+            if name.eq_ignore_ascii_case("Authentication-Results") {
+                // Prefer the results added by this server, which are the topmost ones
+                let is_local = value
+                    .split(';')
+                    .next()
+                    .is_some_and(|id| id.trim().eq_ignore_ascii_case(server_name));
+                if auth_results.is_none() || is_local && !matches!(auth_results, Some((true, _))) {
+                    auth_results = Some((is_local, value));
+                }
+            } else if name.eq_ignore_ascii_case("ARC-Seal") {
+                let chain = report.arc_chain.get_or_insert_with(ArcChain::default);
+                chain.instances += 1;
+                if chain.validation.is_none() {
+                    chain.validation = value.split(';').find_map(|tag| {
+                        tag.trim()
+                            .strip_prefix("cv=")
+                            .map(|cv| cv.trim().to_lowercase())
+                    });
+                }
+            } else if spam_status.is_none()
+                && spam
+                    .status
+                    .as_ref()
+                    .is_some_and(|h| h.eq_ignore_ascii_case(name))
+            {
+                spam_status = Some(value);
+            } else if spam_result.is_none()
+                && spam
+                    .result
+                    .as_ref()
+                    .is_some_and(|h| h.eq_ignore_ascii_case(name))
+            {
+                spam_result = Some(value);
+            } else if spam_exemption.is_none()
+                && spam
+                    .exemption
+                    .as_ref()
+                    .is_some_and(|h| h.eq_ignore_ascii_case(name))
+            {
+                spam_exemption = Some(value);
+            }
+        }
+
+        if let Some((_, auth_results)) = auth_results {
+            let mut results = split_unquoted(&auth_results, ';').into_iter();
+            report.authserv_id = results
+                .next()
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty());
+            for result in results {
+                if let Some((method, result)) = parse_method_result(result) {
+                    match method.as_str() {
+                        "spf" => report.spf.push(result),
+                        "dkim" => report.dkim.push(result),
+                        "arc" => report.arc.push(result),
+                        "dmarc" => report.dmarc.push(result),
+                        "iprev" => report.iprev.push(result),
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        if let Some(status) = spam_status {
+            report.spam = Some(SpamReport {
+                is_spam: status.contains("Yes"),
+                score: status
+                    .split_once("score=")
+                    .and_then(|(_, score)| score.trim().parse().ok()),
+                tags: spam_result
+                    .map(|result| {
+                        result
+                            .split(',')
+                            .filter_map(|tag| {
+                                let (name, score) = tag.trim().split_once(" (")?;
+                                Some(SpamTagScore {
+                                    name: name.trim().to_string(),
+                                    score: score.trim_end_matches(')').trim().parse().ok()?,
+                                })
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                exemption: spam_exemption.map(|exemption| {
+                    let mut parts = exemption.split(';').map(|part| part.trim());
+                    let mut exemption = SpamExemption {
+                        reason: parts.next().unwrap_or_default().to_string(),
+                        ..Default::default()
+                    };
+                    for (name, value) in parts.filter_map(|part| part.split_once('=')) {
+                        match name.trim() {
+                            "action" => exemption.action = Some(value.trim().to_string()),
+                            "adjustment" => exemption.adjustment = value.trim().parse().ok(),
+                            _ => (),
+                        }
+                    }
+                    exemption
+                }),
+            });
+        }
+
+        report
+    }
+}
+
+fn unfold(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .split(['\r', '\n'])
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0u32;
+    let mut in_quote = false;
+    let mut start = 0;
+    for (pos, ch) in value.char_indices() {
+        match ch {
+            '"' => in_quote = !in_quote,
+            '(' if !in_quote => depth += 1,
+            ')' if !in_quote => depth = depth.saturating_sub(1),
+            ch if ch == separator && !in_quote && depth == 0 => {
+                parts.push(&value[start..pos]);
+                start = pos + ch.len_utf8();
+            }
+            _ => (),
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn parse_method_result(value: &str) -> Option<(String, AuthMethodResult)> {
+    let value = value.trim();
+    let (method, rest) = value.split_once('=')?;
+    let rest = rest.trim_start();
+    let result_end = rest
+        .find(|ch: char| ch.is_whitespace() || ch == '(')
+        .unwrap_or(rest.len());
+    let mut result = AuthMethodResult {
+        result: rest[..result_end].to_lowercase(),
+        ..Default::default()
+    };
+
+    let mut rest = rest[result_end..].trim_start();
+    if let Some(comment) = rest.strip_prefix('(') {
+        let mut depth = 1;
+        let end = comment.char_indices().find_map(|(pos, ch)| {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => (),
+            }
+            (depth == 0).then_some(pos)
+        })?;
+        result.comment = Some(comment[..end].trim().to_string());
+        rest = comment[end + 1..].trim_start();
+    }
+
+    for property in split_unquoted(rest, ' ') {
+        if let Some((name, value)) = property.split_once('=') {
+            result.properties.insert(
+                name.trim().to_lowercase(),
+                value.trim().trim_matches('"').to_string(),
+            );
+        }
+    }
+
+    Some((method.trim().to_lowercase(), result))
+}
+
+impl From<AuthReport> for Value {
+    fn from(report: AuthReport) -> Self {
+        let methods = |results: Vec<AuthMethodResult>| {
+            Value::List(
+                results
+                    .into_iter()
+                    .map(|result| {
+                        let mut properties =
+                            jmap_proto::object::Object::with_capacity(result.properties.len());
+                        for (name, value) in result.properties {
+                            properties.append(Property::_T(name), value);
+                        }
+                        jmap_proto::object::Object::with_capacity(3)
+                            .with_property(Property::_T("result".to_string()), result.result)
+                            .with_property(
+                                Property::_T("comment".to_string()),
+                                result.comment.map_or(Value::Null, Value::Text),
+                            )
+                            .with_property(Property::_T("properties".to_string()), properties)
+                            .into()
+                    })
+                    .collect(),
+            )
+        };
+
+        jmap_proto::object::Object::with_capacity(8)
+            .with_property(
+                Property::_T("authservId".to_string()),
+                report.authserv_id.map_or(Value::Null, Value::Text),
+            )
+            .with_property(Property::_T("spf".to_string()), methods(report.spf))
+            .with_property(Property::_T("dkim".to_string()), methods(report.dkim))
+            .with_property(Property::_T("arc".to_string()), methods(report.arc))
+            .with_property(Property::_T("dmarc".to_string()), methods(report.dmarc))
+            .with_property(Property::_T("iprev".to_string()), methods(report.iprev))
+            .with_property(
+                Property::_T("arcChain".to_string()),
+                report.arc_chain.map_or(Value::Null, |chain| {
+                    jmap_proto::object::Object::with_capacity(2)
+                        .with_property(Property::_T("instances".to_string()), chain.instances)
+                        .with_property(
+                            Property::_T("validation".to_string()),
+                            chain.validation.map_or(Value::Null, Value::Text),
+                        )
+                        .into()
+                }),
+            )
+            .with_property(
+                Property::_T("spam".to_string()),
+                report.spam.map_or(Value::Null, |spam| {
+                    let mut tags = jmap_proto::object::Object::with_capacity(spam.tags.len());
+                    for tag in spam.tags {
+                        tags.append(Property::_T(tag.name), format!("{:.2}", tag.score));
+                    }
+                    jmap_proto::object::Object::with_capacity(4)
+                        .with_property(Property::_T("isSpam".to_string()), spam.is_spam)
+                        .with_property(
+                            Property::_T("score".to_string()),
+                            spam.score
+                                .map_or(Value::Null, |score| Value::Text(format!("{score:.2}"))),
+                        )
+                        .with_property(Property::_T("tags".to_string()), tags)
+                        .with_property(
+                            Property::_T("exemption".to_string()),
+                            spam.exemption.map_or(Value::Null, |exemption| {
+                                jmap_proto::object::Object::with_capacity(3)
+                                    .with_property(
+                                        Property::_T("reason".to_string()),
+                                        exemption.reason,
+                                    )
+                                    .with_property(
+                                        Property::_T("action".to_string()),
+                                        exemption.action.map_or(Value::Null, Value::Text),
+                                    )
+                                    .with_property(
+                                        Property::_T("adjustment".to_string()),
+                                        exemption.adjustment.map_or(Value::Null, |adjustment| {
+                                            Value::Text(format!("{adjustment:.2}"))
+                                        }),
+                                    )
+                                    .into()
+                            }),
+                        )
+                        .into()
+                }),
+            )
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::config::spamfilter::SpamFilterHeaderConfig;
+
+    use super::AuthReport;
+
+    #[test]
+    fn parse_auth_report() {
+        let headers = concat!(
+            "X-Spam-Result: DKIM_ALLOW (-0.20),\r\n\tSPF_ALLOW (-0.20),\r\n\tFROM_NO_DN (0.00)\r\n",
+            "X-Spam-Exemption: known-sender; action=score; adjustment=-5.00\r\n",
+            "X-Spam-Status: No, score=-0.40\r\n",
+            "Authentication-Results: mx.example.org;\r\n",
+            "\tdkim=pass header.d=example.com header.s=default header.b=ZGVm;\r\n",
+            "\tspf=pass (mx.example.org: domain of john@example.com designates 1.2.3.4 ",
+            "as permitted sender) smtp.mailfrom=john@example.com;\r\n",
+            "\tdmarc=pass header.from=example.com policy.dmarc=reject\r\n",
+            "ARC-Seal: i=1; a=rsa-sha256; cv=none; d=example.com; s=arc; b=abc\r\n",
+            "Subject: test\r\n\r\n"
+        );
+
+        let report = AuthReport::parse(
+            headers.as_bytes(),
+            "mx.example.org",
+            &SpamFilterHeaderConfig {
+                status: Some("X-Spam-Status".to_string()),
+                result: Some("X-Spam-Result".to_string()),
+                bayes_result: None,
+                llm: None,
+                exemption: Some("X-Spam-Exemption".to_string()),
+            },
+        );
+
+        assert_eq!(report.authserv_id.as_deref(), Some("mx.example.org"));
+        assert_eq!(report.dkim.len(), 1);
+        assert_eq!(report.dkim[0].result, "pass");
+        assert_eq!(report.dkim[0].properties["header.s"], "default");
+        assert_eq!(report.spf[0].result, "pass");
+        assert_eq!(
+            report.spf[0].properties["smtp.mailfrom"],
+            "john@example.com"
+        );
+        assert!(report.spf[0]
+            .comment
+            .as_ref()
+            .unwrap()
+            .starts_with("mx.example.org"));
+        assert_eq!(report.dmarc[0].properties["policy.dmarc"], "reject");
+        let chain = report.arc_chain.unwrap();
+        assert_eq!(
+            (chain.instances, chain.validation.as_deref()),
+            (1, Some("none"))
+        );
+        let spam = report.spam.unwrap();
+        assert!(!spam.is_spam);
+        assert_eq!(spam.score, Some(-0.4));
+        assert_eq!(spam.tags.len(), 3);
+        assert_eq!(spam.tags[1].name, "SPF_ALLOW");
+        let exemption = spam.exemption.unwrap();
+        assert_eq!(exemption.reason, "known-sender");
+        assert_eq!(exemption.action.as_deref(), Some("score"));
+        assert_eq!(exemption.adjustment, Some(-5.0));
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use common::{Conversation, Conversations, Server};
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_parser::HeaderName;
+use store::{
+    ahash::AHashMap,
+    query::log::{Change, Query},
+    roaring::RoaringBitmap,
+    write::Bincode,
+};
+use trc::AddContext;
+
+use crate::{
+    index::{AddressElement, VisitValues},
+    metadata::MessageMetadata,
+};
+
+const METADATA_BATCH_SIZE: usize = 1000;
+const MAX_PARTICIPANTS: usize = 100;
+
+pub trait ConversationIndex: Sync + Send {
+    fn get_conversations(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Arc<Conversations>>> + Send;
+
+    fn build_conversations(
+        &self,
+        account_id: u32,
+        threads: AHashMap<u32, Vec<u32>>,
+    ) -> impl Future<Output = trc::Result<Vec<(u32, Conversation)>>> + Send;
+}
+
+impl ConversationIndex for Server {
+    async fn get_conversations(&self, account_id: u32) -> trc::Result<Arc<Conversations>> {
+        // Obtain current state
+        let modseq = self
+            .core
+            .storage
+            .data
+            .get_last_change_id(account_id, Collection::Thread)
+            .await
+            .caused_by(trc::location!())?;
+
+        // Only the threads changed since the cached state are rebuilt
+        let mut changed_threads = None;
+        let mut conversations = match self.inner.cache.conversations.get(&account_id) {
+            Some(cached) if cached.modseq.unwrap_or(0) >= modseq.unwrap_or(0) => {
+                return Ok(cached);
+            }
+            Some(cached) if cached.modseq.is_some() => {
+                let cached_modseq = cached.modseq.unwrap();
+                let changelog = self
+                    .core
+                    .storage
+                    .data
+                    .changes(
+                        account_id,
+                        Collection::Thread,
+                        Query::SinceInclusive(cached_modseq),
+                    )
+                    .await
+                    .caused_by(trc::location!())?;
+
+                // A purged changelog requires a full rebuild
+                if changelog.from_change_id == cached_modseq {
+                    changed_threads = changelog
+                        .changes
+                        .into_iter()
+                        .map(|change| match change {
+                            Change::Insert(id)
+                            | Change::Update(id)
+                            | Change::ChildUpdate(id)
+                            | Change::Delete(id) => id as u32,
+                        })
+                        .collect::<RoaringBitmap>()
+                        .into();
+                    Conversations::clone(&cached)
+                } else {
+                    Conversations::default()
+                }
+            }
+            _ => Conversations::default(),
+        };
+
+        let threads = if let Some(changed_threads) = changed_threads {
+            let mut threads: AHashMap<u32, Vec<u32>> = AHashMap::new();
+            for thread_id in changed_threads {
+                if let Some(old_conversation) = conversations.threads.remove(&thread_id) {
+                    for message_id in &old_conversation.message_ids {
+                        conversations.message_threads.remove(message_id);
+                    }
+                }
+                if let Some(document_ids) = self
+                    .get_tag(account_id, Collection::Email, Property::ThreadId, thread_id)
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    threads.insert(thread_id, document_ids.into_iter().collect());
+                }
+            }
+            threads
+        } else {
+            let mut threads: AHashMap<u32, Vec<u32>> = AHashMap::new();
+            for (document_id, thread_id) in self
+                .get_properties::<u32, _, _>(account_id, Collection::Email, &(), Property::ThreadId)
+                .await
+                .caused_by(trc::location!())?
+            {
+                threads.entry(thread_id).or_default().push(document_id);
+            }
+            threads
+        };
+
+        for (thread_id, conversation) in self.build_conversations(account_id, threads).await? {
+            for message_id in &conversation.message_ids {
+                conversations.message_threads.insert(*message_id, thread_id);
+            }
+            conversations
+                .threads
+                .insert(thread_id, Arc::new(conversation));
+        }
+        conversations.modseq = modseq;
+        let conversations = Arc::new(conversations.update_size());
+
+        self.inner
+            .cache
+            .conversations
+            .insert(account_id, conversations.clone());
+
+        Ok(conversations)
+    }
+
+    async fn build_conversations(
+        &self,
+        account_id: u32,
+        threads: AHashMap<u32, Vec<u32>>,
+    ) -> trc::Result<Vec<(u32, Conversation)>> {
+        // Obtain the received date and senders of each message
+        let document_ids = threads.values().flatten().copied().collect::<Vec<_>>();
+        let mut messages: AHashMap<u32, (u64, Vec<String>)> =
+            AHashMap::with_capacity(document_ids.len());
+        for chunk in document_ids.chunks(METADATA_BATCH_SIZE) {
+            for (document_id, metadata) in self
+                .get_properties::<Bincode<MessageMetadata>, _, _>(
+                    account_id,
+                    Collection::Email,
+                    &chunk.iter().copied().collect::<RoaringBitmap>(),
+                    Property::BodyStructure,
+                )
+                .await
+                .caused_by(trc::location!())?
+            {
+                let metadata = metadata.inner;
+                let mut senders = Vec::new();
+                if let Some(header) = metadata.contents.parts.first().and_then(|part| {
+                    part.headers
+                        .iter()
+                        .find(|header| header.name == HeaderName::From)
+                }) {
+                    header.value.visit_addresses(|element, value| {
+                        if element == AddressElement::Address {
+                            senders.push(value.to_lowercase());
+                        }
+                    });
+                }
+                messages.insert(document_id, (metadata.received_at, senders));
+            }
+        }
+
+        let mut conversations = Vec::with_capacity(threads.len());
+        for (thread_id, mut message_ids) in threads {
+            message_ids.retain(|id| messages.contains_key(id));
+            if message_ids.is_empty() {
+                continue;
+            }
+            message_ids.sort_unstable_by_key(|id| (messages[id].0, *id));
+
+            let mut conversation = Conversation {
+                last_activity: messages[message_ids.last().unwrap()].0,
+                participants: Vec::new(),
+                message_ids,
+            };
+            for message_id in &conversation.message_ids {
+                for sender in &messages[message_id].1 {
+                    if conversation.participants.len() < MAX_PARTICIPANTS
+                        && !conversation.participants.contains(sender)
+                    {
+                        conversation.participants.push(sender.clone());
+                    }
+                }
+            }
+            conversations.push((thread_id, conversation));
+        }
+
+        Ok(conversations)
+    }
+}
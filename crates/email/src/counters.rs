@@ -0,0 +1,399 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{KV_LOCK_MAILBOX_COUNTERS, Server};
+use jmap_proto::types::{collection::Collection, keyword::Keyword, property::Property};
+use std::future::Future;
+use store::{
+    Deserialize, IndexKeyPrefix, IterateParams, U32_LEN, ValueKey,
+    ahash::AHashMap,
+    roaring::RoaringBitmap,
+    write::{BatchBuilder, Bincode, IntoOperations, ValueClass, key::DeserializeBigEndian},
+};
+use trc::AddContext;
+
+use crate::{mailbox::TOMBSTONE_ID, metadata::MessageMetadata};
+
+// Counter set on the reserved mailbox document id once an account's counters have been built
+const COUNTERS_INITIALIZED_ID: u32 = u32::MAX;
+const COUNTERS_LOCK_EXPIRY: u64 = 300;
+// Larger sets are looked up in the size index rather than by deserializing their metadata
+const METADATA_SIZE_LOOKUP_MAX: u64 = 32;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MailboxCount {
+    pub total: u64,
+    pub unread: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct MailboxCounters {
+    deltas: AHashMap<u32, MailboxCountDelta>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct MailboxCountDelta {
+    total: i64,
+    unread: i64,
+    size: i64,
+}
+
+impl MailboxCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_message(
+        &mut self,
+        mailbox_ids: impl IntoIterator<Item = u32>,
+        is_seen: bool,
+        size: usize,
+    ) {
+        self.update(mailbox_ids, 1, if is_seen { 0 } else { 1 }, size as i64);
+    }
+
+    pub fn remove_message(
+        &mut self,
+        mailbox_ids: impl IntoIterator<Item = u32>,
+        is_seen: bool,
+        size: usize,
+    ) {
+        self.update(
+            mailbox_ids,
+            -1,
+            if is_seen { 0 } else { -1 },
+            -(size as i64),
+        );
+    }
+
+    pub fn set_seen(&mut self, mailbox_ids: impl IntoIterator<Item = u32>, is_seen: bool) {
+        self.update(mailbox_ids, 0, if is_seen { -1 } else { 1 }, 0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    fn update(
+        &mut self,
+        mailbox_ids: impl IntoIterator<Item = u32>,
+        total: i64,
+        unread: i64,
+        size: i64,
+    ) {
+        for mailbox_id in mailbox_ids {
+            if mailbox_id != TOMBSTONE_ID {
+                let delta = self.deltas.entry(mailbox_id).or_default();
+                delta.total += total;
+                delta.unread += unread;
+                delta.size += size;
+            }
+        }
+    }
+}
+
+impl IntoOperations for MailboxCounters {
+    fn build(self, batch: &mut BatchBuilder) {
+        if self.deltas.is_empty() {
+            return;
+        }
+
+        batch.with_collection(Collection::Mailbox);
+        for (mailbox_id, delta) in self.deltas {
+            batch.update_document(mailbox_id);
+            for (property, value) in [
+                (Property::TotalEmails, delta.total),
+                (Property::UnreadEmails, delta.unread),
+                (Property::Size, delta.size),
+            ] {
+                if value != 0 {
+                    batch.add(ValueClass::Property(property.into()), value);
+                }
+            }
+        }
+        batch.with_collection(Collection::Email);
+    }
+}
+
+pub trait MailboxCounterFnc: Sync + Send {
+    fn mailbox_count(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<MailboxCount>>> + Send;
+
+    fn mailbox_counters_rebuild(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn message_sizes(
+        &self,
+        account_id: u32,
+        document_ids: &RoaringBitmap,
+    ) -> impl Future<Output = trc::Result<AHashMap<u32, usize>>> + Send;
+}
+
+impl MailboxCounterFnc for Server {
+    async fn mailbox_count(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+    ) -> trc::Result<Option<MailboxCount>> {
+        // Build counters for accounts created before they were introduced
+        if self
+            .mailbox_counter(account_id, COUNTERS_INITIALIZED_ID, Property::TotalEmails)
+            .await?
+            == 0
+            && !self.mailbox_counters_rebuild(account_id).await?
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(MailboxCount {
+            total: self
+                .mailbox_counter(account_id, mailbox_id, Property::TotalEmails)
+                .await? as u64,
+            unread: self
+                .mailbox_counter(account_id, mailbox_id, Property::UnreadEmails)
+                .await? as u64,
+            size: self
+                .mailbox_counter(account_id, mailbox_id, Property::Size)
+                .await? as u64,
+        }))
+    }
+
+    async fn mailbox_counters_rebuild(&self, account_id: u32) -> trc::Result<bool> {
+        let lock_key = account_id.to_be_bytes();
+        if !self
+            .in_memory_store()
+            .try_lock(KV_LOCK_MAILBOX_COUNTERS, &lock_key, COUNTERS_LOCK_EXPIRY)
+            .await
+            .caused_by(trc::location!())?
+        {
+            return Ok(false);
+        }
+
+        let result = self.build_mailbox_counters(account_id).await;
+
+        if let Err(err) = self
+            .in_memory_store()
+            .remove_lock(KV_LOCK_MAILBOX_COUNTERS, &lock_key)
+            .await
+        {
+            trc::error!(
+                err.account_id(account_id)
+                    .details("Failed to unlock mailbox counters")
+            );
+        }
+
+        result.map(|_| true)
+    }
+
+    async fn message_sizes(
+        &self,
+        account_id: u32,
+        document_ids: &RoaringBitmap,
+    ) -> trc::Result<AHashMap<u32, usize>> {
+        if document_ids.is_empty() {
+            return Ok(AHashMap::new());
+        } else if document_ids.len() > METADATA_SIZE_LOOKUP_MAX {
+            return self
+                .indexed_message_sizes(account_id, Some(document_ids))
+                .await;
+        }
+
+        self.get_properties::<Bincode<MessageMetadata>, _, _>(
+            account_id,
+            Collection::Email,
+            document_ids,
+            Property::BodyStructure,
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|metadata| {
+            metadata
+                .into_iter()
+                .map(|(document_id, metadata)| (document_id, metadata.inner.size))
+                .collect()
+        })
+    }
+}
+
+trait MailboxCounterBuild {
+    fn mailbox_counter(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        property: Property,
+    ) -> impl Future<Output = trc::Result<i64>> + Send;
+
+    fn build_mailbox_counters(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn indexed_message_sizes(
+        &self,
+        account_id: u32,
+        document_ids: Option<&RoaringBitmap>,
+    ) -> impl Future<Output = trc::Result<AHashMap<u32, usize>>> + Send;
+}
+
+impl MailboxCounterBuild for Server {
+    async fn mailbox_counter(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        property: Property,
+    ) -> trc::Result<i64> {
+        self.core
+            .storage
+            .data
+            .get_counter(ValueKey {
+                account_id,
+                collection: Collection::Mailbox.into(),
+                document_id: mailbox_id,
+                class: ValueClass::Property(property.into()),
+            })
+            .await
+            .add_context(|err| {
+                err.caused_by(trc::location!())
+                    .account_id(account_id)
+                    .document_id(mailbox_id)
+            })
+            .map(|value| value.max(0))
+    }
+
+    async fn build_mailbox_counters(&self, account_id: u32) -> trc::Result<()> {
+        // Another process might have built the counters while waiting for the lock
+        if self
+            .mailbox_counter(account_id, COUNTERS_INITIALIZED_ID, Property::TotalEmails)
+            .await?
+            != 0
+        {
+            return Ok(());
+        }
+
+        let mailbox_ids = self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await?
+            .unwrap_or_default();
+        let seen = self
+            .get_tag(
+                account_id,
+                Collection::Email,
+                Property::Keywords,
+                Keyword::Seen,
+            )
+            .await?
+            .unwrap_or_default();
+
+        let sizes = self.indexed_message_sizes(account_id, None).await?;
+
+        // Counters are written as deltas, writes that happen while they are
+        // being built are not reflected until the next rebuild.
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox);
+        for mailbox_id in mailbox_ids {
+            let message_ids = self
+                .get_tag(
+                    account_id,
+                    Collection::Email,
+                    Property::MailboxIds,
+                    mailbox_id,
+                )
+                .await?
+                .unwrap_or_default();
+            let total = message_ids.len() as i64;
+            let unread = (&message_ids - &seen).len() as i64;
+            let size = message_ids
+                .iter()
+                .map(|document_id| sizes.get(&document_id).copied().unwrap_or_default() as i64)
+                .sum::<i64>();
+
+            batch.update_document(mailbox_id);
+            for (property, value) in [
+                (Property::TotalEmails, total),
+                (Property::UnreadEmails, unread),
+                (Property::Size, size),
+            ] {
+                let property = u8::from(property);
+                let delta = value
+                    - self
+                        .core
+                        .storage
+                        .data
+                        .get_counter(ValueKey {
+                            account_id,
+                            collection: Collection::Mailbox.into(),
+                            document_id: mailbox_id,
+                            class: ValueClass::Property(property),
+                        })
+                        .await
+                        .caused_by(trc::location!())?;
+                if delta != 0 {
+                    batch.add(ValueClass::Property(property), delta);
+                }
+            }
+        }
+        batch
+            .update_document(COUNTERS_INITIALIZED_ID)
+            .add(ValueClass::Property(Property::TotalEmails.into()), 1);
+
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+
+    async fn indexed_message_sizes(
+        &self,
+        account_id: u32,
+        document_ids: Option<&RoaringBitmap>,
+    ) -> trc::Result<AHashMap<u32, usize>> {
+        let mut sizes = AHashMap::new();
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    IndexKeyPrefix {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        field: Property::Size.into(),
+                    },
+                    IndexKeyPrefix {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        field: u8::from(Property::Size) + 1,
+                    },
+                )
+                .ascending()
+                .no_values(),
+                |key, _| {
+                    let id_pos = key.len() - U32_LEN;
+                    let document_id = key.deserialize_be_u32(id_pos)?;
+
+                    if document_ids.is_none_or(|ids| ids.contains(document_id)) {
+                        let size = key
+                            .get(IndexKeyPrefix::len()..id_pos)
+                            .ok_or_else(|| trc::Error::corrupted_key(key, None, trc::location!()))
+                            .and_then(u32::deserialize)?;
+                        sizes.insert(document_id, size as usize);
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())
+            .map(|_| sizes)
+    }
+}
@@ -12,7 +12,7 @@ use mail_builder::{encoders::base64::base64_encode_mime, mime::make_boundary};
 use mail_parser::{Message, MimeHeaders, PartType, decoders::base64::base64_decode};
 use openpgp::{
     parse::Parse,
-    serialize::stream,
+    serialize::{Marshal, stream},
     types::{KeyFlags, SymmetricAlgorithm},
 };
 use rand::{RngCore, SeedableRng, rngs::StdRng};
@@ -31,7 +31,7 @@ use store::{
     write::{Bincode, ToBitmaps},
 };
 
-const P: openpgp::policy::StandardPolicy<'static> = openpgp::policy::StandardPolicy::new();
+pub(crate) const P: openpgp::policy::StandardPolicy<'static> = openpgp::policy::StandardPolicy::new();
 
 #[derive(Debug)]
 pub enum EncryptMessageError {
@@ -466,6 +466,33 @@ pub fn try_parse_certs(
     }
 }
 
+/// Exports the OpenPGP certificates that carry a user ID for `address`,
+/// in binary form as required by WKD and OPENPGPKEY records.
+pub fn export_pgp_certs(params: &EncryptionParams, address: &str) -> Option<Vec<u8>> {
+    if params.method != EncryptionMethod::PGP {
+        return None;
+    }
+
+    let mut exported = Vec::new();
+    for cert in params
+        .certs
+        .iter()
+        .filter_map(|cert| openpgp::Cert::from_bytes(cert).ok())
+    {
+        if cert.userids().any(|uid| {
+            uid.userid()
+                .email_normalized()
+                .ok()
+                .flatten()
+                .is_some_and(|email| email == address)
+        }) {
+            cert.export(&mut exported).ok()?;
+        }
+    }
+
+    (!exported.is_empty()).then_some(exported)
+}
+
 fn has_pgp_keys(cert: openpgp::Cert) -> bool {
     cert.keys()
         .with_policy(&P, None)
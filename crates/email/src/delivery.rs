@@ -4,17 +4,24 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::Server;
+use common::{
+    config::spamfilter::{KnownSenderAction, SpamVerdict},
+    Server,
+};
 use directory::Permission;
-use jmap_proto::types::{state::StateChange, type_state::DataType};
-use mail_parser::MessageParser;
+use jmap_proto::types::{blob::BlobId, id::Id, state::StateChange, type_state::DataType};
+use mail_parser::{HeaderName, MessageParser};
 use std::{borrow::Cow, future::Future};
 use store::ahash::AHashMap;
 use utils::BlobHash;
 
 use crate::{
-    ingest::{EmailIngest, IngestEmail, IngestSource},
-    mailbox::INBOX_ID,
+    forward::MailForwarding,
+    ingest::{DeliveryRecipient, EmailIngest, IngestEmail, IngestSource, IngestedEmail},
+    known_sender::{add_exemption_headers, is_sender_authenticated, KnownSenders},
+    link_protection::protect_links,
+    mailbox::{MailboxFnc, INBOX_ID},
+    sender_list::SenderListManager,
     sieve::SieveScriptIngest,
 };
 
@@ -55,6 +62,13 @@ pub trait MailDelivery: Sync + Send {
         &self,
         message: IngestMessage,
     ) -> impl Future<Output = LocalDeliveryResult> + Send;
+
+    fn catch_all_mailbox_id(
+        &self,
+        account_id: u32,
+        rcpt: &str,
+        session_id: u64,
+    ) -> impl Future<Output = u32> + Send;
 }
 
 /*
@@ -169,6 +183,28 @@ impl MailDelivery for Server {
             }
         };
 
+        // Obtain the spam status assigned by the spam filter
+        let spam_status = self.core.spam.headers.status.as_ref().and_then(|name| {
+            MessageParser::new()
+                .parse_headers(&raw_message)
+                .and_then(|message| {
+                    message
+                        .header(name.as_str())
+                        .and_then(|v| v.as_text())
+                        .map(|v| v.to_string())
+                })
+        });
+
+        // Known senders are only trusted when this server authenticated them
+        let is_sender_verified = self.core.spam.known_senders.is_some()
+            && spam_status.is_some()
+            && is_sender_authenticated(
+                &raw_message,
+                &message.sender_address,
+                &self.core.network.server_name,
+                &self.core.spam.headers,
+            );
+
         // Obtain the UIDs for each recipient
         let mut uids: AHashMap<u32, usize> = AHashMap::with_capacity(message.recipients.len());
         let mut result = LocalDeliveryResult {
@@ -177,11 +213,11 @@ impl MailDelivery for Server {
         };
 
         for rcpt in message.recipients {
-            let uid = match self
-                .email_to_id(&self.core.storage.directory, &rcpt, message.session_id)
+            let (uid, is_catch_all) = match self
+                .email_to_id_or_catch_all(&self.core.storage.directory, &rcpt, message.session_id)
                 .await
             {
-                Ok(Some(uid)) => uid,
+                Ok(Some(result)) => result,
                 Ok(None) => {
                     // Something went wrong
                     result.status.push(LocalDeliveryStatus::PermanentFailure {
@@ -207,28 +243,199 @@ impl MailDelivery for Server {
                 continue;
             }
 
+            // Apply the recipient's personal sender lists, followed by the spam policy
+            let sender_status = match self.sender_lists_get(uid).await {
+                Ok(lists) => lists.sender_status(&message.sender_address),
+                Err(err) => {
+                    trc::error!(err
+                        .details("Failed to obtain sender lists.")
+                        .span_id(message.session_id)
+                        .caused_by(trc::location!()));
+                    None
+                }
+            };
+            let mut spam_classify = true;
+            match sender_status
+                .or_else(|| self.core.spam.sender_policy(&rcpt, &message.sender_address))
+            {
+                Some(true) => {
+                    spam_classify = false;
+                }
+                Some(false) => {
+                    uids.insert(uid, result.status.len());
+                    result.status.push(LocalDeliveryStatus::PermanentFailure {
+                        code: [5, 7, 1],
+                        reason: "Sender is blocked by the recipient.".into(),
+                    });
+                    continue;
+                }
+                None => (),
+            }
+            let mut raw_message = Cow::Borrowed(raw_message.as_slice());
+            let mut rcpt_spam_status = spam_status.as_deref().map(Cow::Borrowed);
+
+            // Relax filtering for senders the recipient has written to
+            if spam_classify && is_sender_verified {
+                let action = self.core.spam.known_sender_action(&rcpt);
+                let is_known = action != KnownSenderAction::Ignore
+                    && match self.is_known_sender(uid, &message.sender_address).await {
+                        Ok(is_known) => is_known,
+                        Err(err) => {
+                            trc::error!(err
+                                .details("Failed to lookup known sender.")
+                                .span_id(message.session_id)
+                                .caused_by(trc::location!()));
+                            false
+                        }
+                    };
+
+                if is_known {
+                    match action {
+                        KnownSenderAction::Skip => {
+                            spam_classify = false;
+                            raw_message = add_exemption_headers(
+                                &raw_message,
+                                &self.core.spam.headers,
+                                "known-sender; action=skip",
+                                None,
+                            )
+                            .into();
+                        }
+                        KnownSenderAction::Score => {
+                            let score = self
+                                .core
+                                .spam
+                                .known_senders
+                                .as_ref()
+                                .map_or(0.0, |config| config.score);
+                            if let Some(status) = rcpt_spam_status.as_deref().and_then(|status| {
+                                self.core.spam.adjust_status(&rcpt, status, score)
+                            }) {
+                                raw_message = add_exemption_headers(
+                                    &raw_message,
+                                    &self.core.spam.headers,
+                                    &format!("known-sender; action=score; adjustment={score:.2}"),
+                                    Some(&status),
+                                )
+                                .into();
+                                rcpt_spam_status = Some(Cow::Owned(status));
+                            }
+                        }
+                        KnownSenderAction::Ignore => (),
+                    }
+
+                    trc::event!(
+                        Spam(trc::SpamEvent::Classify),
+                        SpanId = message.session_id,
+                        To = rcpt.clone(),
+                        From = message.sender_address.clone(),
+                        Details = "Spam filtering relaxed for a known sender",
+                    );
+                }
+            }
+
+            let mut is_spam = false;
+            if spam_classify && self.core.spam.enabled {
+                match self
+                    .core
+                    .spam
+                    .recipient_verdict(&rcpt, rcpt_spam_status.as_deref())
+                {
+                    SpamVerdict::Reject => {
+                        uids.insert(uid, result.status.len());
+                        result.status.push(LocalDeliveryStatus::PermanentFailure {
+                            code: [5, 7, 1],
+                            reason: "Message rejected by the recipient's spam policy.".into(),
+                        });
+                        continue;
+                    }
+                    SpamVerdict::Discard => {
+                        trc::event!(
+                            Spam(trc::SpamEvent::Classify),
+                            SpanId = message.session_id,
+                            To = rcpt.clone(),
+                            Details = "Message discarded by the recipient's spam policy",
+                        );
+                        uids.insert(uid, result.status.len());
+                        result.status.push(LocalDeliveryStatus::Success);
+                        continue;
+                    }
+                    SpamVerdict::Spam { subject_tag, .. } => {
+                        is_spam = true;
+                        if let Some(tag) = subject_tag {
+                            let language = self.account_language(uid).await;
+                            let tag = self
+                                .core
+                                .i18n
+                                .get(&language, "spam.subject-tag")
+                                .unwrap_or(tag);
+                            raw_message = tag_subject(&raw_message, tag).into();
+                        }
+                    }
+                    SpamVerdict::Ham => (),
+                }
+            }
+
+            // Rewrite or tag links in HTML parts
+            if let Some(config) = &self.core.spam.link_protection
+                && config.is_enabled_for(&rcpt)
+                && let Some(protected) = protect_links(&raw_message, config, is_spam)
+            {
+                raw_message = protected.into();
+            }
+
             // Obtain access token
             let status = match self.get_access_token(uid).await.and_then(|token| {
                 token
                     .assert_has_permission(Permission::EmailReceive)
                     .map(|_| token)
             }) {
-                Ok(access_token) => {
+                Ok(access_token) => match self
+                    .forward_message(
+                        uid,
+                        &rcpt,
+                        &message.sender_address,
+                        &raw_message,
+                        message.session_id,
+                        &mut result.autogenerated,
+                    )
+                    .await
+                {
+                    Ok(false) => {
+                        // Forwarded without keeping a local copy
+                        Ok(IngestedEmail {
+                            id: Id::default(),
+                            change_id: u64::MAX,
+                            blob_id: BlobId::default(),
+                            imap_uids: Vec::new(),
+                            size: 0,
+                        })
+                    }
                     // Check if there is an active sieve script
-                    match self.sieve_script_get_active(uid).await {
+                    Ok(true) => match self.sieve_script_get_active(uid).await {
                         Ok(None) => {
+                            // Messages received by a catch-all address can be filed
+                            // into a folder named after the unknown local part
+                            let mailbox_id = if is_catch_all {
+                                self.catch_all_mailbox_id(uid, &rcpt, message.session_id)
+                                    .await
+                            } else {
+                                INBOX_ID
+                            };
+
                             // Ingest message
                             self.email_ingest(IngestEmail {
                                 raw_message: &raw_message,
-                                message: MessageParser::new().parse(&raw_message),
+                                message: MessageParser::new().parse(raw_message.as_ref()),
                                 resource: access_token.as_resource_token(),
-                                mailbox_ids: vec![INBOX_ID],
+                                mailbox_ids: vec![mailbox_id],
                                 keywords: vec![],
                                 received_at: None,
                                 source: IngestSource::Smtp { deliver_to: &rcpt },
-                                spam_classify: access_token
-                                    .has_permission(Permission::SpamFilterClassify),
+                                spam_classify: spam_classify
+                                    && access_token.has_permission(Permission::SpamFilterClassify),
                                 spam_train: self.email_bayes_can_train(&access_token),
+                                expires_at: None,
                                 session_id: message.session_id,
                             })
                             .await
@@ -241,13 +448,15 @@ impl MailDelivery for Server {
                                 &rcpt,
                                 message.session_id,
                                 active_script,
+                                spam_classify,
                                 &mut result.autogenerated,
                             )
                             .await
                         }
                         Err(err) => Err(err),
-                    }
-                }
+                    },
+                    Err(err) => Err(err),
+                },
 
                 Err(err) => Err(err),
             };
@@ -323,4 +532,69 @@ impl MailDelivery for Server {
 
         result
     }
+
+    async fn catch_all_mailbox_id(&self, account_id: u32, rcpt: &str, session_id: u64) -> u32 {
+        let Some(parent) = self
+            .eval_if::<String, _>(
+                &self.core.smtp.session.rcpt.catch_all_folder,
+                &DeliveryRecipient(rcpt),
+                session_id,
+            )
+            .await
+        else {
+            return INBOX_ID;
+        };
+
+        // Use the local part without any subaddress as the folder name
+        let local_part = rcpt.rsplit_once('@').map_or(rcpt, |(local, _)| local);
+        let name = local_part
+            .split_once('+')
+            .map_or(local_part, |(local, _)| local)
+            .replace('/', "_");
+        let path = if !parent.is_empty() {
+            format!("{parent}/{name}")
+        } else {
+            name
+        };
+
+        match self.mailbox_create_path(account_id, &path).await {
+            Ok(Some((mailbox_id, _))) => mailbox_id,
+            Ok(None) => INBOX_ID,
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to create catch-all folder.")
+                    .account_id(account_id)
+                    .span_id(session_id)
+                    .caused_by(trc::location!()));
+                INBOX_ID
+            }
+        }
+    }
+}
+
+fn tag_subject(raw_message: &[u8], tag: &str) -> Vec<u8> {
+    let mut tagged = Vec::with_capacity(raw_message.len() + tag.len() + 16);
+    match MessageParser::new()
+        .parse_headers(raw_message)
+        .and_then(|message| {
+            message
+                .headers()
+                .iter()
+                .find(|header| header.name == HeaderName::Subject)
+                .map(|header| header.offset_start)
+        }) {
+        Some(offset) => {
+            tagged.extend_from_slice(&raw_message[..offset]);
+            tagged.push(b' ');
+            tagged.extend_from_slice(tag.as_bytes());
+            tagged.extend_from_slice(&raw_message[offset..]);
+        }
+        None => {
+            tagged.extend_from_slice(b"Subject: ");
+            tagged.extend_from_slice(tag.as_bytes());
+            tagged.extend_from_slice(b"\r\n");
+            tagged.extend_from_slice(raw_message);
+        }
+    }
+    tagged
 }
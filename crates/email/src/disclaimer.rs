@@ -0,0 +1,161 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::config::smtp::disclaimer::Disclaimer;
+use mail_parser::{MessageParser, MimeHeaders, PartType};
+
+use crate::link_protection::{decode_part, encode_part, splice_parts};
+
+/// Appends a disclaimer to the last text and HTML body parts of a message.
+/// Signed or encrypted messages are left untouched, and `None` is returned
+/// if the message was not modified.
+pub fn append_disclaimer(raw_message: &[u8], disclaimer: &Disclaimer) -> Option<Vec<u8>> {
+    let message = MessageParser::new().parse(raw_message)?;
+    if let Some(content_type) = message.root_part().content_type() {
+        let subtype = content_type.subtype().unwrap_or_default();
+        if (content_type.ctype().eq_ignore_ascii_case("multipart")
+            && (subtype.eq_ignore_ascii_case("signed")
+                || subtype.eq_ignore_ascii_case("encrypted")))
+            || (content_type.ctype().eq_ignore_ascii_case("application")
+                && (subtype.eq_ignore_ascii_case("pkcs7-mime")
+                    || subtype.eq_ignore_ascii_case("x-pkcs7-mime")))
+        {
+            return None;
+        }
+    }
+
+    // Text-only messages list the same parts in both bodies
+    let mut text_part = None;
+    let mut html_part = None;
+    for part_id in message.text_body.iter().chain(message.html_body.iter()) {
+        match message.parts.get(*part_id).map(|part| &part.body) {
+            Some(PartType::Text(_)) => text_part = text_part.max(Some(*part_id)),
+            Some(PartType::Html(_)) => html_part = html_part.max(Some(*part_id)),
+            _ => (),
+        }
+    }
+
+    let mut changes = Vec::with_capacity(2);
+    for (part_id, is_html) in [(text_part, false), (html_part, true)] {
+        let Some(part) = part_id.and_then(|part_id| message.parts.get(part_id)) else {
+            continue;
+        };
+        let contents = if is_html {
+            &disclaimer.html
+        } else {
+            &disclaimer.text
+        };
+
+        // Non-ASCII disclaimers can only be added to UTF-8 parts
+        if !contents.is_ascii()
+            && !part
+                .content_type()
+                .and_then(|ct| ct.attribute("charset"))
+                .is_some_and(|charset| {
+                    charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8")
+                })
+        {
+            continue;
+        }
+        if part.offset_body > part.offset_end {
+            continue;
+        }
+
+        let body = raw_message.get(part.offset_body..part.offset_end)?;
+        let mut decoded = decode_part(body, part.encoding)?;
+        if is_html {
+            decoded = insert_html(decoded, contents);
+        } else {
+            if !decoded.is_empty() && !decoded.ends_with(b"\n") {
+                decoded.extend_from_slice(b"\r\n");
+            }
+            decoded.extend_from_slice(b"\r\n");
+            for line in contents.lines() {
+                decoded.extend_from_slice(line.as_bytes());
+                decoded.extend_from_slice(b"\r\n");
+            }
+        }
+        changes.push((
+            part.offset_body..part.offset_end,
+            encode_part(decoded, part.encoding, body)?,
+        ));
+    }
+
+    if changes.is_empty() {
+        return None;
+    }
+    changes.sort_unstable_by_key(|(range, _)| range.start);
+    Some(splice_parts(raw_message, changes))
+}
+
+fn insert_html(html: Vec<u8>, disclaimer: &str) -> Vec<u8> {
+    let position = [b"</body".as_slice(), b"</html"]
+        .into_iter()
+        .find_map(|tag| {
+            html.windows(tag.len())
+                .rposition(|window| window.eq_ignore_ascii_case(tag))
+        })
+        .unwrap_or(html.len());
+
+    let mut result = Vec::with_capacity(html.len() + disclaimer.len() + 2);
+    result.extend_from_slice(&html[..position]);
+    result.extend_from_slice(disclaimer.as_bytes());
+    result.extend_from_slice(b"\r\n");
+    result.extend_from_slice(&html[position..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use common::config::smtp::disclaimer::Disclaimer;
+
+    use super::append_disclaimer;
+
+    #[test]
+    fn append_disclaimers() {
+        let disclaimer = Disclaimer {
+            text: "-- \nConfidential".to_string(),
+            html: "<p>Confidential</p>".to_string(),
+            ..Default::default()
+        };
+
+        let message = concat!(
+            "From: john@example.org\r\n",
+            "Content-Type: multipart/alternative; boundary=\"b\"\r\n\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n\r\n",
+            "Hello\r\n",
+            "--b\r\n",
+            "Content-Type: text/html\r\n",
+            "Content-Transfer-Encoding: base64\r\n\r\n",
+            "PGh0bWw+PGJvZHk+SGVsbG88L2JvZHk+PC9odG1sPg==\r\n",
+            "--b--\r\n"
+        );
+        let result =
+            String::from_utf8(append_disclaimer(message.as_bytes(), &disclaimer).unwrap()).unwrap();
+        assert!(result.contains("Hello\r\n\r\n-- \r\nConfidential\r\n\r\n--b\r\n"));
+        let html = mail_parser::MessageParser::new()
+            .parse(result.as_bytes())
+            .unwrap()
+            .body_html(0)
+            .unwrap()
+            .into_owned();
+        assert_eq!(
+            html,
+            "<html><body>Hello<p>Confidential</p>\r\n</body></html>"
+        );
+
+        let message = concat!(
+            "From: john@example.org\r\n",
+            "Content-Type: multipart/signed; boundary=\"b\"\r\n\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n\r\n",
+            "Hello\r\n",
+            "--b--\r\n"
+        );
+        assert!(append_disclaimer(message.as_bytes(), &disclaimer).is_none());
+    }
+}
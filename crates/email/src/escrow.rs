@@ -0,0 +1,277 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use common::config::smtp::{journal::EscrowKey, smime::SmimeKeyPair};
+use mail_parser::{Message, MessageParser, MimeHeaders, PartType};
+use openpgp::{
+    crypto::SessionKey,
+    packet::{PKESK, SKESK},
+    parse::{
+        stream::{DecryptionHelper, DecryptorBuilder, MessageStructure, VerificationHelper},
+        Parse,
+    },
+    types::SymmetricAlgorithm,
+    Cert, Fingerprint, KeyHandle,
+};
+use rasn::types::OctetString;
+use rasn_cms::{
+    algorithms::{AES128_CBC, AES256_CBC},
+    pkcs7_compat::EncapsulatedContentInfo,
+    EnvelopedData, RecipientIdentifier, RecipientInfo, CONTENT_ENVELOPED_DATA,
+};
+use rsa::Pkcs1v15Encrypt;
+use sequoia_openpgp as openpgp;
+
+use crate::crypto::{EncryptionMethod, P};
+
+/// A message decrypted using an escrowed key.
+#[derive(Debug)]
+pub struct DecryptedMessage {
+    pub method: EncryptionMethod,
+    pub key_id: String,
+    pub contents: Vec<u8>,
+}
+
+/// Decrypts an S/MIME or PGP/MIME message using the first escrowed key it was
+/// encrypted for. The non-MIME headers of the original message are kept, and
+/// `Ok(None)` is returned for messages that are not encrypted.
+pub fn escrow_decrypt(
+    raw_message: &[u8],
+    keys: &[EscrowKey],
+) -> Result<Option<DecryptedMessage>, String> {
+    let Some(message) = MessageParser::new().parse(raw_message) else {
+        return Ok(None);
+    };
+    let Some(content_type) = message.content_type() else {
+        return Ok(None);
+    };
+    let subtype = content_type.subtype().unwrap_or_default();
+
+    let (method, key_id, inner_message) =
+        if content_type.ctype().eq_ignore_ascii_case("application")
+            && (subtype.eq_ignore_ascii_case("pkcs7-mime")
+                || subtype.eq_ignore_ascii_case("x-pkcs7-mime"))
+            && content_type
+                .attribute("smime-type")
+                .is_none_or(|st| st.eq_ignore_ascii_case("enveloped-data"))
+        {
+            let contents = message.root_part().contents();
+            let mut result = Err("Message was not encrypted for any escrowed key".to_string());
+            for key in keys {
+                if let EscrowKey::Smime { id, key_pair } = key
+                    && let Some(decrypted) = smime_decrypt(contents, key_pair)?
+                {
+                    result = Ok((EncryptionMethod::SMIME, id.clone(), decrypted));
+                    break;
+                }
+            }
+            result?
+        } else if content_type.ctype().eq_ignore_ascii_case("multipart")
+            && subtype.eq_ignore_ascii_case("encrypted")
+            && content_type
+                .attribute("protocol")
+                .is_some_and(|p| p.eq_ignore_ascii_case("application/pgp-encrypted"))
+        {
+            let contents = pgp_encrypted_part(&message)
+                .ok_or_else(|| "Encrypted part not found".to_string())?;
+            let certs = keys
+                .iter()
+                .filter_map(|key| match key {
+                    EscrowKey::Pgp { id, private_key } => Cert::from_bytes(private_key.as_bytes())
+                        .ok()
+                        .map(|cert| (id.as_str(), cert)),
+                    EscrowKey::Smime { .. } => None,
+                })
+                .collect::<Vec<_>>();
+            let (key_id, decrypted) = pgp_decrypt(contents, &certs)?;
+            (EncryptionMethod::PGP, key_id, decrypted)
+        } else {
+            return Ok(None);
+        };
+
+    // Replace the MIME headers and body with the decrypted entity
+    let root = message.root_part();
+    let mut contents = Vec::with_capacity(raw_message.len() + inner_message.len());
+    for header in root.headers() {
+        if !header.name.is_mime_header() {
+            contents.extend_from_slice(&raw_message[header.offset_field()..header.offset_end()]);
+        }
+    }
+    contents.extend_from_slice(&inner_message);
+
+    Ok(Some(DecryptedMessage {
+        method,
+        key_id,
+        contents,
+    }))
+}
+
+fn smime_decrypt(contents: &[u8], key_pair: &SmimeKeyPair) -> Result<Option<Vec<u8>>, String> {
+    let content_info = rasn::der::decode::<EncapsulatedContentInfo>(contents)
+        .map_err(|err| format!("Failed to parse ContentInfo: {err}"))?;
+    if *content_info.content_type != *CONTENT_ENVELOPED_DATA {
+        return Err("Message does not contain enveloped data".to_string());
+    }
+    let enveloped_data = content_info
+        .content
+        .ok_or_else(|| "Missing enveloped data".to_string())
+        .and_then(|content| {
+            rasn::der::decode::<EnvelopedData>(content.as_bytes())
+                .map_err(|err| format!("Failed to parse EnvelopedData: {err}"))
+        })?;
+    let cert = key_pair
+        .certificates
+        .first()
+        .ok_or_else(|| "Missing escrow certificate".to_string())
+        .and_then(|cert| {
+            rasn::der::decode::<rasn_pkix::Certificate>(cert)
+                .map_err(|err| format!("Failed to parse certificate: {err}"))
+        })?;
+
+    // Find the key encrypted for the escrow certificate
+    let Some(encrypted_key) = enveloped_data.recipient_infos.iter().find_map(|info| {
+        if let RecipientInfo::KeyTransRecipientInfo(info) = info
+            && let RecipientIdentifier::IssuerAndSerialNumber(rid) = &info.rid
+            && rid.issuer == cert.tbs_certificate.issuer
+            && rid.serial_number == cert.tbs_certificate.serial_number
+        {
+            Some(&info.encrypted_key)
+        } else {
+            None
+        }
+    }) else {
+        return Ok(None);
+    };
+    let key = key_pair
+        .private_key
+        .decrypt(Pkcs1v15Encrypt, encrypted_key)
+        .map_err(|err| format!("Failed to decrypt content key: {err}"))?;
+
+    let content_info = &enveloped_data.encrypted_content_info;
+    let iv = content_info
+        .content_encryption_algorithm
+        .parameters
+        .as_ref()
+        .and_then(|params| rasn::der::decode::<OctetString>(params.as_bytes()).ok())
+        .ok_or_else(|| "Missing initialization vector".to_string())?;
+    let encrypted_content = content_info
+        .encrypted_content
+        .as_ref()
+        .ok_or_else(|| "Missing encrypted content".to_string())?;
+    let algorithm = &content_info.content_encryption_algorithm.algorithm;
+
+    let decrypted = if **algorithm == *AES128_CBC {
+        cbc::Decryptor::<aes::Aes128>::new_from_slices(&key, &iv)
+            .map_err(|err| format!("Invalid content key: {err}"))?
+            .decrypt_padded_vec_mut::<Pkcs7>(encrypted_content)
+            .map_err(|err| format!("Failed to decrypt content: {err}"))
+    } else if **algorithm == *AES256_CBC {
+        cbc::Decryptor::<aes::Aes256>::new_from_slices(&key, &iv)
+            .map_err(|err| format!("Invalid content key: {err}"))?
+            .decrypt_padded_vec_mut::<Pkcs7>(encrypted_content)
+            .map_err(|err| format!("Failed to decrypt content: {err}"))
+    } else {
+        Err(format!(
+            "Unsupported content encryption algorithm {algorithm:?}"
+        ))
+    };
+
+    decrypted.map(Some)
+}
+
+fn pgp_encrypted_part<'x>(message: &'x Message<'x>) -> Option<&'x [u8]> {
+    message.parts.iter().skip(1).find_map(|part| {
+        let content_type = part.content_type()?;
+        if content_type.ctype().eq_ignore_ascii_case("application")
+            && content_type
+                .subtype()
+                .is_some_and(|st| st.eq_ignore_ascii_case("octet-stream"))
+        {
+            match &part.body {
+                PartType::Text(text) => Some(text.as_bytes()),
+                PartType::Binary(bytes) | PartType::InlineBinary(bytes) => Some(bytes.as_ref()),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    })
+}
+
+fn pgp_decrypt(contents: &[u8], certs: &[(&str, Cert)]) -> Result<(String, Vec<u8>), String> {
+    let mut decryptor = DecryptorBuilder::from_bytes(contents)
+        .map_err(|err| format!("Failed to parse PGP message: {err}"))?
+        .with_policy(
+            &P,
+            None,
+            EscrowHelper {
+                certs,
+                key_id: None,
+            },
+        )
+        .map_err(|err| format!("Failed to decrypt PGP message: {err}"))?;
+    let mut decrypted = Vec::with_capacity(contents.len());
+    std::io::copy(&mut decryptor, &mut decrypted)
+        .map_err(|err| format!("Failed to decrypt PGP message: {err}"))?;
+    let key_id = decryptor.helper_ref().key_id.clone().unwrap_or_default();
+
+    Ok((key_id, decrypted))
+}
+
+struct EscrowHelper<'x> {
+    certs: &'x [(&'x str, Cert)],
+    key_id: Option<String>,
+}
+
+impl VerificationHelper for EscrowHelper<'_> {
+    fn get_certs(&mut self, _ids: &[KeyHandle]) -> openpgp::Result<Vec<Cert>> {
+        Ok(Vec::new())
+    }
+
+    fn check(&mut self, _structure: MessageStructure) -> openpgp::Result<()> {
+        Ok(())
+    }
+}
+
+impl DecryptionHelper for EscrowHelper<'_> {
+    fn decrypt<D>(
+        &mut self,
+        pkesks: &[PKESK],
+        _skesks: &[SKESK],
+        sym_algo: Option<SymmetricAlgorithm>,
+        mut decrypt: D,
+    ) -> openpgp::Result<Option<Fingerprint>>
+    where
+        D: FnMut(SymmetricAlgorithm, &SessionKey) -> bool,
+    {
+        for (id, cert) in self.certs {
+            for key in cert
+                .keys()
+                .unencrypted_secret()
+                .with_policy(&P, None)
+                .supported()
+                .for_transport_encryption()
+            {
+                let mut keypair = key.key().clone().into_keypair()?;
+                for pkesk in pkesks {
+                    if pkesk
+                        .decrypt(&mut keypair, sym_algo)
+                        .is_some_and(|(algo, session_key)| decrypt(algo, &session_key))
+                    {
+                        self.key_id = Some(id.to_string());
+                        return Ok(Some(cert.fingerprint()));
+                    }
+                }
+            }
+        }
+
+        Err(openpgp::Error::InvalidOperation(
+            "Message was not encrypted for any escrowed key".to_string(),
+        )
+        .into())
+    }
+}
@@ -0,0 +1,291 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use directory::backend::internal::manage;
+use jmap_proto::types::{collection::Collection, property::Property};
+use serde::{Deserialize, Serialize};
+use store::write::{BatchBuilder, Bincode, F_CLEAR, F_VALUE};
+use trc::AddContext;
+
+use std::future::Future;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalAccounts {
+    pub accounts: Vec<ExternalAccount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalAccount {
+    pub id: String,
+    pub protocol: FetchProtocol,
+    pub host: String,
+    #[serde(default)]
+    pub port: u16,
+    #[serde(default = "default_true")]
+    pub tls: bool,
+    pub username: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub oauth: Option<FetchOAuth>,
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub keep: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub state: FetchState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchProtocol {
+    Pop3,
+    Imap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchOAuth {
+    pub client: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchState {
+    pub last_fetch: u64,
+    pub last_success: u64,
+    pub last_error: Option<String>,
+    pub total_fetched: u64,
+    pub uid_validity: u32,
+    pub last_uid: u32,
+    pub seen_uids: Vec<String>,
+    pub access_token: Option<(String, u64)>,
+}
+
+pub trait ExternalAccountManager: Sync + Send {
+    fn external_accounts_get(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<ExternalAccounts>> + Send;
+
+    fn external_accounts_set(
+        &self,
+        account_id: u32,
+        accounts: Vec<ExternalAccount>,
+    ) -> impl Future<Output = trc::Result<ExternalAccounts>> + Send;
+
+    fn external_account_update(
+        &self,
+        account_id: u32,
+        id: &str,
+        state: FetchState,
+        refresh_token: Option<String>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl ExternalAccountManager for Server {
+    async fn external_accounts_get(&self, account_id: u32) -> trc::Result<ExternalAccounts> {
+        self.get_property::<Bincode<ExternalAccounts>>(
+            account_id,
+            Collection::Principal,
+            0,
+            Property::Auth,
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|accounts| accounts.map(|accounts| accounts.inner).unwrap_or_default())
+    }
+
+    async fn external_accounts_set(
+        &self,
+        account_id: u32,
+        accounts: Vec<ExternalAccount>,
+    ) -> trc::Result<ExternalAccounts> {
+        let max_accounts = self.core.jmap.fetch_max_accounts;
+        if max_accounts == 0 {
+            return Err(manage::unsupported(
+                "Fetching from external accounts has been disabled by the system administrator",
+            ));
+        } else if accounts.len() > max_accounts {
+            return Err(manage::error(
+                format!("A maximum of {max_accounts} external accounts can be configured"),
+                None::<u32>,
+            ));
+        }
+
+        // Secrets and fetch state are carried over from the existing entries
+        let current = self.external_accounts_get(account_id).await?;
+        let mut updated = ExternalAccounts::default();
+        for mut account in accounts {
+            account.id = account.id.trim().to_string();
+            account.host = account.host.trim().to_lowercase();
+            account.username = account.username.trim().to_string();
+            account.folder = account
+                .folder
+                .map(|folder| folder.trim().to_string())
+                .filter(|folder| !folder.is_empty());
+            account.state = FetchState::default();
+
+            if let Some(existing) = current.accounts.iter().find(|a| a.id == account.id) {
+                let has_credentials = account.secret.is_some() || account.oauth.is_some();
+                if !has_credentials {
+                    account.secret = existing.secret.clone();
+                    account.oauth = existing.oauth.clone();
+                }
+                if existing.is_same_mailbox(&account) {
+                    account.state = existing.state.clone();
+                    if has_credentials {
+                        account.state.access_token = None;
+                    }
+                }
+            }
+
+            account.validate(self)?;
+            if updated.accounts.iter().any(|a| a.id == account.id) {
+                return Err(invalid_account(
+                    "Duplicate external account id",
+                    &account.id,
+                ));
+            }
+            updated.accounts.push(account);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Principal)
+            .update_document(0);
+        if updated.accounts.is_empty() {
+            batch.value(Property::Auth, (), F_VALUE | F_CLEAR);
+        } else {
+            batch.value(Property::Auth, Bincode::new(updated.clone()), F_VALUE);
+        }
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(updated)
+    }
+
+    async fn external_account_update(
+        &self,
+        account_id: u32,
+        id: &str,
+        state: FetchState,
+        refresh_token: Option<String>,
+    ) -> trc::Result<()> {
+        // The account list is re-read so concurrent edits made by the user are preserved
+        let mut accounts = self.external_accounts_get(account_id).await?;
+        let Some(account) = accounts.accounts.iter_mut().find(|a| a.id == id) else {
+            return Ok(());
+        };
+        account.state = state;
+        if let (Some(refresh_token), Some(oauth)) = (refresh_token, &mut account.oauth) {
+            oauth.refresh_token = refresh_token;
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Principal)
+            .update_document(0)
+            .value(Property::Auth, Bincode::new(accounts), F_VALUE);
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+}
+
+impl ExternalAccount {
+    /// Returns `true` if both entries point to the same remote mailbox, in which case
+    /// the fetch state (seen UIDs) remains valid.
+    pub fn is_same_mailbox(&self, other: &ExternalAccount) -> bool {
+        self.protocol == other.protocol
+            && self.host == other.host
+            && self.username == other.username
+            && self.folder == other.folder
+    }
+
+    pub fn port_or_default(&self) -> u16 {
+        if self.port != 0 {
+            self.port
+        } else {
+            match (self.protocol, self.tls) {
+                (FetchProtocol::Pop3, true) => 995,
+                (FetchProtocol::Pop3, false) => 110,
+                (FetchProtocol::Imap, true) => 993,
+                (FetchProtocol::Imap, false) => 143,
+            }
+        }
+    }
+
+    fn validate(&self, server: &Server) -> trc::Result<()> {
+        if self.id.is_empty() || self.id.len() > 64 {
+            Err(invalid_account("Invalid external account id", &self.id))
+        } else if self.host.is_empty()
+            || self
+                .host
+                .contains(|ch: char| ch.is_whitespace() || ch == '/' || ch == '@')
+        {
+            Err(invalid_account("Invalid external account host", &self.host))
+        } else if self.username.is_empty() {
+            Err(invalid_account(
+                "External account username is required",
+                &self.id,
+            ))
+        } else if self.protocol == FetchProtocol::Pop3 && self.folder.is_some() {
+            Err(invalid_account(
+                "Folders are only supported for IMAP accounts",
+                &self.id,
+            ))
+        } else {
+            match (&self.secret, &self.oauth) {
+                (Some(secret), None) if !secret.is_empty() => Ok(()),
+                (None, Some(oauth)) => {
+                    if !server
+                        .core
+                        .jmap
+                        .fetch_oauth_clients
+                        .contains_key(&oauth.client)
+                    {
+                        Err(invalid_account("Unknown OAuth client", &oauth.client))
+                    } else if oauth.refresh_token.is_empty() {
+                        Err(invalid_account("OAuth refresh token is required", &self.id))
+                    } else {
+                        Ok(())
+                    }
+                }
+                _ => Err(invalid_account(
+                    "Either a password or OAuth credentials are required",
+                    &self.id,
+                )),
+            }
+        }
+    }
+}
+
+fn invalid_account(details: &'static str, value: &str) -> trc::Error {
+    trc::ResourceEvent::BadParameters
+        .into_err()
+        .details(details)
+        .ctx(trc::Key::Value, value.to_string())
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -0,0 +1,361 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use common::{Server, config::smtp::auth::SrsConfig};
+use directory::backend::internal::forward::ManageForwarding;
+use hmac::{Hmac, Mac};
+use mail_parser::MessageParser;
+use store::write::now;
+use trc::{AddContext, MessageIngestEvent};
+
+use crate::delivery::AutogeneratedMessage;
+
+const MAX_DELIVERED_TO: usize = 25;
+const SRS_HASH_LEN: usize = 4;
+const SRS_TS_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub trait MailForwarding: Sync + Send {
+    /// Queues a copy of the message for the account's forwarding addresses and
+    /// returns whether the message should also be delivered locally.
+    fn forward_message(
+        &self,
+        account_id: u32,
+        rcpt: &str,
+        sender: &str,
+        raw_message: &[u8],
+        session_id: u64,
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl MailForwarding for Server {
+    async fn forward_message(
+        &self,
+        account_id: u32,
+        rcpt: &str,
+        sender: &str,
+        raw_message: &[u8],
+        session_id: u64,
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> trc::Result<bool> {
+        let Some(forwarding) = self
+            .core
+            .storage
+            .data
+            .forwarding_by_id(account_id)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(true);
+        };
+
+        // Avoid loops by looking for our own Delivered-To headers
+        if is_forwarding_loop(raw_message, rcpt, &forwarding.forward_to) {
+            trc::event!(
+                MessageIngest(MessageIngestEvent::ForwardLoop),
+                SpanId = session_id,
+                AccountId = account_id,
+                From = sender.to_string(),
+                To = rcpt.to_string(),
+            );
+
+            return Ok(true);
+        }
+
+        // Rewrite the envelope sender so SPF checks pass at the destination
+        let mut sender_address = sender.to_string();
+        if let (Some(srs), Some((_, domain))) =
+            (&self.core.smtp.mail_auth.srs, sender.rsplit_once('@'))
+            && !self
+                .core
+                .storage
+                .directory
+                .is_local_domain(domain)
+                .await
+                .caused_by(trc::location!())?
+            && let Some(address) = srs_forward(srs, sender, now())
+        {
+            sender_address = address;
+        }
+
+        let mut message = Vec::with_capacity(raw_message.len() + rcpt.len() + 16);
+        message.extend_from_slice(b"Delivered-To: ");
+        message.extend_from_slice(rcpt.as_bytes());
+        message.extend_from_slice(b"\r\n");
+        message.extend_from_slice(raw_message);
+
+        trc::event!(
+            MessageIngest(MessageIngestEvent::Forward),
+            SpanId = session_id,
+            AccountId = account_id,
+            From = sender_address.clone(),
+            To = forwarding
+                .forward_to
+                .iter()
+                .map(|r| trc::Value::String(r.clone()))
+                .collect::<Vec<_>>(),
+            Size = message.len(),
+        );
+
+        autogenerated.push(AutogeneratedMessage {
+            sender_address,
+            recipients: forwarding.forward_to,
+            message,
+        });
+
+        Ok(forwarding.keep_copy)
+    }
+}
+
+/// Returns true when the message has already been delivered to the recipient
+/// or any of its forwarding addresses, or went through too many hops.
+pub fn is_forwarding_loop(raw_message: &[u8], rcpt: &str, forward_to: &[String]) -> bool {
+    let Some(message) = MessageParser::new().parse_headers(raw_message) else {
+        return false;
+    };
+    let mut delivered_to = 0;
+    for (name, value) in message.headers_raw() {
+        if name.eq_ignore_ascii_case("Delivered-To") {
+            let value = value.trim();
+            if value.eq_ignore_ascii_case(rcpt)
+                || forward_to
+                    .iter()
+                    .any(|addr| value.eq_ignore_ascii_case(addr))
+            {
+                return true;
+            }
+            delivered_to += 1;
+        }
+    }
+
+    delivered_to >= MAX_DELIVERED_TO
+}
+
+/// Rewrites a sender address using the Sender Rewriting Scheme, senders that
+/// were already rewritten by another forwarder are converted to SRS1.
+pub fn srs_forward(config: &SrsConfig, sender: &str, now: u64) -> Option<String> {
+    let (local, domain) = sender.rsplit_once('@')?;
+    let secret = config.secrets.first()?;
+    if domain.eq_ignore_ascii_case(&config.domain) {
+        return Some(sender.to_string());
+    }
+
+    if let Some(opaque) = strip_srs_prefix(local, "SRS1") {
+        // SRS1=HHHH=first==opaque@prev, the previous forwarder is dropped
+        let (_, rest) = opaque.split_once('=')?;
+        let (first, opaque) = rest.split_once("==")?;
+        let hash = srs_hash(secret, &[first, "=", opaque]);
+        Some(format!("SRS1={hash}={first}=={opaque}@{}", config.domain))
+    } else if let Some(opaque) = strip_srs_prefix(local, "SRS0") {
+        let hash = srs_hash(secret, &[domain, "=", opaque]);
+        Some(format!("SRS1={hash}={domain}=={opaque}@{}", config.domain))
+    } else {
+        let ts = srs_timestamp(now);
+        let hash = srs_hash(secret, &[&ts, domain, local]);
+        Some(format!(
+            "SRS0={hash}={ts}={domain}={local}@{}",
+            config.domain
+        ))
+    }
+}
+
+/// Reverses an SRS address generated by this server, returning the address
+/// the bounce should be delivered to.
+pub fn srs_reverse(config: &SrsConfig, address: &str, now: u64) -> Option<String> {
+    let (local, domain) = address.rsplit_once('@')?;
+    if !domain.eq_ignore_ascii_case(&config.domain) {
+        return None;
+    }
+
+    if let Some(opaque) = strip_srs_prefix(local, "SRS1") {
+        let (hash, rest) = opaque.split_once('=')?;
+        let (first, opaque) = rest.split_once("==")?;
+        if srs_verify(config, hash, &[first, "=", opaque]) && !first.is_empty() {
+            return Some(format!("SRS0={opaque}@{first}"));
+        }
+    } else if let Some(opaque) = strip_srs_prefix(local, "SRS0") {
+        let mut parts = opaque.splitn(4, '=');
+        let hash = parts.next()?;
+        let ts = parts.next()?;
+        let domain = parts.next()?;
+        let local = parts.next()?;
+        if !domain.is_empty()
+            && !local.is_empty()
+            && srs_verify(config, hash, &[ts, domain, local])
+            && srs_age(ts, now).is_some_and(|age| age <= config.max_age)
+        {
+            return Some(format!("{local}@{domain}"));
+        }
+    }
+
+    None
+}
+
+fn strip_srs_prefix<'x>(local: &'x str, prefix: &str) -> Option<&'x str> {
+    // Separators other than '=' are accepted for interoperability
+    if local.len() > prefix.len() + 1
+        && local.is_char_boundary(prefix.len())
+        && local[..prefix.len()].eq_ignore_ascii_case(prefix)
+        && matches!(local.as_bytes()[prefix.len()], b'=' | b'+' | b'-')
+    {
+        Some(&local[prefix.len() + 1..])
+    } else {
+        None
+    }
+}
+
+fn srs_timestamp(now: u64) -> String {
+    let days = (now / 86400) % 1024;
+    [
+        SRS_TS_ALPHABET[(days >> 5) as usize] as char,
+        SRS_TS_ALPHABET[(days & 31) as usize] as char,
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn srs_age(ts: &str, now: u64) -> Option<u64> {
+    let mut days = 0;
+    for ch in ts.bytes() {
+        let pos = SRS_TS_ALPHABET
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(&ch))?;
+        days = (days << 5) | pos as u64;
+    }
+    if ts.len() == 2 {
+        Some(((now / 86400) + 1024 - days) % 1024)
+    } else {
+        None
+    }
+}
+
+fn srs_hash(secret: &str, data: &[&str]) -> String {
+    let mut mac = Hmac::<sha1::Sha1>::new_from_slice(secret.as_bytes()).unwrap();
+    for item in data {
+        mac.update(item.to_lowercase().as_bytes());
+    }
+    let mut hash = STANDARD.encode(mac.finalize().into_bytes());
+    hash.truncate(SRS_HASH_LEN);
+    hash
+}
+
+fn srs_verify(config: &SrsConfig, hash: &str, data: &[&str]) -> bool {
+    // Hashes are compared ignoring case as addresses are usually lowercased
+    config
+        .secrets
+        .iter()
+        .any(|secret| srs_hash(secret, data).eq_ignore_ascii_case(hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use common::config::smtp::auth::SrsConfig;
+
+    use super::{is_forwarding_loop, srs_forward, srs_reverse};
+
+    #[test]
+    fn srs_rewrite() {
+        let config = SrsConfig {
+            domain: "forwarder.org".to_string(),
+            secrets: vec!["new-secret".to_string(), "old-secret".to_string()],
+            max_age: 21,
+        };
+        let old_config = SrsConfig {
+            secrets: vec!["old-secret".to_string()],
+            ..config.clone()
+        };
+        let now = 1_700_000_000;
+
+        // SRS0 round trip
+        let address = srs_forward(&config, "john@example.org", now).unwrap();
+        assert!(address.starts_with("SRS0="), "{address}");
+        assert!(
+            address.ends_with("=example.org=john@forwarder.org"),
+            "{address}"
+        );
+        assert_eq!(
+            srs_reverse(&config, &address, now + 86400).as_deref(),
+            Some("john@example.org")
+        );
+        assert_eq!(
+            srs_reverse(&config, &address.to_lowercase(), now).as_deref(),
+            Some("john@example.org")
+        );
+
+        // Expired and tampered addresses
+        assert_eq!(srs_reverse(&config, &address, now + 86400 * 30), None);
+        assert_eq!(
+            srs_reverse(&config, &address.replace("john", "jane"), now),
+            None
+        );
+        assert_eq!(srs_reverse(&config, "john@forwarder.org", now), None);
+
+        // Rotated secrets are still accepted
+        let address = srs_forward(&old_config, "john@example.org", now).unwrap();
+        assert_eq!(
+            srs_reverse(&config, &address, now).as_deref(),
+            Some("john@example.org")
+        );
+
+        // SRS0 addresses from another forwarder become SRS1
+        let address = srs_forward(&config, "SRS0=HHHH=TT=example.org=john@other.org", now).unwrap();
+        assert!(
+            address.starts_with("SRS1=")
+                && address.ends_with("=other.org==HHHH=TT=example.org=john@forwarder.org"),
+            "{address}"
+        );
+        assert_eq!(
+            srs_reverse(&config, &address, now).as_deref(),
+            Some("SRS0=HHHH=TT=example.org=john@other.org")
+        );
+
+        // SRS1 addresses keep pointing to the first forwarder
+        let address = srs_forward(
+            &config,
+            "SRS1=XXXX=other.org==HHHH=TT=example.org=john@third.org",
+            now,
+        )
+        .unwrap();
+        assert!(
+            address.ends_with("=other.org==HHHH=TT=example.org=john@forwarder.org"),
+            "{address}"
+        );
+        assert_eq!(
+            srs_reverse(&config, &address, now).as_deref(),
+            Some("SRS0=HHHH=TT=example.org=john@other.org")
+        );
+    }
+
+    #[test]
+    fn forwarding_loops() {
+        let forward_to = vec!["jane@remote.org".to_string()];
+        let message = concat!(
+            "Delivered-To: jane@remote.org\r\n",
+            "From: john@example.org\r\n",
+            "Subject: test\r\n\r\n",
+            "test\r\n"
+        );
+
+        assert!(is_forwarding_loop(
+            message.as_bytes(),
+            "john@example.org",
+            &forward_to
+        ));
+        assert!(is_forwarding_loop(
+            message.as_bytes(),
+            "JANE@remote.org",
+            &[]
+        ));
+        assert!(!is_forwarding_loop(
+            message.as_bytes(),
+            "john@example.org",
+            &["bill@remote.org".to_string()]
+        ));
+    }
+}
@@ -12,7 +12,9 @@ use std::{
 
 use common::{
     auth::{AccessToken, ResourceToken},
-    Server,
+    config::spamfilter::{SpamPolicyAction, SpamVerdict},
+    expr::{functions::ResolveVariable, Variable, V_RECIPIENT, V_RECIPIENT_DOMAIN},
+    Server, KV_DELIVERY_DEDUP,
 };
 use directory::Permission;
 use jmap_proto::{
@@ -23,8 +25,8 @@ use jmap_proto::{
     },
 };
 use mail_parser::{
-    parsers::fields::thread::thread_name, Header, HeaderName, HeaderValue, Message, MessageParser,
-    PartType,
+    parsers::fields::thread::thread_name, DateTime, Header, HeaderName, HeaderValue, Message,
+    MessageParser, PartType,
 };
 
 use spam_filter::{
@@ -34,6 +36,7 @@ use std::future::Future;
 use store::rand::Rng;
 use store::{
     ahash::AHashSet,
+    dispatch::lookup::KeyValue,
     query::Filter,
     write::{
         log::{ChangeLogBuilder, Changes, LogInsert},
@@ -43,11 +46,12 @@ use store::{
     BitmapKey, BlobClass, Serialize,
 };
 use trc::{AddContext, MessageIngestEvent};
-use utils::map::vec_map::VecMap;
+use utils::{map::vec_map::VecMap, BlobHash};
 
 use crate::{
+    counters::MailboxCounters,
     index::{IndexMessage, VisitValues, MAX_ID_LENGTH},
-    mailbox::{UidMailbox, INBOX_ID, JUNK_ID},
+    mailbox::{UidMailbox, INBOX_ID, JUNK_ID, TOMBSTONE_ID},
 };
 
 use super::{
@@ -75,6 +79,7 @@ pub struct IngestEmail<'x> {
     pub source: IngestSource<'x>,
     pub spam_classify: bool,
     pub spam_train: bool,
+    pub expires_at: Option<u64>,
     pub session_id: u64,
 }
 
@@ -105,6 +110,11 @@ pub trait EmailIngest: Sync + Send {
         mailbox_id: u32,
     ) -> impl Future<Output = trc::Result<u32>> + Send;
     fn email_bayes_can_train(&self, access_token: &AccessToken) -> bool;
+    fn email_count(&self, account_id: u32) -> impl Future<Output = trc::Result<u64>> + Send;
+    fn has_available_message_quota(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
 impl EmailIngest for Server {
@@ -118,6 +128,9 @@ impl EmailIngest for Server {
         self.has_available_quota(&params.resource, raw_message_len)
             .await
             .caused_by(trc::location!())?;
+        self.has_available_message_quota(account_id)
+            .await
+            .caused_by(trc::location!())?;
 
         // Parse message
         let mut raw_message = Cow::from(params.raw_message);
@@ -131,6 +144,7 @@ impl EmailIngest for Server {
         let mut train_spam = None;
         let mut extra_headers = String::new();
         let mut extra_headers_parsed = Vec::new();
+        let mut expires_at = None;
         match params.source {
             IngestSource::Smtp { deliver_to } => {
                 // Add delivered to header
@@ -145,20 +159,44 @@ impl EmailIngest for Server {
                     });
                 }
 
+                // Tag the message with an expiry time, either set by Sieve or by policy
+                expires_at = match params.expires_at {
+                    Some(expires_at) => Some(expires_at),
+                    None => self
+                        .eval_if::<Duration, _>(
+                            &self.core.smtp.session.data.expire,
+                            &DeliveryRecipient(deliver_to),
+                            params.session_id,
+                        )
+                        .await
+                        .map(|expire| now() + expire.as_secs()),
+                };
+                if let Some(expires_at) = expires_at {
+                    let offset_field = extra_headers.len();
+                    let date = DateTime::from_timestamp(expires_at as i64);
+                    let _ = write!(&mut extra_headers, "Expires: {}\r\n", date.to_rfc822());
+                    extra_headers_parsed.push(Header {
+                        name: HeaderName::Other("Expires".into()),
+                        value: HeaderValue::DateTime(date),
+                        offset_field,
+                        offset_start: offset_field + 8,
+                        offset_end: extra_headers.len(),
+                    });
+                }
+
                 // Spam classification and training
                 if params.spam_classify
                     && self.core.spam.enabled
                     && params.mailbox_ids == [INBOX_ID]
                 {
-                    // Set the spam filter result
-                    is_spam = self
-                        .core
-                        .spam
-                        .headers
-                        .status
-                        .as_ref()
-                        .and_then(|name| message.header(name.as_str()).and_then(|v| v.as_text()))
-                        .is_some_and(|v| v.contains("Yes"));
+                    // Set the spam filter result, as resolved by the recipient's policy
+                    let verdict = self.core.spam.recipient_verdict(
+                        deliver_to,
+                        self.core.spam.headers.status.as_ref().and_then(|name| {
+                            message.header(name.as_str()).and_then(|v| v.as_text())
+                        }),
+                    );
+                    is_spam = !matches!(verdict, SpamVerdict::Ham);
 
                     // Classify the message with user's model
                     if let Some(bayes_config) = self
@@ -217,7 +255,9 @@ impl EmailIngest for Server {
                         }
                     }
 
-                    if is_spam {
+                    if is_spam
+                        && self.core.spam.recipient_action(deliver_to) == SpamPolicyAction::Junk
+                    {
                         params.mailbox_ids[0] = JUNK_ID;
                         params.keywords.push(Keyword::Junk);
                     }
@@ -242,6 +282,7 @@ impl EmailIngest for Server {
 
         // Obtain message references and thread name
         let mut message_id = String::new();
+        let mut dedup_key = None;
         let thread_id = {
             let mut references = Vec::with_capacity(5);
             let mut subject = "";
@@ -278,43 +319,51 @@ impl EmailIngest for Server {
                 }
             }
 
-            // Check for duplicates
-            if params.source.is_smtp()
-                && !message_id.is_empty()
-                && !self
-                    .core
-                    .storage
-                    .data
-                    .filter(
-                        account_id,
-                        Collection::Email,
-                        vec![
-                            Filter::eq(Property::MessageId, &message_id),
-                            Filter::is_in_bitmap(
-                                Property::MailboxIds,
-                                params.mailbox_ids.first().copied().unwrap_or(INBOX_ID),
-                            ),
-                        ],
+            // Check for duplicates delivered within the configured window
+            if let IngestSource::Smtp { deliver_to } = params.source
+                && let Some(window) = self
+                    .eval_if::<Duration, _>(
+                        &self.core.smtp.session.data.deduplicate,
+                        &DeliveryRecipient(deliver_to),
+                        params.session_id,
                     )
                     .await
-                    .caused_by(trc::location!())?
-                    .results
-                    .is_empty()
             {
-                trc::event!(
-                    MessageIngest(MessageIngestEvent::Duplicate),
-                    SpanId = params.session_id,
-                    AccountId = account_id,
-                    MessageId = message_id,
-                );
-
-                return Ok(IngestedEmail {
-                    id: Id::default(),
-                    change_id: u64::MAX,
-                    blob_id: BlobId::default(),
-                    imap_uids: Vec::new(),
-                    size: 0,
-                });
+                // Messages without a Message-ID are matched by their contents
+                let mailbox_id = params.mailbox_ids.first().copied().unwrap_or(INBOX_ID);
+                let mut key = Vec::with_capacity(8 + MAX_ID_LENGTH);
+                key.extend_from_slice(&account_id.to_be_bytes());
+                key.extend_from_slice(&mailbox_id.to_be_bytes());
+                if !message_id.is_empty() {
+                    key.extend_from_slice(message_id.as_bytes());
+                } else {
+                    key.extend_from_slice(BlobHash::from(params.raw_message).as_slice());
+                }
+
+                if let Some(id) = self
+                    .in_memory_store()
+                    .key_get::<i64>(KeyValue::<()>::build_key(KV_DELIVERY_DEDUP, &key))
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    trc::event!(
+                        MessageIngest(MessageIngestEvent::Duplicate),
+                        SpanId = params.session_id,
+                        AccountId = account_id,
+                        MailboxId = mailbox_id,
+                        MessageId = message_id,
+                    );
+
+                    return Ok(IngestedEmail {
+                        id: Id::from(id as u64),
+                        change_id: u64::MAX,
+                        blob_id: BlobId::default(),
+                        imap_uids: Vec::new(),
+                        size: 0,
+                    });
+                }
+
+                dedup_key = Some((key, window));
             }
 
             if !references.is_empty() {
@@ -470,6 +519,14 @@ impl EmailIngest for Server {
             batch.create_document().log(LogInsert());
         }
 
+        // Update mailbox counters
+        let mut counters = MailboxCounters::new();
+        counters.add_message(
+            params.mailbox_ids.iter().copied(),
+            params.keywords.contains(&Keyword::Seen),
+            raw_message_len as usize,
+        );
+
         // Build write batch
         let mailbox_ids_event = mailbox_ids
             .iter()
@@ -504,6 +561,15 @@ impl EmailIngest for Server {
                 vec![],
             );
 
+        // Index the expiry time for the housekeeper
+        if let Some(expires_at) = expires_at {
+            batch.value(Property::Expires, expires_at, F_VALUE).tag(
+                Property::Expires,
+                TagValue::Id(MaybeDynamicId::Static(0)),
+                0,
+            );
+        }
+
         // Request spam training
         if let Some(learn_spam) = train_spam {
             batch.set(
@@ -517,6 +583,7 @@ impl EmailIngest for Server {
         }
 
         // Insert and obtain ids
+        batch.custom(counters);
         let ids = self
             .core
             .storage
@@ -534,6 +601,23 @@ impl EmailIngest for Server {
         // Request FTS index
         self.notify_task_queue();
 
+        // Reference this copy from duplicate deliveries
+        if let Some((key, window)) = dedup_key
+            && let Err(err) = self
+                .in_memory_store()
+                .key_set(
+                    KeyValue::with_prefix(
+                        KV_DELIVERY_DEDUP,
+                        &key,
+                        (id.id() as i64).to_be_bytes().to_vec(),
+                    )
+                    .expires(window.as_secs()),
+                )
+                .await
+        {
+            trc::error!(err.span_id(params.session_id).caused_by(trc::location!()));
+        }
+
         trc::event!(
             MessageIngest(match params.source {
                 IngestSource::Smtp { .. } =>
@@ -657,6 +741,7 @@ impl EmailIngest for Server {
                     changes.log_delete(Collection::Thread, delete_thread_id);
                 }
             }
+            changes.log_update(Collection::Thread, thread_id);
 
             // Move messages to the new threadId
             batch.with_collection(Collection::Email);
@@ -729,9 +814,54 @@ impl EmailIngest for Server {
     }
 
     fn email_bayes_can_train(&self, access_token: &AccessToken) -> bool {
-        self.core.spam.bayes.as_ref().is_some_and(|bayes| {
-            bayes.account_classify && access_token.has_permission(Permission::SpamFilterTrain)
-        })
+        (self.core.spam.rspamd.as_ref().is_some_and(|rspamd| rspamd.learn)
+            || self
+                .core
+                .spam
+                .bayes
+                .as_ref()
+                .is_some_and(|bayes| bayes.account_classify))
+            && access_token.has_permission(Permission::SpamFilterTrain)
+    }
+
+    async fn email_count(&self, account_id: u32) -> trc::Result<u64> {
+        let mut document_ids = self
+            .get_document_ids(account_id, Collection::Email)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default();
+
+        // Tombstoned messages are not counted
+        if let Some(tombstoned_ids) = self
+            .get_tag(
+                account_id,
+                Collection::Email,
+                Property::MailboxIds,
+                TagValue::Id(TOMBSTONE_ID),
+            )
+            .await
+            .caused_by(trc::location!())?
+        {
+            document_ids -= tombstoned_ids;
+        }
+
+        Ok(document_ids.len())
+    }
+
+    async fn has_available_message_quota(&self, account_id: u32) -> trc::Result<()> {
+        let max_messages = self.core.jmap.mail_max_messages;
+        if max_messages != 0 {
+            let total_messages = self.email_count(account_id).await?;
+
+            if total_messages >= max_messages {
+                return Err(trc::LimitEvent::Quota
+                    .into_err()
+                    .ctx(trc::Key::Limit, max_messages)
+                    .ctx(trc::Key::Total, total_messages));
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -749,6 +879,27 @@ impl IngestSource<'_> {
     }
 }
 
+pub(crate) struct DeliveryRecipient<'x>(pub &'x str);
+
+impl ResolveVariable for DeliveryRecipient<'_> {
+    fn resolve_variable(&self, variable: u32) -> Variable<'_> {
+        match variable {
+            V_RECIPIENT => self.0.into(),
+            V_RECIPIENT_DOMAIN => self
+                .0
+                .rsplit_once('@')
+                .map(|(_, domain)| domain)
+                .unwrap_or_default()
+                .into(),
+            _ => "".into(),
+        }
+    }
+
+    fn resolve_global(&self, _: &str) -> Variable<'_> {
+        Variable::Integer(0)
+    }
+}
+
 impl SerializeWithId for LogEmailInsert {
     fn serialize_with_id(&self, ids: &AssignedIds) -> trc::Result<Vec<u8>> {
         let thread_id = match self.0 {
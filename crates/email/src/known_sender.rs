@@ -0,0 +1,160 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, io::Write};
+
+use common::{config::spamfilter::SpamFilterHeaderConfig, Server, KV_KNOWN_SENDER};
+use mail_parser::MessageParser;
+use store::dispatch::lookup::KeyValue;
+use trc::AddContext;
+
+use crate::auth_report::AuthReport;
+
+pub trait KnownSenders: Sync + Send {
+    fn known_senders_learn<'x>(
+        &self,
+        account_id: u32,
+        recipients: impl IntoIterator<Item = &'x str> + Send,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn is_known_sender(
+        &self,
+        account_id: u32,
+        sender: &str,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl KnownSenders for Server {
+    async fn known_senders_learn<'x>(
+        &self,
+        account_id: u32,
+        recipients: impl IntoIterator<Item = &'x str> + Send,
+        session_id: u64,
+    ) {
+        let Some(expiry) = self
+            .core
+            .spam
+            .expiry
+            .known_sender
+            .filter(|_| self.core.spam.known_senders.is_some())
+        else {
+            return;
+        };
+
+        for rcpt in recipients {
+            if let Err(err) = self
+                .in_memory_store()
+                .key_set(
+                    KeyValue::with_prefix(
+                        KV_KNOWN_SENDER,
+                        known_sender_key(account_id, rcpt),
+                        vec![],
+                    )
+                    .expires(expiry),
+                )
+                .await
+            {
+                trc::error!(err
+                    .span_id(session_id)
+                    .account_id(account_id)
+                    .details("Failed to store known sender")
+                    .caused_by(trc::location!()));
+            }
+        }
+    }
+
+    async fn is_known_sender(&self, account_id: u32, sender: &str) -> trc::Result<bool> {
+        if sender.is_empty() {
+            return Ok(false);
+        }
+
+        self.in_memory_store()
+            .key_exists(KeyValue::<()>::build_key(
+                KV_KNOWN_SENDER,
+                known_sender_key(account_id, sender),
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+}
+
+fn known_sender_key(account_id: u32, address: &str) -> Vec<u8> {
+    let address = address.to_lowercase();
+    let mut key = Vec::with_capacity(std::mem::size_of::<u32>() + address.len());
+    key.extend_from_slice(&account_id.to_be_bytes());
+    key.extend_from_slice(address.as_bytes());
+    key
+}
+
+/// Returns whether this server authenticated the envelope sender, either with
+/// SPF or with a DKIM signature from the sender's domain.
+pub fn is_sender_authenticated(
+    raw_message: &[u8],
+    sender: &str,
+    server_name: &str,
+    headers: &SpamFilterHeaderConfig,
+) -> bool {
+    let sender = sender.to_lowercase();
+    let Some((_, domain)) = sender.rsplit_once('@') else {
+        return false;
+    };
+    let report = AuthReport::parse(raw_message, server_name, headers);
+    if !report
+        .authserv_id
+        .as_ref()
+        .is_some_and(|id| id.eq_ignore_ascii_case(server_name))
+    {
+        return false;
+    }
+
+    report.spf.iter().any(|result| {
+        result.result == "pass"
+            && result
+                .properties
+                .get("smtp.mailfrom")
+                .is_some_and(|from| from.eq_ignore_ascii_case(&sender))
+    }) || report.dkim.iter().any(|result| {
+        result.result == "pass"
+            && result
+                .properties
+                .get("header.d")
+                .is_some_and(|d| d.eq_ignore_ascii_case(domain))
+    })
+}
+
+/// Records a spam filter exemption in the message headers, replacing the
+/// spam status header when the message was rescored.
+pub fn add_exemption_headers(
+    raw_message: &[u8],
+    headers: &SpamFilterHeaderConfig,
+    exemption: &str,
+    status: Option<&str>,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(raw_message.len() + 128);
+    if let Some(name) = &headers.exemption {
+        let _ = write!(&mut message, "{name}: {exemption}\r\n");
+    }
+
+    match (status, &headers.status) {
+        (Some(status), Some(name)) => {
+            let _ = write!(&mut message, "{name}: {status}\r\n");
+            let mut last_offset = 0;
+            if let Some(parsed) = MessageParser::new().parse_headers(raw_message) {
+                for header in parsed.headers() {
+                    if header.name.as_str().eq_ignore_ascii_case(name) {
+                        message.extend_from_slice(&raw_message[last_offset..header.offset_field]);
+                        last_offset = header.offset_end;
+                    }
+                }
+            }
+            message.extend_from_slice(&raw_message[last_offset..]);
+        }
+        _ => message.extend_from_slice(raw_message),
+    }
+
+    message
+}
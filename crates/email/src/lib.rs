@@ -4,11 +4,23 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod auth_report;
 pub mod cache;
+pub mod conversation;
+pub mod counters;
 pub mod crypto;
 pub mod delivery;
+pub mod disclaimer;
+pub mod escrow;
+pub mod fetch;
+pub mod forward;
 pub mod index;
+pub mod known_sender;
 pub mod ingest;
+pub mod link_protection;
 pub mod mailbox;
 pub mod metadata;
+pub mod provision;
+pub mod sender_list;
 pub mod sieve;
+pub mod smime;
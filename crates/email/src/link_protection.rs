@@ -0,0 +1,263 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::ops::Range;
+
+use common::config::spamfilter::{LinkProtectionConfig, LinkProtectionMode};
+use mail_builder::encoders::{
+    base64::base64_encode_mime, quoted_printable::quoted_printable_encode,
+};
+use mail_parser::{
+    decoders::{base64::base64_decode, quoted_printable::quoted_printable_decode},
+    Encoding, MessageParser, PartType,
+};
+
+/// Rewrites or tags the links contained in the HTML parts of a message.
+/// Returns `None` if the message was not modified.
+pub fn protect_links(
+    raw_message: &[u8],
+    config: &LinkProtectionConfig,
+    is_spam: bool,
+) -> Option<Vec<u8>> {
+    if config.spam_only && !is_spam {
+        return None;
+    }
+
+    let message = MessageParser::new().parse(raw_message)?;
+    let mut changes = Vec::new();
+    for part_id in &message.html_body {
+        let part = message.parts.get(*part_id)?;
+        if !matches!(part.body, PartType::Html(_)) || part.offset_body >= part.offset_end {
+            continue;
+        }
+
+        let body = raw_message.get(part.offset_body..part.offset_end)?;
+        let html = decode_part(body, part.encoding)?;
+        if let Some(html) = rewrite_html(&html, config, is_spam) {
+            changes.push((
+                part.offset_body..part.offset_end,
+                encode_part(html, part.encoding, body)?,
+            ));
+        }
+    }
+
+    (!changes.is_empty()).then(|| splice_parts(raw_message, changes))
+}
+
+pub(crate) fn decode_part(body: &[u8], encoding: Encoding) -> Option<Vec<u8>> {
+    match encoding {
+        Encoding::None => Some(body.to_vec()),
+        Encoding::QuotedPrintable => quoted_printable_decode(body),
+        Encoding::Base64 => base64_decode(body),
+    }
+}
+
+pub(crate) fn encode_part(
+    contents: Vec<u8>,
+    encoding: Encoding,
+    original: &[u8],
+) -> Option<Vec<u8>> {
+    let mut encoded = Vec::with_capacity(contents.len() + 128);
+    match encoding {
+        Encoding::None => encoded = contents,
+        Encoding::QuotedPrintable => {
+            quoted_printable_encode(&contents, &mut encoded, false, true).ok()?;
+        }
+        Encoding::Base64 => {
+            base64_encode_mime(&contents, &mut encoded, false).ok()?;
+        }
+    }
+    if !encoded.ends_with(b"\n") && original.ends_with(b"\n") {
+        encoded.extend_from_slice(b"\r\n");
+    }
+    Some(encoded)
+}
+
+/// Replaces the byte ranges of a message with new contents, ranges must be sorted.
+pub(crate) fn splice_parts(raw_message: &[u8], changes: Vec<(Range<usize>, Vec<u8>)>) -> Vec<u8> {
+    let mut result = Vec::with_capacity(raw_message.len() + 1024);
+    let mut last_offset = 0;
+    for (range, contents) in changes {
+        result.extend_from_slice(&raw_message[last_offset..range.start]);
+        result.extend_from_slice(&contents);
+        last_offset = range.end;
+    }
+    result.extend_from_slice(&raw_message[last_offset..]);
+    result
+}
+
+fn rewrite_html(html: &[u8], config: &LinkProtectionConfig, is_spam: bool) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(html.len() + 256);
+    let mut last_offset = 0;
+    let mut pos = 0;
+    let mut is_modified = false;
+
+    while let Some(start) = html[pos..].iter().position(|&ch| ch == b'<') {
+        let tag_start = pos + start;
+        let Some(tag_end) = find_tag_end(html, tag_start) else {
+            break;
+        };
+        pos = tag_end;
+
+        if !is_anchor(&html[tag_start..tag_end]) {
+            continue;
+        }
+        let Some((value_start, value_end)) = find_href(html, tag_start, tag_end) else {
+            continue;
+        };
+        let url = String::from_utf8_lossy(&html[value_start..value_end])
+            .trim()
+            .replace("&amp;", "&");
+        if config.is_excluded(&url) {
+            continue;
+        }
+
+        match &config.mode {
+            LinkProtectionMode::Rewrite { redirector } => {
+                let url = form_urlencoded::byte_serialize(url.as_bytes()).collect::<String>();
+                let rewritten = redirector
+                    .replace("{url}", &url)
+                    .replace("{verdict}", if is_spam { "spam" } else { "ham" });
+                result.extend_from_slice(&html[last_offset..value_start]);
+                result.extend_from_slice(
+                    rewritten
+                        .replace('&', "&amp;")
+                        .replace('"', "&quot;")
+                        .replace('\'', "&#39;")
+                        .as_bytes(),
+                );
+                last_offset = value_end;
+            }
+            LinkProtectionMode::Tag { tag } => {
+                result.extend_from_slice(&html[last_offset..tag_start]);
+                result.extend_from_slice(b"<span style=\"color:#c00;font-weight:bold\">");
+                result.extend_from_slice(
+                    tag.replace('&', "&amp;")
+                        .replace('<', "&lt;")
+                        .replace('>', "&gt;")
+                        .as_bytes(),
+                );
+                result.extend_from_slice(b"</span> ");
+                last_offset = tag_start;
+            }
+        }
+        is_modified = true;
+    }
+
+    if is_modified {
+        result.extend_from_slice(&html[last_offset..]);
+        Some(result)
+    } else {
+        None
+    }
+}
+
+fn find_tag_end(html: &[u8], tag_start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (pos, &ch) in html.iter().enumerate().skip(tag_start + 1) {
+        match (ch, quote) {
+            (b'"' | b'\'', None) => quote = Some(ch),
+            (ch, Some(q)) if ch == q => quote = None,
+            (b'>', None) => return Some(pos + 1),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn is_anchor(tag: &[u8]) -> bool {
+    tag.len() > 3 && tag[1].eq_ignore_ascii_case(&b'a') && tag[2].is_ascii_whitespace()
+}
+
+fn find_href(html: &[u8], tag_start: usize, tag_end: usize) -> Option<(usize, usize)> {
+    let mut pos = tag_start + 2;
+    while pos + 4 < tag_end {
+        if html[pos - 1].is_ascii_whitespace() && html[pos..pos + 4].eq_ignore_ascii_case(b"href") {
+            let mut value_start = pos + 4;
+            while html[value_start].is_ascii_whitespace() {
+                value_start += 1;
+            }
+            if html[value_start] != b'=' {
+                pos += 4;
+                continue;
+            }
+            value_start += 1;
+            while html[value_start].is_ascii_whitespace() {
+                value_start += 1;
+            }
+
+            return match html[value_start] {
+                quote @ (b'"' | b'\'') => {
+                    let value_start = value_start + 1;
+                    html[value_start..tag_end]
+                        .iter()
+                        .position(|&ch| ch == quote)
+                        .map(|len| (value_start, value_start + len))
+                }
+                _ => {
+                    let len = html[value_start..tag_end - 1]
+                        .iter()
+                        .position(|&ch| ch.is_ascii_whitespace())
+                        .unwrap_or(tag_end - 1 - value_start);
+                    Some((value_start, value_start + len))
+                }
+            };
+        }
+        pos += 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use common::config::spamfilter::{LinkProtectionConfig, LinkProtectionMode};
+
+    use super::rewrite_html;
+
+    #[test]
+    fn rewrite_links() {
+        let mut config = LinkProtectionConfig {
+            mode: LinkProtectionMode::Rewrite {
+                redirector: "https://safe.example.org/?u={url}&v={verdict}".to_string(),
+            },
+            spam_only: false,
+            domains: Default::default(),
+            exclude: Default::default(),
+        };
+        config.exclude.insert("safe.example.org");
+        config.exclude.insert("*.trusted.org");
+
+        let html = concat!(
+            "<p>Hi <A HREF=\"https://evil.test/login?a=1&amp;b=2\">login</A> ",
+            "<a class='x' href='https://www.trusted.org/'>ok</a> ",
+            "<a href=mailto:john@example.org>mail</a> ",
+            "<a href=http://evil.test/x>x</a></p>"
+        );
+        assert_eq!(
+            String::from_utf8(rewrite_html(html.as_bytes(), &config, true).unwrap()).unwrap(),
+            concat!(
+                "<p>Hi <A HREF=\"https://safe.example.org/?u=https%3A%2F%2Fevil.test",
+                "%2Flogin%3Fa%3D1%26b%3D2&amp;v=spam\">login</A> ",
+                "<a class='x' href='https://www.trusted.org/'>ok</a> ",
+                "<a href=mailto:john@example.org>mail</a> ",
+                "<a href=https://safe.example.org/?u=http%3A%2F%2Fevil.test%2Fx&amp;v=spam>x</a></p>"
+            )
+        );
+
+        config.mode = LinkProtectionMode::Tag {
+            tag: "[Suspicious]".to_string(),
+        };
+        assert_eq!(
+            String::from_utf8(
+                rewrite_html(b"<a href=\"http://evil.test\">x</a>", &config, true).unwrap()
+            )
+            .unwrap(),
+            "<span style=\"color:#c00;font-weight:bold\">[Suspicious]</span> <a href=\"http://evil.test\">x</a>"
+        );
+        assert!(rewrite_html(b"<p>no links</p>", &config, true).is_none());
+    }
+}
@@ -7,12 +7,16 @@
 use std::{future::Future, slice::Iter};
 
 use common::{config::jmap::settings::SpecialUse, Server};
+use directory::{backend::internal::PrincipalField, QueryBy};
 use jmap_proto::{
     object::{
         index::{IndexAs, IndexProperty, ObjectIndexBuilder},
         Object,
     },
-    types::{collection::Collection, id::Id, keyword::Keyword, property::Property, value::Value},
+    types::{
+        collection::Collection, id::Id, keyword::Keyword, property::Property, state::StateChange,
+        type_state::DataType, value::Value,
+    },
 };
 use store::{
     ahash::AHashSet,
@@ -20,15 +24,15 @@ use store::{
     rand,
     roaring::RoaringBitmap,
     write::{
-        BatchBuilder, BitmapClass, DeserializeFrom, MaybeDynamicId, Operation, SerializeInto,
-        TagValue, ToBitmaps,
+        assert::HashedValue, BatchBuilder, BitmapClass, DeserializeFrom, MaybeDynamicId, Operation,
+        SerializeInto, TagValue, ToBitmaps,
     },
     Serialize, U32_LEN,
 };
 use trc::AddContext;
 use utils::codec::leb128::{Leb128Iterator, Leb128Vec};
 
-use crate::cache::ThreadCache;
+use crate::{cache::ThreadCache, provision::AccountProvision};
 
 pub const INBOX_ID: u32 = 0;
 pub const TRASH_ID: u32 = 1;
@@ -62,6 +66,12 @@ pub static SCHEMA: &[IndexProperty] = &[
     IndexProperty::new(Property::Acl).index_as(IndexAs::Acl),
 ];
 
+#[derive(Debug, Clone, Copy)]
+pub struct MailboxLimits {
+    pub max_count: Option<usize>,
+    pub max_depth: usize,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct UidMailbox {
     pub mailbox_id: u32,
@@ -111,6 +121,16 @@ pub trait MailboxFnc: Sync + Send {
         account_id: u32,
         role: &str,
     ) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
+
+    fn mailbox_map_special_use(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<(String, SpecialUse)>>> + Send;
+
+    fn mailbox_limits(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<MailboxLimits>> + Send;
 }
 
 impl MailboxFnc for Server {
@@ -177,8 +197,12 @@ impl MailboxFnc for Server {
             .data
             .write(batch.build())
             .await
-            .caused_by(trc::location!())
-            .map(|_| mailbox_ids)
+            .caused_by(trc::location!())?;
+
+        // Apply the account provisioning template
+        mailbox_ids.extend(self.account_provision(account_id).await?);
+
+        Ok(mailbox_ids)
     }
 
     async fn mailbox_create_path(
@@ -438,6 +462,134 @@ impl MailboxFnc for Server {
             .caused_by(trc::location!())
             .map(|r| r.results.min())
     }
+
+    async fn mailbox_map_special_use(
+        &self,
+        account_id: u32,
+    ) -> trc::Result<Vec<(String, SpecialUse)>> {
+        let domain = self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .caused_by(trc::location!())?
+            .and_then(|principal| {
+                principal
+                    .get_str_array(PrincipalField::Emails)
+                    .and_then(|emails| emails.first())
+                    .and_then(|email| email.rsplit_once('@'))
+                    .map(|(_, domain)| domain.to_lowercase())
+            });
+
+        // Obtain the roles in use and the folders without a role
+        let mut used_roles = AHashSet::new();
+        let mut candidates = Vec::new();
+        for mailbox_id in self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default()
+        {
+            let Some(mailbox) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::Mailbox,
+                    mailbox_id,
+                    Property::Value,
+                )
+                .await
+                .caused_by(trc::location!())?
+            else {
+                continue;
+            };
+
+            match mailbox.inner.properties.get(&Property::Role) {
+                Some(Value::Text(role)) if !role.is_empty() => {
+                    used_roles.insert(role.to_string());
+                }
+                _ => {
+                    if let Some(special_use) = mailbox
+                        .inner
+                        .properties
+                        .get(&Property::Name)
+                        .and_then(|name| name.as_string())
+                        .and_then(|name| self.core.jmap.folder_special_use(domain.as_deref(), name))
+                    {
+                        let is_top_level = matches!(
+                            mailbox.inner.properties.get(&Property::ParentId),
+                            Some(Value::Id(id)) if id.document_id() == 0
+                        );
+                        candidates.push((!is_top_level, mailbox_id, special_use, mailbox));
+                    }
+                }
+            }
+        }
+
+        // Top-level folders take precedence, each role is assigned only once
+        candidates.sort_unstable_by_key(|(is_nested, mailbox_id, _, _)| (*is_nested, *mailbox_id));
+        let mut mapped = Vec::new();
+        let mut changes = self.begin_changes(account_id)?;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox);
+        for (_, mailbox_id, special_use, mailbox) in candidates {
+            let Some(role) = special_use.as_role() else {
+                continue;
+            };
+            if !used_roles.insert(role.to_string()) {
+                continue;
+            }
+
+            let name = mailbox
+                .inner
+                .properties
+                .get(&Property::Name)
+                .and_then(|name| name.as_string())
+                .unwrap_or_default()
+                .to_string();
+            batch.update_document(mailbox_id).custom(
+                ObjectIndexBuilder::new(SCHEMA)
+                    .with_current(mailbox)
+                    .with_changes(Object::with_capacity(1).with_property(Property::Role, role)),
+            );
+            changes.log_update(Collection::Mailbox, mailbox_id);
+            mapped.push((name, special_use));
+        }
+
+        if !mapped.is_empty() {
+            let change_id = changes.change_id;
+            batch.custom(changes);
+            self.store()
+                .write(batch.build())
+                .await
+                .caused_by(trc::location!())?;
+            self.broadcast_state_change(
+                StateChange::new(account_id).with_change(DataType::Mailbox, change_id),
+            )
+            .await;
+        }
+
+        Ok(mapped)
+    }
+
+    async fn mailbox_limits(&self, account_id: u32) -> trc::Result<MailboxLimits> {
+        // Limits are evaluated for the account owning the mailboxes
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(MailboxLimits {
+            max_count: access_token.mailbox_max_count.or_else(|| {
+                (self.core.jmap.mailbox_max_count > 0).then_some(self.core.jmap.mailbox_max_count)
+            }),
+            max_depth: access_token
+                .mailbox_max_depth
+                .unwrap_or(self.core.jmap.mailbox_max_depth),
+        })
+    }
 }
 
 impl PartialEq for UidMailbox {
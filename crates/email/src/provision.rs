@@ -0,0 +1,299 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::BTreeMap;
+
+use common::{
+    KV_LOCK_PROVISION, Server,
+    auth::AccessToken,
+    config::jmap::settings::{AccountTemplate, Escape, render_template},
+};
+use directory::{QueryBy, Type};
+use jmap_proto::{
+    object::{Object, index::ObjectIndexBuilder},
+    types::{blob::BlobId, collection::Collection, property::Property, value::Value},
+};
+use mail_builder::{MessageBuilder, headers::address::Address};
+use mail_parser::MessageParser;
+use store::{
+    BlobClass,
+    write::{BatchBuilder, BlobOp, DirectoryClass, log::LogInsert},
+};
+use trc::AddContext;
+
+use crate::{
+    ingest::{EmailIngest, IngestEmail, IngestSource},
+    mailbox::{INBOX_ID, MailboxFnc},
+    sieve::SCHEMA,
+};
+use std::future::Future;
+
+const PROVISION_LOCK_EXPIRY: u64 = 300;
+
+pub trait AccountProvision: Sync + Send {
+    fn account_provision(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<u32>>> + Send;
+
+    fn account_provision_sieve(
+        &self,
+        account_id: u32,
+        name: &str,
+        script: &str,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn account_provision_welcome(
+        &self,
+        access_token: &AccessToken,
+        template: &AccountTemplate,
+        variables: &BTreeMap<String, serde_json::Value>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl AccountProvision for Server {
+    async fn account_provision(&self, account_id: u32) -> trc::Result<Vec<u32>> {
+        let mut mailbox_ids = Vec::new();
+        if self.core.jmap.account_templates.is_empty() {
+            return Ok(mailbox_ids);
+        }
+
+        // Only individual accounts are provisioned
+        let Some(principal) = self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|p| p.typ() == Type::Individual)
+        else {
+            return Ok(mailbox_ids);
+        };
+        let access_token = self
+            .get_access_token(principal)
+            .await
+            .caused_by(trc::location!())?;
+        let Some(email) = access_token.emails.first() else {
+            return Ok(mailbox_ids);
+        };
+        let Some(template) = email
+            .rsplit_once('@')
+            .and_then(|(_, domain)| self.core.jmap.account_template(domain))
+        else {
+            return Ok(mailbox_ids);
+        };
+
+        // Make sure concurrent first accesses provision the account only once
+        if !self
+            .in_memory_store()
+            .try_lock(
+                KV_LOCK_PROVISION,
+                &account_id.to_be_bytes(),
+                PROVISION_LOCK_EXPIRY,
+            )
+            .await
+            .caused_by(trc::location!())?
+        {
+            return Ok(mailbox_ids);
+        }
+
+        // Create folders
+        for folder in &template.folders {
+            match self.mailbox_create_path(account_id, folder).await {
+                Ok(Some((mailbox_id, _))) => mailbox_ids.push(mailbox_id),
+                Ok(None) => {
+                    trc::event!(
+                        Store(trc::StoreEvent::UnexpectedError),
+                        AccountId = account_id,
+                        Details = "Invalid provisioning folder name",
+                        Value = folder.clone(),
+                    );
+                }
+                Err(err) => {
+                    trc::error!(
+                        err.account_id(account_id)
+                            .details("Failed to create provisioning folder")
+                    );
+                }
+            }
+        }
+
+        // Create and activate the default sieve script
+        if let Some((name, script)) = &template.sieve
+            && let Err(err) = self.account_provision_sieve(account_id, name, script).await
+        {
+            trc::error!(
+                err.account_id(account_id)
+                    .details("Failed to create provisioning sieve script")
+            );
+        }
+
+        // Deliver the welcome message
+        if template.welcome.is_some() {
+            let variables = template_variables(
+                &access_token.name,
+                access_token
+                    .description
+                    .as_deref()
+                    .unwrap_or(access_token.name.as_str()),
+                email,
+            );
+
+            if let Err(err) = self
+                .account_provision_welcome(&access_token, template, &variables)
+                .await
+            {
+                trc::error!(
+                    err.account_id(account_id)
+                        .details("Failed to deliver welcome message")
+                );
+            }
+        }
+
+        Ok(mailbox_ids)
+    }
+
+    async fn account_provision_sieve(
+        &self,
+        account_id: u32,
+        name: &str,
+        script: &str,
+    ) -> trc::Result<()> {
+        let mut script_bytes = script.as_bytes().to_vec();
+        let script_size = script_bytes.len() as i64;
+        let compiled_script = self
+            .core
+            .sieve
+            .untrusted_compiler
+            .compile(&script_bytes)
+            .map_err(|err| {
+                trc::SieveEvent::UnexpectedError
+                    .into_err()
+                    .details("Failed to compile provisioning sieve script")
+                    .reason(err)
+            })?;
+        script_bytes.extend(bincode::serialize(&compiled_script).unwrap_or_default());
+
+        // Write script blob
+        let blob_id = BlobId::new(
+            self.put_blob(account_id, &script_bytes, false).await?.hash,
+            BlobClass::Linked {
+                account_id,
+                collection: Collection::SieveScript.into(),
+                document_id: 0,
+            },
+        )
+        .with_section_size(script_size as usize);
+
+        // Write record
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::SieveScript)
+            .create_document()
+            .log(LogInsert())
+            .add(DirectoryClass::UsedQuota(account_id), script_size)
+            .set(
+                BlobOp::Link {
+                    hash: blob_id.hash.clone(),
+                },
+                Vec::new(),
+            )
+            .custom(
+                ObjectIndexBuilder::new(SCHEMA).with_changes(
+                    Object::with_capacity(3)
+                        .with_property(Property::Name, name.to_string())
+                        .with_property(Property::IsActive, Value::Bool(true))
+                        .with_property(Property::BlobId, Value::BlobId(blob_id)),
+                ),
+            );
+        self.store()
+            .write(batch)
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+
+    async fn account_provision_welcome(
+        &self,
+        access_token: &AccessToken,
+        template: &AccountTemplate,
+        variables: &BTreeMap<String, serde_json::Value>,
+    ) -> trc::Result<()> {
+        let Some(welcome) = &template.welcome else {
+            return Ok(());
+        };
+        let render = |value: &str, escape: Escape| {
+            render_template(value, variables, escape).map_err(|err| {
+                trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
+                    .into_err()
+                    .details("Failed to render welcome message")
+                    .reason(err)
+            })
+        };
+
+        let from = welcome.from_address.as_deref().unwrap_or_default();
+        let to = variables
+            .get("email")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let mut builder = MessageBuilder::new()
+            .from(match &welcome.from_name {
+                Some(name) => Address::new_address(name.as_str().into(), from),
+                None => Address::new_address(None::<&str>, from),
+            })
+            .to(to)
+            .subject(render(&welcome.subject, Escape::Header)?);
+        if let Some(text_body) = &welcome.text_body {
+            builder = builder.text_body(render(text_body, Escape::None)?);
+        }
+        if let Some(html_body) = &welcome.html_body {
+            builder = builder.html_body(render(html_body, Escape::Html)?);
+        }
+        let raw_message = builder.write_to_vec().map_err(|err| {
+            trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
+                .into_err()
+                .details("Failed to build welcome message")
+                .reason(err)
+        })?;
+
+        self.email_ingest(IngestEmail {
+            raw_message: &raw_message,
+            message: MessageParser::new().parse(&raw_message),
+            resource: access_token.as_resource_token(),
+            mailbox_ids: vec![INBOX_ID],
+            keywords: vec![],
+            received_at: None,
+            source: IngestSource::Smtp { deliver_to: to },
+            spam_classify: false,
+            spam_train: false,
+            expires_at: None,
+            session_id: 0,
+        })
+        .await
+        .map(|_| ())
+    }
+}
+
+/// Variables available to the welcome message and identity signature templates.
+pub fn template_variables(
+    account: &str,
+    name: &str,
+    email: &str,
+) -> BTreeMap<String, serde_json::Value> {
+    let domain = email.rsplit_once('@').map_or("", |(_, domain)| domain);
+
+    BTreeMap::from_iter(
+        [
+            ("account", account),
+            ("name", name),
+            ("email", email),
+            ("domain", domain),
+        ]
+        .map(|(k, v)| (k.to_string(), serde_json::Value::String(v.to_string()))),
+    )
+}
@@ -0,0 +1,159 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use directory::backend::internal::manage;
+use jmap_proto::types::{collection::Collection, property::Property};
+use serde::{Deserialize, Serialize};
+use store::write::{BatchBuilder, Bincode, F_CLEAR, F_VALUE};
+use trc::AddContext;
+
+use std::future::Future;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SenderLists {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub block: Vec<String>,
+}
+
+pub trait SenderListManager: Sync + Send {
+    fn sender_lists_get(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<SenderLists>> + Send;
+
+    fn sender_lists_set(
+        &self,
+        account_id: u32,
+        lists: SenderLists,
+    ) -> impl Future<Output = trc::Result<SenderLists>> + Send;
+}
+
+impl SenderListManager for Server {
+    async fn sender_lists_get(&self, account_id: u32) -> trc::Result<SenderLists> {
+        self.get_property::<Bincode<SenderLists>>(
+            account_id,
+            Collection::Principal,
+            0,
+            Property::Addresses,
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|lists| lists.map(|lists| lists.inner).unwrap_or_default())
+    }
+
+    async fn sender_lists_set(
+        &self,
+        account_id: u32,
+        lists: SenderLists,
+    ) -> trc::Result<SenderLists> {
+        let max_entries = self.core.spam.max_user_list_entries;
+        if max_entries == 0 {
+            return Err(manage::unsupported(
+                "Personal sender lists have been disabled by the system administrator",
+            ));
+        }
+
+        let lists = lists.normalize()?;
+        for (name, list) in [("allow", &lists.allow), ("block", &lists.block)] {
+            if list.len() > max_entries {
+                return Err(manage::error(
+                    format!("The {name} list exceeds the maximum of {max_entries} entries"),
+                    None::<u32>,
+                ));
+            }
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Principal)
+            .update_document(0);
+        if lists.is_empty() {
+            batch.value(Property::Addresses, (), F_VALUE | F_CLEAR);
+        } else {
+            batch.value(Property::Addresses, Bincode::new(lists.clone()), F_VALUE);
+        }
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(lists)
+    }
+}
+
+impl SenderLists {
+    /// Returns `Some(true)` if the sender is allowed and `Some(false)` if it is blocked.
+    /// Address entries take precedence over domain entries, and allow entries over
+    /// block entries.
+    pub fn sender_status(&self, sender: &str) -> Option<bool> {
+        let sender = sender.to_lowercase();
+        let (_, domain) = sender.rsplit_once('@')?;
+
+        if self.allow.contains(&sender) {
+            Some(true)
+        } else if self.block.contains(&sender) {
+            Some(false)
+        } else if self.allow.iter().any(|entry| domain_matches(domain, entry)) {
+            Some(true)
+        } else if self.block.iter().any(|entry| domain_matches(domain, entry)) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.block.is_empty()
+    }
+
+    fn normalize(self) -> trc::Result<Self> {
+        let mut lists = SenderLists::default();
+
+        for (source, target) in [
+            (self.allow, &mut lists.allow),
+            (self.block, &mut lists.block),
+        ] {
+            for entry in source {
+                let entry = entry.trim().trim_start_matches('@').to_lowercase();
+                let is_valid = match entry.rsplit_once('@') {
+                    Some((local, domain)) => !local.is_empty() && is_valid_domain(domain),
+                    None => is_valid_domain(&entry),
+                };
+                if !is_valid {
+                    return Err(trc::ResourceEvent::BadParameters
+                        .into_err()
+                        .details("Invalid sender address or domain")
+                        .ctx(trc::Key::Value, entry));
+                }
+                target.push(entry);
+            }
+            target.sort_unstable();
+            target.dedup();
+        }
+
+        Ok(lists)
+    }
+}
+
+fn domain_matches(domain: &str, entry: &str) -> bool {
+    !entry.contains('@')
+        && domain
+            .strip_suffix(entry)
+            .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('.'))
+}
+
+fn is_valid_domain(domain: &str) -> bool {
+    domain.contains('.')
+        && domain
+            .split('.')
+            .all(|part| !part.is_empty() && !part.contains(char::is_whitespace))
+}
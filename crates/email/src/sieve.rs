@@ -4,30 +4,41 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, sync::Arc, time::Duration};
 
 use crate::{
     delivery::AutogeneratedMessage,
     ingest::{EmailIngest, IngestEmail, IngestSource, IngestedEmail},
     mailbox::{MailboxFnc, INBOX_ID, TRASH_ID},
 };
-use common::{auth::AccessToken, scripts::plugins::PluginContext, Server};
+use common::{
+    auth::AccessToken,
+    scripts::{notify::SieveNotification, plugins::PluginContext},
+    Server, KV_AUTORESPONDER_SUPPRESSED,
+};
 use directory::{backend::internal::PrincipalField, Permission, QueryBy};
 use jmap_proto::{
-    object::Object,
+    object::{
+        index::{IndexAs, IndexProperty},
+        Object,
+    },
     types::{collection::Collection, id::Id, keyword::Keyword, property::Property, value::Value},
 };
 use mail_parser::MessageParser;
 use serde::ser::SerializeSeq;
-use sieve::{Envelope, Event, Input, Mailbox, Recipient, Sieve};
+use sieve::{
+    compiler::grammar::Capability, Envelope, Event, Importance, Input, Mailbox, Recipient, Sieve,
+};
 use store::{
     ahash::AHashSet,
     blake3,
+    dispatch::lookup::KeyValue,
     query::Filter,
     write::{assert::HashedValue, now, BatchBuilder, Bincode, BlobOp, F_VALUE},
     Deserialize, Serialize,
 };
 use trc::{AddContext, SieveEvent};
+use utils::config::utils::ParseValue;
 
 use std::future::Future;
 
@@ -37,6 +48,17 @@ struct SieveMessage<'x> {
     pub flags: Vec<Keyword>,
 }
 
+pub static SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::Name)
+        .index_as(IndexAs::Text {
+            tokenize: true,
+            index: true,
+        })
+        .max_size(255)
+        .required(),
+    IndexProperty::new(Property::IsActive).index_as(IndexAs::Integer),
+];
+
 pub struct ActiveScript {
     pub document_id: u32,
     pub script_name: String,
@@ -57,6 +79,7 @@ pub struct SeenIds {
 }
 
 pub trait SieveScriptIngest: Sync + Send {
+    #[allow(clippy::too_many_arguments)]
     #[allow(clippy::too_many_arguments)]
     fn sieve_script_ingest(
         &self,
@@ -66,6 +89,7 @@ pub trait SieveScriptIngest: Sync + Send {
         envelope_to: &str,
         session_id: u64,
         active_script: ActiveScript,
+        spam_classify: bool,
         autogenerated: &mut Vec<AutogeneratedMessage>,
     ) -> impl Future<Output = trc::Result<IngestedEmail>> + Send;
 
@@ -88,7 +112,7 @@ pub trait SieveScriptIngest: Sync + Send {
 }
 
 impl SieveScriptIngest for Server {
-    #[allow(clippy::blocks_in_conditions)]
+    #[allow(clippy::blocks_in_conditions, clippy::too_many_arguments)]
     async fn sieve_script_ingest(
         &self,
         access_token: &AccessToken,
@@ -97,6 +121,7 @@ impl SieveScriptIngest for Server {
         envelope_to: &str,
         session_id: u64,
         mut active_script: ActiveScript,
+        spam_classify: bool,
         autogenerated: &mut Vec<AutogeneratedMessage>,
     ) -> trc::Result<IngestedEmail> {
         // Parse message
@@ -117,8 +142,37 @@ impl SieveScriptIngest for Server {
             .await
             .caused_by(trc::location!())?;
 
+        // Obtain spam and virus verdicts for the spamtest and virustest extensions
+        let extensions = &self.core.sieve.untrusted_extensions;
+        let spam_status = self
+            .core
+            .spam
+            .headers
+            .status
+            .as_deref()
+            .filter(|_| extensions.is_enabled(&Capability::SpamTest))
+            .map(|header| {
+                extensions.spam_status(&message, header, self.core.spam.scores.spam_threshold)
+            });
+        let virus_status = extensions
+            .is_enabled(&Capability::VirusTest)
+            .then(|| extensions.virus_status(&message));
+
+        // Auto-responses are never sent to automatically generated messages
+        let auto_generated = self
+            .core
+            .smtp
+            .auto_responder
+            .detect(envelope_from, &message);
+
         // Create Sieve instance
         let mut instance = self.core.sieve.untrusted_runtime.filter_parsed(message);
+        if let Some(spam_status) = spam_status {
+            instance.set_spam_status(spam_status);
+        }
+        if let Some(virus_status) = virus_status {
+            instance.set_virus_status(virus_status);
+        }
 
         // Set account name and email
         let mail_from = self
@@ -393,6 +447,38 @@ impl SieveScriptIngest for Server {
                                 }
                             };
 
+                            if let Some(auto_generated) = auto_generated
+                                && is_auto_response(&message.raw_message)
+                            {
+                                trc::event!(
+                                    Sieve(SieveEvent::AutoResponseSuppressed),
+                                    AccountId = account_id,
+                                    From = envelope_from.to_string(),
+                                    To = recipients
+                                        .iter()
+                                        .map(|r| trc::Value::String(r.clone()))
+                                        .collect::<Vec<_>>(),
+                                    Reason = auto_generated.as_str(),
+                                    SpanId = session_id
+                                );
+
+                                if let Err(err) = self
+                                    .in_memory_store()
+                                    .counter_incr(
+                                        KeyValue::with_prefix(
+                                            KV_AUTORESPONDER_SUPPRESSED,
+                                            account_id.to_be_bytes(),
+                                            1,
+                                        ),
+                                        false,
+                                    )
+                                    .await
+                                {
+                                    trc::error!(err.span_id(session_id));
+                                }
+                                continue;
+                            }
+
                             if message.raw_message.len() <= self.core.jmap.mail_max_size {
                                 trc::event!(
                                     Sieve(SieveEvent::SendMessage),
@@ -434,9 +520,34 @@ impl SieveScriptIngest for Server {
                             continue;
                         }
                     }
-                    Event::ListContains { .. }
-                    | Event::Notify { .. }
-                    | Event::SetEnvelope { .. } => {
+                    Event::Notify {
+                        from,
+                        importance,
+                        options,
+                        message,
+                        method,
+                    } => {
+                        input = self
+                            .sieve_notify(
+                                account_id,
+                                &mail_from,
+                                SieveNotification {
+                                    method,
+                                    from,
+                                    importance: match importance {
+                                        Importance::High => "high",
+                                        Importance::Normal => "normal",
+                                        Importance::Low => "low",
+                                    },
+                                    options,
+                                    message,
+                                },
+                                session_id,
+                            )
+                            .await
+                            .into();
+                    }
+                    Event::ListContains { .. } | Event::SetEnvelope { .. } => {
                         // Not allowed
                         input = false.into();
                     }
@@ -488,6 +599,12 @@ impl SieveScriptIngest for Server {
             messages[0].file_into.push(INBOX_ID);
         }
 
+        // Scripts can set an expiry time for the delivered messages
+        let expires_at = instance
+            .global_variable("expire")
+            .and_then(|value| parse_expiry(value.to_string().as_ref()))
+            .map(|expire| now + expire.as_secs());
+
         // Deliver messages
         let mut last_temp_error = None;
         let mut has_delivered = false;
@@ -523,8 +640,10 @@ impl SieveScriptIngest for Server {
                         source: IngestSource::Smtp {
                             deliver_to: envelope_to,
                         },
-                        spam_classify: access_token.has_permission(Permission::SpamFilterClassify),
+                        spam_classify: spam_classify
+                            && access_token.has_permission(Permission::SpamFilterClassify),
                         spam_train: can_spam_train,
+                        expires_at,
                         session_id,
                     })
                     .await
@@ -875,3 +994,27 @@ impl<'de> serde::de::Visitor<'de> for SeenIdsVisitor {
         Ok(seen_ids)
     }
 }
+
+// Plain numbers are read as days, otherwise any duration such as "12h" is accepted
+fn parse_expiry(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    match value.parse::<u64>() {
+        Ok(days) if days > 0 => Some(Duration::from_secs(days * 86400)),
+        Ok(_) => None,
+        Err(_) => Duration::parse_value(value).ok(),
+    }
+}
+
+// Vacation replies and notifications are flagged by the Sieve runtime
+fn is_auto_response(raw_message: &[u8]) -> bool {
+    MessageParser::new()
+        .parse_headers(raw_message)
+        .and_then(|message| {
+            message
+                .header_raw("Auto-Submitted")
+                .map(|value| value.trim().to_ascii_lowercase())
+        })
+        .is_some_and(|value| {
+            value.starts_with("auto-replied") || value.starts_with("auto-notified")
+        })
+}
@@ -0,0 +1,693 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{collections::BTreeSet, future::Future};
+
+use common::{
+    config::smtp::smime::{SmimeCipher, SmimeDomain, SmimeKeyPair},
+    Server, KV_SMIME_CERTIFICATE,
+};
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_builder::{encoders::base64::base64_encode_mime, mime::make_boundary};
+use mail_parser::{HeaderName, Message, MessageParser, MimeHeaders, PartType};
+use rasn::types::{ObjectIdentifier, OctetString, Oid};
+use rasn_cms::{
+    algorithms::RSA, pkcs7_compat::EncapsulatedContentInfo, AlgorithmIdentifier,
+    CertificateChoices, IssuerAndSerialNumber, SignedData, SignerIdentifier, SignerInfo,
+    CONTENT_DATA, CONTENT_SIGNED_DATA,
+};
+use rasn_pkix::Attribute;
+use rsa::Pkcs1v15Sign;
+use sha2::{Digest, Sha256};
+use store::{
+    dispatch::lookup::KeyValue,
+    write::{now, Bincode},
+    Serialize,
+};
+use trc::{AddContext, SmtpEvent};
+use x509_parser::{
+    certificate::X509Certificate, der_parser::asn1_rs::FromDer, extensions::GeneralName,
+    oid_registry::OID_PKCS1_RSAENCRYPTION,
+};
+
+use crate::crypto::{Algorithm, EncryptMessage, EncryptionMethod, EncryptionParams};
+
+const SHA256: &Oid = Oid::const_new(&[2, 16, 840, 1, 101, 3, 4, 2, 1]);
+const ATTR_CONTENT_TYPE: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 9, 3]);
+const ATTR_MESSAGE_DIGEST: &Oid = Oid::const_new(&[1, 2, 840, 113549, 1, 9, 4]);
+
+/// DER encoded DigestInfo prefix for SHA-256 hashes, see RFC 8017 section 9.2.
+const SHA256_DIGEST_INFO: &[u8] = &[
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// A recipient certificate known to the S/MIME gateway.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SmimeCertificate {
+    pub certificate: Vec<u8>,
+    pub harvested: bool,
+    pub updated: u64,
+}
+
+/// Details of an X.509 certificate relevant to S/MIME.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub addresses: Vec<String>,
+    pub not_before: i64,
+    pub not_after: i64,
+}
+
+pub trait SmimeGateway: Sync + Send {
+    fn smime_protect(
+        &self,
+        policy: &SmimeDomain,
+        raw_message: &[u8],
+        recipients: &[&str],
+        session_id: u64,
+    ) -> impl Future<Output = Option<Vec<u8>>> + Send;
+
+    fn smime_harvest(
+        &self,
+        message: &Message<'_>,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn smime_recipient_certificate(
+        &self,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<Option<Vec<u8>>>> + Send;
+
+    fn smime_certificate(
+        &self,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<Option<SmimeCertificate>>> + Send;
+
+    fn smime_certificate_set(
+        &self,
+        address: &str,
+        certificate: SmimeCertificate,
+        expires: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn smime_certificate_delete(
+        &self,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl SmimeGateway for Server {
+    async fn smime_protect(
+        &self,
+        policy: &SmimeDomain,
+        raw_message: &[u8],
+        recipients: &[&str],
+        session_id: u64,
+    ) -> Option<Vec<u8>> {
+        // Messages already signed or encrypted by the client are left untouched
+        let message = MessageParser::new().parse(raw_message)?;
+        if message.is_encrypted() || is_signed(&message) {
+            return None;
+        }
+
+        // Encryption is only possible when all recipients have a certificate
+        let mut certs = Vec::with_capacity(recipients.len());
+        if policy.encrypt {
+            for rcpt in recipients {
+                match self.smime_recipient_certificate(rcpt).await {
+                    Ok(Some(cert)) => certs.push(cert),
+                    Ok(None) => {
+                        certs.clear();
+                        break;
+                    }
+                    Err(err) => {
+                        trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+                        certs.clear();
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut result = None;
+        if let Some(signer) = &policy.signer {
+            match smime_sign(raw_message, signer) {
+                Ok(signed) => {
+                    trc::event!(
+                        Smtp(SmtpEvent::SmimeSigned),
+                        SpanId = session_id,
+                        Id = policy.id.clone(),
+                    );
+                    result = Some(signed);
+                }
+                Err(err) => {
+                    trc::event!(
+                        Smtp(SmtpEvent::SmimeError),
+                        SpanId = session_id,
+                        Id = policy.id.clone(),
+                        Reason = err,
+                    );
+                    return None;
+                }
+            }
+        }
+
+        if !certs.is_empty() {
+            let params = EncryptionParams {
+                method: EncryptionMethod::SMIME,
+                algo: match policy.cipher {
+                    SmimeCipher::Aes128 => Algorithm::Aes128,
+                    SmimeCipher::Aes256 => Algorithm::Aes256,
+                },
+                certs,
+            };
+            let encrypted = match result.as_deref() {
+                Some(signed) => MessageParser::new()
+                    .parse(signed)?
+                    .encrypt(&params)
+                    .await
+                    .map_err(|err| format!("{err:?}")),
+                None => message
+                    .encrypt(&params)
+                    .await
+                    .map_err(|err| format!("{err:?}")),
+            };
+            match encrypted {
+                Ok(encrypted) => {
+                    trc::event!(
+                        Smtp(SmtpEvent::SmimeEncrypted),
+                        SpanId = session_id,
+                        Id = policy.id.clone(),
+                        Total = params.certs.len(),
+                    );
+                    result = Some(encrypted);
+                }
+                Err(err) => {
+                    trc::event!(
+                        Smtp(SmtpEvent::SmimeError),
+                        SpanId = session_id,
+                        Id = policy.id.clone(),
+                        Reason = err,
+                    );
+                }
+            }
+        }
+
+        result
+    }
+
+    async fn smime_harvest(&self, message: &Message<'_>, session_id: u64) -> trc::Result<()> {
+        let Some(sender) = message
+            .from()
+            .and_then(|from| from.first())
+            .and_then(|from| from.address())
+            .map(|address| address.to_lowercase())
+        else {
+            return Ok(());
+        };
+
+        for certificate in extract_certificates(message) {
+            let Ok(info) = inspect_certificate(&certificate) else {
+                continue;
+            };
+            if !info.addresses.contains(&sender) {
+                continue;
+            }
+
+            // Certificates uploaded by an administrator take precedence
+            if self
+                .smime_certificate(&sender)
+                .await?
+                .is_some_and(|cert| !cert.harvested || cert.certificate == certificate)
+            {
+                return Ok(());
+            }
+
+            let now = now();
+            let expires = (info.not_after.max(0) as u64)
+                .saturating_sub(now)
+                .min(self.core.smtp.smime.harvest_expiry.as_secs());
+            if expires > 0 {
+                self.smime_certificate_set(
+                    &sender,
+                    SmimeCertificate {
+                        certificate,
+                        harvested: true,
+                        updated: now,
+                    },
+                    expires,
+                )
+                .await?;
+
+                trc::event!(
+                    Smtp(SmtpEvent::SmimeCertificateHarvested),
+                    SpanId = session_id,
+                    From = sender,
+                    Details = info.subject,
+                    Expires = trc::Value::Timestamp(info.not_after.max(0) as u64),
+                );
+            }
+
+            break;
+        }
+
+        Ok(())
+    }
+
+    async fn smime_recipient_certificate(&self, address: &str) -> trc::Result<Option<Vec<u8>>> {
+        if let Some(cert) = self.smime_certificate(address).await? {
+            return Ok(Some(cert.certificate));
+        }
+
+        // Use the at-rest encryption certificate of local recipients
+        if let Some(account_id) = self
+            .core
+            .storage
+            .directory
+            .email_to_id(address)
+            .await
+            .caused_by(trc::location!())?
+            && let Some(params) = self
+                .get_property::<EncryptionParams>(
+                    account_id,
+                    Collection::Principal,
+                    0,
+                    Property::Parameters,
+                )
+                .await
+                .caused_by(trc::location!())?
+            && params.method == EncryptionMethod::SMIME
+        {
+            return Ok(params.certs.into_iter().find(|cert| {
+                inspect_certificate(cert).is_ok_and(|info| {
+                    info.addresses
+                        .iter()
+                        .any(|a| a.eq_ignore_ascii_case(address))
+                })
+            }));
+        }
+
+        Ok(None)
+    }
+
+    async fn smime_certificate(&self, address: &str) -> trc::Result<Option<SmimeCertificate>> {
+        self.in_memory_store()
+            .key_get::<Bincode<SmimeCertificate>>(KeyValue::<()>::build_key(
+                KV_SMIME_CERTIFICATE,
+                address.to_lowercase(),
+            ))
+            .await
+            .map(|cert| cert.map(|cert| cert.inner))
+    }
+
+    async fn smime_certificate_set(
+        &self,
+        address: &str,
+        certificate: SmimeCertificate,
+        expires: u64,
+    ) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_SMIME_CERTIFICATE,
+                    address.to_lowercase(),
+                    Bincode::new(certificate).serialize(),
+                )
+                .expires(expires),
+            )
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn smime_certificate_delete(&self, address: &str) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_delete(KeyValue::<()>::build_key(
+                KV_SMIME_CERTIFICATE,
+                address.to_lowercase(),
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+}
+
+/// Signs a message using S/MIME, producing a multipart/signed message with a
+/// detached PKCS#7 signature. The MIME headers and body become the signed
+/// entity while all other headers are kept in the outer message.
+pub fn smime_sign(raw_message: &[u8], signer: &SmimeKeyPair) -> Result<Vec<u8>, String> {
+    let message = MessageParser::new()
+        .parse(raw_message)
+        .ok_or_else(|| "Failed to parse message".to_string())?;
+    let root = message.root_part();
+    let mut outer_message = Vec::with_capacity(raw_message.len() + 4096);
+    let mut inner_message = Vec::with_capacity(raw_message.len());
+    let mut has_mime_version = false;
+
+    for header in root.headers() {
+        (if header.name.is_mime_header() {
+            &mut inner_message
+        } else {
+            has_mime_version |= header.name == HeaderName::MimeVersion;
+            &mut outer_message
+        })
+        .extend_from_slice(&raw_message[header.offset_field()..header.offset_end()]);
+    }
+    inner_message.extend_from_slice(b"\r\n");
+    inner_message.extend_from_slice(&raw_message[root.raw_body_offset()..]);
+
+    let signature = build_signature(&inner_message, signer)?;
+
+    let boundary = make_boundary("_");
+    if !has_mime_version {
+        outer_message.extend_from_slice(b"MIME-Version: 1.0\r\n");
+    }
+    outer_message.extend_from_slice(
+        concat!(
+            "Content-Type: multipart/signed;\r\n\t",
+            "protocol=\"application/pkcs7-signature\";\r\n\t",
+            "micalg=sha-256;\r\n\t",
+            "boundary=\""
+        )
+        .as_bytes(),
+    );
+    outer_message.extend_from_slice(boundary.as_bytes());
+    outer_message.extend_from_slice(
+        concat!(
+            "\"\r\n\r\n",
+            "This is a cryptographically signed message in MIME format.\r\n\r\n",
+            "--"
+        )
+        .as_bytes(),
+    );
+    outer_message.extend_from_slice(boundary.as_bytes());
+    outer_message.extend_from_slice(b"\r\n");
+    outer_message.extend_from_slice(&inner_message);
+    // The line break preceding the boundary is not part of the signed entity
+    outer_message.extend_from_slice(b"\r\n--");
+    outer_message.extend_from_slice(boundary.as_bytes());
+    outer_message.extend_from_slice(
+        concat!(
+            "\r\nContent-Type: application/pkcs7-signature;\r\n",
+            "\tname=\"smime.p7s\"\r\n",
+            "Content-Disposition: attachment;\r\n",
+            "\tfilename=\"smime.p7s\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    base64_encode_mime(&signature, &mut outer_message, false)
+        .map_err(|err| format!("Failed to base64 encode PKCS7: {err}"))?;
+    outer_message.extend_from_slice(b"\r\n--");
+    outer_message.extend_from_slice(boundary.as_bytes());
+    outer_message.extend_from_slice(b"--\r\n");
+
+    Ok(outer_message)
+}
+
+fn build_signature(contents: &[u8], signer: &SmimeKeyPair) -> Result<Vec<u8>, String> {
+    let mut certificates = signer
+        .certificates
+        .iter()
+        .map(|cert| {
+            rasn::der::decode::<rasn_pkix::Certificate>(cert)
+                .map_err(|err| format!("Failed to parse certificate: {err}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if certificates.is_empty() {
+        return Err("No signing certificate available".to_string());
+    }
+    let signing_cert = certificates.remove(0);
+
+    // The signature covers the DER encoded signed attributes
+    #[allow(clippy::mutable_key_type)]
+    let signed_attrs = BTreeSet::from([
+        attribute(ATTR_CONTENT_TYPE, &ObjectIdentifier::from(CONTENT_DATA))?,
+        attribute(
+            ATTR_MESSAGE_DIGEST,
+            &OctetString::from(Sha256::digest(contents).to_vec()),
+        )?,
+    ]);
+    let mut digest_info = SHA256_DIGEST_INFO.to_vec();
+    digest_info.extend_from_slice(
+        &Sha256::digest(
+            rasn::der::encode(&signed_attrs)
+                .map_err(|err| format!("Failed to encode signed attributes: {err}"))?,
+        )[..],
+    );
+    let signature = signer
+        .private_key
+        .sign(Pkcs1v15Sign::new_unprefixed(), &digest_info)
+        .map_err(|err| format!("Failed to sign message: {err}"))?;
+
+    let digest_algorithm = AlgorithmIdentifier {
+        algorithm: SHA256.into(),
+        parameters: None,
+    };
+    let signer_info = SignerInfo {
+        version: 1.into(),
+        sid: SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+            issuer: signing_cert.tbs_certificate.issuer.clone(),
+            serial_number: signing_cert.tbs_certificate.serial_number.clone(),
+        }),
+        digest_algorithm: digest_algorithm.clone(),
+        signed_attrs: Some(signed_attrs),
+        signature_algorithm: AlgorithmIdentifier {
+            algorithm: RSA.into(),
+            parameters: Some(
+                rasn::der::encode(&())
+                    .map_err(|err| format!("Failed to encode RSA algorithm identifier: {err}"))?
+                    .into(),
+            ),
+        },
+        signature: OctetString::from(signature),
+        unsigned_attrs: None,
+    };
+
+    #[allow(clippy::mutable_key_type)]
+    let certificate_set = std::iter::once(signing_cert)
+        .chain(certificates)
+        .map(|cert| CertificateChoices::Certificate(Box::new(cert)))
+        .collect::<BTreeSet<_>>();
+
+    rasn::der::encode(&EncapsulatedContentInfo {
+        content_type: CONTENT_SIGNED_DATA.into(),
+        content: Some(
+            rasn::der::encode(&SignedData {
+                version: 1.into(),
+                digest_algorithms: BTreeSet::from([digest_algorithm]),
+                encap_content_info: rasn_cms::EncapsulatedContentInfo {
+                    content_type: CONTENT_DATA.into(),
+                    content: None,
+                },
+                certificates: Some(certificate_set),
+                crls: None,
+                signer_infos: BTreeSet::from([signer_info]),
+            })
+            .map_err(|err| format!("Failed to encode SignedData: {err}"))?
+            .into(),
+        ),
+    })
+    .map_err(|err| format!("Failed to encode ContentInfo: {err}"))
+}
+
+fn attribute(oid: &'static Oid, value: &impl rasn::Encode) -> Result<Attribute, String> {
+    Ok(Attribute {
+        r#type: oid.into(),
+        value: BTreeSet::from([rasn::der::encode(value)
+            .map_err(|err| format!("Failed to encode attribute: {err}"))?
+            .into()]),
+    })
+}
+
+/// Returns the DER encoded certificates included in the S/MIME signatures of
+/// a message.
+pub fn extract_certificates(message: &Message<'_>) -> Vec<Vec<u8>> {
+    let mut certificates = Vec::new();
+
+    for part in &message.parts {
+        let is_signature = part.content_type().is_some_and(|ct| {
+            ct.ctype().eq_ignore_ascii_case("application")
+                && ct.subtype().is_some_and(|st| {
+                    st.eq_ignore_ascii_case("pkcs7-signature")
+                        || st.eq_ignore_ascii_case("x-pkcs7-signature")
+                })
+        });
+        let contents = match &part.body {
+            PartType::Binary(contents) | PartType::InlineBinary(contents) if is_signature => {
+                contents.as_ref()
+            }
+            _ => continue,
+        };
+
+        let Ok(content_info) = rasn::der::decode::<EncapsulatedContentInfo>(contents) else {
+            continue;
+        };
+        if *content_info.content_type != *CONTENT_SIGNED_DATA {
+            continue;
+        }
+        let Some(signed_data) = content_info
+            .content
+            .and_then(|content| rasn::der::decode::<SignedData>(content.as_bytes()).ok())
+        else {
+            continue;
+        };
+
+        for cert in signed_data.certificates.unwrap_or_default() {
+            if let CertificateChoices::Certificate(cert) = cert
+                && let Ok(cert) = rasn::der::encode(&*cert)
+            {
+                certificates.push(cert);
+            }
+        }
+    }
+
+    certificates
+}
+
+/// Parses a DER encoded certificate, verifying that it is currently valid and
+/// that it contains an RSA key usable for S/MIME encryption.
+pub fn inspect_certificate(der: &[u8]) -> Result<CertificateInfo, String> {
+    let (_, cert) = X509Certificate::from_der(der)
+        .map_err(|err| format!("Failed to parse certificate: {err}"))?;
+    if !cert.validity().is_valid() {
+        return Err("Certificate is expired or not yet valid".to_string());
+    }
+    if cert.public_key().algorithm.algorithm != OID_PKCS1_RSAENCRYPTION {
+        return Err("Only RSA certificates are supported".to_string());
+    }
+
+    let mut addresses = cert
+        .subject()
+        .iter_email()
+        .filter_map(|email| email.as_str().ok())
+        .map(|email| email.to_lowercase())
+        .collect::<Vec<_>>();
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            if let GeneralName::RFC822Name(email) = name {
+                let email = email.to_lowercase();
+                if !addresses.contains(&email) {
+                    addresses.push(email);
+                }
+            }
+        }
+    }
+    if addresses.is_empty() {
+        return Err("Certificate does not contain any email addresses".to_string());
+    }
+
+    Ok(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        serial: cert.raw_serial_as_string(),
+        addresses,
+        not_before: cert.validity().not_before.timestamp(),
+        not_after: cert.validity().not_after.timestamp(),
+    })
+}
+
+fn is_signed(message: &Message<'_>) -> bool {
+    message.content_type().is_some_and(|ct| {
+        ct.ctype().eq_ignore_ascii_case("multipart")
+            && ct
+                .subtype()
+                .is_some_and(|st| st.eq_ignore_ascii_case("signed"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use common::config::smtp::smime::build_smime_key_pair;
+    use mail_parser::{MessageParser, MimeHeaders};
+    use rasn_cms::{pkcs7_compat::EncapsulatedContentInfo, SignedData};
+    use rsa::{pkcs1::DecodeRsaPublicKey, Pkcs1v15Sign, RsaPublicKey};
+    use sha2::{Digest, Sha256};
+
+    use super::{extract_certificates, smime_sign, SHA256_DIGEST_INFO};
+
+    #[test]
+    fn smime_sign_message() {
+        let resources = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("tests")
+            .join("resources");
+        let signer = build_smime_key_pair(
+            &fs::read_to_string(resources.join("tls_cert.pem")).unwrap(),
+            &fs::read_to_string(resources.join("tls_privatekey.pem")).unwrap(),
+        )
+        .unwrap();
+
+        let message = concat!(
+            "From: john@example.org\r\n",
+            "To: jane@example.com\r\n",
+            "Subject: Signed\r\n",
+            "MIME-Version: 1.0\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n\r\n",
+            "Hello\r\n",
+        );
+        let signed = smime_sign(message.as_bytes(), &signer).unwrap();
+        let parsed = MessageParser::new().parse(&signed).unwrap();
+        let content_type = parsed.content_type().unwrap();
+        assert_eq!(content_type.subtype(), Some("signed"));
+        assert_eq!(
+            content_type.attribute("protocol"),
+            Some("application/pkcs7-signature")
+        );
+        assert_eq!(parsed.subject(), Some("Signed"));
+        assert_eq!(parsed.body_text(0).unwrap(), "Hello\r\n");
+        assert_eq!(extract_certificates(&parsed), signer.certificates);
+
+        // Verify the digest and signature
+        let signed_entity = b"Content-Type: text/plain; charset=utf-8\r\n\r\nHello\r\n";
+        let signed = String::from_utf8(signed).unwrap();
+        assert!(signed.contains(std::str::from_utf8(signed_entity).unwrap()));
+        let signature = parsed
+            .parts
+            .iter()
+            .find(|part| {
+                part.content_type()
+                    .is_some_and(|ct| ct.subtype() == Some("pkcs7-signature"))
+            })
+            .unwrap()
+            .contents();
+        let content_info = rasn::der::decode::<EncapsulatedContentInfo>(signature).unwrap();
+        let signed_data =
+            rasn::der::decode::<SignedData>(content_info.content.unwrap().as_bytes()).unwrap();
+        let signer_info = signed_data.signer_infos.into_iter().next().unwrap();
+        let signed_attrs = signer_info.signed_attrs.unwrap();
+        let digest = Sha256::digest(signed_entity).to_vec();
+        assert!(signed_attrs.iter().any(|attr| attr
+            .value
+            .iter()
+            .any(|value| value.as_bytes().ends_with(&digest))));
+
+        let mut digest_info = SHA256_DIGEST_INFO.to_vec();
+        digest_info
+            .extend_from_slice(&Sha256::digest(rasn::der::encode(&signed_attrs).unwrap())[..]);
+        let cert = rasn::der::decode::<rasn_pkix::Certificate>(&signer.certificates[0]).unwrap();
+        RsaPublicKey::from_pkcs1_der(
+            cert.tbs_certificate
+                .subject_public_key_info
+                .subject_public_key
+                .as_raw_slice(),
+        )
+        .unwrap()
+        .verify(
+            Pkcs1v15Sign::new_unprefixed(),
+            &digest_info,
+            &signer_info.signature,
+        )
+        .unwrap();
+    }
+}
@@ -170,6 +170,7 @@ impl Capability {
                 Capability::Preview,
                 Capability::Quota,
                 Capability::QuotaResource(QuotaResourceName::Storage),
+                Capability::QuotaResource(QuotaResourceName::Message),
             ]);
         } else {
             capabilities.extend([
@@ -76,6 +76,7 @@ pub enum Attribute {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ChildInfo {
     Subscribed,
+    SpecialUse,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -167,6 +168,7 @@ impl ChildInfo {
         buf.push(b'\"');
         buf.extend_from_slice(match self {
             ChildInfo::Subscribed => b"SUBSCRIBED",
+            ChildInfo::SpecialUse => b"SPECIAL-USE",
         });
         buf.push(b'\"');
     }
@@ -331,6 +333,24 @@ mod tests {
                 "* LIST (\\HasNoChildren) \"/\" \"foo\" (\"CHILDINFO\" (\"SUBSCRIBED\"))\r\n",
                 "* LIST (\\HasNoChildren) \"/\" \"foo\" (\"CHILDINFO\" (\"SUBSCRIBED\"))\r\n",
             ),
+            (
+                super::ListItem {
+                    mailbox_name: "bar".to_string(),
+                    attributes: vec![Attribute::HasChildren],
+                    tags: vec![Tag::ChildInfo(vec![
+                        ChildInfo::Subscribed,
+                        ChildInfo::SpecialUse,
+                    ])],
+                },
+                concat!(
+                    "* LIST (\\HasChildren) \"/\" \"bar\" ",
+                    "(\"CHILDINFO\" (\"SUBSCRIBED\" \"SPECIAL-USE\"))\r\n"
+                ),
+                concat!(
+                    "* LIST (\\HasChildren) \"/\" \"bar\" ",
+                    "(\"CHILDINFO\" (\"SUBSCRIBED\" \"SPECIAL-USE\"))\r\n"
+                ),
+            ),
         ] {
             let mut buf_1 = Vec::with_capacity(100);
             let mut buf_2 = Vec::with_capacity(100);
@@ -501,6 +501,9 @@ impl SerializeResponse for trc::Error {
                 trc::EventType::Store(trc::StoreEvent::NotFound) => {
                     Some(ResponseCode::NonExistent.as_str())
                 }
+                trc::EventType::Store(trc::StoreEvent::BlobStoreUnavailable) => {
+                    Some(ResponseCode::Unavailable.as_str())
+                }
                 trc::EventType::Store(_) => Some(ResponseCode::ContactAdmin.as_str()),
                 trc::EventType::Limit(trc::LimitEvent::Quota) => {
                     Some(ResponseCode::OverQuota.as_str())
@@ -373,7 +373,6 @@ impl<T: SessionStream> Session<T> {
             | Command::Namespace
             | Command::Status
             | Command::Append
-            | Command::Idle
             | Command::SetAcl
             | Command::DeleteAcl
             | Command::GetAcl
@@ -391,6 +390,21 @@ impl<T: SessionStream> Session<T> {
                         .id(request.tag))
                 }
             }
+            Command::Idle => {
+                if !matches!(state, State::Authenticated { .. } | State::Selected { .. }) {
+                    Err(trc::ImapEvent::Error
+                        .into_err()
+                        .details("Not authenticated.")
+                        .id(request.tag))
+                } else if self.allow_idle {
+                    Ok(request)
+                } else {
+                    Err(trc::ImapEvent::Error
+                        .into_err()
+                        .details("IDLE is not available.")
+                        .id(request.tag))
+                }
+            }
             Command::Close
             | Command::Unselect
             | Command::Expunge(_)
@@ -0,0 +1,263 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use ahash::AHashMap;
+use common::{listener::SessionStream, ImapId, MailboxState};
+use jmap::email::set::TagManager;
+use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
+use store::{
+    roaring::RoaringBitmap,
+    write::{assert::HashedValue, log::ChangeLogBuilder, AnyKey, BatchBuilder, TagValue, F_VALUE},
+    IterateParams, SUBSPACE_BITMAP_TAG, U32_LEN,
+};
+use trc::AddContext;
+
+use super::{MailboxId, SessionData};
+
+// Label folders are assigned ids that can't collide with mailbox document ids
+pub const LABEL_MAILBOX_ID: u32 = 1 << 31;
+
+pub fn is_label_mailbox(mailbox_id: u32) -> bool {
+    mailbox_id & LABEL_MAILBOX_ID != 0
+}
+
+pub fn label_mailbox_id(label: &str) -> u32 {
+    let mut hasher = DefaultHasher::new();
+    label.hash(&mut hasher);
+    LABEL_MAILBOX_ID | (hasher.finish() as u32 & !LABEL_MAILBOX_ID)
+}
+
+impl<T: SessionStream> SessionData<T> {
+    /// Returns the keyword a virtual label folder maps to.
+    pub fn get_label(&self, mailbox: &MailboxId) -> Option<String> {
+        if !is_label_mailbox(mailbox.mailbox_id) {
+            return None;
+        }
+        let labels_folder = self.server.core.imap.labels_folder.as_ref()?;
+
+        self.mailboxes
+            .lock()
+            .iter()
+            .find(|account| account.account_id == mailbox.account_id)?
+            .mailbox_names
+            .iter()
+            .find(|(_, mailbox_id)| **mailbox_id == mailbox.mailbox_id)?
+            .0
+            .strip_prefix(labels_folder.as_str())?
+            .strip_prefix('/')
+            .map(|label| label.to_string())
+    }
+
+    pub fn is_labels_folder(&self, mailbox_name: &str) -> bool {
+        self.server
+            .core
+            .imap
+            .labels_folder
+            .as_ref()
+            .is_some_and(|labels_folder| {
+                mailbox_name
+                    .strip_prefix(labels_folder.as_str())
+                    .is_some_and(|name| name.is_empty() || name.starts_with('/'))
+            })
+    }
+
+    /// Lists the custom keywords in use by the messages of an account.
+    pub async fn fetch_labels(&self, account_id: u32) -> trc::Result<Vec<String>> {
+        const BM_MARKER: u8 = 1 << 7;
+
+        let mut prefix = Vec::with_capacity(U32_LEN + 2);
+        prefix.extend_from_slice(&account_id.to_be_bytes());
+        prefix.push(u8::from(Collection::Email));
+        prefix.push(u8::from(Property::Keywords) | BM_MARKER);
+        let mut end = prefix.clone();
+        end.extend_from_slice(&[u8::MAX; 10]);
+
+        let mut labels: Vec<String> = Vec::new();
+        self.server
+            .store()
+            .iterate(
+                IterateParams::new(
+                    AnyKey {
+                        subspace: SUBSPACE_BITMAP_TAG,
+                        key: prefix,
+                    },
+                    AnyKey {
+                        subspace: SUBSPACE_BITMAP_TAG,
+                        key: end,
+                    },
+                )
+                .no_values(),
+                |key, _| {
+                    if let Some(label) = key
+                        .get(U32_LEN + 2..key.len().saturating_sub(U32_LEN))
+                        .filter(|label| !label.is_empty())
+                        .and_then(|label| std::str::from_utf8(label).ok())
+                    {
+                        if labels.last().is_none_or(|last| last != label) {
+                            labels.push(label.to_string());
+                        }
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(labels)
+    }
+
+    pub async fn label_message_ids(
+        &self,
+        account_id: u32,
+        label: &str,
+    ) -> trc::Result<Option<RoaringBitmap>> {
+        self.server
+            .get_tag(
+                account_id,
+                Collection::Email,
+                Property::Keywords,
+                TagValue::Text(label.as_bytes().to_vec()),
+            )
+            .await
+            .caused_by(trc::location!())
+    }
+
+    /// Returns the messages contained in a mailbox or virtual label folder.
+    pub async fn mailbox_message_ids(
+        &self,
+        mailbox: &MailboxId,
+    ) -> trc::Result<Option<RoaringBitmap>> {
+        if let Some(label) = self.get_label(mailbox) {
+            self.label_message_ids(mailbox.account_id, &label).await
+        } else {
+            self.server
+                .get_tag(
+                    mailbox.account_id,
+                    Collection::Email,
+                    Property::MailboxIds,
+                    mailbox.mailbox_id,
+                )
+                .await
+                .caused_by(trc::location!())
+        }
+    }
+
+    // Messages in label folders do not have a mailbox UID, the document id
+    // is used instead as it is unique within the account.
+    pub async fn fetch_label_messages(
+        &self,
+        mailbox: &MailboxId,
+        label: &str,
+    ) -> trc::Result<MailboxState> {
+        let message_ids = self
+            .label_message_ids(mailbox.account_id, label)
+            .await?
+            .unwrap_or_default();
+        let modseq = self.get_modseq(mailbox.account_id).await?;
+
+        let mut id_to_imap = AHashMap::with_capacity(message_ids.len() as usize);
+        let mut uid_to_id = AHashMap::with_capacity(message_ids.len() as usize);
+        for (seqnum, message_id) in message_ids.iter().enumerate() {
+            let uid = message_id + 1;
+            id_to_imap.insert(
+                message_id,
+                ImapId {
+                    uid,
+                    seqnum: seqnum as u32 + 1,
+                },
+            );
+            uid_to_id.insert(uid, message_id);
+        }
+        let uid_max = message_ids.max().map_or(0, |message_id| message_id + 1);
+
+        let mut state = MailboxState {
+            uid_next: uid_max + 1,
+            uid_validity: mailbox.mailbox_id,
+            total_messages: id_to_imap.len(),
+            id_to_imap,
+            uid_to_id,
+            uid_max,
+            modseq,
+            next_state: None,
+            obj_size: 0,
+        };
+        state.obj_size = state.calculate_weight();
+
+        Ok(state)
+    }
+
+    pub async fn email_untag_label(
+        &self,
+        account_id: u32,
+        label: String,
+        deleted_ids: &RoaringBitmap,
+        changelog: &mut ChangeLogBuilder,
+    ) -> trc::Result<()> {
+        let label = Keyword::Other(label);
+
+        for id in deleted_ids {
+            let (mut keywords, thread_id) = if let (Some(keywords), Some(thread_id)) = (
+                self.server
+                    .get_property::<HashedValue<Vec<Keyword>>>(
+                        account_id,
+                        Collection::Email,
+                        id,
+                        Property::Keywords,
+                    )
+                    .await
+                    .caused_by(trc::location!())?,
+                self.server
+                    .get_property::<u32>(account_id, Collection::Email, id, Property::ThreadId)
+                    .await
+                    .caused_by(trc::location!())?,
+            ) {
+                (TagManager::new(keywords), thread_id)
+            } else {
+                continue;
+            };
+
+            // Remove label and Deleted flag
+            keywords.update(label.clone(), false);
+            keywords.update(Keyword::Deleted, false);
+            if !keywords.has_changes() {
+                continue;
+            }
+
+            // Write changes
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Email)
+                .update_document(id);
+            keywords.update_batch(&mut batch, Property::Keywords);
+            if changelog.change_id == u64::MAX {
+                changelog.change_id = self.server.assign_change_id(account_id)?
+            }
+            batch.value(Property::Cid, changelog.change_id, F_VALUE);
+            match self
+                .server
+                .store()
+                .write(batch)
+                .await
+                .caused_by(trc::location!())
+            {
+                Ok(_) => {
+                    changelog.log_update(Collection::Email, Id::from_parts(thread_id, id));
+                }
+                Err(err) => {
+                    if !err.is_assertion_failure() {
+                        return Err(err.caused_by(trc::location!()));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -25,7 +25,7 @@ use parking_lot::Mutex;
 use store::query::log::{Change, Query};
 use trc::AddContext;
 
-use super::{Account, MailboxId, MailboxSync, Session, SessionData};
+use super::{label::label_mailbox_id, Account, MailboxId, MailboxSync, Session, SessionData};
 
 impl<T: SessionStream> SessionData<T> {
     pub async fn new(
@@ -57,21 +57,11 @@ impl<T: SessionStream> SessionData<T> {
                 session
                     .fetch_account_mailboxes(
                         account_id,
-                        format!(
-                            "{}/{}",
-                            session.server.core.jmap.shared_folder,
-                            session
-                                .server
-                                .core
-                                .storage
-                                .directory
-                                .query(QueryBy::Id(account_id), false)
-                                .await
-                                .unwrap_or_default()
-                                .and_then(|mut p| p.take_str(PrincipalField::Name))
-                                .unwrap_or_else(|| Id::from(account_id).to_string())
-                        )
-                        .into(),
+                        session
+                            .shared_account_prefix(account_id)
+                            .await
+                            .caused_by(trc::location!())?
+                            .into(),
                         &access_token,
                     )
                     .await
@@ -84,6 +74,39 @@ impl<T: SessionStream> SessionData<T> {
         Ok(session)
     }
 
+    /// Returns the IMAP prefix of a shared account, mailboxes owned by the
+    /// public folders principal are listed under the public namespace.
+    async fn shared_account_prefix(&self, account_id: u32) -> trc::Result<String> {
+        let name = self
+            .server
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .caused_by(trc::location!())?
+            .and_then(|mut p| p.take_str(PrincipalField::Name));
+
+        Ok(match name {
+            Some(name)
+                if self
+                    .server
+                    .core
+                    .jmap
+                    .public_account
+                    .as_ref()
+                    .is_some_and(|public_account| name.eq_ignore_ascii_case(public_account)) =>
+            {
+                self.server.core.jmap.public_folder.clone()
+            }
+            name => format!(
+                "{}/{}",
+                self.server.core.jmap.shared_folder,
+                name.unwrap_or_else(|| Id::from(account_id).to_string())
+            ),
+        })
+    }
+
     async fn fetch_account_mailboxes(
         &self,
         account_id: u32,
@@ -328,6 +351,31 @@ impl<T: SessionStream> SessionData<T> {
             }
         }
 
+        // Add label folders
+        if let (None, Some(labels_folder)) = (&account.prefix, &self.server.core.imap.labels_folder)
+        {
+            for label in self
+                .fetch_labels(account_id)
+                .await
+                .caused_by(trc::location!())?
+            {
+                // Skip IMAP flags and names already taken by a mailbox
+                let mailbox_name = format!("{labels_folder}/{label}");
+                if label.starts_with('$') || account.mailbox_names.contains_key(&mailbox_name) {
+                    continue;
+                }
+                let mailbox_id = label_mailbox_id(&label);
+                account.mailbox_state.insert(
+                    mailbox_id,
+                    Mailbox {
+                        is_subscribed: true,
+                        ..Default::default()
+                    },
+                );
+                account.mailbox_names.insert(mailbox_name, mailbox_id);
+            }
+        }
+
         // Update cache
         self.server
             .inner
@@ -398,19 +446,7 @@ impl<T: SessionStream> SessionData<T> {
 
             // Fetch mailboxes for each new shared account
             for account_id in added_account_ids {
-                let prefix = format!(
-                    "{}/{}",
-                    self.server.core.jmap.shared_folder,
-                    self.server
-                        .core
-                        .storage
-                        .directory
-                        .query(QueryBy::Id(account_id), false)
-                        .await
-                        .caused_by(trc::location!())?
-                        .and_then(|mut p| p.take_str(PrincipalField::Name))
-                        .unwrap_or_else(|| Id::from(account_id).to_string())
-                );
+                let prefix = self.shared_account_prefix(account_id).await?;
                 added_accounts.push(
                     self.fetch_account_mailboxes(account_id, prefix.into(), &access_token)
                         .await?,
@@ -451,7 +487,12 @@ impl<T: SessionStream> SessionData<T> {
                     }
                 }
 
-                if has_child_changes && !has_changes && changes.is_none() {
+                // Label folders are derived from message keywords, so child changes
+                // on the primary account may add or remove folders
+                let has_labels = self.server.core.imap.labels_folder.is_some()
+                    && access_token.is_primary_id(account_id);
+
+                if has_child_changes && !has_changes && changes.is_none() && !has_labels {
                     // Only child changes, no need to re-fetch mailboxes
                     let state_email = self
                         .server
@@ -506,20 +547,7 @@ impl<T: SessionStream> SessionData<T> {
                 } else {
                     // Refresh mailboxes for changed account
                     let mailbox_prefix = if !access_token.is_primary_id(account_id) {
-                        format!(
-                            "{}/{}",
-                            self.server.core.jmap.shared_folder,
-                            self.server
-                                .core
-                                .storage
-                                .directory
-                                .query(QueryBy::Id(account_id), false)
-                                .await
-                                .caused_by(trc::location!())?
-                                .and_then(|mut p| p.take_str(PrincipalField::Name))
-                                .unwrap_or_else(|| Id::from(account_id).to_string())
-                        )
-                        .into()
+                        self.shared_account_prefix(account_id).await?.into()
                     } else {
                         None
                     };
@@ -630,6 +658,10 @@ impl<T: SessionStream> SessionData<T> {
         document_id: u32,
         item: Acl,
     ) -> trc::Result<bool> {
+        if self.access_token.is_impersonated() && !matches!(item, Acl::Read | Acl::ReadItems) {
+            return Ok(false);
+        }
+
         let access_token = self.get_access_token().await?;
         Ok(access_token.is_member(account_id)
             || self
@@ -20,7 +20,7 @@ use store::{
 };
 use trc::AddContext;
 
-use crate::core::ImapId;
+use crate::core::{ImapId, label::is_label_mailbox};
 
 use super::{ImapUidToId, MailboxId, MailboxState, SelectedMailbox, SessionData};
 
@@ -28,6 +28,11 @@ pub(crate) const MAX_RETRIES: usize = 10;
 
 impl<T: SessionStream> SessionData<T> {
     pub async fn fetch_messages(&self, mailbox: &MailboxId) -> trc::Result<MailboxState> {
+        // Label folders are backed by keywords rather than mailboxes
+        if let Some(label) = self.get_label(mailbox) {
+            return self.fetch_label_messages(mailbox, &label).await;
+        }
+
         // Obtain message ids
         let message_ids = self
             .server
@@ -230,6 +235,10 @@ impl<T: SessionStream> SessionData<T> {
     }
 
     pub async fn get_uid_validity(&self, mailbox: &MailboxId) -> trc::Result<u32> {
+        if is_label_mailbox(mailbox.mailbox_id) {
+            return Ok(mailbox.mailbox_id);
+        }
+
         self.server
             .get_property::<Object<Value>>(
                 mailbox.account_id,
@@ -251,6 +260,17 @@ impl<T: SessionStream> SessionData<T> {
     }
 
     pub async fn get_uid_next(&self, mailbox: &MailboxId) -> trc::Result<u32> {
+        if let Some(label) = self.get_label(mailbox) {
+            return self
+                .label_message_ids(mailbox.account_id, &label)
+                .await
+                .map(|message_ids| {
+                    message_ids
+                        .and_then(|message_ids| message_ids.max())
+                        .map_or(1, |message_id| message_id + 2)
+                });
+        }
+
         self.server
             .core
             .storage
@@ -11,7 +11,7 @@ use std::{
 
 use common::{
     auth::AccessToken,
-    listener::{limiter::InFlight, ServerInstance, SessionStream},
+    listener::{limiter::InFlight, ConnectionInfo, ServerInstance, SessionStream},
     Account, ImapId, Inner, MailboxId, MailboxState, Server,
 };
 use imap_proto::{protocol::ProtocolVersion, receiver::Receiver, Command};
@@ -22,6 +22,7 @@ use tokio::{
 use trc::AddContext;
 
 pub mod client;
+pub mod label;
 pub mod mailbox;
 pub mod message;
 pub mod session;
@@ -46,10 +47,12 @@ pub struct Session<T: SessionStream> {
     pub is_tls: bool,
     pub is_condstore: bool,
     pub is_qresync: bool,
+    pub allow_idle: bool,
     pub stream_rx: ReadHalf<T>,
     pub stream_tx: Arc<tokio::sync::Mutex<WriteHalf<T>>>,
     pub in_flight: InFlight,
     pub remote_addr: IpAddr,
+    pub connection: ConnectionInfo,
     pub session_id: u64,
 }
 
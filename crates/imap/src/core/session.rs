@@ -139,6 +139,7 @@ impl<T: SessionStream> Session<T> {
         let _ = session.stream.flush().await;
 
         // Split stream into read and write halves
+        let connection = session.connection_info();
         let (stream_rx, stream_tx) = tokio::io::split(session.stream);
         let server = manager.inner.build_server();
 
@@ -149,11 +150,13 @@ impl<T: SessionStream> Session<T> {
             is_tls,
             is_condstore: false,
             is_qresync: false,
+            allow_idle: true,
             server,
             instance: session.instance,
             session_id: session.session_id,
             in_flight: session.in_flight,
             remote_addr: session.remote_ip,
+            connection,
             stream_rx,
             stream_tx: Arc::new(tokio::sync::Mutex::new(stream_tx)),
         })
@@ -205,9 +208,11 @@ impl<T: SessionStream> Session<T> {
             is_tls: true,
             is_condstore: self.is_condstore,
             is_qresync: self.is_qresync,
+            allow_idle: self.allow_idle,
             session_id: self.session_id,
             in_flight: self.in_flight,
             remote_addr: self.remote_addr,
+            connection: self.connection,
             stream_rx,
             stream_tx,
         })
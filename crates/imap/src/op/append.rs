@@ -15,10 +15,11 @@ use imap_proto::{
 };
 
 use crate::{
-    core::{ImapUidToId, SelectedMailbox, Session, SessionData},
+    core::{label::is_label_mailbox, ImapUidToId, SelectedMailbox, Session, SessionData},
     spawn_op,
 };
-use common::{listener::SessionStream, MailboxId};
+use common::{config::scripts::ImapSieveCause, listener::SessionStream, MailboxId};
+use jmap::sieve::imapsieve::{ImapSieve, ImapSieveEvent};
 use jmap_proto::types::{acl::Acl, keyword::Keyword, state::StateChange, type_state::DataType};
 use mail_parser::MessageParser;
 
@@ -48,6 +49,13 @@ impl<T: SessionStream> Session<T> {
                 .code(ResponseCode::TryCreate)
                 .id(arguments.tag));
         };
+        if is_label_mailbox(mailbox.mailbox_id) {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Messages cannot be appended to label folders.")
+                .code(ResponseCode::Cannot)
+                .id(arguments.tag));
+        }
         let is_qresync = self.is_qresync;
 
         spawn_op!(data, {
@@ -95,6 +103,8 @@ impl<T: SessionStream> SessionData<T> {
             .imap_ctx(&arguments.tag, trc::location!())?;
         let resource_token = access_token.as_resource_token();
         let spam_train = self.server.email_bayes_can_train(&access_token);
+        let has_imapsieve = self.server.imapsieve_enabled(ImapSieveCause::Append);
+        let mut imapsieve_events = Vec::new();
 
         // Append messages
         let mut response = StatusResponse::completed(Command::Append);
@@ -113,6 +123,7 @@ impl<T: SessionStream> SessionData<T> {
                     source: IngestSource::Imap,
                     spam_classify: false,
                     spam_train,
+                    expires_at: None,
                     session_id: self.session_id,
                 })
                 .await
@@ -123,6 +134,15 @@ impl<T: SessionStream> SessionData<T> {
                         id: email.id.document_id(),
                     });
                     last_change_id = Some(email.change_id);
+
+                    if has_imapsieve {
+                        imapsieve_events.push(ImapSieveEvent {
+                            document_id: email.id.document_id(),
+                            mailbox_id,
+                            cause: ImapSieveCause::Append,
+                            changed_flags: vec![],
+                        });
+                    }
                 }
                 Err(err) => {
                     return Err(
@@ -153,6 +173,11 @@ impl<T: SessionStream> SessionData<T> {
                 .await;
         }
 
+        // Run IMAPSIEVE scripts
+        self.server
+            .imapsieve_run(account_id, imapsieve_events, self.session_id)
+            .await;
+
         trc::event!(
             Imap(trc::ImapEvent::Append),
             SpanId = self.session_id,
@@ -13,7 +13,7 @@ use common::{
 };
 use directory::Permission;
 use imap_proto::{
-    protocol::{authenticate::Mechanism, capability::Capability},
+    protocol::authenticate::Mechanism,
     receiver::{self, Request},
     Command, ResponseCode, StatusResponse,
 };
@@ -115,6 +115,9 @@ impl<T: SessionStream> Session<T> {
             LimiterResult::Disabled => None,
         };
 
+        // Evaluate protocol extensions for the principal
+        self.update_extensions(&access_token.name).await;
+
         // Create session
         self.state = State::Authenticated {
             data: Arc::new(
@@ -126,10 +129,7 @@ impl<T: SessionStream> Session<T> {
         self.write_bytes(
             StatusResponse::ok("Authentication successful")
                 .with_code(ResponseCode::Capability {
-                    capabilities: Capability::all_capabilities(
-                        true,
-                        !self.is_tls && self.instance.acceptor.is_tls(),
-                    ),
+                    capabilities: self.capabilities(true),
                 })
                 .with_tag(tag)
                 .into_bytes(),
@@ -139,6 +139,7 @@ impl<T: SessionStream> Session<T> {
 
     pub async fn handle_unauthenticate(&mut self, request: Request<Command>) -> trc::Result<()> {
         self.state = State::NotAuthenticated { auth_failures: 0 };
+        self.update_extensions("").await;
 
         self.write_bytes(
             StatusResponse::completed(Command::Unauthenticate)
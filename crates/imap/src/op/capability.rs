@@ -7,7 +7,7 @@
 use std::time::Instant;
 
 use crate::core::Session;
-use common::listener::SessionStream;
+use common::listener::{SessionStream, SessionVariables};
 use directory::Permission;
 use imap_proto::{
     protocol::{
@@ -37,10 +37,7 @@ impl<T: SessionStream> Session<T> {
                 .with_tag(request.tag)
                 .serialize(
                     Response {
-                        capabilities: Capability::all_capabilities(
-                            self.state.is_authenticated(),
-                            !self.is_tls && self.instance.acceptor.is_tls(),
-                        ),
+                        capabilities: self.capabilities(self.state.is_authenticated()),
                     }
                     .serialize(),
                 ),
@@ -48,6 +45,33 @@ impl<T: SessionStream> Session<T> {
         .await
     }
 
+    pub fn capabilities(&self, is_authenticated: bool) -> Vec<Capability> {
+        let mut capabilities = Capability::all_capabilities(
+            is_authenticated,
+            !self.is_tls && self.instance.acceptor.is_tls(),
+        );
+        if !self.allow_idle {
+            capabilities.retain(|capability| capability != &Capability::Idle);
+        }
+        capabilities
+    }
+
+    pub async fn update_extensions(&mut self, authenticated_as: &str) {
+        self.allow_idle = self
+            .server
+            .is_extension_enabled(
+                &self.server.core.imap.extension_idle,
+                &SessionVariables {
+                    instance: &self.instance,
+                    connection: &self.connection,
+                    is_tls: self.is_tls,
+                    authenticated_as,
+                },
+                self.session_id,
+            )
+            .await;
+    }
+
     pub async fn handle_id(&mut self, request: Request<Command>) -> trc::Result<()> {
         // Validate access
         self.assert_has_permission(Permission::ImapId)?;
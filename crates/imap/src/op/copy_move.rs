@@ -8,6 +8,7 @@ use std::{sync::Arc, time::Instant};
 
 use directory::Permission;
 use email::{
+    counters::{MailboxCounterFnc, MailboxCounters},
     ingest::EmailIngest,
     mailbox::{UidMailbox, JUNK_ID},
 };
@@ -17,16 +18,19 @@ use imap_proto::{
 };
 
 use crate::{
-    core::{SelectedMailbox, Session, SessionData},
+    core::{label::is_label_mailbox, SelectedMailbox, Session, SessionData},
     spawn_op,
 };
-use common::{listener::SessionStream, MailboxId};
-use jmap::email::{bayes::EmailBayesTrain, copy::EmailCopy, set::TagManager};
+use common::{config::scripts::ImapSieveCause, listener::SessionStream, MailboxId};
+use jmap::{
+    email::{bayes::EmailBayesTrain, copy::EmailCopy, set::TagManager},
+    sieve::imapsieve::{ImapSieve, ImapSieveEvent},
+};
 use jmap_proto::{
     error::set::SetErrorType,
     types::{
-        acl::Acl, collection::Collection, id::Id, property::Property, state::StateChange,
-        type_state::DataType,
+        acl::Acl, collection::Collection, id::Id, keyword::Keyword, property::Property,
+        state::StateChange, type_state::DataType,
     },
 };
 use store::{
@@ -73,6 +77,18 @@ impl<T: SessionStream> Session<T> {
                         .id(arguments.tag));
                 };
 
+            // Label folders can only be used within the same account
+            if src_mailbox.id.account_id != dest_mailbox.account_id
+                && (is_label_mailbox(src_mailbox.id.mailbox_id)
+                    || is_label_mailbox(dest_mailbox.mailbox_id))
+            {
+                return Err(trc::ImapEvent::Error
+                    .into_err()
+                    .details("Labels cannot be copied across accounts.")
+                    .code(ResponseCode::Cannot)
+                    .id(arguments.tag));
+            }
+
             // Check that the destination mailbox is not the same as the source mailbox.
             if src_mailbox.id.account_id == dest_mailbox.account_id
                 && src_mailbox.id.mailbox_id == dest_mailbox.mailbox_id
@@ -174,13 +190,22 @@ impl<T: SessionStream> SessionData<T> {
             .get_access_token(dest_mailbox.account_id)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
+        let has_imapsieve = self.server.imapsieve_enabled(ImapSieveCause::Copy);
+        let mut imapsieve_events = Vec::new();
 
         if src_mailbox.id.account_id == dest_mailbox.account_id {
             // Mailboxes are in the same account
             let account_id = src_mailbox.id.account_id;
             let dest_mailbox_id = UidMailbox::new_unassigned(dest_mailbox_id);
+            let src_label = self.get_label(&src_mailbox.id).map(Keyword::Other);
+            let dest_label = self.get_label(&dest_mailbox).map(Keyword::Other);
             let can_spam_train = self.server.email_bayes_can_train(&access_token);
             let mut has_spam_train_tasks = false;
+            let sizes = self
+                .server
+                .message_sizes(account_id, &ids.keys().copied().collect::<RoaringBitmap>())
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
 
             for (id, imap_id) in ids {
                 // Obtain mailbox tags
@@ -194,19 +219,55 @@ impl<T: SessionStream> SessionData<T> {
                     continue;
                 };
 
-                // Make sure the message still belongs to this mailbox
-                if !mailboxes
-                    .current()
-                    .contains(&UidMailbox::new_unassigned(src_mailbox.id.mailbox_id))
-                    || mailboxes.current().contains(&dest_mailbox_id)
+                // Obtain keywords, labels are stored as keywords
+                let mut keywords = if let Some(keywords) = self
+                    .server
+                    .get_property::<HashedValue<Vec<Keyword>>>(
+                        account_id,
+                        Collection::Email,
+                        id,
+                        Property::Keywords,
+                    )
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?
                 {
+                    TagManager::new(keywords)
+                } else {
+                    continue;
+                };
+
+                // Make sure the message still belongs to this mailbox
+                if !src_label.as_ref().map_or_else(
+                    || {
+                        mailboxes
+                            .current()
+                            .contains(&UidMailbox::new_unassigned(src_mailbox.id.mailbox_id))
+                    },
+                    |label| keywords.current().contains(label),
+                ) || dest_label.as_ref().map_or_else(
+                    || mailboxes.current().contains(&dest_mailbox_id),
+                    |label| keywords.current().contains(label),
+                ) {
                     continue;
                 }
 
-                // Add destination folder
-                mailboxes.update(dest_mailbox_id, true);
+                // Add destination folder, copying to a label only tags the message
+                let mut removed_mailbox = false;
+                if let Some(label) = &dest_label {
+                    keywords.update(label.clone(), true);
+                    copied_ids.push((imap_id.uid, id + 1));
+                } else {
+                    mailboxes.update(dest_mailbox_id, true);
+                }
                 if is_move {
-                    mailboxes.update(UidMailbox::new_unassigned(src_mailbox.id.mailbox_id), false);
+                    if let Some(label) = &src_label {
+                        keywords.update(label.clone(), false);
+                    } else if dest_label.is_none() || mailboxes.current().len() > 1 {
+                        // Messages moved to a label are kept in their only mailbox
+                        mailboxes
+                            .update(UidMailbox::new_unassigned(src_mailbox.id.mailbox_id), false);
+                        removed_mailbox = true;
+                    }
                 }
 
                 // Assign IMAP UIDs
@@ -223,12 +284,23 @@ impl<T: SessionStream> SessionData<T> {
                     }
                 }
 
+                let is_seen = keywords.current().contains(&Keyword::Seen);
+                let size = sizes.get(&id).copied().unwrap_or_default();
+                let mut counters = MailboxCounters::new();
+                if dest_label.is_none() {
+                    counters.add_message([dest_mailbox_id.mailbox_id], is_seen, size);
+                }
+                if removed_mailbox {
+                    counters.remove_message([src_mailbox.id.mailbox_id], is_seen, size);
+                }
+
                 // Prepare write batch
                 let mut batch = BatchBuilder::new();
                 batch
                     .with_account_id(account_id)
                     .with_collection(Collection::Email)
                     .update_document(id);
+                keywords.update_batch(&mut batch, Property::Keywords);
                 mailboxes.update_batch(&mut batch, Property::MailboxIds);
                 if changelog.change_id == u64::MAX {
                     changelog.change_id = self
@@ -264,6 +336,7 @@ impl<T: SessionStream> SessionData<T> {
                         has_spam_train_tasks = true;
                     }
                 }
+                batch.custom(counters);
 
                 // Write changes
                 self.server
@@ -274,11 +347,25 @@ impl<T: SessionStream> SessionData<T> {
 
                 // Update changelog
                 changelog.log_update(Collection::Email, Id::from_parts(thread_id, id));
-                changelog.log_child_update(Collection::Mailbox, dest_mailbox_id.mailbox_id);
+                if dest_label.is_none() {
+                    changelog.log_child_update(Collection::Mailbox, dest_mailbox_id.mailbox_id);
+                }
                 if is_move {
-                    changelog.log_child_update(Collection::Mailbox, src_mailbox.id.mailbox_id);
+                    if removed_mailbox {
+                        changelog.log_child_update(Collection::Mailbox, src_mailbox.id.mailbox_id);
+                    }
                     did_move = true;
                 }
+
+                // Queue IMAPSIEVE event
+                if has_imapsieve && dest_label.is_none() {
+                    imapsieve_events.push(ImapSieveEvent {
+                        document_id: id,
+                        mailbox_id: dest_mailbox_id.mailbox_id,
+                        cause: ImapSieveCause::Copy,
+                        changed_flags: vec![],
+                    });
+                }
             }
 
             // Trigger Bayes training
@@ -309,6 +396,14 @@ impl<T: SessionStream> SessionData<T> {
                 {
                     Ok(email) => {
                         dest_change_id = email.change_id.into();
+                        if has_imapsieve {
+                            imapsieve_events.push(ImapSieveEvent {
+                                document_id: email.id.document_id(),
+                                mailbox_id: dest_mailbox_id,
+                                cause: ImapSieveCause::Copy,
+                                changed_flags: vec![],
+                            });
+                        }
                         if let Some(assigned_uid) = email.imap_uids.first() {
                             debug_assert!(*assigned_uid > 0);
                             copied_ids.push((imap_id.uid, *assigned_uid));
@@ -373,6 +468,11 @@ impl<T: SessionStream> SessionData<T> {
                 .await;
         }
 
+        // Run IMAPSIEVE scripts
+        self.server
+            .imapsieve_run(dest_mailbox.account_id, imapsieve_events, self.session_id)
+            .await;
+
         // Map copied JMAP Ids to IMAP UIDs in the destination folder.
         if copied_ids.is_empty() {
             return Err(if response.rtype != ResponseType::Ok {
@@ -13,7 +13,7 @@ use crate::{
 };
 use common::{listener::SessionStream, Account, Mailbox};
 use directory::Permission;
-use email::mailbox::SCHEMA;
+use email::mailbox::{MailboxFnc, SCHEMA};
 use imap_proto::{
     protocol::{create::Arguments, list::Attribute},
     receiver::Request,
@@ -73,6 +73,9 @@ impl<T: SessionStream> SessionData<T> {
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
         debug_assert!(!params.path.is_empty());
+        self.assert_mailbox_count(params.account_id, params.path.len())
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
 
         // Build batch
         let mut changes = self
@@ -293,9 +296,15 @@ impl<T: SessionStream> SessionData<T> {
 
         // Validate special folders
         let full_path = path.join("/");
+        if self.is_labels_folder(&full_path) {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Mailboxes cannot be created under the labels folder.")
+                .code(ResponseCode::Cannot));
+        }
         let mut parent_mailbox_id = None;
         let mut parent_mailbox_name = None;
-        let (account_id, path) = {
+        let (account_id, depth, path) = {
             let mailboxes = self.mailboxes.lock();
             let account = if path.first() == Some(&self.server.core.jmap.shared_folder.as_str()) {
                 // Shared Folders/<username>/<folder>
@@ -321,6 +330,28 @@ impl<T: SessionStream> SessionData<T> {
                         prefix.unwrap_or_default()
                     )));
                 }
+            } else if path.first() == Some(&self.server.core.jmap.public_folder.as_str()) {
+                // Public/<folder>
+                if path.len() < 2 {
+                    return Err(trc::ImapEvent::Error
+                        .into_err()
+                        .details("Mailboxes under root public folders are not allowed.")
+                        .code(ResponseCode::Cannot));
+                }
+                let prefix = Some(path.remove(0).to_string());
+
+                // Locate account
+                if let Some(account) = mailboxes
+                    .iter()
+                    .skip(1)
+                    .find(|account| account.prefix == prefix)
+                {
+                    account
+                } else {
+                    return Err(trc::ImapEvent::Error
+                        .into_err()
+                        .details("Public folders are not available."));
+                }
             } else if let Some(account) = mailboxes.first() {
                 account
             } else {
@@ -340,6 +371,7 @@ impl<T: SessionStream> SessionData<T> {
 
             (
                 account.account_id,
+                path.len(),
                 if path.len() > 1 {
                     let mut create_path = Vec::with_capacity(path.len());
                     while !path.is_empty() {
@@ -360,6 +392,30 @@ impl<T: SessionStream> SessionData<T> {
             )
         };
 
+        // Validate mailbox policies
+        if depth
+            > self
+                .server
+                .mailbox_limits(account_id)
+                .await
+                .caused_by(trc::location!())?
+                .max_depth
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Mailbox path is too deep.")
+                .code(ResponseCode::Limit));
+        }
+        if let Some(name) = path
+            .iter()
+            .find(|name| !self.server.core.jmap.is_mailbox_name_allowed(name))
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details(format!("Mailbox name '{name}' is not allowed."))
+                .code(ResponseCode::Cannot));
+        }
+
         // Validate ACLs
         if let Some(parent_mailbox_id) = parent_mailbox_id {
             if !self
@@ -420,6 +476,37 @@ impl<T: SessionStream> SessionData<T> {
     }
 }
 
+impl<T: SessionStream> SessionData<T> {
+    pub async fn assert_mailbox_count(
+        &self,
+        account_id: u32,
+        new_mailboxes: usize,
+    ) -> trc::Result<()> {
+        if let Some(max_count) = self
+            .server
+            .mailbox_limits(account_id)
+            .await
+            .caused_by(trc::location!())?
+            .max_count
+        {
+            let count = self
+                .mailboxes
+                .lock()
+                .iter()
+                .find(|account| account.account_id == account_id)
+                .map_or(0, |account| account.mailbox_state.len());
+            if count + new_mailboxes > max_count {
+                return Err(trc::ImapEvent::Error
+                    .into_err()
+                    .details("Maximum number of mailboxes exceeded.")
+                    .code(ResponseCode::OverQuota));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct CreateParams<'x> {
     pub account_id: u32,
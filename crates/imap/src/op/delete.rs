@@ -7,7 +7,7 @@
 use std::time::Instant;
 
 use crate::{
-    core::{Session, SessionData},
+    core::{label::is_label_mailbox, Session, SessionData},
     spawn_op,
 };
 use common::listener::SessionStream;
@@ -69,6 +69,13 @@ impl<T: SessionStream> SessionData<T> {
                     .code(ResponseCode::TryCreate)
                     .id(arguments.tag));
             };
+        if is_label_mailbox(mailbox_id) {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Label folders cannot be deleted.")
+                .code(ResponseCode::Cannot)
+                .id(arguments.tag));
+        }
 
         // Delete message
         let access_token = self
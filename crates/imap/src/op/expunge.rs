@@ -8,7 +8,10 @@ use std::{sync::Arc, time::Instant};
 
 use ahash::AHashMap;
 use directory::Permission;
-use email::mailbox::UidMailbox;
+use email::{
+    counters::{MailboxCounterFnc, MailboxCounters},
+    mailbox::UidMailbox,
+};
 use imap_proto::{
     parser::parse_sequence_set,
     receiver::{Request, Token},
@@ -119,13 +122,7 @@ impl<T: SessionStream> SessionData<T> {
         // Obtain message ids
         let account_id = mailbox.id.account_id;
         let mut deleted_ids = self
-            .server
-            .get_tag(
-                account_id,
-                Collection::Email,
-                Property::MailboxIds,
-                mailbox.id.mailbox_id,
-            )
+            .mailbox_message_ids(&mailbox.id)
             .await
             .caused_by(trc::location!())?
             .unwrap_or_default()
@@ -146,16 +143,22 @@ impl<T: SessionStream> SessionData<T> {
             deleted_ids &= RoaringBitmap::from_iter(sequence.keys());
         }
 
-        // Delete ids
+        // Delete ids, expunging from a label folder only removes the label
         let mut changelog = ChangeLogBuilder::new();
-        self.email_untag_or_delete(
-            account_id,
-            mailbox.id.mailbox_id,
-            &deleted_ids,
-            &mut changelog,
-        )
-        .await
-        .caused_by(trc::location!())?;
+        if let Some(label) = self.get_label(&mailbox.id) {
+            self.email_untag_label(account_id, label, &deleted_ids, &mut changelog)
+                .await
+                .caused_by(trc::location!())?;
+        } else {
+            self.email_untag_or_delete(
+                account_id,
+                mailbox.id.mailbox_id,
+                &deleted_ids,
+                &mut changelog,
+            )
+            .await
+            .caused_by(trc::location!())?;
+        }
 
         trc::event!(
             Imap(trc::ImapEvent::Expunge),
@@ -191,6 +194,11 @@ impl<T: SessionStream> SessionData<T> {
     ) -> trc::Result<()> {
         let mailbox_id = UidMailbox::new_unassigned(mailbox_id);
         let mut destroy_ids = RoaringBitmap::new();
+        let sizes = self
+            .server
+            .message_sizes(account_id, deleted_ids)
+            .await
+            .caused_by(trc::location!())?;
 
         for (id, mailbox_ids) in self
             .server
@@ -236,6 +244,12 @@ impl<T: SessionStream> SessionData<T> {
                     // Untag message from this mailbox and remove Deleted flag
                     mailboxes.update(mailbox_id, false);
                     keywords.update(Keyword::Deleted, false);
+                    let mut counters = MailboxCounters::new();
+                    counters.remove_message(
+                        [mailbox_id.mailbox_id],
+                        keywords.current().contains(&Keyword::Seen),
+                        sizes.get(&id).copied().unwrap_or_default(),
+                    );
 
                     // Write changes
                     let mut batch = BatchBuilder::new();
@@ -248,7 +262,9 @@ impl<T: SessionStream> SessionData<T> {
                     if changelog.change_id == u64::MAX {
                         changelog.change_id = self.server.assign_change_id(account_id)?
                     }
-                    batch.value(Property::Cid, changelog.change_id, F_VALUE);
+                    batch
+                        .value(Property::Cid, changelog.change_id, F_VALUE)
+                        .custom(counters);
                     match self
                         .server
                         .store()
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{borrow::Cow, sync::Arc, time::Instant};
+use std::{borrow::Cow, collections::VecDeque, sync::Arc, time::Instant};
 
 use crate::{
     core::{SelectedMailbox, Session, SessionData},
@@ -13,7 +13,7 @@ use crate::{
 use ahash::AHashMap;
 use common::listener::SessionStream;
 use directory::Permission;
-use email::metadata::MessageMetadata;
+use email::{counters::MailboxCounters, mailbox::UidMailbox, metadata::MessageMetadata};
 use imap_proto::{
     parser::PushUnique,
     protocol::{
@@ -35,12 +35,18 @@ use jmap_proto::types::{
 use mail_parser::{Address, GetHeader, HeaderName, Message, PartType};
 use store::{
     query::log::{Change, Query},
+    roaring::RoaringBitmap,
     write::{assert::HashedValue, BatchBuilder, Bincode, F_BITMAP, F_VALUE},
 };
 use trc::AddContext;
 
 use super::{FromModSeq, ImapContext};
 
+// Number of messages whose metadata is fetched in a single store query
+const FETCH_BATCH_SIZE: usize = 256;
+// Number of message blobs read ahead while responses are being written
+const FETCH_BLOB_PREFETCH: usize = 8;
+
 impl<T: SessionStream> Session<T> {
     pub async fn handle_fetch(&mut self, requests: Vec<Request<Command>>) -> trc::Result<()> {
         // Validate access
@@ -226,6 +232,7 @@ impl<T: SessionStream> SessionData<T> {
         // Build properties list
         let mut set_seen_flags = false;
         let mut needs_thread_id = false;
+        let mut needs_modseq = false;
         let mut needs_blobs = false;
 
         for attribute in &arguments.attributes {
@@ -259,6 +266,9 @@ impl<T: SessionStream> SessionData<T> {
                 Attribute::ThreadId => {
                     needs_thread_id = true;
                 }
+                Attribute::ModSeq => {
+                    needs_modseq = true;
+                }
                 _ => (),
             }
         }
@@ -297,247 +307,312 @@ impl<T: SessionStream> SessionData<T> {
             .map(|id| trc::Value::from(id.2))
             .collect::<Vec<_>>();
 
-        for (seqnum, uid, id) in ids {
-            // Obtain attributes and keywords
-            let (email, keywords) = if let (Some(email), Some(keywords)) = (
+        for chunk in ids.chunks(FETCH_BATCH_SIZE) {
+            // Obtain metadata and keywords for the whole batch
+            let document_ids = chunk
+                .iter()
+                .map(|(_, _, id)| *id)
+                .collect::<RoaringBitmap>();
+            let mut metadata = self
+                .server
+                .get_properties::<Bincode<MessageMetadata>, _, _>(
+                    account_id,
+                    Collection::Email,
+                    &document_ids,
+                    Property::BodyStructure,
+                )
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?
+                .into_iter()
+                .collect::<AHashMap<_, _>>();
+            let mut keywords = self
+                .server
+                .get_properties::<HashedValue<Vec<Keyword>>, _, _>(
+                    account_id,
+                    Collection::Email,
+                    &document_ids,
+                    Property::Keywords,
+                )
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?
+                .into_iter()
+                .collect::<AHashMap<_, _>>();
+            let thread_ids = if needs_thread_id || set_seen_flags {
                 self.server
-                    .get_property::<Bincode<MessageMetadata>>(
+                    .get_properties::<u32, _, _>(
                         account_id,
                         Collection::Email,
-                        id,
-                        &Property::BodyStructure,
+                        &document_ids,
+                        Property::ThreadId,
                     )
                     .await
-                    .imap_ctx(&arguments.tag, trc::location!())?,
+                    .imap_ctx(&arguments.tag, trc::location!())?
+                    .into_iter()
+                    .collect::<AHashMap<_, _>>()
+            } else {
+                AHashMap::new()
+            };
+            let modseqs = if needs_modseq {
                 self.server
-                    .get_property::<HashedValue<Vec<Keyword>>>(
+                    .get_properties::<u64, _, _>(
                         account_id,
                         Collection::Email,
-                        id,
-                        &Property::Keywords,
+                        &document_ids,
+                        Property::Cid,
                     )
                     .await
-                    .imap_ctx(&arguments.tag, trc::location!())?,
-            ) {
-                (email.inner, keywords)
-            } else {
-                trc::event!(
-                    Store(trc::StoreEvent::NotFound),
-                    AccountId = account_id,
-                    DocumentId = id,
-                    Collection = Collection::Email,
-                    Details = "Message metadata not found.",
-                    CausedBy = trc::location!(),
-                );
-                continue;
-            };
-
-            // Fetch and parse blob
-            let raw_message = if needs_blobs {
-                // Retrieve raw message if needed
-                match self
-                    .server
-                    .get_blob(&email.blob_hash, 0..usize::MAX)
-                    .await
                     .imap_ctx(&arguments.tag, trc::location!())?
-                {
-                    Some(raw_message) => raw_message,
-                    None => {
-                        trc::event!(
-                            Store(trc::StoreEvent::NotFound),
-                            AccountId = account_id,
-                            DocumentId = id,
-                            Collection = Collection::Email,
-                            BlobId = email.blob_hash.to_hex(),
-                            Details = "Blob not found.",
-                            CausedBy = trc::location!(),
-                        );
-
-                        continue;
-                    }
-                }
+                    .into_iter()
+                    .collect::<AHashMap<_, _>>()
             } else {
-                email.raw_headers
+                AHashMap::new()
             };
-            let message = email.contents.into_message(&raw_message);
-
-            // Build response
-            let mut items = Vec::with_capacity(arguments.attributes.len());
-            let set_seen_flag =
-                set_seen_flags && !keywords.inner.iter().any(|k| k == &Keyword::Seen);
-            let thread_id = if needs_thread_id || set_seen_flag {
-                if let Some(thread_id) = self
-                    .server
-                    .get_property::<u32>(account_id, Collection::Email, id, Property::ThreadId)
-                    .await
-                    .imap_ctx(&arguments.tag, trc::location!())?
+
+            let mut messages = Vec::with_capacity(chunk.len());
+            for &(seqnum, uid, id) in chunk {
+                if let (Some(email), Some(keywords)) = (metadata.remove(&id), keywords.remove(&id))
                 {
-                    thread_id
+                    messages.push((seqnum, uid, id, email.inner, keywords));
                 } else {
-                    continue;
+                    trc::event!(
+                        Store(trc::StoreEvent::NotFound),
+                        AccountId = account_id,
+                        DocumentId = id,
+                        Collection = Collection::Email,
+                        Details = "Message metadata not found.",
+                        CausedBy = trc::location!(),
+                    );
                 }
+            }
+
+            // Blobs are fetched ahead of time while responses are being written
+            let blob_hashes = if needs_blobs {
+                messages
+                    .iter()
+                    .map(|(_, _, _, email, _)| email.blob_hash.clone())
+                    .collect::<Vec<_>>()
             } else {
-                0
+                Vec::new()
             };
-            for attribute in &arguments.attributes {
-                match attribute {
-                    Attribute::Envelope => {
-                        items.push(DataItem::Envelope {
-                            envelope: message.envelope(),
-                        });
-                    }
-                    Attribute::Flags => {
-                        let mut flags = keywords
-                            .inner
-                            .iter()
-                            .map(|k| Flag::from(k.clone()))
-                            .collect::<Vec<_>>();
-                        if set_seen_flag {
-                            flags.push(Flag::Seen);
+            let mut blob_hashes = blob_hashes.into_iter();
+            let mut pending_blobs = VecDeque::with_capacity(FETCH_BLOB_PREFETCH);
+
+            for (seqnum, uid, id, email, keywords) in messages {
+                // Fetch and parse blob
+                let raw_message = if needs_blobs {
+                    while pending_blobs.len() < FETCH_BLOB_PREFETCH {
+                        if let Some(blob_hash) = blob_hashes.next() {
+                            let server = self.server.clone();
+                            pending_blobs.push_back(tokio::spawn(async move {
+                                let result = server.get_blob(&blob_hash, 0..usize::MAX).await;
+                                (blob_hash, result)
+                            }));
+                        } else {
+                            break;
                         }
-                        items.push(DataItem::Flags { flags });
                     }
-                    Attribute::InternalDate => {
-                        items.push(DataItem::InternalDate {
-                            date: email.received_at as i64,
-                        });
-                    }
-                    Attribute::Preview { .. } => {
-                        items.push(DataItem::Preview {
-                            contents: if !email.preview.is_empty() {
-                                Some(email.preview.as_bytes().into())
-                            } else {
-                                None
-                            },
-                        });
-                    }
-                    Attribute::Rfc822Size => {
-                        items.push(DataItem::Rfc822Size { size: email.size });
-                    }
-                    Attribute::Uid => {
-                        items.push(DataItem::Uid { uid });
+
+                    // Retrieve raw message if needed
+                    let (blob_hash, result) = pending_blobs
+                        .pop_front()
+                        .unwrap()
+                        .await
+                        .map_err(|err| {
+                            trc::EventType::Server(trc::ServerEvent::ThreadError)
+                                .reason(err)
+                                .caused_by(trc::location!())
+                        })
+                        .imap_ctx(&arguments.tag, trc::location!())?;
+                    match result.imap_ctx(&arguments.tag, trc::location!())? {
+                        Some(raw_message) => raw_message,
+                        None => {
+                            trc::event!(
+                                Store(trc::StoreEvent::NotFound),
+                                AccountId = account_id,
+                                DocumentId = id,
+                                Collection = Collection::Email,
+                                BlobId = blob_hash.to_hex(),
+                                Details = "Blob not found.",
+                                CausedBy = trc::location!(),
+                            );
+
+                            continue;
+                        }
                     }
-                    Attribute::Rfc822 => {
-                        items.push(DataItem::Rfc822 {
-                            contents: raw_message.as_slice().into(),
-                        });
+                } else {
+                    email.raw_headers
+                };
+                let message = email.contents.into_message(&raw_message);
+
+                // Build response
+                let mut items = Vec::with_capacity(arguments.attributes.len());
+                let set_seen_flag =
+                    set_seen_flags && !keywords.inner.iter().any(|k| k == &Keyword::Seen);
+                let thread_id = if needs_thread_id || set_seen_flag {
+                    if let Some(thread_id) = thread_ids.get(&id) {
+                        *thread_id
+                    } else {
+                        continue;
                     }
-                    Attribute::Rfc822Header => {
-                        let message = message.root_part();
-                        if let Some(header) =
-                            raw_message.get(message.offset_header..message.offset_body)
-                        {
-                            items.push(DataItem::Rfc822Header {
-                                contents: header.into(),
+                } else {
+                    0
+                };
+                for attribute in &arguments.attributes {
+                    match attribute {
+                        Attribute::Envelope => {
+                            items.push(DataItem::Envelope {
+                                envelope: message.envelope(),
                             });
                         }
-                    }
-                    Attribute::Rfc822Text => {
-                        items.push(DataItem::Rfc822Text {
-                            contents: raw_message.as_slice().into(),
-                        });
-                    }
-                    Attribute::Body => {
-                        items.push(DataItem::Body {
-                            part: message.body_structure(false),
-                        });
-                    }
-                    Attribute::BodyStructure => {
-                        items.push(DataItem::BodyStructure {
-                            part: message.body_structure(true),
-                        });
-                    }
-                    Attribute::BodySection {
-                        sections, partial, ..
-                    } => {
-                        if let Some(contents) = message.body_section(sections, *partial) {
-                            items.push(DataItem::BodySection {
-                                sections: sections.to_vec(),
-                                origin_octet: partial.map(|(start, _)| start),
-                                contents,
+                        Attribute::Flags => {
+                            let mut flags = keywords
+                                .inner
+                                .iter()
+                                .map(|k| Flag::from(k.clone()))
+                                .collect::<Vec<_>>();
+                            if set_seen_flag {
+                                flags.push(Flag::Seen);
+                            }
+                            items.push(DataItem::Flags { flags });
+                        }
+                        Attribute::InternalDate => {
+                            items.push(DataItem::InternalDate {
+                                date: email.received_at as i64,
                             });
                         }
-                    }
-
-                    Attribute::Binary {
-                        sections, partial, ..
-                    } => match message.binary(sections, *partial) {
-                        Ok(Some(contents)) => {
-                            items.push(DataItem::Binary {
-                                sections: sections.to_vec(),
-                                offset: partial.map(|(start, _)| start),
-                                contents,
+                        Attribute::Preview { .. } => {
+                            items.push(DataItem::Preview {
+                                contents: if !email.preview.is_empty() {
+                                    Some(email.preview.as_bytes().into())
+                                } else {
+                                    None
+                                },
                             });
                         }
-                        Err(_) => {
-                            self.write_error(
-                                trc::ImapEvent::Error
-                                    .into_err()
-                                    .details(format!(
-                                        "Failed to decode part {} of message {}.",
-                                        sections
-                                            .iter()
-                                            .map(|s| s.to_string())
-                                            .collect::<Vec<_>>()
-                                            .join("."),
-                                        if is_uid { uid } else { seqnum }
-                                    ))
-                                    .code(ResponseCode::UnknownCte),
-                            )
-                            .await?;
-                            continue;
+                        Attribute::Rfc822Size => {
+                            items.push(DataItem::Rfc822Size { size: email.size });
+                        }
+                        Attribute::Uid => {
+                            items.push(DataItem::Uid { uid });
                         }
-                        _ => (),
-                    },
-                    Attribute::BinarySize { sections } => {
-                        if let Some(size) = message.binary_size(sections) {
-                            items.push(DataItem::BinarySize {
-                                sections: sections.to_vec(),
-                                size,
+                        Attribute::Rfc822 => {
+                            items.push(DataItem::Rfc822 {
+                                contents: raw_message.as_slice().into(),
                             });
                         }
-                    }
-                    Attribute::ModSeq => {
-                        if let Ok(Some(modseq)) = self
-                            .server
-                            .get_property::<u64>(account_id, Collection::Email, id, Property::Cid)
-                            .await
-                        {
-                            items.push(DataItem::ModSeq { modseq: modseq + 1 });
+                        Attribute::Rfc822Header => {
+                            let message = message.root_part();
+                            if let Some(header) =
+                                raw_message.get(message.offset_header..message.offset_body)
+                            {
+                                items.push(DataItem::Rfc822Header {
+                                    contents: header.into(),
+                                });
+                            }
+                        }
+                        Attribute::Rfc822Text => {
+                            items.push(DataItem::Rfc822Text {
+                                contents: raw_message.as_slice().into(),
+                            });
+                        }
+                        Attribute::Body => {
+                            items.push(DataItem::Body {
+                                part: message.body_structure(false),
+                            });
+                        }
+                        Attribute::BodyStructure => {
+                            items.push(DataItem::BodyStructure {
+                                part: message.body_structure(true),
+                            });
+                        }
+                        Attribute::BodySection {
+                            sections, partial, ..
+                        } => {
+                            if let Some(contents) = message.body_section(sections, *partial) {
+                                items.push(DataItem::BodySection {
+                                    sections: sections.to_vec(),
+                                    origin_octet: partial.map(|(start, _)| start),
+                                    contents,
+                                });
+                            }
+                        }
+
+                        Attribute::Binary {
+                            sections, partial, ..
+                        } => match message.binary(sections, *partial) {
+                            Ok(Some(contents)) => {
+                                items.push(DataItem::Binary {
+                                    sections: sections.to_vec(),
+                                    offset: partial.map(|(start, _)| start),
+                                    contents,
+                                });
+                            }
+                            Err(_) => {
+                                self.write_error(
+                                    trc::ImapEvent::Error
+                                        .into_err()
+                                        .details(format!(
+                                            "Failed to decode part {} of message {}.",
+                                            sections
+                                                .iter()
+                                                .map(|s| s.to_string())
+                                                .collect::<Vec<_>>()
+                                                .join("."),
+                                            if is_uid { uid } else { seqnum }
+                                        ))
+                                        .code(ResponseCode::UnknownCte),
+                                )
+                                .await?;
+                                continue;
+                            }
+                            _ => (),
+                        },
+                        Attribute::BinarySize { sections } => {
+                            if let Some(size) = message.binary_size(sections) {
+                                items.push(DataItem::BinarySize {
+                                    sections: sections.to_vec(),
+                                    size,
+                                });
+                            }
+                        }
+                        Attribute::ModSeq => {
+                            if let Some(modseq) = modseqs.get(&id) {
+                                items.push(DataItem::ModSeq { modseq: modseq + 1 });
+                            }
+                        }
+                        Attribute::EmailId => {
+                            items.push(DataItem::EmailId {
+                                email_id: Id::from_parts(account_id, id).to_string(),
+                            });
+                        }
+                        Attribute::ThreadId => {
+                            items.push(DataItem::ThreadId {
+                                thread_id: Id::from_parts(account_id, thread_id).to_string(),
+                            });
                         }
-                    }
-                    Attribute::EmailId => {
-                        items.push(DataItem::EmailId {
-                            email_id: Id::from_parts(account_id, id).to_string(),
-                        });
-                    }
-                    Attribute::ThreadId => {
-                        items.push(DataItem::ThreadId {
-                            thread_id: Id::from_parts(account_id, thread_id).to_string(),
-                        });
                     }
                 }
-            }
 
-            // Add flags to the response if the message was unseen
-            if set_seen_flag && !arguments.attributes.contains(&Attribute::Flags) {
-                let mut flags = keywords
-                    .inner
-                    .iter()
-                    .map(|k| Flag::from(k.clone()))
-                    .collect::<Vec<_>>();
-                flags.push(Flag::Seen);
-                items.push(DataItem::Flags { flags });
-            }
+                // Add flags to the response if the message was unseen
+                if set_seen_flag && !arguments.attributes.contains(&Attribute::Flags) {
+                    let mut flags = keywords
+                        .inner
+                        .iter()
+                        .map(|k| Flag::from(k.clone()))
+                        .collect::<Vec<_>>();
+                    flags.push(Flag::Seen);
+                    items.push(DataItem::Flags { flags });
+                }
 
-            // Serialize fetch item
-            let mut buf = Vec::with_capacity(128);
-            FetchItem { id: seqnum, items }.serialize(&mut buf);
-            self.write_bytes(buf).await?;
+                // Serialize fetch item
+                let mut buf = Vec::with_capacity(128);
+                FetchItem { id: seqnum, items }.serialize(&mut buf);
+                self.write_bytes(buf).await?;
 
-            // Add to set flags
-            if set_seen_flag {
-                set_seen_ids.push((Id::from_parts(thread_id, id), keywords));
+                // Add to set flags
+                if set_seen_flag {
+                    set_seen_ids.push((Id::from_parts(thread_id, id), keywords));
+                }
             }
         }
 
@@ -548,6 +623,16 @@ impl<T: SessionStream> SessionData<T> {
                 .begin_changes(account_id)
                 .imap_ctx(&arguments.tag, trc::location!())?;
             for (id, mut keywords) in set_seen_ids {
+                let mailboxes = self
+                    .server
+                    .get_property::<HashedValue<Vec<UidMailbox>>>(
+                        account_id,
+                        Collection::Email,
+                        id.document_id(),
+                        Property::MailboxIds,
+                    )
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?;
                 keywords.inner.push(Keyword::Seen);
                 let mut batch = BatchBuilder::new();
                 batch
@@ -558,6 +643,13 @@ impl<T: SessionStream> SessionData<T> {
                     .value(Property::Keywords, keywords.inner, F_VALUE)
                     .value(Property::Keywords, Keyword::Seen, F_BITMAP)
                     .value(Property::Cid, changelog.change_id, F_VALUE);
+                if let Some(mailboxes) = &mailboxes {
+                    let mut counters = MailboxCounters::new();
+                    counters.set_seen(mailboxes.inner.iter().map(|m| m.mailbox_id), true);
+                    batch
+                        .assert_value(Property::MailboxIds, mailboxes)
+                        .custom(counters);
+                }
                 match self
                     .server
                     .store()
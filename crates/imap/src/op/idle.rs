@@ -71,6 +71,7 @@ impl<T: SessionStream> Session<T> {
 
         let op_start = Instant::now();
         let mut buf = vec![0; 4];
+        let mut shutdown_rx = self.instance.shutdown_rx.clone();
         loop {
             tokio::select! {
                 result = tokio::time::timeout(self.server.core.imap.timeout_idle, self.stream_rx.read_exact(&mut buf)) => {
@@ -121,6 +122,10 @@ impl<T: SessionStream> Session<T> {
                         return Err(trc::NetworkEvent::Closed.into_err().details("IDLE channel closed.").id(request.tag));
                     }
                 }
+                _ = shutdown_rx.changed() => {
+                    self.write_bytes(&b"* BYE Server shutting down.\r\n"[..]).await.ok();
+                    return Err(trc::NetworkEvent::Closed.into_err().details("Server shutting down.").id(request.tag));
+                }
             }
         }
     }
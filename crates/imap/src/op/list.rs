@@ -7,10 +7,11 @@
 use std::time::Instant;
 
 use crate::{
-    core::{Session, SessionData},
+    core::{Session, SessionData, label::is_label_mailbox},
     spawn_op,
 };
-use common::listener::SessionStream;
+use ahash::AHashSet;
+use common::{Mailbox, listener::SessionStream};
 use directory::Permission;
 use imap_proto::{
     Command, StatusResponse,
@@ -152,13 +153,27 @@ impl<T: SessionStream> SessionData<T> {
                 }
             }
         }
-        if recursive_match && !filter_subscribed {
+        if recursive_match && !filter_subscribed && !filter_special_use {
             return Err(trc::ImapEvent::Error
                 .into_err()
-                .details("RECURSIVEMATCH requires the SUBSCRIBED selection option.")
+                .details("RECURSIVEMATCH requires the SUBSCRIBED or SPECIAL-USE selection options.")
                 .id(tag));
         }
 
+        // Selection options are combined, a mailbox has to match all of them
+        let has_selection_filter = filter_subscribed || filter_special_use;
+        let matches_selection = |mailbox: &Mailbox| {
+            (!filter_subscribed || mailbox.is_subscribed)
+                && (!filter_special_use || mailbox.special_use.is_some())
+        };
+        let mut child_info = Vec::with_capacity(2);
+        if filter_subscribed {
+            child_info.push(ChildInfo::Subscribed);
+        }
+        if filter_special_use {
+            child_info.push(ChildInfo::SpecialUse);
+        }
+
         // Append reference name
         if !patterns.is_empty() && !reference_name.is_empty() {
             patterns.iter_mut().for_each(|item| {
@@ -172,8 +187,8 @@ impl<T: SessionStream> SessionData<T> {
         let mut added_shared_folder = false;
         for account in self.mailboxes.lock().iter() {
             if let Some(prefix) = &account.prefix {
-                if !added_shared_folder {
-                    if !filter_subscribed
+                if !added_shared_folder && prefix != &self.server.core.jmap.public_folder {
+                    if !has_selection_filter
                         && matches_pattern(&patterns, &self.server.core.jmap.shared_folder)
                     {
                         list_items.push(ListItem {
@@ -188,7 +203,7 @@ impl<T: SessionStream> SessionData<T> {
                     }
                     added_shared_folder = true;
                 }
-                if !filter_subscribed && matches_pattern(&patterns, prefix) {
+                if !has_selection_filter && matches_pattern(&patterns, prefix) {
                     list_items.push(ListItem {
                         mailbox_name: prefix.clone(),
                         attributes: if include_children {
@@ -199,67 +214,93 @@ impl<T: SessionStream> SessionData<T> {
                         tags: vec![],
                     });
                 }
+            } else if let Some(labels_folder) = &self.server.core.imap.labels_folder {
+                if !has_selection_filter
+                    && matches_pattern(&patterns, labels_folder)
+                    && account
+                        .mailbox_names
+                        .values()
+                        .any(|mailbox_id| is_label_mailbox(*mailbox_id))
+                {
+                    list_items.push(ListItem {
+                        mailbox_name: labels_folder.clone(),
+                        attributes: if include_children {
+                            vec![Attribute::HasChildren, Attribute::NoSelect]
+                        } else {
+                            vec![Attribute::NoSelect]
+                        },
+                        tags: vec![],
+                    });
+                }
             }
 
-            for (mailbox_name, mailbox_id) in &account.mailbox_names {
-                if matches_pattern(&patterns, mailbox_name) {
-                    let mailbox = if let Some(mailbox) = account.mailbox_state.get(mailbox_id) {
-                        mailbox
-                    } else {
-                        trc::event!(
-                            Store(StoreEvent::UnexpectedError),
-                            Details = "IMAP mailbox no longer present in account state",
-                            Id = *mailbox_id,
-                            Details = account
-                                .mailbox_state
-                                .keys()
-                                .copied()
-                                .map(trc::Value::from)
-                                .collect::<Vec<_>>()
-                        );
-                        continue;
-                    };
-                    let mut has_recursive_match = false;
-                    if recursive_match {
-                        let prefix = format!("{}/", mailbox_name);
-                        for (mailbox_name, mailbox_id) in &account.mailbox_names {
-                            if mailbox_name.starts_with(&prefix)
-                                && account.mailbox_state.get(mailbox_id).unwrap().is_subscribed
-                            {
-                                has_recursive_match = true;
+            // Index the ancestors of the mailboxes matching the selection criteria,
+            // so RECURSIVEMATCH does not require a tree walk for every mailbox
+            let mut recursive_matches = AHashSet::new();
+            if recursive_match {
+                for (mailbox_name, mailbox_id) in &account.mailbox_names {
+                    if account
+                        .mailbox_state
+                        .get(mailbox_id)
+                        .is_some_and(|mailbox| matches_selection(mailbox))
+                    {
+                        let mut name = mailbox_name.as_str();
+                        while let Some((parent_name, _)) = name.rsplit_once('/') {
+                            if !recursive_matches.insert(parent_name) {
                                 break;
                             }
+                            name = parent_name;
                         }
                     }
-                    if !filter_subscribed || mailbox.is_subscribed || has_recursive_match {
-                        let mut attributes = Vec::with_capacity(2);
-                        if include_children {
-                            attributes.push(if mailbox.has_children {
-                                Attribute::HasChildren
-                            } else {
-                                Attribute::HasNoChildren
-                            });
-                        }
-                        if include_subscribed && mailbox.is_subscribed {
-                            attributes.push(Attribute::Subscribed);
-                        }
-                        if include_special_use {
-                            if let Some(special_use) = &mailbox.special_use {
-                                attributes.push(*special_use);
-                            } else if filter_special_use {
-                                continue;
-                            }
-                        }
-                        list_items.push(ListItem {
-                            mailbox_name: mailbox_name.clone(),
-                            attributes,
-                            tags: if !has_recursive_match {
-                                vec![]
-                            } else {
-                                vec![Tag::ChildInfo(vec![ChildInfo::Subscribed])]
-                            },
+                }
+            }
+
+            for (mailbox_name, mailbox_id) in &account.mailbox_names {
+                let mailbox = if let Some(mailbox) = account.mailbox_state.get(mailbox_id) {
+                    mailbox
+                } else {
+                    trc::event!(
+                        Store(StoreEvent::UnexpectedError),
+                        Details = "IMAP mailbox no longer present in account state",
+                        Id = *mailbox_id,
+                        Details = account
+                            .mailbox_state
+                            .keys()
+                            .copied()
+                            .map(trc::Value::from)
+                            .collect::<Vec<_>>()
+                    );
+                    continue;
+                };
+                let has_recursive_match = recursive_matches.contains(mailbox_name.as_str());
+                if (matches_selection(mailbox) || has_recursive_match)
+                    && matches_pattern(&patterns, mailbox_name)
+                {
+                    let mut attributes = Vec::with_capacity(2);
+                    if include_children {
+                        attributes.push(if mailbox.has_children {
+                            Attribute::HasChildren
+                        } else {
+                            Attribute::HasNoChildren
                         });
                     }
+                    if include_subscribed && mailbox.is_subscribed {
+                        attributes.push(Attribute::Subscribed);
+                    }
+                    if include_special_use {
+                        if let Some(special_use) = &mailbox.special_use {
+                            attributes.push(*special_use);
+                        }
+                    }
+                    list_items.push(ListItem {
+                        mailbox_name: mailbox_name.clone(),
+                        attributes,
+                        tags: if !has_recursive_match {
+                            vec![]
+                        } else {
+                            vec![Tag::ChildInfo(child_info.clone())]
+                        },
+                    });
                 }
             }
         }
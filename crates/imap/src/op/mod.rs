@@ -85,16 +85,22 @@ impl<T> ImapContext<T> for trc::Result<T> {
     fn imap_ctx(self, tag: &str, location: &'static str) -> trc::Result<T> {
         match self {
             Ok(value) => Ok(value),
-            Err(err) => Err(
-                if !err.matches(trc::EventType::Imap(trc::ImapEvent::Error)) {
-                    err.ctx(trc::Key::Id, tag.to_string())
-                        .ctx(trc::Key::Details, "Internal Server Error")
-                        .ctx(trc::Key::Code, ResponseCode::ContactAdmin)
-                        .ctx(trc::Key::CausedBy, location)
-                } else {
-                    err.ctx(trc::Key::Id, tag.to_string())
-                },
-            ),
+            Err(err) => Err(if err.is_unavailable() {
+                err.ctx(trc::Key::Id, tag.to_string())
+                    .ctx(
+                        trc::Key::Details,
+                        "Message contents are temporarily unavailable, try again later",
+                    )
+                    .ctx(trc::Key::Code, ResponseCode::Unavailable)
+                    .ctx(trc::Key::CausedBy, location)
+            } else if !err.matches(trc::EventType::Imap(trc::ImapEvent::Error)) {
+                err.ctx(trc::Key::Id, tag.to_string())
+                    .ctx(trc::Key::Details, "Internal Server Error")
+                    .ctx(trc::Key::Code, ResponseCode::ContactAdmin)
+                    .ctx(trc::Key::CausedBy, location)
+            } else {
+                err.ctx(trc::Key::Id, tag.to_string())
+            }),
         }
     }
 }
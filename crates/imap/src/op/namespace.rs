@@ -24,16 +24,25 @@ impl<T: SessionStream> Session<T> {
             Elapsed = trc::Value::Duration(0)
         );
 
+        // Public folders are listed in the shared namespace, other users in the
+        // other users namespace
+        let mut shared_prefix = None;
+        let mut public_prefix = None;
+        for account in self.state.session_data().mailboxes.lock().iter().skip(1) {
+            if account.prefix.as_ref() == Some(&self.server.core.jmap.public_folder) {
+                public_prefix = self.server.core.jmap.public_folder.clone().into();
+            } else {
+                shared_prefix = self.server.core.jmap.shared_folder.clone().into();
+            }
+        }
+
         self.write_bytes(
             StatusResponse::completed(Command::Namespace)
                 .with_tag(request.tag)
                 .serialize(
                     Response {
-                        shared_prefix: if self.state.session_data().mailboxes.lock().len() > 1 {
-                            self.server.core.jmap.shared_folder.clone().into()
-                        } else {
-                            None
-                        },
+                        shared_prefix,
+                        public_prefix,
                     }
                     .serialize(),
                 ),
@@ -19,6 +19,7 @@ use crate::{
 };
 use common::listener::SessionStream;
 use directory::Permission;
+use email::ingest::EmailIngest;
 use imap_proto::{
     protocol::{
         capability::QuotaResourceName,
@@ -100,26 +101,29 @@ impl<T: SessionStream> SessionData<T> {
                     .id(arguments.tag.to_string())
             })?;
 
-        // Obtain access token for mailbox
-        let access_token = self
-            .server
-            .get_access_token(account_id)
-            .await
-            .imap_ctx(&arguments.tag, trc::location!())?;
-        let used_quota = self
-            .server
-            .get_used_quota(account_id)
+        // Obtain quota resources
+        let resources = self
+            .get_quota_resources(account_id)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
+        if resources.is_empty() {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Quota root has no resource limits.")
+                .id(arguments.tag));
+        }
 
         trc::event!(
             Imap(trc::ImapEvent::GetQuota),
             SpanId = self.session_id,
             Id = arguments.name.clone(),
-            Details = vec![
-                trc::Value::from(used_quota),
-                trc::Value::from(access_token.quota)
-            ],
+            Details = resources
+                .iter()
+                .flat_map(|resource| [
+                    trc::Value::from(resource.used),
+                    trc::Value::from(resource.total)
+                ])
+                .collect::<Vec<_>>(),
             Elapsed = op_start.elapsed()
         );
 
@@ -128,11 +132,7 @@ impl<T: SessionStream> SessionData<T> {
             quota_root_items: vec![],
             quota_items: vec![QuotaItem {
                 name: arguments.name,
-                resources: vec![QuotaResource {
-                    resource: QuotaResourceName::Storage,
-                    total: access_token.quota,
-                    used: used_quota as u64,
-                }],
+                resources,
             }],
         };
 
@@ -160,15 +160,9 @@ impl<T: SessionStream> SessionData<T> {
                 .id(arguments.tag));
         };
 
-        // Obtain access token for mailbox
-        let access_token = self
-            .server
-            .get_access_token(account_id)
-            .await
-            .imap_ctx(&arguments.tag, trc::location!())?;
-        let used_quota = self
-            .server
-            .get_used_quota(account_id)
+        // Obtain quota resources
+        let resources = self
+            .get_quota_resources(account_id)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
 
@@ -176,28 +170,61 @@ impl<T: SessionStream> SessionData<T> {
             Imap(trc::ImapEvent::GetQuota),
             SpanId = self.session_id,
             MailboxName = arguments.name.clone(),
-            Details = vec![
-                trc::Value::from(used_quota),
-                trc::Value::from(access_token.quota)
-            ],
+            Details = resources
+                .iter()
+                .flat_map(|resource| [
+                    trc::Value::from(resource.used),
+                    trc::Value::from(resource.total)
+                ])
+                .collect::<Vec<_>>(),
             Elapsed = op_start.elapsed()
         );
 
-        // Build response
-        let response = Response {
-            quota_root_items: vec![arguments.name, format!("#{account_id}")],
-            quota_items: vec![QuotaItem {
-                name: format!("#{account_id}"),
-                resources: vec![QuotaResource {
-                    resource: QuotaResourceName::Storage,
-                    total: access_token.quota,
-                    used: used_quota as u64,
+        // Build response, mailboxes without limits have no quota roots
+        let response = if !resources.is_empty() {
+            let quota_root = format!("#{account_id}");
+            Response {
+                quota_root_items: vec![arguments.name, quota_root.clone()],
+                quota_items: vec![QuotaItem {
+                    name: quota_root,
+                    resources,
                 }],
-            }],
+            }
+        } else {
+            Response {
+                quota_root_items: vec![arguments.name],
+                quota_items: vec![],
+            }
         };
 
         Ok(StatusResponse::ok("GETQUOTAROOT successful.")
             .with_tag(arguments.tag)
             .serialize(response.serialize()))
     }
+
+    async fn get_quota_resources(&self, account_id: u32) -> trc::Result<Vec<QuotaResource>> {
+        let mut resources = Vec::with_capacity(2);
+
+        // Storage quota of the account that owns the mailbox
+        let access_token = self.server.get_access_token(account_id).await?;
+        if access_token.quota > 0 {
+            resources.push(QuotaResource {
+                resource: QuotaResourceName::Storage,
+                total: access_token.quota,
+                used: self.server.get_used_quota(account_id).await? as u64,
+            });
+        }
+
+        // Message count quota
+        let max_messages = self.server.core.jmap.mail_max_messages;
+        if max_messages > 0 {
+            resources.push(QuotaResource {
+                resource: QuotaResourceName::Message,
+                total: max_messages,
+                used: self.server.email_count(account_id).await?,
+            });
+        }
+
+        Ok(resources)
+    }
 }
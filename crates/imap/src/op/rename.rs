@@ -62,6 +62,9 @@ impl<T: SessionStream> SessionData<T> {
             .await
             .add_context(|err| err.id(arguments.tag.clone()))?;
         params.is_rename = true;
+        self.assert_mailbox_count(params.account_id, params.path.len() - 1)
+            .await
+            .add_context(|err| err.id(arguments.tag.clone()))?;
 
         // Validate source mailbox
         let mailbox_id = {
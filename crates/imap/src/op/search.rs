@@ -261,13 +261,7 @@ impl<T: SessionStream> SessionData<T> {
         // Obtain message ids
         let mut filters = Vec::with_capacity(imap_filter.len() + 1);
         let message_ids = self
-            .server
-            .get_tag(
-                mailbox.id.account_id,
-                Collection::Email,
-                Property::MailboxIds,
-                mailbox.id.mailbox_id,
-            )
+            .mailbox_message_ids(&mailbox.id)
             .await?
             .unwrap_or_default();
         filters.push(query::Filter::is_in_set(message_ids.clone()));
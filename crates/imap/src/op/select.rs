@@ -34,11 +34,13 @@ impl<T: SessionStream> Session<T> {
         })?;
 
         let op_start = Instant::now();
-        let is_select = request.command == Command::Select;
         let command = request.command;
         let arguments = request.parse_select(self.version)?;
         let data = self.state.session_data();
 
+        // Impersonated sessions can only open mailboxes in read-only mode
+        let is_select = command == Command::Select && !data.access_token.is_impersonated();
+
         // Refresh mailboxes
         data.synchronize_mailboxes(false)
             .await
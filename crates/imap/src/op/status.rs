@@ -7,12 +7,13 @@
 use std::{sync::Arc, time::Instant};
 
 use crate::{
-    core::{Session, SessionData},
+    core::{Session, SessionData, label::is_label_mailbox},
     op::ImapContext,
     spawn_op,
 };
 use common::{Mailbox, listener::SessionStream};
 use directory::Permission;
+use email::counters::MailboxCounterFnc;
 use imap_proto::{
     Command, ResponseCode, StatusResponse,
     parser::PushUnique,
@@ -98,6 +99,14 @@ impl<T: SessionStream> SessionData<T> {
         } else {
             // Some IMAP clients will try to get the status of a mailbox with the NoSelect flag
             return if mailbox_name == self.server.core.jmap.shared_folder
+                || mailbox_name == self.server.core.jmap.public_folder
+                || self
+                    .server
+                    .core
+                    .imap
+                    .labels_folder
+                    .as_ref()
+                    .is_some_and(|labels_folder| &mailbox_name == labels_folder)
                 || mailbox_name
                     .split_once('/')
                     .is_some_and(|(base_name, path)| {
@@ -230,13 +239,7 @@ impl<T: SessionStream> SessionData<T> {
             // Retrieve latest values
             let mut values_update = Vec::with_capacity(items_update.len());
             let mailbox_message_ids = self
-                .server
-                .get_tag(
-                    mailbox.account_id,
-                    Collection::Email,
-                    Property::MailboxIds,
-                    mailbox.mailbox_id,
-                )
+                .mailbox_message_ids(&mailbox)
                 .await
                 .caused_by(trc::location!())?
                 .map(Arc::new);
@@ -246,13 +249,37 @@ impl<T: SessionStream> SessionData<T> {
                 .await
                 .caused_by(trc::location!())?;
 
+            let count = if items_update
+                .iter()
+                .any(|item| matches!(item, Status::Messages | Status::Unseen | Status::Size))
+                && !is_label_mailbox(mailbox.mailbox_id)
+            {
+                self.server
+                    .mailbox_count(mailbox.account_id, mailbox.mailbox_id)
+                    .await
+                    .caused_by(trc::location!())?
+            } else {
+                None
+            };
+
             for item in items_update {
                 let result = match item {
-                    Status::Messages => mailbox_message_ids.as_ref().map(|v| v.len()).unwrap_or(0),
+                    Status::Messages => {
+                        if let Some(count) = count {
+                            count.total
+                        } else {
+                            mailbox_message_ids.as_ref().map(|v| v.len()).unwrap_or(0)
+                        }
+                    }
                     Status::UidNext => self
                         .get_uid_next(&mailbox)
                         .await
                         .caused_by(trc::location!())? as u64,
+                    Status::UidValidity if is_label_mailbox(mailbox.mailbox_id) => {
+                        self.get_uid_validity(&mailbox)
+                            .await
+                            .caused_by(trc::location!())? as u64
+                    }
                     Status::UidValidity => self
                         .server
                         .get_property::<Object<Value>>(
@@ -273,7 +300,9 @@ impl<T: SessionStream> SessionData<T> {
                                 .document_id(mailbox.mailbox_id)
                         })?,
                     Status::Unseen => {
-                        if let (Some(message_ids), Some(mailbox_message_ids)) =
+                        if let Some(count) = count {
+                            count.unread
+                        } else if let (Some(message_ids), Some(mailbox_message_ids)) =
                             (&message_ids, &mailbox_message_ids)
                         {
                             if let Some(mut seen) = self
@@ -338,7 +367,9 @@ impl<T: SessionStream> SessionData<T> {
                         }
                     }
                     Status::Size => {
-                        if let Some(mailbox_message_ids) = &mailbox_message_ids {
+                        if let Some(count) = count {
+                            count.size
+                        } else if let Some(mailbox_message_ids) = &mailbox_message_ids {
                             self.calculate_mailbox_size(mailbox.account_id, mailbox_message_ids)
                                 .await
                                 .caused_by(trc::location!())?
@@ -11,9 +11,9 @@ use crate::{
     spawn_op,
 };
 use ahash::AHashSet;
-use common::listener::SessionStream;
+use common::{config::scripts::ImapSieveCause, listener::SessionStream};
 use directory::Permission;
-use email::{ingest::EmailIngest, mailbox::UidMailbox};
+use email::{counters::MailboxCounters, ingest::EmailIngest, mailbox::UidMailbox};
 use imap_proto::{
     protocol::{
         fetch::{DataItem, FetchItem},
@@ -25,7 +25,12 @@ use imap_proto::{
 };
 use jmap::{
     changes::get::ChangesLookup,
-    email::{bayes::EmailBayesTrain, set::TagManager},
+    email::{
+        bayes::EmailBayesTrain,
+        keyword::{KeywordChange, KeywordEvents},
+        set::TagManager,
+    },
+    sieve::imapsieve::{ImapSieve, ImapSieveEvent},
 };
 use jmap_proto::types::{
     acl::Acl, collection::Collection, id::Id, keyword::Keyword, property::Property,
@@ -199,6 +204,10 @@ impl<T: SessionStream> SessionData<T> {
             .imap_ctx(response.tag.as_ref().unwrap(), trc::location!())?;
         let can_spam_train = self.server.email_bayes_can_train(&access_token);
         let mut has_spam_train_tasks = false;
+        let has_imapsieve = self.server.imapsieve_enabled(ImapSieveCause::Flag);
+        let mut imapsieve_events = Vec::new();
+        let has_keyword_events = self.server.keyword_events_enabled(account_id).await;
+        let mut keyword_changes: Vec<KeywordChange> = Vec::new();
 
         'outer: for (id, imap_id) in &ids {
             let mut try_count = 0;
@@ -268,6 +277,16 @@ impl<T: SessionStream> SessionData<T> {
                     let seen_changed = keywords
                         .changed_tags()
                         .any(|keyword| keyword == &Keyword::Seen);
+                    let changed_flags = if has_imapsieve {
+                        keywords.changed_tags().cloned().collect::<Vec<_>>()
+                    } else {
+                        vec![]
+                    };
+                    let keyword_change = if has_keyword_events {
+                        self.server.keyword_change(*id, &keywords)
+                    } else {
+                        None
+                    };
                     let flags = if !arguments.is_silent {
                         keywords
                             .current()
@@ -279,12 +298,35 @@ impl<T: SessionStream> SessionData<T> {
                         vec![]
                     };
 
+                    // Obtain mailboxes to update their counters if the Seen tag changed
+                    let mailboxes = if seen_changed {
+                        self.server
+                            .get_property::<HashedValue<Vec<UidMailbox>>>(
+                                account_id,
+                                Collection::Email,
+                                *id,
+                                Property::MailboxIds,
+                            )
+                            .await
+                            .imap_ctx(response.tag.as_ref().unwrap(), trc::location!())?
+                    } else {
+                        None
+                    };
+
                     // Write changes
                     let mut batch = BatchBuilder::new();
+                    let mut counters = MailboxCounters::new();
                     batch
                         .with_account_id(account_id)
                         .with_collection(Collection::Email)
                         .update_document(*id);
+                    if let Some(mailboxes) = &mailboxes {
+                        batch.assert_value(Property::MailboxIds, mailboxes);
+                        counters.set_seen(
+                            mailboxes.inner.iter().map(|m| m.mailbox_id),
+                            keywords.current().contains(&Keyword::Seen),
+                        );
+                    }
                     keywords.update_batch(&mut batch, Property::Keywords);
                     if changelog.change_id == u64::MAX {
                         changelog.change_id = self
@@ -307,6 +349,7 @@ impl<T: SessionStream> SessionData<T> {
                         );
                         has_spam_train_tasks = true;
                     }
+                    batch.custom(counters);
 
                     match self
                         .server
@@ -317,27 +360,26 @@ impl<T: SessionStream> SessionData<T> {
                     {
                         Ok(_) => {
                             // Set all current mailboxes as changed if the Seen tag changed
-                            if seen_changed {
-                                if let Some(mailboxes) = self
-                                    .server
-                                    .get_property::<Vec<UidMailbox>>(
-                                        account_id,
-                                        Collection::Email,
-                                        *id,
-                                        Property::MailboxIds,
-                                    )
-                                    .await
-                                    .imap_ctx(response.tag.as_ref().unwrap(), trc::location!())?
-                                {
-                                    for mailbox_id in mailboxes {
-                                        changed_mailboxes.insert(mailbox_id.mailbox_id);
-                                    }
+                            if let Some(mailboxes) = mailboxes {
+                                for mailbox_id in mailboxes.inner {
+                                    changed_mailboxes.insert(mailbox_id.mailbox_id);
                                 }
                             }
 
                             // Update changelog
                             changelog.log_update(Collection::Email, Id::from_parts(thread_id, *id));
 
+                            // Queue IMAPSIEVE event
+                            if has_imapsieve {
+                                imapsieve_events.push(ImapSieveEvent {
+                                    document_id: *id,
+                                    mailbox_id: mailbox.id.mailbox_id,
+                                    cause: ImapSieveCause::Flag,
+                                    changed_flags,
+                                });
+                            }
+                            keyword_changes.extend(keyword_change);
+
                             // Add item to response
                             let modseq = changelog.change_id + 1;
                             if !arguments.is_silent {
@@ -412,6 +454,15 @@ impl<T: SessionStream> SessionData<T> {
                 .await;
         }
 
+        // Run IMAPSIEVE scripts
+        self.server
+            .imapsieve_run(account_id, imapsieve_events, self.session_id)
+            .await;
+
+        // Notify keyword changes
+        self.server
+            .keyword_change_notify(account_id, keyword_changes, self.session_id);
+
         trc::event!(
             Imap(trc::ImapEvent::Store),
             SpanId = self.session_id,
@@ -7,7 +7,7 @@
 use std::time::Instant;
 
 use crate::{
-    core::{Session, SessionData},
+    core::{label::is_label_mailbox, Session, SessionData},
     spawn_op,
 };
 use common::listener::SessionStream;
@@ -79,6 +79,13 @@ impl<T: SessionStream> SessionData<T> {
                     .caused_by(trc::location!()));
             }
         };
+        if is_label_mailbox(mailbox_id) {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Label folders are always subscribed.")
+                .code(ResponseCode::Cannot)
+                .id(tag));
+        }
 
         // Verify if mailbox is already subscribed/unsubscribed
         for account in self.mailboxes.lock().iter_mut() {
@@ -13,7 +13,7 @@ use crate::{
 use ahash::AHashMap;
 use common::listener::SessionStream;
 use directory::Permission;
-use email::cache::ThreadCache;
+use email::conversation::ConversationIndex;
 use imap_proto::{
     protocol::{
         thread::{Arguments, Response},
@@ -79,19 +79,22 @@ impl<T: SessionStream> SessionData<T> {
             });
         }
 
-        // Lock the cache
-        let thread_ids = self
+        // Obtain the conversation index
+        let conversations = self
             .server
-            .get_cached_thread_ids(mailbox.id.account_id, result_set.results.iter())
+            .get_conversations(mailbox.id.account_id)
             .await
             .caused_by(trc::location!())?;
 
         // Group messages by thread
         let mut threads: AHashMap<u32, Vec<u32>> = AHashMap::new();
         let state = mailbox.state.lock();
-        for (document_id, thread_id) in thread_ids {
-            if let Some((imap_id, _)) = state.map_result_id(document_id, is_uid) {
-                threads.entry(thread_id).or_default().push(imap_id);
+        for document_id in &result_set.results {
+            if let (Some(thread_id), Some((imap_id, _))) = (
+                conversations.message_threads.get(&document_id),
+                state.map_result_id(document_id, is_uid),
+            ) {
+                threads.entry(*thread_id).or_default().push(imap_id);
             }
         }
 
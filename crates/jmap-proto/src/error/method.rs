@@ -165,6 +165,13 @@ impl Serialize for MethodErrorWrapper {
                     "This server is temporarily unavailable.",
                 ),
             },
+            trc::EventType::Store(trc::StoreEvent::BlobStoreUnavailable) => (
+                "serverUnavailable",
+                concat!(
+                    "Message contents are temporarily unavailable, ",
+                    "properties that do not require them can still be fetched."
+                ),
+            ),
             _ => (
                 "serverUnavailable",
                 concat!(
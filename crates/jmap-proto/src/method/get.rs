@@ -165,6 +165,15 @@ impl<T> GetRequest<T> {
         }
     }
 
+    pub fn validate(&self, max_objects_in_get: usize) -> trc::Result<()> {
+        match &self.ids {
+            Some(MaybeReference::Value(ids)) if ids.len() > max_objects_in_get => {
+                Err(trc::JmapEvent::RequestTooLarge.into_err())
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn unwrap_ids(&mut self, max_objects_in_get: usize) -> trc::Result<Option<Vec<Id>>> {
         if let Some(ids) = self.ids.take() {
             let ids = ids.unwrap();
@@ -14,6 +14,7 @@ pub mod lookup;
 pub mod parse;
 pub mod query;
 pub mod query_changes;
+pub mod recall;
 pub mod search_snippet;
 pub mod set;
 pub mod upload;
@@ -147,6 +147,7 @@ pub enum RequestArguments {
     SieveScript,
     Principal,
     Quota,
+    Thread,
 }
 
 impl JsonObjectParser for QueryRequest<RequestArguments> {
@@ -162,6 +163,7 @@ impl JsonObjectParser for QueryRequest<RequestArguments> {
                 MethodObject::SieveScript => RequestArguments::SieveScript,
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::Thread => RequestArguments::Thread,
                 _ => {
                     return Err(trc::JmapEvent::UnknownMethod
                         .into_err()
@@ -0,0 +1,92 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use utils::map::vec_map::VecMap;
+
+use crate::{
+    error::set::SetError,
+    parser::{json::Parser, JsonObjectParser, Token},
+    request::RequestProperty,
+    types::id::Id,
+};
+
+#[derive(Debug, Clone)]
+pub struct EmailRecallRequest {
+    pub account_id: Id,
+    pub ids: Vec<Id>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct EmailRecallResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    #[serde(rename = "recalled")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub recalled: VecMap<Id, Vec<RecallRecipient>>,
+
+    #[serde(rename = "notRecalled")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub not_recalled: VecMap<Id, SetError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RecallRecipient {
+    pub email: String,
+    pub status: RecallStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum RecallStatus {
+    #[serde(rename = "recalled")]
+    Recalled,
+    #[serde(rename = "read")]
+    Read,
+    #[serde(rename = "notFound")]
+    NotFound,
+}
+
+impl JsonObjectParser for EmailRecallRequest {
+    fn parse(parser: &mut Parser<'_>) -> trc::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = EmailRecallRequest {
+            account_id: Id::default(),
+            ids: Vec::new(),
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                0x0073_6469 if !key.is_ref => {
+                    request.ids = <Vec<Id>>::parse(parser)?;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+impl RecallStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecallStatus::Recalled => "recalled",
+            RecallStatus::Read => "read",
+            RecallStatus::NotFound => "not found",
+        }
+    }
+}
@@ -255,6 +255,14 @@ impl Session {
         );
     }
 
+    pub fn remove_capability(&mut self, capability: &Capability) {
+        self.capabilities.remove(capability);
+        self.primary_accounts.remove(capability);
+        for account in self.accounts.values_mut() {
+            account.account_capabilities.remove(capability);
+        }
+    }
+
     pub fn set_state(&mut self, state: u32) {
         self.state = state;
     }
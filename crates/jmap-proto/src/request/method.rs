@@ -44,6 +44,7 @@ pub enum MethodFunction {
     Validate,
     Lookup,
     Upload,
+    Recall,
     Echo,
 }
 
@@ -111,6 +112,7 @@ impl JsonObjectParser for MethodName {
                 0x6574_6164_696c_6176 => MethodFunction::Validate,
                 0x7075_6b6f_6f6c => MethodFunction::Lookup,
                 0x6461_6f6c_7075 => MethodFunction::Upload,
+                0x6c6c_6163_6572 => MethodFunction::Recall,
                 0x6f68_6365 => MethodFunction::Echo,
                 _ => return Err(parser.error_value()),
             },
@@ -149,6 +151,7 @@ impl MethodName {
 
             (MethodFunction::Get, MethodObject::Thread) => "Thread/get",
             (MethodFunction::Changes, MethodObject::Thread) => "Thread/changes",
+            (MethodFunction::Query, MethodObject::Thread) => "Thread/query",
 
             (MethodFunction::Get, MethodObject::Email) => "Email/get",
             (MethodFunction::Changes, MethodObject::Email) => "Email/changes",
@@ -158,6 +161,7 @@ impl MethodName {
             (MethodFunction::Copy, MethodObject::Email) => "Email/copy",
             (MethodFunction::Import, MethodObject::Email) => "Email/import",
             (MethodFunction::Parse, MethodObject::Email) => "Email/parse",
+            (MethodFunction::Recall, MethodObject::Email) => "Email/recall",
 
             (MethodFunction::Get, MethodObject::SearchSnippet) => "SearchSnippet/get",
 
@@ -26,6 +26,7 @@ use crate::{
         parse::ParseEmailRequest,
         query::{self, QueryRequest},
         query_changes::QueryChangesRequest,
+        recall::EmailRecallRequest,
         search_snippet::GetSearchSnippetRequest,
         set::{self, SetRequest},
         upload::BlobUploadRequest,
@@ -71,6 +72,7 @@ pub enum RequestMethod {
     SearchSnippet(GetSearchSnippetRequest),
     ValidateScript(ValidateSieveScriptRequest),
     LookupBlob(BlobLookupRequest),
+    RecallEmail(EmailRecallRequest),
     UploadBlob(BlobUploadRequest),
     Echo(Echo),
     Error(trc::Error),
@@ -16,6 +16,7 @@ use crate::{
         parse::ParseEmailRequest,
         query::QueryRequest,
         query_changes::QueryChangesRequest,
+        recall::EmailRecallRequest,
         search_snippet::GetSearchSnippetRequest,
         set::SetRequest,
         upload::BlobUploadRequest,
@@ -168,6 +169,9 @@ impl Request {
                             (MethodFunction::Parse, MethodObject::Email) => {
                                 ParseEmailRequest::parse(parser).map(RequestMethod::ParseEmail)
                             }
+                            (MethodFunction::Recall, MethodObject::Email) => {
+                                EmailRecallRequest::parse(parser).map(RequestMethod::RecallEmail)
+                            }
                             (MethodFunction::Validate, MethodObject::SieveScript) => {
                                 ValidateSieveScriptRequest::parse(parser)
                                     .map(RequestMethod::ValidateScript)
@@ -20,6 +20,7 @@ use crate::{
         parse::ParseEmailResponse,
         query::QueryResponse,
         query_changes::QueryChangesResponse,
+        recall::EmailRecallResponse,
         search_snippet::GetSearchSnippetResponse,
         set::SetResponse,
         upload::BlobUploadResponse,
@@ -46,6 +47,7 @@ pub enum ResponseMethod {
     SearchSnippet(GetSearchSnippetResponse),
     ValidateScript(ValidateSieveScriptResponse),
     LookupBlob(BlobLookupResponse),
+    RecallEmail(EmailRecallResponse),
     UploadBlob(BlobUploadResponse),
     Echo(Echo),
     Error(MethodErrorWrapper),
@@ -190,6 +192,12 @@ impl From<BlobLookupResponse> for ResponseMethod {
     }
 }
 
+impl From<EmailRecallResponse> for ResponseMethod {
+    fn from(recall_email: EmailRecallResponse) -> Self {
+        ResponseMethod::RecallEmail(recall_email)
+    }
+}
+
 impl<T: Into<ResponseMethod>> From<trc::Result<T>> for ResponseMethod {
     fn from(result: trc::Result<T>) -> Self {
         match result {
@@ -60,6 +60,22 @@ impl JsonObjectParser for Acl {
 }
 
 impl Acl {
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "read" => Some(Acl::Read),
+            "modify" => Some(Acl::Modify),
+            "delete" => Some(Acl::Delete),
+            "readItems" => Some(Acl::ReadItems),
+            "addItems" => Some(Acl::AddItems),
+            "modifyItems" => Some(Acl::ModifyItems),
+            "removeItems" => Some(Acl::RemoveItems),
+            "createChild" => Some(Acl::CreateChild),
+            "administer" => Some(Acl::Administer),
+            "submit" => Some(Acl::Submit),
+            _ => None,
+        }
+    }
+
     fn as_str(&self) -> &'static str {
         match self {
             Acl::Read => "read",
@@ -92,6 +108,17 @@ impl serde::Serialize for Acl {
     }
 }
 
+impl<'de> serde::Deserialize<'de> for Acl {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = <&str>::deserialize(deserializer)?;
+        Acl::from_name(value)
+            .ok_or_else(|| serde::de::Error::custom(format!("Invalid ACL value {value:?}")))
+    }
+}
+
 impl BitmapItem for Acl {
     fn max() -> u64 {
         Acl::None as u64
@@ -111,6 +111,7 @@ impl EventSourceHandler for Server {
             content_type: "text/event-stream".into(),
             content_disposition: "".into(),
             cache_control: "no-store".into(),
+            etag: "".into(),
             body: HttpResponseBody::Stream(BoxBody::new(StreamBody::new(async_stream::stream! {
                 let mut last_message = Instant::now() - throttle;
                 let mut timeout =
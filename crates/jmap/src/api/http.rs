@@ -27,14 +27,17 @@ use hyper::{
 use hyper_util::rt::TokioIo;
 use jmap_proto::{
     error::request::{RequestError, RequestLimitError},
-    request::{capability::Session, Request},
+    request::{
+        capability::{Capability, Session},
+        Request,
+    },
     response::Response,
     types::{blob::BlobId, id::Id},
 };
 use std::future::Future;
 use store::dispatch::lookup::KeyValue;
 use trc::SecurityEvent;
-use utils::url_params::UrlParams;
+use utils::{url_params::UrlParams, BlobHash};
 
 
 use crate::{
@@ -46,7 +49,10 @@ use crate::{
         },
         rate_limit::RateLimiter,
     },
-    blob::{download::BlobDownload, upload::BlobUpload, DownloadResponse, UploadResponse},
+    blob::{
+        blob_etag, download::BlobDownload, upload::BlobUpload, ByteRange, DownloadResponse,
+        Precondition, UploadResponse,
+    },
     websocket::upgrade::WebSocketUpgrade,
 };
 
@@ -57,6 +63,7 @@ use super::{
     management::{troubleshoot::TroubleshootApi, ManagementApi, ManagementApiError},
     request::RequestHandler,
     session::SessionHandler,
+    wkd::WebKeyDirectory,
     HtmlResponse, HttpRequest, HttpResponse, HttpResponseBody, JmapSessionManager, JsonResponse,
 };
 
@@ -141,7 +148,35 @@ impl ParseHttp for Server {
                             path.next().and_then(BlobId::from_base32),
                             path.next(),
                         ) {
-                            return match self.blob_download(&blob_id, &access_token).await? {
+                            // Evaluate preconditions before fetching the blob
+                            let etag = blob_etag(&blob_id);
+                            if !self.has_download_access(&blob_id, &access_token).await? {
+                                return Err(trc::ResourceEvent::NotFound.into_err());
+                            }
+                            match Precondition::evaluate(req.headers(), &etag) {
+                                Precondition::Passed => {}
+                                Precondition::NotModified => {
+                                    return Ok(HttpResponse::new_empty(StatusCode::NOT_MODIFIED)
+                                        .with_etag(etag));
+                                }
+                                Precondition::Failed => {
+                                    return Ok(HttpResponse::new_empty(
+                                        StatusCode::PRECONDITION_FAILED,
+                                    ));
+                                }
+                            }
+
+                            let range = req
+                                .headers()
+                                .get(header::RANGE)
+                                .and_then(|h| h.to_str().ok())
+                                .filter(|_| Precondition::is_range_valid(req.headers(), &etag))
+                                .and_then(ByteRange::parse);
+
+                            return match self
+                                .blob_download_stream(&blob_id, &access_token, range)
+                                .await?
+                            {
                                 Some(blob) => Ok(DownloadResponse {
                                     filename: name.to_string(),
                                     content_type: req
@@ -153,6 +188,7 @@ impl ParseHttp for Server {
                                                 .map(|(_, v)| v.into_owned())
                                         })
                                         .unwrap_or("application/octet-stream".to_string()),
+                                    etag,
                                     blob,
                                 }
                                 .into_http_response()),
@@ -179,18 +215,22 @@ impl ParseHttp for Server {
                             )
                             .await
                             {
-                                Some(bytes) => Ok(self
-                                    .blob_upload(
-                                        account_id,
-                                        req.headers()
-                                            .get(CONTENT_TYPE)
-                                            .and_then(|h| h.to_str().ok())
-                                            .unwrap_or("application/octet-stream"),
-                                        &bytes,
-                                        access_token,
-                                    )
-                                    .await?
-                                    .into_http_response()),
+                                Some(bytes) => {
+                                    let response = self
+                                        .blob_upload(
+                                            account_id,
+                                            req.headers()
+                                                .get(CONTENT_TYPE)
+                                                .and_then(|h| h.to_str().ok())
+                                                .unwrap_or("application/octet-stream"),
+                                            &bytes,
+                                            access_token,
+                                        )
+                                        .await?;
+                                    let etag = response.etag();
+
+                                    Ok(response.into_http_response().with_etag(etag))
+                                }
                                 None => Err(trc::LimitEvent::SizeUpload.into_err()),
                             };
                         }
@@ -207,6 +247,12 @@ impl ParseHttp for Server {
                         let (_in_flight, access_token) =
                             self.authenticate_headers(&req, &session, false).await?;
 
+                        if !access_token.has_jmap_capability(Capability::WebSocket) {
+                            return Err(trc::JmapEvent::Forbidden
+                                .into_err()
+                                .details("WebSocket access is not allowed"));
+                        }
+
                         return self
                             .upgrade_websocket_connection(req, access_token, session)
                             .await;
@@ -262,6 +308,23 @@ impl ParseHttp for Server {
                     self.is_http_anonymous_request_allowed(&session.remote_ip)
                         .await?;
 
+                    // Policies are only published for local domains
+                    if let Some(domain) = req
+                        .headers()
+                        .get(header::HOST)
+                        .and_then(|host| host.to_str().ok())
+                        .and_then(|host| host.split(':').next())
+                        .and_then(|host| host.strip_prefix("mta-sts."))
+                        && !self
+                            .core
+                            .storage
+                            .directory
+                            .is_local_domain(&domain.to_lowercase())
+                            .await?
+                    {
+                        return Err(trc::ResourceEvent::NotFound.into_err());
+                    }
+
                     return if let Some(policy) = self.build_mta_sts_policy() {
                         Ok(Resource::new("text/plain", policy.to_string().into_bytes())
                             .into_http_response())
@@ -287,6 +350,13 @@ impl ParseHttp for Server {
                         return self.handle_autoconfig_request(&req).await;
                     }
                 }
+                ("openpgpkey", &Method::GET) => {
+                    // Limit anonymous requests
+                    self.is_http_anonymous_request_allowed(&session.remote_ip)
+                        .await?;
+
+                    return self.handle_wkd_request(&req, path.collect()).await;
+                }
                 (_, &Method::OPTIONS) => {
                     return Ok(StatusCode::NO_CONTENT.into_http_response());
                 }
@@ -435,7 +505,19 @@ impl ParseHttp for Server {
                     }
                     "ready" => {
                         return Ok({
-                            if !self.core.storage.data.is_none() {
+                            if !self.core.storage.data.is_none()
+                                && self.inner.data.migration.is_completed()
+                            {
+                                StatusCode::OK
+                            } else {
+                                StatusCode::SERVICE_UNAVAILABLE
+                            }
+                        }
+                        .into_http_response());
+                    }
+                    "blob" => {
+                        return Ok({
+                            if !self.blob_store().is_degraded() {
                                 StatusCode::OK
                             } else {
                                 StatusCode::SERVICE_UNAVAILABLE
@@ -443,6 +525,10 @@ impl ParseHttp for Server {
                         }
                         .into_http_response());
                     }
+                    "migration" => {
+                        return Ok(JsonResponse::new(self.inner.data.migration.progress())
+                            .into_http_response());
+                    }
                     _ => (),
                 }
             }
@@ -503,7 +589,16 @@ impl ParseHttp for Server {
                     .await?;
 
                 if !resource.is_empty() {
-                    return Ok(resource.into_http_response());
+                    let etag = format!("\"{}\"", BlobHash::from(&resource.contents).to_hex());
+                    return Ok(match Precondition::evaluate(req.headers(), &etag) {
+                        Precondition::Passed => resource.into_http_response().with_etag(etag),
+                        Precondition::NotModified => {
+                            HttpResponse::new_empty(StatusCode::NOT_MODIFIED).with_etag(etag)
+                        }
+                        Precondition::Failed => {
+                            HttpResponse::new_empty(StatusCode::PRECONDITION_FAILED)
+                        }
+                    });
                 }
             }
         }
@@ -526,8 +621,9 @@ impl ParseHttp for Server {
 async fn handle_session<T: SessionStream>(inner: Arc<Inner>, session: SessionData<T>) {
     let _in_flight = session.in_flight;
     let is_tls = session.stream.is_tls();
+    let mut shutdown_rx = session.instance.shutdown_rx.clone();
 
-    if let Err(http_err) = http1::Builder::new()
+    let connection = http1::Builder::new()
         .keep_alive(true)
         .serve_connection(
             TokioIo::new(session.stream),
@@ -644,7 +740,9 @@ async fn handle_session<T: SessionStream>(inner: Arc<Inner>, session: SessionDat
                         Contents = match &response.body {
                             HttpResponseBody::Text(value) => trc::Value::String(value.clone()),
                             HttpResponseBody::Binary(_) => trc::Value::Static("[binary data]"),
-                            HttpResponseBody::Stream(_) => trc::Value::Static("[stream]"),
+                            HttpResponseBody::Stream(_) | HttpResponseBody::Download { .. } => {
+                                trc::Value::Static("[stream]")
+                            }
                             _ => trc::Value::None,
                         },
                         Code = response.status.as_u16(),
@@ -667,9 +765,19 @@ async fn handle_session<T: SessionStream>(inner: Arc<Inner>, session: SessionDat
                 }
             }),
         )
-        .with_upgrades()
-        .await
-    {
+        .with_upgrades();
+    tokio::pin!(connection);
+
+    let result = tokio::select! {
+        result = connection.as_mut() => result,
+        _ = shutdown_rx.changed() => {
+            // Complete the request in progress, then close the connection
+            connection.as_mut().graceful_shutdown();
+            connection.as_mut().await
+        }
+    };
+
+    if let Err(http_err) = result {
         match inner
             .build_server()
             .is_scanner_fail2banned(session.remote_ip)
@@ -834,6 +942,7 @@ impl HttpResponse {
             content_type: "".into(),
             content_disposition: "".into(),
             cache_control: "".into(),
+            etag: "".into(),
             body: HttpResponseBody::Empty,
         }
     }
@@ -848,6 +957,7 @@ impl HttpResponse {
             content_type: content_type.into(),
             content_disposition: "".into(),
             cache_control: "".into(),
+            etag: "".into(),
             body: HttpResponseBody::Text(body.into()),
         }
     }
@@ -862,10 +972,21 @@ impl HttpResponse {
             content_type: content_type.into(),
             content_disposition: "".into(),
             cache_control: "".into(),
+            etag: "".into(),
             body: HttpResponseBody::Binary(body.into()),
         }
     }
 
+    pub fn with_etag(mut self, etag: impl Into<Cow<'static, str>>) -> Self {
+        self.etag = etag.into();
+        self
+    }
+
+    pub fn with_content_disposition(mut self, disposition: impl Into<Cow<'static, str>>) -> Self {
+        self.content_disposition = disposition.into();
+        self
+    }
+
     pub fn size(&self) -> usize {
         match &self.body {
             HttpResponseBody::Text(value) => value.len(),
@@ -878,7 +999,11 @@ impl HttpResponse {
         self,
     ) -> hyper::Response<http_body_util::combinators::BoxBody<hyper::body::Bytes, hyper::Error>>
     {
-        let builder = hyper::Response::builder().status(self.status);
+        let mut builder = hyper::Response::builder().status(self.status);
+
+        if !self.etag.is_empty() {
+            builder = builder.header(header::ETAG, self.etag.as_ref());
+        }
 
         match self.body {
             HttpResponseBody::Text(body) => builder
@@ -913,6 +1038,31 @@ impl HttpResponse {
                     .map_err(|never| match never {})
                     .boxed(),
             ),
+            HttpResponseBody::Download {
+                content_range,
+                body,
+            } => {
+                let mut builder = builder
+                    .header(header::CONTENT_TYPE, self.content_type.as_ref())
+                    .header(header::ACCEPT_RANGES, "bytes");
+
+                if !self.content_disposition.is_empty() {
+                    builder = builder.header(
+                        header::CONTENT_DISPOSITION,
+                        self.content_disposition.as_ref(),
+                    );
+                }
+
+                if !self.cache_control.is_empty() {
+                    builder = builder.header(header::CACHE_CONTROL, self.cache_control.as_ref());
+                }
+
+                if let Some(content_range) = content_range {
+                    builder = builder.header(header::CONTENT_RANGE, content_range);
+                }
+
+                builder.body(body)
+            }
             HttpResponseBody::Stream(stream) => builder
                 .header(header::CONTENT_TYPE, self.content_type.as_ref())
                 .header(header::CACHE_CONTROL, self.cache_control.as_ref())
@@ -944,6 +1094,7 @@ impl<T: serde::Serialize> ToHttpResponse for JsonResponse<T> {
                 "no-store, no-cache, must-revalidate"
             }
             .into(),
+            etag: "".into(),
             body: HttpResponseBody::Text(serde_json::to_string(&self.inner).unwrap_or_default()),
         }
     }
@@ -1042,6 +1193,13 @@ impl ToRequestError for trc::Error {
                 | trc::SecurityEvent::LoiterBan
                 | trc::SecurityEvent::IpBlocked => RequestError::too_many_auth_attempts(),
                 trc::SecurityEvent::Unauthorized => RequestError::forbidden(),
+                trc::SecurityEvent::DataSearch
+                | trc::SecurityEvent::DataExport
+                | trc::SecurityEvent::EscrowDecryption
+                | trc::SecurityEvent::SessionTerminated
+                | trc::SecurityEvent::IpListUpdated
+                | trc::SecurityEvent::BootstrapEnabled
+                | trc::SecurityEvent::BootstrapCompleted => RequestError::internal_server_error(),
             },
             trc::EventType::Resource(cause) => match cause {
                 trc::ResourceEvent::NotFound => RequestError::not_found(),
@@ -1053,6 +1211,14 @@ impl ToRequestError for trc::Error {
                 trc::ResourceEvent::Error => RequestError::internal_server_error(),
                 _ => RequestError::internal_server_error(),
             },
+            trc::EventType::Store(trc::StoreEvent::BlobStoreUnavailable) => RequestError::blank(
+                StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+                "Service unavailable",
+                concat!(
+                    "Message contents are temporarily unavailable, ",
+                    "please try again later."
+                ),
+            ),
             _ => RequestError::internal_server_error(),
         }
     }
@@ -1108,14 +1274,24 @@ impl ToHttpResponse for Session {
 
 impl ToHttpResponse for ManagementApiError<'_> {
     fn into_http_response(self) -> super::HttpResponse {
-        JsonResponse::new(self).into_http_response()
+        let status = match self {
+            ManagementApiError::RequestTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            ManagementApiError::IdempotencyKeyInProgress => StatusCode::CONFLICT,
+            ManagementApiError::IdempotencyKeyMismatch => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::OK,
+        };
+        JsonResponse::with_status(status, self).into_http_response()
     }
 }
 
 impl ToHttpResponse for DownloadResponse {
     fn into_http_response(self) -> HttpResponse {
         HttpResponse {
-            status: StatusCode::OK,
+            status: if self.blob.content_range.is_some() {
+                StatusCode::PARTIAL_CONTENT
+            } else {
+                StatusCode::OK
+            },
             content_type: self.content_type.into(),
             content_disposition: format!(
                 "attachment; filename=\"{}\"",
@@ -1123,7 +1299,11 @@ impl ToHttpResponse for DownloadResponse {
             )
             .into(),
             cache_control: "private, immutable, max-age=31536000".into(),
-            body: HttpResponseBody::Binary(self.blob),
+            etag: self.etag.into(),
+            body: HttpResponseBody::Download {
+                content_range: self.blob.content_range,
+                body: self.blob.body,
+            },
         }
     }
 }
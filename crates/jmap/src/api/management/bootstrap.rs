@@ -0,0 +1,58 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use hyper::Method;
+use serde_json::json;
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+pub trait BootstrapManagement: Sync + Send {
+    fn handle_manage_bootstrap(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl BootstrapManagement for Server {
+    async fn handle_manage_bootstrap(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match (path.get(1).copied(), req.method()) {
+            (None, &Method::GET) => Ok(JsonResponse::new(json!({
+                "data": {
+                    "active": self.is_bootstrap_active(),
+                },
+            }))
+            .into_http_response()),
+            (Some("complete"), &Method::POST) => {
+                // Revoke the bootstrap token once provisioning is done
+                let completed = self.complete_bootstrap().await?;
+
+                if completed {
+                    trc::event!(
+                        Security(trc::SecurityEvent::BootstrapCompleted),
+                        AccountId = access_token.primary_id(),
+                        AccountName = access_token.name.clone(),
+                    );
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": completed,
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
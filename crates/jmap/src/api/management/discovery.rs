@@ -0,0 +1,422 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use directory::{
+    backend::internal::{
+        manage::{self, not_found, ManageDirectory},
+        PrincipalField,
+    },
+    Type,
+};
+use email::{mailbox::TOMBSTONE_ID, metadata::MessageMetadata};
+use hyper::{Method, StatusCode};
+use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+use mail_parser::{DateTime, HeaderName, MimeHeaders};
+use nlp::language::Language;
+use serde_json::json;
+use store::{
+    fts::{Field, FtsFilter},
+    query::Filter,
+    roaring::RoaringBitmap,
+    write::Bincode,
+};
+use trc::AddContext;
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    email::archive::{EmailArchive, MessageArchive},
+    JmapMethods,
+};
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryQuery {
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub participants: Vec<String>,
+    #[serde(default)]
+    pub after: Option<String>,
+    #[serde(default)]
+    pub before: Option<String>,
+    #[serde(default)]
+    pub attachment_types: Vec<String>,
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryMatch {
+    pub account: String,
+    pub id: Id,
+    pub subject: Option<String>,
+    pub from: Option<String>,
+    pub received_at: String,
+    pub size: usize,
+}
+
+pub trait EDiscoveryApi: Sync + Send {
+    fn handle_ediscovery_request(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn ediscovery_accounts(
+        &self,
+        query: &DiscoveryQuery,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<Vec<(u32, String)>>> + Send;
+
+    fn ediscovery_query(
+        &self,
+        account_id: u32,
+        query: &DiscoveryQuery,
+    ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
+}
+
+impl EDiscoveryApi for Server {
+    async fn handle_ediscovery_request(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        let is_export = match (path.get(1).copied().unwrap_or_default(), req.method()) {
+            ("search", &Method::POST) => false,
+            ("export", &Method::POST) => true,
+            _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+        };
+        let query = serde_json::from_slice::<DiscoveryQuery>(body.as_deref().unwrap_or_default())
+            .map_err(|err| {
+            trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+        })?;
+        let accounts = self.ediscovery_accounts(&query, access_token).await?;
+
+        if is_export {
+            let mut archive = MessageArchive::new();
+            for (account_id, account_name) in &accounts {
+                let document_ids = self.ediscovery_query(*account_id, &query).await?;
+                if !document_ids.is_empty() {
+                    self.email_archive(&mut archive, *account_id, account_name, document_ids)
+                        .await?;
+                }
+            }
+
+            trc::event!(
+                Security(trc::SecurityEvent::DataExport),
+                AccountId = access_token.primary_id(),
+                AccountName = access_token.name.clone(),
+                Details = serde_json::to_string(&query).unwrap_or_default(),
+                Total = archive.len(),
+            );
+
+            Ok(
+                HttpResponse::new_binary(StatusCode::OK, "application/zip", archive.finish()?)
+                    .with_content_disposition("attachment; filename=\"ediscovery.zip\""),
+            )
+        } else {
+            let max_results = self.core.jmap.query_max_results;
+            let mut matches = Vec::new();
+            let mut total = 0;
+            for (account_id, account_name) in &accounts {
+                let document_ids = self.ediscovery_query(*account_id, &query).await?;
+                total += document_ids.len() as usize;
+
+                for document_id in document_ids {
+                    if matches.len() >= max_results {
+                        break;
+                    }
+                    let Some(metadata) = self
+                        .get_property::<Bincode<MessageMetadata>>(
+                            *account_id,
+                            Collection::Email,
+                            document_id,
+                            &Property::BodyStructure,
+                        )
+                        .await
+                        .caused_by(trc::location!())?
+                        .map(|metadata| metadata.inner)
+                    else {
+                        continue;
+                    };
+                    let thread_id = self
+                        .get_property::<u32>(
+                            *account_id,
+                            Collection::Email,
+                            document_id,
+                            Property::ThreadId,
+                        )
+                        .await
+                        .caused_by(trc::location!())?
+                        .unwrap_or_default();
+                    let root_part = metadata.contents.root_part();
+                    matches.push(DiscoveryMatch {
+                        account: account_name.clone(),
+                        id: Id::from_parts(thread_id, document_id),
+                        subject: root_part.headers.iter().find_map(|header| {
+                            if header.name == HeaderName::Subject {
+                                header.value.as_text().map(|subject| subject.to_string())
+                            } else {
+                                None
+                            }
+                        }),
+                        from: root_part.headers.iter().find_map(|header| {
+                            if header.name == HeaderName::From {
+                                header
+                                    .value
+                                    .as_address()
+                                    .and_then(|addr| addr.first())
+                                    .and_then(|addr| addr.address())
+                                    .map(|addr| addr.to_string())
+                            } else {
+                                None
+                            }
+                        }),
+                        received_at: DateTime::from_timestamp(metadata.received_at as i64)
+                            .to_rfc3339(),
+                        size: metadata.size,
+                    });
+                }
+            }
+
+            trc::event!(
+                Security(trc::SecurityEvent::DataSearch),
+                AccountId = access_token.primary_id(),
+                AccountName = access_token.name.clone(),
+                Details = serde_json::to_string(&query).unwrap_or_default(),
+                Total = total,
+            );
+
+            Ok(JsonResponse::new(json!({
+                "data": {
+                    "items": matches,
+                    "total": total,
+                },
+            }))
+            .into_http_response())
+        }
+    }
+
+    async fn ediscovery_accounts(
+        &self,
+        query: &DiscoveryQuery,
+        access_token: &AccessToken,
+    ) -> trc::Result<Vec<(u32, String)>> {
+        let tenant_id = access_token.tenant.map(|t| t.id);
+        let mut accounts = Vec::new();
+
+        for name in &query.accounts {
+            let account_id = self
+                .store()
+                .get_principal_info(name)
+                .await
+                .caused_by(trc::location!())?
+                .filter(|p| {
+                    matches!(p.typ, Type::Individual | Type::Group)
+                        && p.has_tenant_access(tenant_id)
+                })
+                .map(|p| p.id)
+                .ok_or_else(|| not_found(name.to_string()))?;
+            if !accounts.iter().any(|(id, _)| *id == account_id) {
+                accounts.push((account_id, name.to_string()));
+            }
+        }
+
+        if let Some(domain) = query.domain.as_deref().map(|domain| domain.to_lowercase()) {
+            let suffix = format!("@{domain}");
+            for principal in self
+                .store()
+                .list_principals(
+                    domain.as_str().into(),
+                    tenant_id,
+                    &[Type::Individual, Type::Group],
+                    &[PrincipalField::Name, PrincipalField::Emails],
+                    0,
+                    0,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .items
+            {
+                if principal
+                    .iter_str(PrincipalField::Emails)
+                    .any(|email| email.to_lowercase().ends_with(&suffix))
+                    && !accounts.iter().any(|(id, _)| *id == principal.id())
+                {
+                    accounts.push((principal.id(), principal.name().to_string()));
+                }
+            }
+        }
+
+        if accounts.is_empty() {
+            Err(manage::err_missing("accounts"))
+        } else {
+            Ok(accounts)
+        }
+    }
+
+    async fn ediscovery_query(
+        &self,
+        account_id: u32,
+        query: &DiscoveryQuery,
+    ) -> trc::Result<RoaringBitmap> {
+        // Full-text conditions
+        let mut fts_filters = Vec::new();
+        if let Some(text) = &query.text {
+            fts_filters.push(FtsFilter::Or);
+            for header in [HeaderName::From, HeaderName::To, HeaderName::Cc] {
+                fts_filters.push(FtsFilter::has_text(
+                    Field::Header(header),
+                    text,
+                    Language::None,
+                ));
+            }
+            for field in [
+                Field::Header(HeaderName::Subject),
+                Field::Body,
+                Field::Attachment,
+            ] {
+                fts_filters.push(FtsFilter::has_text_detect(
+                    field,
+                    text,
+                    self.core.jmap.default_language,
+                ));
+            }
+            fts_filters.push(FtsFilter::End);
+        }
+        if let Some(subject) = &query.subject {
+            fts_filters.push(FtsFilter::has_text_detect(
+                Field::Header(HeaderName::Subject),
+                subject,
+                self.core.jmap.default_language,
+            ));
+        }
+        for participant in &query.participants {
+            fts_filters.push(FtsFilter::Or);
+            for header in [
+                HeaderName::From,
+                HeaderName::To,
+                HeaderName::Cc,
+                HeaderName::Bcc,
+            ] {
+                fts_filters.push(FtsFilter::has_text(
+                    Field::Header(header),
+                    participant,
+                    Language::None,
+                ));
+            }
+            fts_filters.push(FtsFilter::End);
+        }
+
+        // Metadata conditions
+        let mut filters = Vec::new();
+        if !fts_filters.is_empty() {
+            filters.push(Filter::is_in_set(
+                self.fts_filter(account_id, Collection::Email, fts_filters)
+                    .await?,
+            ));
+        }
+        for (date, is_after) in [(&query.after, true), (&query.before, false)] {
+            if let Some(date) = date {
+                let date = DateTime::parse_rfc3339(date)
+                    .ok_or_else(|| {
+                        manage::error(
+                            "Invalid date",
+                            format!("Failed to parse date {date:?}").into(),
+                        )
+                    })?
+                    .to_timestamp() as u64;
+                filters.push(if is_after {
+                    Filter::gt(Property::ReceivedAt, date)
+                } else {
+                    Filter::lt(Property::ReceivedAt, date)
+                });
+            }
+        }
+        if !query.attachment_types.is_empty() {
+            filters.push(Filter::is_in_bitmap(Property::HasAttachment, ()));
+        }
+        if !query.include_deleted {
+            filters.push(Filter::Not);
+            filters.push(Filter::is_in_bitmap(Property::MailboxIds, TOMBSTONE_ID));
+            filters.push(Filter::End);
+        }
+
+        let mut document_ids = self
+            .filter(account_id, Collection::Email, filters)
+            .await?
+            .results;
+
+        // Attachment types are matched against the message structure
+        if !query.attachment_types.is_empty() {
+            let attachment_types = query
+                .attachment_types
+                .iter()
+                .map(|typ| typ.to_lowercase())
+                .collect::<Vec<_>>();
+            let mut matched_ids = RoaringBitmap::new();
+            for document_id in &document_ids {
+                let Some(metadata) = self
+                    .get_property::<Bincode<MessageMetadata>>(
+                        account_id,
+                        Collection::Email,
+                        document_id,
+                        &Property::BodyStructure,
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+                    .map(|metadata| metadata.inner)
+                else {
+                    continue;
+                };
+
+                if metadata.contents.attachments.iter().any(|part_id| {
+                    metadata.contents.parts.get(*part_id).is_some_and(|part| {
+                        let content_type = part.content_type().map(|ct| {
+                            (
+                                ct.c_type.to_lowercase(),
+                                ct.c_subtype.as_deref().unwrap_or_default().to_lowercase(),
+                            )
+                        });
+                        let file_name = part.attachment_name().map(|name| name.to_lowercase());
+                        attachment_types.iter().any(|typ| {
+                            if let Some(ext) = typ.strip_prefix('.') {
+                                file_name.as_ref().is_some_and(|name| {
+                                    name.rsplit_once('.').is_some_and(|(_, e)| e == ext)
+                                })
+                            } else if let Some((c_type, c_subtype)) = typ.split_once('/') {
+                                content_type.as_ref().is_some_and(|(t, s)| {
+                                    t == c_type && (c_subtype == "*" || s == c_subtype)
+                                })
+                            } else {
+                                content_type.as_ref().is_some_and(|(t, _)| t == typ)
+                            }
+                        })
+                    })
+                }) {
+                    matched_ids.insert(document_id);
+                }
+            }
+            document_ids = matched_ids;
+        }
+
+        Ok(document_ids)
+    }
+}
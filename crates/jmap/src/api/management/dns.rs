@@ -4,10 +4,14 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use base64::{engine::general_purpose::STANDARD, Engine};
 use common::{auth::AccessToken, Server};
 use directory::{
-    backend::internal::manage::{self},
-    Permission,
+    backend::internal::{
+        manage::{self, ManageDirectory},
+        PrincipalField,
+    },
+    Permission, Type,
 };
 
 use hyper::Method;
@@ -20,6 +24,7 @@ use x509_parser::parse_x509_certificate;
 use crate::api::{
     http::ToHttpResponse,
     management::dkim::{obtain_dkim_public_key, Algorithm},
+    wkd::{openpgpkey_owner, WebKeyDirectory},
     HttpRequest, HttpResponse, JsonResponse,
 };
 
@@ -241,6 +246,38 @@ impl DnsManagement for Server {
             content: format!("v=TLSRPTv1; rua=mailto:postmaster@{domain_name}",),
         });
 
+        // Add OPENPGPKEY records for users that uploaded a public key
+        for principal in self
+            .core
+            .storage
+            .data
+            .list_principals(
+                format!("@{domain_name}").as_str().into(),
+                None,
+                &[Type::Individual],
+                &[PrincipalField::Emails],
+                0,
+                0,
+            )
+            .await?
+            .items
+        {
+            for email in principal.iter_str(PrincipalField::Emails) {
+                if let Some(local_part) = email.strip_suffix(&format!("@{domain_name}"))
+                    && let Some(key) = self.openpgp_key(principal.id(), email).await?
+                {
+                    records.push(DnsRecord {
+                        typ: "OPENPGPKEY".to_string(),
+                        name: format!(
+                            "{}._openpgpkey.{domain_name}.",
+                            openpgpkey_owner(local_part)
+                        ),
+                        content: STANDARD.encode(key),
+                    });
+                }
+            }
+        }
+
         // Add TLSA records
         for (name, key) in self.inner.data.tls_certificates.load().iter() {
             if !name.ends_with(domain_name)
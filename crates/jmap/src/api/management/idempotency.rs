@@ -0,0 +1,169 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, KV_IDEMPOTENCY, KV_LOCK_IDEMPOTENCY};
+use hyper::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use store::{dispatch::lookup::KeyValue, write::Bincode, Serialize as _};
+use trc::AddContext;
+
+use crate::api::{HttpRequest, HttpResponse, HttpResponseBody};
+use std::future::Future;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const IDEMPOTENCY_KEY_MAX_LEN: usize = 255;
+const IDEMPOTENCY_LOCK_EXPIRY: u64 = 60;
+
+pub struct IdempotencyKey {
+    key: Vec<u8>,
+    fingerprint: Vec<u8>,
+}
+
+pub enum IdempotencyState {
+    New(IdempotencyKey),
+    Replay(HttpResponse),
+    InProgress,
+    Mismatch,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredResponse {
+    fingerprint: Vec<u8>,
+    status: u16,
+    content_type: String,
+    body: Vec<u8>,
+}
+
+pub trait IdempotencyManager: Sync + Send {
+    fn idempotency_begin(
+        &self,
+        req: &HttpRequest,
+        account_id: u32,
+        body: &[u8],
+    ) -> impl Future<Output = trc::Result<Option<IdempotencyState>>> + Send;
+
+    fn idempotency_complete(
+        &self,
+        key: IdempotencyKey,
+        response: Option<&HttpResponse>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl IdempotencyManager for Server {
+    async fn idempotency_begin(
+        &self,
+        req: &HttpRequest,
+        account_id: u32,
+        body: &[u8],
+    ) -> trc::Result<Option<IdempotencyState>> {
+        // Only mutating requests carrying a key are deduplicated
+        let idempotency_key = match (
+            self.core.jmap.idempotency_ttl,
+            req.method(),
+            req.headers()
+                .get(IDEMPOTENCY_KEY_HEADER)
+                .and_then(|h| h.to_str().ok()),
+        ) {
+            (
+                Some(_),
+                &Method::POST | &Method::PUT | &Method::PATCH | &Method::DELETE,
+                Some(key),
+            ) if !key.is_empty() && key.len() <= IDEMPOTENCY_KEY_MAX_LEN => key,
+            _ => return Ok(None),
+        };
+
+        // Keys are scoped to the account so clients cannot collide
+        let mut key = Vec::with_capacity(idempotency_key.len() + std::mem::size_of::<u32>());
+        key.extend_from_slice(&account_id.to_be_bytes());
+        key.extend_from_slice(idempotency_key.as_bytes());
+
+        let mut hasher = Sha256::new();
+        hasher.update(req.method().as_str().as_bytes());
+        hasher.update(b" ");
+        hasher.update(req.uri().to_string().as_bytes());
+        hasher.update(b"\n");
+        hasher.update(body);
+        let fingerprint = hasher.finalize().to_vec();
+
+        let lookup = &self.core.storage.lookup;
+        if let Some(stored) = lookup
+            .key_get::<Bincode<StoredResponse>>(KeyValue::<()>::build_key(KV_IDEMPOTENCY, &key))
+            .await
+            .caused_by(trc::location!())?
+        {
+            let stored = stored.inner;
+            return Ok(Some(if stored.fingerprint == fingerprint {
+                IdempotencyState::Replay(HttpResponse {
+                    status: StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK),
+                    content_type: stored.content_type.into(),
+                    content_disposition: "".into(),
+                    cache_control: "".into(),
+                    etag: "".into(),
+                    body: HttpResponseBody::Binary(stored.body),
+                })
+            } else {
+                IdempotencyState::Mismatch
+            }));
+        }
+
+        if lookup
+            .try_lock(KV_LOCK_IDEMPOTENCY, &key, IDEMPOTENCY_LOCK_EXPIRY)
+            .await
+            .caused_by(trc::location!())?
+        {
+            Ok(Some(IdempotencyState::New(IdempotencyKey {
+                key,
+                fingerprint,
+            })))
+        } else {
+            Ok(Some(IdempotencyState::InProgress))
+        }
+    }
+
+    async fn idempotency_complete(
+        &self,
+        key: IdempotencyKey,
+        response: Option<&HttpResponse>,
+    ) -> trc::Result<()> {
+        let lookup = &self.core.storage.lookup;
+
+        // Streamed responses and server errors are not stored, allowing clients to retry
+        if let (Some(response), Some(ttl)) = (response, self.core.jmap.idempotency_ttl) {
+            let body = match &response.body {
+                HttpResponseBody::Text(text) => Some(text.as_bytes().to_vec()),
+                HttpResponseBody::Binary(bytes) => Some(bytes.clone()),
+                HttpResponseBody::Empty => Some(Vec::new()),
+                _ => None,
+            };
+
+            if let Some(body) = body.filter(|_| !response.status.is_server_error()) {
+                lookup
+                    .key_set(
+                        KeyValue::with_prefix(
+                            KV_IDEMPOTENCY,
+                            &key.key,
+                            Bincode::new(StoredResponse {
+                                fingerprint: key.fingerprint,
+                                status: response.status.as_u16(),
+                                content_type: response.content_type.to_string(),
+                                body,
+                            })
+                            .serialize(),
+                        )
+                        .expires(ttl.as_secs()),
+                    )
+                    .await
+                    .caused_by(trc::location!())?;
+            }
+        }
+
+        lookup
+            .remove_lock(KV_LOCK_IDEMPOTENCY, &key.key)
+            .await
+            .caused_by(trc::location!())
+    }
+}
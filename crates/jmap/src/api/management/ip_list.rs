@@ -0,0 +1,264 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{
+    auth::AccessToken,
+    listener::blocked::{IpList, IpListEntry, ALLOWED_IP_PREFIX, BLOCKED_IP_PREFIX},
+    Server,
+};
+use directory::backend::internal::manage::{self, not_found};
+use hyper::Method;
+use serde_json::json;
+use store::write::now;
+use utils::{
+    config::{ipmask::IpAddrOrMask, utils::ParseValue, ConfigKey},
+    url_params::UrlParams,
+};
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+use super::decode_path_element;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IpListRequest {
+    ip: String,
+    #[serde(default)]
+    expires: Option<u64>,
+    #[serde(default)]
+    comment: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IpListItem {
+    ip: String,
+    #[serde(flatten)]
+    entry: IpListEntry,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum IpListType {
+    Allowed,
+    Blocked,
+}
+
+pub trait IpListManagement: Sync + Send {
+    fn handle_manage_ip_list(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl IpListManagement for Server {
+    async fn handle_manage_ip_list(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        let list = match path.get(1).copied() {
+            Some("allowed") => IpListType::Allowed,
+            Some("blocked") => IpListType::Blocked,
+            _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+        };
+        let prefix = list.prefix();
+
+        match (path.get(2), req.method()) {
+            (None, &Method::GET) => {
+                let params = UrlParams::new(req.uri().query());
+                let filter = params.get("filter");
+                let source = params.get("source");
+                let page: usize = params.parse("page").unwrap_or(0);
+                let limit: usize = params.parse("limit").unwrap_or(0);
+
+                let now = now();
+                let items = self
+                    .core
+                    .storage
+                    .config
+                    .list(prefix, true)
+                    .await?
+                    .into_iter()
+                    .map(|(ip, value)| IpListItem {
+                        entry: IpListEntry::parse(&value),
+                        ip,
+                    })
+                    .filter(|item| {
+                        !item.entry.is_expired(now)
+                            && filter.is_none_or(|filter| {
+                                item.ip.contains(filter)
+                                    || item
+                                        .entry
+                                        .comment
+                                        .as_ref()
+                                        .is_some_and(|comment| comment.contains(filter))
+                            })
+                            && source
+                                .is_none_or(|source| item.entry.source.as_deref() == Some(source))
+                    })
+                    .collect::<Vec<_>>();
+                let total = items.len();
+                let items = if limit > 0 {
+                    items
+                        .into_iter()
+                        .skip(page.saturating_sub(1) * limit)
+                        .take(limit)
+                        .collect::<Vec<_>>()
+                } else {
+                    items
+                };
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": items,
+                        "total": total,
+                    },
+                }))
+                .into_http_response())
+            }
+            (None, &Method::POST) => {
+                let request =
+                    serde_json::from_slice::<IpListRequest>(body.as_deref().unwrap_or_default())
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+                let ip = request.ip.trim();
+                let network = IpAddrOrMask::parse_value(ip)
+                    .map_err(|err| manage::error("Invalid IP address", Some(err)))?;
+                let now = now();
+                if request.expires.is_some_and(|expires| expires <= now) {
+                    return Err(manage::error(
+                        "Invalid expiration time",
+                        Some("Expiration time must be in the future"),
+                    ));
+                }
+                let entry = IpListEntry {
+                    expires: request.expires,
+                    created: now.into(),
+                    source: access_token.name.clone().into(),
+                    comment: request.comment.filter(|comment| !comment.is_empty()),
+                };
+
+                self.core
+                    .storage
+                    .config
+                    .set(
+                        [ConfigKey {
+                            key: format!("{prefix}{ip}"),
+                            value: entry.to_value(),
+                        }],
+                        true,
+                    )
+                    .await?;
+                list.update(self, |ips| ips.insert(network, entry.expires));
+                self.purge_expired_ip_list(prefix, now).await?;
+                self.increment_blocked_version();
+
+                trc::event!(
+                    Security(trc::SecurityEvent::IpListUpdated),
+                    AccountId = access_token.primary_id(),
+                    AccountName = access_token.name.clone(),
+                    Type = list.as_str(),
+                    Id = ip.to_string(),
+                    Details = "add",
+                    Expires = entry.expires,
+                );
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some(ip), &Method::DELETE) => {
+                let ip = decode_path_element(ip);
+                let ip = ip.trim();
+                let network =
+                    IpAddrOrMask::parse_value(ip).map_err(|_| not_found(ip.to_string()))?;
+                let key = format!("{prefix}{ip}");
+                if self.core.storage.config.get(&key).await?.is_none() {
+                    return Err(not_found(ip.to_string()));
+                }
+
+                self.core.storage.config.clear(&key).await?;
+                list.update(self, |ips| ips.remove(&network));
+                self.purge_expired_ip_list(prefix, now()).await?;
+                self.increment_blocked_version();
+
+                trc::event!(
+                    Security(trc::SecurityEvent::IpListUpdated),
+                    AccountId = access_token.primary_id(),
+                    AccountName = access_token.name.clone(),
+                    Type = list.as_str(),
+                    Id = ip.to_string(),
+                    Details = "remove",
+                );
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+trait IpListPurge {
+    fn purge_expired_ip_list(
+        &self,
+        prefix: &str,
+        now: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl IpListPurge for Server {
+    async fn purge_expired_ip_list(&self, prefix: &str, now: u64) -> trc::Result<()> {
+        // Expired entries are ignored when matching, remove them from the
+        // settings whenever the list is modified.
+        for (ip, value) in self.core.storage.config.list(prefix, true).await? {
+            if IpListEntry::parse(&value).is_expired(now) {
+                self.core
+                    .storage
+                    .config
+                    .clear(format!("{prefix}{ip}"))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl IpListType {
+    fn prefix(&self) -> &'static str {
+        match self {
+            IpListType::Allowed => ALLOWED_IP_PREFIX,
+            IpListType::Blocked => BLOCKED_IP_PREFIX,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            IpListType::Allowed => "allowed",
+            IpListType::Blocked => "blocked",
+        }
+    }
+
+    fn update(&self, server: &Server, f: impl FnOnce(&mut IpList)) {
+        match self {
+            IpListType::Allowed => f(&mut server.inner.data.allowed_ips.write()),
+            IpListType::Blocked => f(&mut server.inner.data.blocked_ips.write()),
+        }
+    }
+}
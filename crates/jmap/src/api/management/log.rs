@@ -10,12 +10,15 @@ use directory::{backend::internal::manage, Permission};
 use rev_lines::RevLines;
 use serde::Serialize;
 use serde_json::json;
+use smtp::queue::trace_id;
 use std::future::Future;
 use tokio::sync::oneshot;
 use utils::url_params::UrlParams;
 
 use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
 
+const MAX_TRACE_EVENTS: usize = 1000;
+
 #[derive(Serialize)]
 struct LogEntry {
     timestamp: String,
@@ -31,6 +34,12 @@ pub trait LogManagement: Sync + Send {
         req: &HttpRequest,
         access_token: &AccessToken,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_view_trace(
+        &self,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
 }
 
 impl LogManagement for Server {
@@ -83,6 +92,66 @@ impl LogManagement for Server {
         }))
         .into_http_response())
     }
+
+    async fn handle_view_trace(
+        &self,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        // Validate the access token
+        access_token.assert_has_permission(Permission::LogsView)?;
+
+        let trace_id = path
+            .get(1)
+            .and_then(|id| u64::from_str_radix(id, 16).ok())
+            .map(trace_id)
+            .ok_or_else(|| {
+                trc::ResourceEvent::BadParameters
+                    .into_err()
+                    .details("Invalid trace id")
+            })?;
+        let path = self
+            .core
+            .metrics
+            .log_path
+            .clone()
+            .ok_or_else(|| manage::unsupported("Tracer log path not configured"))?;
+
+        // Events of the same message are tagged with its trace id
+        let filter = format!("traceId = \"{trace_id}\"");
+        let (tx, rx) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(read_log_files(path, &filter, 0, MAX_TRACE_EVENTS));
+        });
+
+        let (_, mut items) = rx
+            .await
+            .map_err(|err| {
+                trc::EventType::Server(trc::ServerEvent::ThreadError)
+                    .reason(err)
+                    .caused_by(trc::location!())
+            })?
+            .map_err(|err| {
+                trc::ManageEvent::Error
+                    .reason(err)
+                    .details("Failed to read log files")
+                    .caused_by(trc::location!())
+            })?;
+        if items.is_empty() {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        // Log files are read from newest to oldest
+        items.reverse();
+
+        Ok(JsonResponse::new(json!({
+            "data": {
+                "traceId": trace_id,
+                "items": items,
+            },
+        }))
+        .into_http_response())
+    }
 }
 
 fn read_log_files(
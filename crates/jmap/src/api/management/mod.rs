@@ -4,25 +4,37 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod bootstrap;
+pub mod discovery;
 pub mod dkim;
 pub mod dns;
+pub mod idempotency;
+pub mod ip_list;
 pub mod log;
 pub mod principal;
 pub mod queue;
 pub mod reload;
 pub mod report;
+pub mod sessions;
 pub mod settings;
+pub mod sieve;
+pub mod smime;
 pub mod spam;
 pub mod stores;
+pub mod tenant;
 pub mod troubleshoot;
 
 use std::{borrow::Cow, str::FromStr, sync::Arc};
 
+use bootstrap::BootstrapManagement;
 use common::{auth::AccessToken, Server};
-use directory::{backend::internal::manage, Permission};
+use directory::Permission;
+use discovery::EDiscoveryApi;
 use dkim::DkimManagement;
 use dns::DnsManagement;
-use hyper::Method;
+use hyper::{header, Method};
+use idempotency::{IdempotencyManager, IdempotencyState};
+use ip_list::IpListManagement;
 use log::LogManagement;
 use mail_parser::DateTime;
 use principal::PrincipalManager;
@@ -30,16 +42,27 @@ use queue::QueueManagement;
 use reload::ManageReload;
 use report::ManageReports;
 use serde::Serialize;
+use sessions::SessionManagement;
 use settings::ManageSettings;
+use sieve::ManageSieveLibrary;
+use smime::SmimeManagement;
 use spam::ManageSpamHandler;
 use store::write::now;
 use stores::ManageStore;
+use tenant::TenantManager;
 use troubleshoot::TroubleshootApi;
 
-use crate::{auth::oauth::auth::OAuthApiHandler, email::crypto::CryptoHandler};
+use crate::{
+    auth::oauth::auth::OAuthApiHandler,
+    email::{
+        auth_report::AuthReportHandler, crypto::CryptoHandler, fetch::ExternalAccountHandler,
+        recall::EmailRecall, sender_list::SenderListHandler,
+    },
+    submission::api::HttpSubmission, vacation::manage::VacationScheduleHandler,
+};
 
 use super::{
-    http::{fetch_body, HttpSessionData},
+    http::{fetch_body, HttpSessionData, ToHttpResponse},
     HttpRequest, HttpResponse,
 };
 use std::future::Future;
@@ -62,6 +85,12 @@ pub enum ManagementApiError<'x> {
         details: &'x str,
     },
     AssertFailed,
+    RequestTooLarge {
+        size: Option<usize>,
+        limit: usize,
+    },
+    IdempotencyKeyInProgress,
+    IdempotencyKeyMismatch,
     Other {
         details: &'x str,
         reason: Option<&'x str>,
@@ -85,10 +114,63 @@ impl ManagementApi for Server {
         access_token: Arc<AccessToken>,
         session: &HttpSessionData,
     ) -> trc::Result<HttpResponse> {
-        let body = fetch_body(req, 1024 * 1024, session.session_id).await;
-        let path = req.uri().path().split('/').skip(2).collect::<Vec<_>>();
+        // Read the request body, rejecting it early when it exceeds the route limit
+        let route = req
+            .uri()
+            .path()
+            .split('/')
+            .nth(2)
+            .unwrap_or_default()
+            .to_string();
+        let max_size = manage_max_body_size(self, &route);
+        let content_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.parse::<usize>().ok());
+        let body = if content_length.is_none_or(|size| size <= max_size) {
+            fetch_body(req, max_size, session.session_id).await
+        } else {
+            None
+        };
+        if body.is_none() {
+            trc::event!(
+                Limit(trc::LimitEvent::SizeRequest),
+                SpanId = session.session_id,
+                Id = route,
+                Size = content_length,
+                Limit = max_size,
+            );
 
-        match path.first().copied().unwrap_or_default() {
+            return Ok(ManagementApiError::RequestTooLarge {
+                size: content_length,
+                limit: max_size,
+            }
+            .into_http_response());
+        }
+
+        // Replay the stored response when a retried request reuses its idempotency key
+        let idempotency_key = match self
+            .idempotency_begin(
+                req,
+                access_token.primary_id(),
+                body.as_deref().unwrap_or_default(),
+            )
+            .await?
+        {
+            Some(IdempotencyState::New(key)) => Some(key),
+            Some(IdempotencyState::Replay(response)) => return Ok(response),
+            Some(IdempotencyState::InProgress) => {
+                return Ok(ManagementApiError::IdempotencyKeyInProgress.into_http_response());
+            }
+            Some(IdempotencyState::Mismatch) => {
+                return Ok(ManagementApiError::IdempotencyKeyMismatch.into_http_response());
+            }
+            None => None,
+        };
+
+        let path = req.uri().path().split('/').skip(2).collect::<Vec<_>>();
+        let result = match path.first().copied().unwrap_or_default() {
             "queue" => self.handle_manage_queue(req, path, &access_token).await,
             "settings" => {
                 self.handle_manage_settings(req, path, body, &access_token)
@@ -99,6 +181,7 @@ impl ManagementApi for Server {
                 self.handle_manage_principal(req, path, body, &access_token)
                     .await
             }
+            "tenant" => self.handle_manage_tenant(req, path, &access_token).await,
             "dns" => self.handle_manage_dns(req, path, &access_token).await,
             "store" => {
                 self.handle_manage_store(req, path, body, session, &access_token)
@@ -113,16 +196,18 @@ impl ManagementApi for Server {
             "logs" if req.method() == Method::GET => {
                 self.handle_view_logs(req, &access_token).await
             }
+            "trace" if req.method() == Method::GET => {
+                self.handle_view_trace(path, &access_token).await
+            }
+            "sieve" => {
+                self.handle_manage_sieve(req, path, body, &access_token)
+                    .await
+            }
             "spam-filter" => {
                 self.handle_manage_spam(req, path, body, session, &access_token)
                     .await
             }
-            "restart" if req.method() == Method::GET => {
-                // Validate the access token
-                access_token.assert_has_permission(Permission::Restart)?;
-
-                Err(manage::unsupported("Restart is not yet supported"))
-            }
+            "restart" => self.handle_manage_restart(req, path, &access_token).await,
             "oauth" => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::AuthenticateOauth)?;
@@ -154,8 +239,65 @@ impl ManagementApi for Server {
 
                     self.handle_account_auth_post(req, access_token, body).await
                 }
+                ("vacation", &Method::GET) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::JmapVacationResponseGet)?;
+
+                    self.handle_vacation_get(access_token).await
+                }
+                ("vacation", &Method::POST) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::JmapVacationResponseSet)?;
+
+                    self.handle_vacation_post(access_token, body).await
+                }
+                ("auth-report", &Method::GET) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::JmapEmailGet)?;
+
+                    let email_id = path.get(2).copied().unwrap_or_default();
+                    self.handle_auth_report_get(access_token, email_id).await
+                }
+                ("sender-lists", &Method::GET) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageSenderLists)?;
+
+                    self.handle_sender_lists_get(access_token).await
+                }
+                ("sender-lists", &Method::POST) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageSenderLists)?;
+
+                    self.handle_sender_lists_post(access_token, body).await
+                }
+                ("fetch", &Method::GET) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageExternalAccounts)?;
+
+                    self.handle_external_accounts_get(access_token).await
+                }
+                ("fetch", &Method::POST) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageExternalAccounts)?;
+
+                    self.handle_external_accounts_post(access_token, body).await
+                }
+                ("recall", &Method::POST) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::JmapEmailRecall)?;
+
+                    let email_id = path.get(2).copied().unwrap_or_default();
+                    self.handle_email_recall_post(access_token, email_id).await
+                }
                 _ => Err(trc::ResourceEvent::NotFound.into_err()),
             },
+            "submit" => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::EmailSend)?;
+
+                self.handle_submit_message(req, path, access_token, body, session)
+                    .await
+            }
             "troubleshoot" => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::Troubleshoot)?;
@@ -163,11 +305,70 @@ impl ManagementApi for Server {
                 self.handle_troubleshoot_api_request(req, path, &access_token, body)
                     .await
             }
+            "ediscovery" => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::EDiscovery)?;
+
+                self.handle_ediscovery_request(req, path, &access_token, body)
+                    .await
+            }
+            "smime" => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SmimeManage)?;
+
+                self.handle_manage_smime(req, path, body).await
+            }
+            "sessions" => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SessionManage)?;
+
+                self.handle_manage_sessions(req, path, &access_token).await
+            }
+            "ip-list" => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::IpListManage)?;
+
+                self.handle_manage_ip_list(req, path, &access_token, body)
+                    .await
+            }
+            "bootstrap" => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsUpdate)?;
+
+                self.handle_manage_bootstrap(req, path, &access_token).await
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        };
+
+        if let Some(idempotency_key) = idempotency_key {
+            if let Err(err) = self
+                .idempotency_complete(idempotency_key, result.as_ref().ok())
+                .await
+            {
+                trc::error!(err.span_id(session.session_id));
+            }
         }
+
+        result
     }
 }
 
+fn manage_max_body_size(server: &Server, route: &str) -> usize {
+    server
+        .core
+        .jmap
+        .manage_max_body_size
+        .get(route)
+        .copied()
+        .unwrap_or_else(|| match route {
+            // Message submissions are allowed to exceed the message size limit
+            "submit" => server.core.jmap.mail_max_size * 2,
+            "spam-filter" | "troubleshoot" => server.core.jmap.mail_max_size,
+            "principal" => 10 * 1024 * 1024,
+            _ => 1024 * 1024,
+        })
+}
+
 pub fn decode_path_element(item: &str) -> Cow<'_, str> {
     // Bit hackish but avoids an extra dependency
     form_urlencoded::parse(item.as_bytes())
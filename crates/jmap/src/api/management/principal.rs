@@ -4,11 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
-use common::{auth::AccessToken, Server, KV_BAYES_MODEL_USER};
+use common::{auth::AccessToken, Server, KV_AUTORESPONDER_SUPPRESSED, KV_BAYES_MODEL_USER};
 use directory::{
     backend::internal::{
+        list::ManageMailingList,
         lookup::DirectoryStore,
         manage::{self, not_found, ChangedPrincipals, ManageDirectory, UpdatePrincipal},
         PrincipalAction, PrincipalField, PrincipalUpdate, PrincipalValue, SpecialSecrets,
@@ -16,12 +17,38 @@ use directory::{
     DirectoryInner, Permission, Principal, QueryBy, Type,
 };
 
-use hyper::{header, Method};
+use email::mailbox::{MailboxFnc, SCHEMA};
+use hyper::{header, Method, StatusCode};
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{
+        acl::Acl,
+        collection::Collection,
+        id::Id,
+        property::Property,
+        state::StateChange,
+        type_state::DataType,
+        value::{AclGrant, Value},
+    },
+};
+use mail_parser::DateTime;
 use serde_json::json;
+use smtp::{inbound::list::MailingListManager, queue};
+use store::{
+    ahash::{AHashMap, AHashSet},
+    dispatch::lookup::KeyValue,
+    query::acl::AclQuery,
+    write::{assert::HashedValue, BatchBuilder, QueueClass, ValueClass},
+    Deserialize, IterateParams, ValueKey,
+};
 use trc::AddContext;
-use utils::url_params::UrlParams;
+use utils::{map::bitmap::Bitmap, url_params::UrlParams};
 
-use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    auth::acl::AclMethods,
+    email::archive::{EmailArchive, MessageArchive},
+};
 
 use super::decode_path_element;
 use std::future::Future;
@@ -45,6 +72,26 @@ pub struct AccountAuthResponse {
     pub app_passwords: Vec<String>,
 }
 
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionReport {
+    pub messages: u64,
+    pub mailboxes: u64,
+    pub identities: u64,
+    pub sieve_scripts: u64,
+    pub blobs_size: u64,
+    pub shares: usize,
+    pub shared_by_accounts: usize,
+    pub queued_messages: usize,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct PublicFolderAcl {
+    pub mailbox: String,
+    #[serde(default)]
+    pub acl: BTreeMap<String, Vec<Acl>>,
+}
+
 pub trait PrincipalManager: Sync + Send {
     fn handle_manage_principal(
         &self,
@@ -66,6 +113,23 @@ pub trait PrincipalManager: Sync + Send {
         body: Option<Vec<u8>>,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
 
+    fn principal_deletion_report(
+        &self,
+        account_id: u32,
+        typ: Type,
+    ) -> impl Future<Output = trc::Result<DeletionReport>> + Send;
+
+    fn public_folder_acl_get(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<PublicFolderAcl>>> + Send;
+
+    fn public_folder_acl_set(
+        &self,
+        account_id: u32,
+        request: PublicFolderAcl,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
     fn assert_supported_directory(&self) -> trc::Result<()>;
 }
 
@@ -99,6 +163,9 @@ impl PrincipalManager for Server {
                     Type::OauthClient => Permission::OauthClientCreate,
                     Type::Resource | Type::Location | Type::Other => Permission::PrincipalCreate,
                 })?;
+                if principal.has_field(PrincipalField::LegalHold) {
+                    access_token.assert_has_permission(Permission::LegalHoldManage)?;
+                }
 
                 // Make sure the current directory supports updates
                 if matches!(principal.typ(), Type::Individual) {
@@ -274,17 +341,7 @@ impl PrincipalManager for Server {
                 }
 
                 // Validate the access token
-                access_token.assert_has_permission(match typ {
-                    Type::Individual => Permission::IndividualDelete,
-                    Type::Group => Permission::GroupDelete,
-                    Type::List => Permission::MailingListDelete,
-                    Type::Domain => Permission::DomainDelete,
-                    Type::Tenant => Permission::TenantDelete,
-                    Type::Role => Permission::RoleDelete,
-                    Type::ApiKey => Permission::ApiKeyDelete,
-                    Type::OauthClient => Permission::OauthClientDelete,
-                    Type::Resource | Type::Location | Type::Other => Permission::PrincipalDelete,
-                })?;
+                access_token.assert_has_permission(delete_permission(typ))?;
 
                 let mut tenant = access_token.tenant.map(|t| t.id);
 
@@ -345,6 +402,20 @@ impl PrincipalManager for Server {
                                         );
                                     }
                                 }
+
+                                // Delete suppressed auto-response counter
+                                if let Err(err) = server
+                                    .in_memory_store()
+                                    .counter_delete(KeyValue::<()>::build_key(
+                                        KV_AUTORESPONDER_SUPPRESSED,
+                                        principal.id().to_be_bytes(),
+                                    ))
+                                    .await
+                                {
+                                    trc::error!(
+                                        err.details("Failed to delete auto-responder counter")
+                                    );
+                                }
                             }
                         }
                     });
@@ -355,6 +426,189 @@ impl PrincipalManager for Server {
                 }))
                 .into_http_response())
             }
+            (Some(name), &Method::GET) if path.get(2).is_some_and(|p| *p == "export") => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::LegalHoldManage)?;
+
+                // Export all messages, including deleted ones retained by a legal hold
+                let name = decode_path_element(name);
+                let account_id = self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_info(name.as_ref())
+                    .await?
+                    .filter(|p| {
+                        matches!(p.typ, Type::Individual | Type::Group)
+                            && p.has_tenant_access(access_token.tenant.map(|t| t.id))
+                    })
+                    .map(|p| p.id)
+                    .ok_or_else(|| not_found(name.to_string()))?;
+                let document_ids = self
+                    .get_document_ids(account_id, Collection::Email)
+                    .await
+                    .caused_by(trc::location!())?
+                    .unwrap_or_default();
+
+                let mut archive = MessageArchive::new();
+                self.email_archive(&mut archive, account_id, name.as_ref(), document_ids)
+                    .await?;
+
+                trc::event!(
+                    Security(trc::SecurityEvent::DataExport),
+                    AccountId = access_token.primary_id(),
+                    AccountName = access_token.name.clone(),
+                    Details = name.to_string(),
+                    Total = archive.len(),
+                );
+
+                Ok(
+                    HttpResponse::new_binary(StatusCode::OK, "application/zip", archive.finish()?)
+                        .with_content_disposition(format!(
+                            "attachment; filename=\"{}.zip\"",
+                            name.replace('\"', "\\\"")
+                        )),
+                )
+            }
+            (Some(name), method) if path.get(2).is_some_and(|p| *p == "moderation") => {
+                // Review posts held for moderation
+                let name = decode_path_element(name);
+                let list = match self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_info(name.as_ref())
+                    .await?
+                    .filter(|p| {
+                        p.typ == Type::List
+                            && p.has_tenant_access(access_token.tenant.map(|t| t.id))
+                    }) {
+                    Some(principal) => self
+                        .core
+                        .storage
+                        .data
+                        .mailing_list_by_id(principal.id)
+                        .await?,
+                    None => None,
+                }
+                .ok_or_else(|| not_found(name.to_string()))?;
+
+                match (path.get(3), method) {
+                    (None, &Method::GET) => {
+                        // Validate the access token
+                        access_token.assert_has_permission(Permission::MailingListGet)?;
+
+                        let posts = self
+                            .held_list_posts(list.id)
+                            .await?
+                            .into_iter()
+                            .map(|post| {
+                                json!({
+                                    "id": post.id,
+                                    "from": post.return_path,
+                                    "subject": post.subject,
+                                    "size": post.size,
+                                    "received": DateTime::from_timestamp(post.received as i64).to_rfc3339(),
+                                    "expires": DateTime::from_timestamp(post.expires as i64).to_rfc3339(),
+                                })
+                            })
+                            .collect::<Vec<_>>();
+
+                        Ok(JsonResponse::new(json!({
+                            "data": {
+                                "items": posts,
+                                "total": posts.len(),
+                            },
+                        }))
+                        .into_http_response())
+                    }
+                    (Some(post_id), &Method::POST | &Method::DELETE) => {
+                        // Validate the access token
+                        access_token.assert_has_permission(Permission::MailingListUpdate)?;
+
+                        let post_id = post_id
+                            .parse::<u64>()
+                            .map_err(|_| trc::ResourceEvent::NotFound.into_err())?;
+                        if self
+                            .release_list_post(&list, post_id, method == Method::POST)
+                            .await?
+                        {
+                            Ok(JsonResponse::new(json!({
+                                "data": (),
+                            }))
+                            .into_http_response())
+                        } else {
+                            Err(not_found(post_id))
+                        }
+                    }
+                    _ => Err(trc::ResourceEvent::NotFound.into_err()),
+                }
+            }
+            (Some(name), method) if path.get(2).is_some_and(|p| *p == "acl") => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::PublicFolderManage)?;
+
+                // Only the public folders principal can be managed
+                let name = decode_path_element(name);
+                let account_id = self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_info(name.as_ref())
+                    .await?
+                    .filter(|p| {
+                        p.has_tenant_access(access_token.tenant.map(|t| t.id))
+                            && self.core.jmap.public_account.as_ref().is_some_and(
+                                |public_account| name.eq_ignore_ascii_case(public_account),
+                            )
+                    })
+                    .map(|p| p.id)
+                    .ok_or_else(|| not_found(name.to_string()))?;
+
+                match *method {
+                    Method::GET => Ok(JsonResponse::new(json!({
+                        "data": self.public_folder_acl_get(account_id).await?,
+                    }))
+                    .into_http_response()),
+                    Method::POST => {
+                        let request = serde_json::from_slice::<PublicFolderAcl>(
+                            body.as_deref().unwrap_or_default(),
+                        )
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+                        self.public_folder_acl_set(account_id, request).await?;
+
+                        Ok(JsonResponse::new(json!({
+                            "data": (),
+                        }))
+                        .into_http_response())
+                    }
+                    _ => Err(trc::ResourceEvent::NotFound.into_err()),
+                }
+            }
+            (Some(name), &Method::GET) if path.get(2).is_some_and(|p| *p == "deletion-preview") => {
+                // Report what deleting a principal would remove
+                let name = decode_path_element(name);
+                let (account_id, typ) = self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_info(name.as_ref())
+                    .await?
+                    .filter(|p| p.has_tenant_access(access_token.tenant.map(|t| t.id)))
+                    .map(|p| (p.id, p.typ))
+                    .ok_or_else(|| not_found(name.to_string()))?;
+
+                // Validate the access token
+                access_token.assert_has_permission(delete_permission(typ))?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.principal_deletion_report(account_id, typ).await?,
+                }))
+                .into_http_response())
+            }
             (Some(name), method) => {
                 // Fetch, update or delete principal
                 let name = decode_path_element(name);
@@ -408,19 +662,36 @@ impl PrincipalManager for Server {
                     }
                     Method::DELETE => {
                         // Validate the access token
-                        access_token.assert_has_permission(match typ {
-                            Type::Individual => Permission::IndividualDelete,
-                            Type::Group => Permission::GroupDelete,
-                            Type::List => Permission::MailingListDelete,
-                            Type::Domain => Permission::DomainDelete,
-                            Type::Tenant => Permission::TenantDelete,
-                            Type::Role => Permission::RoleDelete,
-                            Type::ApiKey => Permission::ApiKeyDelete,
-                            Type::OauthClient => Permission::OauthClientDelete,
-                            Type::Resource | Type::Location | Type::Other => {
-                                Permission::PrincipalDelete
-                            }
-                        })?;
+                        access_token.assert_has_permission(delete_permission(typ))?;
+
+                        // Archive the account by disabling authentication, keeping its data
+                        let params = UrlParams::new(req.uri().query());
+                        if params.get("archive") == Some("true") {
+                            let changed_principals = self
+                                .core
+                                .storage
+                                .data
+                                .update_principal(
+                                    UpdatePrincipal::by_id(account_id)
+                                        .with_updates(vec![PrincipalUpdate::add_item(
+                                            PrincipalField::DisabledPermissions,
+                                            PrincipalValue::String(
+                                                Permission::Authenticate.name().to_string(),
+                                            ),
+                                        )])
+                                        .with_tenant(access_token.tenant.map(|t| t.id))
+                                        .with_allowed_permissions(&access_token.permissions),
+                                )
+                                .await?;
+
+                            // Increment revision
+                            self.increment_token_revision(changed_principals).await;
+
+                            return Ok(JsonResponse::new(json!({
+                                "data": (),
+                            }))
+                            .into_http_response());
+                        }
 
                         // Delete account
                         let changed_principals = self
@@ -450,6 +721,18 @@ impl PrincipalManager for Server {
                                     trc::error!(err.details("Failed to delete user bayes model"));
                                 }
                             }
+
+                            // Delete suppressed auto-response counter
+                            if let Err(err) = self
+                                .in_memory_store()
+                                .counter_delete(KeyValue::<()>::build_key(
+                                    KV_AUTORESPONDER_SUPPRESSED,
+                                    account_id.to_be_bytes(),
+                                ))
+                                .await
+                            {
+                                trc::error!(err.details("Failed to delete auto-responder counter"));
+                            }
                         }
 
                         // Increment revision
@@ -502,7 +785,21 @@ impl PrincipalManager for Server {
                                 | PrincipalField::Members
                                 | PrincipalField::Lists
                                 | PrincipalField::Urls
-                                | PrincipalField::ExternalMembers => (),
+                                | PrincipalField::ExternalMembers
+                                | PrincipalField::ListPostPolicy
+                                | PrincipalField::ListModerators
+                                | PrincipalField::ListArchive
+                                | PrincipalField::ListSubscription
+                                | PrincipalField::ForwardTo
+                                | PrincipalField::ForwardKeepCopy
+                                | PrincipalField::BookingPolicy
+                                | PrincipalField::BookingManagers
+                                | PrincipalField::BookingMaxDuration
+                                | PrincipalField::Locale => (),
+                                PrincipalField::LegalHold => {
+                                    access_token
+                                        .assert_has_permission(Permission::LegalHoldManage)?;
+                                }
                                 PrincipalField::Tenant => {
                                     // Tenants are not allowed to change their tenantId
                                     if access_token.tenant.is_some() {
@@ -761,6 +1058,253 @@ impl PrincipalManager for Server {
         .into_http_response())
     }
 
+    async fn principal_deletion_report(
+        &self,
+        account_id: u32,
+        typ: Type,
+    ) -> trc::Result<DeletionReport> {
+        let mut report = DeletionReport::default();
+        if !matches!(typ, Type::Individual | Type::Group) {
+            return Ok(report);
+        }
+
+        // Count stored objects
+        for (count, collection) in [
+            (&mut report.messages, Collection::Email),
+            (&mut report.mailboxes, Collection::Mailbox),
+            (&mut report.sieve_scripts, Collection::SieveScript),
+            (&mut report.identities, Collection::Identity),
+        ] {
+            *count = self
+                .get_document_ids(account_id, collection)
+                .await?
+                .map_or(0, |ids| ids.len());
+        }
+        report.blobs_size = self.get_used_quota(account_id).await?.max(0) as u64;
+
+        // Shares granted to this account by other accounts
+        let mut shared_by = AHashSet::new();
+        for acl_item in self
+            .store()
+            .acl_query(AclQuery::HasAccess {
+                grant_account_id: account_id,
+            })
+            .await
+            .caused_by(trc::location!())?
+        {
+            if acl_item.to_account_id != account_id {
+                report.shares += 1;
+                shared_by.insert(acl_item.to_account_id);
+            }
+        }
+        report.shared_by_accounts = shared_by.len();
+
+        // Messages queued for delivery that were sent by this account
+        let emails = self
+            .core
+            .storage
+            .data
+            .query(QueryBy::Id(account_id), true)
+            .await?
+            .and_then(|mut principal| principal.take_str_array(PrincipalField::Emails))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|email| email.to_lowercase())
+            .collect::<AHashSet<_>>();
+        if !emails.is_empty() {
+            self.core
+                .storage
+                .data
+                .iterate(
+                    IterateParams::new(
+                        ValueKey::from(ValueClass::Queue(QueueClass::Message(0))),
+                        ValueKey::from(ValueClass::Queue(QueueClass::Message(u64::MAX))),
+                    )
+                    .ascending(),
+                    |key, value| {
+                        let message = queue::format::SpooledMessage::deserialize(value)
+                            .add_context(|ctx| ctx.ctx(trc::Key::Key, key))?
+                            .inner;
+                        if emails.contains(&message.return_path_lcase) {
+                            report.queued_messages += 1;
+                        }
+                        Ok(true)
+                    },
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(report)
+    }
+
+    async fn public_folder_acl_get(&self, account_id: u32) -> trc::Result<Vec<PublicFolderAcl>> {
+        let mut mailboxes = AHashMap::new();
+        for mailbox_id in self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default()
+        {
+            if let Some(mut mailbox) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::Mailbox,
+                    mailbox_id,
+                    Property::Value,
+                )
+                .await
+                .caused_by(trc::location!())?
+            {
+                let name = mailbox
+                    .properties
+                    .remove(&Property::Name)
+                    .and_then(|name| name.try_unwrap_string())
+                    .unwrap_or_default();
+                let parent_id = mailbox
+                    .properties
+                    .get(&Property::ParentId)
+                    .and_then(|id| id.as_id())
+                    .map(|id| id.document_id())
+                    .unwrap_or_default();
+                let acl = match mailbox.properties.remove(&Property::Acl) {
+                    Some(Value::Acl(acl)) => acl,
+                    _ => Vec::new(),
+                };
+                mailboxes.insert(mailbox_id, (name, parent_id, acl));
+            }
+        }
+
+        let mut result = Vec::with_capacity(mailboxes.len());
+        for (name, parent_id, acl) in mailboxes.values() {
+            // Build the full mailbox path
+            let mut path = vec![name.as_str()];
+            let mut parent_id = *parent_id;
+            while parent_id > 0 && path.len() <= self.core.jmap.mailbox_max_depth {
+                if let Some((name, next_parent_id, _)) = mailboxes.get(&(parent_id - 1)) {
+                    path.push(name.as_str());
+                    parent_id = *next_parent_id;
+                } else {
+                    break;
+                }
+            }
+            path.reverse();
+
+            let mut folder = PublicFolderAcl {
+                mailbox: path.join("/"),
+                acl: BTreeMap::new(),
+            };
+            for grant in acl {
+                let grantee = self
+                    .core
+                    .storage
+                    .directory
+                    .query(QueryBy::Id(grant.account_id), false)
+                    .await
+                    .caused_by(trc::location!())?
+                    .and_then(|mut p| p.take_str(PrincipalField::Name))
+                    .unwrap_or_else(|| Id::from(grant.account_id).to_string());
+                folder
+                    .acl
+                    .insert(grantee, grant.grants.into_iter().collect());
+            }
+            result.push(folder);
+        }
+        result.sort_unstable_by(|a, b| a.mailbox.cmp(&b.mailbox));
+
+        Ok(result)
+    }
+
+    async fn public_folder_acl_set(
+        &self,
+        account_id: u32,
+        request: PublicFolderAcl,
+    ) -> trc::Result<()> {
+        // Obtain the mailbox, creating it if missing
+        let mailbox_id = match self
+            .mailbox_get_by_name(account_id, &request.mailbox)
+            .await
+            .caused_by(trc::location!())?
+        {
+            Some(mailbox_id) => mailbox_id,
+            None => self
+                .mailbox_create_path(account_id, &request.mailbox)
+                .await
+                .caused_by(trc::location!())?
+                .map(|(mailbox_id, _)| mailbox_id)
+                .ok_or_else(|| {
+                    trc::ManageEvent::Error
+                        .into_err()
+                        .details("Invalid mailbox name")
+                        .ctx(trc::Key::Path, request.mailbox.clone())
+                })?,
+        };
+        let current = self
+            .get_property::<HashedValue<Object<Value>>>(
+                account_id,
+                Collection::Mailbox,
+                mailbox_id,
+                Property::Value,
+            )
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| not_found(request.mailbox.clone()))?;
+
+        // Replace the grants of each listed principal, empty rights revoke access
+        let mut acl = match current.inner.properties.get(&Property::Acl) {
+            Some(Value::Acl(acl)) => acl.clone(),
+            _ => Vec::new(),
+        };
+        for (grantee, rights) in request.acl {
+            let grantee_id = self
+                .core
+                .storage
+                .directory
+                .query(QueryBy::Name(&grantee), false)
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| not_found(grantee.clone()))?
+                .id();
+            let grants = Bitmap::from_iter(rights);
+            acl.retain(|item| item.account_id != grantee_id);
+            if !grants.is_empty() {
+                acl.push(AclGrant {
+                    account_id: grantee_id,
+                    grants,
+                });
+            }
+        }
+        let changes = Object::with_capacity(1).with_property(Property::Acl, Value::Acl(acl));
+        let current = Some(current);
+        self.refresh_acls(&changes, &current).await;
+
+        // Write changes
+        let mut log = self.begin_changes(account_id)?;
+        log.log_update(Collection::Mailbox, mailbox_id);
+        let change_id = log.change_id;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox)
+            .update_document(mailbox_id)
+            .custom(
+                ObjectIndexBuilder::new(SCHEMA)
+                    .with_current_opt(current)
+                    .with_changes(changes),
+            )
+            .custom(log);
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+        self.broadcast_state_change(
+            StateChange::new(account_id).with_change(DataType::Mailbox, change_id),
+        )
+        .await;
+
+        Ok(())
+    }
+
     fn assert_supported_directory(&self) -> trc::Result<()> {
         let class = match &self.core.storage.directory.store {
             DirectoryInner::Internal(_) => return Ok(()),
@@ -782,3 +1326,17 @@ impl PrincipalManager for Server {
         )))
     }
 }
+
+fn delete_permission(typ: Type) -> Permission {
+    match typ {
+        Type::Individual => Permission::IndividualDelete,
+        Type::Group => Permission::GroupDelete,
+        Type::List => Permission::MailingListDelete,
+        Type::Domain => Permission::DomainDelete,
+        Type::Tenant => Permission::TenantDelete,
+        Type::Role => Permission::RoleDelete,
+        Type::ApiKey => Permission::ApiKeyDelete,
+        Type::OauthClient => Permission::OauthClientDelete,
+        Type::Resource | Type::Location | Type::Other => Permission::PrincipalDelete,
+    }
+}
@@ -4,10 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{future::Future, sync::atomic::Ordering};
+use std::{collections::BTreeMap, future::Future, sync::atomic::Ordering};
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-use common::{auth::AccessToken, ipc::QueueEvent, Server};
+use common::{
+    auth::AccessToken, config::smtp::report::AggregateFrequency, ipc::QueueEvent, Server,
+};
 use directory::{
     backend::internal::{manage::ManageDirectory, PrincipalField},
     Permission, Type,
@@ -22,15 +24,23 @@ use mail_parser::DateTime;
 use serde::{Deserializer, Serializer};
 use serde_json::json;
 use smtp::{
-    queue::{self, spool::SmtpSpool, ErrorDetails, HostResponse, QueueId, Status},
-    reporting::{dmarc::DmarcReporting, tls::TlsReporting},
+    outbound::warmup::IpWarmup,
+    queue::{
+        self, format::SpooledMessage, moderation::OutboundModeration, shaping::QueueRateShaping,
+        spool::SmtpSpool, QueueId, Status,
+    },
+    reporting::{
+        dmarc::DmarcReporting,
+        scheduler::{AggregateReportType, ReportSchedule},
+        tls::TlsReporting,
+    },
 };
 use store::{
-    write::{key::DeserializeBigEndian, now, Bincode, QueueClass, ReportEvent, ValueClass},
+    write::{key::DeserializeBigEndian, now, QueueClass, ReportEvent, ValueClass},
     Deserialize, IterateParams, ValueKey,
 };
 use trc::AddContext;
-use utils::url_params::UrlParams;
+use utils::{config::utils::ParseValue, url_params::UrlParams};
 
 use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
 
@@ -109,6 +119,24 @@ pub enum Report {
     },
 }
 
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainReportSchedule {
+    pub domain: String,
+    pub dmarc: ScheduledReports,
+    pub tls: ScheduledReports,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledReports {
+    pub pending: usize,
+    #[serde(deserialize_with = "deserialize_maybe_datetime")]
+    #[serde(serialize_with = "serialize_maybe_datetime")]
+    pub next_due: Option<DateTime>,
+    pub interval: Option<AggregateFrequency>,
+}
+
 pub trait QueueManagement: Sync + Send {
     fn handle_manage_queue(
         &self,
@@ -199,22 +227,8 @@ impl QueueManagement for Server {
                         for id in result.ids {
                             if let Some(mut message) = server.read_message(id).await {
                                 let prev_event = message.next_event().unwrap_or_default();
-                                let mut has_changes = false;
-
-                                for domain in &mut message.domains {
-                                    if matches!(
-                                        domain.status,
-                                        Status::Scheduled | Status::TemporaryFailure(_)
-                                    ) {
-                                        domain.retry.due = time;
-                                        if domain.expires > time {
-                                            domain.expires = time + 10;
-                                        }
-                                        has_changes = true;
-                                    }
-                                }
 
-                                if has_changes {
+                                if message.reschedule(time, None) {
                                     let next_event = message.next_event().unwrap_or_default();
                                     message
                                         .save_changes(&server, prev_event.into(), next_event.into())
@@ -252,23 +266,7 @@ impl QueueManagement for Server {
                     })
                 {
                     let prev_event = message.next_event().unwrap_or_default();
-                    let mut found = false;
-
-                    for domain in &mut message.domains {
-                        if matches!(
-                            domain.status,
-                            Status::Scheduled | Status::TemporaryFailure(_)
-                        ) && item
-                            .as_ref()
-                            .is_none_or( |item| domain.domain.contains(item))
-                        {
-                            domain.retry.due = time;
-                            if domain.expires > time {
-                                domain.expires = time + 10;
-                            }
-                            found = true;
-                        }
-                    }
+                    let found = message.reschedule(time, item);
 
                     if found {
                         let next_event = message.next_event().unwrap_or_default();
@@ -343,59 +341,14 @@ impl QueueManagement for Server {
                             .is_none_or( |domains| message.has_domain(domains))
                     })
                 {
-                    let mut found = false;
+                    let found;
                     let prev_event = message.next_event().unwrap_or_default();
 
                     if let Some(item) = params.get("filter") {
-                        // Cancel delivery for all recipients that match
-                        for rcpt in &mut message.recipients {
-                            if rcpt.address_lcase.contains(item) {
-                                rcpt.status = Status::PermanentFailure(HostResponse {
-                                    hostname: ErrorDetails::default(),
-                                    response: smtp_proto::Response {
-                                        code: 0,
-                                        esc: [0, 0, 0],
-                                        message: "Delivery canceled.".to_string(),
-                                    },
-                                });
-                                found = true;
-                            }
-                        }
+                        found = message.cancel_recipients(item);
                         if found {
-                            // Mark as completed domains without any pending deliveries
-                            for (domain_idx, domain) in message.domains.iter_mut().enumerate() {
-                                if matches!(
-                                    domain.status,
-                                    Status::TemporaryFailure(_) | Status::Scheduled
-                                ) {
-                                    let mut total_rcpt = 0;
-                                    let mut total_completed = 0;
-
-                                    for rcpt in &message.recipients {
-                                        if rcpt.domain_idx == domain_idx {
-                                            total_rcpt += 1;
-                                            if matches!(
-                                                rcpt.status,
-                                                Status::PermanentFailure(_) | Status::Completed(_)
-                                            ) {
-                                                total_completed += 1;
-                                            }
-                                        }
-                                    }
-
-                                    if total_rcpt == total_completed {
-                                        domain.status = Status::Completed(());
-                                    }
-                                }
-                            }
-
                             // Delete message if there are no pending deliveries
-                            if message.domains.iter().any(|domain| {
-                                matches!(
-                                    domain.status,
-                                    Status::TemporaryFailure(_) | Status::Scheduled
-                                )
-                            }) {
+                            if message.has_pending_domains() {
                                 let next_event = message.next_event().unwrap_or_default();
                                 message
                                     .save_changes(self, next_event.into(), prev_event.into())
@@ -538,6 +491,237 @@ impl QueueManagement for Server {
                     Err(trc::ResourceEvent::NotFound.into_err())
                 }
             }
+            ("schedule", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::OutgoingReportList)?;
+
+                let result = fetch_queued_reports(self, &params, &tenant_domains).await?;
+                let mut schedules: BTreeMap<String, DomainReportSchedule> = BTreeMap::new();
+                for id in result.ids {
+                    let (event, report_type) = match id {
+                        QueueClass::DmarcReportHeader(event) => (event, AggregateReportType::Dmarc),
+                        QueueClass::TlsReportHeader(event) => (event, AggregateReportType::Tls),
+                        _ => continue,
+                    };
+                    let schedule = schedules.entry(event.domain.clone()).or_default();
+                    let reports = match report_type {
+                        AggregateReportType::Dmarc => &mut schedule.dmarc,
+                        AggregateReportType::Tls => &mut schedule.tls,
+                    };
+                    reports.pending += 1;
+                    if reports
+                        .next_due
+                        .as_ref()
+                        .is_none_or(|due| due.to_timestamp() > event.due as i64)
+                    {
+                        reports.next_due = DateTime::from_timestamp(event.due as i64).into();
+                    }
+                }
+
+                let mut items = Vec::with_capacity(schedules.len());
+                for (domain, mut schedule) in schedules {
+                    schedule.dmarc.interval = self
+                        .report_interval(AggregateReportType::Dmarc, &domain)
+                        .await;
+                    schedule.tls.interval = self
+                        .report_interval(AggregateReportType::Tls, &domain)
+                        .await;
+                    schedule.domain = domain;
+                    items.push(schedule);
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "items": items,
+                            "total": items.len(),
+                        },
+                }))
+                .into_http_response())
+            }
+            ("schedule", Some(domain), &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let domain = domain.to_lowercase();
+                if tenant_domains
+                    .as_ref()
+                    .is_some_and(|domains| !domains.contains(&domain))
+                {
+                    return Err(trc::ResourceEvent::NotFound.into_err());
+                }
+
+                // Send all pending reports for the domain, regardless of their due date
+                let ids = fetch_queued_reports(self, &params, &tenant_domains)
+                    .await?
+                    .ids
+                    .into_iter()
+                    .filter(|id| match id {
+                        QueueClass::DmarcReportHeader(event)
+                        | QueueClass::TlsReportHeader(event) => event.domain == domain,
+                        _ => false,
+                    })
+                    .collect::<Vec<_>>();
+                let total = ids.len();
+                if total > 0 {
+                    self.send_queued_reports(ids).await;
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": total,
+                }))
+                .into_http_response())
+            }
+            ("schedule", Some(domain), &Method::PATCH) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let domain = domain.to_lowercase();
+                if tenant_domains
+                    .as_ref()
+                    .is_some_and(|domains| !domains.contains(&domain))
+                {
+                    return Err(trc::ResourceEvent::NotFound.into_err());
+                }
+
+                // Parse intervals, "default" restores the configured interval
+                let mut intervals = Vec::with_capacity(2);
+                for (param, report_type) in [
+                    ("dmarc", AggregateReportType::Dmarc),
+                    ("tls", AggregateReportType::Tls),
+                ] {
+                    if let Some(value) = params.get(param) {
+                        let interval = if value == "default" {
+                            None
+                        } else {
+                            AggregateFrequency::parse_value(value)
+                                .map_err(|err| {
+                                    trc::ResourceEvent::BadParameters.into_err().reason(err)
+                                })?
+                                .into()
+                        };
+                        intervals.push((report_type, interval));
+                    }
+                }
+
+                let found = !intervals.is_empty();
+                for (report_type, interval) in intervals {
+                    self.set_report_interval(report_type, &domain, interval)
+                        .await?;
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": found,
+                }))
+                .into_http_response())
+            }
+            ("moderation", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueList)?;
+
+                let items = self
+                    .held_outbound_messages()
+                    .await?
+                    .into_iter()
+                    .filter(|held| {
+                        tenant_domains
+                            .as_ref()
+                            .is_none_or(|domains| held.message.has_domain(domains))
+                    })
+                    .map(|held| {
+                        let to = held
+                            .message
+                            .recipients
+                            .iter()
+                            .map(|rcpt| rcpt.address.as_str())
+                            .collect::<Vec<_>>();
+                        json!({
+                            "id": held.message.queue_id,
+                            "account": held.account,
+                            "from": held.message.return_path,
+                            "to": to,
+                            "subject": held.subject,
+                            "size": held.message.size,
+                            "received": DateTime::from_timestamp(held.received as i64).to_rfc3339(),
+                            "expires": DateTime::from_timestamp(held.expires as i64).to_rfc3339(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "total": items.len(),
+                            "items": items,
+                        },
+                }))
+                .into_http_response())
+            }
+            ("moderation", Some(queue_id), &Method::POST | &Method::DELETE) => {
+                // Validate the access token
+                let approve = req.method() == Method::POST;
+                access_token.assert_has_permission(if approve {
+                    Permission::MessageQueueUpdate
+                } else {
+                    Permission::MessageQueueDelete
+                })?;
+
+                let queue_id = queue_id
+                    .parse::<QueueId>()
+                    .map_err(|_| trc::ResourceEvent::NotFound.into_err())?;
+                if self.release_outbound_message(queue_id, approve).await? {
+                    Ok(JsonResponse::new(json!({
+                            "data": (),
+                    }))
+                    .into_http_response())
+                } else {
+                    Err(trc::ResourceEvent::NotFound.into_err())
+                }
+            }
+            ("warmup", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let mut ips = self
+                    .core
+                    .smtp
+                    .queue
+                    .warmup
+                    .ips
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>();
+                ips.sort_unstable();
+                let mut items = Vec::with_capacity(ips.len());
+                for ip in ips {
+                    if let Some(status) = self.warmup_status(ip).await? {
+                        items.push(status);
+                    }
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "items": items,
+                            "total": items.len(),
+                        },
+                }))
+                .into_http_response())
+            }
+            ("shaping", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let mut items = Vec::with_capacity(self.core.smtp.queue.shaping.len());
+                for shaper in &self.core.smtp.queue.shaping {
+                    items.push(self.shaping_status(shaper).await?);
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "items": items,
+                            "total": items.len(),
+                        },
+                }))
+                .into_http_response())
+            }
             ("status", None, &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::MessageQueueGet)?;
@@ -647,6 +831,7 @@ async fn fetch_queued_messages(
     let text = params.get("text");
     let from = params.get("from");
     let to = params.get("to");
+    let env_id = params.get("envid");
     let before = params
         .parse::<FutureTimestamp>("before")
         .map(|t| t.into_inner());
@@ -668,8 +853,12 @@ async fn fetch_queued_messages(
     };
     let from_key = ValueKey::from(ValueClass::Queue(QueueClass::Message(range_start)));
     let to_key = ValueKey::from(ValueClass::Queue(QueueClass::Message(range_end)));
-    let has_filters =
-        text.is_some() || from.is_some() || to.is_some() || before.is_some() || after.is_some();
+    let has_filters = text.is_some()
+        || from.is_some()
+        || to.is_some()
+        || env_id.is_some()
+        || before.is_some()
+        || after.is_some();
     let mut offset = page.saturating_sub(1) * limit;
     let mut total_returned = 0;
 
@@ -680,7 +869,7 @@ async fn fetch_queued_messages(
         .iterate(
             IterateParams::new(from_key, to_key).ascending(),
             |key, value| {
-                let message = Bincode::<queue::Message>::deserialize(value)
+                let message = SpooledMessage::deserialize(value)
                     .add_context(|ctx| ctx.ctx(trc::Key::Key, key))?
                     .inner;
                 let matches = tenant_domains
@@ -706,6 +895,8 @@ async fn fetch_queued_messages(
                                             .any(|r| r.address_lcase.contains(to))
                                     })
                             })
+                            && env_id
+                                .is_none_or( |env_id| message.env_id.as_deref() == Some(env_id))
                             && before
                                 .as_ref()
                                 .is_none_or( |before| message.next_delivery_event() < *before)
@@ -5,7 +5,7 @@
  */
 
 use common::{auth::AccessToken, ipc::HousekeeperEvent, Server};
-use directory::Permission;
+use directory::{backend::internal::manage, Permission};
 use hyper::Method;
 use serde_json::json;
 use std::future::Future;
@@ -30,6 +30,13 @@ pub trait ManageReload: Sync + Send {
         path: Vec<&str>,
         access_token: &AccessToken,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_manage_restart(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
 }
 
 impl ManageReload for Server {
@@ -156,4 +163,38 @@ impl ManageReload for Server {
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
+
+    async fn handle_manage_restart(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        // Validate the access token
+        access_token.assert_has_permission(Permission::Restart)?;
+
+        match (path.get(1).copied(), req.method()) {
+            (None, &Method::GET) => {
+                if cfg!(windows) {
+                    return Err(manage::unsupported(
+                        "Restart is not supported when running as a Windows service",
+                    ));
+                }
+
+                // The restart is carried out once sessions and deliveries are drained,
+                // requesting it again while in progress only reports its progress.
+                self.inner.data.restart.request();
+
+                Ok(JsonResponse::new(json!({
+                    "data": self.restart_progress(),
+                }))
+                .into_http_response())
+            }
+            (Some("status"), &Method::GET) => Ok(JsonResponse::new(json!({
+                "data": self.restart_progress(),
+            }))
+            .into_http_response()),
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
 }
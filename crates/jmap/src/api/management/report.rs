@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::future::Future;
+use std::{collections::BTreeMap, future::Future};
 
 use common::{auth::AccessToken, Server};
 use directory::{
@@ -14,8 +14,9 @@ use directory::{
 use hyper::Method;
 use mail_auth::report::{
     tlsrpt::{FailureDetails, Policy, TlsReport},
-    Feedback,
+    Feedback, FeedbackType,
 };
+use serde::Serialize;
 use serde_json::json;
 use smtp::reporting::analysis::IncomingReport;
 use store::{
@@ -96,7 +97,7 @@ impl ManageReports for Server {
                             Some(report)
                                 if tenant_domains
                                     .as_ref()
-                                    .is_none_or( |domains| report.inner.has_domain(domains)) =>
+                                    .is_none_or(|domains| report.inner.has_domain(domains)) =>
                             {
                                 Ok(JsonResponse::new(json!({
                                         "data": report.inner,
@@ -117,7 +118,7 @@ impl ManageReports for Server {
                             Some(report)
                                 if tenant_domains
                                     .as_ref()
-                                    .is_none_or( |domains| report.inner.has_domain(domains)) =>
+                                    .is_none_or(|domains| report.inner.has_domain(domains)) =>
                             {
                                 Ok(JsonResponse::new(json!({
                                         "data": report.inner,
@@ -138,7 +139,7 @@ impl ManageReports for Server {
                             Some(report)
                                 if tenant_domains
                                     .as_ref()
-                                    .is_none_or( |domains| report.inner.has_domain(domains)) =>
+                                    .is_none_or(|domains| report.inner.has_domain(domains)) =>
                             {
                                 Ok(JsonResponse::new(json!({
                                         "data": report.inner,
@@ -220,7 +221,7 @@ impl ManageReports for Server {
                                     ValueClass::Report(report_id.clone()),
                                 ))
                                 .await?
-                                .is_none_or( |report| report.inner.has_domain(domains)),
+                                .is_none_or(|report| report.inner.has_domain(domains)),
                             ReportClass::Dmarc { .. } => self
                                 .core
                                 .storage
@@ -229,7 +230,7 @@ impl ManageReports for Server {
                                     ValueKey::from(ValueClass::Report(report_id.clone())),
                                 )
                                 .await?
-                                .is_none_or( |report| report.inner.has_domain(domains)),
+                                .is_none_or(|report| report.inner.has_domain(domains)),
 
                             ReportClass::Arf { .. } => self
                                 .core
@@ -239,7 +240,7 @@ impl ManageReports for Server {
                                     ValueClass::Report(report_id.clone()),
                                 ))
                                 .await?
-                                .is_none_or( |report| report.inner.has_domain(domains)),
+                                .is_none_or(|report| report.inner.has_domain(domains)),
                         };
 
                         if !is_tenant_report {
@@ -259,11 +260,114 @@ impl ManageReports for Server {
                     Err(trc::ResourceEvent::NotFound.into_err())
                 }
             }
+            ("complaints", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::IncomingReportList)?;
+
+                let params = UrlParams::new(req.uri().query());
+
+                Ok(JsonResponse::new(json!({
+                        "data": fetch_complaint_stats(self, &params, &tenant_domains).await?,
+                }))
+                .into_http_response())
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
 }
 
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComplaintStats {
+    total: u64,
+    incidents: u64,
+    feedback_types: BTreeMap<&'static str, u64>,
+    senders: BTreeMap<String, u64>,
+    reported_domains: BTreeMap<String, u64>,
+    reporters: BTreeMap<String, u64>,
+}
+
+async fn fetch_complaint_stats(
+    server: &Server,
+    params: &UrlParams<'_>,
+    tenant_domains: &Option<Vec<String>>,
+) -> trc::Result<ComplaintStats> {
+    let range_start = params.parse::<u64>("range-start").unwrap_or_default();
+    let range_end = params.parse::<u64>("range-end").unwrap_or(u64::MAX);
+    let mut stats = ComplaintStats::default();
+    let mut last_id = 0;
+
+    server
+        .core
+        .storage
+        .data
+        .iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Report(ReportClass::Arf {
+                    id: range_start,
+                    expires: 0,
+                })),
+                ValueKey::from(ValueClass::Report(ReportClass::Arf {
+                    id: range_end,
+                    expires: u64::MAX,
+                })),
+            ),
+            |key, value| {
+                // Skip chunked records
+                let id = key.deserialize_be_u64(U64_LEN + 1)?;
+                if id == last_id {
+                    return Ok(true);
+                }
+                last_id = id;
+
+                let report = Bincode::<IncomingReport<Feedback>>::deserialize(value)
+                    .caused_by(trc::location!())?
+                    .inner;
+                if tenant_domains
+                    .as_ref()
+                    .is_some_and(|domains| !report.has_domain(domains))
+                {
+                    return Ok(true);
+                }
+
+                let feedback = &report.report;
+                stats.total += 1;
+                stats.incidents += feedback.incidents().max(1) as u64;
+                *stats
+                    .feedback_types
+                    .entry(match feedback.feedback_type() {
+                        FeedbackType::Abuse => "abuse",
+                        FeedbackType::AuthFailure => "auth-failure",
+                        FeedbackType::Fraud => "fraud",
+                        FeedbackType::NotSpam => "not-spam",
+                        FeedbackType::Other => "other",
+                        FeedbackType::Virus => "virus",
+                    })
+                    .or_default() += 1;
+                if let Some(sender) = feedback.original_mail_from() {
+                    *stats
+                        .senders
+                        .entry(sender.trim_matches(['<', '>']).to_lowercase())
+                        .or_default() += 1;
+                }
+                for domain in feedback.reported_domain() {
+                    *stats
+                        .reported_domains
+                        .entry(domain.to_lowercase())
+                        .or_default() += 1;
+                }
+                if let Some((_, domain)) = report.from.rsplit_once('@') {
+                    *stats.reporters.entry(domain.to_lowercase()).or_default() += 1;
+                }
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|_| stats)
+}
+
 struct IncomingReports {
     ids: Vec<(u64, u64)>,
     total: usize,
@@ -355,30 +459,30 @@ async fn fetch_incoming_reports(
                                 .caused_by(trc::location!())?
                                 .inner;
 
-                            filter.is_none_or( |f| report.contains(f))
+                            filter.is_none_or(|f| report.contains(f))
                                 && tenant_domains
                                     .as_ref()
-                                    .is_none_or( |domains| report.has_domain(domains))
+                                    .is_none_or(|domains| report.has_domain(domains))
                         }
                         ReportType::Tls => {
                             let report = Bincode::<IncomingReport<TlsReport>>::deserialize(value)
                                 .caused_by(trc::location!())?
                                 .inner;
 
-                            filter.is_none_or( |f| report.contains(f))
+                            filter.is_none_or(|f| report.contains(f))
                                 && tenant_domains
                                     .as_ref()
-                                    .is_none_or( |domains| report.has_domain(domains))
+                                    .is_none_or(|domains| report.has_domain(domains))
                         }
                         ReportType::Arf => {
                             let report = Bincode::<IncomingReport<Feedback>>::deserialize(value)
                                 .caused_by(trc::location!())?
                                 .inner;
 
-                            filter.is_none_or( |f| report.contains(f))
+                            filter.is_none_or(|f| report.contains(f))
                                 && tenant_domains
                                     .as_ref()
-                                    .is_none_or( |domains| report.has_domain(domains))
+                                    .is_none_or(|domains| report.has_domain(domains))
                         }
                     }
                 } else {
@@ -0,0 +1,249 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use common::{auth::AccessToken, listener::sessions::ActiveSession, Server};
+use directory::backend::internal::manage::{not_found, ManageDirectory};
+use hyper::Method;
+use serde_json::json;
+use store::write::now;
+use trc::AddContext;
+use utils::url_params::UrlParams;
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+use super::decode_path_element;
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionInfo {
+    id: String,
+    protocol: &'static str,
+    listener_id: String,
+    remote_ip: String,
+    remote_port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    asn: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    country: Option<String>,
+    account_id: Option<u32>,
+    account: Option<String>,
+    started: u64,
+    idle: u64,
+    bytes_received: u64,
+    bytes_sent: u64,
+}
+
+pub trait SessionManagement: Sync + Send {
+    fn handle_manage_sessions(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl SessionManagement for Server {
+    async fn handle_manage_sessions(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let sessions = &self.inner.data.active_sessions;
+
+        match (path.get(1).copied(), path.get(2), req.method()) {
+            (None, None, &Method::GET) => {
+                let params = UrlParams::new(req.uri().query());
+                let account_id = if let Some(account) = params.get("account") {
+                    Some(self.session_account_id(account).await?)
+                } else {
+                    None
+                };
+                let protocol = params.get("protocol");
+                let page: usize = params.parse("page").unwrap_or(0);
+                let limit: usize = params.parse("limit").unwrap_or(0);
+
+                let mut active = sessions
+                    .list()
+                    .into_iter()
+                    .filter(|session| {
+                        account_id.is_none_or(|id| session.account_id() == Some(id))
+                            && protocol.is_none_or(|p| session.protocol.as_str() == p)
+                    })
+                    .collect::<Vec<_>>();
+                active.sort_unstable_by_key(|session| session.session_id);
+
+                let now = now();
+                let mut items = Vec::with_capacity(active.len());
+                for session in active {
+                    if let Some(info) = self
+                        .session_info(&session, access_token, now)
+                        .await
+                        .caused_by(trc::location!())?
+                    {
+                        items.push(info);
+                    }
+                }
+                let total = items.len();
+                let items = if limit > 0 {
+                    items
+                        .into_iter()
+                        .skip(page.saturating_sub(1) * limit)
+                        .take(limit)
+                        .collect::<Vec<_>>()
+                } else {
+                    items
+                };
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": items,
+                        "total": total,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some("account"), Some(account), &Method::DELETE) => {
+                let account = decode_path_element(account);
+                let account_id = self.session_account_id(account.as_ref()).await?;
+                if !self.has_session_access(account_id, access_token).await? {
+                    return Err(not_found(account.to_string()));
+                }
+                let terminated = sessions.terminate_account(account_id);
+
+                for session in &terminated {
+                    trc::event!(
+                        Security(trc::SecurityEvent::SessionTerminated),
+                        SpanId = session.session_id,
+                        AccountId = access_token.primary_id(),
+                        AccountName = access_token.name.clone(),
+                        Id = account.to_string(),
+                        Type = session.protocol.as_str(),
+                        RemoteIp = session.remote_ip,
+                    );
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": terminated.len(),
+                }))
+                .into_http_response())
+            }
+            (Some(session_id), None, &Method::DELETE) => {
+                let session = session_id
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|session_id| sessions.get(session_id))
+                    .ok_or_else(|| not_found(session_id.to_string()))?;
+                if let Some(account_id) = session.account_id() {
+                    if !self.has_session_access(account_id, access_token).await? {
+                        return Err(not_found(session_id.to_string()));
+                    }
+                } else if access_token.tenant.is_some() {
+                    return Err(not_found(session_id.to_string()));
+                }
+                session.terminate();
+
+                trc::event!(
+                    Security(trc::SecurityEvent::SessionTerminated),
+                    SpanId = session.session_id,
+                    AccountId = access_token.primary_id(),
+                    AccountName = access_token.name.clone(),
+                    Id = session.account_id(),
+                    Type = session.protocol.as_str(),
+                    RemoteIp = session.remote_ip,
+                );
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+trait SessionAccess {
+    fn session_account_id(&self, name: &str) -> impl Future<Output = trc::Result<u32>> + Send;
+
+    fn has_session_access(
+        &self,
+        account_id: u32,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn session_info(
+        &self,
+        session: &Arc<ActiveSession>,
+        access_token: &AccessToken,
+        now: u64,
+    ) -> impl Future<Output = trc::Result<Option<SessionInfo>>> + Send;
+}
+
+impl SessionAccess for Server {
+    async fn session_account_id(&self, name: &str) -> trc::Result<u32> {
+        self.store()
+            .get_principal_id(name)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| not_found(name.to_string()))
+    }
+
+    async fn has_session_access(
+        &self,
+        account_id: u32,
+        access_token: &AccessToken,
+    ) -> trc::Result<bool> {
+        // Tenant administrators can only manage the sessions of their own accounts
+        if let Some(tenant) = &access_token.tenant {
+            self.get_access_token(account_id)
+                .await
+                .map(|token| token.tenant.is_some_and(|t| t.id == tenant.id))
+        } else {
+            Ok(true)
+        }
+    }
+
+    async fn session_info(
+        &self,
+        session: &Arc<ActiveSession>,
+        access_token: &AccessToken,
+        now: u64,
+    ) -> trc::Result<Option<SessionInfo>> {
+        let account = if let Some(account_id) = session.account_id() {
+            let token = self.get_access_token(account_id).await?;
+            if access_token
+                .tenant
+                .is_some_and(|tenant| token.tenant.is_none_or(|t| t.id != tenant.id))
+            {
+                return Ok(None);
+            }
+            Some(token.name.clone())
+        } else if access_token.tenant.is_some() {
+            return Ok(None);
+        } else {
+            None
+        };
+
+        Ok(Some(SessionInfo {
+            id: session.session_id.to_string(),
+            protocol: session.protocol.as_str(),
+            listener_id: session.listener_id.clone(),
+            remote_ip: session.remote_ip.to_string(),
+            remote_port: session.remote_port,
+            asn: session.asn,
+            country: session.country.as_ref().map(|country| country.to_string()),
+            account_id: session.account_id(),
+            account,
+            started: session.started,
+            idle: now.saturating_sub(session.last_activity()),
+            bytes_received: session.bytes_received(),
+            bytes_sent: session.bytes_sent(),
+        }))
+    }
+}
@@ -222,6 +222,51 @@ impl ManageSettings for Server {
                 }))
                 .into_http_response())
             }
+            (Some("effective"), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                let params = UrlParams::new(req.uri().query());
+                let prefix = params.get("prefix").unwrap_or_default();
+                let filter = params.get("filter").unwrap_or_default().to_lowercase();
+                let limit: usize = params.parse("limit").unwrap_or(0);
+                let mut offset =
+                    params.parse::<usize>("page").unwrap_or(0).saturating_sub(1) * limit;
+
+                // Parse the current configuration to obtain the applied defaults
+                let defaults = self.reload().await?.config.defaults;
+
+                let mut total = 0;
+                let mut items = Vec::new();
+                for (key, value) in self.core.storage.config.effective_config(defaults).await? {
+                    if key.starts_with(prefix)
+                        && (filter.is_empty()
+                            || key.to_lowercase().contains(&filter)
+                            || value.value.to_lowercase().contains(&filter))
+                    {
+                        if offset == 0 {
+                            if limit == 0 || items.len() < limit {
+                                items.push(json!({
+                                    "_id": key,
+                                    "_value": value.value,
+                                    "_source": value.source,
+                                }));
+                            }
+                        } else {
+                            offset -= 1;
+                        }
+                        total += 1;
+                    }
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "total": total,
+                        "items": items,
+                    },
+                }))
+                .into_http_response())
+            }
             (Some("keys"), &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::SettingsList)?;
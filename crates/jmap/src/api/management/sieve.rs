@@ -0,0 +1,955 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Write},
+};
+
+use common::{auth::AccessToken, SandboxId, Server};
+use directory::{
+    backend::internal::{
+        manage::{self, ManageDirectory},
+        PrincipalField,
+    },
+    Permission, Type,
+};
+use hyper::{Method, StatusCode};
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{blob::BlobId, collection::Collection, property::Property, value::Value},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use smtp::scripts::sandbox::ScriptSandbox;
+use store::{
+    query::Filter,
+    write::{
+        assert::HashedValue,
+        log::{ChangeLogBuilder, Changes, LogInsert},
+        now, BatchBuilder, BlobOp, DirectoryClass,
+    },
+    BlobClass,
+};
+use trc::AddContext;
+use utils::url_params::UrlParams;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    blob::download::BlobDownload,
+    sieve::set::{ObjectBlobId, SieveScriptSet, SCHEMA},
+    JmapMethods,
+};
+
+use super::decode_path_element;
+use std::future::Future;
+
+const LIBRARY_PREFIX: &str = "sieve.untrusted.library.";
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryScript {
+    name: String,
+    version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    versions: Vec<LibraryScriptVersion>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryScriptVersion {
+    version: u32,
+    created: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PublishScript {
+    contents: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateScript {
+    #[serde(default)]
+    version: Option<u32>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+pub trait ManageSieveLibrary: Sync + Send {
+    fn handle_manage_sieve(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageSieveLibrary for Server {
+    async fn handle_manage_sieve(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match (path.get(1).copied(), req.method()) {
+            (Some("library"), _) => (),
+            (Some("scripts"), _) => {
+                return self
+                    .handle_manage_sieve_scripts(req, path, body, access_token)
+                    .await;
+            }
+            (Some("export"), &Method::GET) if path.len() == 2 => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SieveScriptExport)?;
+
+                let (archive, total) = self.sieve_scripts_export(access_token).await?;
+
+                trc::event!(
+                    Security(trc::SecurityEvent::DataExport),
+                    AccountId = access_token.primary_id(),
+                    AccountName = access_token.name.clone(),
+                    Details = "sieve",
+                    Total = total,
+                );
+
+                return Ok(
+                    HttpResponse::new_binary(StatusCode::OK, "application/zip", archive)
+                        .with_content_disposition("attachment; filename=\"sieve.zip\""),
+                );
+            }
+            (Some("stats"), &Method::GET) if path.len() == 2 => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MetricsList)?;
+
+                let now = now();
+                let mut stats = self.sandbox_stats();
+                stats.sort_unstable_by(|a, b| b.1.violations.cmp(&a.1.violations));
+
+                return Ok(JsonResponse::new(json!({
+                    "data": stats
+                        .into_iter()
+                        .map(|(id, stats)| {
+                            let (typ, id) = match id {
+                                SandboxId::Sieve(id) => ("sieve", id),
+                                SandboxId::Hook(id) => ("hook", id),
+                            };
+                            json!({
+                                "type": typ,
+                                "id": id,
+                                "runs": stats.runs,
+                                "violations": stats.violations,
+                                "elapsed": stats.elapsed.as_millis() as u64,
+                                "disabledUntil": (stats.disabled_until > now)
+                                    .then_some(stats.disabled_until),
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                }))
+                .into_http_response());
+            }
+            _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+        let name = path.get(2).map(|name| decode_path_element(name));
+        let version = path
+            .get(3)
+            .map(|version| {
+                version
+                    .parse::<u32>()
+                    .map_err(|_| trc::ResourceEvent::BadParameters.into_err())
+            })
+            .transpose()?;
+
+        match (name, version, req.method()) {
+            (None, None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                let scripts = self.sieve_library_list().await?;
+                let params = UrlParams::new(req.uri().query());
+                let page: usize = params.parse("page").unwrap_or(0);
+                let limit: usize = params.parse("limit").unwrap_or(0);
+                let total = scripts.len();
+                let items = scripts
+                    .into_values()
+                    .skip(page.saturating_sub(1) * limit)
+                    .take(if limit > 0 { limit } else { total })
+                    .collect::<Vec<_>>();
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": items,
+                        "total": total,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some(name), version, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                let script = self
+                    .sieve_library_list()
+                    .await?
+                    .remove(name.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                let version = version.unwrap_or(script.version);
+                let contents = self
+                    .core
+                    .storage
+                    .config
+                    .get(format!(
+                        "{LIBRARY_PREFIX}{name}.versions.{version}.contents"
+                    ))
+                    .await?
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "name": script.name,
+                        "version": version,
+                        "published": version == script.version,
+                        "description": script.description,
+                        "contents": contents,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some(name), None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsUpdate)?;
+
+                let request =
+                    serde_json::from_slice::<PublishScript>(body.as_deref().unwrap_or_default())
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+
+                // Validate name and script
+                if name.is_empty()
+                    || !name.chars().all(|ch| {
+                        ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-' || ch == '_'
+                    })
+                {
+                    return Err(manage::error(
+                        "Invalid script name",
+                        "Script names may only contain lowercase letters, digits, '-' and '_'."
+                            .into(),
+                    ));
+                }
+                if self
+                    .core
+                    .sieve
+                    .untrusted_scripts
+                    .contains_key(name.as_ref())
+                {
+                    return Err(manage::err_exists("name", name.into_owned()));
+                }
+                if let Some(capability) = self
+                    .core
+                    .sieve
+                    .untrusted_extensions
+                    .find_disabled(request.contents.as_bytes())
+                {
+                    return Err(manage::error(
+                        "Invalid script",
+                        format!("Extension \"{capability}\" is not enabled on this server.").into(),
+                    ));
+                }
+                if let Err(err) = self
+                    .core
+                    .sieve
+                    .untrusted_compiler
+                    .compile(request.contents.as_bytes())
+                {
+                    return Err(manage::error("Invalid script", err.to_string().into()));
+                }
+
+                // Publish the script as a new version
+                let version = self
+                    .sieve_library_list()
+                    .await?
+                    .remove(name.as_ref())
+                    .and_then(|script| script.versions.iter().map(|v| v.version).max())
+                    .unwrap_or(0)
+                    + 1;
+                let mut keys = vec![
+                    (
+                        format!("{LIBRARY_PREFIX}{name}.versions.{version}.contents"),
+                        request.contents,
+                    ),
+                    (
+                        format!("{LIBRARY_PREFIX}{name}.versions.{version}.created"),
+                        now().to_string(),
+                    ),
+                    (
+                        format!("{LIBRARY_PREFIX}{name}.version"),
+                        version.to_string(),
+                    ),
+                ];
+                if let Some(description) = request.description {
+                    keys.push((format!("{LIBRARY_PREFIX}{name}.description"), description));
+                }
+                self.core.storage.config.set(keys, true).await?;
+                self.sieve_library_apply().await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": version,
+                }))
+                .into_http_response())
+            }
+            (Some(name), None, &Method::PATCH) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsUpdate)?;
+
+                let request =
+                    serde_json::from_slice::<UpdateScript>(body.as_deref().unwrap_or_default())
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+                let script = self
+                    .sieve_library_list()
+                    .await?
+                    .remove(name.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                // Publish a previous version
+                let mut keys = Vec::new();
+                if let Some(version) = request.version {
+                    if !script.versions.iter().any(|v| v.version == version) {
+                        return Err(manage::not_found(format!("{name}/{version}")));
+                    }
+                    keys.push((
+                        format!("{LIBRARY_PREFIX}{name}.version"),
+                        version.to_string(),
+                    ));
+                }
+                if let Some(description) = request.description {
+                    keys.push((format!("{LIBRARY_PREFIX}{name}.description"), description));
+                }
+                if !keys.is_empty() {
+                    self.core.storage.config.set(keys, true).await?;
+                    self.sieve_library_apply().await?;
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some(name), version, &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsDelete)?;
+
+                let script = self
+                    .sieve_library_list()
+                    .await?
+                    .remove(name.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                match version {
+                    Some(version) => {
+                        if version == script.version {
+                            return Err(manage::error(
+                                "Version is published",
+                                "Publish a different version before deleting this one.".into(),
+                            ));
+                        } else if !script.versions.iter().any(|v| v.version == version) {
+                            return Err(manage::not_found(format!("{name}/{version}")));
+                        }
+                        self.core
+                            .storage
+                            .config
+                            .clear_prefix(format!("{LIBRARY_PREFIX}{name}.versions.{version}."))
+                            .await?;
+                    }
+                    None => {
+                        self.core
+                            .storage
+                            .config
+                            .clear_prefix(format!("{LIBRARY_PREFIX}{name}."))
+                            .await?;
+                        self.sieve_library_apply().await?;
+                    }
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+trait SieveLibraryList {
+    fn sieve_library_list(
+        &self,
+    ) -> impl Future<Output = trc::Result<BTreeMap<String, LibraryScript>>> + Send;
+
+    fn sieve_library_apply(&self) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl SieveLibraryList for Server {
+    async fn sieve_library_list(&self) -> trc::Result<BTreeMap<String, LibraryScript>> {
+        let mut scripts: BTreeMap<String, LibraryScript> = BTreeMap::new();
+
+        for (key, value) in self.core.storage.config.list(LIBRARY_PREFIX, true).await? {
+            let Some((name, property)) = key.split_once('.') else {
+                continue;
+            };
+            let script = scripts
+                .entry(name.to_string())
+                .or_insert_with(|| LibraryScript {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+
+            match property.split('.').collect::<Vec<_>>().as_slice() {
+                ["version"] => {
+                    script.version = value.parse().unwrap_or_default();
+                }
+                ["description"] => {
+                    script.description = Some(value);
+                }
+                ["versions", version, "created"] => {
+                    if let Ok(version) = version.parse() {
+                        script.versions.push(LibraryScriptVersion {
+                            version,
+                            created: value.parse().unwrap_or_default(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for script in scripts.values_mut() {
+            script.versions.sort_unstable_by_key(|v| v.version);
+        }
+
+        Ok(scripts)
+    }
+
+    async fn sieve_library_apply(&self) -> trc::Result<()> {
+        // Published scripts are recompiled right away, without a full reload
+        if let Some(core) = self.reload_sieve_library().await?.new_core {
+            self.inner.shared_core.store(core.into());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountScript {
+    #[serde(skip)]
+    document_id: u32,
+    name: String,
+    is_active: bool,
+    size: usize,
+    #[serde(skip)]
+    blob_id: BlobId,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedScript {
+    path: String,
+    account: String,
+    name: String,
+    is_active: bool,
+    size: usize,
+}
+
+trait ManageSieveScripts {
+    fn handle_manage_sieve_scripts(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn sieve_scripts_export(
+        &self,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<(Vec<u8>, usize)>> + Send;
+
+    fn sieve_script_list(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<AccountScript>>> + Send;
+
+    fn sieve_script_put(
+        &self,
+        account_id: u32,
+        name: &str,
+        script: Vec<u8>,
+    ) -> impl Future<Output = trc::Result<u32>> + Send;
+
+    fn sieve_script_activate(
+        &self,
+        account_id: u32,
+        document_id: Option<u32>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl ManageSieveScripts for Server {
+    async fn handle_manage_sieve_scripts(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        // Validate the access token
+        access_token.assert_has_permission(Permission::SieveScriptManage)?;
+
+        let account = path
+            .get(2)
+            .map(|account| decode_path_element(account))
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+        let account_id = self
+            .core
+            .storage
+            .data
+            .get_principal_info(account.as_ref())
+            .await?
+            .filter(|p| {
+                matches!(p.typ, Type::Individual | Type::Group)
+                    && p.has_tenant_access(access_token.tenant.map(|t| t.id))
+            })
+            .map(|p| p.id)
+            .ok_or_else(|| manage::not_found(account.to_string()))?;
+        let name = path.get(3).map(|name| decode_path_element(name));
+
+        match (name, path.get(4).copied(), req.method()) {
+            (None, None, &Method::GET) => {
+                let scripts = self.sieve_script_list(account_id).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": scripts,
+                        "total": scripts.len(),
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some(name), None, &Method::GET) => {
+                let script = self
+                    .sieve_script_list(account_id)
+                    .await?
+                    .into_iter()
+                    .find(|script| script.name == name)
+                    .ok_or_else(|| manage::not_found(name.to_string()))?;
+                let contents = self
+                    .get_blob_section(
+                        &script.blob_id.hash,
+                        script.blob_id.section.as_ref().unwrap(),
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+                    .ok_or_else(|| manage::not_found(name.to_string()))?;
+
+                Ok(
+                    HttpResponse::new_binary(StatusCode::OK, "application/sieve", contents)
+                        .with_content_disposition(format!(
+                            "attachment; filename=\"{}.sieve\"",
+                            name.replace('\"', "\\\"")
+                        )),
+                )
+            }
+            (Some(name), None, &Method::PUT) => {
+                let document_id = self
+                    .sieve_script_put(account_id, name.trim(), body.unwrap_or_default())
+                    .await?;
+
+                // Optionally activate the uploaded script
+                if UrlParams::new(req.uri().query()).parse::<bool>("activate") == Some(true) {
+                    self.sieve_script_activate(account_id, Some(document_id))
+                        .await?;
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some(name), Some(action @ ("activate" | "deactivate")), &Method::POST) => {
+                let document_id = if action == "activate" {
+                    self.sieve_script_list(account_id)
+                        .await?
+                        .into_iter()
+                        .find(|script| script.name == name)
+                        .ok_or_else(|| manage::not_found(name.to_string()))?
+                        .document_id
+                        .into()
+                } else {
+                    None
+                };
+                self.sieve_script_activate(account_id, document_id).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+
+    async fn sieve_scripts_export(
+        &self,
+        access_token: &AccessToken,
+    ) -> trc::Result<(Vec<u8>, usize)> {
+        let accounts = self
+            .core
+            .storage
+            .data
+            .list_principals(
+                None,
+                access_token.tenant.map(|t| t.id),
+                &[Type::Individual, Type::Group],
+                &[PrincipalField::Name],
+                0,
+                0,
+            )
+            .await?
+            .items;
+
+        // Scripts are stored as <account>/<name>.sieve, with a manifest
+        // recording the active script of each account
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let mut manifest = Vec::new();
+        for account in accounts {
+            for script in self.sieve_script_list(account.id()).await? {
+                let Some(contents) = self
+                    .get_blob_section(
+                        &script.blob_id.hash,
+                        script.blob_id.section.as_ref().unwrap(),
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+                else {
+                    trc::event!(
+                        Store(trc::StoreEvent::NotFound),
+                        AccountId = account.id(),
+                        DocumentId = script.document_id,
+                        Collection = Collection::SieveScript,
+                        BlobId = script.blob_id.hash.to_hex(),
+                        Details = "Blob not found.",
+                        CausedBy = trc::location!(),
+                    );
+                    continue;
+                };
+                let path = format!(
+                    "{}/{}.sieve",
+                    safe_path_element(account.name()),
+                    safe_path_element(&script.name)
+                );
+                add_file(&mut zip, &path, &contents)?;
+                manifest.push(ExportedScript {
+                    path,
+                    account: account.name().to_string(),
+                    name: script.name,
+                    is_active: script.is_active,
+                    size: script.size,
+                });
+            }
+        }
+
+        let total = manifest.len();
+        add_file(
+            &mut zip,
+            "manifest.json",
+            &serde_json::to_vec_pretty(&manifest).unwrap_or_default(),
+        )?;
+        zip.finish()
+            .map(|cursor| (cursor.into_inner(), total))
+            .map_err(export_error)
+    }
+
+    async fn sieve_script_list(&self, account_id: u32) -> trc::Result<Vec<AccountScript>> {
+        let mut scripts = Vec::new();
+        for document_id in self
+            .get_document_ids(account_id, Collection::SieveScript)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default()
+        {
+            let Some(mut script) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::SieveScript,
+                    document_id,
+                    Property::Value,
+                )
+                .await
+                .caused_by(trc::location!())?
+            else {
+                continue;
+            };
+            if let (Some(Value::Text(name)), Some(Value::BlobId(blob_id))) = (
+                script.properties.remove(&Property::Name),
+                script.properties.remove(&Property::BlobId),
+            ) {
+                if let Some(size) = blob_id.section.as_ref().map(|section| section.size) {
+                    scripts.push(AccountScript {
+                        document_id,
+                        name,
+                        is_active: script.get(&Property::IsActive).as_bool() == Some(true),
+                        size,
+                        blob_id,
+                    });
+                }
+            }
+        }
+        scripts.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(scripts)
+    }
+
+    async fn sieve_script_put(
+        &self,
+        account_id: u32,
+        name: &str,
+        mut script: Vec<u8>,
+    ) -> trc::Result<u32> {
+        // Validate name
+        if name.is_empty() || name.len() > self.core.jmap.sieve_max_script_name {
+            return Err(manage::error(
+                "Invalid script name",
+                format!(
+                    "Script names must be between 1 and {} bytes long.",
+                    self.core.jmap.sieve_max_script_name
+                )
+                .into(),
+            ));
+        } else if name.eq_ignore_ascii_case("vacation") {
+            return Err(manage::error(
+                "Invalid script name",
+                "The 'vacation' name is reserved, please use a different name.".into(),
+            ));
+        }
+        let current = self
+            .filter(
+                account_id,
+                Collection::SieveScript,
+                vec![Filter::eq(Property::Name, name)],
+            )
+            .await
+            .caused_by(trc::location!())?
+            .results
+            .min();
+
+        // Check quota and limits on behalf of the account
+        let script_size = script.len();
+        self.has_available_quota(
+            &self.get_access_token(account_id).await?.as_resource_token(),
+            script_size as u64,
+        )
+        .await?;
+        if current.is_none()
+            && self
+                .get_document_ids(account_id, Collection::SieveScript)
+                .await
+                .caused_by(trc::location!())?
+                .is_some_and(|ids| ids.len() as usize >= self.core.jmap.sieve_max_scripts)
+        {
+            return Err(manage::error(
+                "Too many scripts",
+                "The account has reached the maximum number of scripts.".into(),
+            ));
+        }
+
+        // Validate and compile script
+        if let Some(capability) = self.core.sieve.untrusted_extensions.find_disabled(&script) {
+            return Err(manage::error(
+                "Invalid script",
+                format!("Extension \"{capability}\" is not enabled on this server.").into(),
+            ));
+        }
+        match self.core.sieve.untrusted_compiler.compile(&script) {
+            Ok(compiled_script) => {
+                script.extend(bincode::serialize(&compiled_script).unwrap_or_default());
+            }
+            Err(err) => {
+                return Err(manage::error("Invalid script", err.to_string().into()));
+            }
+        }
+
+        let hash = self
+            .put_blob(account_id, &script, false)
+            .await
+            .caused_by(trc::location!())?
+            .hash;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::SieveScript);
+
+        if let Some(document_id) = current {
+            let current = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::SieveScript,
+                    document_id,
+                    Property::Value,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| manage::not_found(name.to_string()))?;
+            let prev_blob_id = current
+                .inner
+                .blob_id()
+                .cloned()
+                .ok_or_else(|| manage::not_found(name.to_string()))?;
+            let prev_size = prev_blob_id
+                .section
+                .as_ref()
+                .map_or(0, |section| section.size);
+            let blob_id = BlobId::new(
+                hash,
+                BlobClass::Linked {
+                    account_id,
+                    collection: Collection::SieveScript.into(),
+                    document_id,
+                },
+            )
+            .with_section_size(script_size);
+
+            batch
+                .update_document(document_id)
+                .clear(BlobOp::Link {
+                    hash: prev_blob_id.hash,
+                })
+                .set(
+                    BlobOp::Link {
+                        hash: blob_id.hash.clone(),
+                    },
+                    Vec::new(),
+                )
+                .log(Changes::update([document_id]));
+            if script_size != prev_size {
+                batch.add(
+                    DirectoryClass::UsedQuota(account_id),
+                    script_size as i64 - prev_size as i64,
+                );
+            }
+            batch.custom(
+                ObjectIndexBuilder::new(SCHEMA)
+                    .with_current(current)
+                    .with_changes(
+                        Object::with_capacity(1)
+                            .with_property(Property::BlobId, Value::BlobId(blob_id)),
+                    ),
+            );
+            self.store()
+                .write(batch)
+                .await
+                .caused_by(trc::location!())?;
+
+            Ok(document_id)
+        } else {
+            let blob_id = BlobId::new(
+                hash,
+                BlobClass::Linked {
+                    account_id,
+                    collection: Collection::SieveScript.into(),
+                    document_id: 0,
+                },
+            )
+            .with_section_size(script_size);
+
+            batch
+                .create_document()
+                .log(LogInsert())
+                .add(DirectoryClass::UsedQuota(account_id), script_size as i64)
+                .set(
+                    BlobOp::Link {
+                        hash: blob_id.hash.clone(),
+                    },
+                    Vec::new(),
+                )
+                .custom(
+                    ObjectIndexBuilder::new(SCHEMA).with_changes(
+                        Object::with_capacity(3)
+                            .with_property(Property::Name, name.to_string())
+                            .with_property(Property::IsActive, Value::Bool(false))
+                            .with_property(Property::BlobId, Value::BlobId(blob_id)),
+                    ),
+                );
+            self.store()
+                .write(batch)
+                .await
+                .caused_by(trc::location!())?
+                .last_document_id()
+        }
+    }
+
+    async fn sieve_script_activate(
+        &self,
+        account_id: u32,
+        document_id: Option<u32>,
+    ) -> trc::Result<()> {
+        let changes = self
+            .sieve_activate_script(account_id, document_id)
+            .await
+            .caused_by(trc::location!())?;
+
+        if !changes.is_empty() {
+            let mut changelog = ChangeLogBuilder::new();
+            for (document_id, _) in changes {
+                changelog.log_update(Collection::SieveScript, document_id);
+            }
+            self.commit_changes(account_id, changelog)
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn safe_path_element(name: &str) -> String {
+    if matches!(name, "." | "..") {
+        return "_".repeat(name.len());
+    }
+    name.chars()
+        .map(|ch| match ch {
+            '/' | '\\' | '\0' => '_',
+            _ => ch,
+        })
+        .collect()
+}
+
+fn add_file(zip: &mut ZipWriter<Cursor<Vec<u8>>>, path: &str, contents: &[u8]) -> trc::Result<()> {
+    zip.start_file(
+        path,
+        SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+    )
+    .map_err(export_error)?;
+    zip.write_all(contents).map_err(export_error)
+}
+
+fn export_error(err: impl std::fmt::Display) -> trc::Error {
+    trc::StoreEvent::UnexpectedError
+        .caused_by(trc::location!())
+        .reason(err)
+        .details("Failed to build Sieve script archive")
+}
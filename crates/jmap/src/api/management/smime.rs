@@ -0,0 +1,234 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{config::smtp::smime::build_smime_key_pair, Server};
+use directory::backend::internal::manage;
+use email::{
+    crypto::{try_parse_certs, EncryptionMethod},
+    smime::{inspect_certificate, SmimeCertificate, SmimeGateway},
+};
+use hyper::Method;
+use mail_builder::encoders::base64::base64_encode;
+use serde_json::json;
+use store::write::now;
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+use super::decode_path_element;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SmimeDomainRequest {
+    id: String,
+    domains: Vec<String>,
+    certificate: String,
+    private_key: String,
+    #[serde(default = "default_true")]
+    encrypt: bool,
+    #[serde(default)]
+    cipher: Option<String>,
+}
+
+pub trait SmimeManagement: Sync + Send {
+    fn handle_manage_smime(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl SmimeManagement for Server {
+    async fn handle_manage_smime(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        match (
+            path.get(1).copied(),
+            path.get(2).map(|v| decode_path_element(v)),
+            req.method(),
+        ) {
+            (Some("certificate"), Some(address), &Method::GET) => {
+                let address = address.to_lowercase();
+                let cert = self
+                    .smime_certificate(&address)
+                    .await?
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "certificate": der_to_pem(&cert.certificate),
+                        "harvested": cert.harvested,
+                        "updated": cert.updated,
+                        "info": inspect_certificate(&cert.certificate).ok(),
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some("certificate"), Some(address), &Method::POST) => {
+                let address = address.to_lowercase();
+                let certificate = try_parse_certs(
+                    EncryptionMethod::SMIME,
+                    body.ok_or_else(|| manage::err_missing("certificate"))?,
+                )
+                .map_err(|err| manage::error(err, None::<u32>))?
+                .into_iter()
+                .next()
+                .ok_or_else(|| manage::err_missing("certificate"))?;
+                let info = inspect_certificate(&certificate)
+                    .map_err(|err| manage::error(err, None::<u32>))?;
+                if !info.addresses.contains(&address) {
+                    return Err(manage::error(
+                        "Certificate does not match address",
+                        Some(format!(
+                            "The certificate was issued for {}",
+                            info.addresses.join(", ")
+                        )),
+                    ));
+                }
+
+                let now = now();
+                self.smime_certificate_set(
+                    &address,
+                    SmimeCertificate {
+                        certificate,
+                        harvested: false,
+                        updated: now,
+                    },
+                    (info.not_after.max(0) as u64).saturating_sub(now),
+                )
+                .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": info,
+                }))
+                .into_http_response())
+            }
+            (Some("certificate"), Some(address), &Method::DELETE) => {
+                self.smime_certificate_delete(&address).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some("domain"), None, &Method::GET) => {
+                let mut policies = self
+                    .core
+                    .smtp
+                    .smime
+                    .domains
+                    .iter()
+                    .map(|(domain, policy)| (policy.id.as_str(), domain.as_str(), policy))
+                    .collect::<Vec<_>>();
+                policies.sort_unstable_by(|a, b| a.0.cmp(b.0).then_with(|| a.1.cmp(b.1)));
+                let mut results: Vec<serde_json::Value> = Vec::new();
+                for (id, domain, policy) in policies {
+                    if let Some(result) = results.last_mut().filter(|r| r["id"] == id) {
+                        if let Some(domains) = result["domains"].as_array_mut() {
+                            domains.push(domain.into());
+                        }
+                        continue;
+                    }
+                    results.push(json!({
+                        "id": id,
+                        "domains": [domain],
+                        "sign": policy.signer.is_some(),
+                        "encrypt": policy.encrypt,
+                        "certificate": policy
+                            .signer
+                            .as_ref()
+                            .and_then(|signer| signer.certificates.first())
+                            .and_then(|cert| inspect_certificate(cert).ok()),
+                    }));
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": results,
+                }))
+                .into_http_response())
+            }
+            (Some("domain"), None, &Method::POST) => {
+                let request = serde_json::from_slice::<SmimeDomainRequest>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+                if request.id.is_empty()
+                    || !request
+                        .id
+                        .chars()
+                        .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+                {
+                    return Err(manage::error("Invalid policy id", None::<u32>));
+                } else if request.domains.is_empty() {
+                    return Err(manage::err_missing("domains"));
+                }
+                let cipher = match request.cipher.as_deref() {
+                    Some(cipher @ ("aes128" | "aes256")) => cipher,
+                    None => "aes256",
+                    Some(_) => return Err(manage::error("Invalid cipher", None::<u32>)),
+                };
+                build_smime_key_pair(&request.certificate, &request.private_key)
+                    .map_err(|err| manage::error(err, None::<u32>))?;
+
+                let id = request.id;
+                let prefix = format!("smime.domain.{id}.");
+                self.core.storage.config.clear_prefix(&prefix).await?;
+                let mut keys = vec![
+                    (format!("{prefix}certificate"), request.certificate),
+                    (format!("{prefix}private-key"), request.private_key),
+                    (format!("{prefix}sign"), "true".to_string()),
+                    (format!("{prefix}encrypt"), request.encrypt.to_string()),
+                    (format!("{prefix}cipher"), cipher.to_string()),
+                ];
+                for (idx, domain) in request.domains.into_iter().enumerate() {
+                    keys.push((format!("{prefix}domains.{idx}"), domain.to_lowercase()));
+                }
+                self.core.storage.config.set(keys, true).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some("domain"), Some(id), &Method::DELETE) => {
+                self.core
+                    .storage
+                    .config
+                    .clear_prefix(&format!("smime.domain.{id}."))
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+fn der_to_pem(der: &[u8]) -> String {
+    let encoded = base64_encode(der).unwrap_or_default();
+    let mut pem = String::with_capacity(encoded.len() + 64);
+    pem.push_str("-----BEGIN CERTIFICATE-----\n");
+    for chunk in encoded.chunks(64) {
+        pem.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem
+}
+
+fn default_true() -> bool {
+    true
+}
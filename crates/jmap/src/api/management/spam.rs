@@ -4,9 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::net::IpAddr;
+use std::{collections::BTreeMap, net::IpAddr};
 
-use common::{auth::AccessToken, config::spamfilter::SpamFilterAction, psl, Server};
+use common::{
+    auth::AccessToken,
+    config::spamfilter::{SpamFilterAction, SpamFilterLists, SpamFilterRules},
+    psl, Server,
+};
 use directory::{
     backend::internal::manage::{self, ManageDirectory},
     Permission,
@@ -15,20 +19,29 @@ use hyper::Method;
 use mail_auth::{
     dmarc::verify::DmarcParameters, spf::verify::SpfParameters, AuthenticatedMessage, DmarcResult,
 };
-use mail_parser::{Message, MessageParser};
+use mail_parser::{DateTime, Message, MessageParser};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use smtp::inbound::reputation::{ConnectionReputation, ReputationType};
 use spam_filter::{
     analysis::{init::SpamFilterInit, score::SpamFilterAnalyzeScore},
-    modules::bayes::BayesClassifier,
+    modules::{bayes::BayesClassifier, stats::SpamFilterStats},
     SpamFilterInput,
 };
 use std::future::Future;
-use store::ahash::AHashMap;
+use store::{ahash::AHashMap, write::now};
+use utils::{
+    config::{Config, ConfigKey},
+    url_params::UrlParams,
+    Semver,
+};
 
-use crate::api::{
-    http::{HttpSessionData, ToHttpResponse},
-    HttpRequest, HttpResponse, JsonResponse,
+use crate::{
+    api::{
+        http::{HttpSessionData, ToHttpResponse},
+        HttpRequest, HttpResponse, JsonResponse,
+    },
+    JmapMethods,
 };
 
 use super::decode_path_element;
@@ -42,8 +55,20 @@ pub trait ManageSpamHandler: Sync + Send {
         session: &HttpSessionData,
         access_token: &AccessToken,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn spam_pack_install(
+        &self,
+        name: &str,
+        pack: &[u8],
+        force: bool,
+    ) -> impl Future<Output = trc::Result<Semver>> + Send;
+
+    fn spam_pack_remove(&self, name: &str) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
+const MAX_PACK_NAME_LEN: usize = 64;
+const MAX_STATS_HOURS: u64 = 24 * 7;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SpamClassifyRequest {
@@ -280,9 +305,274 @@ impl ManageSpamHandler for Server {
                 }))
                 .into_http_response())
             }
+            (Some("reputation"), Some(rep_type), method @ (&Method::GET | &Method::DELETE)) => {
+                let rep_type = ReputationType::parse(rep_type)
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                let key = decode_path_element(path.get(3).copied().unwrap_or_default());
+                let key = match rep_type {
+                    ReputationType::Ip => key
+                        .parse::<IpAddr>()
+                        .map_err(|_| {
+                            trc::ResourceEvent::BadParameters
+                                .into_err()
+                                .details("Invalid IP address")
+                        })?
+                        .to_string(),
+                    ReputationType::Asn => key
+                        .parse::<u32>()
+                        .map_err(|_| {
+                            trc::ResourceEvent::BadParameters
+                                .into_err()
+                                .details("Invalid ASN")
+                        })?
+                        .to_string(),
+                    ReputationType::Helo if !key.is_empty() => key.to_lowercase(),
+                    ReputationType::Helo => {
+                        return Err(trc::ResourceEvent::NotFound.into_err());
+                    }
+                };
+
+                if method == Method::GET {
+                    let reputation = self
+                        .reputation(rep_type, &key)
+                        .await?
+                        .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                    Ok(JsonResponse::new(json!({
+                        "data": reputation,
+                    }))
+                    .into_http_response())
+                } else {
+                    access_token.assert_has_permission(Permission::SpamFilterUpdate)?;
+
+                    self.reset_reputation(rep_type, &key).await?;
+
+                    Ok(JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response())
+                }
+            }
+            (Some("pack"), None, &Method::GET) => {
+                access_token.assert_has_permission(Permission::SpamFilterUpdate)?;
+
+                let config = &self.core.storage.config;
+                let mut packs = Vec::new();
+                for (name, settings) in config.group("spam-filter.pack.", ".version").await? {
+                    let rules = config
+                        .list(&format!("spam-filter.rule.{name}_"), true)
+                        .await?
+                        .keys()
+                        .filter(|key| key.ends_with(".scope"))
+                        .count();
+                    packs.push(json!({
+                        "name": name,
+                        "version": settings.get("version"),
+                        "updatedAt": settings
+                            .get("updated")
+                            .and_then(|v| v.parse::<i64>().ok())
+                            .map(|v| DateTime::from_timestamp(v).to_rfc3339()),
+                        "rules": rules,
+                        "scores": settings.keys().filter(|k| k.starts_with("scores.")).count(),
+                    }));
+                }
+                packs.sort_unstable_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+                Ok(JsonResponse::new(json!({
+                    "data": packs,
+                }))
+                .into_http_response())
+            }
+            (Some("pack"), Some(name), method @ (&Method::POST | &Method::DELETE)) => {
+                access_token.assert_has_permission(Permission::SpamFilterUpdate)?;
+
+                let name = decode_path_element(name);
+                if name.is_empty()
+                    || name.len() > MAX_PACK_NAME_LEN
+                    || !name
+                        .chars()
+                        .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-')
+                {
+                    return Err(trc::ResourceEvent::BadParameters
+                        .into_err()
+                        .details("Invalid rule pack name"));
+                }
+
+                let data = if method == Method::POST {
+                    let version = self
+                        .spam_pack_install(
+                            name.as_ref(),
+                            body.as_deref().unwrap_or_default(),
+                            UrlParams::new(req.uri().query()).has_key("force"),
+                        )
+                        .await?;
+                    json!({ "version": version.to_string() })
+                } else {
+                    self.spam_pack_remove(name.as_ref()).await?;
+                    json!(())
+                };
+
+                // Activate the updated rules without restarting
+                let result = self.reload_spam_filter().await?;
+                if let Some((key, error)) = result.config.errors.iter().next() {
+                    return Err(manage::error(
+                        "Failed to activate spam filter rules",
+                        format!("{key}: {error:?}").into(),
+                    ));
+                }
+                if let Some(core) = result.new_core {
+                    self.inner.shared_core.store(core.into());
+                    self.increment_config_version();
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": data,
+                }))
+                .into_http_response())
+            }
+            (Some("stats"), None, &Method::GET) => {
+                let params = UrlParams::new(req.uri().query());
+                let hours = params
+                    .parse::<u64>("hours")
+                    .unwrap_or(24)
+                    .clamp(1, MAX_STATS_HOURS);
+                let limit = params.parse::<usize>("limit").unwrap_or(50);
+
+                Ok(JsonResponse::new(json!({
+                    "data": self
+                        .spam_filter_top_tags(hours, limit)
+                        .into_iter()
+                        .map(|(tag, hits)| json!({
+                            "tag": tag,
+                            "hits": hits,
+                            "score": match self.core.spam.lists.scores.get(&tag) {
+                                Some(SpamFilterAction::Allow(score)) => json!(score),
+                                Some(SpamFilterAction::Discard) => json!("discard"),
+                                Some(SpamFilterAction::Reject) => json!("reject"),
+                                None => json!(0.0),
+                            },
+                        }))
+                        .collect::<Vec<_>>(),
+                }))
+                .into_http_response())
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
+
+    async fn spam_pack_install(&self, name: &str, pack: &[u8], force: bool) -> trc::Result<Semver> {
+        let mut pack = std::str::from_utf8(pack)
+            .map_err(|_| "Rule pack contains invalid UTF-8".to_string())
+            .and_then(|pack| Config::new(pack).map_err(|err| err.to_string()))
+            .map_err(|err| {
+                trc::ResourceEvent::BadParameters
+                    .into_err()
+                    .details("Failed to parse rule pack")
+                    .reason(err)
+            })?;
+
+        // Validate version
+        let version = pack
+            .keys
+            .remove("version")
+            .and_then(|v| Semver::try_from(v.as_str()).ok())
+            .ok_or_else(|| {
+                trc::ResourceEvent::BadParameters
+                    .into_err()
+                    .details("Rule pack does not contain a valid version")
+            })?;
+        let config = &self.core.storage.config;
+        if let Some(current) = config
+            .get(format!("spam-filter.pack.{name}.version"))
+            .await?
+            .and_then(|v| Semver::try_from(v.as_str()).ok())
+            .filter(|current| !force && *current >= version)
+        {
+            return Err(trc::ManageEvent::AssertFailed.into_err().details(format!(
+                "Rule pack version {current} is already installed, use force to replace it"
+            )));
+        }
+
+        // Rule ids are prefixed with the pack name to keep packs isolated
+        let mut keys = BTreeMap::new();
+        let mut scores = Vec::new();
+        for (key, value) in std::mem::take(&mut pack.keys) {
+            if let Some(rule) = key.strip_prefix("spam-filter.rule.") {
+                keys.insert(format!("spam-filter.rule.{name}_{rule}"), value);
+            } else if let Some(tag) = key.strip_prefix("spam-filter.list.scores.") {
+                scores.push(tag.to_string());
+                keys.insert(key, value);
+            } else {
+                return Err(trc::ResourceEvent::BadParameters
+                    .into_err()
+                    .details("Unsupported rule pack setting")
+                    .ctx(trc::Key::Key, key));
+            }
+        }
+        pack.keys = keys;
+        SpamFilterRules::parse(&mut pack);
+        SpamFilterLists::parse(&mut pack);
+        if let Some((key, error)) = pack.errors.iter().next() {
+            return Err(manage::error(
+                "Invalid rule pack",
+                format!("{key}: {error:?}").into(),
+            ));
+        }
+
+        // Replace the previous version
+        self.spam_pack_remove(name).await?;
+        config
+            .set(
+                pack.keys
+                    .into_iter()
+                    .chain([
+                        (
+                            format!("spam-filter.pack.{name}.version"),
+                            version.to_string(),
+                        ),
+                        (
+                            format!("spam-filter.pack.{name}.updated"),
+                            now().to_string(),
+                        ),
+                    ])
+                    .chain(scores.into_iter().map(|tag| {
+                        (
+                            format!("spam-filter.pack.{name}.scores.{tag}"),
+                            "true".to_string(),
+                        )
+                    }))
+                    .map(ConfigKey::from),
+                true,
+            )
+            .await?;
+
+        trc::event!(
+            Config(trc::ConfigEvent::ImportExternal),
+            Version = version.to_string(),
+            Id = format!("spam-filter.pack.{name}"),
+        );
+
+        Ok(version)
+    }
+
+    async fn spam_pack_remove(&self, name: &str) -> trc::Result<()> {
+        let config = &self.core.storage.config;
+        for tag in config
+            .list(&format!("spam-filter.pack.{name}.scores."), true)
+            .await?
+            .into_keys()
+        {
+            config
+                .clear(format!("spam-filter.list.scores.{tag}"))
+                .await?;
+        }
+        config
+            .clear_prefix(format!("spam-filter.rule.{name}_"))
+            .await?;
+        config
+            .clear_prefix(format!("spam-filter.pack.{name}."))
+            .await
+    }
 }
 
 fn parse_message_or_err(bytes: &[u8]) -> trc::Result<Message<'_>> {
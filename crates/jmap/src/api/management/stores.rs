@@ -9,15 +9,19 @@ use common::{
     auth::AccessToken,
     ipc::{HousekeeperEvent, PurgeType},
     manager::webadmin::Resource,
+    telemetry::metrics::storage::StorageScope,
     *,
 };
 use directory::{
-    backend::internal::manage::{self, ManageDirectory},
-    Permission,
+    backend::internal::{
+        manage::{self, ManageDirectory},
+        PrincipalField,
+    },
+    Permission, Type,
 };
 use email::{
     ingest::EmailIngest,
-    mailbox::{UidMailbox, SCHEMA},
+    mailbox::{MailboxFnc, UidMailbox, SCHEMA},
 };
 use hyper::Method;
 use jmap_proto::{
@@ -25,7 +29,11 @@ use jmap_proto::{
     types::{collection::Collection, property::Property, value::Value},
 };
 use serde_json::json;
-use store::write::{assert::HashedValue, BatchBuilder, ValueClass, F_VALUE};
+use store::{
+    ahash::AHashMap,
+    write::{assert::HashedValue, BatchBuilder, ValueClass, F_VALUE},
+    Value as StoreValue,
+};
 use trc::AddContext;
 use utils::url_params::UrlParams;
 
@@ -182,11 +190,16 @@ impl ManageStore for Server {
                     }
                     Some("bayes-global") => vec![KV_BAYES_MODEL_GLOBAL].into(),
                     Some("trusted-reply") => vec![KV_TRUSTED_REPLY].into(),
+                    Some("known-sender") => vec![KV_KNOWN_SENDER].into(),
+                    Some("storage-history") => vec![KV_STORAGE_HISTORY].into(),
                     Some("lock-purge-account") => vec![KV_LOCK_PURGE_ACCOUNT].into(),
                     Some("lock-queue-message") => vec![KV_LOCK_QUEUE_MESSAGE].into(),
                     Some("lock-queue-report") => vec![KV_LOCK_QUEUE_REPORT].into(),
                     Some("lock-email-task") => vec![KV_LOCK_EMAIL_TASK].into(),
                     Some("lock-housekeeper") => vec![KV_LOCK_HOUSEKEEPER].into(),
+                    Some("list-moderation") => vec![KV_LIST_MODERATION].into(),
+                    Some("lock-list-moderation") => vec![KV_LOCK_LIST_MODERATION].into(),
+                    Some("delivery-dedup") => vec![KV_DELIVERY_DEDUP].into(),
                     _ => None,
                 };
 
@@ -244,6 +257,69 @@ impl ManageStore for Server {
                 }))
                 .into_http_response())
             }
+            (Some("special-use"), id, None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MailboxRoleMapping)?;
+
+                if let Some(id) = id {
+                    let account_id = self
+                        .core
+                        .storage
+                        .data
+                        .get_principal_id(decode_path_element(id).as_ref())
+                        .await?
+                        .ok_or_else(|| trc::ManageEvent::NotFound.into_err())?;
+
+                    let result = self
+                        .mailbox_map_special_use(account_id)
+                        .await?
+                        .into_iter()
+                        .filter_map(|(name, special_use)| {
+                            special_use.as_role().map(|role| (name, role))
+                        })
+                        .collect::<AHashMap<_, _>>();
+
+                    Ok(JsonResponse::new(json!({
+                        "data": result,
+                    }))
+                    .into_http_response())
+                } else {
+                    let tenant_id = access_token.tenant.map(|t| t.id);
+                    let account_ids = self
+                        .core
+                        .storage
+                        .data
+                        .list_principals(
+                            None,
+                            tenant_id,
+                            &[Type::Individual, Type::Group],
+                            &[PrincipalField::Name],
+                            0,
+                            0,
+                        )
+                        .await?
+                        .items
+                        .into_iter()
+                        .map(|principal| principal.id())
+                        .collect::<Vec<_>>();
+
+                    let server = self.clone();
+                    tokio::spawn(async move {
+                        for account_id in account_ids {
+                            if let Err(err) = server.mailbox_map_special_use(account_id).await {
+                                trc::error!(err
+                                    .account_id(account_id)
+                                    .details("Failed to map special-use folders"));
+                            }
+                        }
+                    });
+
+                    Ok(JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response())
+                }
+            }
             (Some("uids"), Some(account_id), None, &Method::DELETE) => {
                 let account_id = self
                     .core
@@ -280,6 +356,114 @@ impl ManageStore for Server {
                 }))
                 .into_http_response())
             }
+            (Some("forecast"), typ, name, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MetricsList)?;
+
+                if self.core.metrics.storage_forecast.is_none() {
+                    return Err(manage::unsupported("Storage forecasting is disabled"));
+                }
+                let tenant_id = access_token.tenant.map(|t| t.id);
+
+                match (typ, name) {
+                    (None, None) => {
+                        // Forecast the whole server and every domain
+                        let mut domains = AHashMap::new();
+                        for domain in self
+                            .core
+                            .storage
+                            .data
+                            .list_principals(
+                                None,
+                                tenant_id,
+                                &[Type::Domain],
+                                &[PrincipalField::Name],
+                                0,
+                                0,
+                            )
+                            .await?
+                            .items
+                        {
+                            if let Some(trend) = self
+                                .storage_trend(StorageScope::Domain(domain.name()))
+                                .await?
+                            {
+                                domains.insert(domain.name().to_string(), trend);
+                            }
+                        }
+                        let server = if tenant_id.is_none() {
+                            self.storage_trend(StorageScope::Server).await?
+                        } else {
+                            None
+                        };
+
+                        Ok(JsonResponse::new(json!({
+                            "data": {
+                                "server": server,
+                                "domains": domains,
+                            },
+                        }))
+                        .into_http_response())
+                    }
+                    (Some(typ @ ("account" | "domain")), Some(name)) => {
+                        let name = decode_path_element(name);
+                        let principal = self
+                            .core
+                            .storage
+                            .data
+                            .get_principal_info(name.as_ref())
+                            .await?
+                            .filter(|p| {
+                                p.has_tenant_access(tenant_id)
+                                    && if typ == "account" {
+                                        matches!(p.typ, Type::Individual | Type::Group)
+                                    } else {
+                                        p.typ == Type::Domain
+                                    }
+                            })
+                            .ok_or_else(|| manage::not_found(name.to_string()))?;
+                        let scope = if typ == "account" {
+                            StorageScope::Account(principal.id)
+                        } else {
+                            StorageScope::Domain(name.as_ref())
+                        };
+
+                        Ok(JsonResponse::new(json!({
+                            "data": self.storage_trend(scope).await?,
+                        }))
+                        .into_http_response())
+                    }
+                    _ => Err(trc::ResourceEvent::NotFound.into_err()),
+                }
+            }
+            (Some("lookup"), Some(id), None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::Troubleshoot)?;
+
+                let params = match serde_json::from_slice::<Vec<serde_json::Value>>(
+                    body.as_deref().unwrap_or(&b"[]"[..]),
+                ) {
+                    Ok(params) => params.into_iter().map(json_to_store_value).collect(),
+                    Err(err) => {
+                        return Err(
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters).reason(err)
+                        )
+                    }
+                };
+
+                let rows = self
+                    .lookup_query(decode_path_element(id).as_ref(), params)
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": rows
+                        .rows
+                        .iter()
+                        .map(|row| row.values.iter().map(store_value_to_json).collect::<Vec<_>>())
+                        .collect::<Vec<_>>(),
+                }))
+                .into_http_response())
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
@@ -303,6 +487,30 @@ impl ManageStore for Server {
     }
 }
 
+fn json_to_store_value(value: serde_json::Value) -> StoreValue<'static> {
+    match value {
+        serde_json::Value::Null => StoreValue::Null,
+        serde_json::Value::Bool(value) => StoreValue::Bool(value),
+        serde_json::Value::Number(value) => value
+            .as_i64()
+            .map(StoreValue::Integer)
+            .unwrap_or_else(|| StoreValue::Float(value.as_f64().unwrap_or_default())),
+        serde_json::Value::String(value) => StoreValue::Text(value.into()),
+        value => StoreValue::Text(value.to_string().into()),
+    }
+}
+
+fn store_value_to_json(value: &StoreValue<'_>) -> serde_json::Value {
+    match value {
+        StoreValue::Integer(value) => json!(value),
+        StoreValue::Bool(value) => json!(value),
+        StoreValue::Float(value) => json!(value),
+        StoreValue::Text(value) => json!(value),
+        StoreValue::Blob(value) => json!(String::from_utf8_lossy(value)),
+        StoreValue::Null => serde_json::Value::Null,
+    }
+}
+
 pub async fn reset_imap_uids(server: &Server, account_id: u32) -> trc::Result<(u32, u32)> {
     let mut mailbox_count = 0;
     let mut email_count = 0;
@@ -0,0 +1,153 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use directory::{
+    backend::internal::manage::{not_found, ManageDirectory},
+    tenant_quota_principals, Permission, Type, TENANT_QUOTA_OUTBOUND, TENANT_QUOTA_STORAGE,
+};
+use hyper::Method;
+use serde::Serialize;
+use serde_json::json;
+use trc::AddContext;
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+use super::decode_path_element;
+use std::future::Future;
+
+const TENANT_MEMBER_TYPES: [Type; 8] = [
+    Type::Individual,
+    Type::Group,
+    Type::Resource,
+    Type::Location,
+    Type::List,
+    Type::Domain,
+    Type::Role,
+    Type::ApiKey,
+];
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantUsage {
+    pub id: u32,
+    pub name: String,
+    pub storage: UsageEntry,
+    pub outbound_today: UsageEntry,
+    pub principals: Vec<PrincipalUsage>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageEntry {
+    pub used: u64,
+    pub limit: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrincipalUsage {
+    #[serde(rename = "type")]
+    pub typ: Type,
+    pub used: u64,
+    pub limit: u64,
+}
+
+pub trait TenantManager: Sync + Send {
+    fn handle_manage_tenant(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn tenant_usage(&self, tenant_id: u32)
+        -> impl Future<Output = trc::Result<TenantUsage>> + Send;
+}
+
+impl TenantManager for Server {
+    async fn handle_manage_tenant(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        if req.method() != Method::GET {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        // Validate the access token
+        access_token.assert_has_permission(Permission::TenantGet)?;
+
+        let tenant_id = match (path.get(1), access_token.tenant) {
+            (Some(name), tenant) => {
+                let name = decode_path_element(name);
+                let tenant_id = self
+                    .store()
+                    .get_principal_info(name.as_ref())
+                    .await
+                    .caused_by(trc::location!())?
+                    .filter(|p| p.typ == Type::Tenant)
+                    .ok_or_else(|| not_found(name.to_string()))?
+                    .id;
+
+                // Tenant administrators can only view their own tenant
+                if tenant.is_some_and(|t| t.id != tenant_id) {
+                    return Err(not_found(name.to_string()));
+                }
+
+                tenant_id
+            }
+            (None, Some(tenant)) => tenant.id,
+            (None, None) => {
+                return Err(trc::ManageEvent::MissingParameter
+                    .into_err()
+                    .details("Tenant name is required for accounts without a tenant"));
+            }
+        };
+
+        Ok(JsonResponse::new(json!({
+            "data": self.tenant_usage(tenant_id).await?,
+        }))
+        .into_http_response())
+    }
+
+    async fn tenant_usage(&self, tenant_id: u32) -> trc::Result<TenantUsage> {
+        let tenant = self
+            .store()
+            .get_principal(tenant_id)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| not_found(tenant_id.to_string()))?;
+
+        let mut principals = Vec::with_capacity(TENANT_MEMBER_TYPES.len());
+        for typ in TENANT_MEMBER_TYPES {
+            principals.push(PrincipalUsage {
+                typ,
+                used: self
+                    .store()
+                    .count_principals(None, typ.into(), tenant_id.into())
+                    .await
+                    .caused_by(trc::location!())?,
+                limit: tenant.tenant_quota(tenant_quota_principals(typ)),
+            });
+        }
+
+        Ok(TenantUsage {
+            id: tenant_id,
+            name: tenant.name().to_string(),
+            storage: UsageEntry {
+                used: self.get_used_quota(tenant_id).await?.max(0) as u64,
+                limit: tenant.tenant_quota(TENANT_QUOTA_STORAGE),
+            },
+            outbound_today: UsageEntry {
+                used: self.get_tenant_outbound_count(tenant_id).await?,
+                limit: tenant.tenant_quota(TENANT_QUOTA_OUTBOUND),
+            },
+            principals,
+        })
+    }
+}
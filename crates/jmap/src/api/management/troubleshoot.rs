@@ -12,10 +12,24 @@ use std::{
 
 use common::{
     auth::{oauth::GrantType, AccessToken},
-    config::smtp::resolver::{Policy, Tlsa},
-    psl, Server,
+    config::smtp::{
+        resolver::{Policy, Tlsa},
+        session::{AddressMapping, Mechanism},
+    },
+    expr::{
+        functions::ResolveVariable, Variable, V_AUTHENTICATED_AS, V_HELO_DOMAIN, V_LISTENER,
+        V_LOCAL_PORT, V_PROTOCOL, V_RECIPIENT, V_RECIPIENT_DOMAIN, V_REMOTE_IP, V_SENDER,
+        V_SENDER_DOMAIN, V_TLS,
+    },
+    psl, Server, KV_STORE_BENCHMARK,
+};
+use directory::{
+    backend::internal::{
+        manage::{self, ManageDirectory},
+        PrincipalField,
+    },
+    Permission, Type,
 };
-use directory::backend::internal::manage;
 use http_body_util::{combinators::BoxBody, StreamBody};
 use hyper::{
     body::{Bytes, Frame},
@@ -36,7 +50,14 @@ use smtp::outbound::{
     lookup::{DnsLookup, ToNextHop},
     mta_sts::{lookup::MtaStsLookup, verify::VerifyPolicy},
 };
+use smtp_proto::{AUTH_LOGIN, AUTH_PLAIN};
+use store::{
+    dispatch::lookup::KeyValue,
+    write::{key::KeySerializer, now, BatchBuilder, InMemoryClass, ValueClass},
+    IterateParams, ValueKey, U64_LEN,
+};
 use tokio::{io::AsyncWriteExt, sync::mpsc};
+use trc::AddContext;
 use utils::url_params::UrlParams;
 
 use crate::api::{
@@ -96,6 +117,7 @@ impl TroubleshootApi for Server {
                     content_type: "text/event-stream".into(),
                     content_disposition: "".into(),
                     cache_control: "no-store".into(),
+                    etag: "".into(),
                     body: HttpResponseBody::Stream(BoxBody::new(StreamBody::new(
                         async_stream::stream! {
                             while let Some(stage) = rx.recv().await {
@@ -125,6 +147,33 @@ impl TroubleshootApi for Server {
                 }))
                 .into_http_response())
             }
+            ("audit", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": config_audit(self, access_token.tenant.map(|t| t.id)).await?,
+                }))
+                .into_http_response())
+            }
+            ("store", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                let iterations = params
+                    .parse::<usize>("iterations")
+                    .unwrap_or(100)
+                    .clamp(1, 10_000);
+                let value_size = params
+                    .parse::<usize>("size")
+                    .unwrap_or(1024)
+                    .clamp(1, 1024 * 1024);
+
+                Ok(JsonResponse::new(json!({
+                    "data": store_benchmark(self, iterations, value_size).await?,
+                }))
+                .into_http_response())
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
@@ -1203,3 +1252,451 @@ impl From<DmarcPolicy> for dmarc::Policy {
         }
     }
 }
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuditFinding {
+    id: &'static str,
+    severity: AuditSeverity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    description: String,
+    remediation: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+enum AuditSeverity {
+    Critical,
+    Warning,
+    Info,
+}
+
+// Simulates an unauthenticated SMTP session from an untrusted network
+struct AuditSession {
+    remote_ip: &'static str,
+    local_port: u16,
+    is_tls: bool,
+}
+
+impl ResolveVariable for AuditSession {
+    fn resolve_variable(&self, variable: u32) -> Variable<'_> {
+        match variable {
+            V_RECIPIENT => "audit@example.org".into(),
+            V_RECIPIENT_DOMAIN => "example.org".into(),
+            V_SENDER => "audit@example.net".into(),
+            V_SENDER_DOMAIN | V_HELO_DOMAIN => "example.net".into(),
+            V_AUTHENTICATED_AS => "".into(),
+            V_LISTENER | V_PROTOCOL => "smtp".into(),
+            V_REMOTE_IP => self.remote_ip.into(),
+            V_LOCAL_PORT => self.local_port.into(),
+            V_TLS => self.is_tls.into(),
+            _ => "".into(),
+        }
+    }
+
+    fn resolve_global(&self, _: &str) -> Variable<'_> {
+        Variable::Integer(0)
+    }
+}
+
+async fn config_audit(server: &Server, tenant_id: Option<u32>) -> trc::Result<Vec<AuditFinding>> {
+    let mut findings = Vec::new();
+    let session = &server.core.smtp.session;
+
+    // Relaying for unauthenticated clients on untrusted networks
+    for remote_ip in ["192.0.2.1", "2001:db8::1"] {
+        if server
+            .eval_if::<bool, _>(
+                &session.rcpt.relay,
+                &AuditSession {
+                    remote_ip,
+                    local_port: 25,
+                    is_tls: false,
+                },
+                0,
+            )
+            .await
+            .unwrap_or(false)
+        {
+            findings.push(AuditFinding {
+                id: "open-relay",
+                severity: AuditSeverity::Critical,
+                target: Some(remote_ip.to_string()),
+                description: "Unauthenticated clients from untrusted networks are allowed to relay messages to remote domains.".to_string(),
+                remediation: "Restrict 'session.rcpt.relay' to authenticated users or trusted networks.".to_string(),
+            });
+            break;
+        }
+    }
+
+    // Plain text authentication over unencrypted connections
+    let mechanisms: u64 = server
+        .eval_if::<Mechanism, _>(
+            &session.auth.mechanisms,
+            &AuditSession {
+                remote_ip: "192.0.2.1",
+                local_port: 587,
+                is_tls: false,
+            },
+            0,
+        )
+        .await
+        .unwrap_or_default()
+        .into();
+    if mechanisms & (AUTH_PLAIN | AUTH_LOGIN) != 0 {
+        findings.push(AuditFinding {
+            id: "smtp-plain-text-auth",
+            severity: AuditSeverity::Warning,
+            target: None,
+            description: "SMTP clients may send credentials over unencrypted connections."
+                .to_string(),
+            remediation:
+                "Only offer PLAIN and LOGIN in 'session.auth.mechanisms' when 'is_tls' is true."
+                    .to_string(),
+        });
+    }
+    if server.core.imap.allow_plain_auth {
+        findings.push(AuditFinding {
+            id: "imap-plain-text-auth",
+            severity: AuditSeverity::Warning,
+            target: None,
+            description: "IMAP clients may send credentials over unencrypted connections."
+                .to_string(),
+            remediation: "Set 'imap.auth.allow-plain-text' to false.".to_string(),
+        });
+    }
+
+    // TLS certificates
+    if server.inner.data.tls_certificates.load().is_empty() {
+        findings.push(AuditFinding {
+            id: "no-tls-certificate",
+            severity: AuditSeverity::Critical,
+            target: None,
+            description: "No TLS certificates are configured, clients will be presented a self-signed certificate.".to_string(),
+            remediation: "Add a certificate under 'certificate.<id>' or enable ACME.".to_string(),
+        });
+    }
+
+    // Management interface exposed without authentication rate limits
+    if !server.has_auth_fail2ban() {
+        for (id, listener) in server
+            .core
+            .storage
+            .config
+            .group("server.listener.", ".protocol")
+            .await?
+        {
+            if listener.get("protocol").is_some_and(|p| p == "http")
+                && listener
+                    .iter()
+                    .filter(|(key, _)| *key == "bind" || key.starts_with("bind."))
+                    .filter_map(|(_, value)| value.parse::<SocketAddr>().ok())
+                    .any(|addr| !addr.ip().is_loopback())
+            {
+                findings.push(AuditFinding {
+                    id: "management-no-auth-limit",
+                    severity: AuditSeverity::Critical,
+                    target: Some(id),
+                    description: "The management interface is reachable from the network and failed logins are not rate limited.".to_string(),
+                    remediation: "Configure 'server.auto-ban.auth.rate' or bind the HTTP listener to a loopback address.".to_string(),
+                });
+            }
+        }
+    }
+
+    // Local domains
+    let mut signature_domains = Vec::new();
+    for (key, value) in server.core.storage.config.list("signature.", true).await? {
+        if key.ends_with(".domain") {
+            signature_domains.push(value.to_lowercase());
+        }
+    }
+    for principal in server
+        .core
+        .storage
+        .data
+        .list_principals(
+            None,
+            tenant_id,
+            &[Type::Domain],
+            &[PrincipalField::Name],
+            0,
+            0,
+        )
+        .await?
+        .items
+    {
+        let domain = principal.name().to_lowercase();
+
+        // SPF record
+        match server
+            .core
+            .smtp
+            .resolvers
+            .dns
+            .txt_raw_lookup(format!("{domain}."))
+            .await
+        {
+            Ok(record)
+                if std::str::from_utf8(&record)
+                    .is_ok_and(|record| record.to_ascii_lowercase().contains("v=spf1")) => {}
+            Ok(_) | Err(mail_auth::Error::DnsRecordNotFound(_)) => {
+                findings.push(AuditFinding {
+                    id: "missing-spf",
+                    severity: AuditSeverity::Warning,
+                    target: Some(domain.clone()),
+                    description: "The domain does not publish an SPF record.".to_string(),
+                    remediation: "Publish the SPF record listed in the domain's DNS records."
+                        .to_string(),
+                });
+            }
+            Err(err) => {
+                findings.push(AuditFinding {
+                    id: "spf-lookup-failed",
+                    severity: AuditSeverity::Info,
+                    target: Some(domain.clone()),
+                    description: format!("The SPF record could not be verified: {err}"),
+                    remediation: "Check that the domain resolves from this server.".to_string(),
+                });
+            }
+        }
+
+        // DKIM signatures
+        if !signature_domains.contains(&domain) {
+            findings.push(AuditFinding {
+                id: "missing-dkim",
+                severity: AuditSeverity::Warning,
+                target: Some(domain.clone()),
+                description: "No DKIM signature is configured for the domain.".to_string(),
+                remediation: "Create a DKIM signature for the domain and publish its public key."
+                    .to_string(),
+            });
+        }
+
+        // Catch-all recipients
+        if !matches!(session.rcpt.catch_all, AddressMapping::Disable)
+            && server
+                .email_to_id_or_catch_all(
+                    &server.core.storage.directory,
+                    &format!("nonexistent-audit-recipient@{domain}"),
+                    0,
+                )
+                .await?
+                .is_some_and(|(_, is_catch_all)| is_catch_all)
+        {
+            findings.push(AuditFinding {
+                id: "catch-all",
+                severity: AuditSeverity::Warning,
+                target: Some(domain),
+                description: "Messages addressed to any recipient at the domain are accepted, which attracts spam and backscatter.".to_string(),
+                remediation: "Remove the catch-all address from the domain unless it is required.".to_string(),
+            });
+        }
+    }
+
+    findings.sort_by(|a, b| a.severity.cmp(&b.severity));
+
+    Ok(findings)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreBenchmark {
+    iterations: usize,
+    value_size: usize,
+    point_read: LatencyStats,
+    range_scan: LatencyStats,
+    batch_write: LatencyStats,
+    blob_put: LatencyStats,
+    blob_get: LatencyStats,
+    in_memory_set: LatencyStats,
+    in_memory_get: LatencyStats,
+}
+
+// Latencies are reported in microseconds
+#[derive(Debug, Default, Serialize)]
+struct LatencyStats {
+    min: u64,
+    avg: u64,
+    p95: u64,
+    max: u64,
+}
+
+const BENCHMARK_BATCH_SIZE: usize = 10;
+
+// Runs a standard set of operations against the configured stores, keys are
+// written with an expiration so they are purged even if the benchmark fails.
+async fn store_benchmark(
+    server: &Server,
+    iterations: usize,
+    value_size: usize,
+) -> trc::Result<StoreBenchmark> {
+    let store = server.store();
+    let blob_store = server.blob_store();
+    let in_memory = server.in_memory_store();
+    let value = vec![b'a'; value_size];
+    let key = |id: usize| {
+        KeyValue::<()>::build_key(KV_STORE_BENCHMARK, (id as u64).to_be_bytes().as_slice())
+    };
+    let bench_value = KeySerializer::new(value_size + U64_LEN)
+        .write(now() + 3600)
+        .write(value.as_slice())
+        .finalize();
+
+    let mut batch_write = Vec::with_capacity(iterations);
+    let mut point_read = Vec::with_capacity(iterations);
+    let mut range_scan = Vec::with_capacity(iterations);
+    let mut blob_put = Vec::with_capacity(iterations);
+    let mut blob_get = Vec::with_capacity(iterations);
+    let mut in_memory_set = Vec::with_capacity(iterations);
+    let mut in_memory_get = Vec::with_capacity(iterations);
+
+    // Batch writes
+    for iteration in 0..iterations {
+        let mut batch = BatchBuilder::new();
+        for id in 0..BENCHMARK_BATCH_SIZE {
+            batch.set(
+                ValueClass::InMemory(InMemoryClass::Key(key(
+                    iteration * BENCHMARK_BATCH_SIZE + id
+                ))),
+                bench_value.clone(),
+            );
+        }
+        let time = Instant::now();
+        store
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+        batch_write.push(time.elapsed());
+    }
+    let total_keys = iterations * BENCHMARK_BATCH_SIZE;
+
+    // Point reads
+    for iteration in 0..iterations {
+        let time = Instant::now();
+        store
+            .get_value::<Vec<u8>>(ValueKey::from(ValueClass::InMemory(InMemoryClass::Key(
+                key((iteration * 7919) % total_keys),
+            ))))
+            .await
+            .caused_by(trc::location!())?;
+        point_read.push(time.elapsed());
+    }
+
+    // Range scans
+    let from_key = ValueKey::from(ValueClass::InMemory(InMemoryClass::Key(key(0))));
+    let to_key = ValueKey::from(ValueClass::InMemory(InMemoryClass::Key(key(total_keys))));
+    for _ in 0..iterations {
+        let time = Instant::now();
+        store
+            .iterate(
+                IterateParams::new(from_key.clone(), to_key.clone()).ascending(),
+                |_, _| Ok(true),
+            )
+            .await
+            .caused_by(trc::location!())?;
+        range_scan.push(time.elapsed());
+    }
+
+    store
+        .delete_range(from_key, to_key)
+        .await
+        .caused_by(trc::location!())?;
+
+    // Blob put and get
+    let mut blob_keys = Vec::with_capacity(iterations);
+    for iteration in 0..iterations {
+        let blob_key = format!("troubleshoot-benchmark-{}-{iteration}", now()).into_bytes();
+        let time = Instant::now();
+        let result = blob_store
+            .put_blob(&blob_key, &value)
+            .await
+            .caused_by(trc::location!());
+        blob_put.push(time.elapsed());
+        blob_keys.push(blob_key);
+        if let Err(err) = result {
+            delete_benchmark_blobs(server, &blob_keys).await;
+            return Err(err);
+        }
+    }
+    for blob_key in &blob_keys {
+        let time = Instant::now();
+        let result = blob_store
+            .get_blob(blob_key, 0..usize::MAX)
+            .await
+            .caused_by(trc::location!());
+        blob_get.push(time.elapsed());
+        if let Err(err) = result {
+            delete_benchmark_blobs(server, &blob_keys).await;
+            return Err(err);
+        }
+    }
+    delete_benchmark_blobs(server, &blob_keys).await;
+
+    // In-memory store
+    for iteration in 0..iterations {
+        let time = Instant::now();
+        in_memory
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_STORE_BENCHMARK,
+                    (iteration as u64).to_be_bytes().as_slice(),
+                    value.clone(),
+                )
+                .expires(3600),
+            )
+            .await
+            .caused_by(trc::location!())?;
+        in_memory_set.push(time.elapsed());
+    }
+    for iteration in 0..iterations {
+        let time = Instant::now();
+        in_memory
+            .key_get::<String>(key(iteration))
+            .await
+            .caused_by(trc::location!())?;
+        in_memory_get.push(time.elapsed());
+    }
+    in_memory
+        .key_delete_prefix(&[KV_STORE_BENCHMARK])
+        .await
+        .caused_by(trc::location!())?;
+
+    Ok(StoreBenchmark {
+        iterations,
+        value_size,
+        point_read: LatencyStats::new(point_read),
+        range_scan: LatencyStats::new(range_scan),
+        batch_write: LatencyStats::new(batch_write),
+        blob_put: LatencyStats::new(blob_put),
+        blob_get: LatencyStats::new(blob_get),
+        in_memory_set: LatencyStats::new(in_memory_set),
+        in_memory_get: LatencyStats::new(in_memory_get),
+    })
+}
+
+async fn delete_benchmark_blobs(server: &Server, blob_keys: &[Vec<u8>]) {
+    for blob_key in blob_keys {
+        if let Err(err) = server.blob_store().delete_blob(blob_key).await {
+            trc::error!(err.details("Failed to delete benchmark blob"));
+        }
+    }
+}
+
+impl LatencyStats {
+    fn new(mut samples: Vec<Duration>) -> Self {
+        if samples.is_empty() {
+            return LatencyStats::default();
+        }
+        samples.sort_unstable();
+        let total: Duration = samples.iter().sum();
+
+        LatencyStats {
+            min: samples[0].as_micros() as u64,
+            avg: (total / samples.len() as u32).as_micros() as u64,
+            p95: samples[(samples.len() * 95 / 100).min(samples.len() - 1)].as_micros() as u64,
+            max: samples[samples.len() - 1].as_micros() as u64,
+        }
+    }
+}
@@ -19,6 +19,7 @@ pub mod http;
 pub mod management;
 pub mod request;
 pub mod session;
+pub mod wkd;
 
 #[derive(Clone)]
 pub struct JmapSessionManager {
@@ -46,6 +47,10 @@ pub enum HttpResponseBody {
     Text(String),
     Binary(Vec<u8>),
     Stream(http_body_util::combinators::BoxBody<hyper::body::Bytes, hyper::Error>),
+    Download {
+        content_range: Option<String>,
+        body: http_body_util::combinators::BoxBody<hyper::body::Bytes, hyper::Error>,
+    },
     WebsocketUpgrade(String),
     Empty,
 }
@@ -55,6 +60,7 @@ pub struct HttpResponse {
     pub content_type: Cow<'static, str>,
     pub content_disposition: Cow<'static, str>,
     pub cache_control: Cow<'static, str>,
+    pub etag: Cow<'static, str>,
     pub body: HttpResponseBody,
 }
 
@@ -23,7 +23,7 @@ use crate::{
     changes::{get::ChangesLookup, query::QueryChanges},
     email::{
         copy::EmailCopy, get::EmailGet, import::EmailImport, parse::EmailParse, query::EmailQuery,
-        set::EmailSet, snippet::EmailSearchSnippet,
+        recall::EmailRecall, set::EmailSet, snippet::EmailSearchSnippet,
     },
     identity::{get::IdentityGet, set::IdentitySet},
     mailbox::{get::MailboxGet, query::MailboxQuery, set::MailboxSet},
@@ -35,7 +35,7 @@ use crate::{
         validate::SieveScriptValidate,
     },
     submission::{get::EmailSubmissionGet, query::EmailSubmissionQuery, set::EmailSubmissionSet},
-    thread::get::ThreadGet,
+    thread::{get::ThreadGet, query::ThreadQuery},
     vacation::{get::VacationResponseGet, set::VacationResponseSet},
 };
 
@@ -178,8 +178,25 @@ impl RequestHandler for Server {
         // Check permissions
         access_token.assert_has_jmap_permission(&method)?;
 
+        // Enforce per-account object limits
+        let mut get_max_objects = None;
+        match &method {
+            RequestMethod::Get(req) => {
+                if let Some(max_objects) = access_token.get_max_objects {
+                    req.validate(max_objects)?;
+                    get_max_objects = Some(max_objects);
+                }
+            }
+            RequestMethod::Set(req) => {
+                if let Some(max_objects) = access_token.set_max_objects {
+                    req.validate(max_objects)?;
+                }
+            }
+            _ => {}
+        }
+
         // Handle method
-        let response = match method {
+        let mut response = match method {
             RequestMethod::Get(mut req) => match req.take_arguments() {
                 get::RequestArguments::Email(arguments) => {
                     access_token.assert_has_access(req.account_id, Collection::Email)?;
@@ -268,6 +285,11 @@ impl RequestHandler for Server {
 
                     self.quota_query(req, access_token).await?.into()
                 }
+                query::RequestArguments::Thread => {
+                    access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                    self.thread_query(req, access_token).await?.into()
+                }
             },
             RequestMethod::Set(mut req) => match req.take_arguments() {
                 set::RequestArguments::Email => {
@@ -355,6 +377,11 @@ impl RequestHandler for Server {
 
                 self.blob_lookup(req).await?.into()
             }
+            RequestMethod::RecallEmail(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.email_recall(req, access_token).await?.into()
+            }
             RequestMethod::UploadBlob(req) => {
                 access_token.assert_is_member(req.account_id)?;
 
@@ -364,6 +391,13 @@ impl RequestHandler for Server {
             RequestMethod::Error(error) => return Err(error),
         };
 
+        // Fetching all objects is bounded by the global limit, apply the account one
+        if let (Some(max_objects), ResponseMethod::Get(get_response)) =
+            (get_max_objects, &mut response)
+        {
+            get_response.list.truncate(max_objects);
+        }
+
         trc::event!(
             Jmap(JmapEvent::MethodCall),
             Id = method_name,
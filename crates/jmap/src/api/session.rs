@@ -71,6 +71,19 @@ impl SessionHandler for Server {
             );
         }
 
+        // Remove capabilities disabled for this principal or its roles
+        for capability in [
+            Capability::Submission,
+            Capability::VacationResponse,
+            Capability::Sieve,
+            Capability::WebSocket,
+            Capability::Blob,
+        ] {
+            if !access_token.has_jmap_capability(capability) {
+                session.remove_capability(&capability);
+            }
+        }
+
         Ok(session)
     }
 }
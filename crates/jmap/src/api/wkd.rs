@@ -0,0 +1,150 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{manager::webadmin::Resource, Server};
+use email::crypto::{export_pgp_certs, EncryptionParams};
+use hyper::header;
+use jmap_proto::types::{collection::Collection, property::Property};
+use sha1::Digest;
+use trc::AddContext;
+use utils::url_params::UrlParams;
+
+use crate::api::http::ToHttpResponse;
+
+use super::{HttpRequest, HttpResponse};
+use std::future::Future;
+
+const ZBASE32_ALPHABET: &[u8; 32] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
+
+pub trait WebKeyDirectory: Sync + Send {
+    fn handle_wkd_request(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn openpgp_key(
+        &self,
+        account_id: u32,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<Option<Vec<u8>>>> + Send;
+}
+
+impl WebKeyDirectory for Server {
+    async fn handle_wkd_request(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+    ) -> trc::Result<HttpResponse> {
+        // The advanced method includes the domain name in the path,
+        // the direct method obtains it from the Host header.
+        let (domain, path) = match path.as_slice() {
+            [domain, rest @ ..] if !matches!(*domain, "hu" | "policy") => {
+                (domain.to_lowercase(), rest)
+            }
+            path => (
+                req.headers()
+                    .get(header::HOST)
+                    .and_then(|host| host.to_str().ok())
+                    .and_then(|host| host.split(':').next())
+                    .map(|host| host.strip_prefix("openpgpkey.").unwrap_or(host))
+                    .unwrap_or_default()
+                    .to_lowercase(),
+                path,
+            ),
+        };
+        if domain.is_empty() || !self.core.storage.directory.is_local_domain(&domain).await? {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        match path {
+            ["policy"] => Ok(Resource::new("text/plain", Vec::new()).into_http_response()),
+            ["hu", hash] => {
+                // Clients provide the local part as the hash cannot be reversed
+                let params = UrlParams::new(req.uri().query());
+                let local_part = params
+                    .get("l")
+                    .map(|l| l.to_lowercase())
+                    .filter(|l| wkd_hash(l) == *hash)
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                let address = format!("{local_part}@{domain}");
+
+                if let Some(account_id) = self
+                    .core
+                    .storage
+                    .directory
+                    .email_to_id(&address)
+                    .await
+                    .caused_by(trc::location!())?
+                    && let Some(key) = self.openpgp_key(account_id, &address).await?
+                {
+                    return Ok(Resource::new("application/octet-stream", key).into_http_response());
+                }
+
+                Err(trc::ResourceEvent::NotFound.into_err())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+
+    async fn openpgp_key(&self, account_id: u32, address: &str) -> trc::Result<Option<Vec<u8>>> {
+        self.get_property::<EncryptionParams>(
+            account_id,
+            Collection::Principal,
+            0,
+            Property::Parameters,
+        )
+        .await
+        .map(|params| params.and_then(|params| export_pgp_certs(&params, address)))
+    }
+}
+
+/// Hashed local part used by the Web Key Directory, the SHA-1 digest of the
+/// lowercased local part encoded using z-base-32.
+pub fn wkd_hash(local_part: &str) -> String {
+    let digest = sha1::Sha1::digest(local_part.as_bytes());
+    let mut result = String::with_capacity(32);
+    let mut buf = 0u32;
+    let mut bits = 0;
+
+    for byte in digest {
+        buf = (buf << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            result.push(ZBASE32_ALPHABET[((buf >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        result.push(ZBASE32_ALPHABET[((buf << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    result
+}
+
+/// Owner name of an OPENPGPKEY record as defined in RFC 7929, the SHA2-256
+/// digest of the local part truncated to 28 octets.
+pub fn openpgpkey_owner(local_part: &str) -> String {
+    sha2::Sha256::digest(local_part.as_bytes())[..28]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{openpgpkey_owner, wkd_hash};
+
+    #[test]
+    fn wkd_hashes() {
+        // Examples from draft-koch-openpgp-webkey-service and RFC 7929
+        assert_eq!(wkd_hash("joe.doe"), "iy9q119eutrkn8s1mk4r39qejnbu3n5q");
+        assert_eq!(
+            openpgpkey_owner("hugh"),
+            "c93f1e400f26708f98cb19d936620da35eec8f72e57f9eec01c1afd6"
+        );
+    }
+}
@@ -41,6 +41,11 @@ impl Authenticator for Server {
 
                 // Make sure the revision is still valid
                 if access_token.revision == http_cache.revision {
+                    self.inner
+                        .data
+                        .active_sessions
+                        .set_account_id(session.session_id, access_token.primary_id());
+
                     // Enforce authenticated rate limit
                     return self
                         .is_http_authenticated_request_allowed(&access_token)
@@ -63,6 +68,20 @@ impl Authenticator for Server {
                 self.is_http_anonymous_request_allowed(&session.remote_ip)
                     .await?;
 
+                // The bootstrap token grants administrator access to the management API
+                if allow_api_access && self.is_bootstrap_token(token) {
+                    trc::event!(
+                        Auth(trc::AuthEvent::Success),
+                        AccountName = "bootstrap",
+                        SpanId = session.session_id,
+                    );
+
+                    return self
+                        .bootstrap_access_token()
+                        .await
+                        .map(|token| (None, token));
+                }
+
                 decode_bearer_token(token, allow_api_access).ok_or_else(|| {
                     trc::AuthEvent::Error
                         .into_err()
@@ -91,14 +110,16 @@ impl Authenticator for Server {
                 ))
                 .await?;
 
-            // Cache credentials
-            self.inner.cache.http_auth.insert(
-                token.to_string(),
-                HttpAuthCache {
-                    account_id: access_token.primary_id(),
-                    revision: access_token.revision,
-                },
-            );
+            // Cache credentials, impersonated sessions are audited on every request
+            if !access_token.is_impersonated() {
+                self.inner.cache.http_auth.insert(
+                    token.to_string(),
+                    HttpAuthCache {
+                        account_id: access_token.primary_id(),
+                        revision: access_token.revision,
+                    },
+                );
+            }
 
             // Enforce authenticated rate limit
             self.is_http_authenticated_request_allowed(&access_token)
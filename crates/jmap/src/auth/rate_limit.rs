@@ -4,15 +4,16 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::net::IpAddr;
+use std::{net::IpAddr, time::Duration};
 
 use common::{
     ip_to_bytes,
     listener::limiter::{InFlight, LimiterResult},
-    Server, KV_RATE_LIMIT_HTTP_ANONYMOUS, KV_RATE_LIMIT_HTTP_AUTHENTICATED,
+    Server, KV_RATE_LIMIT_HTTP_ANONYMOUS, KV_RATE_LIMIT_HTTP_AUTHENTICATED, KV_RATE_LIMIT_UPLOAD,
 };
 use directory::Permission;
 use trc::AddContext;
+use utils::config::Rate;
 
 use common::auth::AccessToken;
 use std::future::Future;
@@ -27,6 +28,11 @@ pub trait RateLimiter: Sync + Send {
         addr: &IpAddr,
     ) -> impl Future<Output = trc::Result<()>> + Send;
     fn is_upload_allowed(&self, access_token: &AccessToken) -> trc::Result<Option<InFlight>>;
+    fn is_upload_bandwidth_allowed(
+        &self,
+        access_token: &AccessToken,
+        size: usize,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
 impl RateLimiter for Server {
@@ -106,4 +112,39 @@ impl RateLimiter for Server {
             LimiterResult::Disabled => Ok(None),
         }
     }
+
+    async fn is_upload_bandwidth_allowed(
+        &self,
+        access_token: &AccessToken,
+        size: usize,
+    ) -> trc::Result<()> {
+        if let Some(max_bandwidth) = access_token.upload_max_bandwidth {
+            if !access_token.has_permission(Permission::UnlimitedUploads)
+                && self
+                    .core
+                    .storage
+                    .lookup
+                    .is_rate_allowed_by(
+                        KV_RATE_LIMIT_UPLOAD,
+                        &access_token.primary_id.to_be_bytes(),
+                        &Rate {
+                            requests: max_bandwidth,
+                            period: Duration::from_secs(60),
+                        },
+                        size as u64,
+                        false,
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+                    .is_some()
+            {
+                return Err(trc::LimitEvent::TooManyRequests
+                    .into_err()
+                    .ctx(trc::Key::Size, size)
+                    .ctx(trc::Key::Limit, max_bandwidth));
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -7,6 +7,8 @@
 use std::ops::Range;
 
 use common::{auth::AccessToken, Server};
+use http_body_util::{combinators::BoxBody, StreamBody};
+use hyper::body::{Bytes, Frame};
 use jmap_proto::types::{
     acl::Acl,
     blob::{BlobId, BlobSection},
@@ -17,12 +19,16 @@ use mail_parser::{
     Encoding,
 };
 use std::future::Future;
-use store::BlobClass;
+use store::{BlobClass, CompressionAlgo};
 use trc::AddContext;
 use utils::BlobHash;
 
 use crate::auth::acl::AclMethods;
 
+use super::{BlobStream, ByteRange};
+
+const DOWNLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
 pub trait BlobDownload: Sync + Send {
     fn blob_download(
         &self,
@@ -30,6 +36,19 @@ pub trait BlobDownload: Sync + Send {
         access_token: &AccessToken,
     ) -> impl Future<Output = trc::Result<Option<Vec<u8>>>> + Send;
 
+    fn blob_download_stream(
+        &self,
+        blob_id: &BlobId,
+        access_token: &AccessToken,
+        range: Option<ByteRange>,
+    ) -> impl Future<Output = trc::Result<Option<BlobStream>>> + Send;
+
+    fn has_download_access(
+        &self,
+        blob_id: &BlobId,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
     fn get_blob_section(
         &self,
         hash: &BlobHash,
@@ -50,12 +69,190 @@ pub trait BlobDownload: Sync + Send {
 }
 
 impl BlobDownload for Server {
-    #[allow(clippy::blocks_in_conditions)]
     async fn blob_download(
         &self,
         blob_id: &BlobId,
         access_token: &AccessToken,
     ) -> trc::Result<Option<Vec<u8>>> {
+        if !self.has_download_access(blob_id, access_token).await? {
+            return Ok(None);
+        }
+
+        if let Some(section) = &blob_id.section {
+            self.get_blob_section(&blob_id.hash, section).await
+        } else {
+            self.get_blob(&blob_id.hash, 0..usize::MAX).await
+        }
+    }
+
+    async fn blob_download_stream(
+        &self,
+        blob_id: &BlobId,
+        access_token: &AccessToken,
+        range: Option<ByteRange>,
+    ) -> trc::Result<Option<BlobStream>> {
+        if !self.has_download_access(blob_id, access_token).await? {
+            return Ok(None);
+        }
+
+        let (offset, size, encoding) = match &blob_id.section {
+            Some(section) => (
+                section.offset_start,
+                Some(section.size),
+                Encoding::from(section.encoding),
+            ),
+            None => (0, None, Encoding::None),
+        };
+
+        // Compressed blobs and quoted-printable sections can't be read in chunks
+        if !matches!(self.core.storage.blob.compression, CompressionAlgo::None)
+            || matches!(encoding, Encoding::QuotedPrintable)
+        {
+            return self
+                .blob_download(blob_id, access_token)
+                .await
+                .map(|bytes| bytes.map(|bytes| BlobStream::from_bytes(bytes, range)));
+        }
+
+        // Fetch the first chunk, small blobs are served from memory
+        let chunk_size = size.unwrap_or(usize::MAX).min(DOWNLOAD_CHUNK_SIZE);
+        let chunk = if let Some(chunk) = self
+            .get_blob(&blob_id.hash, offset..offset.saturating_add(chunk_size))
+            .await?
+        {
+            chunk
+        } else {
+            return Ok(None);
+        };
+        if chunk.len() < DOWNLOAD_CHUNK_SIZE || size.is_some_and(|size| size <= chunk.len()) {
+            let bytes = match encoding {
+                Encoding::Base64 => base64_decode(&chunk).unwrap_or_default(),
+                _ => chunk,
+            };
+            return Ok(Some(BlobStream::from_bytes(bytes, range)));
+        }
+
+        let server = self.clone();
+        let hash = blob_id.hash.clone();
+        let end = size.map_or(usize::MAX, |size| offset.saturating_add(size));
+
+        if matches!(encoding, Encoding::None) {
+            // The content length is only known for sections
+            let (start, end, content_range) = match (range, size) {
+                (Some(range), Some(size)) => match range.resolve(size) {
+                    Some(range) => (
+                        offset + range.start,
+                        offset + range.end,
+                        Some(format!("bytes {}-{}/{size}", range.start, range.end - 1)),
+                    ),
+                    None => (offset, end, None),
+                },
+                _ => (offset, end, None),
+            };
+            let mut first_chunk = (start == offset).then_some(chunk);
+
+            Ok(Some(BlobStream {
+                content_range,
+                body: BoxBody::new(StreamBody::new(async_stream::stream! {
+                    let mut offset = start;
+
+                    while offset < end {
+                        let chunk_end = offset.saturating_add(DOWNLOAD_CHUNK_SIZE).min(end);
+                        let chunk = match first_chunk.take() {
+                            Some(mut chunk) => {
+                                chunk.truncate(chunk_end - offset);
+                                chunk
+                            }
+                            None => match server.get_blob(&hash, offset..chunk_end).await {
+                                Ok(Some(chunk)) => chunk,
+                                Ok(None) => break,
+                                Err(err) => {
+                                    trc::error!(err
+                                        .details("Failed to stream blob")
+                                        .caused_by(trc::location!()));
+                                    break;
+                                }
+                            },
+                        };
+                        let is_last = chunk.len() < chunk_end - offset;
+                        offset += chunk.len();
+
+                        if !chunk.is_empty() {
+                            yield Ok(Frame::data(Bytes::from(chunk)));
+                        }
+                        if is_last {
+                            break;
+                        }
+                    }
+                })),
+            }))
+        } else {
+            // Base64 encoded sections are decoded on the fly, ranges are not supported
+            Ok(Some(BlobStream {
+                content_range: None,
+                body: BoxBody::new(StreamBody::new(async_stream::stream! {
+                    let mut offset = offset + chunk.len();
+                    let mut is_last = offset >= end;
+                    let mut pending = Vec::with_capacity(DOWNLOAD_CHUNK_SIZE);
+                    let mut chunk = chunk;
+
+                    loop {
+                        pending.extend(
+                            chunk
+                                .iter()
+                                .copied()
+                                .filter(|ch| ch.is_ascii_alphanumeric() || matches!(ch, b'+' | b'/' | b'=')),
+                        );
+                        let decode_len = if is_last {
+                            pending.len()
+                        } else {
+                            pending.len() & !3
+                        };
+                        if decode_len > 0 {
+                            match base64_decode(&pending[..decode_len]) {
+                                Some(bytes) => {
+                                    yield Ok(Frame::data(Bytes::from(bytes)));
+                                }
+                                None => {
+                                    trc::event!(
+                                        Store(trc::StoreEvent::DataCorruption),
+                                        Details = "Failed to decode base64 blob section",
+                                        CausedBy = trc::location!(),
+                                    );
+                                    break;
+                                }
+                            }
+                            pending.drain(..decode_len);
+                        }
+                        if is_last {
+                            break;
+                        }
+
+                        let chunk_end = offset.saturating_add(DOWNLOAD_CHUNK_SIZE).min(end);
+                        chunk = match server.get_blob(&hash, offset..chunk_end).await {
+                            Ok(Some(chunk)) => chunk,
+                            Ok(None) => break,
+                            Err(err) => {
+                                trc::error!(err
+                                    .details("Failed to stream blob")
+                                    .caused_by(trc::location!()));
+                                break;
+                            }
+                        };
+                        is_last = chunk.len() < chunk_end - offset || chunk_end >= end;
+                        offset += chunk.len();
+                    }
+                })),
+            }))
+        }
+    }
+
+    #[allow(clippy::blocks_in_conditions)]
+    async fn has_download_access(
+        &self,
+        blob_id: &BlobId,
+        access_token: &AccessToken,
+    ) -> trc::Result<bool> {
         if !self
             .core
             .storage
@@ -64,7 +261,7 @@ impl BlobDownload for Server {
             .await
             .caused_by(trc::location!())?
         {
-            return Ok(None);
+            return Ok(false);
         }
 
         if !access_token.is_member(blob_id.class.account_id()) {
@@ -80,7 +277,7 @@ impl BlobDownload for Server {
                             .await
                         {
                             Ok(shared_messages) if shared_messages.contains(*document_id) => (),
-                            _ => return Ok(None),
+                            _ => return Ok(false),
                         }
                     } else {
                         match self
@@ -94,21 +291,17 @@ impl BlobDownload for Server {
                             .await
                         {
                             Ok(has_access) if has_access => (),
-                            _ => return Ok(None),
+                            _ => return Ok(false),
                         }
                     }
                 }
                 BlobClass::Reserved { .. } => {
-                    return Ok(None);
+                    return Ok(false);
                 }
             }
         }
 
-        if let Some(section) = &blob_id.section {
-            self.get_blob_section(&blob_id.hash, section).await
-        } else {
-            self.get_blob(&blob_id.hash, 0..usize::MAX).await
-        }
+        Ok(true)
     }
 
     async fn get_blob_section(
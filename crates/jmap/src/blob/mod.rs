@@ -4,6 +4,10 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::ops::Range;
+
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::{body::Bytes, header, HeaderMap};
 use jmap_proto::types::{blob::BlobId, id::Id};
 
 pub mod copy;
@@ -25,5 +29,196 @@ pub struct UploadResponse {
 pub struct DownloadResponse {
     pub filename: String,
     pub content_type: String,
-    pub blob: Vec<u8>,
+    pub etag: String,
+    pub blob: BlobStream,
+}
+
+pub struct BlobStream {
+    pub content_range: Option<String>,
+    pub body: BoxBody<Bytes, hyper::Error>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: Option<usize>,
+    pub end: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition {
+    Passed,
+    NotModified,
+    Failed,
+}
+
+impl UploadResponse {
+    pub fn etag(&self) -> String {
+        blob_etag(&self.blob_id)
+    }
+}
+
+// Blob ids are derived from the blob hash, so their contents never change
+pub fn blob_etag(blob_id: &BlobId) -> String {
+    format!("\"{blob_id}\"")
+}
+
+impl BlobStream {
+    pub fn from_bytes(mut bytes: Vec<u8>, range: Option<ByteRange>) -> Self {
+        let content_range = if let Some(range) = range.and_then(|r| r.resolve(bytes.len())) {
+            let content_range = format!("bytes {}-{}/{}", range.start, range.end - 1, bytes.len());
+            bytes.truncate(range.end);
+            bytes.drain(..range.start);
+            Some(content_range)
+        } else {
+            None
+        };
+
+        BlobStream {
+            content_range,
+            body: Full::new(Bytes::from(bytes))
+                .map_err(|never| match never {})
+                .boxed(),
+        }
+    }
+}
+
+impl ByteRange {
+    // Only single byte ranges are supported
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+        let start = if !start.is_empty() {
+            Some(start.parse().ok()?)
+        } else {
+            None
+        };
+        let end = if !end.is_empty() {
+            Some(end.parse().ok()?)
+        } else {
+            None
+        };
+
+        (start.is_some() || end.is_some()).then_some(ByteRange { start, end })
+    }
+
+    pub fn resolve(&self, size: usize) -> Option<Range<usize>> {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) if start <= end && start < size => {
+                Some(start..(end + 1).min(size))
+            }
+            (Some(start), None) if start < size => Some(start..size),
+            (None, Some(suffix)) if suffix > 0 && size > 0 => {
+                Some(size.saturating_sub(suffix)..size)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Precondition {
+    // Evaluates If-Match and If-None-Match as described in RFC 9110, Section 13.2.2
+    pub fn evaluate(headers: &HeaderMap, etag: &str) -> Self {
+        if let Some(if_match) = headers.get(header::IF_MATCH).and_then(|h| h.to_str().ok())
+            && !etag_matches(if_match, etag, false)
+        {
+            return Precondition::Failed;
+        }
+
+        if let Some(if_none_match) = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|h| h.to_str().ok())
+            && etag_matches(if_none_match, etag, true)
+        {
+            return Precondition::NotModified;
+        }
+
+        Precondition::Passed
+    }
+
+    // Range requests are only honoured if the If-Range validator is current
+    pub fn is_range_valid(headers: &HeaderMap, etag: &str) -> bool {
+        headers
+            .get(header::IF_RANGE)
+            .and_then(|h| h.to_str().ok())
+            .is_none_or(|if_range| if_range.trim() == etag)
+    }
+}
+
+fn etag_matches(header: &str, etag: &str, weak: bool) -> bool {
+    header.split(',').map(|tag| tag.trim()).any(|tag| {
+        tag == "*" || tag == etag || (weak && tag.strip_prefix("W/").is_some_and(|tag| tag == etag))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{header, HeaderMap};
+
+    use super::{ByteRange, Precondition};
+
+    #[test]
+    fn parse_byte_range() {
+        for (value, size, expected) in [
+            ("bytes=0-99", 1000, Some(0..100)),
+            ("bytes=900-", 1000, Some(900..1000)),
+            ("bytes=-100", 1000, Some(900..1000)),
+            ("bytes=500-2000", 1000, Some(500..1000)),
+            ("bytes=1000-", 1000, None),
+            ("bytes=-0", 1000, None),
+            ("bytes=10-5", 1000, None),
+        ] {
+            assert_eq!(
+                ByteRange::parse(value).and_then(|r| r.resolve(size)),
+                expected,
+                "{value}"
+            );
+        }
+
+        for value in [
+            "bytes=",
+            "bytes=-",
+            "bytes=a-b",
+            "items=0-10",
+            "bytes=0-1,5-6",
+        ] {
+            assert_eq!(ByteRange::parse(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn evaluate_preconditions() {
+        let etag = "\"abc\"";
+
+        for (name, value, expected) in [
+            (header::IF_NONE_MATCH, "\"abc\"", Precondition::NotModified),
+            (
+                header::IF_NONE_MATCH,
+                "W/\"abc\"",
+                Precondition::NotModified,
+            ),
+            (
+                header::IF_NONE_MATCH,
+                "\"xyz\", \"abc\"",
+                Precondition::NotModified,
+            ),
+            (header::IF_NONE_MATCH, "*", Precondition::NotModified),
+            (header::IF_NONE_MATCH, "\"xyz\"", Precondition::Passed),
+            (header::IF_MATCH, "\"abc\"", Precondition::Passed),
+            (header::IF_MATCH, "*", Precondition::Passed),
+            (header::IF_MATCH, "W/\"abc\"", Precondition::Failed),
+            (header::IF_MATCH, "\"xyz\"", Precondition::Failed),
+        ] {
+            let mut headers = HeaderMap::new();
+            headers.insert(name.clone(), value.parse().unwrap());
+            assert_eq!(
+                Precondition::evaluate(&headers, etag),
+                expected,
+                "{name}: {value}"
+            );
+        }
+
+        let mut headers = HeaderMap::new();
+        assert!(Precondition::is_range_valid(&headers, etag));
+        headers.insert(header::IF_RANGE, "\"xyz\"".parse().unwrap());
+        assert!(!Precondition::is_range_valid(&headers, etag));
+    }
 }
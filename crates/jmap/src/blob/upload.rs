@@ -57,7 +57,11 @@ impl BlobUpload for Server {
         };
         let account_id = request.account_id.document_id();
 
-        if request.create.len() > self.core.jmap.set_max_objects {
+        if request.create.len()
+            > access_token
+                .set_max_objects
+                .unwrap_or(self.core.jmap.set_max_objects)
+        {
             return Err(trc::JmapEvent::RequestTooLarge.into_err());
         }
 
@@ -151,6 +155,23 @@ impl BlobUpload for Server {
                 continue 'outer;
             }
 
+            // Limit upload bandwidth
+            if let Err(err) = self
+                .is_upload_bandwidth_allowed(access_token, data.len())
+                .await
+            {
+                if err.matches(trc::EventType::Limit(trc::LimitEvent::TooManyRequests)) {
+                    response.not_created.append(
+                        create_id,
+                        SetError::over_quota()
+                            .with_description("Upload bandwidth limit exceeded, try again later."),
+                    );
+                    continue 'outer;
+                } else {
+                    return Err(err.caused_by(trc::location!()));
+                }
+            }
+
             // Enforce quota
             let used = self
                 .core
@@ -203,6 +224,11 @@ impl BlobUpload for Server {
             .is_upload_allowed(&access_token)
             .caused_by(trc::location!())?;
 
+        // Limit upload bandwidth
+        self.is_upload_bandwidth_allowed(&access_token, data.len())
+            .await
+            .caused_by(trc::location!())?;
+
         #[cfg(feature = "test_mode")]
         {
             // Used for concurrent upload tests
@@ -0,0 +1,258 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    future::Future,
+    io::{Cursor, Write},
+};
+
+use common::Server;
+use email::{
+    mailbox::{UidMailbox, TOMBSTONE_ID},
+    metadata::MessageMetadata,
+};
+use jmap_proto::{
+    object::Object,
+    types::{collection::Collection, id::Id, keyword::Keyword, property::Property, value::Value},
+};
+use mail_parser::DateTime;
+use store::{ahash::AHashMap, roaring::RoaringBitmap, write::Bincode};
+use trc::{AddContext, StoreEvent};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::blob::download::BlobDownload;
+
+/// A zip archive of raw messages along with a JSON manifest describing
+/// where each message was found.
+pub struct MessageArchive {
+    zip: ZipWriter<Cursor<Vec<u8>>>,
+    manifest: Vec<ArchivedMessage>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedMessage {
+    pub path: String,
+    pub account: String,
+    pub id: Id,
+    pub mailboxes: Vec<String>,
+    pub keywords: Vec<String>,
+    pub received_at: String,
+    pub size: usize,
+    pub deleted: bool,
+}
+
+pub trait EmailArchive: Sync + Send {
+    fn email_archive(
+        &self,
+        archive: &mut MessageArchive,
+        account_id: u32,
+        account_name: &str,
+        document_ids: RoaringBitmap,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn mailbox_paths(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<AHashMap<u32, String>>> + Send;
+}
+
+impl EmailArchive for Server {
+    async fn email_archive(
+        &self,
+        archive: &mut MessageArchive,
+        account_id: u32,
+        account_name: &str,
+        document_ids: RoaringBitmap,
+    ) -> trc::Result<()> {
+        let mailbox_paths = self.mailbox_paths(account_id).await?;
+
+        for document_id in document_ids {
+            let Some(metadata) = self
+                .get_property::<Bincode<MessageMetadata>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    &Property::BodyStructure,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .map(|metadata| metadata.inner)
+            else {
+                continue;
+            };
+            let Some(raw_message) = self
+                .get_blob(&metadata.blob_hash, 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())?
+            else {
+                trc::event!(
+                    Store(StoreEvent::NotFound),
+                    AccountId = account_id,
+                    DocumentId = document_id,
+                    Collection = Collection::Email,
+                    BlobId = metadata.blob_hash.to_hex(),
+                    Details = "Blob not found.",
+                    CausedBy = trc::location!(),
+                );
+                continue;
+            };
+            let thread_id = self
+                .get_property::<u32>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::ThreadId,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .unwrap_or_default();
+            let mailbox_ids = self
+                .get_property::<Vec<UidMailbox>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::MailboxIds,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .unwrap_or_default();
+            let keywords = self
+                .get_property::<Vec<Keyword>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::Keywords,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .unwrap_or_default();
+
+            let id = Id::from_parts(thread_id, document_id);
+            let path = format!("{account_name}/{id}.eml");
+            archive.add_file(&path, &raw_message)?;
+            archive.manifest.push(ArchivedMessage {
+                path,
+                account: account_name.to_string(),
+                id,
+                mailboxes: mailbox_ids
+                    .iter()
+                    .filter_map(|mailbox| mailbox_paths.get(&mailbox.mailbox_id).cloned())
+                    .collect(),
+                keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+                received_at: DateTime::from_timestamp(metadata.received_at as i64).to_rfc3339(),
+                size: metadata.size,
+                deleted: mailbox_ids
+                    .iter()
+                    .any(|mailbox| mailbox.mailbox_id == TOMBSTONE_ID),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn mailbox_paths(&self, account_id: u32) -> trc::Result<AHashMap<u32, String>> {
+        let mut mailboxes = AHashMap::new();
+        for document_id in self
+            .get_document_ids(account_id, Collection::Mailbox)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default()
+        {
+            if let Some(mut obj) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::Mailbox,
+                    document_id,
+                    Property::Value,
+                )
+                .await
+                .caused_by(trc::location!())?
+            {
+                let name = match obj.properties.remove(&Property::Name) {
+                    Some(Value::Text(name)) => name,
+                    _ => continue,
+                };
+                let parent_id = match obj.properties.remove(&Property::ParentId) {
+                    Some(Value::Id(parent_id)) if parent_id.document_id() > 0 => {
+                        Some(parent_id.document_id() - 1)
+                    }
+                    _ => None,
+                };
+                mailboxes.insert(document_id, (name, parent_id));
+            }
+        }
+
+        // Build full paths, guarding against cycles
+        let mut paths = AHashMap::with_capacity(mailboxes.len());
+        for (document_id, (name, parent_id)) in &mailboxes {
+            let mut path = vec![name.as_str()];
+            let mut parent_id = *parent_id;
+            while let Some((parent_name, next_parent_id)) =
+                parent_id.and_then(|parent_id| mailboxes.get(&parent_id))
+            {
+                if path.len() > self.core.jmap.mailbox_max_depth {
+                    break;
+                }
+                path.push(parent_name.as_str());
+                parent_id = *next_parent_id;
+            }
+            path.reverse();
+            paths.insert(*document_id, path.join("/"));
+        }
+
+        Ok(paths)
+    }
+}
+
+impl MessageArchive {
+    pub fn new() -> Self {
+        MessageArchive {
+            zip: ZipWriter::new(Cursor::new(Vec::new())),
+            manifest: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.manifest.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.is_empty()
+    }
+
+    fn add_file(&mut self, path: &str, contents: &[u8]) -> trc::Result<()> {
+        self.zip
+            .start_file(
+                path,
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+            )
+            .map_err(archive_error)?;
+        self.zip.write_all(contents).map_err(archive_error)
+    }
+
+    pub fn finish(mut self) -> trc::Result<Vec<u8>> {
+        let manifest = serde_json::to_vec_pretty(&self.manifest).unwrap_or_default();
+        self.add_file("manifest.json", &manifest)?;
+        self.zip
+            .finish()
+            .map(|cursor| cursor.into_inner())
+            .map_err(archive_error)
+    }
+}
+
+impl Default for MessageArchive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn archive_error(err: impl std::fmt::Display) -> trc::Error {
+    trc::StoreEvent::UnexpectedError
+        .caused_by(trc::location!())
+        .reason(err)
+        .details("Failed to build message archive")
+}
@@ -0,0 +1,70 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use crate::{
+    api::{http::ToHttpResponse, HttpResponse, JsonResponse},
+    auth::acl::AclMethods,
+};
+use common::{auth::AccessToken, Server};
+use directory::backend::internal::manage;
+use email::{auth_report::AuthReport, metadata::MessageMetadata};
+use jmap_proto::types::{acl::Acl, collection::Collection, id::Id, property::Property};
+use serde_json::json;
+use store::write::Bincode;
+use trc::AddContext;
+
+pub trait AuthReportHandler: Sync + Send {
+    fn handle_auth_report_get(
+        &self,
+        access_token: Arc<AccessToken>,
+        email_id: &str,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl AuthReportHandler for Server {
+    async fn handle_auth_report_get(
+        &self,
+        access_token: Arc<AccessToken>,
+        email_id: &str,
+    ) -> trc::Result<HttpResponse> {
+        let account_id = access_token.primary_id();
+        let document_id = Id::from_bytes(email_id.as_bytes())
+            .ok_or_else(|| manage::not_found(email_id.to_string()))?
+            .document_id();
+
+        // Make sure the message exists and is accessible
+        if !self
+            .owned_or_shared_messages(&access_token, account_id, Acl::ReadItems)
+            .await?
+            .contains(document_id)
+        {
+            return Err(manage::not_found(email_id.to_string()));
+        }
+
+        let metadata = self
+            .get_property::<Bincode<MessageMetadata>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                &Property::BodyStructure,
+            )
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| manage::not_found(email_id.to_string()))?
+            .inner;
+
+        Ok(JsonResponse::new(json!({
+            "data": AuthReport::parse(
+                &metadata.raw_headers,
+                &self.core.network.server_name,
+                &self.core.spam.headers,
+            ),
+        }))
+        .into_http_response())
+    }
+}
@@ -4,17 +4,19 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::future::Future;
+use std::{future::Future, time::Instant};
 
 use common::Server;
 use email::metadata::MessageMetadata;
 use jmap_proto::types::{collection::Collection, property::Property};
 use mail_parser::Message;
 use spam_filter::{
-    analysis::init::SpamFilterInit, modules::bayes::BayesClassifier, SpamFilterInput,
+    analysis::init::SpamFilterInit,
+    modules::{bayes::BayesClassifier, rspamd::rspamd_learn},
+    SpamFilterInput,
 };
 use store::write::{Bincode, TaskQueueClass};
-use trc::StoreEvent;
+use trc::{SpamEvent, StoreEvent};
 
 pub trait EmailBayesTrain: Sync + Send {
     fn email_bayes_train(
@@ -41,6 +43,30 @@ impl EmailBayesTrain for Server {
         message: Message<'_>,
         learn_spam: bool,
     ) {
+        // Forward training to Rspamd when it replaces the built-in classifier
+        if let Some(config) = self.core.spam.rspamd.as_ref().filter(|config| config.learn) {
+            let time = Instant::now();
+            match rspamd_learn(config, message.raw_message(), learn_spam).await {
+                Ok(_) => {
+                    trc::event!(
+                        Spam(SpamEvent::Train),
+                        SpanId = span_id,
+                        AccountId = account_id,
+                        Details = "rspamd",
+                        Result = learn_spam,
+                        Elapsed = time.elapsed(),
+                    );
+                }
+                Err(err) => {
+                    trc::error!(err
+                        .span_id(span_id)
+                        .account_id(account_id)
+                        .caused_by(trc::location!()));
+                }
+            }
+            return;
+        }
+
         self.bayes_train_if_balanced(
             &self.spam_filter_init(SpamFilterInput::from_account_message(
                 &message, account_id, span_id,
@@ -9,6 +9,7 @@ use common::{
     Server,
 };
 use email::{
+    counters::MailboxCounters,
     index::{EmailIndexBuilder, TrimTextValue, VisitValues, MAX_ID_LENGTH, MAX_SORT_FIELD_LENGTH},
     ingest::{EmailIngest, IngestedEmail, LogEmailInsert},
     mailbox::{MailboxFnc, UidMailbox},
@@ -327,10 +328,14 @@ impl EmailCopy for Server {
         };
 
         // Check quota
-        match self
+        let has_quota = match self
             .has_available_quota(resource_token, metadata.size as u64)
             .await
         {
+            Ok(_) => self.has_available_message_quota(account_id).await,
+            err => err,
+        };
+        match has_quota {
             Ok(_) => (),
             Err(err) => {
                 if err.matches(trc::EventType::Limit(trc::LimitEvent::Quota))
@@ -418,6 +423,14 @@ impl EmailCopy for Server {
             batch.create_document().log(LogInsert());
         };
 
+        // Update mailbox counters
+        let mut counters = MailboxCounters::new();
+        counters.add_message(
+            mailboxes.iter().copied(),
+            keywords.contains(&Keyword::Seen),
+            metadata.size,
+        );
+
         // Build batch
         let maybe_thread_id = thread_id
             .map(MaybeDynamicId::Static)
@@ -445,6 +458,7 @@ impl EmailCopy for Server {
             account_id,
             resource_token.tenant.map(|t| t.id),
         );
+        batch.custom(counters);
 
         // Insert and obtain ids
         let ids = self
@@ -8,6 +8,7 @@ use std::time::Duration;
 
 use common::{KV_LOCK_PURGE_ACCOUNT, Server};
 use email::{
+    counters::{MailboxCounterFnc, MailboxCounters},
     index::EmailIndexBuilder,
     mailbox::{JUNK_ID, TOMBSTONE_ID, TRASH_ID, UidMailbox},
     metadata::MessageMetadata,
@@ -22,7 +23,7 @@ use store::{
     roaring::RoaringBitmap,
     write::{
         BatchBuilder, Bincode, BitmapClass, F_BITMAP, F_CLEAR, F_VALUE, MaybeDynamicId, TagValue,
-        ValueClass, log::ChangeLogBuilder,
+        ValueClass, log::ChangeLogBuilder, now,
     },
 };
 use trc::{AddContext, StoreEvent};
@@ -48,6 +49,8 @@ pub trait EmailDeletion: Sync + Send {
         period: Duration,
     ) -> impl Future<Output = trc::Result<()>> + Send;
 
+    fn emails_expire(&self, account_id: u32) -> impl Future<Output = trc::Result<()>> + Send;
+
     fn emails_purge_tombstoned(
         &self,
         account_id: u32,
@@ -140,6 +143,22 @@ impl EmailDeletion for Server {
             .await
             .caused_by(trc::location!())?;
 
+        // Obtain seen flags and sizes to update mailbox counters
+        let seen = self
+            .get_tag(
+                account_id,
+                Collection::Email,
+                Property::Keywords,
+                Keyword::Seen,
+            )
+            .await?
+            .unwrap_or_default();
+        let sizes = self
+            .message_sizes(account_id, &document_ids)
+            .await
+            .caused_by(trc::location!())?;
+        let mut counters = MailboxCounters::new();
+
         // Tombstone message and untag it from the mailboxes
         let mut batch = BatchBuilder::new();
         batch
@@ -154,6 +173,11 @@ impl EmailDeletion for Server {
                     debug_assert!(mailbox_id.uid != 0);
                     changes.log_child_update(Collection::Mailbox, mailbox_id.mailbox_id);
                 }
+                counters.remove_message(
+                    delete_properties.mailboxes.iter().map(|m| m.mailbox_id),
+                    seen.contains(document_id),
+                    sizes.get(&document_id).copied().unwrap_or_default(),
+                );
 
                 batch.value(
                     Property::MailboxIds,
@@ -196,6 +220,7 @@ impl EmailDeletion for Server {
             document_ids.remove(document_id);
 
             if batch.ops.len() >= 1000 {
+                batch.custom(std::mem::take(&mut counters));
                 self.core
                     .storage
                     .data
@@ -209,6 +234,7 @@ impl EmailDeletion for Server {
                     .with_collection(Collection::Email);
             }
         }
+        batch.custom(counters);
 
         // Delete threadIds
         for (thread_id, thread_count) in thread_ids {
@@ -279,6 +305,14 @@ impl EmailDeletion for Server {
             }
         }
 
+        // Delete messages past their expiry time
+        if let Err(err) = self.emails_expire(account_id).await {
+            trc::error!(
+                err.details("Failed to delete expired messages.")
+                    .account_id(account_id)
+            );
+        }
+
         // Purge tombstoned messages
         if let Err(err) = self.emails_purge_tombstoned(account_id).await {
             trc::error!(
@@ -381,6 +415,65 @@ impl EmailDeletion for Server {
         Ok(())
     }
 
+    async fn emails_expire(&self, account_id: u32) -> trc::Result<()> {
+        let expiry_candidates = self
+            .get_tag(
+                account_id,
+                Collection::Email,
+                Property::Expires,
+                TagValue::Id(0),
+            )
+            .await?
+            .unwrap_or_default();
+        if expiry_candidates.is_empty() {
+            return Ok(());
+        }
+
+        // Find messages to destroy
+        let now = now();
+        let mut destroy_ids = RoaringBitmap::new();
+        for (document_id, expires_at) in self
+            .get_properties::<u64, _, _>(
+                account_id,
+                Collection::Email,
+                &expiry_candidates,
+                Property::Expires,
+            )
+            .await?
+        {
+            if expires_at <= now {
+                destroy_ids.insert(document_id);
+            }
+        }
+
+        if destroy_ids.is_empty() {
+            return Ok(());
+        }
+
+        trc::event!(
+            Purge(trc::PurgeEvent::MessageExpiry),
+            AccountId = account_id,
+            Total = destroy_ids.len(),
+        );
+
+        // Tombstone messages
+        let (changes, _) = self.emails_tombstone(account_id, destroy_ids).await?;
+
+        // Write and broadcast changes
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            self.broadcast_state_change(
+                StateChange::new(account_id)
+                    .with_change(DataType::Email, change_id)
+                    .with_change(DataType::Mailbox, change_id)
+                    .with_change(DataType::Thread, change_id),
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+
     async fn emails_purge_tombstoned(&self, account_id: u32) -> trc::Result<()> {
         // Obtain tombstoned messages
         let tombstoned_ids = self
@@ -403,6 +496,20 @@ impl EmailDeletion for Server {
             return Ok(());
         }
 
+        // Deleted messages are retained while the account is on legal hold
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        if access_token.legal_hold {
+            trc::event!(
+                Purge(trc::PurgeEvent::LegalHold),
+                AccountId = account_id,
+                Total = tombstoned_ids.len(),
+            );
+            return Ok(());
+        }
+
         trc::event!(
             Purge(trc::PurgeEvent::TombstoneCleanup),
             AccountId = account_id,
@@ -417,12 +524,7 @@ impl EmailDeletion for Server {
             .await?;
 
         // Obtain tenant id
-        let tenant_id = self
-            .get_access_token(account_id)
-            .await
-            .caused_by(trc::location!())?
-            .tenant
-            .map(|t| t.id);
+        let tenant_id = access_token.tenant.map(|t| t.id);
 
         // Delete messages
         for document_id in tombstoned_ids {
@@ -432,10 +534,16 @@ impl EmailDeletion for Server {
                 .with_collection(Collection::Email)
                 .delete_document(document_id)
                 .clear(Property::Cid)
+                .clear(Property::Expires)
                 .tag(
                     Property::MailboxIds,
                     TagValue::Id(MaybeDynamicId::Static(TOMBSTONE_ID)),
                     F_CLEAR,
+                )
+                .tag(
+                    Property::Expires,
+                    TagValue::Id(MaybeDynamicId::Static(0)),
+                    F_CLEAR,
                 );
 
             // Remove keywords
@@ -0,0 +1,95 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use crate::api::{http::ToHttpResponse, HttpResponse, JsonResponse};
+use common::{auth::AccessToken, Server};
+use email::fetch::{ExternalAccountManager, ExternalAccounts};
+use serde_json::json;
+
+pub trait ExternalAccountHandler: Sync + Send {
+    fn handle_external_accounts_get(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_external_accounts_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ExternalAccountHandler for Server {
+    async fn handle_external_accounts_get(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> trc::Result<HttpResponse> {
+        let accounts = self
+            .external_accounts_get(access_token.primary_id())
+            .await?;
+
+        Ok(JsonResponse::new(json!({
+            "data": self.external_accounts_json(&accounts),
+        }))
+        .into_http_response())
+    }
+
+    async fn handle_external_accounts_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        let request =
+            serde_json::from_slice::<ExternalAccounts>(body.as_deref().unwrap_or_default())
+                .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+        let accounts = self
+            .external_accounts_set(access_token.primary_id(), request.accounts)
+            .await?;
+
+        Ok(JsonResponse::new(json!({
+            "data": self.external_accounts_json(&accounts),
+        }))
+        .into_http_response())
+    }
+}
+
+trait ExternalAccountsJson {
+    fn external_accounts_json(&self, accounts: &ExternalAccounts) -> serde_json::Value;
+}
+
+impl ExternalAccountsJson for Server {
+    fn external_accounts_json(&self, accounts: &ExternalAccounts) -> serde_json::Value {
+        // Credentials and synchronization state are never returned
+        let config = &self.core.jmap;
+        json!({
+            "accounts": accounts.accounts.iter().map(|account| json!({
+                "id": account.id,
+                "protocol": account.protocol,
+                "host": account.host,
+                "port": account.port_or_default(),
+                "tls": account.tls,
+                "username": account.username,
+                "oauthClient": account.oauth.as_ref().map(|oauth| &oauth.client),
+                "folder": account.folder,
+                "keep": account.keep,
+                "enabled": account.enabled,
+                "status": {
+                    "lastFetch": (account.state.last_fetch != 0)
+                        .then_some(account.state.last_fetch),
+                    "lastSuccess": (account.state.last_success != 0)
+                        .then_some(account.state.last_success),
+                    "lastError": account.state.last_error,
+                    "totalFetched": account.state.total_fetched,
+                },
+            })).collect::<Vec<_>>(),
+            "maxAccounts": config.fetch_max_accounts,
+            "frequency": config.fetch_frequency.as_secs(),
+            "oauthClients": config.fetch_oauth_clients.keys().collect::<Vec<_>>(),
+        })
+    }
+}
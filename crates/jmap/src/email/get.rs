@@ -6,6 +6,7 @@
 
 use common::{auth::AccessToken, Server};
 use email::{
+    auth_report::AuthReport,
     cache::ThreadCache,
     mailbox::UidMailbox,
     metadata::{MessageMetadata, MetadataPartType},
@@ -357,6 +358,16 @@ impl EmailGet for Server {
                                 .header_to_value(property, &raw_message),
                         );
                     }
+                    Property::AuthResults => {
+                        email.append(
+                            Property::AuthResults,
+                            AuthReport::parse(
+                                &raw_message,
+                                &self.core.network.server_name,
+                                &self.core.spam.headers,
+                            ),
+                        );
+                    }
                     Property::Headers => {
                         email.append(
                             Property::Headers,
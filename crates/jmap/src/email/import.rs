@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::{auth::AccessToken, Server};
+use common::{auth::AccessToken, config::scripts::ImapSieveCause, Server};
 use email::{
     ingest::{EmailIngest, IngestEmail, IngestSource},
     mailbox::MailboxFnc,
@@ -25,8 +25,11 @@ use mail_parser::MessageParser;
 use utils::map::vec_map::VecMap;
 
 use crate::{
-    api::http::HttpSessionData, auth::acl::AclMethods, blob::download::BlobDownload,
+    api::http::HttpSessionData,
+    auth::acl::AclMethods,
+    blob::download::BlobDownload,
     changes::state::StateManager,
+    sieve::imapsieve::{ImapSieve, ImapSieveEvent},
 };
 
 use std::future::Future;
@@ -74,6 +77,9 @@ impl EmailImport for Server {
             state_change: None,
         };
         let can_train_spam = self.email_bayes_can_train(access_token);
+        let has_imapsieve = self.imapsieve_enabled(ImapSieveCause::Append);
+        let mut imapsieve_events = Vec::new();
+        let mut last_change_id = None;
 
         'outer: for (id, email) in request.emails {
             // Validate mailboxIds
@@ -130,6 +136,11 @@ impl EmailImport for Server {
             };
 
             // Import message
+            let imapsieve_mailbox_ids = if has_imapsieve {
+                mailbox_ids.clone()
+            } else {
+                vec![]
+            };
             match self
                 .email_ingest(IngestEmail {
                     raw_message: &raw_message,
@@ -141,11 +152,21 @@ impl EmailImport for Server {
                     source: IngestSource::Jmap,
                     spam_classify: false,
                     spam_train: can_train_spam,
+                    expires_at: None,
                     session_id: session.session_id,
                 })
                 .await
             {
                 Ok(email) => {
+                    for mailbox_id in imapsieve_mailbox_ids {
+                        imapsieve_events.push(ImapSieveEvent {
+                            document_id: email.id.document_id(),
+                            mailbox_id,
+                            cause: ImapSieveCause::Append,
+                            changed_flags: vec![],
+                        });
+                    }
+                    last_change_id = last_change_id.max(Some(email.change_id));
                     response.created.append(id, email.into());
                 }
                 Err(mut err) => match err.as_ref() {
@@ -173,16 +194,18 @@ impl EmailImport for Server {
             }
         }
 
+        // Run IMAPSIEVE scripts
+        self.imapsieve_run(account_id, imapsieve_events, session.session_id)
+            .await;
+
         // Update state
-        if !response.created.is_empty() {
-            response.new_state = self.get_state(account_id, Collection::Email).await?;
-            if let State::Exact(change_id) = &response.new_state {
-                response.state_change = StateChange::new(account_id)
-                    .with_change(DataType::Email, *change_id)
-                    .with_change(DataType::Mailbox, *change_id)
-                    .with_change(DataType::Thread, *change_id)
-                    .into()
-            }
+        if let Some(change_id) = last_change_id {
+            response.new_state = State::Exact(change_id);
+            response.state_change = StateChange::new(account_id)
+                .with_change(DataType::Email, change_id)
+                .with_change(DataType::Mailbox, change_id)
+                .with_change(DataType::Thread, change_id)
+                .into()
         }
 
         Ok(response)
@@ -0,0 +1,112 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use jmap_proto::types::keyword::Keyword;
+use trc::AddContext;
+
+use super::set::TagManager;
+
+#[derive(Debug, Clone)]
+pub struct KeywordChange {
+    pub document_id: u32,
+    pub added: Vec<Keyword>,
+    pub removed: Vec<Keyword>,
+}
+
+pub trait KeywordEvents: Sync + Send {
+    fn keyword_events_enabled(&self, account_id: u32) -> impl Future<Output = bool> + Send;
+
+    fn keyword_change(
+        &self,
+        document_id: u32,
+        keywords: &TagManager<Keyword>,
+    ) -> Option<KeywordChange>;
+
+    fn keyword_change_notify(&self, account_id: u32, changes: Vec<KeywordChange>, session_id: u64);
+}
+
+impl KeywordEvents for Server {
+    async fn keyword_events_enabled(&self, account_id: u32) -> bool {
+        if self.core.jmap.mail_keyword_events.is_empty() {
+            return false;
+        }
+
+        match self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())
+        {
+            Ok(access_token) => access_token.keyword_events,
+            Err(err) => {
+                trc::error!(err
+                    .account_id(account_id)
+                    .details("Failed to obtain access token"));
+                false
+            }
+        }
+    }
+
+    fn keyword_change(
+        &self,
+        document_id: u32,
+        keywords: &TagManager<Keyword>,
+    ) -> Option<KeywordChange> {
+        // Only keywords listed in the configuration are reported
+        let monitored = &self.core.jmap.mail_keyword_events;
+        let added = keywords
+            .added()
+            .iter()
+            .filter(|keyword| monitored.contains(keyword))
+            .cloned()
+            .collect::<Vec<_>>();
+        let removed = keywords
+            .removed()
+            .iter()
+            .filter(|keyword| monitored.contains(keyword))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        (!added.is_empty() || !removed.is_empty()).then_some(KeywordChange {
+            document_id,
+            added,
+            removed,
+        })
+    }
+
+    fn keyword_change_notify(&self, account_id: u32, changes: Vec<KeywordChange>, session_id: u64) {
+        for change in changes {
+            if !change.added.is_empty() {
+                trc::event!(
+                    MessageIngest(trc::MessageIngestEvent::KeywordAdded),
+                    SpanId = session_id,
+                    AccountId = account_id,
+                    DocumentId = change.document_id,
+                    Value = change
+                        .added
+                        .iter()
+                        .map(|keyword| keyword.to_string())
+                        .collect::<Vec<_>>(),
+                );
+            }
+            if !change.removed.is_empty() {
+                trc::event!(
+                    MessageIngest(trc::MessageIngestEvent::KeywordRemoved),
+                    SpanId = session_id,
+                    AccountId = account_id,
+                    DocumentId = change.document_id,
+                    Value = change
+                        .removed
+                        .iter()
+                        .map(|keyword| keyword.to_string())
+                        .collect::<Vec<_>>(),
+                );
+            }
+        }
+    }
+}
@@ -4,15 +4,21 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod archive;
+pub mod auth_report;
 pub mod bayes;
 pub mod body;
 pub mod copy;
 pub mod crypto;
 pub mod delete;
+pub mod fetch;
 pub mod get;
 pub mod headers;
 pub mod import;
+pub mod keyword;
 pub mod parse;
 pub mod query;
+pub mod recall;
+pub mod sender_list;
 pub mod set;
 pub mod snippet;
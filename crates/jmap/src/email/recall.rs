@@ -0,0 +1,362 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use crate::api::{http::ToHttpResponse, HttpResponse, JsonResponse};
+use common::{auth::AccessToken, Server};
+use directory::backend::internal::manage;
+use email::{
+    ingest::{EmailIngest, IngestEmail, IngestSource},
+    mailbox::INBOX_ID,
+    metadata::MessageMetadata,
+};
+use jmap_proto::{
+    error::set::{SetError, SetErrorType},
+    method::recall::{EmailRecallRequest, EmailRecallResponse, RecallRecipient, RecallStatus},
+    types::{
+        collection::Collection, id::Id, keyword::Keyword, property::Property, state::StateChange,
+        type_state::DataType,
+    },
+};
+use mail_builder::MessageBuilder;
+use mail_parser::{HeaderName, MessageParser};
+use serde_json::json;
+use store::{
+    ahash::AHashMap,
+    query::Filter,
+    write::{now, Bincode},
+};
+use trc::AddContext;
+
+use super::delete::EmailDeletion;
+
+pub trait EmailRecall: Sync + Send {
+    fn email_recall(
+        &self,
+        request: EmailRecallRequest,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<EmailRecallResponse>> + Send;
+
+    fn email_recall_message(
+        &self,
+        access_token: &AccessToken,
+        document_id: u32,
+    ) -> impl Future<Output = trc::Result<Result<Vec<RecallRecipient>, SetError>>> + Send;
+
+    fn email_recall_copies(
+        &self,
+        account_id: u32,
+        rcpt_account_id: u32,
+        rcpt: &str,
+        message_id: &str,
+    ) -> impl Future<Output = trc::Result<RecallStatus>> + Send;
+
+    fn handle_email_recall_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        email_id: &str,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl EmailRecall for Server {
+    async fn email_recall(
+        &self,
+        request: EmailRecallRequest,
+        access_token: &AccessToken,
+    ) -> trc::Result<EmailRecallResponse> {
+        if request.ids.len() > self.core.jmap.set_max_objects {
+            return Err(trc::JmapEvent::RequestTooLarge.into_err());
+        }
+
+        let mut response = EmailRecallResponse {
+            account_id: request.account_id,
+            ..Default::default()
+        };
+
+        // Only the sender is allowed to recall a message
+        if request.account_id.document_id() != access_token.primary_id() {
+            for id in request.ids {
+                response.not_recalled.append(
+                    id,
+                    SetError::forbidden().with_description(
+                        "Only messages sent from your own account can be recalled.",
+                    ),
+                );
+            }
+            return Ok(response);
+        }
+
+        for id in request.ids {
+            match self
+                .email_recall_message(access_token, id.document_id())
+                .await?
+            {
+                Ok(recipients) => {
+                    response.recalled.append(id, recipients);
+                }
+                Err(err) => {
+                    response.not_recalled.append(id, err);
+                }
+            }
+        }
+
+        Ok(response)
+    }
+
+    async fn email_recall_message(
+        &self,
+        access_token: &AccessToken,
+        document_id: u32,
+    ) -> trc::Result<Result<Vec<RecallRecipient>, SetError>> {
+        let account_id = access_token.primary_id();
+        let Some(metadata) = self
+            .get_property::<Bincode<MessageMetadata>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                &Property::BodyStructure,
+            )
+            .await
+            .caused_by(trc::location!())?
+            .map(|metadata| metadata.inner)
+        else {
+            return Ok(Err(SetError::not_found()));
+        };
+
+        // Messages can only be recalled within the configured period
+        if let Some(max_age) = self.core.jmap.mail_recall_max_age
+            && metadata.received_at + max_age.as_secs() < now()
+        {
+            return Ok(Err(
+                SetError::forbidden().with_description("The message is too old to be recalled.")
+            ));
+        }
+
+        // Obtain the sender, recipients and Message-ID
+        let mut message_id = None;
+        let mut subject = None;
+        let mut is_sender = false;
+        let mut recipients = Vec::new();
+        for header in &metadata.contents.root_part().headers {
+            match &header.name {
+                HeaderName::MessageId => {
+                    message_id = header.value.as_text().map(|id| id.to_string());
+                }
+                HeaderName::Subject => {
+                    subject = header.value.as_text().map(|subject| subject.to_string());
+                }
+                HeaderName::From => {
+                    is_sender = header.value.as_address().is_some_and(|addrs| {
+                        addrs.iter().any(|addr| {
+                            addr.address().is_some_and(|addr| {
+                                access_token
+                                    .emails
+                                    .iter()
+                                    .any(|email| email.eq_ignore_ascii_case(addr))
+                            })
+                        })
+                    });
+                }
+                HeaderName::To | HeaderName::Cc | HeaderName::Bcc => {
+                    if let Some(addrs) = header.value.as_address() {
+                        for addr in addrs.iter() {
+                            if let Some(addr) = addr.address() {
+                                let addr = addr.trim().to_lowercase();
+                                if !addr.is_empty() && !recipients.contains(&addr) {
+                                    recipients.push(addr);
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(message_id) = message_id.filter(|_| is_sender && !recipients.is_empty()) else {
+            return Ok(Err(SetError::forbidden()
+                .with_description("The message was not sent from this account.")));
+        };
+
+        // Recalls are only possible when every recipient is local
+        let mut local_recipients = Vec::with_capacity(recipients.len());
+        for rcpt in recipients {
+            match self
+                .email_to_id(&self.core.storage.directory, &rcpt, 0)
+                .await
+                .caused_by(trc::location!())?
+            {
+                Some(rcpt_account_id) => {
+                    local_recipients.push((rcpt, rcpt_account_id));
+                }
+                None => {
+                    return Ok(Err(SetError::forbidden().with_description(format!(
+                        "The message was delivered to the non-local recipient {rcpt}."
+                    ))));
+                }
+            }
+        }
+
+        // Remove unread copies from the recipients' mailboxes
+        let mut account_status: AHashMap<u32, RecallStatus> = AHashMap::new();
+        let mut result = Vec::with_capacity(local_recipients.len());
+        for (rcpt, rcpt_account_id) in local_recipients {
+            let status = if rcpt_account_id == account_id {
+                RecallStatus::NotFound
+            } else if let Some(status) = account_status.get(&rcpt_account_id) {
+                *status
+            } else {
+                let status = self
+                    .email_recall_copies(account_id, rcpt_account_id, &rcpt, &message_id)
+                    .await?;
+                account_status.insert(rcpt_account_id, status);
+                status
+            };
+
+            result.push(RecallRecipient {
+                email: rcpt,
+                status,
+            });
+        }
+
+        // Notify the sender
+        let sender = access_token
+            .emails
+            .first()
+            .map(|email| email.as_str())
+            .unwrap_or_default();
+        let mut report = format!(
+            "The recall of your message {message_id} has completed with the following results:\r\n\r\n"
+        );
+        for rcpt in &result {
+            report.push_str(&format!("{}: {}\r\n", rcpt.email, rcpt.status.as_str()));
+        }
+        let raw_message = MessageBuilder::new()
+            .from(format!("MAILER-DAEMON@{}", self.core.network.server_name))
+            .to(sender)
+            .subject(format!(
+                "Recall: {}",
+                subject.as_deref().unwrap_or_default()
+            ))
+            .in_reply_to(message_id.as_str())
+            .text_body(report)
+            .write_to_vec()
+            .map_err(|err| {
+                trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
+                    .into_err()
+                    .details("Failed to build recall notification")
+                    .reason(err)
+            })?;
+        self.email_ingest(IngestEmail {
+            raw_message: &raw_message,
+            message: MessageParser::new().parse(&raw_message),
+            resource: access_token.as_resource_token(),
+            mailbox_ids: vec![INBOX_ID],
+            keywords: vec![],
+            received_at: None,
+            source: IngestSource::Smtp { deliver_to: sender },
+            spam_classify: false,
+            spam_train: false,
+            expires_at: None,
+            session_id: 0,
+        })
+        .await
+        .caused_by(trc::location!())?;
+
+        Ok(Ok(result))
+    }
+
+    async fn handle_email_recall_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        email_id: &str,
+    ) -> trc::Result<HttpResponse> {
+        let document_id = Id::from_bytes(email_id.as_bytes())
+            .ok_or_else(|| manage::not_found(email_id.to_string()))?
+            .document_id();
+
+        match self
+            .email_recall_message(&access_token, document_id)
+            .await?
+        {
+            Ok(recipients) => Ok(JsonResponse::new(json!({
+                "data": recipients,
+            }))
+            .into_http_response()),
+            Err(err) if err.type_ == SetErrorType::NotFound => {
+                Err(manage::not_found(email_id.to_string()))
+            }
+            Err(err) => Err(manage::error(
+                "Message cannot be recalled",
+                err.description.map(|description| description.into_owned()),
+            )),
+        }
+    }
+
+    async fn email_recall_copies(
+        &self,
+        account_id: u32,
+        rcpt_account_id: u32,
+        rcpt: &str,
+        message_id: &str,
+    ) -> trc::Result<RecallStatus> {
+        let mut document_ids = self
+            .core
+            .storage
+            .data
+            .filter(
+                rcpt_account_id,
+                Collection::Email,
+                vec![Filter::eq(Property::MessageId, message_id)],
+            )
+            .await
+            .caused_by(trc::location!())?
+            .results;
+        if document_ids.is_empty() {
+            return Ok(RecallStatus::NotFound);
+        }
+
+        // Messages already read by the recipient are left untouched
+        if let Some(seen) = self
+            .get_tag(
+                rcpt_account_id,
+                Collection::Email,
+                Property::Keywords,
+                Keyword::Seen,
+            )
+            .await?
+        {
+            document_ids -= seen;
+            if document_ids.is_empty() {
+                return Ok(RecallStatus::Read);
+            }
+        }
+
+        let total = document_ids.len();
+        let (changes, _) = self.emails_tombstone(rcpt_account_id, document_ids).await?;
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(rcpt_account_id, changes).await?;
+            self.broadcast_state_change(
+                StateChange::new(rcpt_account_id)
+                    .with_change(DataType::Email, change_id)
+                    .with_change(DataType::Mailbox, change_id)
+                    .with_change(DataType::Thread, change_id),
+            )
+            .await;
+        }
+
+        trc::event!(
+            MessageIngest(trc::MessageIngestEvent::Recall),
+            AccountId = rcpt_account_id,
+            From = account_id,
+            To = rcpt.to_string(),
+            MessageId = message_id.to_string(),
+            Total = total,
+        );
+
+        Ok(RecallStatus::Recalled)
+    }
+}
@@ -0,0 +1,57 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use crate::api::{http::ToHttpResponse, HttpResponse, JsonResponse};
+use common::{auth::AccessToken, Server};
+use email::sender_list::{SenderListManager, SenderLists};
+use serde_json::json;
+
+pub trait SenderListHandler: Sync + Send {
+    fn handle_sender_lists_get(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_sender_lists_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl SenderListHandler for Server {
+    async fn handle_sender_lists_get(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> trc::Result<HttpResponse> {
+        let lists = self.sender_lists_get(access_token.primary_id()).await?;
+
+        Ok(JsonResponse::new(json!({
+            "data": {
+                "allow": lists.allow,
+                "block": lists.block,
+                "maxEntries": self.core.spam.max_user_list_entries,
+            },
+        }))
+        .into_http_response())
+    }
+
+    async fn handle_sender_lists_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        let request = serde_json::from_slice::<SenderLists>(body.as_deref().unwrap_or_default())
+            .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+
+        Ok(JsonResponse::new(json!({
+            "data": self.sender_lists_set(access_token.primary_id(), request).await?,
+        }))
+        .into_http_response())
+    }
+}
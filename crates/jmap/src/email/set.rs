@@ -6,8 +6,9 @@
 
 use std::{borrow::Cow, collections::HashMap, slice::IterMut};
 
-use common::{auth::AccessToken, Server};
+use common::{auth::AccessToken, config::scripts::ImapSieveCause, Server};
 use email::{
+    counters::{MailboxCounterFnc, MailboxCounters},
     ingest::{EmailIngest, IngestEmail, IngestSource},
     mailbox::{MailboxFnc, UidMailbox},
 };
@@ -18,6 +19,7 @@ use jmap_proto::{
     types::{
         acl::Acl,
         collection::Collection,
+        id::Id,
         keyword::Keyword,
         property::Property,
         state::{State, StateChange},
@@ -46,14 +48,19 @@ use store::{
 use trc::AddContext;
 
 use crate::{
-    api::http::HttpSessionData, auth::acl::AclMethods, blob::download::BlobDownload,
-    changes::state::StateManager, JmapMethods,
+    api::http::HttpSessionData,
+    auth::acl::AclMethods,
+    blob::download::BlobDownload,
+    changes::state::StateManager,
+    sieve::imapsieve::{ImapSieve, ImapSieveEvent},
+    JmapMethods,
 };
 use std::future::Future;
 
 use super::{
     delete::EmailDeletion,
     headers::{BuildHeader, ValueToHeader},
+    keyword::{KeywordChange, KeywordEvents},
 };
 
 pub trait EmailSet: Sync + Send {
@@ -63,6 +70,15 @@ pub trait EmailSet: Sync + Send {
         access_token: &AccessToken,
         session: &HttpSessionData,
     ) -> impl Future<Output = trc::Result<SetResponse>> + Send;
+
+    fn email_set_write_updates(
+        &self,
+        batch: &mut BatchBuilder,
+        updates: &mut Vec<(Id, Vec<ImapSieveEvent>, Option<KeywordChange>)>,
+        response: &mut SetResponse,
+        imapsieve_events: &mut Vec<ImapSieveEvent>,
+        keyword_changes: &mut Vec<KeywordChange>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
 impl EmailSet for Server {
@@ -78,6 +94,12 @@ impl EmailSet for Server {
             .prepare_set_response(&request, Collection::Email)
             .await?;
         let can_train_spam = self.email_bayes_can_train(access_token);
+        let has_imapsieve_append = self.imapsieve_enabled(ImapSieveCause::Append);
+        let has_imapsieve_copy = self.imapsieve_enabled(ImapSieveCause::Copy);
+        let has_imapsieve_flag = self.imapsieve_enabled(ImapSieveCause::Flag);
+        let mut imapsieve_events = Vec::new();
+        let has_keyword_events = self.keyword_events_enabled(account_id).await;
+        let mut keyword_changes = Vec::new();
 
         // Obtain mailboxIds
         let mailbox_ids = self.mailbox_get_or_create(account_id).await?;
@@ -729,6 +751,11 @@ impl EmailSet for Server {
             builder.write_to(&mut raw_message).unwrap_or_default();
 
             // Ingest message
+            let imapsieve_mailbox_ids = if has_imapsieve_append {
+                mailboxes.clone()
+            } else {
+                vec![]
+            };
             match self
                 .email_ingest(IngestEmail {
                     raw_message: &raw_message,
@@ -740,11 +767,20 @@ impl EmailSet for Server {
                     source: IngestSource::Jmap,
                     spam_classify: false,
                     spam_train: can_train_spam,
+                    expires_at: None,
                     session_id: session.session_id,
                 })
                 .await
             {
                 Ok(message) => {
+                    for mailbox_id in imapsieve_mailbox_ids {
+                        imapsieve_events.push(ImapSieveEvent {
+                            document_id: message.id.document_id(),
+                            mailbox_id,
+                            cause: ImapSieveCause::Append,
+                            changed_flags: vec![],
+                        });
+                    }
                     response.created.insert(id, message.into());
                 }
                 Err(err) if err.matches(trc::EventType::Limit(trc::LimitEvent::Quota)) => {
@@ -760,6 +796,8 @@ impl EmailSet for Server {
 
         // Process updates
         let mut changes = ChangeLogBuilder::new();
+        let mut pending_batch = BatchBuilder::new();
+        let mut pending_updates = Vec::new();
         'update: for (id, object) in request.unwrap_update() {
             // Make sure id won't be destroyed
             if will_destroy.contains(&id) {
@@ -863,6 +901,85 @@ impl EmailSet for Server {
             let mut changed_mailboxes = AHashSet::new();
             changes.log_update(Collection::Email, id);
 
+            // Prepare IMAPSIEVE events
+            let mut update_events = Vec::new();
+            if has_imapsieve_copy {
+                for mailbox_id in mailboxes.added() {
+                    update_events.push(ImapSieveEvent {
+                        document_id,
+                        mailbox_id: mailbox_id.mailbox_id,
+                        cause: ImapSieveCause::Copy,
+                        changed_flags: vec![],
+                    });
+                }
+            }
+            let keyword_change = if has_keyword_events {
+                self.keyword_change(document_id, &keywords)
+            } else {
+                None
+            };
+            if has_imapsieve_flag && keywords.has_changes() {
+                let changed_flags = keywords.changed_tags().cloned().collect::<Vec<_>>();
+                for mailbox_id in mailboxes.current() {
+                    if !mailboxes.added().contains(mailbox_id) {
+                        update_events.push(ImapSieveEvent {
+                            document_id,
+                            mailbox_id: mailbox_id.mailbox_id,
+                            cause: ImapSieveCause::Flag,
+                            changed_flags: changed_flags.clone(),
+                        });
+                    }
+                }
+            }
+
+            // Calculate mailbox counter changes
+            let mut counters = MailboxCounters::new();
+            let is_seen = keywords.current().contains(&Keyword::Seen);
+            let was_seen = if keywords.added().contains(&Keyword::Seen) {
+                false
+            } else if keywords.removed().contains(&Keyword::Seen) {
+                true
+            } else {
+                is_seen
+            };
+            if was_seen != is_seen {
+                // Make sure the mailboxes being updated are still current
+                if !mailboxes.has_changes() {
+                    batch.assert_value(Property::MailboxIds, &mailboxes.current);
+                }
+                counters.set_seen(
+                    mailboxes
+                        .current()
+                        .iter()
+                        .filter(|mailbox_id| !mailboxes.added().contains(mailbox_id))
+                        .map(|mailbox_id| mailbox_id.mailbox_id),
+                    is_seen,
+                );
+            }
+            if mailboxes.has_changes() {
+                let size = self
+                    .message_sizes(account_id, &RoaringBitmap::from_iter([document_id]))
+                    .await?
+                    .remove(&document_id)
+                    .unwrap_or_default();
+                counters.remove_message(
+                    mailboxes
+                        .removed()
+                        .iter()
+                        .map(|mailbox_id| mailbox_id.mailbox_id),
+                    was_seen,
+                    size,
+                );
+                counters.add_message(
+                    mailboxes
+                        .added()
+                        .iter()
+                        .map(|mailbox_id| mailbox_id.mailbox_id),
+                    is_seen,
+                    size,
+                );
+            }
+
             // Process keywords
             if keywords.has_changes() {
                 // Verify permissions on shared accounts
@@ -971,33 +1088,44 @@ impl EmailSet for Server {
                 mailboxes.update_batch(&mut batch, Property::MailboxIds);
             }
 
+            // Update mailbox counters
+            batch.custom(counters);
+
             // Log mailbox changes
             for mailbox_id in changed_mailboxes {
                 changes.log_child_update(Collection::Mailbox, mailbox_id);
             }
 
-            // Write changes
+            // Queue changes
             if !batch.is_empty() {
-                match self.core.storage.data.write(batch.build()).await {
-                    Ok(_) => {
-                        // Add to updated list
-                        response.updated.append(id, None);
-                    }
-                    Err(err) if err.is_assertion_failure() => {
-                        response.not_updated.append(
-                            id,
-                            SetError::forbidden().with_description(
-                                "Another process modified this message, please try again.",
-                            ),
-                        );
-                    }
-                    Err(err) => {
-                        return Err(err.caused_by(trc::location!()));
-                    }
+                pending_batch.ops.append(&mut batch.ops);
+                pending_updates.push((id, update_events, keyword_change));
+
+                if pending_batch.ops.len() >= 1000 {
+                    self.email_set_write_updates(
+                        &mut pending_batch,
+                        &mut pending_updates,
+                        &mut response,
+                        &mut imapsieve_events,
+                        &mut keyword_changes,
+                    )
+                    .await?;
                 }
             }
         }
 
+        // Write pending changes
+        if !pending_updates.is_empty() {
+            self.email_set_write_updates(
+                &mut pending_batch,
+                &mut pending_updates,
+                &mut response,
+                &mut imapsieve_events,
+                &mut keyword_changes,
+            )
+            .await?;
+        }
+
         // Process deletions
         if !will_destroy.is_empty() {
             let email_ids = self
@@ -1068,6 +1196,13 @@ impl EmailSet for Server {
             } else {
                 self.get_state(account_id, Collection::Email).await?
             };
+
+            // Run IMAPSIEVE scripts
+            self.imapsieve_run(account_id, imapsieve_events, session.session_id)
+                .await;
+
+            // Notify keyword changes
+            self.keyword_change_notify(account_id, keyword_changes, session.session_id);
             if let State::Exact(change_id) = &new_state {
                 response.state_change = StateChange::new(account_id)
                     .with_change(DataType::Email, *change_id)
@@ -1081,6 +1216,40 @@ impl EmailSet for Server {
 
         Ok(response)
     }
+
+    async fn email_set_write_updates(
+        &self,
+        batch: &mut BatchBuilder,
+        updates: &mut Vec<(Id, Vec<ImapSieveEvent>, Option<KeywordChange>)>,
+        response: &mut SetResponse,
+        imapsieve_events: &mut Vec<ImapSieveEvent>,
+        keyword_changes: &mut Vec<KeywordChange>,
+    ) -> trc::Result<()> {
+        match self.core.storage.data.write(batch.build_batch()).await {
+            Ok(_) => {
+                // Add to updated list
+                for (id, update_events, keyword_change) in updates.drain(..) {
+                    response.updated.append(id, None);
+                    imapsieve_events.extend(update_events);
+                    keyword_changes.extend(keyword_change);
+                }
+                Ok(())
+            }
+            Err(err) if err.is_assertion_failure() => {
+                // The batch is written atomically, so a conflict on any message rejects all of them
+                for (id, _, _) in updates.drain(..) {
+                    response.not_updated.append(
+                        id,
+                        SetError::forbidden().with_description(
+                            "Another process modified this message, please try again.",
+                        ),
+                    );
+                }
+                Ok(())
+            }
+            Err(err) => Err(err.caused_by(trc::location!())),
+        }
+    }
 }
 pub struct TagManager<
     T: PartialEq + Clone + ToBitmaps + SerializeInto + Serialize + DeserializeFrom + Sync + Send,
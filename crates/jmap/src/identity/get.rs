@@ -4,8 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::Server;
+use common::{
+    config::jmap::settings::{render_template, Escape},
+    Server,
+};
 use directory::{backend::internal::PrincipalField, QueryBy};
+use email::provision::template_variables;
 use jmap_proto::{
     method::get::{GetRequest, GetResponse, RequestArguments},
     object::Object,
@@ -169,9 +173,38 @@ impl IdentityGet for Server {
             } else {
                 name.clone()
             };
+            let mut identity = Object::with_capacity(4);
+
+            // Add the signatures from the domain's provisioning template
+            if let Some(template) = email
+                .rsplit_once('@')
+                .and_then(|(_, domain)| self.core.jmap.account_template(domain))
+            {
+                let variables = template_variables(principal.name(), &name, &email);
+                for (property, signature, escape) in [
+                    (
+                        Property::TextSignature,
+                        &template.signature_text,
+                        Escape::None,
+                    ),
+                    (
+                        Property::HtmlSignature,
+                        &template.signature_html,
+                        Escape::Html,
+                    ),
+                ] {
+                    if let Some(signature) = signature
+                        .as_ref()
+                        .and_then(|s| render_template(s, &variables, escape).ok())
+                    {
+                        identity.set(property, signature);
+                    }
+                }
+            }
+
             batch.create_document_with_id(document_id).value(
                 Property::Value,
-                Object::with_capacity(4)
+                identity
                     .with_property(Property::Name, name)
                     .with_property(Property::Email, email),
                 F_VALUE,
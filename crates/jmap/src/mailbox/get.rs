@@ -5,7 +5,7 @@
  */
 
 use common::{auth::AccessToken, Server};
-use email::mailbox::MailboxFnc;
+use email::{counters::MailboxCounterFnc, mailbox::MailboxFnc};
 use jmap_proto::{
     method::get::{GetRequest, GetResponse, RequestArguments},
     object::Object,
@@ -113,6 +113,15 @@ impl MailboxGet for Server {
                 Object::with_capacity(0)
             };
 
+            let count = if properties
+                .iter()
+                .any(|p| matches!(p, Property::TotalEmails | Property::UnreadEmails))
+            {
+                self.mailbox_count(account_id, document_id).await?
+            } else {
+                None
+            };
+
             let mut mailbox = Object::with_capacity(properties.len());
 
             for property in &properties {
@@ -133,7 +142,9 @@ impl MailboxGet for Server {
                             _ => Value::Null,
                         })
                         .unwrap_or_default(),
-                    Property::TotalEmails => Value::UnsignedInt(
+                    Property::TotalEmails => Value::UnsignedInt(if let Some(count) = count {
+                        count.total
+                    } else {
                         self.get_tag(
                             account_id,
                             Collection::Email,
@@ -142,14 +153,16 @@ impl MailboxGet for Server {
                         )
                         .await?
                         .map(|v| v.len())
-                        .unwrap_or(0),
-                    ),
-                    Property::UnreadEmails => Value::UnsignedInt(
+                        .unwrap_or(0)
+                    }),
+                    Property::UnreadEmails => Value::UnsignedInt(if let Some(count) = count {
+                        count.unread
+                    } else {
                         self.mailbox_unread_tags(account_id, document_id, &message_ids)
                             .await?
                             .map(|v| v.len())
-                            .unwrap_or(0),
-                    ),
+                            .unwrap_or(0)
+                    }),
                     Property::TotalThreads => Value::UnsignedInt(
                         self.mailbox_count_threads(
                             account_id,
@@ -6,7 +6,10 @@
 
 use common::{auth::AccessToken, Server};
 use directory::Permission;
-use email::mailbox::{MailboxFnc, SCHEMA};
+use email::{
+    counters::{MailboxCounterFnc, MailboxCounters},
+    mailbox::{MailboxFnc, MailboxLimits, SCHEMA},
+};
 use jmap_proto::{
     error::set::{SetError, SetErrorType},
     method::set::{SetRequest, SetResponse},
@@ -16,6 +19,7 @@ use jmap_proto::{
         acl::Acl,
         collection::Collection,
         id::Id,
+        keyword::Keyword,
         property::Property,
         state::StateChange,
         type_state::DataType,
@@ -32,6 +36,8 @@ use store::{
     },
 };
 
+use trc::AddContext;
+
 use crate::{
     auth::acl::{AclMethods, EffectiveAcl},
     email::delete::EmailDeletion,
@@ -49,6 +55,7 @@ pub struct SetContext<'x> {
     response: SetResponse,
     mailbox_ids: RoaringBitmap,
     will_destroy: Vec<Id>,
+    limits: MailboxLimits,
 }
 
 pub trait MailboxSet: Sync + Send {
@@ -94,11 +101,25 @@ impl MailboxSet for Server {
                 .await?,
             mailbox_ids: self.mailbox_get_or_create(account_id).await?,
             will_destroy: request.unwrap_destroy(),
+            limits: self.mailbox_limits(account_id).await?,
         };
 
         // Process creates
         let mut changes = ChangeLogBuilder::new();
         'create: for (id, object) in request.unwrap_create() {
+            if ctx
+                .limits
+                .max_count
+                .is_some_and(|max_count| ctx.mailbox_ids.len() as usize >= max_count)
+            {
+                ctx.response.not_created.append(
+                    id,
+                    SetError::over_quota()
+                        .with_description("Maximum number of mailboxes exceeded."),
+                );
+                continue 'create;
+            }
+
             match self.mailbox_set_item(object, None, &ctx).await? {
                 Ok(builder) => {
                     let mut batch = BatchBuilder::new();
@@ -346,6 +367,21 @@ impl MailboxSet for Server {
                 // Flag removal for state change notification
                 did_remove_emails = true;
 
+                // Obtain seen flags and sizes to update mailbox counters
+                let seen = self
+                    .get_tag(
+                        account_id,
+                        Collection::Email,
+                        Property::Keywords,
+                        Keyword::Seen,
+                    )
+                    .await?
+                    .unwrap_or_default();
+                let sizes = self
+                    .message_sizes(account_id, &message_ids)
+                    .await
+                    .caused_by(trc::location!())?;
+
                 // If the message is in multiple mailboxes, untag it from the current mailbox,
                 // otherwise delete it.
                 let mut destroy_ids = RoaringBitmap::new();
@@ -377,6 +413,12 @@ impl MailboxSet for Server {
                             .await?
                         {
                             // Untag message from mailbox
+                            let mut counters = MailboxCounters::new();
+                            counters.remove_message(
+                                [document_id],
+                                seen.contains(message_id),
+                                sizes.get(&message_id).copied().unwrap_or_default(),
+                            );
                             let mut batch = BatchBuilder::new();
                             batch
                                 .with_account_id(account_id)
@@ -384,7 +426,8 @@ impl MailboxSet for Server {
                                 .update_document(message_id)
                                 .assert_value(Property::MailboxIds, &mailbox_ids)
                                 .value(Property::MailboxIds, mailbox_ids.inner, F_VALUE)
-                                .value(Property::MailboxIds, document_id, F_BITMAP | F_CLEAR);
+                                .value(Property::MailboxIds, document_id, F_BITMAP | F_CLEAR)
+                                .custom(counters);
                             match self.core.storage.data.write(batch.build()).await {
                                 Ok(_) => changes.log_update(
                                     Collection::Email,
@@ -500,6 +543,11 @@ impl MailboxSet for Server {
                 (Property::Name, MaybePatchValue::Value(Value::Text(value))) => {
                     let value = value.trim();
                     if !value.is_empty() && value.len() < self.core.jmap.mailbox_name_max_len {
+                        if !self.core.jmap.is_mailbox_name_allowed(value) {
+                            return Ok(Err(SetError::invalid_properties()
+                                .with_property(Property::Name)
+                                .with_description("Mailbox name is not allowed.")));
+                        }
                         Value::Text(value.to_string())
                     } else {
                         return Ok(Err(SetError::invalid_properties()
@@ -590,7 +638,7 @@ impl MailboxSet for Server {
                 .map_or(u32::MAX, |(mailbox_id, _)| *mailbox_id + 1);
             let mut mailbox_parent_id = mailbox_parent_id.document_id();
             let mut success = false;
-            for depth in 0..self.core.jmap.mailbox_max_depth {
+            for depth in 0..ctx.limits.max_depth {
                 if mailbox_parent_id == current_mailbox_id {
                     return Ok(Err(SetError::invalid_properties()
                         .with_property(Property::ParentId)
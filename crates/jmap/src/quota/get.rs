@@ -5,6 +5,7 @@
  */
 
 use common::{auth::AccessToken, Server};
+use email::ingest::EmailIngest;
 use jmap_proto::{
     method::get::{GetRequest, GetResponse, RequestArguments},
     object::Object,
@@ -18,8 +19,13 @@ pub trait QuotaGet: Sync + Send {
         request: GetRequest<RequestArguments>,
         access_token: &AccessToken,
     ) -> impl Future<Output = trc::Result<GetResponse>> + Send;
+
+    fn quota_ids(&self, access_token: &AccessToken) -> Vec<u32>;
 }
 
+pub const QUOTA_STORAGE: u32 = 0;
+pub const QUOTA_MESSAGES: u32 = 1;
+
 impl QuotaGet for Server {
     async fn quota_get(
         &self,
@@ -40,11 +46,7 @@ impl QuotaGet for Server {
             Property::Types,
         ]);
         let account_id = request.account_id.document_id();
-        let quota_ids = if access_token.quota > 0 {
-            vec![0u32]
-        } else {
-            vec![]
-        };
+        let quota_ids = self.quota_ids(access_token);
         let ids = if let Some(ids) = ids {
             ids
         } else {
@@ -69,12 +71,24 @@ impl QuotaGet for Server {
             for property in &properties {
                 let value = match property {
                     Property::Id => Value::Id(id),
+                    Property::ResourceType if document_id == QUOTA_MESSAGES => {
+                        "count".to_string().into()
+                    }
                     Property::ResourceType => "octets".to_string().into(),
+                    Property::Used if document_id == QUOTA_MESSAGES => {
+                        self.email_count(account_id).await?.into()
+                    }
                     Property::Used => (self.get_used_quota(account_id).await? as u64).into(),
+                    Property::HardLimit if document_id == QUOTA_MESSAGES => {
+                        self.core.jmap.mail_max_messages.into()
+                    }
                     Property::HardLimit => access_token.quota.into(),
                     Property::Scope => "account".to_string().into(),
                     Property::Name => access_token.name.clone().into(),
                     Property::Description => access_token.description.clone().into(),
+                    Property::Types if document_id == QUOTA_MESSAGES => {
+                        vec![Value::Text(DataType::Email.to_string())].into()
+                    }
                     Property::Types => vec![
                         Value::Text(DataType::Email.to_string()),
                         Value::Text(DataType::SieveScript.to_string()),
@@ -90,4 +104,15 @@ impl QuotaGet for Server {
 
         Ok(response)
     }
+
+    fn quota_ids(&self, access_token: &AccessToken) -> Vec<u32> {
+        let mut quota_ids = Vec::with_capacity(2);
+        if access_token.quota > 0 {
+            quota_ids.push(QUOTA_STORAGE);
+        }
+        if self.core.jmap.mail_max_messages > 0 {
+            quota_ids.push(QUOTA_MESSAGES);
+        }
+        quota_ids
+    }
 }
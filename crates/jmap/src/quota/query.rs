@@ -11,6 +11,8 @@ use jmap_proto::{
 };
 use std::future::Future;
 
+use super::get::QuotaGet;
+
 pub trait QuotaQuery: Sync + Send {
     fn quota_query(
         &self,
@@ -25,17 +27,19 @@ impl QuotaQuery for Server {
         request: QueryRequest<RequestArguments>,
         access_token: &AccessToken,
     ) -> trc::Result<QueryResponse> {
+        let ids = self
+            .quota_ids(access_token)
+            .into_iter()
+            .map(|id| Id::new(id as u64))
+            .collect::<Vec<_>>();
+
         Ok(QueryResponse {
             account_id: request.account_id,
             query_state: State::Initial,
             can_calculate_changes: false,
             position: 0,
-            ids: if access_token.quota > 0 {
-                vec![Id::new(0)]
-            } else {
-                vec![]
-            },
-            total: Some(1),
+            total: Some(ids.len()),
+            ids,
             limit: None,
         })
 
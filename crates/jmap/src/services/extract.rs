@@ -0,0 +1,163 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, process::Stdio, time::Instant};
+
+use common::{
+    config::jmap::settings::{TextExtractorConfig, TextExtractorMethod},
+    Server,
+};
+use mail_parser::{Message, MessagePart, MimeHeaders, PartType};
+use tokio::{io::AsyncWriteExt, process::Command};
+use trc::TaskQueueEvent;
+
+pub trait TextExtractor: Sync + Send {
+    fn extract_attachment_text(
+        &self,
+        message: &Message<'_>,
+        account_id: u32,
+        document_id: u32,
+    ) -> impl Future<Output = Vec<String>> + Send;
+}
+
+impl TextExtractor for Server {
+    async fn extract_attachment_text(
+        &self,
+        message: &Message<'_>,
+        account_id: u32,
+        document_id: u32,
+    ) -> Vec<String> {
+        let config = &self.core.jmap.text_extractor;
+        if matches!(config.method, TextExtractorMethod::Disabled) {
+            return Vec::new();
+        }
+
+        let mut results = Vec::new();
+        for part in message
+            .parts
+            .iter()
+            .filter(|part| is_image_attachment(part, config))
+            .take(config.max_parts)
+        {
+            let op_start = Instant::now();
+            let content_type = part
+                .content_type()
+                .map(|ct| format!("{}/{}", ct.ctype(), ct.subtype().unwrap_or_default()))
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+
+            match extract_text(config, &content_type, part.contents()).await {
+                Ok(text) => {
+                    trc::event!(
+                        TaskQueue(TaskQueueEvent::TextExtract),
+                        AccountId = account_id,
+                        DocumentId = document_id,
+                        Type = content_type,
+                        Size = text.len(),
+                        Elapsed = op_start.elapsed(),
+                    );
+
+                    if !text.is_empty() {
+                        results.push(text);
+                    }
+                }
+                Err(reason) => {
+                    trc::event!(
+                        TaskQueue(TaskQueueEvent::TextExtractError),
+                        AccountId = account_id,
+                        DocumentId = document_id,
+                        Type = content_type,
+                        Reason = reason,
+                        Elapsed = op_start.elapsed(),
+                    );
+                }
+            }
+        }
+
+        results
+    }
+}
+
+fn is_image_attachment(part: &MessagePart<'_>, config: &TextExtractorConfig) -> bool {
+    matches!(part.body, PartType::Binary(_) | PartType::InlineBinary(_))
+        && part.is_content_type("image", "")
+        && !part.contents().is_empty()
+        && part.contents().len() <= config.max_size
+}
+
+async fn extract_text(
+    config: &TextExtractorConfig,
+    content_type: &str,
+    contents: &[u8],
+) -> Result<String, String> {
+    let output = match &config.method {
+        TextExtractorMethod::Http {
+            url,
+            headers,
+            tls_allow_invalid_certs,
+        } => {
+            let response = reqwest::Client::builder()
+                .timeout(config.timeout)
+                .danger_accept_invalid_certs(*tls_allow_invalid_certs)
+                .default_headers(headers.clone())
+                .build()
+                .map_err(|err| format!("Failed to create HTTP client: {err}"))?
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(contents.to_vec())
+                .send()
+                .await
+                .map_err(|err| format!("HTTP request failed: {err}"))?;
+
+            if !response.status().is_success() {
+                return Err(format!(
+                    "Extractor returned HTTP status {}",
+                    response.status()
+                ));
+            }
+
+            response
+                .bytes()
+                .await
+                .map_err(|err| format!("Failed to read HTTP response: {err}"))?
+                .to_vec()
+        }
+        TextExtractorMethod::Command { command, arguments } => {
+            let mut child = Command::new(command)
+                .args(arguments)
+                .env("CONTENT_TYPE", content_type)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|err| format!("Failed to execute {command:?}: {err}"))?;
+
+            // Write the attachment while the output is being read
+            if let Some(mut stdin) = child.stdin.take() {
+                let contents = contents.to_vec();
+                tokio::spawn(async move {
+                    let _ = stdin.write_all(&contents).await;
+                });
+            }
+
+            let output = tokio::time::timeout(config.timeout, child.wait_with_output())
+                .await
+                .map_err(|_| format!("Command {command:?} timed out"))?
+                .map_err(|err| format!("Failed to execute {command:?}: {err}"))?;
+            if !output.status.success() {
+                return Err(format!(
+                    "Command {command:?} exited with status {}",
+                    output.status
+                ));
+            }
+
+            output.stdout
+        }
+        TextExtractorMethod::Disabled => Vec::new(),
+    };
+
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
@@ -0,0 +1,811 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, time::Duration};
+
+use base64::{engine::general_purpose, Engine};
+use common::{Server, KV_LOCK_FETCH_ACCOUNT};
+use directory::Permission;
+use email::{
+    delivery::{IngestMessage, LocalDeliveryStatus, MailDelivery},
+    fetch::{ExternalAccount, ExternalAccountManager, FetchProtocol, FetchState},
+};
+use futures_util::StreamExt;
+use jmap_proto::types::collection::Collection;
+use mail_parser::MessageParser;
+use serde::Deserialize;
+use smtp::reporting::SmtpReporting;
+use store::{
+    ahash::AHashSet,
+    write::{now, BatchBuilder, BlobOp},
+    Serialize,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tokio_rustls::rustls::pki_types::ServerName;
+use utils::BlobHash;
+
+const FETCH_CONCURRENCY: usize = 8;
+const MAX_LINE_LEN: u64 = 64 * 1024;
+
+type Result<T> = std::result::Result<T, String>;
+
+pub trait ExternalFetch: Sync + Send {
+    fn fetch_external_accounts(&self) -> impl Future<Output = ()> + Send;
+
+    fn fetch_external_account(&self, account_id: u32) -> impl Future<Output = ()> + Send;
+}
+
+enum Credentials {
+    Password(String),
+    Bearer(String),
+}
+
+struct FetchContext<'x> {
+    server: &'x Server,
+    account: &'x ExternalAccount,
+    credentials: Credentials,
+    recipient: &'x str,
+    session_id: u64,
+}
+
+struct FetchClient<T> {
+    stream: BufReader<T>,
+    timeout: Duration,
+}
+
+#[derive(Default)]
+struct ImapResponse {
+    untagged: Vec<String>,
+    literals: Vec<Option<Vec<u8>>>,
+}
+
+#[derive(Deserialize)]
+struct OAuthTokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+impl ExternalFetch for Server {
+    async fn fetch_external_accounts(&self) {
+        if let Ok(Some(account_ids)) = self.get_document_ids(u32::MAX, Collection::Principal).await
+        {
+            futures_util::stream::iter(account_ids)
+                .map(|account_id| self.fetch_external_account(account_id))
+                .buffer_unordered(FETCH_CONCURRENCY)
+                .collect::<Vec<_>>()
+                .await;
+        }
+    }
+
+    async fn fetch_external_account(&self, account_id: u32) {
+        let accounts = match self.external_accounts_get(account_id).await {
+            Ok(accounts) if !accounts.accounts.is_empty() => accounts.accounts,
+            Ok(_) => return,
+            Err(err) => {
+                trc::error!(err
+                    .account_id(account_id)
+                    .details("Failed to obtain external accounts."));
+                return;
+            }
+        };
+
+        // Obtain the account's address, skipping accounts without permission
+        let recipient = match self.get_access_token(account_id).await {
+            Ok(access_token) if access_token.has_permission(Permission::ManageExternalAccounts) => {
+                match access_token.emails.first() {
+                    Some(email) => email.clone(),
+                    None => return,
+                }
+            }
+            Ok(_) => return,
+            Err(err) => {
+                trc::error!(err
+                    .account_id(account_id)
+                    .details("Failed to obtain access token."));
+                return;
+            }
+        };
+
+        // Lock account
+        let lock_key = account_id.to_be_bytes();
+        match self
+            .in_memory_store()
+            .try_lock(KV_LOCK_FETCH_ACCOUNT, &lock_key, 3600)
+            .await
+        {
+            Ok(true) => (),
+            Ok(false) => {
+                return;
+            }
+            Err(err) => {
+                trc::error!(err
+                    .account_id(account_id)
+                    .details("Failed to lock account."));
+                return;
+            }
+        }
+
+        for account in accounts
+            .into_iter()
+            .filter(|account| account.enabled)
+            .take(self.core.jmap.fetch_max_accounts)
+        {
+            let session_id = self.inner.data.span_id_gen.generate().unwrap_or_else(now);
+            let mut state = account.state.clone();
+            let total_fetched = state.total_fetched;
+            state.last_fetch = now();
+
+            let (result, refresh_token) = match self.fetch_credentials(&account, &mut state).await {
+                Ok((credentials, refresh_token)) => (
+                    FetchContext {
+                        server: self,
+                        account: &account,
+                        credentials,
+                        recipient: &recipient,
+                        session_id,
+                    }
+                    .fetch(&mut state)
+                    .await,
+                    refresh_token,
+                ),
+                Err(err) => (Err(err), None),
+            };
+
+            match result {
+                Ok(_) => {
+                    state.last_success = state.last_fetch;
+                    state.last_error = None;
+
+                    trc::event!(
+                        MessageIngest(trc::MessageIngestEvent::Fetch),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        Id = account.id.clone(),
+                        Hostname = account.host.clone(),
+                        Total = state.total_fetched - total_fetched,
+                    );
+                }
+                Err(err) => {
+                    trc::event!(
+                        MessageIngest(trc::MessageIngestEvent::FetchError),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        Id = account.id.clone(),
+                        Hostname = account.host.clone(),
+                        Total = state.total_fetched - total_fetched,
+                        Reason = err.clone(),
+                    );
+
+                    state.last_error = Some(err);
+                }
+            }
+
+            if let Err(err) = self
+                .external_account_update(account_id, &account.id, state, refresh_token)
+                .await
+            {
+                trc::error!(err
+                    .account_id(account_id)
+                    .details("Failed to update external account state."));
+            }
+        }
+
+        if let Err(err) = self
+            .in_memory_store()
+            .remove_lock(KV_LOCK_FETCH_ACCOUNT, &lock_key)
+            .await
+        {
+            trc::error!(err
+                .account_id(account_id)
+                .details("Failed to delete fetch lock."));
+        }
+    }
+}
+
+trait FetchCredentials {
+    fn fetch_credentials(
+        &self,
+        account: &ExternalAccount,
+        state: &mut FetchState,
+    ) -> impl Future<Output = Result<(Credentials, Option<String>)>> + Send;
+}
+
+impl FetchCredentials for Server {
+    async fn fetch_credentials(
+        &self,
+        account: &ExternalAccount,
+        state: &mut FetchState,
+    ) -> Result<(Credentials, Option<String>)> {
+        let oauth = match (&account.secret, &account.oauth) {
+            (_, Some(oauth)) => oauth,
+            (Some(secret), None) => return Ok((Credentials::Password(secret.clone()), None)),
+            (None, None) => return Err("No credentials configured".to_string()),
+        };
+
+        // Reuse the access token until it is about to expire
+        if let Some((token, _)) = state
+            .access_token
+            .as_ref()
+            .filter(|(_, expires)| *expires > now() + 60)
+        {
+            return Ok((Credentials::Bearer(token.clone()), None));
+        }
+
+        let client = self
+            .core
+            .jmap
+            .fetch_oauth_clients
+            .get(&oauth.client)
+            .ok_or_else(|| format!("OAuth client {:?} is not configured", oauth.client))?;
+        let mut params = vec![
+            ("grant_type", "refresh_token"),
+            ("refresh_token", oauth.refresh_token.as_str()),
+            ("client_id", client.client_id.as_str()),
+        ];
+        if let Some(client_secret) = &client.client_secret {
+            params.push(("client_secret", client_secret.as_str()));
+        }
+
+        let response = reqwest::Client::builder()
+            .timeout(self.core.jmap.fetch_timeout)
+            .build()
+            .map_err(|err| format!("Failed to create HTTP client: {err}"))?
+            .post(&client.token_url)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|err| format!("OAuth token request failed: {err}"))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "OAuth token endpoint returned HTTP status {}",
+                response.status()
+            ));
+        }
+        let response = response
+            .bytes()
+            .await
+            .map_err(|err| format!("Failed to read OAuth token response: {err}"))?;
+        let response = serde_json::from_slice::<OAuthTokenResponse>(&response)
+            .map_err(|err| format!("Invalid OAuth token response: {err}"))?;
+
+        state.access_token = Some((
+            response.access_token.clone(),
+            now() + response.expires_in.unwrap_or(3600),
+        ));
+
+        Ok((
+            Credentials::Bearer(response.access_token),
+            response
+                .refresh_token
+                .filter(|token| token != &oauth.refresh_token),
+        ))
+    }
+}
+
+impl FetchContext<'_> {
+    async fn fetch(&self, state: &mut FetchState) -> Result<()> {
+        let config = &self.server.core.jmap;
+        let stream = tokio::time::timeout(
+            config.fetch_timeout,
+            TcpStream::connect((self.account.host.as_str(), self.account.port_or_default())),
+        )
+        .await
+        .map_err(|_| "Connection timed out".to_string())?
+        .map_err(|err| format!("Failed to connect: {err}"))?;
+
+        if self.account.tls {
+            let tls_connector = if config.fetch_allow_invalid_certs {
+                &self.server.inner.data.smtp_connectors.dummy_verify
+            } else {
+                &self.server.inner.data.smtp_connectors.pki_verify
+            };
+            let server_name = ServerName::try_from(self.account.host.clone())
+                .map_err(|_| "Invalid TLS server name".to_string())?;
+            let stream = tokio::time::timeout(
+                config.fetch_timeout,
+                tls_connector.connect(server_name, stream),
+            )
+            .await
+            .map_err(|_| "TLS handshake timed out".to_string())?
+            .map_err(|err| format!("TLS handshake failed: {err}"))?;
+
+            self.fetch_with(FetchClient::new(stream, config.fetch_timeout), state)
+                .await
+        } else {
+            self.fetch_with(FetchClient::new(stream, config.fetch_timeout), state)
+                .await
+        }
+    }
+
+    async fn fetch_with<T: AsyncRead + AsyncWrite + Unpin + Send>(
+        &self,
+        mut client: FetchClient<T>,
+        state: &mut FetchState,
+    ) -> Result<()> {
+        match self.account.protocol {
+            FetchProtocol::Pop3 => self.fetch_pop3(&mut client, state).await,
+            FetchProtocol::Imap => self.fetch_imap(&mut client, state).await,
+        }
+    }
+
+    async fn fetch_pop3<T: AsyncRead + AsyncWrite + Unpin + Send>(
+        &self,
+        client: &mut FetchClient<T>,
+        state: &mut FetchState,
+    ) -> Result<()> {
+        client.pop3_response().await?;
+
+        // Authenticate
+        match &self.credentials {
+            Credentials::Password(secret) => {
+                client
+                    .pop3_command(format!("USER {}\r\n", self.account.username))
+                    .await?;
+                client.pop3_command(format!("PASS {secret}\r\n")).await?;
+            }
+            Credentials::Bearer(token) => {
+                client
+                    .write(format!("AUTH XOAUTH2 {}\r\n", self.xoauth2(token)).as_bytes())
+                    .await?;
+                let line = client.read_line().await?;
+                if line.starts_with(b"+ ") {
+                    // Server sent an error challenge, which must be acknowledged
+                    client.write(b"\r\n").await?;
+                    client.pop3_response().await?;
+                } else if !line.starts_with(b"+OK") {
+                    return Err(pop3_error(&line));
+                }
+            }
+        }
+
+        // Obtain message list
+        let uids = client.pop3_list("UIDL\r\n").await?;
+        let sizes = client.pop3_list("LIST\r\n").await?;
+        let mut seen = state.seen_uids.iter().cloned().collect::<AHashSet<_>>();
+        let pending = uids
+            .iter()
+            .filter(|(_, uid)| !self.account.keep || !seen.contains(uid))
+            .take(self.server.core.jmap.fetch_max_messages)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let result = async {
+            let max_size = self.server.core.jmap.mail_max_size;
+            for (num, uid) in pending {
+                let size = sizes
+                    .iter()
+                    .find(|(n, _)| n == &num)
+                    .and_then(|(_, size)| size.parse::<usize>().ok())
+                    .unwrap_or_default();
+                if size <= max_size {
+                    client.pop3_command(format!("RETR {num}\r\n")).await?;
+                    let message = client.pop3_multiline().await?;
+                    self.deliver(message).await?;
+                    state.total_fetched += 1;
+                    if !self.account.keep {
+                        client.pop3_command(format!("DELE {num}\r\n")).await?;
+                    }
+                }
+                if self.account.keep {
+                    seen.insert(uid);
+                }
+            }
+
+            Ok(())
+        }
+        .await;
+
+        // Deletions are only committed after QUIT
+        let quit = client.pop3_command("QUIT\r\n").await;
+
+        // Only UIDs that are still present on the server are remembered
+        state.seen_uids = uids
+            .into_iter()
+            .filter_map(|(_, uid)| seen.contains(&uid).then_some(uid))
+            .collect();
+
+        result.and(quit)
+    }
+
+    async fn fetch_imap<T: AsyncRead + AsyncWrite + Unpin + Send>(
+        &self,
+        client: &mut FetchClient<T>,
+        state: &mut FetchState,
+    ) -> Result<()> {
+        let greeting = client.read_line().await?;
+        if !greeting.starts_with(b"* OK") && !greeting.starts_with(b"* PREAUTH") {
+            return Err(format!(
+                "Unexpected IMAP greeting: {}",
+                String::from_utf8_lossy(&greeting).trim_end()
+            ));
+        }
+
+        // Authenticate
+        match &self.credentials {
+            Credentials::Password(secret) => {
+                client
+                    .imap_command(
+                        "A1",
+                        &format!(
+                            "LOGIN {} {}",
+                            imap_quote(&self.account.username)?,
+                            imap_quote(secret)?
+                        ),
+                        0,
+                    )
+                    .await?;
+            }
+            Credentials::Bearer(token) => {
+                client
+                    .imap_command(
+                        "A1",
+                        &format!("AUTHENTICATE XOAUTH2 {}", self.xoauth2(token)),
+                        0,
+                    )
+                    .await?;
+            }
+        }
+        let has_uidplus = client
+            .imap_command("A2", "CAPABILITY", 0)
+            .await?
+            .untagged
+            .iter()
+            .any(|line| {
+                line.starts_with("CAPABILITY ")
+                    && line
+                        .split_ascii_whitespace()
+                        .any(|cap| cap.eq_ignore_ascii_case("UIDPLUS"))
+            });
+
+        // Select folder, in read-only mode when messages are kept on the server
+        let response = client
+            .imap_command(
+                "A3",
+                &format!(
+                    "{} {}",
+                    if self.account.keep {
+                        "EXAMINE"
+                    } else {
+                        "SELECT"
+                    },
+                    imap_quote(self.account.folder.as_deref().unwrap_or("INBOX"))?
+                ),
+                0,
+            )
+            .await?;
+        let uid_validity = response
+            .untagged
+            .iter()
+            .find_map(|line| {
+                line.split_once("[UIDVALIDITY ")
+                    .and_then(|(_, value)| value.split_once(']'))
+                    .and_then(|(value, _)| value.trim().parse::<u32>().ok())
+            })
+            .unwrap_or_default();
+        if uid_validity != state.uid_validity {
+            state.uid_validity = uid_validity;
+            state.last_uid = 0;
+        }
+
+        // Search for new messages
+        let mut uids = client
+            .imap_command("A4", &format!("UID SEARCH UID {}:*", state.last_uid + 1), 0)
+            .await?
+            .untagged
+            .iter()
+            .filter_map(|line| line.strip_prefix("SEARCH"))
+            .flat_map(|line| line.split_ascii_whitespace())
+            .filter_map(|uid| uid.parse::<u32>().ok())
+            .filter(|uid| *uid > state.last_uid)
+            .collect::<Vec<_>>();
+        uids.sort_unstable();
+        uids.truncate(self.server.core.jmap.fetch_max_messages);
+
+        let result = async {
+            let mut deleted = Vec::new();
+            for uid in uids {
+                let message = client
+                    .imap_command(
+                        "A5",
+                        &format!("UID FETCH {uid} BODY.PEEK[]"),
+                        self.server.core.jmap.mail_max_size,
+                    )
+                    .await?
+                    .literals
+                    .into_iter()
+                    .next();
+                match message {
+                    Some(Some(message)) => {
+                        self.deliver(message).await?;
+                        state.total_fetched += 1;
+                    }
+                    Some(None) => {
+                        // Message exceeds the maximum size
+                    }
+                    None => {
+                        // Message was expunged in the meantime
+                        continue;
+                    }
+                }
+                state.last_uid = uid;
+
+                if !self.account.keep {
+                    client
+                        .imap_command(
+                            "A6",
+                            &format!("UID STORE {uid} +FLAGS.SILENT (\\Deleted)"),
+                            0,
+                        )
+                        .await?;
+                    deleted.push(uid.to_string());
+                }
+            }
+
+            if !deleted.is_empty() {
+                if has_uidplus {
+                    client
+                        .imap_command("A7", &format!("UID EXPUNGE {}", deleted.join(",")), 0)
+                        .await?;
+                } else {
+                    client.imap_command("A7", "EXPUNGE", 0).await?;
+                }
+            }
+
+            Ok(())
+        }
+        .await;
+
+        let logout = client.imap_command("A8", "LOGOUT", 0).await.map(|_| ());
+
+        result.and(logout)
+    }
+
+    async fn deliver(&self, message: Vec<u8>) -> Result<()> {
+        let server = self.server;
+        let sender_address = MessageParser::new()
+            .parse_headers(&message)
+            .and_then(|message| message.return_address().map(|addr| addr.to_lowercase()))
+            .unwrap_or_default();
+
+        // Reserve and write blob
+        let message_blob = BlobHash::from(message.as_slice());
+        let message_size = message.len();
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            BlobOp::Reserve {
+                hash: message_blob.clone(),
+                until: now() + 120,
+            },
+            0u32.serialize(),
+        );
+        server
+            .store()
+            .write(batch.build())
+            .await
+            .map_err(|err| format!("Failed to write message: {err}"))?;
+        server
+            .blob_store()
+            .put_blob(message_blob.as_slice(), message.as_ref())
+            .await
+            .map_err(|err| format!("Failed to write message: {err}"))?;
+
+        let result = server
+            .deliver_message(IngestMessage {
+                sender_address,
+                recipients: vec![self.recipient.to_string()],
+                message_blob,
+                message_size,
+                session_id: self.session_id,
+            })
+            .await;
+
+        // Send any messages generated by Sieve scripts
+        for autogenerated in result.autogenerated {
+            server
+                .send_autogenerated(
+                    autogenerated.sender_address,
+                    autogenerated.recipients.into_iter(),
+                    autogenerated.message,
+                    Some(&server.core.sieve.sign),
+                    self.session_id,
+                )
+                .await;
+        }
+
+        match result.status.into_iter().next() {
+            Some(LocalDeliveryStatus::Success) => Ok(()),
+            Some(LocalDeliveryStatus::TemporaryFailure { reason })
+            | Some(LocalDeliveryStatus::PermanentFailure { reason, .. }) => {
+                Err(format!("Delivery failed: {reason}"))
+            }
+            None => Err("Delivery failed".to_string()),
+        }
+    }
+
+    fn xoauth2(&self, token: &str) -> String {
+        general_purpose::STANDARD.encode(format!(
+            "user={}\x01auth=Bearer {}\x01\x01",
+            self.account.username, token
+        ))
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> FetchClient<T> {
+    fn new(stream: T, timeout: Duration) -> Self {
+        FetchClient {
+            stream: BufReader::new(stream),
+            timeout,
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<Vec<u8>> {
+        let mut line = Vec::new();
+        let bytes_read = tokio::time::timeout(
+            self.timeout,
+            (&mut self.stream)
+                .take(MAX_LINE_LEN)
+                .read_until(b'\n', &mut line),
+        )
+        .await
+        .map_err(|_| "Read timed out".to_string())?
+        .map_err(|err| format!("Read failed: {err}"))?;
+
+        if bytes_read == 0 {
+            Err("Connection closed by remote server".to_string())
+        } else if !line.ends_with(b"\n") {
+            Err("Line too long".to_string())
+        } else {
+            Ok(line)
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        tokio::time::timeout(self.timeout, async {
+            let stream = self.stream.get_mut();
+            stream.write_all(bytes).await?;
+            stream.flush().await
+        })
+        .await
+        .map_err(|_| "Write timed out".to_string())?
+        .map_err(|err| format!("Write failed: {err}"))
+    }
+
+    async fn pop3_response(&mut self) -> Result<()> {
+        let line = self.read_line().await?;
+        if line.starts_with(b"+OK") {
+            Ok(())
+        } else {
+            Err(pop3_error(&line))
+        }
+    }
+
+    async fn pop3_command(&mut self, command: impl AsRef<str>) -> Result<()> {
+        self.write(command.as_ref().as_bytes()).await?;
+        self.pop3_response().await
+    }
+
+    async fn pop3_multiline(&mut self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+            if line == b".\r\n" || line == b".\n" {
+                return Ok(data);
+            }
+            data.extend_from_slice(line.strip_prefix(b".").unwrap_or(&line));
+        }
+    }
+
+    async fn pop3_list(&mut self, command: &str) -> Result<Vec<(String, String)>> {
+        self.pop3_command(command).await?;
+        let data = self.pop3_multiline().await?;
+        Ok(String::from_utf8_lossy(&data)
+            .lines()
+            .filter_map(|line| {
+                let (num, value) = line.trim().split_once(' ')?;
+                Some((num.to_string(), value.trim().to_string()))
+            })
+            .collect())
+    }
+
+    async fn imap_command(
+        &mut self,
+        tag: &str,
+        command: &str,
+        max_literal: usize,
+    ) -> Result<ImapResponse> {
+        self.write(format!("{tag} {command}\r\n").as_bytes())
+            .await?;
+
+        let mut response = ImapResponse::default();
+        let mut untagged = String::new();
+        loop {
+            let line = self.read_line().await?;
+            let line = String::from_utf8_lossy(&line);
+            let text = line.trim_end();
+
+            if !untagged.is_empty() {
+                untagged.push_str(text);
+            } else if let Some(status) = text
+                .strip_prefix(tag)
+                .and_then(|status| status.strip_prefix(' '))
+            {
+                return if status
+                    .get(..3)
+                    .is_some_and(|status| status.eq_ignore_ascii_case("OK "))
+                {
+                    Ok(response)
+                } else {
+                    Err(format!("IMAP server error: {status}"))
+                };
+            } else if text.starts_with('+') {
+                // Continuation requests are only sent for failed authentications
+                self.write(b"\r\n").await?;
+                continue;
+            } else {
+                untagged.push_str(text.strip_prefix("* ").unwrap_or(text));
+            }
+
+            // Read literal
+            if let Some(size) = untagged
+                .strip_suffix('}')
+                .and_then(|line| line.rsplit_once('{'))
+                .and_then(|(_, size)| size.trim_end_matches('+').parse::<usize>().ok())
+            {
+                if size <= max_literal {
+                    let mut literal = vec![0u8; size];
+                    tokio::time::timeout(self.timeout, self.stream.read_exact(&mut literal))
+                        .await
+                        .map_err(|_| "Read timed out".to_string())?
+                        .map_err(|err| format!("Read failed: {err}"))?;
+                    response.literals.push(Some(literal));
+                } else {
+                    tokio::time::timeout(
+                        self.timeout,
+                        tokio::io::copy(
+                            &mut (&mut self.stream).take(size as u64),
+                            &mut tokio::io::sink(),
+                        ),
+                    )
+                    .await
+                    .map_err(|_| "Read timed out".to_string())?
+                    .map_err(|err| format!("Read failed: {err}"))?;
+                    response.literals.push(None);
+                }
+            } else {
+                response.untagged.push(std::mem::take(&mut untagged));
+            }
+        }
+    }
+}
+
+fn pop3_error(line: &[u8]) -> String {
+    format!(
+        "POP3 server error: {}",
+        String::from_utf8_lossy(line).trim_end()
+    )
+}
+
+fn imap_quote(value: &str) -> Result<String> {
+    if value.contains(['\r', '\n']) {
+        return Err("Invalid characters in IMAP string".to_string());
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        if ch == '"' || ch == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(ch);
+    }
+    quoted.push('"');
+    Ok(quoted)
+}
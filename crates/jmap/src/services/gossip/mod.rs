@@ -65,6 +65,7 @@ pub struct Peer {
     pub epoch: EpochId,
     pub gen_config: GenerationId,
     pub gen_lists: GenerationId,
+    pub gen_cache: u64,
     pub state: State,
 
     // Heartbeat state
@@ -82,6 +83,7 @@ pub struct PeerStatus {
     pub epoch: EpochId,
     pub gen_config: GenerationId,
     pub gen_lists: GenerationId,
+    pub gen_cache: u64,
 }
 
 impl From<&Peer> for PeerStatus {
@@ -91,6 +93,7 @@ impl From<&Peer> for PeerStatus {
             epoch: peer.epoch,
             gen_config: peer.gen_config,
             gen_lists: peer.gen_lists,
+            gen_cache: peer.gen_cache,
         }
     }
 }
@@ -106,6 +109,14 @@ impl From<&Gossiper> for PeerStatus {
                 .data
                 .blocked_ips_version
                 .load(Ordering::Relaxed),
+            gen_cache: cluster
+                .inner
+                .shared_core
+                .load()
+                .storage
+                .data
+                .value_cache()
+                .map_or(0, |cache| cache.generation()),
         }
     }
 }
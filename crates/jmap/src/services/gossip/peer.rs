@@ -14,6 +14,7 @@ impl Peer {
             epoch: 0,
             gen_config: 0,
             gen_lists: 0,
+            gen_cache: 0,
             addr,
             state: State::Seed,
             last_heartbeat: Instant::now(),
@@ -80,6 +81,7 @@ impl From<PeerStatus> for Peer {
             epoch: value.epoch,
             gen_config: value.gen_config,
             gen_lists: value.gen_lists,
+            gen_cache: value.gen_cache,
             state: State::Alive,
             last_heartbeat: Instant::now(),
             hb_window: vec![0; HEARTBEAT_WINDOW],
@@ -100,6 +100,7 @@ impl Gossiper {
         let mut remove_seeds = false;
         let mut update_config = false;
         let mut update_lists = false;
+        let mut update_cache = false;
 
         'outer: for (pos, peer) in peers.into_iter().enumerate() {
             if peer.addr == self.addr {
@@ -137,6 +138,18 @@ impl Gossiper {
                                     update_lists = true;
                                 }
                             }
+                            if local_peer.gen_cache != peer.gen_cache {
+                                local_peer.gen_cache = peer.gen_cache;
+                                if local_peer.hb_sum > 0 {
+                                    trc::event!(
+                                        Cluster(ClusterEvent::PeerHasChanges),
+                                        RemoteIp = peer.addr,
+                                        Details = "cache"
+                                    );
+
+                                    update_cache = true;
+                                }
+                            }
                         }
 
                         continue 'outer;
@@ -160,6 +173,13 @@ impl Gossiper {
                 .await;
         }
 
+        // Discard cached values modified by other peers
+        if update_cache
+            && let Some(cache) = self.inner.shared_core.load().storage.data.value_cache()
+        {
+            cache.clear();
+        }
+
         // Reload settings
         if update_config || update_lists {
             let server = self.inner.build_server();
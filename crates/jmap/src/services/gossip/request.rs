@@ -56,6 +56,7 @@ impl Request {
                 epoch: EpochId::from_leb128_it(&mut it)?,
                 gen_config: it.next().copied()?,
                 gen_lists: it.next().copied()?,
+                gen_cache: u64::from_leb128_it(&mut it)?,
             });
         }
         match flags & !(1 << 7) {
@@ -107,6 +108,7 @@ impl Request {
             peer.epoch.to_leb128_bytes(&mut bytes);
             bytes.push(peer.gen_config);
             bytes.push(peer.gen_lists);
+            peer.gen_cache.to_leb128_bytes(&mut bytes);
         }
 
         bytes
@@ -25,6 +25,8 @@ use trc::{Collector, MetricType, PurgeEvent};
 
 use crate::{email::delete::EmailDeletion, JmapMethods, LONG_SLUMBER};
 
+use super::fetch::ExternalFetch;
+
 #[derive(PartialEq, Eq)]
 struct Action {
     due: Instant,
@@ -38,6 +40,7 @@ enum ActionClass {
     Acme(String),
     OtelMetrics,
     CalculateMetrics,
+    FetchAccounts,
 }
 
 #[derive(Default)]
@@ -63,6 +66,14 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                 );
             }
 
+            // External account fetching
+            if server.core.network.roles.fetch_accounts && server.core.jmap.fetch_max_accounts > 0 {
+                queue.schedule(
+                    Instant::now() + server.core.jmap.fetch_frequency,
+                    ActionClass::FetchAccounts,
+                );
+            }
+
             // Store purges
             if server.core.network.roles.purge_stores {
                 for (idx, schedule) in server.core.storage.purge_schedules.iter().enumerate() {
@@ -243,6 +254,22 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                     server.purge(PurgeType::Account(None), 0).await;
                                 });
                             }
+                            ActionClass::FetchAccounts => {
+                                trc::event!(
+                                    Housekeeper(trc::HousekeeperEvent::Run),
+                                    Type = "fetch_accounts"
+                                );
+
+                                queue.schedule(
+                                    Instant::now() + server.core.jmap.fetch_frequency,
+                                    ActionClass::FetchAccounts,
+                                );
+
+                                let server = server.clone();
+                                tokio::spawn(async move {
+                                    server.fetch_external_accounts().await;
+                                });
+                            }
                             ActionClass::Store(idx) => {
                                 if let Some(schedule) =
                                     server.core.storage.purge_schedules.get(idx).cloned()
@@ -323,6 +350,8 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                     false
                                 };
 
+                                server.inner.ipc.update_channel_metrics();
+
                                 let server = server.clone();
                                 tokio::spawn(async move {
                                     if server.core.network.roles.calculate_metrics {
@@ -353,6 +382,12 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                                         .details("Failed to obtain domain count"));
                                                 }
                                             }
+
+                                            if let Err(err) = server.record_storage_usage().await {
+                                                trc::error!(
+                                                    err.details("Failed to record storage usage")
+                                                );
+                                            }
                                         }
                                     }
 
@@ -4,7 +4,10 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use common::{core::BuildServer, Inner, Server, KV_LOCK_EMAIL_TASK};
 use directory::{
@@ -12,10 +15,12 @@ use directory::{
     Type,
 };
 use email::{index::IndexMessageText, metadata::MessageMetadata};
+use futures_util::StreamExt;
 use jmap_proto::types::{collection::Collection, property::Property};
+use nlp::language::Language;
 use store::{
     ahash::AHashMap,
-    fts::index::FtsDocument,
+    fts::{index::FtsDocument, Field},
     roaring::RoaringBitmap,
     write::{
         key::{DeserializeBigEndian, KeySerializer},
@@ -30,6 +35,8 @@ use utils::{BlobHash, BLOB_HASH_LEN};
 
 use crate::{blob::download::BlobDownload, email::bayes::EmailBayesTrain};
 
+use super::extract::TextExtractor;
+
 #[derive(Debug, Clone)]
 pub struct EmailTask {
     account_id: u32,
@@ -48,19 +55,25 @@ pub enum EmailTaskAction {
 const FTS_LOCK_EXPIRY: u64 = 60 * 5;
 const BAYES_LOCK_EXPIRY: u64 = 60 * 30;
 
+// Interval between retries of deferred tasks while the blob store is failing
+const DEFERRED_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
 pub fn spawn_email_queue_task(inner: Arc<Inner>) {
     tokio::spawn(async move {
         let rx = inner.ipc.index_tx.clone();
         let mut locked_seq_ids = AHashMap::new();
         loop {
             // Index any queued messages
-            inner
-                .build_server()
-                .email_task_queued(&mut locked_seq_ids)
-                .await;
-
-            // Wait for a signal to index more messages
-            rx.notified().await;
+            let server = inner.build_server();
+            server.email_task_queued(&mut locked_seq_ids).await;
+
+            // Wait for a signal to index more messages, or retry deferred
+            // tasks periodically while the blob store is failing
+            if server.blob_store().is_degraded() {
+                let _ = tokio::time::timeout(DEFERRED_RETRY_INTERVAL, rx.notified()).await;
+            } else {
+                rx.notified().await;
+            }
         }
     });
 }
@@ -70,6 +83,12 @@ pub trait Indexer: Sync + Send {
         &self,
         locked_seq_ids: &mut AHashMap<u64, Instant>,
     ) -> impl Future<Output = ()> + Send;
+    fn email_task(&self, event: &EmailTask) -> impl Future<Output = Option<Instant>> + Send;
+    fn process_email_task(
+        &self,
+        event: &EmailTask,
+        op_start: Instant,
+    ) -> impl Future<Output = ()> + Send;
     fn try_lock_index(&self, event: &EmailTask) -> impl Future<Output = bool> + Send;
     fn remove_index_lock(&self, event: &EmailTask) -> impl Future<Output = ()> + Send;
     fn reindex(
@@ -81,6 +100,11 @@ pub trait Indexer: Sync + Send {
 
 impl Indexer for Server {
     async fn email_task_queued(&self, locked_seq_ids: &mut AHashMap<u64, Instant>) {
+        // Tasks stay queued until the blob store is available again
+        if !self.blob_store().is_available() {
+            return;
+        }
+
         let from_key = ValueKey::<ValueClass<u32>> {
             account_id: 0,
             collection: 0,
@@ -113,7 +137,7 @@ impl Indexer for Server {
                     let entry = EmailTask::deserialize(key)?;
                     if locked_seq_ids
                         .get(&entry.seq)
-                        .is_none_or( |expires| now >= *expires)
+                        .is_none_or(|expires| now >= *expires)
                     {
                         entries.push(entry);
                     }
@@ -128,143 +152,186 @@ impl Indexer for Server {
                     .details("Failed to iterate over index emails"));
             });
 
-        // Add entries to the index
-        let mut unlock_events = Vec::with_capacity(entries.len());
-        for event in entries {
-            let op_start = Instant::now();
-            // Lock index
-            if !self.try_lock_index(&event).await {
-                locked_seq_ids.insert(
-                    event.seq,
-                    Instant::now() + std::time::Duration::from_secs(event.lock_expiry() + 1),
-                );
-                continue;
+        // Group entries by account, tasks for the same account are processed in order
+        let mut accounts: Vec<Vec<EmailTask>> = Vec::new();
+        let mut account_idx: AHashMap<u32, usize> = AHashMap::new();
+        for entry in entries {
+            match account_idx.get(&entry.account_id) {
+                Some(idx) => accounts[*idx].push(entry),
+                None => {
+                    account_idx.insert(entry.account_id, accounts.len());
+                    accounts.push(vec![entry]);
+                }
             }
+        }
 
-            if event.remove_lock() {
-                unlock_events.push(event.clone());
-            }
+        // Process accounts concurrently
+        let locked = futures_util::stream::iter(accounts)
+            .map(|events| async move {
+                let mut locked = Vec::new();
+                for event in events {
+                    if let Some(expires) = self.email_task(&event).await {
+                        locked.push((event.seq, expires));
+                    }
+                }
+                locked
+            })
+            .buffer_unordered(self.core.jmap.index_workers)
+            .collect::<Vec<_>>()
+            .await;
+        for (seq, expires) in locked.into_iter().flatten() {
+            locked_seq_ids.insert(seq, expires);
+        }
 
-            match self
-                .get_property::<Bincode<MessageMetadata>>(
-                    event.account_id,
-                    Collection::Email,
-                    event.document_id,
-                    Property::BodyStructure,
-                )
-                .await
-            {
-                Ok(Some(metadata))
-                    if metadata.inner.blob_hash.as_slice() == event.hash.as_slice() =>
+        // Delete expired locks
+        let now = Instant::now();
+        locked_seq_ids.retain(|_, expires| *expires > now);
+    }
+
+    async fn email_task(&self, event: &EmailTask) -> Option<Instant> {
+        let op_start = Instant::now();
+        // Lock index
+        if !self.try_lock_index(event).await {
+            return Some(Instant::now() + std::time::Duration::from_secs(event.lock_expiry() + 1));
+        }
+
+        self.process_email_task(event, op_start).await;
+
+        if event.remove_lock() || self.blob_store().is_degraded() {
+            self.remove_index_lock(event).await;
+        }
+
+        None
+    }
+
+    async fn process_email_task(&self, event: &EmailTask, op_start: Instant) {
+        match self
+            .get_property::<Bincode<MessageMetadata>>(
+                event.account_id,
+                Collection::Email,
+                event.document_id,
+                Property::BodyStructure,
+            )
+            .await
+        {
+            Ok(Some(metadata)) if metadata.inner.blob_hash.as_slice() == event.hash.as_slice() => {
+                // Obtain raw message
+                let raw_message = match self
+                    .get_blob(&metadata.inner.blob_hash, 0..usize::MAX)
+                    .await
                 {
-                    // Obtain raw message
-                    let raw_message = if let Ok(Some(raw_message)) = self
-                        .get_blob(&metadata.inner.blob_hash, 0..usize::MAX)
-                        .await
-                    {
-                        raw_message
-                    } else {
+                    Ok(Some(raw_message)) => raw_message,
+                    Err(err) if err.is_unavailable() => {
+                        trc::event!(
+                            TaskQueue(TaskQueueEvent::Deferred),
+                            AccountId = event.account_id,
+                            DocumentId = event.document_id,
+                            BlobId = metadata.inner.blob_hash.to_hex(),
+                            CausedBy = err,
+                        );
+                        return;
+                    }
+                    _ => {
                         trc::event!(
                             TaskQueue(TaskQueueEvent::BlobNotFound),
                             AccountId = event.account_id,
                             DocumentId = event.document_id,
                             BlobId = metadata.inner.blob_hash.to_hex(),
                         );
-                        continue;
-                    };
-                    let message = metadata.inner.contents.into_message(&raw_message);
-
-                    match event.action {
-                        EmailTaskAction::Index => {
-                            // Index message
-                            let document =
-                                FtsDocument::with_default_language(self.core.jmap.default_language)
-                                    .with_account_id(event.account_id)
-                                    .with_collection(Collection::Email)
-                                    .with_document_id(event.document_id)
-                                    .index_message(&message);
-                            if let Err(err) = self.core.storage.fts.index(document).await {
-                                trc::error!(err
-                                    .account_id(event.account_id)
-                                    .document_id(event.document_id)
-                                    .details("Failed to index email in FTS index"));
-
-                                continue;
-                            }
-
-                            trc::event!(
-                                TaskQueue(TaskQueueEvent::Index),
-                                AccountId = event.account_id,
-                                Collection = Collection::Email,
-                                DocumentId = event.document_id,
-                                Elapsed = op_start.elapsed(),
-                            );
+                        return;
+                    }
+                };
+                let message = metadata.inner.contents.into_message(&raw_message);
+
+                match event.action {
+                    EmailTaskAction::Index => {
+                        // Extract text from image attachments
+                        let extracted_text = self
+                            .extract_attachment_text(&message, event.account_id, event.document_id)
+                            .await;
+
+                        // Index message
+                        let mut document =
+                            FtsDocument::with_default_language(self.core.jmap.default_language)
+                                .with_account_id(event.account_id)
+                                .with_collection(Collection::Email)
+                                .with_document_id(event.document_id)
+                                .index_message(&message);
+                        for text in extracted_text {
+                            document.index(Field::Attachment, text, Language::Unknown);
                         }
-                        EmailTaskAction::BayesTrain { learn_spam } => {
-                            // Train bayes classifier for account
-                            self.email_bayes_train(event.account_id, 0, message, learn_spam)
-                                .await;
-
-                            trc::event!(
-                                TaskQueue(TaskQueueEvent::BayesTrain),
-                                AccountId = event.account_id,
-                                Collection = Collection::Email,
-                                DocumentId = event.document_id,
-                                Elapsed = op_start.elapsed(),
-                            );
+                        if let Err(err) = self.core.storage.fts.index(document).await {
+                            trc::error!(err
+                                .account_id(event.account_id)
+                                .document_id(event.document_id)
+                                .details("Failed to index email in FTS index"));
+
+                            return;
                         }
-                    }
-                }
 
-                Err(err) => {
-                    trc::error!(err
-                        .account_id(event.account_id)
-                        .document_id(event.document_id)
-                        .caused_by(trc::location!())
-                        .details("Failed to retrieve email metadata"));
+                        trc::event!(
+                            TaskQueue(TaskQueueEvent::Index),
+                            AccountId = event.account_id,
+                            Collection = Collection::Email,
+                            DocumentId = event.document_id,
+                            Elapsed = op_start.elapsed(),
+                        );
+                    }
+                    EmailTaskAction::BayesTrain { learn_spam } => {
+                        // Train bayes classifier for account
+                        self.email_bayes_train(event.account_id, 0, message, learn_spam)
+                            .await;
 
-                    continue;
-                }
-                _ => {
-                    // The message was probably deleted or overwritten
-                    trc::event!(
-                        TaskQueue(TaskQueueEvent::MetadataNotFound),
-                        AccountId = event.account_id,
-                        DocumentId = event.document_id,
-                    );
+                        trc::event!(
+                            TaskQueue(TaskQueueEvent::BayesTrain),
+                            AccountId = event.account_id,
+                            Collection = Collection::Email,
+                            DocumentId = event.document_id,
+                            Elapsed = op_start.elapsed(),
+                        );
+                    }
                 }
             }
 
-            // Remove entry from queue
-            if let Err(err) = self
-                .core
-                .storage
-                .data
-                .write(
-                    BatchBuilder::new()
-                        .with_account_id(event.account_id)
-                        .with_collection(Collection::Email)
-                        .update_document(event.document_id)
-                        .clear(event.value_class())
-                        .build_batch(),
-                )
-                .await
-            {
+            Err(err) => {
                 trc::error!(err
                     .account_id(event.account_id)
                     .document_id(event.document_id)
-                    .details("Failed to remove index email from queue."));
+                    .caused_by(trc::location!())
+                    .details("Failed to retrieve email metadata"));
+
+                return;
+            }
+            _ => {
+                // The message was probably deleted or overwritten
+                trc::event!(
+                    TaskQueue(TaskQueueEvent::MetadataNotFound),
+                    AccountId = event.account_id,
+                    DocumentId = event.document_id,
+                );
             }
         }
 
-        // Unlock entries
-        for event in unlock_events {
-            self.remove_index_lock(&event).await;
+        // Remove entry from queue
+        if let Err(err) = self
+            .core
+            .storage
+            .data
+            .write(
+                BatchBuilder::new()
+                    .with_account_id(event.account_id)
+                    .with_collection(Collection::Email)
+                    .update_document(event.document_id)
+                    .clear(event.value_class())
+                    .build_batch(),
+            )
+            .await
+        {
+            trc::error!(err
+                .account_id(event.account_id)
+                .document_id(event.document_id)
+                .details("Failed to remove index email from queue."));
         }
-
-        // Delete expired locks
-        let now = Instant::now();
-        locked_seq_ids.retain(|_, expires| *expires > now);
     }
 
     async fn try_lock_index(&self, event: &EmailTask) -> bool {
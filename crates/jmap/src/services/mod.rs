@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod extract;
+pub mod fetch;
 pub mod gossip;
 pub mod housekeeper;
 pub mod index;
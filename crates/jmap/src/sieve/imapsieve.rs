@@ -0,0 +1,217 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{
+    config::scripts::ImapSieveCause,
+    expr::{functions::ResolveVariable, Variable, V_RECIPIENT, V_RECIPIENT_DOMAIN},
+    Server,
+};
+use email::{mailbox::MailboxFnc, metadata::MessageMetadata};
+use jmap_proto::types::{collection::Collection, keyword::Keyword, property::Property};
+use mail_parser::MessageParser;
+use smtp::scripts::{event_loop::RunScript, ScriptParameters};
+use store::write::Bincode;
+use trc::AddContext;
+
+use crate::blob::download::BlobDownload;
+
+#[derive(Debug, Clone)]
+pub struct ImapSieveEvent {
+    pub document_id: u32,
+    pub mailbox_id: u32,
+    pub cause: ImapSieveCause,
+    pub changed_flags: Vec<Keyword>,
+}
+
+pub trait ImapSieve: Sync + Send {
+    fn imapsieve_enabled(&self, cause: ImapSieveCause) -> bool;
+
+    fn imapsieve_run(
+        &self,
+        account_id: u32,
+        events: Vec<ImapSieveEvent>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl ImapSieve for Server {
+    fn imapsieve_enabled(&self, cause: ImapSieveCause) -> bool {
+        self.core
+            .sieve
+            .imapsieve
+            .iter()
+            .any(|rule| rule.causes.contains(&cause))
+    }
+
+    async fn imapsieve_run(&self, account_id: u32, events: Vec<ImapSieveEvent>, session_id: u64) {
+        if events.is_empty() {
+            return;
+        }
+
+        if let Err(err) = run_imapsieve(self, account_id, events, session_id).await {
+            trc::error!(err
+                .account_id(account_id)
+                .span_id(session_id)
+                .details("Failed to run IMAPSIEVE scripts"));
+        }
+    }
+}
+
+async fn run_imapsieve(
+    server: &Server,
+    account_id: u32,
+    events: Vec<ImapSieveEvent>,
+    session_id: u64,
+) -> trc::Result<()> {
+    // Resolve the mailboxes the rules are attached to
+    let mut rules = Vec::new();
+    for rule in &server.core.sieve.imapsieve {
+        if !events
+            .iter()
+            .any(|event| rule.causes.contains(&event.cause))
+        {
+            continue;
+        }
+
+        let mailbox_id = match server
+            .mailbox_get_by_name(account_id, &rule.mailbox)
+            .await
+            .caused_by(trc::location!())?
+        {
+            Some(mailbox_id) => Some(mailbox_id),
+            None => server
+                .mailbox_get_by_role(account_id, &rule.mailbox.to_lowercase())
+                .await
+                .caused_by(trc::location!())?,
+        };
+        if let Some(mailbox_id) = mailbox_id {
+            rules.push((rule, mailbox_id));
+        }
+    }
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let access_token = server
+        .get_access_token(account_id)
+        .await
+        .caused_by(trc::location!())?;
+    let user_email = access_token.emails.first().cloned().unwrap_or_default();
+
+    for event in events {
+        let mut raw_message = None;
+
+        for (rule, mailbox_id) in &rules {
+            if *mailbox_id != event.mailbox_id || !rule.causes.contains(&event.cause) {
+                continue;
+            }
+            let Some(script) = server.get_trusted_sieve_script(&rule.script, session_id) else {
+                continue;
+            };
+
+            // Fetch the message contents once per event
+            if raw_message.is_none() {
+                let Some(metadata) = server
+                    .get_property::<Bincode<MessageMetadata>>(
+                        account_id,
+                        Collection::Email,
+                        event.document_id,
+                        Property::BodyStructure,
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+                else {
+                    break;
+                };
+                let Some(raw) = server
+                    .get_blob(&metadata.inner.blob_hash, 0..usize::MAX)
+                    .await
+                    .caused_by(trc::location!())?
+                else {
+                    trc::event!(
+                        Store(trc::StoreEvent::NotFound),
+                        AccountId = account_id,
+                        DocumentId = event.document_id,
+                        BlobId = metadata.inner.blob_hash.to_hex(),
+                        SpanId = session_id,
+                        CausedBy = trc::location!(),
+                    );
+                    break;
+                };
+                raw_message = Some(raw);
+            }
+            let Some(message) = raw_message
+                .as_deref()
+                .and_then(|raw| MessageParser::new().parse(raw))
+            else {
+                break;
+            };
+
+            let params = ScriptParameters::new()
+                .with_envelope(server, &ImapSieveUser(&user_email), session_id)
+                .await
+                .with_message(message)
+                .with_access_token(&access_token)
+                .with_session_id(session_id)
+                .set_variable("location", "MS")
+                .set_variable("phase", "post")
+                .set_variable("imap.user", access_token.name.clone())
+                .set_variable("imap.email", user_email.clone())
+                .set_variable("imap.cause", event.cause.as_str())
+                .set_variable("imap.mailbox", rule.mailbox.clone())
+                .set_variable(
+                    "imap.changedflags",
+                    event
+                        .changed_flags
+                        .iter()
+                        .map(keyword_to_flag)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                );
+
+            server
+                .run_script(rule.script.clone(), script.clone(), params)
+                .await;
+        }
+    }
+
+    Ok(())
+}
+
+fn keyword_to_flag(keyword: &Keyword) -> String {
+    match keyword {
+        Keyword::Seen => "\\Seen".to_string(),
+        Keyword::Draft => "\\Draft".to_string(),
+        Keyword::Flagged => "\\Flagged".to_string(),
+        Keyword::Answered => "\\Answered".to_string(),
+        Keyword::Recent => "\\Recent".to_string(),
+        Keyword::Deleted => "\\Deleted".to_string(),
+        keyword => keyword.to_string(),
+    }
+}
+
+struct ImapSieveUser<'x>(&'x str);
+
+impl ResolveVariable for ImapSieveUser<'_> {
+    fn resolve_variable(&self, variable: u32) -> Variable<'_> {
+        match variable {
+            V_RECIPIENT => self.0.into(),
+            V_RECIPIENT_DOMAIN => self
+                .0
+                .rsplit_once('@')
+                .map(|(_, domain)| domain)
+                .unwrap_or_default()
+                .into(),
+            _ => "".into(),
+        }
+    }
+
+    fn resolve_global(&self, _: &str) -> Variable<'_> {
+        Variable::Integer(0)
+    }
+}
@@ -5,6 +5,7 @@
  */
 
 pub mod get;
+pub mod imapsieve;
 pub mod query;
 pub mod set;
 pub mod validate;
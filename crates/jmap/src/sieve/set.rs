@@ -11,11 +11,7 @@ use common::{
 use jmap_proto::{
     error::set::{SetError, SetErrorType},
     method::set::{SetRequest, SetResponse},
-    object::{
-        Object,
-        index::{IndexAs, IndexProperty, ObjectIndexBuilder},
-        sieve::SetArguments,
-    },
+    object::{Object, index::ObjectIndexBuilder, sieve::SetArguments},
     request::reference::MaybeReference,
     response::references::EvalObjectReferences,
     types::{
@@ -42,23 +38,14 @@ use trc::AddContext;
 use crate::{JmapMethods, api::http::HttpSessionData, blob::download::BlobDownload};
 use std::future::Future;
 
+pub use email::sieve::SCHEMA;
+
 pub struct SetContext<'x> {
     resource_token: ResourceToken,
     access_token: &'x AccessToken,
     response: SetResponse,
 }
 
-pub static SCHEMA: &[IndexProperty] = &[
-    IndexProperty::new(Property::Name)
-        .index_as(IndexAs::Text {
-            tokenize: true,
-            index: true,
-        })
-        .max_size(255)
-        .required(),
-    IndexProperty::new(Property::IsActive).index_as(IndexAs::Integer),
-];
-
 pub trait SieveScriptSet: Sync + Send {
     fn sieve_script_set(
         &self,
@@ -552,6 +539,17 @@ impl SieveScriptSet for Server {
                         }
                     }
 
+                    // Reject extensions that have been disabled by the administrator
+                    if let Some(capability) =
+                        self.core.sieve.untrusted_extensions.find_disabled(&bytes)
+                    {
+                        return Ok(Err(SetError::new(SetErrorType::InvalidScript)
+                            .with_property(Property::BlobId)
+                            .with_description(format!(
+                                "Extension \"{capability}\" is not enabled on this server."
+                            ))));
+                    }
+
                     // Compile script
                     match self.core.sieve.untrusted_compiler.compile(&bytes) {
                         Ok(script) => {
@@ -29,15 +29,24 @@ impl SieveScriptValidate for Server {
     ) -> trc::Result<ValidateSieveScriptResponse> {
         Ok(ValidateSieveScriptResponse {
             account_id: request.account_id,
-            error: match self
-                .blob_download(&request.blob_id, access_token)
-                .await?
-                .map(|bytes| self.core.sieve.untrusted_compiler.compile(&bytes))
-            {
-                Some(Ok(_)) => None,
-                Some(Err(err)) => SetError::new(SetErrorType::InvalidScript)
-                    .with_description(err.to_string())
-                    .into(),
+            error: match self.blob_download(&request.blob_id, access_token).await? {
+                Some(bytes) => {
+                    if let Some(capability) =
+                        self.core.sieve.untrusted_extensions.find_disabled(&bytes)
+                    {
+                        SetError::new(SetErrorType::InvalidScript)
+                            .with_description(format!(
+                                "Extension \"{capability}\" is not enabled on this server."
+                            ))
+                            .into()
+                    } else if let Err(err) = self.core.sieve.untrusted_compiler.compile(&bytes) {
+                        SetError::new(SetErrorType::InvalidScript)
+                            .with_description(err.to_string())
+                            .into()
+                    } else {
+                        None
+                    }
+                }
                 None => SetError::new(SetErrorType::BlobNotFound).into(),
             },
         })
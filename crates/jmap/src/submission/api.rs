@@ -0,0 +1,697 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{collections::BTreeMap, future::Future, sync::Arc};
+
+use common::{auth::AccessToken, listener::stream::NullIo, Server};
+use directory::{backend::internal::manage, Permission};
+use hyper::{header, Method};
+use mail_builder::{
+    headers::{address::Address, raw::Raw},
+    MessageBuilder,
+};
+use mail_parser::{decoders::base64::base64_decode, HeaderName, MessageParser};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use smtp::{
+    core::{Session, SessionData, State},
+    queue::suppression::{SuppressionList, SuppressionReason},
+};
+use smtp_proto::{MailFrom, RcptTo};
+use utils::{sanitize_email, sanitize_language, url_params::UrlParams};
+
+use crate::api::{
+    http::{HttpSessionData, ToHttpResponse},
+    management::decode_path_element,
+    HttpRequest, HttpResponse, JsonResponse,
+};
+
+use super::template::TemplateRequest;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SubmissionRequest {
+    pub from: SubmissionAddress,
+    #[serde(default)]
+    pub to: Vec<SubmissionAddress>,
+    #[serde(default)]
+    pub cc: Vec<SubmissionAddress>,
+    #[serde(default)]
+    pub bcc: Vec<SubmissionAddress>,
+    #[serde(default)]
+    pub reply_to: Vec<SubmissionAddress>,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub text_body: Option<String>,
+    #[serde(default)]
+    pub html_body: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub attachments: Vec<SubmissionAttachment>,
+    #[serde(default)]
+    pub envelope: Option<SubmissionEnvelope>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SubmissionAddress {
+    Email(String),
+    Named {
+        #[serde(default)]
+        name: Option<String>,
+        email: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionAttachment {
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub content_id: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionEnvelope {
+    #[serde(default)]
+    pub mail_from: Option<String>,
+    #[serde(default)]
+    pub rcpt_to: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionResult {
+    pub queue_id: Option<u64>,
+    pub mail_from: String,
+    pub rcpt_to: Vec<SubmissionRecipient>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionRecipient {
+    pub address: String,
+    pub accepted: bool,
+    pub response: String,
+}
+
+pub trait HttpSubmission: Sync + Send {
+    fn handle_submit_message(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn submit_message(
+        &self,
+        access_token: Arc<AccessToken>,
+        session: &HttpSessionData,
+        mail_from: String,
+        rcpt_to: Vec<String>,
+        message: Vec<u8>,
+    ) -> impl Future<Output = trc::Result<SubmissionResult>> + Send;
+}
+
+impl HttpSubmission for Server {
+    async fn handle_submit_message(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        let body = body.unwrap_or_default();
+        let (mail_from, rcpt_to, message) =
+            match (path.get(1).copied(), path.get(2).copied(), req.method()) {
+                (None, _, &Method::POST) => {
+                    let is_raw = req
+                        .headers()
+                        .get(header::CONTENT_TYPE)
+                        .and_then(|ct| ct.to_str().ok())
+                        .is_some_and(|ct| ct.starts_with("message/rfc822"));
+                    if is_raw {
+                        let params = UrlParams::new(req.uri().query());
+                        parse_raw_submission(
+                            body,
+                            params.get("mailFrom"),
+                            params.get("rcptTo").map(|rcpt| rcpt.split(',')),
+                        )
+                    } else {
+                        serde_json::from_slice::<SubmissionRequest>(&body)
+                            .map_err(|err| err.to_string())
+                            .and_then(|request| request.build())
+                    }
+                }
+                (Some("template"), Some(id), &Method::POST) => {
+                    let template = self
+                        .core
+                        .jmap
+                        .submission_templates
+                        .get(decode_path_element(id).as_ref())
+                        .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                    match serde_json::from_slice::<TemplateRequest>(&body) {
+                        Ok(request) => {
+                            // Render in the language of the first recipient unless requested
+                            let language = match &request.language {
+                                Some(language) => sanitize_language(language)
+                                    .ok_or_else(|| format!("Invalid language {language:?}.")),
+                                None => Ok(match request.to.first() {
+                                    Some(
+                                        SubmissionAddress::Email(email)
+                                        | SubmissionAddress::Named { email, .. },
+                                    ) => {
+                                        self.recipient_language(
+                                            &email.to_lowercase(),
+                                            session.session_id,
+                                        )
+                                        .await
+                                    }
+                                    None => self.core.i18n.default_language.clone(),
+                                }),
+                            };
+
+                            language.and_then(|language| {
+                                request
+                                    .build(template, &language)
+                                    .and_then(|request| request.build())
+                            })
+                        }
+                        Err(err) => Err(err.to_string()),
+                    }
+                }
+                (Some("template"), None, &Method::GET) => {
+                    let mut ids = self
+                        .core
+                        .jmap
+                        .submission_templates
+                        .keys()
+                        .collect::<Vec<_>>();
+                    ids.sort_unstable();
+
+                    return Ok(JsonResponse::new(json!({
+                        "data": ids,
+                    }))
+                    .into_http_response());
+                }
+                (Some("suppression"), Some(sender), _) => {
+                    let sender = decode_path_element(sender).to_lowercase();
+                    let rcpt = path
+                        .get(3)
+                        .map(|rcpt| decode_path_element(rcpt).to_lowercase())
+                        .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                    // Only the owner of the sender address can manage its suppression list
+                    if !access_token.has_permission(Permission::MessageQueueUpdate)
+                        && !access_token.emails.iter().any(|email| email == &sender)
+                    {
+                        return Err(trc::SecurityEvent::Unauthorized
+                            .into_err()
+                            .details("Sender address does not belong to this account"));
+                    }
+
+                    let data = match *req.method() {
+                        Method::GET => json!(self.is_suppressed(&sender, &rcpt).await?),
+                        Method::POST => {
+                            self.suppress(&sender, &rcpt, SuppressionReason::Manual)
+                                .await?;
+                            json!(())
+                        }
+                        Method::DELETE => {
+                            self.unsuppress(&sender, &rcpt).await?;
+                            json!(())
+                        }
+                        _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+                    };
+
+                    return Ok(JsonResponse::new(json!({
+                        "data": data,
+                    }))
+                    .into_http_response());
+                }
+                (Some("schema"), None, &Method::GET) => {
+                    return Ok(JsonResponse::new(json!({
+                        "data": submission_schema(),
+                    }))
+                    .into_http_response());
+                }
+                _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+            }
+            .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+
+        if message.len() > self.core.jmap.mail_max_size {
+            return Err(trc::ResourceEvent::BadParameters.into_err().reason(format!(
+                "Message exceeds maximum size of {} bytes.",
+                self.core.jmap.mail_max_size
+            )));
+        }
+
+        let result = self
+            .submit_message(access_token, session, mail_from, rcpt_to, message)
+            .await?;
+
+        Ok(JsonResponse::new(json!({
+            "data": result,
+        }))
+        .into_http_response())
+    }
+
+    async fn submit_message(
+        &self,
+        access_token: Arc<AccessToken>,
+        session: &HttpSessionData,
+        mail_from: String,
+        rcpt_to: Vec<String>,
+        message: Vec<u8>,
+    ) -> trc::Result<SubmissionResult> {
+        // Submissions are processed as if they were received on an
+        // authenticated SMTP session, enforcing the same rules and limits.
+        let mut data = SessionData::local(None, vec![], vec![], session.session_id);
+        data.authenticated_as = Some(access_token);
+        data.remote_ip = session.remote_ip;
+        data.remote_ip_str = session.remote_ip.to_string();
+        data.remote_port = session.remote_port;
+        data.local_ip = session.local_ip;
+        data.local_ip_str = session.local_ip.to_string();
+        data.local_port = session.local_port;
+        let mut smtp = Session::<NullIo>::local(self.clone(), session.instance.clone(), data);
+
+        // MAIL FROM
+        let _ = smtp
+            .handle_mail_from(MailFrom {
+                address: mail_from.clone(),
+                ..Default::default()
+            })
+            .await;
+        if let Some(error) = smtp.has_failed() {
+            return Err(manage::error(
+                "Sender rejected",
+                Some(format!("Server rejected MAIL-FROM: {}", error.trim())),
+            ));
+        }
+
+        // RCPT TO
+        let mut result = SubmissionResult {
+            mail_from,
+            ..Default::default()
+        };
+        for rcpt in rcpt_to {
+            // Skip recipients that bounced or complained
+            if let Some(reason) = self.is_suppressed(&result.mail_from, &rcpt).await? {
+                result.rcpt_to.push(SubmissionRecipient {
+                    address: rcpt,
+                    accepted: false,
+                    response: format!(
+                        "550 5.1.1 Recipient is suppressed for this sender ({}).",
+                        match reason {
+                            SuppressionReason::Bounce => "bounce",
+                            SuppressionReason::Complaint => "complaint",
+                            SuppressionReason::Manual => "manual",
+                        }
+                    ),
+                });
+                continue;
+            }
+
+            let _ = smtp
+                .handle_rcpt_to(RcptTo {
+                    address: rcpt.clone(),
+                    ..Default::default()
+                })
+                .await;
+            let response = smtp.has_failed();
+            result.rcpt_to.push(SubmissionRecipient {
+                address: rcpt,
+                accepted: response.is_none(),
+                response: response
+                    .map(|r| r.trim().to_string())
+                    .unwrap_or_else(|| "250 2.1.5 Queued".to_string()),
+            });
+        }
+
+        // DATA
+        if result.rcpt_to.iter().any(|rcpt| rcpt.accepted) {
+            smtp.data.message = message;
+            let response = smtp.queue_message().await;
+            if let State::Accepted(queue_id) = smtp.state {
+                result.queue_id = Some(queue_id);
+            } else {
+                return Err(manage::error(
+                    "Message rejected",
+                    Some(format!(
+                        "Server rejected DATA: {}",
+                        String::from_utf8_lossy(&response).trim()
+                    )),
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl SubmissionAddress {
+    pub fn email(&self) -> &str {
+        match self {
+            SubmissionAddress::Email(email) => email,
+            SubmissionAddress::Named { email, .. } => email,
+        }
+    }
+
+    fn to_address(&self) -> Result<Address<'static>, String> {
+        let email = sanitize_email(self.email())
+            .ok_or_else(|| format!("Invalid e-mail address {:?}.", self.email()))?;
+        Ok(match self {
+            SubmissionAddress::Named {
+                name: Some(name), ..
+            } => Address::new_address(Some(name.clone()), email),
+            _ => Address::new_address(None::<String>, email),
+        })
+    }
+}
+
+impl Default for SubmissionAddress {
+    fn default() -> Self {
+        SubmissionAddress::Email(String::new())
+    }
+}
+
+impl SubmissionRequest {
+    /// Builds the message and its envelope, which is obtained from the
+    /// message addresses unless it was explicitly provided.
+    pub fn build(self) -> Result<(String, Vec<String>, Vec<u8>), String> {
+        let envelope = self.envelope.unwrap_or_default();
+        let mail_from = match &envelope.mail_from {
+            Some(mail_from) => sanitize_email(mail_from)
+                .ok_or_else(|| format!("Invalid e-mail address {mail_from:?}."))?,
+            None => sanitize_email(self.from.email())
+                .ok_or_else(|| format!("Invalid e-mail address {:?}.", self.from.email()))?,
+        };
+        let mut rcpt_to = Vec::new();
+        if !envelope.rcpt_to.is_empty() {
+            for rcpt in &envelope.rcpt_to {
+                add_recipient(&mut rcpt_to, rcpt)?;
+            }
+        } else {
+            for rcpt in self.to.iter().chain(self.cc.iter()).chain(self.bcc.iter()) {
+                add_recipient(&mut rcpt_to, rcpt.email())?;
+            }
+        }
+        if rcpt_to.is_empty() {
+            return Err("No recipients found.".to_string());
+        }
+
+        let mut builder = MessageBuilder::new().from(self.from.to_address()?);
+        for (header, addresses) in [
+            ("To", &self.to),
+            ("Cc", &self.cc),
+            ("Reply-To", &self.reply_to),
+        ] {
+            if !addresses.is_empty() {
+                let addresses = addresses
+                    .iter()
+                    .map(|addr| addr.to_address())
+                    .collect::<Result<Vec<_>, _>>()?;
+                builder = builder.header(header, Address::new_list(addresses));
+            }
+        }
+        if let Some(subject) = self.subject {
+            builder = builder.subject(subject);
+        }
+        for (name, value) in self.headers {
+            if !is_valid_header(&name, &value) {
+                return Err(format!("Invalid header {name:?}."));
+            }
+            builder = builder.header(name, Raw::new(value));
+        }
+        if let Some(text_body) = self.text_body {
+            builder = builder.text_body(text_body);
+        }
+        if let Some(html_body) = self.html_body {
+            builder = builder.html_body(html_body);
+        }
+        for attachment in self.attachments {
+            let contents = base64_decode(attachment.content.as_bytes())
+                .ok_or_else(|| "Failed to decode attachment contents.".to_string())?;
+            let content_type = attachment
+                .content_type
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            builder = if let Some(cid) = attachment.content_id {
+                builder.inline(content_type, cid, contents)
+            } else {
+                builder.attachment(
+                    content_type,
+                    attachment
+                        .filename
+                        .unwrap_or_else(|| "attachment".to_string()),
+                    contents,
+                )
+            };
+        }
+
+        builder
+            .write_to_vec()
+            .map(|message| (mail_from, rcpt_to, message))
+            .map_err(|err| format!("Failed to build message: {err}"))
+    }
+}
+
+/// Obtains the envelope of a raw message from its headers, unless provided,
+/// and removes any Bcc headers before submission.
+pub fn parse_raw_submission<'x>(
+    mut message: Vec<u8>,
+    mail_from: Option<&str>,
+    rcpt_to: Option<impl Iterator<Item = &'x str>>,
+) -> Result<(String, Vec<String>, Vec<u8>), String> {
+    let parsed = MessageParser::new()
+        .parse_headers(&message)
+        .ok_or_else(|| "Failed to parse e-mail message.".to_string())?;
+    let mail_from = match mail_from {
+        Some(mail_from) => mail_from.to_string(),
+        None => parsed
+            .from()
+            .and_then(|from| from.first())
+            .and_then(|addr| addr.address())
+            .ok_or_else(|| "Missing From header.".to_string())?
+            .to_string(),
+    };
+    let mail_from = sanitize_email(&mail_from)
+        .ok_or_else(|| format!("Invalid e-mail address {mail_from:?}."))?;
+
+    let mut recipients = Vec::new();
+    if let Some(rcpt_to) = rcpt_to {
+        for rcpt in rcpt_to {
+            add_recipient(&mut recipients, rcpt)?;
+        }
+    } else {
+        for addr in [parsed.to(), parsed.cc(), parsed.bcc()]
+            .into_iter()
+            .flatten()
+            .flat_map(|addr| addr.iter())
+        {
+            if let Some(addr) = addr.address() {
+                add_recipient(&mut recipients, addr)?;
+            }
+        }
+    }
+    if recipients.is_empty() {
+        return Err("No recipients found.".to_string());
+    }
+
+    // Remove Bcc headers
+    let mut bcc_headers = parsed
+        .headers()
+        .iter()
+        .filter(|header| matches!(header.name, HeaderName::Bcc))
+        .map(|header| header.offset_field..header.offset_end)
+        .collect::<Vec<_>>();
+    drop(parsed);
+    while let Some(range) = bcc_headers.pop() {
+        message.drain(range);
+    }
+
+    Ok((mail_from, recipients, message))
+}
+
+/// JSON schema describing the structured submission format.
+pub fn submission_schema() -> serde_json::Value {
+    let address = json!({
+        "oneOf": [
+            {"type": "string", "format": "email"},
+            {
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "email": {"type": "string", "format": "email"}
+                },
+                "required": ["email"]
+            }
+        ]
+    });
+    let addresses = json!({"type": "array", "items": address});
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Message submission",
+        "type": "object",
+        "properties": {
+            "from": address,
+            "to": addresses,
+            "cc": addresses,
+            "bcc": addresses,
+            "replyTo": addresses,
+            "subject": {"type": "string"},
+            "textBody": {"type": "string"},
+            "htmlBody": {"type": "string"},
+            "headers": {
+                "type": "object",
+                "additionalProperties": {"type": "string"}
+            },
+            "attachments": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "filename": {"type": "string"},
+                        "contentType": {"type": "string"},
+                        "contentId": {"type": "string"},
+                        "content": {"type": "string", "contentEncoding": "base64"}
+                    },
+                    "required": ["content"]
+                }
+            },
+            "envelope": {
+                "type": "object",
+                "properties": {
+                    "mailFrom": {"type": "string", "format": "email"},
+                    "rcptTo": {
+                        "type": "array",
+                        "items": {"type": "string", "format": "email"}
+                    }
+                }
+            }
+        },
+        "required": ["from"],
+        "additionalProperties": false
+    })
+}
+
+fn add_recipient(rcpt_to: &mut Vec<String>, address: &str) -> Result<(), String> {
+    let address = sanitize_email(address.trim())
+        .ok_or_else(|| format!("Invalid e-mail address {address:?}."))?;
+    if !rcpt_to.contains(&address) {
+        rcpt_to.push(address);
+    }
+    Ok(())
+}
+
+fn is_valid_header(name: &str, value: &str) -> bool {
+    !name.is_empty()
+        && name.bytes().all(|ch| ch.is_ascii_graphic() && ch != b':')
+        && !matches!(
+            name.to_ascii_lowercase().as_str(),
+            "from" | "to" | "cc" | "bcc" | "reply-to" | "subject" | "content-type" | "mime-version"
+        )
+        && !value.contains(['\r', '\n'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_raw_submission, SubmissionRequest};
+
+    #[test]
+    fn build_submission() {
+        let request = serde_json::from_str::<SubmissionRequest>(
+            r#"{
+                "from": {"name": "Billing", "email": "billing@example.org"},
+                "to": ["john@example.com", {"email": "jane@example.com"}],
+                "bcc": ["audit@example.org"],
+                "subject": "Your invoice",
+                "textBody": "Please find your invoice attached.",
+                "headers": {"X-Invoice-Id": "1234"},
+                "attachments": [{
+                    "filename": "invoice.txt",
+                    "contentType": "text/plain",
+                    "content": "aW52b2ljZQ=="
+                }]
+            }"#,
+        )
+        .unwrap();
+        let (mail_from, rcpt_to, message) = request.build().unwrap();
+        let message = String::from_utf8(message).unwrap();
+
+        assert_eq!(mail_from, "billing@example.org");
+        assert_eq!(
+            rcpt_to,
+            ["john@example.com", "jane@example.com", "audit@example.org"]
+        );
+        assert!(message.contains("X-Invoice-Id: 1234\r\n"), "{message}");
+        assert!(message.contains("invoice.txt"), "{message}");
+        assert!(!message.contains("audit@example.org"), "{message}");
+
+        // Invalid headers are rejected
+        let request = serde_json::from_str::<SubmissionRequest>(
+            r#"{
+                "from": "billing@example.org",
+                "to": ["john@example.com"],
+                "headers": {"Bcc": "jane@example.com"}
+            }"#,
+        )
+        .unwrap();
+        assert!(request.build().is_err());
+    }
+
+    #[test]
+    fn parse_raw() {
+        let message = concat!(
+            "From: billing@example.org\r\n",
+            "To: john@example.com\r\n",
+            "Bcc: audit@example.org\r\n",
+            "Subject: Your invoice\r\n",
+            "\r\n",
+            "Hi!\r\n"
+        );
+
+        let (mail_from, rcpt_to, message) = parse_raw_submission(
+            message.as_bytes().to_vec(),
+            None,
+            None::<std::str::Split<char>>,
+        )
+        .unwrap();
+        assert_eq!(mail_from, "billing@example.org");
+        assert_eq!(rcpt_to, ["john@example.com", "audit@example.org"]);
+        assert_eq!(
+            String::from_utf8(message).unwrap(),
+            concat!(
+                "From: billing@example.org\r\n",
+                "To: john@example.com\r\n",
+                "Subject: Your invoice\r\n",
+                "\r\n",
+                "Hi!\r\n"
+            )
+        );
+
+        let (mail_from, rcpt_to, _) = parse_raw_submission(
+            b"From: billing@example.org\r\n\r\nHi!\r\n".to_vec(),
+            Some("bounces@example.org"),
+            Some("jane@example.com,bill@example.com".split(',')),
+        )
+        .unwrap();
+        assert_eq!(mail_from, "bounces@example.org");
+        assert_eq!(rcpt_to, ["jane@example.com", "bill@example.com"]);
+    }
+}
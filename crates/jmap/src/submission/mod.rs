@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod api;
 pub mod get;
 pub mod query;
 pub mod set;
+pub mod template;
@@ -0,0 +1,173 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::BTreeMap;
+
+use common::config::jmap::settings::{render_template, Escape, MessageTemplate};
+use serde::Deserialize;
+
+use super::api::{SubmissionAddress, SubmissionAttachment, SubmissionEnvelope, SubmissionRequest};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TemplateRequest {
+    #[serde(default)]
+    pub from: Option<SubmissionAddress>,
+    #[serde(default)]
+    pub to: Vec<SubmissionAddress>,
+    #[serde(default)]
+    pub cc: Vec<SubmissionAddress>,
+    #[serde(default)]
+    pub bcc: Vec<SubmissionAddress>,
+    #[serde(default)]
+    pub reply_to: Vec<SubmissionAddress>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub attachments: Vec<SubmissionAttachment>,
+    #[serde(default)]
+    pub envelope: Option<SubmissionEnvelope>,
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl TemplateRequest {
+    /// Renders the template in the requested language using the request
+    /// variables, the sender defaults to the template's sender when not provided.
+    pub fn build(
+        self,
+        template: &MessageTemplate,
+        language: &str,
+    ) -> Result<SubmissionRequest, String> {
+        let from = match (self.from, &template.from_address) {
+            (Some(from), _) => from,
+            (None, Some(email)) => SubmissionAddress::Named {
+                name: template.from_name.clone(),
+                email: email.clone(),
+            },
+            (None, None) => return Err("Missing sender address.".to_string()),
+        };
+        let (text_body, html_body) = template.bodies(language);
+
+        Ok(SubmissionRequest {
+            from,
+            to: self.to,
+            cc: self.cc,
+            bcc: self.bcc,
+            reply_to: self.reply_to,
+            subject: render_template(template.subject(language), &self.variables, Escape::Header)?
+                .into(),
+            text_body: text_body
+                .map(|body| render_template(body, &self.variables, Escape::None))
+                .transpose()?,
+            html_body: html_body
+                .map(|body| render_template(body, &self.variables, Escape::Html))
+                .transpose()?,
+            headers: self.headers,
+            attachments: self.attachments,
+            envelope: self.envelope,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use common::config::jmap::settings::{MessageTemplate, MessageTemplateTranslation};
+    use store::ahash::AHashMap;
+
+    use super::{render_template, Escape, TemplateRequest};
+
+    #[test]
+    fn render_templates() {
+        let variables = serde_json::from_str::<BTreeMap<String, serde_json::Value>>(
+            r#"{"name": "<Jane & John>", "total": 12.5, "paid": true, "note": null}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            render_template(
+                "Dear {{name}}, you owe {{ total }} ({{paid}}){{note}}.",
+                &variables,
+                Escape::None
+            )
+            .unwrap(),
+            "Dear <Jane & John>, you owe 12.5 (true)."
+        );
+        assert_eq!(
+            render_template("<p>Dear {{name}}</p>", &variables, Escape::Html).unwrap(),
+            "<p>Dear &lt;Jane &amp; John&gt;</p>"
+        );
+        assert_eq!(
+            render_template("Unclosed {{name", &variables, Escape::None).unwrap(),
+            "Unclosed {{name"
+        );
+        assert!(render_template("Hi {{unknown}}", &variables, Escape::None).is_err());
+
+        let mut variables = variables;
+        variables.insert(
+            "name".to_string(),
+            serde_json::Value::String("Jane\r\nBcc: x@y.org".to_string()),
+        );
+        assert_eq!(
+            render_template("Hi {{name}}", &variables, Escape::Header).unwrap(),
+            "Hi Jane  Bcc: x@y.org"
+        );
+    }
+
+    #[test]
+    fn build_template() {
+        let template = MessageTemplate {
+            from_name: Some("Billing".to_string()),
+            from_address: Some("billing@example.org".to_string()),
+            subject: "Invoice {{number}}".to_string(),
+            text_body: Some("Hi {{name}}, your invoice is ready.".to_string()),
+            html_body: None,
+            translations: AHashMap::from_iter([(
+                "es".to_string(),
+                MessageTemplateTranslation {
+                    subject: Some("Factura {{number}}".to_string()),
+                    text_body: Some("Hola {{name}}, su factura está lista.".to_string()),
+                    html_body: None,
+                },
+            )]),
+        };
+        let request = serde_json::from_str::<TemplateRequest>(
+            r#"{
+                "to": ["john@example.com"],
+                "variables": {"number": 1234, "name": "John"}
+            }"#,
+        )
+        .unwrap();
+
+        let (mail_from, rcpt_to, message) =
+            request.build(&template, "en").unwrap().build().unwrap();
+        let message = String::from_utf8(message).unwrap();
+        assert_eq!(mail_from, "billing@example.org");
+        assert_eq!(rcpt_to, ["john@example.com"]);
+        assert!(message.contains("Subject: Invoice 1234\r\n"), "{message}");
+        assert!(
+            message.contains("Hi John, your invoice is ready."),
+            "{message}"
+        );
+
+        // Translated templates fall back to the primary language subtag
+        let request = serde_json::from_str::<TemplateRequest>(
+            r#"{
+                "to": ["juan@example.com"],
+                "variables": {"number": 1234, "name": "Juan"}
+            }"#,
+        )
+        .unwrap();
+        let (_, _, message) = request.build(&template, "es-mx").unwrap().build().unwrap();
+        let message = String::from_utf8(message).unwrap();
+        assert!(message.contains("Subject: Factura 1234\r\n"), "{message}");
+        assert!(!message.contains("invoice is ready"), "{message}");
+    }
+}
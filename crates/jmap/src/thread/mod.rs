@@ -5,3 +5,4 @@
  */
 
 pub mod get;
+pub mod query;
@@ -0,0 +1,145 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use email::conversation::ConversationIndex;
+use jmap_proto::{
+    method::query::{Filter, QueryRequest, QueryResponse, RequestArguments, SortProperty},
+    types::{acl::Acl, collection::Collection, property::Property},
+};
+use std::future::Future;
+use store::query::ResultSet;
+use trc::AddContext;
+
+use crate::{auth::acl::AclMethods, JmapMethods, UpdateResults};
+
+pub trait ThreadQuery: Sync + Send {
+    fn thread_query(
+        &self,
+        request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<QueryResponse>> + Send;
+}
+
+impl ThreadQuery for Server {
+    async fn thread_query(
+        &self,
+        mut request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> trc::Result<QueryResponse> {
+        let account_id = request.account_id.document_id();
+        let conversations = self
+            .get_conversations(account_id)
+            .await
+            .caused_by(trc::location!())?;
+
+        // Shared accounts only expose threads with at least one visible message
+        let mut thread_ids = if access_token.is_shared(account_id) {
+            conversations.threads_of(
+                &self
+                    .shared_messages(access_token, account_id, Acl::ReadItems)
+                    .await?,
+            )
+        } else {
+            conversations.threads.keys().copied().collect()
+        };
+
+        // Conditions are matched against the conversation index
+        for cond in std::mem::take(&mut request.filter) {
+            match cond {
+                Filter::InMailbox(mailbox_id) => {
+                    thread_ids &= conversations.threads_of(
+                        &self
+                            .get_tag(
+                                account_id,
+                                Collection::Email,
+                                Property::MailboxIds,
+                                mailbox_id.document_id(),
+                            )
+                            .await?
+                            .unwrap_or_default(),
+                    );
+                }
+                Filter::After(date) => {
+                    let timestamp = date.timestamp() as u64;
+                    thread_ids = conversations.filter(thread_ids, |conversation| {
+                        conversation.last_activity > timestamp
+                    });
+                }
+                Filter::Before(date) => {
+                    let timestamp = date.timestamp() as u64;
+                    thread_ids = conversations.filter(thread_ids, |conversation| {
+                        conversation.last_activity < timestamp
+                    });
+                }
+                Filter::From(participant) => {
+                    let participant = participant.to_lowercase();
+                    thread_ids = conversations.filter(thread_ids, |conversation| {
+                        conversation
+                            .participants
+                            .iter()
+                            .any(|address| address.contains(&participant))
+                    });
+                }
+                Filter::And | Filter::Close => (),
+                other => {
+                    return Err(trc::JmapEvent::UnsupportedFilter
+                        .into_err()
+                        .details(other.to_string()))
+                }
+            }
+        }
+
+        let result_set = ResultSet::new(account_id, Collection::Thread, thread_ids);
+        let (mut response, paginate) = self.build_query_response(&result_set, &request).await?;
+        response.can_calculate_changes = false;
+
+        if let Some(mut paginate) = paginate {
+            // Threads are sorted by their last activity, most recent first by default
+            let mut is_ascending = false;
+            for comparator in request.sort.unwrap_or_default() {
+                match comparator.property {
+                    SortProperty::ReceivedAt => {
+                        is_ascending = comparator.is_ascending;
+                    }
+                    other => {
+                        return Err(trc::JmapEvent::UnsupportedSort
+                            .into_err()
+                            .details(other.to_string()))
+                    }
+                }
+            }
+
+            let mut threads = result_set
+                .results
+                .iter()
+                .map(|thread_id| {
+                    (
+                        conversations
+                            .threads
+                            .get(&thread_id)
+                            .map_or(0, |conversation| conversation.last_activity),
+                        thread_id,
+                    )
+                })
+                .collect::<Vec<_>>();
+            if is_ascending {
+                threads.sort_unstable();
+            } else {
+                threads.sort_unstable_by(|a, b| b.cmp(a));
+            }
+            for (_, thread_id) in threads {
+                if !paginate.add(0, thread_id) {
+                    break;
+                }
+            }
+
+            response.update_results(paginate.build())?;
+        }
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,268 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use common::{auth::AccessToken, Server, KV_AUTORESPONDER_SUPPRESSED};
+use directory::backend::internal::manage;
+use jmap_proto::{
+    method::set::{RequestArguments, SetRequest},
+    object::Object,
+    types::{
+        collection::Collection,
+        date::UTCDate,
+        id::Id,
+        property::Property,
+        value::{SetValue, Value},
+    },
+};
+use mail_parser::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use store::dispatch::lookup::KeyValue;
+use utils::map::vec_map::VecMap;
+
+use crate::api::{http::ToHttpResponse, HttpResponse, JsonResponse};
+
+use super::{
+    get::VacationResponseGet,
+    set::{VacationResponseSet, REPLY_INTERVAL, SCHEDULE},
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VacationSchedule {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub from_date: Option<String>,
+    #[serde(default)]
+    pub to_date: Option<String>,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub text_body: Option<String>,
+    #[serde(default)]
+    pub html_body: Option<String>,
+    #[serde(default)]
+    pub reply_interval: Option<u64>,
+    #[serde(default)]
+    pub schedule: Vec<VacationRange>,
+    #[serde(default, skip_deserializing)]
+    pub suppressed_replies: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VacationRange {
+    #[serde(default)]
+    pub from_date: Option<String>,
+    #[serde(default)]
+    pub to_date: Option<String>,
+}
+
+pub trait VacationScheduleHandler: Sync + Send {
+    fn handle_vacation_get(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_vacation_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl VacationScheduleHandler for Server {
+    async fn handle_vacation_get(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> trc::Result<HttpResponse> {
+        let account_id = access_token.primary_id();
+        let mut schedule = VacationSchedule::default();
+
+        if let Some(document_id) = self.get_vacation_sieve_script_id(account_id).await?
+            && let Some(mut obj) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::SieveScript,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+        {
+            schedule.enabled = obj.remove(&Property::IsActive) == Value::Bool(true);
+            schedule.from_date = date_to_string(obj.remove(&Property::FromDate));
+            schedule.to_date = date_to_string(obj.remove(&Property::ToDate));
+            schedule.subject = text_to_string(obj.remove(&Property::Subject));
+            schedule.text_body = text_to_string(obj.remove(&Property::TextBody));
+            schedule.html_body = text_to_string(obj.remove(&Property::HtmlBody));
+            if let Value::UnsignedInt(seconds) =
+                obj.remove(&Property::_T(REPLY_INTERVAL.to_string()))
+            {
+                schedule.reply_interval = Some(seconds);
+            }
+            if let Value::List(ranges) = obj.remove(&Property::_T(SCHEDULE.to_string())) {
+                for range in ranges {
+                    if let Value::Object(mut range) = range {
+                        schedule.schedule.push(VacationRange {
+                            from_date: date_to_string(range.remove(&Property::FromDate)),
+                            to_date: date_to_string(range.remove(&Property::ToDate)),
+                        });
+                    }
+                }
+            }
+        }
+
+        // Number of replies that were not sent to automatically generated messages
+        schedule.suppressed_replies = self
+            .in_memory_store()
+            .counter_get(KeyValue::<()>::build_key(
+                KV_AUTORESPONDER_SUPPRESSED,
+                account_id.to_be_bytes(),
+            ))
+            .await?
+            .max(0) as u64;
+
+        Ok(JsonResponse::new(json!({
+            "data": schedule,
+        }))
+        .into_http_response())
+    }
+
+    async fn handle_vacation_post(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        let request =
+            serde_json::from_slice::<VacationSchedule>(body.as_deref().unwrap_or_default())
+                .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+        let account_id = access_token.primary_id();
+
+        // Build VacationResponse object
+        let mut ranges = Vec::with_capacity(request.schedule.len());
+        for range in request.schedule {
+            let mut obj = Object::with_capacity(2);
+            if let Some(from_date) = parse_date(range.from_date)? {
+                obj.append(Property::FromDate, from_date);
+            }
+            if let Some(to_date) = parse_date(range.to_date)? {
+                obj.append(Property::ToDate, to_date);
+            }
+            if !obj.properties.is_empty() {
+                ranges.push(Value::Object(obj));
+            }
+        }
+        let mut obj = Object {
+            properties: VecMap::with_capacity(8),
+        };
+        for (property, value) in [
+            (Property::IsEnabled, Value::Bool(request.enabled)),
+            (
+                Property::FromDate,
+                parse_date(request.from_date)?.unwrap_or_default(),
+            ),
+            (
+                Property::ToDate,
+                parse_date(request.to_date)?.unwrap_or_default(),
+            ),
+            (
+                Property::Subject,
+                request.subject.map(Value::Text).unwrap_or_default(),
+            ),
+            (
+                Property::TextBody,
+                request.text_body.map(Value::Text).unwrap_or_default(),
+            ),
+            (
+                Property::HtmlBody,
+                request.html_body.map(Value::Text).unwrap_or_default(),
+            ),
+            (
+                Property::_T(REPLY_INTERVAL.to_string()),
+                request
+                    .reply_interval
+                    .map(Value::UnsignedInt)
+                    .unwrap_or_default(),
+            ),
+            (
+                Property::_T(SCHEDULE.to_string()),
+                if !ranges.is_empty() {
+                    Value::List(ranges)
+                } else {
+                    Value::Null
+                },
+            ),
+        ] {
+            obj.properties.append(property, SetValue::Value(value));
+        }
+
+        // Create or update the vacation response
+        let mut set_request = SetRequest {
+            account_id: Id::from(account_id),
+            if_in_state: None,
+            create: None,
+            update: None,
+            destroy: None,
+            arguments: RequestArguments::VacationResponse,
+        };
+        if self.get_vacation_sieve_script_id(account_id).await?.is_some() {
+            set_request.update = VecMap::new().with_key_value(Id::singleton(), obj).into();
+        } else {
+            set_request.create = VecMap::new().with_key_value("v".to_string(), obj).into();
+        }
+        let response = self
+            .vacation_response_set(set_request, &access_token)
+            .await?;
+
+        if let Some(err) = response
+            .not_created
+            .values()
+            .chain(response.not_updated.values())
+            .next()
+        {
+            Err(manage::error(
+                "Failed to update vacation response",
+                err.description.as_ref().map(|d| d.to_string()),
+            ))
+        } else {
+            Ok(JsonResponse::new(json!({
+                "data": (),
+            }))
+            .into_http_response())
+        }
+    }
+}
+
+fn parse_date(value: Option<String>) -> trc::Result<Option<Value>> {
+    match value.filter(|value| !value.is_empty()) {
+        Some(value) => DateTime::parse_rfc3339(&value)
+            .filter(|dt| dt.is_valid())
+            .map(|dt| Some(Value::Date(UTCDate::from_timestamp(dt.to_timestamp()))))
+            .ok_or_else(|| {
+                trc::ResourceEvent::BadParameters
+                    .into_err()
+                    .details(format!("Invalid date {value:?}"))
+            }),
+        None => Ok(None),
+    }
+}
+
+fn date_to_string(value: Value) -> Option<String> {
+    match value {
+        Value::Date(date) => Some(date.to_string()),
+        _ => None,
+    }
+}
+
+fn text_to_string(value: Value) -> Option<String> {
+    match value {
+        Value::Text(text) => Some(text),
+        _ => None,
+    }
+}
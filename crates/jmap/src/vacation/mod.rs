@@ -5,4 +5,5 @@
  */
 
 pub mod get;
+pub mod manage;
 pub mod set;
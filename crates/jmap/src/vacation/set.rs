@@ -160,6 +160,12 @@ impl VacationResponseSet for Server {
                         build_script = true;
                         changes.append(property, value);
                     }
+                    (Property::_T(name), MaybePatchValue::Value(value))
+                        if is_valid_extension(name, &value) =>
+                    {
+                        build_script = true;
+                        changes.append(property, value);
+                    }
                     (Property::IsEnabled, MaybePatchValue::Value(Value::Bool(value))) => {
                         is_active = value;
                         changes.append(Property::IsActive, value);
@@ -351,7 +357,18 @@ impl VacationResponseSet for Server {
     fn build_script(&self, obj: &mut ObjectIndexBuilder) -> trc::Result<Vec<u8>> {
         // Build Sieve script
         let mut script = Vec::with_capacity(1024);
-        script.extend_from_slice(b"require [\"vacation\", \"relational\", \"date\"];\r\n\r\n");
+        let reply_interval = match obj.get(&Property::_T(REPLY_INTERVAL.to_string())) {
+            Value::UnsignedInt(seconds) => Some(*seconds),
+            _ => None,
+        };
+        if reply_interval.is_some() {
+            script.extend_from_slice(
+                b"require [\"vacation\", \"vacation-seconds\", \"relational\", \"date\"];",
+            );
+            script.extend_from_slice(b"\r\n\r\n");
+        } else {
+            script.extend_from_slice(b"require [\"vacation\", \"relational\", \"date\"];\r\n\r\n");
+        }
         let mut num_blocks = 0;
 
         // Add start date
@@ -370,7 +387,36 @@ impl VacationResponseSet for Server {
             num_blocks += 1;
         }
 
+        // Restrict replies to the scheduled date ranges
+        if let Value::List(ranges) = obj.get(&Property::_T(SCHEDULE.to_string())) {
+            let mut tests = Vec::with_capacity(ranges.len());
+            for range in ranges {
+                if let Value::Object(range) = range {
+                    let mut test = Vec::with_capacity(2);
+                    for (property, op) in [(Property::FromDate, "ge"), (Property::ToDate, "le")] {
+                        if let Some(Value::Date(value)) = range.properties.get(&property) {
+                            test.push(format!(
+                                "currentdate :value \"{op}\" \"iso8601\" \"{value}\""
+                            ));
+                        }
+                    }
+                    if !test.is_empty() {
+                        tests.push(format!("allof({})", test.join(", ")));
+                    }
+                }
+            }
+            if !tests.is_empty() {
+                script.extend_from_slice(b"if anyof(");
+                script.extend_from_slice(tests.join(", ").as_bytes());
+                script.extend_from_slice(b") {\r\n");
+                num_blocks += 1;
+            }
+        }
+
         script.extend_from_slice(b"vacation :mime ");
+        if let Some(seconds) = reply_interval {
+            script.extend_from_slice(format!(":seconds {seconds} ").as_bytes());
+        }
         if let Value::Text(value) = obj.get(&Property::Subject) {
             script.extend_from_slice(b":subject \"");
             for &ch in value.as_bytes().iter() {
@@ -456,6 +502,30 @@ impl VacationResponseSet for Server {
     }
 }
 
+/// Non-standard VacationResponse properties, managed through the account API.
+pub const REPLY_INTERVAL: &str = "replyInterval";
+pub const SCHEDULE: &str = "schedule";
+pub const MAX_SCHEDULE_RANGES: usize = 32;
+
+fn is_valid_extension(name: &str, value: &Value) -> bool {
+    match (name, value) {
+        (REPLY_INTERVAL, Value::UnsignedInt(_) | Value::Null) => true,
+        (SCHEDULE, Value::List(ranges)) => {
+            ranges.len() <= MAX_SCHEDULE_RANGES
+                && ranges.iter().all(|range| {
+                    matches!(range, Value::Object(range) if range.properties.iter().all(
+                        |(property, value)| matches!(
+                            (property, value),
+                            (Property::FromDate | Property::ToDate, Value::Date(_))
+                        )
+                    ))
+                })
+        }
+        (SCHEDULE, Value::Null) => true,
+        _ => false,
+    }
+}
+
 fn set_error(mut response: SetResponse, id: Option<String>, err: SetError) -> SetResponse {
     if let Some(id) = id {
         response.not_created.append(id, err);
@@ -27,6 +27,7 @@ use crate::{
         http::{HttpSessionData, ToRequestError},
         request::RequestHandler,
     },
+    auth::rate_limit::RateLimiter,
     services::state::StateManager,
 };
 use std::future::Future;
@@ -100,16 +101,26 @@ impl WebSocketHandler for Server {
                                         self.core.jmap.request_max_size,
                                     ) {
                                         Ok(WebSocketMessage::Request(request)) => {
-                                            let response = self
-                                                .handle_request(
-                                                    request.request,
-                                                    access_token.clone(),
-                                                    &session,
-                                                )
-                                                .await;
+                                            // Apply the account limits to each request
+                                            match self.is_http_authenticated_request_allowed(&access_token).await {
+                                                Ok(_in_flight) => {
+                                                    let response = self
+                                                        .handle_request(
+                                                            request.request,
+                                                            access_token.clone(),
+                                                            &session,
+                                                        )
+                                                        .await;
 
-                                            WebSocketResponse::from_response(response, request.id)
-                                            .to_json()
+                                                    WebSocketResponse::from_response(response, request.id)
+                                                    .to_json()
+                                                }
+                                                Err(err) => {
+                                                    let response = WebSocketRequestError::from(err.to_request_error()).to_json();
+                                                    trc::error!(err.span_id(session.session_id));
+                                                    response
+                                                }
+                                            }
                                         }
                                         Ok(WebSocketMessage::PushEnable(push_enable)) => {
                                             change_types = if !push_enable.data_types.is_empty() {
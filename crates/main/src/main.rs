@@ -6,14 +6,23 @@
 
 use std::time::Duration;
 
-use common::{config::server::ServerProtocol, core::BuildServer, manager::boot::BootManager};
-use directory::backend::internal::MigrateDirectory;
+use common::{
+    config::server::ServerProtocol,
+    core::BuildServer,
+    ipc::{HousekeeperEvent, QueueEvent},
+    manager::{
+        boot::{BootManager, BootMode},
+        migrate::MigrationState,
+        restart::RestartState,
+    },
+};
 use imap::core::ImapSessionManager;
 use jmap::{api::JmapSessionManager, services::gossip::spawn::GossiperBuilder, StartServices};
 use managesieve::core::ManageSieveSessionManager;
 use pop3::Pop3SessionManager;
-use smtp::{core::SmtpSessionManager, StartQueueManager};
+use smtp::{core::SmtpSessionManager, queue::console::queue_console, StartQueueManager};
 use trc::Collector;
+#[cfg(not(windows))]
 use utils::wait_for_shutdown;
 
 #[cfg(not(target_env = "msvc"))]
@@ -28,24 +37,42 @@ async fn main() -> std::io::Result<()> {
     // Load config and apply macros
     let mut init = BootManager::init().await;
 
+    // Offline queue console
+    if init.mode == BootMode::QueueConsole {
+        queue_console(init.inner.build_server()).await;
+        std::process::exit(0);
+    }
+
+    // Offline store migration
+    if init.mode == BootMode::Migrate {
+        let server = init.inner.build_server();
+        if let Err(err) = server.run_migrations().await {
+            trc::error!(err.details("Store migration failed"));
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
     // Init services
     init.start_services().await;
     init.start_queue_manager();
     let gossiper = GossiperBuilder::try_parse(&mut init.config);
+    let shutdown_timeout = init
+        .config
+        .property_or_default::<Duration>("server.shutdown.timeout", "30s")
+        .unwrap_or(Duration::from_secs(30));
+    let migrate_on_startup = init
+        .config
+        .property_or_default::<bool>("server.migration.on-startup", "true")
+        .unwrap_or(true);
+    let inner = init.inner.clone();
 
     // Log configuration errors
     init.config.log_errors();
     init.config.log_warnings();
 
-    {
-        let server = init.inner.build_server();
-
-        // Migrate directory
-        if let Err(err) = server.store().migrate_directory().await {
-            trc::error!(err.details("Directory migration failed"));
-            std::process::exit(1);
-        }
-    }
+    // Report the server as not ready until the store migrations complete
+    inner.data.migration.set_state(MigrationState::Pending);
 
     // Spawn servers
     let (shutdown_tx, shutdown_rx) = init.servers.spawn(|server, acceptor, shutdown_rx| {
@@ -83,22 +110,98 @@ async fn main() -> std::io::Result<()> {
         };
     });
 
+    // Run store migrations, or wait for them to be run with '--migrate'
+    let server = init.inner.build_server();
+    tokio::spawn(async move {
+        if migrate_on_startup {
+            if let Err(err) = server.run_migrations().await {
+                trc::error!(err.details("Store migration failed"));
+                std::process::exit(1);
+            }
+        } else {
+            server.wait_for_migrations().await;
+        }
+    });
+
     // Spawn gossip
     if let Some(gossiper) = gossiper {
         gossiper.spawn(init.inner, shutdown_rx.clone()).await;
     }
 
-    // Wait for shutdown signal
-    wait_for_shutdown().await;
+    // Wait for shutdown signal or a restart requested through the management API
+    #[cfg(not(windows))]
+    let restart = tokio::select! {
+        _ = wait_for_shutdown() => false,
+        _ = inner.data.restart.requested() => true,
+    };
+    #[cfg(windows)]
+    let restart = {
+        common::manager::service::wait_for_shutdown().await;
+        false
+    };
+    if restart {
+        trc::event!(Server(trc::ServerEvent::Restart));
+        inner.data.restart.set_state(RestartState::Draining);
+    }
+
+    // Stop accepting connections and disconnect idle sessions
+    let _ = shutdown_tx.send(true);
+    drop(shutdown_rx);
+
+    // Wait for active sessions to finish, they hold a shutdown receiver until closed
+    let deadline = tokio::time::Instant::now() + shutdown_timeout;
+    if tokio::time::timeout_at(deadline, shutdown_tx.closed())
+        .await
+        .is_err()
+    {
+        trc::event!(
+            Server(trc::ServerEvent::Shutdown),
+            Details = "Timed out waiting for active sessions to close",
+            Limit = shutdown_timeout,
+        );
+    }
+
+    // Wait for in-flight deliveries
+    if restart {
+        inner.data.restart.set_state(RestartState::Flushing);
+    }
+    let _ = inner.ipc.queue_tx.send(QueueEvent::Stop).await;
+    if tokio::time::timeout_at(deadline, inner.ipc.queue_tx.closed())
+        .await
+        .is_err()
+    {
+        trc::event!(
+            Server(trc::ServerEvent::Shutdown),
+            Details = "Timed out waiting for in-flight deliveries",
+            Limit = shutdown_timeout,
+        );
+    }
+
+    // Stop housekeeper
+    let _ = inner.ipc.housekeeper_tx.send(HousekeeperEvent::Exit).await;
+    let _ = tokio::time::timeout_at(deadline, inner.ipc.housekeeper_tx.closed()).await;
 
     // Shutdown collector
+    if restart {
+        inner.data.restart.set_state(RestartState::Restarting);
+    }
     Collector::shutdown();
 
-    // Stop services
-    let _ = shutdown_tx.send(true);
-
     // Wait for services to finish
     tokio::time::sleep(Duration::from_secs(1)).await;
 
+    // Replace the process with a new instance, which boots from scratch
+    #[cfg(unix)]
+    if restart {
+        use std::os::unix::process::CommandExt;
+
+        return Err(std::process::Command::new(std::env::current_exe()?)
+            .args(std::env::args_os().skip(1))
+            .exec());
+    }
+
+    #[cfg(windows)]
+    common::manager::service::report_stopped();
+
     Ok(())
 }
@@ -26,6 +26,18 @@ impl<T: SessionStream> Session<T> {
         }
 
         let script = request.tokens.into_iter().next().unwrap().unwrap_bytes();
+        if let Some(capability) = self
+            .server
+            .core
+            .sieve
+            .untrusted_extensions
+            .find_disabled(&script)
+        {
+            return Err(trc::ManageSieveEvent::Error.into_err().details(format!(
+                "Extension \"{capability}\" is not enabled on this server."
+            )));
+        }
+
         self.server
             .core
             .sieve
@@ -77,6 +77,19 @@ impl<T: SessionStream> Session<T> {
                 .code(ResponseCode::QuotaMaxScripts));
         }
 
+        // Reject extensions that have been disabled by the administrator
+        if let Some(capability) = self
+            .server
+            .core
+            .sieve
+            .untrusted_extensions
+            .find_disabled(&script_bytes)
+        {
+            return Err(trc::ManageSieveEvent::Error.into_err().details(format!(
+                "Extension \"{capability}\" is not enabled on this server."
+            )));
+        }
+
         // Compile script
         match self
             .server
@@ -225,6 +225,13 @@ impl<T: SessionStream> Session<T> {
                 }
             }
 
+            Command::Top { .. }
+                if !self.allow_top && matches!(self.state, State::Authenticated { .. }) =>
+            {
+                Err(trc::Pop3Event::Error
+                    .into_err()
+                    .details("TOP is not available."))
+            }
             Command::List { .. }
             | Command::Retr { .. }
             | Command::Dele { .. }
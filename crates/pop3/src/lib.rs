@@ -8,7 +8,7 @@ use std::{net::IpAddr, sync::Arc};
 
 use common::{
     auth::AccessToken,
-    listener::{limiter::InFlight, ServerInstance, SessionStream},
+    listener::{limiter::InFlight, ConnectionInfo, ServerInstance, SessionStream},
     Inner, Server,
 };
 use mailbox::Mailbox;
@@ -41,6 +41,8 @@ pub struct Session<T: SessionStream> {
     pub stream: T,
     pub in_flight: InFlight,
     pub remote_addr: IpAddr,
+    pub connection: ConnectionInfo,
+    pub allow_top: bool,
     pub session_id: u64,
 }
 
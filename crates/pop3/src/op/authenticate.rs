@@ -112,6 +112,9 @@ impl<T: SessionStream> Session<T> {
         // Fetch mailbox
         let mailbox = self.fetch_mailbox(access_token.primary_id()).await?;
 
+        // Evaluate protocol extensions for the principal
+        self.update_extensions(&access_token.name).await;
+
         // Create session
         self.state = State::Authenticated {
             in_flight,
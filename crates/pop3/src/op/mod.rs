@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::listener::SessionStream;
+use common::listener::{SessionStream, SessionVariables};
 
 use crate::{
     protocol::{response::Response, Mechanism},
@@ -36,12 +36,29 @@ impl<T: SessionStream> Session<T> {
             Response::Capability::<u32> {
                 mechanisms,
                 stls: !self.stream.is_tls(),
+                top: self.allow_top,
             }
             .serialize(),
         )
         .await
     }
 
+    pub async fn update_extensions(&mut self, authenticated_as: &str) {
+        self.allow_top = self
+            .server
+            .is_extension_enabled(
+                &self.server.core.imap.extension_pop3_top,
+                &SessionVariables {
+                    instance: &self.instance,
+                    connection: &self.connection,
+                    is_tls: self.stream.is_tls(),
+                    authenticated_as,
+                },
+                self.session_id,
+            )
+            .await;
+    }
+
     pub async fn handle_stls(&mut self) -> trc::Result<()> {
         trc::event!(
             Pop3(trc::Pop3Event::StartTls),
@@ -19,6 +19,7 @@ pub enum Response<T> {
     Capability {
         mechanisms: Vec<Mechanism>,
         stls: bool,
+        top: bool,
     },
 }
 
@@ -88,7 +89,11 @@ impl<T: Display> Response<T> {
                 buf.extend_from_slice(b".\r\n");
                 buf
             }
-            Response::Capability { mechanisms, stls } => {
+            Response::Capability {
+                mechanisms,
+                stls,
+                top,
+            } => {
                 let mut buf = Vec::with_capacity(256);
                 buf.extend_from_slice(b"+OK Capability list follows\r\n");
                 if !mechanisms.is_empty() {
@@ -107,8 +112,11 @@ impl<T: Display> Response<T> {
                     buf.extend_from_slice(b"STLS\r\n");
                 }
 
+                if *top {
+                    buf.extend_from_slice(b"TOP\r\n");
+                }
+
                 for capa in [
-                    "TOP",
                     "RESP-CODES",
                     "PIPELINING",
                     "EXPIRE NEVER",
@@ -189,6 +197,7 @@ mod tests {
                 Response::Capability {
                     mechanisms: vec![Mechanism::Plain, Mechanism::CramMd5],
                     stls: true,
+                    top: true,
                 },
                 concat!(
                     "+OK Capability list follows\r\n",
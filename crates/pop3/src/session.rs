@@ -29,6 +29,7 @@ impl SessionManager for Pop3SessionManager {
         session: SessionData<T>,
     ) -> impl std::future::Future<Output = ()> + Send {
         async move {
+            let connection = session.connection_info();
             let mut session = Session {
                 server: self.inner.build_server(),
                 instance: session.instance,
@@ -40,8 +41,11 @@ impl SessionManager for Pop3SessionManager {
                 stream: session.stream,
                 in_flight: session.in_flight,
                 remote_addr: session.remote_ip,
+                connection,
+                allow_top: true,
                 session_id: session.session_id,
             };
+            session.update_extensions("").await;
 
             if session
                 .write_bytes(SERVER_GREETING.as_bytes())
@@ -149,6 +153,8 @@ impl<T: SessionStream> Session<T> {
             session_id: self.session_id,
             in_flight: self.in_flight,
             remote_addr: self.remote_addr,
+            connection: self.connection,
+            allow_top: self.allow_top,
         })
     }
 }
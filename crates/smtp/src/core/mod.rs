@@ -12,9 +12,9 @@ use std::{
 };
 
 use common::{
-    Inner, Server,
     auth::AccessToken,
     config::smtp::auth::VerifyStrategy,
+    Inner, Server,
     listener::{ServerInstance, asn::AsnGeoLookupResult},
 };
 use directory::Directory;
@@ -25,7 +25,7 @@ use smtp_proto::request::receiver::{
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::{
-    inbound::auth::SaslToken,
+    inbound::{auth::SaslToken, list::ListRecipient, spool::SpooledMessage},
     queue::{DomainPart, QueueId},
 };
 
@@ -73,13 +73,17 @@ pub struct SessionData {
     pub remote_ip_str: String,
     pub remote_port: u16,
     pub asn_geo_data: AsnGeoLookupResult,
+    pub reputation: f64,
     pub helo_domain: String,
 
     pub mail_from: Option<SessionAddress>,
     pub rcpt_to: Vec<SessionAddress>,
+    pub list_rcpts: Vec<ListRecipient>,
     pub rcpt_errors: usize,
+    pub rcpt_unknown: usize,
     pub rcpt_oks: usize,
     pub message: Vec<u8>,
+    pub spool: SpooledMessage,
 
     pub authenticated_as: Option<Arc<AccessToken>>,
     pub auth_errors: usize,
@@ -96,6 +100,10 @@ pub struct SessionData {
     pub spf_ehlo: Option<SpfOutput>,
     pub spf_mail_from: Option<SpfOutput>,
     pub dnsbl_error: Option<Vec<u8>>,
+
+    pub batch_responses: bool,
+    pub pending_responses: Vec<u8>,
+    pub pipelining_violations: usize,
 }
 
 #[derive(Clone, Debug)]
@@ -125,10 +133,17 @@ pub struct SessionParameters {
     // Rcpt parameters
     pub rcpt_errors_max: usize,
     pub rcpt_errors_wait: Duration,
+    pub rcpt_harvest: bool,
+    pub rcpt_harvest_delay: Duration,
+    pub rcpt_harvest_max_delay: Duration,
+    pub rcpt_harvest_disconnect: usize,
+    pub rcpt_harvest_block: bool,
     pub rcpt_max: usize,
     pub rcpt_dsn: bool,
     pub can_expn: bool,
     pub can_vrfy: bool,
+    pub pipelining: bool,
+    pub pipelining_strict: bool,
     pub max_message_size: usize,
 
     // Mail authentication parameters
@@ -155,15 +170,19 @@ impl SessionData {
             remote_ip_str: remote_ip.to_string(),
             remote_port,
             asn_geo_data,
+            reputation: 0.0,
             helo_domain: String::new(),
             mail_from: None,
             rcpt_to: Vec::new(),
+            list_rcpts: Vec::new(),
             authenticated_as: None,
             priority: 0,
             valid_until: Instant::now(),
             rcpt_errors: 0,
+            rcpt_unknown: 0,
             rcpt_oks: 0,
             message: Vec::with_capacity(0),
+            spool: SpooledMessage::default(),
             auth_errors: 0,
             messages_sent: 0,
             bytes_left: 0,
@@ -173,6 +192,9 @@ impl SessionData {
             spf_ehlo: None,
             spf_mail_from: None,
             dnsbl_error: None,
+            batch_responses: false,
+            pending_responses: Vec::new(),
+            pipelining_violations: 0,
         }
     }
 }
@@ -235,6 +257,11 @@ impl Session<common::listener::stream::NullIo> {
                 auth_errors_wait: Default::default(),
                 rcpt_errors_max: Default::default(),
                 rcpt_errors_wait: Default::default(),
+                rcpt_harvest: Default::default(),
+                rcpt_harvest_delay: Default::default(),
+                rcpt_harvest_max_delay: Default::default(),
+                rcpt_harvest_disconnect: Default::default(),
+                rcpt_harvest_block: Default::default(),
                 rcpt_max: Default::default(),
                 rcpt_dsn: Default::default(),
                 max_message_size: Default::default(),
@@ -243,6 +270,8 @@ impl Session<common::listener::stream::NullIo> {
                 spf_mail_from: VerifyStrategy::Disable,
                 can_expn: false,
                 can_vrfy: false,
+                pipelining: false,
+                pipelining_strict: false,
             },
         }
     }
@@ -278,12 +307,16 @@ impl SessionData {
             local_port: 0,
             session_id,
             asn_geo_data: AsnGeoLookupResult::default(),
+            reputation: 0.0,
             helo_domain: "localhost".into(),
             mail_from,
             rcpt_to,
+            list_rcpts: Vec::new(),
             rcpt_errors: 0,
+            rcpt_unknown: 0,
             rcpt_oks: 0,
             message,
+            spool: SpooledMessage::default(),
             authenticated_as: Some(Arc::new(AccessToken::from_id(0))),
             auth_errors: 0,
             priority: 0,
@@ -296,6 +329,9 @@ impl SessionData {
             spf_ehlo: None,
             spf_mail_from: None,
             dnsbl_error: None,
+            batch_responses: false,
+            pending_responses: Vec::new(),
+            pipelining_violations: 0,
         }
     }
 }
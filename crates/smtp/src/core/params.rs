@@ -106,6 +106,13 @@ impl<T: SessionStream> Session<T> {
             .eval_if(&ec.vrfy, self, self.data.session_id)
             .await
             .unwrap_or(false);
+
+        // Pipelining parameters
+        self.params.pipelining_strict = self
+            .server
+            .eval_if(&ec.pipelining_strict, self, self.data.session_id)
+            .await
+            .unwrap_or(false);
     }
 
     pub async fn eval_post_auth_params(&mut self) {
@@ -135,6 +142,33 @@ impl<T: SessionStream> Session<T> {
             .eval_if(&rc.errors_wait, self, self.data.session_id)
             .await
             .unwrap_or_else(|| Duration::from_secs(30));
+        self.params.rcpt_harvest = self
+            .server
+            .eval_if(&rc.harvest_enable, self, self.data.session_id)
+            .await
+            .unwrap_or(false);
+        if self.params.rcpt_harvest {
+            self.params.rcpt_harvest_delay = self
+                .server
+                .eval_if(&rc.harvest_delay, self, self.data.session_id)
+                .await
+                .unwrap_or_else(|| Duration::from_secs(1));
+            self.params.rcpt_harvest_max_delay = self
+                .server
+                .eval_if(&rc.harvest_max_delay, self, self.data.session_id)
+                .await
+                .unwrap_or_else(|| Duration::from_secs(30));
+            self.params.rcpt_harvest_disconnect = self
+                .server
+                .eval_if(&rc.harvest_disconnect, self, self.data.session_id)
+                .await
+                .unwrap_or(10);
+            self.params.rcpt_harvest_block = self
+                .server
+                .eval_if(&rc.harvest_block, self, self.data.session_id)
+                .await
+                .unwrap_or(false);
+        }
         self.params.rcpt_max = self
             .server
             .eval_if(&rc.max_recipients, self, self.data.session_id)
@@ -129,6 +129,12 @@ impl NewKey for QueueRateLimiter {
         if (self.keys & THROTTLE_LOCAL_IP) != 0 {
             hasher.update(e.resolve_variable(V_LOCAL_IP).to_string().as_bytes());
         }
+        if (self.keys & THROTTLE_ASN) != 0 {
+            hasher.update(e.resolve_variable(V_ASN).to_string().as_bytes());
+        }
+        if (self.keys & THROTTLE_COUNTRY) != 0 {
+            hasher.update(e.resolve_variable(V_COUNTRY).to_string().as_bytes());
+        }
         hasher.update(&self.rate.period.as_secs().to_be_bytes()[..]);
         hasher.update(&self.rate.requests.to_be_bytes()[..]);
         hasher.update(context.as_bytes());
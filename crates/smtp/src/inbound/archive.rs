@@ -0,0 +1,136 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{config::smtp::archive::ArchivePolicy, Server};
+use mail_parser::DateTime;
+use sha2::{Digest, Sha256};
+use store::write::now;
+use trc::SmtpEvent;
+
+use crate::queue::{trace_id, QueueId};
+
+pub const ARCHIVE_MANIFEST_VERSION: u32 = 1;
+
+pub struct ArchivedMessage<'x> {
+    pub queue_id: QueueId,
+    pub return_path: &'x str,
+    pub recipients: Vec<&'x str>,
+    pub message_id: Option<&'x str>,
+    pub headers: &'x [u8],
+    pub raw_message: &'x [u8],
+}
+
+pub trait MessageArchive: Sync + Send {
+    fn archive_message(
+        &self,
+        policy: &ArchivePolicy,
+        domain: &str,
+        message: ArchivedMessage<'_>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl MessageArchive for Server {
+    async fn archive_message(
+        &self,
+        policy: &ArchivePolicy,
+        domain: &str,
+        message: ArchivedMessage<'_>,
+        session_id: u64,
+    ) {
+        let Some(store) = self.core.storage.blobs.get(&policy.blob_store) else {
+            trc::event!(
+                Smtp(SmtpEvent::ArchiveFailed),
+                SpanId = session_id,
+                Id = policy.id.clone(),
+                Reason = format!("Blob store {:?} not found", policy.blob_store),
+            );
+            return;
+        };
+
+        let mut contents = Vec::with_capacity(message.headers.len() + message.raw_message.len());
+        contents.extend_from_slice(message.headers);
+        contents.extend_from_slice(message.raw_message);
+        let hash = Sha256::digest(&contents)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        // Names are unique per message and never reused, which allows buckets
+        // with object lock to reject any overwrite
+        let archived_at = DateTime::from_timestamp(now() as i64);
+        let name = format!(
+            "{}{}/{:04}/{:02}/{:02}/{}-{}",
+            policy.prefix,
+            domain,
+            archived_at.year,
+            archived_at.month,
+            archived_at.day,
+            trace_id(message.queue_id),
+            &hash[..16]
+        );
+        let object = format!("{name}.eml");
+
+        if let Err(err) = store
+            .put_named_blob(&object, &contents, "message/rfc822")
+            .await
+        {
+            trc::event!(
+                Smtp(SmtpEvent::ArchiveFailed),
+                SpanId = session_id,
+                Id = policy.id.clone(),
+                Details = object,
+                CausedBy = err,
+            );
+            return;
+        }
+
+        if policy.manifest {
+            let manifest = serde_json::json!({
+                "version": ARCHIVE_MANIFEST_VERSION,
+                "policy": policy.id,
+                "object": object,
+                "queueId": trace_id(message.queue_id),
+                "archivedAt": archived_at.to_rfc3339(),
+                "returnPath": message.return_path,
+                "recipients": message.recipients,
+                "messageId": message.message_id,
+                "size": contents.len(),
+                "sha256": hash,
+            });
+
+            if let Err(err) = store
+                .put_named_blob(
+                    &format!("{name}.json"),
+                    &serde_json::to_vec_pretty(&manifest).unwrap_or_default(),
+                    "application/json",
+                )
+                .await
+            {
+                trc::event!(
+                    Smtp(SmtpEvent::ArchiveFailed),
+                    SpanId = session_id,
+                    Id = policy.id.clone(),
+                    Details = format!("{name}.json"),
+                    CausedBy = err,
+                );
+                return;
+            }
+        }
+
+        trc::event!(
+            Smtp(SmtpEvent::Archived),
+            SpanId = session_id,
+            Id = policy.id.clone(),
+            QueueId = message.queue_id,
+            Details = object,
+            From = message.return_path.to_string(),
+            Size = contents.len(),
+        );
+    }
+}
@@ -11,6 +11,7 @@ use common::{
         },
         AuthRequest,
     },
+    config::smtp::session::Mechanism,
     listener::SessionStream,
 };
 use directory::Permission;
@@ -21,6 +22,8 @@ use trc::{AuthEvent, SmtpEvent};
 
 use crate::core::Session;
 
+use super::reputation::ReputationEvent;
+
 pub struct SaslToken {
     mechanism: u64,
     credentials: Credentials<String>,
@@ -58,6 +61,26 @@ impl SaslToken {
 }
 
 impl<T: SessionStream> Session<T> {
+    /// Returns the SASL mechanisms available in this session, or zero when
+    /// authentication is not allowed for this listener or network.
+    pub async fn auth_mechanisms(&self) -> u64 {
+        let ac = &self.server.core.smtp.session.auth;
+        if self
+            .server
+            .eval_if(&ac.allow, self, self.data.session_id)
+            .await
+            .unwrap_or(true)
+        {
+            self.server
+                .eval_if::<Mechanism, _>(&ac.mechanisms, self, self.data.session_id)
+                .await
+                .unwrap_or_default()
+                .into()
+        } else {
+            0
+        }
+    }
+
     pub async fn handle_sasl_response(
         &mut self,
         token: &mut SaslToken,
@@ -148,6 +171,7 @@ impl<T: SessionStream> Session<T> {
 
                     match reason {
                         trc::EventType::Auth(trc::AuthEvent::Failed) => {
+                            self.update_reputation(ReputationEvent::AuthFailure).await;
                             return self
                                 .auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
                                 .await;
@@ -11,13 +11,17 @@ use std::{
 
 use common::{
     config::{
-        smtp::{auth::VerifyStrategy, session::Stage},
+        smtp::{
+            auth::VerifyStrategy,
+            session::{HeaderPrivacy, Stage},
+        },
         spamfilter::SpamFilterAction,
     },
     listener::SessionStream,
     psl,
     scripts::ScriptModification,
 };
+use email::{disclaimer::append_disclaimer, smime::SmimeGateway};
 use mail_auth::{
     common::{headers::HeaderWriter, verify::VerifySignature},
     dmarc::{self, verify::DmarcParameters},
@@ -35,8 +39,17 @@ use utils::config::Rate;
 
 use crate::{
     core::{Session, SessionAddress, State},
-    inbound::milter::Modification,
-    queue::{self, quota::HasQueueQuota, Message, MessageSource, QueueEnvelope, Schedule},
+    inbound::{
+        archive::{ArchivedMessage, MessageArchive},
+        dns_cache::AuthDnsCache,
+        journal::MessageJournal,
+        list::ListPostAuth,
+        milter::Modification,
+    },
+    queue::{
+        self, moderation::OutboundModeration, quota::HasQueueQuota, trace_id, Message,
+        MessageSource, QueueEnvelope, Schedule,
+    },
     reporting::analysis::AnalyzeReport,
     scripts::ScriptResult,
 };
@@ -46,7 +59,16 @@ use super::{ArcSeal, AuthResult, DkimSign};
 impl<T: SessionStream> Session<T> {
     pub async fn queue_message(&mut self) -> Cow<'static, [u8]> {
         // Parse message
-        let raw_message = std::mem::take(&mut self.data.message);
+        let raw_message = match self.unspool_message().await {
+            Ok(raw_message) => raw_message,
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to read spooled message.")
+                    .span_id(self.data.session_id));
+
+                return (&b"451 4.3.0 Temporary server failure.\r\n"[..]).into();
+            }
+        };
         let parsed_message = match MessageParser::new()
             .parse(&raw_message)
             .filter(|p| p.headers().iter().any(|h| !h.name.is_other()))
@@ -103,6 +125,19 @@ impl<T: SessionStream> Session<T> {
             .await
             .unwrap_or(VerifyStrategy::Relaxed);
         let dkim_output = if dkim.verify() || dmarc.verify() {
+            // Warm the local DNS cache from the cache shared across the cluster
+            self.server
+                .prefetch_dkim_records(
+                    &auth_message,
+                    auth_message
+                        .from
+                        .first()
+                        .filter(|_| dmarc.verify())
+                        .and_then(|from| from.rsplit_once('@'))
+                        .map(|(_, domain)| domain),
+                )
+                .await;
+
             let time = Instant::now();
             let dkim_output = self
                 .server
@@ -328,37 +363,41 @@ impl<T: SessionStream> Session<T> {
         // Analyze reports
         if is_report {
             if !rc.analysis.forward {
-                self.server.analyze_report(
-                    mail_parser::Message {
-                        html_body: parsed_message.html_body,
-                        text_body: parsed_message.text_body,
-                        attachments: parsed_message.attachments,
-                        parts: parsed_message
-                            .parts
-                            .into_iter()
-                            .map(|p| p.into_owned())
-                            .collect(),
-                        raw_message: b"".into(),
-                    },
-                    self.data.session_id,
-                );
+                self.server
+                    .analyze_report(
+                        mail_parser::Message {
+                            html_body: parsed_message.html_body,
+                            text_body: parsed_message.text_body,
+                            attachments: parsed_message.attachments,
+                            parts: parsed_message
+                                .parts
+                                .into_iter()
+                                .map(|p| p.into_owned())
+                                .collect(),
+                            raw_message: b"".into(),
+                        },
+                        self.data.session_id,
+                    )
+                    .await;
                 self.data.messages_sent += 1;
                 return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
             } else {
-                self.server.analyze_report(
-                    mail_parser::Message {
-                        html_body: parsed_message.html_body.clone(),
-                        text_body: parsed_message.text_body.clone(),
-                        attachments: parsed_message.attachments.clone(),
-                        parts: parsed_message
-                            .parts
-                            .iter()
-                            .map(|p| p.clone().into_owned())
-                            .collect(),
-                        raw_message: b"".into(),
-                    },
-                    self.data.session_id,
-                );
+                self.server
+                    .analyze_report(
+                        mail_parser::Message {
+                            html_body: parsed_message.html_body.clone(),
+                            text_body: parsed_message.text_body.clone(),
+                            attachments: parsed_message.attachments.clone(),
+                            parts: parsed_message
+                                .parts
+                                .iter()
+                                .map(|p| p.clone().into_owned())
+                                .collect(),
+                            raw_message: b"".into(),
+                        },
+                        self.data.session_id,
+                    )
+                    .await;
             }
         }
 
@@ -371,13 +410,38 @@ impl<T: SessionStream> Session<T> {
             .generate()
             .unwrap_or_else(now);
         let mut headers = Vec::with_capacity(64);
+        let privacy = if self.is_authenticated() {
+            self.server
+                .eval_if(&dc.privacy, self, self.data.session_id)
+                .await
+                .unwrap_or_default()
+        } else {
+            HeaderPrivacy::Disable
+        };
+        if privacy != HeaderPrivacy::Strip
+            && self
+                .server
+                .eval_if(&dc.add_received, self, self.data.session_id)
+                .await
+                .unwrap_or(true)
+        {
+            self.write_received(
+                &mut headers,
+                message_id,
+                privacy == HeaderPrivacy::Anonymize,
+            )
+        }
+
+        // Add trace id header
         if self
             .server
-            .eval_if(&dc.add_received, self, self.data.session_id)
+            .eval_if(&dc.add_trace_id, self, self.data.session_id)
             .await
-            .unwrap_or(true)
+            .unwrap_or(false)
         {
-            self.write_received(&mut headers, message_id)
+            headers.extend_from_slice(b"X-Trace-Id: ");
+            headers.extend_from_slice(trace_id(message_id).as_bytes());
+            headers.extend_from_slice(b"\r\n");
         }
 
         // Add authentication results header
@@ -410,11 +474,14 @@ impl<T: SessionStream> Session<T> {
         }
 
         // ARC Seal
+        let mut arc_set = None;
         if let (Some(arc_sealer), Some(arc_output)) = (arc_sealer, &arc_output) {
             if !dkim_output.is_empty() && arc_output.can_be_sealed() {
                 match arc_sealer.seal(&auth_message, &auth_results, arc_output) {
                     Ok(set) => {
+                        let offset = headers.len();
                         set.write_header(&mut headers);
+                        arc_set = Some(offset..headers.len());
                     }
                     Err(err) => {
                         trc::error!(trc::Error::from(err)
@@ -440,6 +507,7 @@ impl<T: SessionStream> Session<T> {
                     (&arc_output).into(),
                     dmarc_result.as_ref(),
                     dmarc_policy.as_ref(),
+                    message_id,
                 )
                 .await
             {
@@ -490,6 +558,8 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Apply modifications
+        let is_filter_modified = !modifications.is_empty();
+        let mut is_script_modified = false;
         let mut edited_message = if !modifications.is_empty() {
             self.data
                 .apply_milter_modifications(modifications, &auth_message)
@@ -555,7 +625,7 @@ impl<T: SessionStream> Session<T> {
                         .map(|a| a.as_str())
                         .unwrap_or_default(),
                 )
-                .with_message(parsed_message);
+                .with_message(parsed_message.clone());
 
             let modifications = match self.run_script(script_id, script.clone(), params).await {
                 ScriptResult::Accept { modifications } => modifications,
@@ -564,6 +634,7 @@ impl<T: SessionStream> Session<T> {
                     modifications,
                 } => {
                     edited_message = message.into();
+                    is_script_modified = true;
                     modifications
                 }
                 ScriptResult::Reject(message) => {
@@ -578,6 +649,7 @@ impl<T: SessionStream> Session<T> {
             for modification in modifications {
                 match modification {
                     ScriptModification::AddHeader { name, value } => {
+                        is_script_modified = true;
                         headers.extend_from_slice(name.as_bytes());
                         headers.extend_from_slice(b": ");
                         headers.extend_from_slice(value.as_bytes());
@@ -592,9 +664,80 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Append outbound disclaimer
+        let mut is_disclaimer_modified = false;
+        if self.is_authenticated()
+            && let Some(disclaimer) = self.server.core.smtp.disclaimers.disclaimer_for(
+                &self.data.mail_from.as_ref().unwrap().address_lcase,
+                self.data
+                    .rcpt_to
+                    .iter()
+                    .map(|rcpt| rcpt.address_lcase.as_str()),
+            )
+            && let Some(message) = append_disclaimer(
+                edited_message.as_deref().unwrap_or(raw_message.as_slice()),
+                disclaimer,
+            )
+        {
+            edited_message = message.into();
+            is_disclaimer_modified = true;
+        }
+
+        // Remove headers revealing details about the originating client
+        if privacy != HeaderPrivacy::Disable
+            && let Some(message) = scrub_headers(
+                edited_message.as_deref().unwrap_or(raw_message.as_slice()),
+                &dc.privacy_headers,
+                privacy == HeaderPrivacy::Strip,
+            )
+        {
+            edited_message = message.into();
+        }
+
+        // Sign and encrypt outbound messages, or collect the S/MIME certificates
+        // of verified senders
+        let smime = &self.server.core.smtp.smime;
+        if self.is_authenticated() {
+            if let Some(policy) =
+                smime.policy_for(&self.data.mail_from.as_ref().unwrap().address_lcase)
+                && let Some(message) = self
+                    .server
+                    .smime_protect(
+                        policy,
+                        edited_message.as_deref().unwrap_or(raw_message.as_slice()),
+                        &self
+                            .data
+                            .rcpt_to
+                            .iter()
+                            .map(|rcpt| rcpt.address_lcase.as_str())
+                            .collect::<Vec<_>>(),
+                        self.data.session_id,
+                    )
+                    .await
+            {
+                edited_message = message.into();
+            }
+        } else if smime.harvest
+            && matches!(dmarc_result, Some(DmarcResult::Pass))
+            && let Err(err) = self
+                .server
+                .smime_harvest(&parsed_message, self.data.session_id)
+                .await
+        {
+            trc::error!(err.span_id(self.data.session_id));
+        }
+
         // Build message
         let mail_from = self.data.mail_from.clone().unwrap();
-        let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
+        let mut rcpt_to = std::mem::take(&mut self.data.rcpt_to);
+        let list_rcpts = std::mem::take(&mut self.data.list_rcpts);
+        if !list_rcpts.is_empty() {
+            rcpt_to.retain(|rcpt| {
+                !list_rcpts
+                    .iter()
+                    .any(|list_rcpt| list_rcpt.address_lcase == rcpt.address_lcase)
+            });
+        }
         let mut message = self
             .build_message(mail_from, rcpt_to, message_id, self.data.session_id)
             .await;
@@ -635,14 +778,74 @@ impl<T: SessionStream> Session<T> {
             headers.extend_from_slice(b"\r\n");
         }
 
-        // DKIM sign
+        // Re-seal and re-sign messages modified by filters or scripts
         let raw_message = edited_message.as_deref().unwrap_or(raw_message.as_slice());
-        for signer in self
+        let mut signers = self
             .server
             .eval_if::<Vec<String>, _>(&ac.dkim.sign, self, self.data.session_id)
             .await
-            .unwrap_or_default()
-        {
+            .unwrap_or_default();
+        let mut is_resealed = false;
+        for (is_modified, policy) in [
+            (is_filter_modified, &ac.resign.milter),
+            (is_script_modified, &ac.resign.script),
+            (is_disclaimer_modified, &ac.resign.disclaimer),
+        ] {
+            if !is_modified {
+                continue;
+            }
+
+            for signer in self
+                .server
+                .eval_if::<Vec<String>, _>(&policy.dkim, self, self.data.session_id)
+                .await
+                .unwrap_or_default()
+            {
+                if !signers.contains(&signer) {
+                    signers.push(signer);
+                }
+            }
+
+            if let Some(arc_output) = arc_output.as_ref().filter(|arc_output| {
+                !is_resealed && !dkim_output.is_empty() && arc_output.can_be_sealed()
+            }) && let Some(arc_sealer) = self
+                .server
+                .eval_if::<String, _>(&policy.arc, self, self.data.session_id)
+                .await
+                .and_then(|name| self.server.get_arc_sealer(&name, self.data.session_id))
+            {
+                // Replace the set added before the message was modified
+                if let Some(arc_set) = arc_set.take() {
+                    headers.drain(arc_set);
+                }
+
+                let mut sealed_message = Vec::with_capacity(headers.len() + raw_message.len());
+                sealed_message.extend_from_slice(&headers);
+                sealed_message.extend_from_slice(raw_message);
+                match AuthenticatedMessage::parse(&sealed_message)
+                    .ok_or(mail_auth::Error::ParseError)
+                    .and_then(|auth_message| {
+                        arc_sealer
+                            .seal(&auth_message, &auth_results, arc_output)
+                            .map(|set| set.to_header())
+                    }) {
+                    Ok(set) => {
+                        let offset = headers.len();
+                        headers.extend_from_slice(set.as_bytes());
+                        arc_set = Some(offset..headers.len());
+                    }
+                    Err(err) => {
+                        trc::error!(trc::Error::from(err)
+                            .span_id(self.data.session_id)
+                            .details("Failed to ARC seal modified message"));
+                    }
+                }
+                is_resealed = true;
+            }
+        }
+
+        // DKIM sign
+        for signer in signers {
             if let Some(signer) = self.server.get_dkim_signer(&signer, self.data.session_id) {
                 match signer.sign_chained(&[headers.as_ref(), raw_message]) {
                     Ok(signature) => {
@@ -657,14 +860,144 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Deliver to mailing lists
+        if !list_rcpts.is_empty() {
+            let is_verified =
+                self.is_authenticated() || matches!(dmarc_result, Some(DmarcResult::Pass));
+            let auth = arc_output.as_ref().map(|arc_output| ListPostAuth {
+                results: &auth_results,
+                arc_output,
+                arc_set,
+            });
+            if !self
+                .deliver_list_rcpts(list_rcpts, &headers, raw_message, auth, is_verified)
+                .await
+            {
+                return (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into();
+            } else if message.recipients.is_empty() {
+                self.data.messages_sent += 1;
+                return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
+            }
+        }
+
         // Update size
         message.size = raw_message.len() + headers.len();
 
+        // Verify tenant outbound limit
+        if let Some(tenant) = self
+            .data
+            .authenticated_as
+            .as_ref()
+            .and_then(|token| token.tenant.as_ref())
+        {
+            match self.server.is_tenant_outbound_allowed(tenant).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    trc::event!(
+                        Limit(trc::LimitEvent::TenantQuota),
+                        SpanId = self.data.session_id,
+                        AccountId = tenant.id,
+                        Limit = tenant.max_outbound,
+                    );
+
+                    return (b"452 4.5.3 Tenant daily outbound message limit exceeded.\r\n"[..])
+                        .into();
+                }
+                Err(err) => {
+                    trc::error!(err.span_id(self.data.session_id));
+
+                    return (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into();
+                }
+            }
+        }
+
+        // Learn the sender's language, used to localize messages sent to the account
+        if let Some(account_id) = self
+            .data
+            .authenticated_as
+            .as_ref()
+            .map(|token| token.primary_id)
+        {
+            self.server
+                .learn_account_language(account_id, &parsed_message)
+                .await;
+        }
+
+        // Hold submissions from moderated accounts until an administrator reviews them
+        if let Some(account) = self.authenticated_as().map(|account| account.to_string())
+            && self
+                .server
+                .eval_if(
+                    &self.server.core.smtp.session.data.moderation.enable,
+                    self,
+                    self.data.session_id,
+                )
+                .await
+                .unwrap_or(false)
+        {
+            return match self
+                .server
+                .hold_outbound_message(
+                    message,
+                    &account,
+                    parsed_message.subject().unwrap_or_default(),
+                    &headers,
+                    raw_message,
+                    self.data.session_id,
+                )
+                .await
+            {
+                Ok(_) => {
+                    self.data.messages_sent += 1;
+                    (b"250 2.0.0 Message held for moderation.\r\n"[..]).into()
+                }
+                Err(err) => {
+                    trc::error!(err.span_id(self.data.session_id));
+
+                    (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
+                }
+            };
+        }
+
         // Verify queue quota
         if self.server.has_quota(&mut message).await {
             // Prepare webhook event
             let queue_id = message.queue_id;
 
+            // Inbound messages are copied to the journal of their recipient domains
+            let journal_rcpts =
+                if !self.is_authenticated() && !self.server.core.smtp.journal.domains.is_empty() {
+                    message
+                        .recipients
+                        .iter()
+                        .map(|rcpt| rcpt.address_lcase.clone())
+                        .collect::<Vec<_>>()
+                } else {
+                    Vec::new()
+                };
+            let return_path = message.return_path.clone();
+
+            // Outbound messages are archived for their sender domain
+            let archive = self
+                .server
+                .core
+                .smtp
+                .archive
+                .domains
+                .get(&message.return_path_domain)
+                .filter(|_| self.is_authenticated())
+                .map(|policy| {
+                    (
+                        policy.clone(),
+                        message.return_path_domain.clone(),
+                        message
+                            .recipients
+                            .iter()
+                            .map(|rcpt| rcpt.address_lcase.clone())
+                            .collect::<Vec<_>>(),
+                    )
+                });
+
             // Queue message
             let source = if !self.is_authenticated() {
                 MessageSource::Unauthenticated
@@ -681,6 +1014,36 @@ impl<T: SessionStream> Session<T> {
                 )
                 .await
             {
+                if !journal_rcpts.is_empty() {
+                    self.server
+                        .journal_message(
+                            &return_path,
+                            journal_rcpts.iter().map(|rcpt| rcpt.as_str()),
+                            &headers,
+                            raw_message,
+                            self.data.session_id,
+                        )
+                        .await;
+                }
+
+                if let Some((policy, domain, rcpts)) = &archive {
+                    self.server
+                        .archive_message(
+                            policy,
+                            domain,
+                            ArchivedMessage {
+                                queue_id,
+                                return_path: &return_path,
+                                recipients: rcpts.iter().map(|rcpt| rcpt.as_str()).collect(),
+                                message_id: parsed_message.message_id(),
+                                headers: &headers,
+                                raw_message,
+                            },
+                            self.data.session_id,
+                        )
+                        .await;
+                }
+
                 self.state = State::Accepted(queue_id);
                 self.data.messages_sent += 1;
                 (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
@@ -865,8 +1228,37 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
-    fn write_received(&self, headers: &mut Vec<u8>, id: u64) {
-        headers.extend_from_slice(b"Received: from ");
+    fn write_received(&self, headers: &mut Vec<u8>, id: u64, anonymize: bool) {
+        headers.extend_from_slice(b"Received: ");
+        if !anonymize {
+            self.write_received_from(headers);
+        }
+        if self.stream.is_tls() {
+            let (version, cipher) = self.stream.tls_version_and_cipher();
+            headers.extend_from_slice(b"(using ");
+            headers.extend_from_slice(version.as_bytes());
+            headers.extend_from_slice(b" with cipher ");
+            headers.extend_from_slice(cipher.as_bytes());
+            headers.extend_from_slice(b")\r\n\t");
+        }
+        headers.extend_from_slice(b"by ");
+        headers.extend_from_slice(self.hostname.as_bytes());
+        headers.extend_from_slice(b" (Stalwart SMTP) with ");
+        headers.extend_from_slice(match (self.stream.is_tls(), !self.is_authenticated()) {
+            (true, true) => b"ESMTPS",
+            (true, false) => b"ESMTPSA",
+            (false, true) => b"ESMTP",
+            (false, false) => b"ESMTPA",
+        });
+        headers.extend_from_slice(b" id ");
+        headers.extend_from_slice(format!("{id:X}").as_bytes());
+        headers.extend_from_slice(b";\r\n\t");
+        headers.extend_from_slice(Date::now().to_rfc822().as_bytes());
+        headers.extend_from_slice(b"\r\n");
+    }
+
+    fn write_received_from(&self, headers: &mut Vec<u8>) {
+        headers.extend_from_slice(b"from ");
         headers.extend_from_slice(self.data.helo_domain.as_bytes());
         headers.extend_from_slice(b" (");
         headers.extend_from_slice(
@@ -900,27 +1292,30 @@ impl<T: SessionStream> Session<T> {
             headers.extend_from_slice(b")");
         }
         headers.extend_from_slice(b")\r\n\t");
-        if self.stream.is_tls() {
-            let (version, cipher) = self.stream.tls_version_and_cipher();
-            headers.extend_from_slice(b"(using ");
-            headers.extend_from_slice(version.as_bytes());
-            headers.extend_from_slice(b" with cipher ");
-            headers.extend_from_slice(cipher.as_bytes());
-            headers.extend_from_slice(b")\r\n\t");
+    }
+}
+
+/// Removes the listed headers, and optionally any Received headers, from
+/// the top-level header section of a message.
+fn scrub_headers(raw_message: &[u8], names: &[String], strip_received: bool) -> Option<Vec<u8>> {
+    let message = MessageParser::new().parse_headers(raw_message)?;
+    let mut scrubbed = Vec::with_capacity(raw_message.len());
+    let mut last_offset = 0;
+
+    for header in message.headers() {
+        let name = header.name.as_str();
+        if (strip_received && name.eq_ignore_ascii_case("Received"))
+            || names.iter().any(|n| n.eq_ignore_ascii_case(name))
+        {
+            scrubbed.extend_from_slice(raw_message.get(last_offset..header.offset_field)?);
+            last_offset = header.offset_end;
         }
-        headers.extend_from_slice(b"by ");
-        headers.extend_from_slice(self.hostname.as_bytes());
-        headers.extend_from_slice(b" (Stalwart SMTP) with ");
-        headers.extend_from_slice(match (self.stream.is_tls(), !self.is_authenticated()) {
-            (true, true) => b"ESMTPS",
-            (true, false) => b"ESMTPSA",
-            (false, true) => b"ESMTP",
-            (false, false) => b"ESMTPA",
-        });
-        headers.extend_from_slice(b" id ");
-        headers.extend_from_slice(format!("{id:X}").as_bytes());
-        headers.extend_from_slice(b";\r\n\t");
-        headers.extend_from_slice(Date::now().to_rfc822().as_bytes());
-        headers.extend_from_slice(b"\r\n");
+    }
+
+    if last_offset > 0 {
+        scrubbed.extend_from_slice(raw_message.get(last_offset..)?);
+        Some(scrubbed)
+    } else {
+        None
     }
 }
@@ -0,0 +1,227 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use common::{config::smtp::auth::DnsCacheConfig, Server, KV_DNS_AUTH_CACHE};
+use mail_auth::{
+    common::{parse::TxtRecordParser, verify::DomainKey},
+    dmarc::Dmarc,
+    hickory_resolver::{proto::op::ResponseCode, Name, TokioAsyncResolver},
+    spf::Spf,
+    AuthenticatedMessage, Error, Txt,
+};
+use store::{
+    dispatch::lookup::KeyValue,
+    write::{now, Bincode},
+    Serialize,
+};
+use trc::AddContext;
+
+/// TXT record shared across the cluster through the in-memory store.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CachedTxt {
+    pub records: Vec<Vec<u8>>,
+    pub not_found: Option<u16>,
+    pub dnssec: bool,
+    pub expires: u64,
+}
+
+pub trait AuthDnsCache: Sync + Send {
+    fn prefetch_dkim_records(
+        &self,
+        message: &AuthenticatedMessage<'_>,
+        from_domain: Option<&str>,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn prefetch_spf_record(&self, domain: &str) -> impl Future<Output = ()> + Send;
+
+    fn prefetch_txt<T: TxtRecordParser + Into<Txt> + Send>(
+        &self,
+        name: String,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl AuthDnsCache for Server {
+    async fn prefetch_dkim_records(
+        &self,
+        message: &AuthenticatedMessage<'_>,
+        from_domain: Option<&str>,
+    ) {
+        if self.core.smtp.mail_auth.dns_cache.is_none() {
+            return;
+        }
+
+        let mut names = Vec::new();
+        for header in &message.dkim_headers {
+            if let Ok(signature) = &header.header {
+                let name = format!("{}._domainkey.{}.", signature.s, signature.d).to_lowercase();
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        for name in names {
+            self.prefetch_txt::<DomainKey>(name).await;
+        }
+
+        if let Some(domain) = from_domain.filter(|domain| !domain.is_empty()) {
+            self.prefetch_txt::<Dmarc>(format!("_dmarc.{domain}.").to_lowercase())
+                .await;
+        }
+    }
+
+    async fn prefetch_spf_record(&self, domain: &str) {
+        if self.core.smtp.mail_auth.dns_cache.is_some() && !domain.is_empty() {
+            self.prefetch_txt::<Spf>(format!("{domain}.").to_lowercase())
+                .await;
+        }
+    }
+
+    async fn prefetch_txt<T: TxtRecordParser + Into<Txt> + Send>(&self, name: String) {
+        let Some(config) = &self.core.smtp.mail_auth.dns_cache else {
+            return;
+        };
+        if self.inner.cache.dns_txt.get(&name).is_some() {
+            return;
+        }
+
+        // Obtain the record from the shared cache, or resolve it and share it
+        let key = KeyValue::<()>::build_key(KV_DNS_AUTH_CACHE, name.as_bytes());
+        let cached = match self
+            .in_memory_store()
+            .key_get::<Bincode<CachedTxt>>(key)
+            .await
+            .caused_by(trc::location!())
+        {
+            Ok(Some(cached)) => cached.inner,
+            Ok(None) => match self.resolve_txt(&name, config).await {
+                Some(cached) => {
+                    if let Err(err) = self
+                        .in_memory_store()
+                        .key_set(
+                            KeyValue::with_prefix(
+                                KV_DNS_AUTH_CACHE,
+                                name.as_bytes(),
+                                Bincode::new(cached.clone()).serialize(),
+                            )
+                            .expires(cached.expires.saturating_sub(now())),
+                        )
+                        .await
+                    {
+                        trc::error!(err.details("Failed to store DNS record in shared cache"));
+                    }
+                    cached
+                }
+                None => return,
+            },
+            Err(err) => {
+                trc::error!(err.details("Failed to obtain DNS record from shared cache"));
+                return;
+            }
+        };
+
+        // Parse the record and add it to the local cache used by mail-auth
+        let ttl = cached.expires.saturating_sub(now());
+        if ttl == 0 {
+            return;
+        }
+        let txt: Txt = if let Some(code) = cached.not_found {
+            Txt::Error(Error::DnsRecordNotFound(ResponseCode::from(code)))
+        } else {
+            let mut result: mail_auth::Result<T> = Err(Error::InvalidRecordType);
+            for record in &cached.records {
+                result = T::parse(record);
+                if result.is_ok() {
+                    break;
+                }
+            }
+            result.into()
+        };
+        self.inner
+            .cache
+            .dns_txt
+            .insert(name, txt, Duration::from_secs(ttl));
+    }
+}
+
+trait ResolveTxt {
+    fn resolve_txt(
+        &self,
+        name: &str,
+        config: &DnsCacheConfig,
+    ) -> impl Future<Output = Option<CachedTxt>> + Send;
+}
+
+impl ResolveTxt for Server {
+    async fn resolve_txt(&self, name: &str, config: &DnsCacheConfig) -> Option<CachedTxt> {
+        // Prefer DNSSEC validated answers when enabled, falling back to the
+        // regular resolver for unsigned zones.
+        let resolvers = &self.core.smtp.resolvers;
+        let result = if config.dnssec {
+            match lookup_txt(&resolvers.dnssec.resolver, name).await {
+                Ok((records, valid_until)) => Ok((records, valid_until, true)),
+                Err(_) => lookup_txt(resolvers.dns.resolver(), name)
+                    .await
+                    .map(|(records, valid_until)| (records, valid_until, false)),
+            }
+        } else {
+            lookup_txt(resolvers.dns.resolver(), name)
+                .await
+                .map(|(records, valid_until)| (records, valid_until, false))
+        };
+
+        let now = now();
+        match result {
+            Ok((records, valid_until, dnssec)) => Some(CachedTxt {
+                records,
+                not_found: None,
+                dnssec,
+                expires: now
+                    + valid_until
+                        .saturating_duration_since(Instant::now())
+                        .as_secs()
+                        .clamp(config.min_ttl, config.max_ttl),
+            }),
+            Err(Error::DnsRecordNotFound(code)) => Some(CachedTxt {
+                records: vec![],
+                not_found: Some(code.into()),
+                dnssec: false,
+                expires: now + config.negative_ttl,
+            }),
+            Err(_) => None,
+        }
+    }
+}
+
+async fn lookup_txt(
+    resolver: &TokioAsyncResolver,
+    name: &str,
+) -> mail_auth::Result<(Vec<Vec<u8>>, Instant)> {
+    let lookup = resolver.txt_lookup(Name::from_str_relaxed(name)?).await?;
+    let records = lookup
+        .as_lookup()
+        .record_iter()
+        .filter_map(|record| {
+            let txt_data = record.data()?.as_txt()?.txt_data();
+            if !txt_data.is_empty() {
+                Some(
+                    txt_data
+                        .iter()
+                        .flat_map(|data| data.iter().copied())
+                        .collect(),
+                )
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    Ok((records, lookup.valid_until()))
+}
@@ -8,7 +8,7 @@ use std::time::{Duration, Instant, SystemTime};
 
 use crate::{core::Session, scripts::ScriptResult};
 use common::{
-    config::smtp::session::{Mechanism, Stage},
+    config::smtp::session::Stage,
     listener::SessionStream,
 };
 use mail_auth::{
@@ -42,6 +42,7 @@ impl<T: SessionStream> Session<T> {
 
             // SPF check
             let prev_helo_domain = std::mem::replace(&mut self.data.helo_domain, domain);
+            self.load_reputation().await;
             if self.params.spf_ehlo.verify() {
                 let time = Instant::now();
                 let spf_output = self
@@ -136,6 +137,7 @@ impl<T: SessionStream> Session<T> {
         }
 
         if !is_extended {
+            self.params.pipelining = false;
             return self
                 .write(format!("250 {} you had me at HELO\r\n", self.hostname).as_bytes())
                 .await;
@@ -148,16 +150,15 @@ impl<T: SessionStream> Session<T> {
             response.capabilities |= EXT_START_TLS;
         }
         let ec = &self.server.core.smtp.session.extensions;
-        let ac = &self.server.core.smtp.session.auth;
         let dc = &self.server.core.smtp.session.data;
 
         // Pipelining
-        if self
+        self.params.pipelining = self
             .server
             .eval_if(&ec.pipelining, self, self.data.session_id)
             .await
-            .unwrap_or(true)
-        {
+            .unwrap_or(true);
+        if self.params.pipelining {
             response.capabilities |= EXT_PIPELINING;
         }
 
@@ -213,12 +214,7 @@ impl<T: SessionStream> Session<T> {
 
         // Authentication
         if !self.is_authenticated() {
-            response.auth_mechanisms = self
-                .server
-                .eval_if::<Mechanism, _>(&ac.mechanisms, self, self.data.session_id)
-                .await
-                .unwrap_or_default()
-                .into();
+            response.auth_mechanisms = self.auth_mechanisms().await;
             if response.auth_mechanisms != 0 {
                 response.capabilities |= EXT_AUTH;
             }
@@ -10,7 +10,7 @@ use ahash::AHashMap;
 use common::{
     config::smtp::session::{MTAHook, Stage},
     listener::SessionStream,
-    DAEMON_NAME,
+    SandboxId, DAEMON_NAME,
 };
 use mail_auth::AuthenticatedMessage;
 use trc::MtaHookEvent;
@@ -25,6 +25,7 @@ use crate::{
         FilterResponse,
     },
     queue::QueueId,
+    scripts::sandbox::ScriptSandbox,
 };
 
 use super::{client::send_mta_hook_request, Action, Queue, Response};
@@ -53,8 +54,29 @@ impl<T: SessionStream> Session<T> {
                 continue;
             }
 
+            // Skip hooks that were disabled after failing repeatedly
+            let sandbox_id = SandboxId::Hook(mta_hook.id.clone());
+            if self.server.sandbox_is_disabled(&sandbox_id) {
+                continue;
+            }
+
             let time = Instant::now();
-            match self.run_mta_hook(stage, mta_hook, message, queue_id).await {
+            let result = self.run_mta_hook(stage, mta_hook, message, queue_id).await;
+            if let Some(disabled_until) = self.server.sandbox_record(
+                sandbox_id,
+                &mta_hook.sandbox,
+                time.elapsed(),
+                result.is_err(),
+            ) {
+                trc::event!(
+                    MtaHook(MtaHookEvent::Disabled),
+                    SpanId = self.data.session_id,
+                    Id = mta_hook.id.clone(),
+                    Expires = trc::Value::Timestamp(disabled_until),
+                );
+            }
+
+            match result {
                 Ok(response) => {
                     trc::event!(
                         MtaHook(match response.action {
@@ -0,0 +1,127 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use common::{config::smtp::journal::JournalPolicy, Server};
+use email::{crypto::EncryptionMethod, escrow::escrow_decrypt};
+use trc::{SecurityEvent, SmtpEvent};
+
+use crate::reporting::SmtpReporting;
+
+pub trait MessageJournal: Sync + Send {
+    fn journal_message<'x>(
+        &self,
+        return_path: &str,
+        recipients: impl IntoIterator<Item = &'x str> + Send,
+        headers: &[u8],
+        raw_message: &[u8],
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl MessageJournal for Server {
+    async fn journal_message<'x>(
+        &self,
+        return_path: &str,
+        recipients: impl IntoIterator<Item = &'x str> + Send,
+        headers: &[u8],
+        raw_message: &[u8],
+        session_id: u64,
+    ) {
+        // Group recipients by journal policy
+        let mut policies: Vec<(Arc<JournalPolicy>, Vec<&str>)> = Vec::new();
+        for rcpt in recipients {
+            let Some(policy) = rcpt
+                .rsplit_once('@')
+                .and_then(|(_, domain)| self.core.smtp.journal.domains.get(domain))
+            else {
+                continue;
+            };
+            if let Some((_, rcpts)) = policies.iter_mut().find(|(p, _)| p.id == policy.id) {
+                rcpts.push(rcpt);
+            } else {
+                policies.push((policy.clone(), vec![rcpt]));
+            }
+        }
+
+        for (policy, rcpts) in policies {
+            // The recipients always receive the original message, only the
+            // journal copy is decrypted
+            let mut decrypted = None;
+            if policy.decrypt {
+                match escrow_decrypt(raw_message, &policy.escrow_keys) {
+                    Ok(Some(message)) => {
+                        trc::event!(
+                            Security(SecurityEvent::EscrowDecryption),
+                            SpanId = session_id,
+                            Id = policy.id.clone(),
+                            Type = match message.method {
+                                EncryptionMethod::SMIME => "smime",
+                                EncryptionMethod::PGP => "pgp",
+                            },
+                            Details = message.key_id.clone(),
+                            From = return_path.to_string(),
+                            To = rcpts
+                                .iter()
+                                .map(|rcpt| trc::Value::String(rcpt.to_string()))
+                                .collect::<Vec<_>>(),
+                        );
+                        decrypted = Some(message.contents);
+                    }
+                    Ok(None) => (),
+                    Err(err) => {
+                        trc::event!(
+                            Smtp(SmtpEvent::JournalDecryptFailed),
+                            SpanId = session_id,
+                            Id = policy.id.clone(),
+                            From = return_path.to_string(),
+                            Reason = err,
+                        );
+                    }
+                }
+            }
+            let contents = decrypted.as_deref().unwrap_or(raw_message);
+
+            // Record the envelope, which is otherwise lost in the copy
+            let mut journal = Vec::with_capacity(headers.len() + contents.len() + 128);
+            journal.extend_from_slice(b"X-Journal-Sender: <");
+            journal.extend_from_slice(return_path.as_bytes());
+            journal.extend_from_slice(b">\r\nX-Journal-Recipients: ");
+            for (idx, rcpt) in rcpts.iter().enumerate() {
+                if idx > 0 {
+                    journal.extend_from_slice(b",\r\n\t");
+                }
+                journal.push(b'<');
+                journal.extend_from_slice(rcpt.as_bytes());
+                journal.push(b'>');
+            }
+            if decrypted.is_some() {
+                journal.extend_from_slice(b"\r\nX-Journal-Decrypted: yes");
+            }
+            journal.extend_from_slice(b"\r\n");
+            journal.extend_from_slice(headers);
+            journal.extend_from_slice(contents);
+
+            trc::event!(
+                Smtp(SmtpEvent::JournalQueued),
+                SpanId = session_id,
+                Id = policy.id.clone(),
+                To = policy.address.clone(),
+                Total = rcpts.len(),
+            );
+
+            self.send_autogenerated(
+                "",
+                [policy.address.as_str()].into_iter(),
+                journal,
+                None,
+                session_id,
+            )
+            .await;
+        }
+    }
+}
@@ -0,0 +1,649 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, fmt::Write, future::Future, ops::Range, time::Duration};
+
+use common::{KV_LIST_MODERATION, KV_LOCK_LIST_MODERATION, Server, listener::SessionStream};
+use directory::backend::internal::{
+    list::{ListCommand, ListPostPolicy, MailingList, ManageMailingList},
+    lookup::DirectoryStore,
+};
+use mail_auth::{
+    ArcOutput, AuthenticatedMessage, AuthenticationResults, common::headers::HeaderWriter,
+};
+use mail_builder::MessageBuilder;
+use mail_parser::MessageParser;
+use store::{
+    Serialize,
+    dispatch::lookup::KeyValue,
+    write::{BatchBuilder, Bincode, BlobOp, now},
+};
+use trc::{AddContext, SmtpEvent};
+use utils::BlobHash;
+
+use crate::{
+    core::Session,
+    queue::{MessageSource, RecipientDomain, spool::SmtpSpool},
+    reporting::SmtpReporting,
+};
+
+use super::{ArcSeal, DkimSign};
+
+/// Held posts are discarded after a week without a moderator decision.
+pub const MODERATION_EXPIRY: u64 = 7 * 86400;
+const MAX_HELD_POSTS: usize = 1000;
+const LOCK_EXPIRY: u64 = 10;
+const LOCK_RETRIES: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct ListRecipient {
+    pub address_lcase: String,
+    pub list: MailingList,
+    pub action: ListAction,
+}
+
+#[derive(Debug, Clone)]
+pub enum ListAction {
+    Post { members: Vec<String> },
+    Command(ListCommand),
+}
+
+/// Authentication results of a post received over SMTP, used to add an ARC set
+/// once the list headers have been added.
+pub struct ListPostAuth<'x> {
+    pub results: &'x AuthenticationResults<'x>,
+    pub arc_output: &'x ArcOutput<'x>,
+    /// Location of the ARC set added on reception, which is replaced by the
+    /// set sealed by the list.
+    pub arc_set: Option<Range<usize>>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HeldPost {
+    pub id: u64,
+    pub return_path: String,
+    pub subject: String,
+    pub size: usize,
+    pub received: u64,
+    pub expires: u64,
+    pub blob_hash: BlobHash,
+}
+
+pub trait MailingListManager: Sync + Send {
+    fn distribute_list_post(
+        &self,
+        list: &MailingList,
+        members: Vec<String>,
+        return_path: &str,
+        message: &[u8],
+        auth: Option<&ListPostAuth<'_>>,
+        session_id: u64,
+    ) -> impl Future<Output = bool> + Send;
+
+    fn hold_list_post(
+        &self,
+        list: &MailingList,
+        return_path: &str,
+        subject: &str,
+        message: &[u8],
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn held_list_posts(
+        &self,
+        list_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<HeldPost>>> + Send;
+
+    fn release_list_post(
+        &self,
+        list: &MailingList,
+        post_id: u64,
+        approve: bool,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn handle_list_command(
+        &self,
+        list: &MailingList,
+        command: ListCommand,
+        address: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl MailingListManager for Server {
+    async fn distribute_list_post(
+        &self,
+        list: &MailingList,
+        members: Vec<String>,
+        return_path: &str,
+        message: &[u8],
+        auth: Option<&ListPostAuth<'_>>,
+        session_id: u64,
+    ) -> bool {
+        let return_path_lcase = return_path.to_lowercase();
+        let return_path_domain = return_path_lcase
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_string())
+            .unwrap_or_default();
+        let mut queue_message = self.new_message(
+            return_path,
+            return_path_lcase,
+            return_path_domain,
+            session_id,
+        );
+        for member in members.iter().chain(list.archive.iter()) {
+            let member = member.to_lowercase();
+            if member != list.address
+                && !queue_message
+                    .recipients
+                    .iter()
+                    .any(|rcpt| rcpt.address_lcase == member)
+            {
+                queue_message.add_recipient(member, self).await;
+            }
+        }
+
+        trc::event!(
+            Smtp(SmtpEvent::ListPost),
+            SpanId = session_id,
+            To = list.address.clone(),
+            From = return_path.to_string(),
+            Total = queue_message.recipients.len(),
+        );
+
+        if queue_message.recipients.is_empty() {
+            return true;
+        }
+
+        // Add RFC 2369 and RFC 2919 headers
+        let mut headers = list_headers(list).into_bytes();
+
+        // Re-seal and re-sign the modified message
+        let message = resign_list_post(self, list, &mut headers, message, auth, session_id).await;
+
+        queue_message
+            .queue(
+                Some(&headers),
+                &message,
+                session_id,
+                self,
+                MessageSource::Autogenerated,
+            )
+            .await
+    }
+
+    async fn hold_list_post(
+        &self,
+        list: &MailingList,
+        return_path: &str,
+        subject: &str,
+        message: &[u8],
+        session_id: u64,
+    ) -> trc::Result<()> {
+        // Store the message until a moderator reviews it
+        let received = now();
+        let expires = received + MODERATION_EXPIRY;
+        let blob_hash = BlobHash::from(message);
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            BlobOp::Reserve {
+                hash: blob_hash.clone(),
+                until: expires,
+            },
+            0u32.serialize(),
+        );
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+        self.blob_store()
+            .put_blob(blob_hash.as_slice(), message)
+            .await
+            .caused_by(trc::location!())?;
+
+        let post = HeldPost {
+            id: self.inner.data.queue_id_gen.generate().unwrap_or(received),
+            return_path: return_path.to_string(),
+            subject: subject.to_string(),
+            size: message.len(),
+            received,
+            expires,
+            blob_hash,
+        };
+        let post_id = post.id;
+        update_held_posts(self, list.id, |posts| {
+            if posts.len() < MAX_HELD_POSTS {
+                posts.push(post);
+                Ok(())
+            } else {
+                Err(trc::LimitEvent::Quota
+                    .into_err()
+                    .details("Too many posts held for moderation"))
+            }
+        })
+        .await?;
+
+        trc::event!(
+            Smtp(SmtpEvent::ListPostHeld),
+            SpanId = session_id,
+            To = list.address.clone(),
+            From = return_path.to_string(),
+            Id = post_id,
+        );
+
+        Ok(())
+    }
+
+    async fn held_list_posts(&self, list_id: u32) -> trc::Result<Vec<HeldPost>> {
+        let now = now();
+        self.in_memory_store()
+            .key_get::<Bincode<Vec<HeldPost>>>(KeyValue::<()>::build_key(
+                KV_LIST_MODERATION,
+                list_id.to_be_bytes(),
+            ))
+            .await
+            .map(|posts| {
+                posts
+                    .map(|posts| posts.inner)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|post| post.expires > now)
+                    .collect()
+            })
+    }
+
+    async fn release_list_post(
+        &self,
+        list: &MailingList,
+        post_id: u64,
+        approve: bool,
+    ) -> trc::Result<bool> {
+        let mut held_post = None;
+        update_held_posts(self, list.id, |posts| {
+            if let Some(idx) = posts.iter().position(|post| post.id == post_id) {
+                held_post = Some(posts.swap_remove(idx));
+            }
+            Ok(())
+        })
+        .await?;
+        let Some(post) = held_post else {
+            return Ok(false);
+        };
+
+        if approve {
+            let message = self
+                .blob_store()
+                .get_blob(post.blob_hash.as_slice(), 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| {
+                    trc::StoreEvent::NotFound
+                        .into_err()
+                        .details("Held post blob not found")
+                        .caused_by(trc::location!())
+                })?;
+            let members = self
+                .store()
+                .expn_by_id(list.id)
+                .await
+                .caused_by(trc::location!())?;
+
+            if !self
+                .distribute_list_post(list, members, &post.return_path, &message, None, 0)
+                .await
+            {
+                return Err(trc::StoreEvent::UnexpectedError
+                    .into_err()
+                    .details("Failed to queue held post")
+                    .caused_by(trc::location!()));
+            }
+        } else {
+            trc::event!(
+                Smtp(SmtpEvent::ListPostRejected),
+                To = list.address.clone(),
+                From = post.return_path,
+                Id = post_id,
+            );
+        }
+
+        // Release the blob reservation
+        let mut batch = BatchBuilder::new();
+        batch.clear(BlobOp::Reserve {
+            hash: post.blob_hash,
+            until: post.expires,
+        });
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(true)
+    }
+
+    async fn handle_list_command(
+        &self,
+        list: &MailingList,
+        command: ListCommand,
+        address: &str,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        let (changed, subject) = match command {
+            ListCommand::Subscribe => (
+                self.store()
+                    .mailing_list_subscribe(list, address)
+                    .await
+                    .caused_by(trc::location!())?,
+                "subscribed to",
+            ),
+            ListCommand::Unsubscribe => (
+                self.store()
+                    .mailing_list_unsubscribe(list, address)
+                    .await
+                    .caused_by(trc::location!())?,
+                "unsubscribed from",
+            ),
+        };
+
+        trc::event!(
+            Smtp(match command {
+                ListCommand::Subscribe => SmtpEvent::ListSubscribe,
+                ListCommand::Unsubscribe => SmtpEvent::ListUnsubscribe,
+            }),
+            SpanId = session_id,
+            To = list.address.clone(),
+            From = address.to_string(),
+            Result = changed,
+        );
+
+        // Confirm the request to the sender
+        if changed {
+            let message = MessageBuilder::new()
+                .from(list.address.as_str())
+                .to(address)
+                .header(
+                    "Auto-Submitted",
+                    mail_builder::headers::HeaderType::Text("auto-replied".into()),
+                )
+                .subject(format!("You have been {subject} {}", list.address))
+                .text_body(format!(
+                    "Your address {address} has been {subject} the mailing list {}.\r\n",
+                    list.address
+                ))
+                .write_to_vec()
+                .unwrap_or_default();
+            self.send_autogenerated(
+                "",
+                [address].into_iter(),
+                message,
+                Some(&self.core.smtp.queue.dsn.sign),
+                session_id,
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+}
+
+async fn update_held_posts(
+    server: &Server,
+    list_id: u32,
+    f: impl FnOnce(&mut Vec<HeldPost>) -> trc::Result<()> + Send,
+) -> trc::Result<()> {
+    let list_key = list_id.to_be_bytes();
+    let store = server.in_memory_store();
+    let mut is_locked = false;
+    for _ in 0..LOCK_RETRIES {
+        if store
+            .try_lock(KV_LOCK_LIST_MODERATION, &list_key, LOCK_EXPIRY)
+            .await?
+        {
+            is_locked = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    if !is_locked {
+        return Err(trc::StoreEvent::UnexpectedError
+            .into_err()
+            .details("Failed to lock moderation queue")
+            .caused_by(trc::location!()));
+    }
+
+    let result = match server.held_list_posts(list_id).await {
+        Ok(mut posts) => match f(&mut posts) {
+            Ok(_) if !posts.is_empty() => store
+                .key_set(
+                    KeyValue::with_prefix(
+                        KV_LIST_MODERATION,
+                        list_key,
+                        Bincode::new(posts).serialize(),
+                    )
+                    .expires(MODERATION_EXPIRY),
+                )
+                .await
+                .caused_by(trc::location!()),
+            Ok(_) => store
+                .key_delete(KeyValue::<()>::build_key(KV_LIST_MODERATION, list_key))
+                .await
+                .caused_by(trc::location!()),
+            Err(err) => Err(err),
+        },
+        Err(err) => Err(err),
+    };
+
+    store
+        .remove_lock(KV_LOCK_LIST_MODERATION, &list_key)
+        .await?;
+
+    result
+}
+
+async fn resign_list_post<'x>(
+    server: &Server,
+    list: &MailingList,
+    headers: &mut Vec<u8>,
+    message: &'x [u8],
+    auth: Option<&ListPostAuth<'_>>,
+    session_id: u64,
+) -> Cow<'x, [u8]> {
+    let domain = list
+        .address
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .unwrap_or_default();
+    let policy = &server.core.smtp.mail_auth.resign.list;
+    let mut message = Cow::Borrowed(message);
+
+    // ARC seal
+    if let Some(auth) = auth.filter(|auth| auth.arc_output.can_be_sealed())
+        && let Some(sealer) = server
+            .eval_if::<String, _>(&policy.arc, &RecipientDomain::new(domain), session_id)
+            .await
+            .and_then(|name| server.get_arc_sealer(&name, session_id))
+    {
+        // Replace the set added on reception, which used the same instance number
+        if let Some(arc_set) = auth.arc_set.clone() {
+            message.to_mut().drain(arc_set);
+        }
+
+        let mut sealed_message = Vec::with_capacity(headers.len() + message.len());
+        sealed_message.extend_from_slice(headers);
+        sealed_message.extend_from_slice(&message);
+        match AuthenticatedMessage::parse(&sealed_message)
+            .ok_or(mail_auth::Error::ParseError)
+            .and_then(|auth_message| {
+                sealer
+                    .seal(&auth_message, auth.results, auth.arc_output)
+                    .map(|set| set.to_header())
+            }) {
+            Ok(set) => {
+                headers.splice(0..0, set.into_bytes());
+            }
+            Err(err) => {
+                trc::error!(
+                    trc::Error::from(err)
+                        .span_id(session_id)
+                        .details("Failed to ARC seal list post")
+                );
+            }
+        }
+    }
+
+    // DKIM sign
+    for signer in server
+        .eval_if::<Vec<String>, _>(&policy.dkim, &RecipientDomain::new(domain), session_id)
+        .await
+        .unwrap_or_default()
+    {
+        if let Some(signer) = server.get_dkim_signer(&signer, session_id) {
+            match signer.sign_chained(&[headers.as_ref(), message.as_ref()]) {
+                Ok(signature) => {
+                    let mut signature_header = Vec::with_capacity(256);
+                    signature.write_header(&mut signature_header);
+                    headers.splice(0..0, signature_header);
+                }
+                Err(err) => {
+                    trc::error!(
+                        trc::Error::from(err)
+                            .span_id(session_id)
+                            .details("Failed to DKIM sign list post")
+                    );
+                }
+            }
+        }
+    }
+
+    message
+}
+
+fn list_headers(list: &MailingList) -> String {
+    let mut headers = String::with_capacity(256);
+    headers.push_str("List-Id: ");
+    if let Some(description) = list.description.as_deref().filter(|d| !d.is_empty()) {
+        let _ = write!(
+            headers,
+            "\"{}\" ",
+            description.replace(['"', '\r', '\n'], "")
+        );
+    }
+    let _ = write!(
+        headers,
+        "<{}>\r\nList-Post: <mailto:{}>\r\nList-Unsubscribe: <mailto:{}>\r\n",
+        list.list_id(),
+        list.address,
+        list.command_address("unsubscribe"),
+    );
+    if list.open_subscription {
+        let _ = write!(
+            headers,
+            "List-Subscribe: <mailto:{}>\r\n",
+            list.command_address("subscribe")
+        );
+    }
+    headers.push_str("Precedence: list\r\n");
+    headers
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Distributes, holds or executes the commands addressed to managed
+    /// lists. Returns `false` if the message could not be processed.
+    pub(crate) async fn deliver_list_rcpts(
+        &self,
+        list_rcpts: Vec<ListRecipient>,
+        headers: &[u8],
+        raw_message: &[u8],
+        auth: Option<ListPostAuth<'_>>,
+        is_verified: bool,
+    ) -> bool {
+        let mail_from = self.data.mail_from.as_ref().unwrap();
+        let mut message = Vec::with_capacity(headers.len() + raw_message.len());
+        message.extend_from_slice(headers);
+        message.extend_from_slice(raw_message);
+
+        for list_rcpt in list_rcpts {
+            let list = &list_rcpt.list;
+            match list_rcpt.action {
+                ListAction::Post { members } => {
+                    if list.post_policy != ListPostPolicy::Moderated
+                        || list.is_moderator(&mail_from.address_lcase)
+                    {
+                        if !self
+                            .server
+                            .distribute_list_post(
+                                list,
+                                members,
+                                &mail_from.address,
+                                &message,
+                                auth.as_ref(),
+                                self.data.session_id,
+                            )
+                            .await
+                        {
+                            return false;
+                        }
+                    } else {
+                        let subject = MessageParser::new()
+                            .parse_headers(raw_message)
+                            .and_then(|message| message.subject().map(|s| s.to_string()))
+                            .unwrap_or_default();
+                        if let Err(err) = self
+                            .server
+                            .hold_list_post(
+                                list,
+                                &mail_from.address,
+                                &subject,
+                                &message,
+                                self.data.session_id,
+                            )
+                            .await
+                        {
+                            trc::error!(
+                                err.span_id(self.data.session_id)
+                                    .caused_by(trc::location!())
+                                    .details("Failed to hold list post.")
+                            );
+                            return false;
+                        }
+                    }
+                }
+                ListAction::Command(command) => {
+                    // Commands are only accepted from verified senders
+                    if mail_from.address_lcase.is_empty() || !is_verified {
+                        trc::event!(
+                            Smtp(match command {
+                                ListCommand::Subscribe => SmtpEvent::ListSubscribe,
+                                ListCommand::Unsubscribe => SmtpEvent::ListUnsubscribe,
+                            }),
+                            SpanId = self.data.session_id,
+                            To = list.address.clone(),
+                            From = mail_from.address_lcase.clone(),
+                            Result = false,
+                            Reason = "Sender could not be verified",
+                        );
+                    } else if let Err(err) = self
+                        .server
+                        .handle_list_command(
+                            list,
+                            command,
+                            &mail_from.address_lcase,
+                            self.data.session_id,
+                        )
+                        .await
+                    {
+                        trc::error!(
+                            err.span_id(self.data.session_id)
+                                .caused_by(trc::location!())
+                                .details("Failed to process list command.")
+                        );
+                    }
+                }
+            }
+        }
+
+        true
+    }
+}
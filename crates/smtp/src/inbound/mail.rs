@@ -8,13 +8,16 @@ use std::time::{Duration, Instant, SystemTime};
 
 use common::{config::smtp::session::Stage, listener::SessionStream, scripts::ScriptModification};
 use mail_auth::{IprevOutput, IprevResult, SpfOutput, SpfResult, spf::verify::SpfParameters};
-use smtp_proto::{MAIL_BY_NOTIFY, MAIL_BY_RETURN, MAIL_REQUIRETLS, MailFrom, MtPriority};
+use smtp_proto::{
+    MAIL_BY_NOTIFY, MAIL_BY_RETURN, MAIL_REQUIRETLS, MAIL_SMTPUTF8, MailFrom, MtPriority,
+};
 use trc::SmtpEvent;
 use utils::config::Rate;
 
 use crate::{
     core::{Session, SessionAddress},
-    queue::DomainPart,
+    inbound::dns_cache::AuthDnsCache,
+    queue::{DomainPart, admission::AdmissionControl},
     scripts::ScriptResult,
 };
 
@@ -107,6 +110,18 @@ impl<T: SessionStream> Session<T> {
             return self.write(message).await;
         }
 
+        // Internationalized addresses require SMTPUTF8 (RFC 6531)
+        if !from.address.is_ascii() && (from.flags & MAIL_SMTPUTF8) == 0 {
+            trc::event!(
+                Smtp(SmtpEvent::SmtpUtf8Required),
+                SpanId = self.data.session_id,
+                From = from.address,
+            );
+            return self
+                .write(b"553 5.6.7 Internationalized addresses require SMTPUTF8.\r\n")
+                .await;
+        }
+
         let (address, address_lcase, domain) = if !from.address.is_empty() {
             let address_lcase = from.address.to_lowercase();
             let domain = address_lcase.domain_part().to_string();
@@ -439,11 +454,33 @@ impl<T: SessionStream> Session<T> {
                 .await;
         }
 
+        // Tempfail new messages while the queue is unable to spool them
+        if !self.server.is_queue_admitting(self.data.session_id).await {
+            trc::event!(
+                Queue(trc::QueueEvent::AdmissionRejected),
+                SpanId = self.data.session_id,
+                From = self.data.mail_from.as_ref().unwrap().address_lcase.clone(),
+            );
+
+            self.data.mail_from = None;
+            return self
+                .write(b"451 4.3.2 System not accepting messages, try again later.\r\n")
+                .await;
+        }
+
         if self.is_allowed().await {
             // Verify SPF
             if self.params.spf_mail_from.verify() {
-                let time = Instant::now();
                 let mail_from = self.data.mail_from.as_ref().unwrap();
+                self.server
+                    .prefetch_spf_record(if !mail_from.address.is_empty() {
+                        &mail_from.domain
+                    } else {
+                        &self.data.helo_domain
+                    })
+                    .await;
+
+                let time = Instant::now();
                 let spf_output = if !mail_from.address.is_empty() {
                     self.server
                         .core
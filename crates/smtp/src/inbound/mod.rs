@@ -12,16 +12,22 @@ use mail_auth::{
     AuthenticationResults, DkimResult, DmarcResult, IprevResult, SpfResult,
 };
 
+pub mod archive;
 pub mod auth;
 pub mod data;
+pub mod dns_cache;
 pub mod ehlo;
 pub mod hooks;
+pub mod journal;
+pub mod list;
 pub mod mail;
 pub mod milter;
 pub mod rcpt;
+pub mod reputation;
 pub mod session;
 pub mod spam;
 pub mod spawn;
+pub mod spool;
 pub mod vrfy;
 
 #[derive(Debug, Default)]
@@ -7,15 +7,25 @@
 use common::{
     config::smtp::session::Stage, listener::SessionStream, scripts::ScriptModification, KV_GREYLIST,
 };
-use directory::backend::RcptType;
+use directory::backend::{
+    internal::list::{parse_list_command, ListCommand, ListPostPolicy},
+    RcptType,
+};
+use email::forward::srs_reverse;
+use mail_auth::SpfResult;
 use smtp_proto::{
-    RcptTo, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
+    RcptTo, MAIL_SMTPUTF8, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER,
+    RCPT_NOTIFY_SUCCESS,
 };
-use store::dispatch::lookup::KeyValue;
+use store::{dispatch::lookup::KeyValue, write::now};
 use trc::{SecurityEvent, SmtpEvent};
 
 use crate::{
     core::{Session, SessionAddress},
+    inbound::{
+        list::{ListAction, ListRecipient},
+        reputation::ReputationEvent,
+    },
     queue::DomainPart,
     scripts::ScriptResult,
 };
@@ -66,6 +76,24 @@ impl<T: SessionStream> Session<T> {
                 .await;
         }
 
+        // Internationalized addresses require SMTPUTF8 (RFC 6531)
+        if !to.address.is_ascii()
+            && self
+                .data
+                .mail_from
+                .as_ref()
+                .is_some_and(|from| (from.flags & MAIL_SMTPUTF8) == 0)
+        {
+            trc::event!(
+                Smtp(SmtpEvent::SmtpUtf8Required),
+                SpanId = self.data.session_id,
+                To = to.address,
+            );
+            return self
+                .write(b"553 5.6.7 Internationalized addresses require SMTPUTF8.\r\n")
+                .await;
+        }
+
         // Build RCPT
         let address_lcase = to.address.to_lowercase();
         let rcpt = SessionAddress {
@@ -174,6 +202,10 @@ impl<T: SessionStream> Session<T> {
                 );
 
                 if new_address.contains('@') {
+                    // Keep the address used by the client for DSNs
+                    if rcpt.dsn_info.is_none() {
+                        rcpt.dsn_info = Some(std::mem::take(&mut rcpt.address));
+                    }
                     rcpt.address_lcase = new_address.to_lowercase();
                     rcpt.domain = rcpt.address_lcase.domain_part().to_string();
                     rcpt.address = new_address;
@@ -194,10 +226,32 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Bounces sent to SRS addresses are relayed to the original sender
+        let mut is_srs = false;
+        if let Some(srs) = &self.server.core.smtp.mail_auth.srs {
+            let rcpt = self.data.rcpt_to.last_mut().unwrap();
+            if let Some(address) = srs_reverse(srs, &rcpt.address_lcase, now()) {
+                trc::event!(
+                    Smtp(SmtpEvent::RcptToRewritten),
+                    SpanId = self.data.session_id,
+                    Details = rcpt.address_lcase.clone(),
+                    To = address.clone(),
+                );
+
+                rcpt.address_lcase = address.to_lowercase();
+                rcpt.domain = rcpt.address_lcase.domain_part().to_string();
+                rcpt.address = address;
+                is_srs = true;
+            }
+        }
+
         // Verify address
         let rcpt = self.data.rcpt_to.last().unwrap();
         let mut rcpt_members = None;
-        if let Some(directory) = self
+        let mut list_rcpt = None;
+        if is_srs {
+            // The address has been verified by its hash
+        } else if let Some(directory) = self
             .server
             .eval_if::<String, _>(
                 &self.server.core.smtp.session.rcpt.directory,
@@ -216,19 +270,78 @@ impl<T: SessionStream> Session<T> {
                     {
                         Ok(RcptType::Mailbox) => {}
                         Ok(RcptType::List(members)) => {
-                            rcpt_members = Some(members);
+                            match directory.mailing_list(&rcpt.address_lcase).await {
+                                Ok(Some(list)) => {
+                                    // Enforce the posting policy
+                                    let sender =
+                                        &self.data.mail_from.as_ref().unwrap().address_lcase;
+                                    if list.post_policy == ListPostPolicy::Members
+                                        && !list.is_moderator(sender)
+                                        && !members.iter().any(|member| member == sender)
+                                    {
+                                        trc::event!(
+                                            Smtp(SmtpEvent::ListPostRejected),
+                                            SpanId = self.data.session_id,
+                                            To = rcpt.address_lcase.clone(),
+                                            From = sender.clone(),
+                                        );
+
+                                        self.data.rcpt_to.pop();
+                                        return self
+                                            .write(
+                                                b"550 5.7.1 Only members may post to this list.\r\n",
+                                            )
+                                            .await;
+                                    }
+
+                                    list_rcpt = Some(ListRecipient {
+                                        address_lcase: rcpt.address_lcase.clone(),
+                                        list,
+                                        action: ListAction::Post { members },
+                                    });
+                                }
+                                Ok(None) => {
+                                    rcpt_members = Some(members);
+                                }
+                                Err(err) => {
+                                    return self.rcpt_verify_error(err).await;
+                                }
+                            }
                         }
                         Ok(RcptType::Invalid) => {
-                            trc::event!(
-                                Smtp(SmtpEvent::MailboxDoesNotExist),
-                                SpanId = self.data.session_id,
-                                To = rcpt.address_lcase.clone(),
-                            );
+                            // List commands such as list-unsubscribe@domain
+                            if let Some((list_addr, command)) =
+                                parse_list_command(&rcpt.address_lcase)
+                            {
+                                match directory.mailing_list(&list_addr).await {
+                                    Ok(Some(list))
+                                        if command == ListCommand::Unsubscribe
+                                            || list.open_subscription =>
+                                    {
+                                        list_rcpt = Some(ListRecipient {
+                                            address_lcase: rcpt.address_lcase.clone(),
+                                            list,
+                                            action: ListAction::Command(command),
+                                        });
+                                    }
+                                    Ok(_) => {}
+                                    Err(err) => {
+                                        return self.rcpt_verify_error(err).await;
+                                    }
+                                }
+                            }
 
-                            let rcpt_to = self.data.rcpt_to.pop().unwrap().address_lcase;
-                            return self
-                                .rcpt_error(b"550 5.1.2 Mailbox does not exist.\r\n", rcpt_to)
-                                .await;
+                            if list_rcpt.is_none() {
+                                trc::event!(
+                                    Smtp(SmtpEvent::MailboxDoesNotExist),
+                                    SpanId = self.data.session_id,
+                                    To = rcpt.address_lcase.clone(),
+                                );
+
+                                self.update_reputation(ReputationEvent::InvalidRcpt).await;
+                                let rcpt_to = self.data.rcpt_to.pop().unwrap().address_lcase;
+                                return self.rcpt_unknown(rcpt_to).await;
+                            }
                         }
                         Err(err) => {
                             trc::error!(err
@@ -386,6 +499,11 @@ impl<T: SessionStream> Session<T> {
                 .await;
         }
 
+        // Mailing lists are distributed once the message is received
+        if let Some(list_rcpt) = list_rcpt {
+            self.data.list_rcpts.push(list_rcpt);
+        }
+
         // Expand list
         if let Some(members) = rcpt_members {
             let list_addr = self.data.rcpt_to.pop().unwrap();
@@ -406,6 +524,85 @@ impl<T: SessionStream> Session<T> {
         self.write(b"250 2.1.5 OK\r\n").await
     }
 
+    async fn rcpt_verify_error(&mut self, err: trc::Error) -> Result<(), ()> {
+        trc::error!(err
+            .span_id(self.data.session_id)
+            .caused_by(trc::location!())
+            .details("Failed to verify address."));
+
+        self.data.rcpt_to.pop();
+        self.write(b"451 4.4.3 Unable to verify address at this time.\r\n")
+            .await
+    }
+
+    async fn rcpt_unknown(&mut self, rcpt: String) -> Result<(), ()> {
+        if !self.params.rcpt_harvest || self.is_verified_sender() {
+            return self
+                .rcpt_error(b"550 5.1.2 Mailbox does not exist.\r\n", rcpt)
+                .await;
+        }
+
+        // Tarpit clients probing for valid addresses, doubling the delay on every attempt
+        self.data.rcpt_unknown += 1;
+        if self.data.rcpt_unknown < self.params.rcpt_harvest_disconnect {
+            let delay = self
+                .params
+                .rcpt_harvest_delay
+                .saturating_mul(1 << (self.data.rcpt_unknown - 1).min(16))
+                .min(self.params.rcpt_harvest_max_delay);
+            tokio::time::sleep(delay).await;
+
+            return self
+                .rcpt_error(b"550 5.1.2 Mailbox does not exist.\r\n", rcpt)
+                .await;
+        }
+
+        trc::event!(
+            Smtp(SmtpEvent::HarvestAttempt),
+            SpanId = self.data.session_id,
+            RemoteIp = self.data.remote_ip,
+            Limit = self.params.rcpt_harvest_disconnect,
+            To = rcpt.clone(),
+        );
+
+        if self.params.rcpt_harvest_block {
+            match self
+                .server
+                .is_harvest_fail2banned(self.data.remote_ip)
+                .await
+            {
+                Ok(true) => {
+                    trc::event!(
+                        Security(SecurityEvent::AbuseBan),
+                        SpanId = self.data.session_id,
+                        RemoteIp = self.data.remote_ip,
+                        To = rcpt,
+                    );
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    trc::error!(err
+                        .span_id(self.data.session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to block IP."));
+                }
+            }
+        }
+
+        self.write(b"421 4.7.0 Too many unknown recipients, disconnecting.\r\n")
+            .await?;
+        Err(())
+    }
+
+    fn is_verified_sender(&self) -> bool {
+        self.is_authenticated()
+            || self
+                .data
+                .spf_mail_from
+                .as_ref()
+                .is_some_and(|spf| spf.result() == SpfResult::Pass)
+    }
+
     async fn rcpt_error(&mut self, response: &[u8], rcpt: String) -> Result<(), ()> {
         tokio::time::sleep(self.params.rcpt_errors_wait).await;
         self.data.rcpt_errors += 1;
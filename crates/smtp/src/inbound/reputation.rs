@@ -0,0 +1,265 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{listener::SessionStream, Server, KV_REPUTATION_CONNECTION};
+use store::{dispatch::lookup::KeyValue, write::now, Deserialize, Serialize};
+use trc::AddContext;
+
+use crate::core::Session;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReputationType {
+    Ip,
+    Asn,
+    Helo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReputationEvent {
+    Spam,
+    Ham,
+    AuthFailure,
+    InvalidRcpt,
+    ProtocolViolation,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reputation {
+    pub score: f64,
+    pub updated: u64,
+    pub spam: u32,
+    pub ham: u32,
+    pub auth_failures: u32,
+    pub invalid_rcpts: u32,
+    pub protocol_violations: u32,
+}
+
+pub trait ConnectionReputation: Sync + Send {
+    fn reputation(
+        &self,
+        rep_type: ReputationType,
+        key: &str,
+    ) -> impl Future<Output = trc::Result<Option<Reputation>>> + Send;
+
+    fn update_reputation(
+        &self,
+        rep_type: ReputationType,
+        key: &str,
+        event: ReputationEvent,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn reset_reputation(
+        &self,
+        rep_type: ReputationType,
+        key: &str,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl ConnectionReputation for Server {
+    async fn reputation(
+        &self,
+        rep_type: ReputationType,
+        key: &str,
+    ) -> trc::Result<Option<Reputation>> {
+        let reputation = self
+            .in_memory_store()
+            .key_get::<Reputation>(KeyValue::<()>::build_key(
+                KV_REPUTATION_CONNECTION,
+                rep_type.key(key),
+            ))
+            .await
+            .caused_by(trc::location!())?;
+
+        // Decay the score to the current time
+        Ok(reputation.map(|mut reputation| {
+            if let Some(config) = &self.core.smtp.session.reputation {
+                reputation.decay(config.half_life);
+            }
+            reputation
+        }))
+    }
+
+    async fn update_reputation(
+        &self,
+        rep_type: ReputationType,
+        key: &str,
+        event: ReputationEvent,
+    ) -> trc::Result<()> {
+        let Some(config) = &self.core.smtp.session.reputation else {
+            return Ok(());
+        };
+        let mut reputation = self.reputation(rep_type, key).await?.unwrap_or_default();
+        reputation.score += match event {
+            ReputationEvent::Spam => {
+                reputation.spam += 1;
+                config.penalty_spam
+            }
+            ReputationEvent::Ham => {
+                reputation.ham += 1;
+                config.penalty_ham
+            }
+            ReputationEvent::AuthFailure => {
+                reputation.auth_failures += 1;
+                config.penalty_auth_failure
+            }
+            ReputationEvent::InvalidRcpt => {
+                reputation.invalid_rcpts += 1;
+                config.penalty_invalid_rcpt
+            }
+            ReputationEvent::ProtocolViolation => {
+                reputation.protocol_violations += 1;
+                config.penalty_protocol_violation
+            }
+        };
+        reputation.updated = now();
+
+        self.in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_REPUTATION_CONNECTION,
+                    rep_type.key(key),
+                    reputation.serialize(),
+                )
+                .expires(config.expiry),
+            )
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn reset_reputation(&self, rep_type: ReputationType, key: &str) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_delete(KeyValue::<()>::build_key(
+                KV_REPUTATION_CONNECTION,
+                rep_type.key(key),
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Obtains the worst reputation score of the remote IP, ASN and HELO domain.
+    pub async fn load_reputation(&mut self) {
+        if self.server.core.smtp.session.reputation.is_none() {
+            return;
+        }
+
+        let mut score = None;
+        for (rep_type, key) in self.reputation_keys() {
+            match self.server.reputation(rep_type, &key).await {
+                Ok(Some(reputation)) => {
+                    score = Some(score.map_or(reputation.score, |s: f64| s.max(reputation.score)));
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    trc::error!(err.span_id(self.data.session_id));
+                }
+            }
+        }
+        self.data.reputation = score.unwrap_or_default();
+    }
+
+    pub async fn update_reputation(&self, event: ReputationEvent) {
+        if self.server.core.smtp.session.reputation.is_none() || self.is_authenticated() {
+            return;
+        }
+
+        for (rep_type, key) in self.reputation_keys() {
+            if let Err(err) = self.server.update_reputation(rep_type, &key, event).await {
+                trc::error!(err.span_id(self.data.session_id));
+            }
+        }
+    }
+
+    fn reputation_keys(&self) -> Vec<(ReputationType, String)> {
+        let mut keys = Vec::with_capacity(3);
+        keys.push((ReputationType::Ip, self.data.remote_ip_str.clone()));
+        if let Some(asn) = &self.data.asn_geo_data.asn {
+            keys.push((ReputationType::Asn, asn.id.to_string()));
+        }
+        if !self.data.helo_domain.is_empty() {
+            keys.push((ReputationType::Helo, self.data.helo_domain.clone()));
+        }
+        keys
+    }
+}
+
+impl ReputationType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ip" => Some(ReputationType::Ip),
+            "asn" => Some(ReputationType::Asn),
+            "helo" => Some(ReputationType::Helo),
+            _ => None,
+        }
+    }
+
+    fn key(&self, key: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(key.len() + 1);
+        bytes.push(match self {
+            ReputationType::Ip => 0,
+            ReputationType::Asn => 1,
+            ReputationType::Helo => 2,
+        });
+        bytes.extend_from_slice(key.to_lowercase().as_bytes());
+        bytes
+    }
+}
+
+impl Reputation {
+    fn decay(&mut self, half_life: u64) {
+        let elapsed = now().saturating_sub(self.updated);
+        if elapsed > 0 {
+            self.score *= 0.5f64.powf(elapsed as f64 / half_life as f64);
+        }
+    }
+}
+
+impl Serialize for &Reputation {
+    fn serialize(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(36);
+        buf.extend_from_slice(&self.score.to_be_bytes());
+        buf.extend_from_slice(&self.updated.to_be_bytes());
+        buf.extend_from_slice(&self.spam.to_be_bytes());
+        buf.extend_from_slice(&self.ham.to_be_bytes());
+        buf.extend_from_slice(&self.auth_failures.to_be_bytes());
+        buf.extend_from_slice(&self.invalid_rcpts.to_be_bytes());
+        buf.extend_from_slice(&self.protocol_violations.to_be_bytes());
+        buf
+    }
+}
+
+impl Deserialize for Reputation {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        let u32_at = |pos: usize| u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap());
+
+        // Entries written before protocol violations were tracked are 32 bytes long
+        if bytes.len() == 32 || bytes.len() == 36 {
+            Ok(Reputation {
+                score: f64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+                updated: u64::from_be_bytes(bytes[8..16].try_into().unwrap()),
+                spam: u32_at(16),
+                ham: u32_at(20),
+                auth_failures: u32_at(24),
+                invalid_rcpts: u32_at(28),
+                protocol_violations: if bytes.len() == 36 { u32_at(32) } else { 0 },
+            })
+        } else {
+            Err(trc::StoreEvent::DataCorruption
+                .caused_by(trc::location!())
+                .ctx(trc::Key::Value, bytes))
+        }
+    }
+}
+
+impl From<store::Value<'_>> for Reputation {
+    fn from(_: store::Value<'_>) -> Self {
+        unimplemented!()
+    }
+}
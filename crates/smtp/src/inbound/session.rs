@@ -5,7 +5,7 @@
  */
 
 use common::{
-    config::{server::ServerProtocol, smtp::session::Mechanism},
+    config::server::ServerProtocol,
     expr::{self, functions::ResolveVariable, *},
     listener::SessionStream,
 };
@@ -21,7 +21,7 @@ use trc::{NetworkEvent, SecurityEvent, SmtpEvent};
 
 use crate::core::{Session, State};
 
-use super::auth::SaslToken;
+use super::{auth::SaslToken, reputation::ReputationEvent};
 
 impl<T: SessionStream> Session<T> {
     pub async fn ingest(&mut self, bytes: &[u8]) -> Result<bool, ()> {
@@ -31,7 +31,10 @@ impl<T: SessionStream> Session<T> {
         'outer: loop {
             match &mut state {
                 State::Request(receiver) => loop {
-                    match receiver.ingest(&mut iter, bytes) {
+                    let result = receiver.ingest(&mut iter, bytes);
+                    self.handle_pipelining(&result, !iter.as_slice().is_empty())
+                        .await?;
+                    match result {
                         Ok(request) => match request {
                             Request::Rcpt { to } => {
                                 self.handle_rcpt_to(to).await?;
@@ -64,13 +67,22 @@ impl<T: SessionStream> Session<T> {
                                 chunk_size,
                                 is_last,
                             } => {
-                                state = if chunk_size + self.data.message.len()
+                                state = if chunk_size + self.message_size()
                                     < self.params.max_message_size
                                 {
+                                    // Large chunks are spooled, avoid reserving all their bytes
+                                    let capacity = self
+                                        .server
+                                        .core
+                                        .smtp
+                                        .session
+                                        .data
+                                        .spool_chunk_size
+                                        .map_or(chunk_size, |size| chunk_size.min(size));
                                     if self.data.message.is_empty() {
-                                        self.data.message = Vec::with_capacity(chunk_size);
+                                        self.data.message = Vec::with_capacity(capacity);
                                     } else {
-                                        self.data.message.reserve(chunk_size);
+                                        self.data.message.reserve(capacity);
                                     }
                                     State::Bdat(BdatReceiver::new(chunk_size, is_last))
                                 } else {
@@ -83,16 +95,26 @@ impl<T: SessionStream> Session<T> {
                                 mechanism,
                                 initial_response,
                             } => {
-                                let auth: u64 = self
+                                if self
                                     .server
-                                    .eval_if::<Mechanism, _>(
-                                        &self.server.core.smtp.session.auth.mechanisms,
+                                    .eval_if(
+                                        &self.server.core.smtp.session.auth.alert,
                                         self,
                                         self.data.session_id,
                                     )
                                     .await
-                                    .unwrap_or_default()
-                                    .into();
+                                    .unwrap_or(false)
+                                {
+                                    trc::event!(
+                                        Smtp(SmtpEvent::AuthAttemptAlert),
+                                        SpanId = self.data.session_id,
+                                        ListenerId = self.instance.id.clone(),
+                                        LocalPort = self.data.local_port,
+                                        RemoteIp = self.data.remote_ip,
+                                    );
+                                }
+
+                                let auth = self.auth_mechanisms().await;
                                 if auth == 0 || self.params.auth_directory.is_none() {
                                     trc::event!(
                                         Smtp(SmtpEvent::AuthNotAllowed),
@@ -179,6 +201,7 @@ impl<T: SessionStream> Session<T> {
                             Request::Rset => {
                                 trc::event!(Smtp(SmtpEvent::Rset), SpanId = self.data.session_id,);
 
+                                self.discard_spool().await;
                                 self.reset();
                                 self.write(b"250 2.0.0 OK\r\n").await?;
                             }
@@ -336,7 +359,7 @@ impl<T: SessionStream> Session<T> {
                     }
                 },
                 State::Data(receiver) => {
-                    if self.data.message.len() + bytes.len() < self.params.max_message_size {
+                    if self.message_size() + bytes.len() < self.params.max_message_size {
                         if receiver.ingest(&mut iter, &mut self.data.message) {
                             let message = self.queue_message().await;
                             let num_responses = if self.instance.protocol == ServerProtocol::Smtp {
@@ -355,6 +378,7 @@ impl<T: SessionStream> Session<T> {
                                 return Err(());
                             }
                         } else {
+                            self.spool_message().await;
                             break 'outer;
                         }
                     } else {
@@ -382,13 +406,16 @@ impl<T: SessionStream> Session<T> {
                                     return Err(());
                                 }
                             } else {
+                                self.spool_message().await;
                                 self.write(b"250 2.6.0 Chunk accepted.\r\n").await?;
                             }
                         } else {
+                            self.discard_spool().await;
                             self.data.message = Vec::with_capacity(0);
                         }
                         state = State::default();
                     } else {
+                        self.spool_message().await;
                         break 'outer;
                     }
                 }
@@ -426,6 +453,7 @@ impl<T: SessionStream> Session<T> {
                             SpanId = self.data.session_id,
                         );
 
+                        self.discard_spool().await;
                         self.data.message = Vec::with_capacity(0);
                         self.write(b"552 5.3.4 Message too big for system.\r\n")
                             .await?;
@@ -451,9 +479,59 @@ impl<T: SessionStream> Session<T> {
             }
         }
         self.state = state;
+        self.flush_responses().await?;
 
         Ok(true)
     }
+
+    async fn handle_pipelining(
+        &mut self,
+        result: &Result<Request<String>, Error>,
+        has_more: bool,
+    ) -> Result<(), ()> {
+        // Responses to a pipelined command group are sent together once the group ends
+        self.data.batch_responses = has_more
+            && self.params.pipelining
+            && !matches!(result, Err(Error::NeedsMoreData { .. }));
+        if !has_more {
+            return Ok(());
+        }
+
+        // EHLO, DATA, VRFY, EXPN, NOOP, AUTH and STARTTLS may only appear last in a group
+        let command = match result {
+            _ if !self.params.pipelining => "PIPELINING not negotiated",
+            Ok(Request::Ehlo { .. } | Request::Helo { .. } | Request::Lhlo { .. }) => "EHLO",
+            Ok(Request::Data) => "DATA",
+            Ok(Request::Vrfy { .. }) => "VRFY",
+            Ok(Request::Expn { .. }) => "EXPN",
+            Ok(Request::Noop { .. }) => "NOOP",
+            Ok(Request::Auth { .. }) => "AUTH",
+            Ok(Request::StartTls) => "STARTTLS",
+            _ => return Ok(()),
+        };
+        self.data.batch_responses = false;
+        self.data.pipelining_violations += 1;
+
+        trc::event!(
+            Smtp(SmtpEvent::PipeliningViolation),
+            SpanId = self.data.session_id,
+            Details = command,
+            Strict = self.params.pipelining_strict,
+        );
+
+        if self.data.pipelining_violations == 1 {
+            self.update_reputation(ReputationEvent::ProtocolViolation)
+                .await;
+        }
+
+        if self.params.pipelining_strict {
+            self.write(b"554 5.5.0 Pipelining protocol violation.\r\n")
+                .await?;
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
@@ -461,15 +539,39 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
         self.data.mail_from = None;
         self.data.spf_mail_from = None;
         self.data.rcpt_to.clear();
+        self.data.list_rcpts.clear();
         self.data.message = Vec::with_capacity(0);
+        self.data.spool = Default::default();
         self.data.priority = 0;
         self.data.delivery_by = 0;
         self.data.future_release = 0;
         self.data.rcpt_oks = 0;
     }
 
-    #[inline(always)]
     pub async fn write(&mut self, bytes: &[u8]) -> Result<(), ()> {
+        if self.data.batch_responses {
+            self.data.pending_responses.extend_from_slice(bytes);
+            Ok(())
+        } else if !self.data.pending_responses.is_empty() {
+            let mut responses = std::mem::take(&mut self.data.pending_responses);
+            responses.extend_from_slice(bytes);
+            self.write_stream(&responses).await
+        } else {
+            self.write_stream(bytes).await
+        }
+    }
+
+    pub async fn flush_responses(&mut self) -> Result<(), ()> {
+        if !self.data.pending_responses.is_empty() {
+            let responses = std::mem::take(&mut self.data.pending_responses);
+            self.write_stream(&responses).await
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline(always)]
+    async fn write_stream(&mut self, bytes: &[u8]) -> Result<(), ()> {
         match self.stream.write_all(bytes).await {
             Ok(_) => match self.stream.flush().await {
                 Ok(_) => {
@@ -594,6 +696,7 @@ impl<T: SessionStream> ResolveVariable for Session<T> {
                 .map(|c| c.as_str())
                 .unwrap_or_default()
                 .into(),
+            V_REPUTATION => self.data.reputation.into(),
             _ => expr::Variable::default(),
         }
     }
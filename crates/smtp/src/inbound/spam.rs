@@ -5,6 +5,7 @@
  */
 
 use common::{config::spamfilter::SpamFilterAction, listener::SessionStream};
+use email::known_sender::KnownSenders;
 use mail_auth::{dmarc::Policy, ArcOutput, DkimOutput, DmarcResult};
 use mail_parser::Message;
 use spam_filter::{
@@ -15,7 +16,12 @@ use spam_filter::{
     SpamFilterInput,
 };
 
-use crate::core::Session;
+use crate::{
+    core::Session,
+    queue::{trace_id, QueueId},
+};
+
+use super::reputation::ReputationEvent;
 
 impl<T: SessionStream> Session<T> {
     pub async fn spam_classify<'x>(
@@ -25,6 +31,7 @@ impl<T: SessionStream> Session<T> {
         arc_result: Option<&'x ArcOutput<'x>>,
         dmarc_result: Option<&'x DmarcResult>,
         dmarc_policy: Option<&'x Policy>,
+        queue_id: QueueId,
     ) -> SpamFilterAction<String> {
         let server = &self.server;
         let mut ctx = server.spam_filter_init(self.build_spam_input(
@@ -37,10 +44,54 @@ impl<T: SessionStream> Session<T> {
 
         if !self.is_authenticated() {
             // Spam classification
-            server.spam_filter_classify(&mut ctx).await
+            let result = server.spam_filter_classify(&mut ctx).await;
+
+            trc::event!(
+                Spam(trc::SpamEvent::Result),
+                SpanId = self.data.session_id,
+                TraceId = trace_id(queue_id),
+                Result = match &result {
+                    SpamFilterAction::Allow(_) => "allow",
+                    SpamFilterAction::Discard => "discard",
+                    SpamFilterAction::Reject => "reject",
+                },
+                Value = ctx.result.score,
+                Details = ctx
+                    .result
+                    .tags
+                    .iter()
+                    .map(|tag| trc::Value::String(tag.clone()))
+                    .collect::<Vec<_>>(),
+            );
+
+            // Update the reputation of the connection
+            let is_spam = !matches!(result, SpamFilterAction::Allow(_))
+                || ctx.result.score >= server.core.spam.scores.spam_threshold;
+            self.update_reputation(if is_spam {
+                ReputationEvent::Spam
+            } else {
+                ReputationEvent::Ham
+            })
+            .await;
+
+            result
         } else {
             // Trusted reply tracking
             server.spam_filter_analyze_reply_out(&mut ctx).await;
+
+            // Recipients become known senders of the account
+            if let Some(token) = &self.data.authenticated_as {
+                server
+                    .known_senders_learn(
+                        token.primary_id,
+                        self.data
+                            .rcpt_to
+                            .iter()
+                            .map(|rcpt| rcpt.address_lcase.as_str()),
+                        self.data.session_id,
+                    )
+                    .await;
+            }
             SpamFilterAction::Allow(String::new())
         }
     }
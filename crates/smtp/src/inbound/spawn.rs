@@ -75,6 +75,7 @@ impl SessionManager for SmtpSessionManager {
 impl<T: SessionStream> Session<T> {
     pub async fn init_conn(&mut self) -> bool {
         self.eval_session_params().await;
+        self.load_reputation().await;
 
         let config = &self.server.core.smtp.session.connect;
 
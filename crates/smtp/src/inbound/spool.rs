@@ -0,0 +1,195 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use common::listener::SessionStream;
+use store::{
+    write::{now, BatchBuilder, BlobOp},
+    Serialize,
+};
+use trc::AddContext;
+use utils::BlobHash;
+
+use crate::core::Session;
+
+// Bytes kept in memory after spooling, the DATA receiver
+// needs them to strip the terminating <CRLF>.<CRLF>
+const SPOOL_TAIL_LEN: usize = 3;
+
+/// Chunks of a message that were written to the blob store while
+/// the message was still being received.
+#[derive(Debug, Default)]
+pub struct SpooledMessage {
+    pub chunks: Vec<SpooledChunk>,
+    pub size: usize,
+}
+
+#[derive(Debug)]
+pub struct SpooledChunk {
+    pub hash: BlobHash,
+    pub until: u64,
+    pub size: usize,
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Total number of message bytes received so far, including spooled chunks.
+    pub fn message_size(&self) -> usize {
+        self.data.spool.size + self.data.message.len()
+    }
+
+    /// Writes the received message bytes to the blob store in fixed size chunks
+    /// once the in-memory buffer exceeds the configured spool window.
+    pub async fn spool_message(&mut self) {
+        let Some(chunk_size) = self.server.core.smtp.session.data.spool_chunk_size else {
+            return;
+        };
+
+        while self.data.message.len() >= chunk_size + SPOOL_TAIL_LEN {
+            let chunk = &self.data.message[..chunk_size];
+            let hash = BlobHash::from(chunk);
+
+            // Chunks are reserved until the session expires, so they are purged
+            // automatically if the message is rejected or the client disconnects
+            let until = now()
+                + self
+                    .data
+                    .valid_until
+                    .saturating_duration_since(Instant::now())
+                    .as_secs()
+                + 60;
+            let mut batch = BatchBuilder::new();
+            batch.set(
+                BlobOp::Reserve {
+                    hash: hash.clone(),
+                    until,
+                },
+                0u32.serialize(),
+            );
+
+            if let Err(err) = self
+                .server
+                .store()
+                .write(batch.build())
+                .await
+                .caused_by(trc::location!())
+            {
+                trc::error!(err
+                    .details("Failed to reserve spooled message chunk.")
+                    .span_id(self.data.session_id));
+                return;
+            }
+            if let Err(err) = self
+                .server
+                .blob_store()
+                .put_blob(hash.as_slice(), chunk)
+                .await
+                .caused_by(trc::location!())
+            {
+                // Keep buffering in memory if the blob store is unavailable
+                trc::error!(err
+                    .details("Failed to write spooled message chunk.")
+                    .span_id(self.data.session_id));
+                return;
+            }
+
+            // Commit the chunk so it is purged once the reservation is released
+            let mut batch = BatchBuilder::new();
+            batch.set(BlobOp::Commit { hash: hash.clone() }, Vec::new());
+            if let Err(err) = self
+                .server
+                .store()
+                .write(batch.build())
+                .await
+                .caused_by(trc::location!())
+            {
+                trc::error!(err
+                    .details("Failed to commit spooled message chunk.")
+                    .span_id(self.data.session_id));
+                return;
+            }
+
+            self.data.message.drain(..chunk_size);
+            self.data.spool.size += chunk_size;
+            self.data.spool.chunks.push(SpooledChunk {
+                hash,
+                until,
+                size: chunk_size,
+            });
+        }
+    }
+
+    /// Reassembles a message that was partially written to the blob store
+    /// and releases the spooled chunks.
+    pub async fn unspool_message(&mut self) -> trc::Result<Vec<u8>> {
+        let spool = std::mem::take(&mut self.data.spool);
+        let message = std::mem::take(&mut self.data.message);
+        if spool.chunks.is_empty() {
+            return Ok(message);
+        }
+
+        let mut raw_message = Vec::with_capacity(spool.size + message.len());
+        let mut result = Ok(());
+        for chunk in &spool.chunks {
+            match self
+                .server
+                .blob_store()
+                .get_blob(chunk.hash.as_slice(), 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())
+            {
+                Ok(Some(bytes)) if bytes.len() == chunk.size => {
+                    raw_message.extend_from_slice(&bytes);
+                }
+                Ok(_) => {
+                    result = Err(trc::StoreEvent::NotFound
+                        .into_err()
+                        .details("Spooled message chunk not found.")
+                        .caused_by(trc::location!()));
+                    break;
+                }
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        raw_message.extend_from_slice(&message);
+
+        self.release_spool(spool).await;
+
+        result.map(|_| raw_message)
+    }
+
+    /// Discards any chunks spooled for the current message.
+    pub async fn discard_spool(&mut self) {
+        let spool = std::mem::take(&mut self.data.spool);
+        if !spool.chunks.is_empty() {
+            self.release_spool(spool).await;
+        }
+    }
+
+    async fn release_spool(&self, spool: SpooledMessage) {
+        let mut batch = BatchBuilder::new();
+        for chunk in spool.chunks {
+            batch.clear(BlobOp::Reserve {
+                hash: chunk.hash,
+                until: chunk.until,
+            });
+        }
+        if let Err(err) = self
+            .server
+            .store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())
+        {
+            trc::error!(err
+                .details("Failed to release spooled message chunks.")
+                .span_id(self.data.session_id));
+        }
+    }
+}
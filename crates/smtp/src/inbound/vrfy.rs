@@ -4,8 +4,9 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::listener::SessionStream;
+use common::{expr::if_block::IfBlock, listener::SessionStream};
 use trc::SmtpEvent;
+use utils::config::Rate;
 
 use crate::core::Session;
 use std::fmt::Write;
@@ -23,6 +24,15 @@ impl<T: SessionStream> Session<T> {
             .and_then(|name| self.server.get_directory(&name))
         {
             Some(directory) if self.params.can_vrfy => {
+                if !self
+                    .is_query_allowed(&self.server.core.smtp.session.extensions.vrfy_rate, "vrfy")
+                    .await
+                {
+                    return self
+                        .write(b"452 4.4.5 Rate limit exceeded, try again later.\r\n")
+                        .await;
+                }
+
                 match self
                     .server
                     .vrfy(directory, &address.to_lowercase(), self.data.session_id)
@@ -96,12 +106,50 @@ impl<T: SessionStream> Session<T> {
             .and_then(|name| self.server.get_directory(&name))
         {
             Some(directory) if self.params.can_expn => {
+                if !self
+                    .is_query_allowed(&self.server.core.smtp.session.extensions.expn_rate, "expn")
+                    .await
+                {
+                    return self
+                        .write(b"452 4.4.5 Rate limit exceeded, try again later.\r\n")
+                        .await;
+                }
+
                 match self
                     .server
                     .expn(directory, &address.to_lowercase(), self.data.session_id)
                     .await
                 {
-                    Ok(values) if !values.is_empty() => {
+                    Ok(mut values) if !values.is_empty() => {
+                        // Large lists are acknowledged without disclosing their members
+                        let ec = &self.server.core.smtp.session.extensions;
+                        let opaque_size = self
+                            .server
+                            .eval_if::<usize, _>(&ec.expn_opaque_size, self, self.data.session_id)
+                            .await
+                            .unwrap_or(0);
+                        if opaque_size > 0 && values.len() > opaque_size {
+                            trc::event!(
+                                Smtp(SmtpEvent::ExpnWithheld),
+                                SpanId = self.data.session_id,
+                                To = address,
+                                Total = values.len(),
+                                Limit = opaque_size,
+                            );
+
+                            return self
+                                .write(b"252 2.1.5 Mailing list exists, members not disclosed.\r\n")
+                                .await;
+                        }
+                        let max_size = self
+                            .server
+                            .eval_if::<usize, _>(&ec.expn_max_size, self, self.data.session_id)
+                            .await
+                            .unwrap_or(0);
+                        if max_size > 0 {
+                            values.truncate(max_size);
+                        }
+
                         let mut result = String::with_capacity(32);
                         for (pos, value) in values.iter().enumerate() {
                             let _ = write!(
@@ -156,4 +204,34 @@ impl<T: SessionStream> Session<T> {
             }
         }
     }
+
+    async fn is_query_allowed(&self, rate: &IfBlock, ctx: &str) -> bool {
+        let Some(rate) = self
+            .server
+            .eval_if::<Rate, _>(rate, self, self.data.session_id)
+            .await
+        else {
+            return true;
+        };
+
+        // Limits are enforced per remote IP
+        if self
+            .throttle_rcpt(&self.data.remote_ip_str, &rate, ctx)
+            .await
+        {
+            true
+        } else {
+            trc::event!(
+                Smtp(SmtpEvent::RateLimitExceeded),
+                SpanId = self.data.session_id,
+                Id = ctx.to_string(),
+                Limit = vec![
+                    trc::Value::from(rate.requests),
+                    trc::Value::from(rate.period)
+                ],
+            );
+
+            false
+        }
+    }
 }
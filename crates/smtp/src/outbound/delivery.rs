@@ -9,15 +9,21 @@ use crate::outbound::dane::dnssec::TlsaLookup;
 use crate::outbound::lookup::DnsLookup;
 use crate::outbound::mta_sts::lookup::MtaStsLookup;
 use crate::outbound::mta_sts::verify::VerifyPolicy;
+use crate::outbound::warmup::IpWarmup;
 use crate::outbound::{client::StartTlsResult, dane::verify::TlsaVerify};
 use crate::queue::dsn::SendDsn;
+use crate::queue::shaping::QueueRateShaping;
 use crate::queue::spool::{LOCK_EXPIRY, SmtpSpool};
 use crate::queue::throttle::IsAllowed;
 use crate::reporting::SmtpReporting;
+use crate::reporting::scheduler::{AggregateReportType, ReportSchedule};
 use common::Server;
 use common::config::{
     server::ServerProtocol,
-    smtp::{queue::RequireOptional, report::AggregateFrequency},
+    smtp::{
+        queue::{RequireOptional, ShapingTarget},
+        report::AggregateFrequency,
+    },
 };
 use common::ipc::{PolicyType, QueueEvent, QueueEventStatus, TlsEvent};
 use mail_auth::{
@@ -40,7 +46,7 @@ use crate::{
 };
 
 use super::{NextHop, TlsStrategy, lookup::ToNextHop, mta_sts, session::SessionParams};
-use crate::queue::{Domain, Error, QueueEnvelope, QueuedMessage, Status};
+use crate::queue::{Domain, Error, QueueEnvelope, QueuedMessage, Status, trace_id};
 
 impl QueuedMessage {
     pub fn try_deliver(self, server: Server) {
@@ -57,6 +63,7 @@ impl QueuedMessage {
                         Delivery(DeliveryEvent::AttemptStart),
                         SpanId = message.span_id,
                         QueueId = message.queue_id,
+                        TraceId = trace_id(message.queue_id),
                         From = if !message.return_path.is_empty() {
                             trc::Value::String(message.return_path.to_string())
                         } else {
@@ -235,6 +242,21 @@ impl QueuedMessage {
                 }
             }
 
+            // Shape traffic to the recipient domain
+            if let Err(retry_at) = server
+                .shaping_take(ShapingTarget::Domain, &domain.domain, message.span_id)
+                .await
+            {
+                trc::event!(
+                    Delivery(DeliveryEvent::RateLimitExceeded),
+                    SpanId = span_id,
+                    Domain = domain.domain.clone(),
+                );
+
+                message.domains[domain_idx].set_rate_limiter_error(retry_at);
+                continue 'next_domain;
+            }
+
             // Obtain next hop
             let (mut remote_hosts, is_smtp) = match server
                 .eval_if::<String, _>(&queue_config.next_hop, &envelope, message.span_id)
@@ -283,15 +305,21 @@ impl QueuedMessage {
                 .unwrap_or(false);
 
             // Obtain TLS reporting
-            let tls_report = match server
-                .eval_if(
-                    &server.core.smtp.report.tls.send,
-                    &envelope,
-                    message.span_id,
-                )
+            let tls_interval = match server
+                .report_interval(AggregateReportType::Tls, &domain.domain)
                 .await
-                .unwrap_or(AggregateFrequency::Never)
             {
+                Some(interval) => interval,
+                None => server
+                    .eval_if(
+                        &server.core.smtp.report.tls.send,
+                        &envelope,
+                        message.span_id,
+                    )
+                    .await
+                    .unwrap_or(AggregateFrequency::Never),
+            };
+            let tls_report = match tls_interval {
                 interval @ (AggregateFrequency::Hourly
                 | AggregateFrequency::Daily
                 | AggregateFrequency::Weekly)
@@ -578,6 +606,7 @@ impl QueuedMessage {
                 .await
                 .unwrap_or(2);
             let mut last_status = Status::Scheduled;
+            let mut is_mx_shaped = false;
             'next_host: for remote_host in &remote_hosts {
                 // Validate MTA-STS
                 envelope.mx = remote_host.hostname();
@@ -871,6 +900,33 @@ impl QueuedMessage {
                         }
                     }
 
+                    // Shape traffic to the MX host, only once per domain delivery
+                    if !is_mx_shaped {
+                        match server
+                            .shaping_take(ShapingTarget::Mx, envelope.mx, message.span_id)
+                            .await
+                        {
+                            Ok(is_shaped) => is_mx_shaped = is_shaped,
+                            Err(retry_at) => {
+                                trc::event!(
+                                    Delivery(DeliveryEvent::RateLimitExceeded),
+                                    SpanId = message.span_id,
+                                    Domain = domain.domain.clone(),
+                                    Hostname = envelope.mx.to_string(),
+                                );
+                                message.domains[domain_idx].set_rate_limiter_error(retry_at);
+                                continue 'next_domain;
+                            }
+                        }
+                    }
+
+                    // Count the delivery towards the warm-up limit of the source IP
+                    if resolve_result.warmup
+                        && let Some(source_ip) = source_ip
+                    {
+                        server.warmup_record(source_ip, message.span_id).await;
+                    }
+
                     // Connect
                     let time = Instant::now();
                     let conn_timeout = server
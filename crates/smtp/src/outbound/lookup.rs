@@ -16,15 +16,17 @@ use common::{
 };
 use mail_auth::{IpLookupStrategy, MX};
 use rand::{Rng, seq::SliceRandom};
+use trc::DeliveryEvent;
 
 use crate::queue::{Error, ErrorDetails, Status};
 
-use super::NextHop;
+use super::{NextHop, warmup::IpWarmup};
 
 pub struct IpLookupResult {
     pub source_ipv4: Option<IpAddr>,
     pub source_ipv6: Option<IpAddr>,
     pub remote_ips: Vec<IpAddr>,
+    pub warmup: bool,
 }
 
 pub trait DnsLookup: Sync + Send {
@@ -42,6 +44,13 @@ pub trait DnsLookup: Sync + Send {
         max_multihomed: usize,
         session_id: u64,
     ) -> impl Future<Output = Result<IpLookupResult, Status<(), Error>>> + Send;
+
+    fn select_source_ip(
+        &self,
+        source_ips: Vec<IpAddr>,
+        warmup: bool,
+        session_id: u64,
+    ) -> impl Future<Output = Option<IpAddr>> + Send;
 }
 
 impl DnsLookup for Server {
@@ -149,6 +158,13 @@ impl DnsLookup for Server {
                 source_ipv4: None,
                 source_ipv6: None,
                 remote_ips,
+                warmup: !self.core.smtp.queue.warmup.ips.is_empty()
+                    && self
+                        .core
+                        .smtp
+                        .queue
+                        .warmup
+                        .is_provider(remote_host.hostname()),
             };
 
             // Obtain source IPv4 address
@@ -159,17 +175,16 @@ impl DnsLookup for Server {
                     session_id,
                 )
                 .await
-                .unwrap_or_default();
-            match source_ips.len().cmp(&1) {
-                std::cmp::Ordering::Equal => {
-                    result.source_ipv4 = IpAddr::from(*source_ips.first().unwrap()).into();
-                }
-                std::cmp::Ordering::Greater => {
-                    result.source_ipv4 =
-                        IpAddr::from(source_ips[rand::rng().random_range(0..source_ips.len())])
-                            .into();
-                }
-                std::cmp::Ordering::Less => (),
+                .unwrap_or_default()
+                .into_iter()
+                .map(IpAddr::from)
+                .collect::<Vec<_>>();
+            let has_source_ips = !source_ips.is_empty();
+            result.source_ipv4 = self
+                .select_source_ip(source_ips, result.warmup, session_id)
+                .await;
+            if has_source_ips && result.source_ipv4.is_none() {
+                result.remote_ips.retain(|ip| !ip.is_ipv4());
             }
 
             // Obtain source IPv6 address
@@ -180,17 +195,25 @@ impl DnsLookup for Server {
                     session_id,
                 )
                 .await
-                .unwrap_or_default();
-            match source_ips.len().cmp(&1) {
-                std::cmp::Ordering::Equal => {
-                    result.source_ipv6 = IpAddr::from(*source_ips.first().unwrap()).into();
-                }
-                std::cmp::Ordering::Greater => {
-                    result.source_ipv6 =
-                        IpAddr::from(source_ips[rand::rng().random_range(0..source_ips.len())])
-                            .into();
-                }
-                std::cmp::Ordering::Less => (),
+                .unwrap_or_default()
+                .into_iter()
+                .map(IpAddr::from)
+                .collect::<Vec<_>>();
+            let has_source_ips = !source_ips.is_empty();
+            result.source_ipv6 = self
+                .select_source_ip(source_ips, result.warmup, session_id)
+                .await;
+            if has_source_ips && result.source_ipv6.is_none() {
+                result.remote_ips.retain(|ip| !ip.is_ipv6());
+            }
+
+            if result.remote_ips.is_empty() {
+                return Err(Status::TemporaryFailure(Error::ConnectionError(
+                    ErrorDetails {
+                        entity: remote_host.hostname().to_string(),
+                        details: "Daily warm-up limit reached for all source IPs".to_string(),
+                    },
+                )));
             }
 
             Ok(result)
@@ -201,6 +224,38 @@ impl DnsLookup for Server {
             ))))
         }
     }
+
+    async fn select_source_ip(
+        &self,
+        mut source_ips: Vec<IpAddr>,
+        warmup: bool,
+        session_id: u64,
+    ) -> Option<IpAddr> {
+        // Spill over to other addresses when an IP reached its daily warm-up limit
+        if warmup {
+            let mut allowed_ips = Vec::with_capacity(source_ips.len());
+            for ip in source_ips {
+                if self.warmup_allowed(ip, session_id).await {
+                    allowed_ips.push(ip);
+                } else {
+                    trc::event!(
+                        Delivery(DeliveryEvent::WarmupLimitReached),
+                        SpanId = session_id,
+                        LocalIp = ip,
+                    );
+                }
+            }
+            source_ips = allowed_ips;
+        }
+
+        match source_ips.len().cmp(&1) {
+            std::cmp::Ordering::Equal => source_ips.first().copied(),
+            std::cmp::Ordering::Greater => {
+                source_ips[rand::rng().random_range(0..source_ips.len())].into()
+            }
+            std::cmp::Ordering::Less => None,
+        }
+    }
 }
 
 pub trait ToNextHop {
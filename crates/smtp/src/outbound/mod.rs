@@ -22,6 +22,7 @@ pub mod local;
 pub mod lookup;
 pub mod mta_sts;
 pub mod session;
+pub mod warmup;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct TlsStrategy {
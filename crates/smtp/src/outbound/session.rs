@@ -8,16 +8,18 @@ use common::config::smtp::queue::RequireOptional;
 use common::Server;
 use mail_send::Credentials;
 use smtp_proto::{
-    EhloResponse, Severity, EXT_CHUNKING, EXT_DSN, EXT_REQUIRE_TLS, EXT_SIZE, EXT_SMTP_UTF8,
-    MAIL_REQUIRETLS, MAIL_RET_FULL, MAIL_RET_HDRS, MAIL_SMTPUTF8, RCPT_NOTIFY_DELAY,
+    EhloResponse, Response, Severity, EXT_CHUNKING, EXT_DSN, EXT_REQUIRE_TLS, EXT_SIZE,
+    EXT_SMTP_UTF8, MAIL_REQUIRETLS, MAIL_RET_FULL, MAIL_RET_HDRS, MAIL_SMTPUTF8, RCPT_NOTIFY_DELAY,
     RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
 };
 use std::time::Duration;
 use std::{fmt::Write, time::Instant};
 use tokio::io::{AsyncRead, AsyncWrite};
 use trc::DeliveryEvent;
+use utils::ascii_address;
 
 use crate::outbound::client::{from_error_status, from_mail_send_error};
+use crate::queue::dsn::write_xtext;
 use crate::queue::{ErrorDetails, HostResponse, RCPT_STATUS_CHANGED};
 
 use crate::queue::{Error, Message, Recipient, Status};
@@ -112,10 +114,31 @@ impl Message {
             };*/
         }
 
+        // Internationalized messages can only be relayed to hosts that do not
+        // support SMTPUTF8 if downgrading is enabled (RFC 6531)
+        let downgrade = self.has_flag(MAIL_SMTPUTF8) && !capabilities.has_capability(EXT_SMTP_UTF8);
+        if downgrade
+            && (!params.server.core.smtp.queue.smtputf8_downgrade
+                || ascii_address(&self.return_path).is_none())
+        {
+            trc::event!(
+                Delivery(DeliveryEvent::SmtpUtf8Unavailable),
+                SpanId = params.session_id,
+                Hostname = params.hostname.to_string(),
+                From = self.return_path.to_string(),
+            );
+
+            smtp_client.quit().await;
+            return Status::PermanentFailure(Error::UnexpectedResponse(smtputf8_required(
+                params.hostname,
+                "MAIL FROM",
+            )));
+        }
+
         // MAIL FROM
         let time = Instant::now();
         smtp_client.timeout = params.timeout_mail;
-        let cmd = self.build_mail_from(&capabilities);
+        let cmd = self.build_mail_from(&capabilities, downgrade);
         match smtp_client.cmd(cmd.as_bytes()).await.and_then(|r| {
             if r.is_positive_completion() {
                 Ok(r)
@@ -164,7 +187,23 @@ impl Message {
                 continue;
             }
 
-            let cmd = self.build_rcpt_to(rcpt, &capabilities);
+            // Recipients with internationalized local parts cannot be downgraded
+            if downgrade && ascii_address(&rcpt.address).is_none() {
+                trc::event!(
+                    Delivery(DeliveryEvent::SmtpUtf8Unavailable),
+                    SpanId = params.session_id,
+                    Hostname = params.hostname.to_string(),
+                    To = rcpt.address.to_string(),
+                );
+
+                rcpt.flags |= RCPT_STATUS_CHANGED;
+                rcpt.status =
+                    Status::PermanentFailure(smtputf8_required(params.hostname, "RCPT TO"));
+                total_completed += 1;
+                continue;
+            }
+
+            let cmd = self.build_rcpt_to(rcpt, &capabilities, downgrade);
             match smtp_client.cmd(cmd.as_bytes()).await {
                 Ok(response) => match response.severity() {
                     Severity::PositiveCompletion => {
@@ -387,9 +426,16 @@ impl Message {
         }
     }
 
-    fn build_mail_from(&self, capabilities: &EhloResponse<String>) -> String {
+    fn build_mail_from(&self, capabilities: &EhloResponse<String>, downgrade: bool) -> String {
         let mut mail_from = String::with_capacity(self.return_path.len() + 60);
-        let _ = write!(mail_from, "MAIL FROM:<{}>", self.return_path);
+        let _ = write!(
+            mail_from,
+            "MAIL FROM:<{}>",
+            downgrade
+                .then(|| ascii_address(&self.return_path))
+                .flatten()
+                .unwrap_or(self.return_path.as_str().into())
+        );
         if capabilities.has_capability(EXT_SIZE) {
             let _ = write!(mail_from, " SIZE={}", self.size);
         }
@@ -406,7 +452,8 @@ impl Message {
                 mail_from.push_str(" RET=HDRS");
             }
             if let Some(env_id) = &self.env_id {
-                let _ = write!(mail_from, " ENVID={env_id}");
+                mail_from.push_str(" ENVID=");
+                write_xtext(&mut mail_from, env_id);
             }
         }
 
@@ -414,9 +461,21 @@ impl Message {
         mail_from
     }
 
-    fn build_rcpt_to(&self, rcpt: &Recipient, capabilities: &EhloResponse<String>) -> String {
+    fn build_rcpt_to(
+        &self,
+        rcpt: &Recipient,
+        capabilities: &EhloResponse<String>,
+        downgrade: bool,
+    ) -> String {
         let mut rcpt_to = String::with_capacity(rcpt.address.len() + 60);
-        let _ = write!(rcpt_to, "RCPT TO:<{}>", rcpt.address);
+        let _ = write!(
+            rcpt_to,
+            "RCPT TO:<{}>",
+            downgrade
+                .then(|| ascii_address(&rcpt.address))
+                .flatten()
+                .unwrap_or(rcpt.address.as_str().into())
+        );
         if capabilities.has_capability(EXT_DSN) {
             if rcpt.has_flag(RCPT_NOTIFY_SUCCESS | RCPT_NOTIFY_FAILURE | RCPT_NOTIFY_DELAY) {
                 rcpt_to.push_str(" NOTIFY=");
@@ -443,6 +502,10 @@ impl Message {
             } else if rcpt.has_flag(RCPT_NOTIFY_NEVER) {
                 rcpt_to.push_str(" NOTIFY=NEVER");
             }
+            if let Some(orcpt) = &rcpt.orcpt {
+                rcpt_to.push_str(" ORCPT=rfc822;");
+                write_xtext(&mut rcpt_to, orcpt);
+            }
         }
         rcpt_to.push_str("\r\n");
         rcpt_to
@@ -454,6 +517,20 @@ impl Message {
     }
 }
 
+fn smtputf8_required(hostname: &str, command: &str) -> HostResponse<ErrorDetails> {
+    HostResponse {
+        hostname: ErrorDetails {
+            entity: hostname.to_string(),
+            details: command.to_string(),
+        },
+        response: Response {
+            code: 553,
+            esc: [5, 6, 7],
+            message: "Remote host does not support SMTPUTF8".to_string(),
+        },
+    }
+}
+
 impl Recipient {
     #[inline(always)]
     pub fn has_flag(&self, flag: u64) -> bool {
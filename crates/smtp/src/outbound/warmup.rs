@@ -0,0 +1,101 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, net::IpAddr};
+
+use common::{KV_WARMUP, Server};
+use store::{dispatch::lookup::KeyValue, write::now};
+use trc::AddContext;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WarmupStatus {
+    pub address: IpAddr,
+    pub day: u64,
+    pub days: u64,
+    pub limit: Option<u64>,
+    pub sent: u64,
+}
+
+pub trait IpWarmup: Sync + Send {
+    fn warmup_status(
+        &self,
+        ip: IpAddr,
+    ) -> impl Future<Output = trc::Result<Option<WarmupStatus>>> + Send;
+
+    fn warmup_allowed(&self, ip: IpAddr, session_id: u64) -> impl Future<Output = bool> + Send;
+
+    fn warmup_record(&self, ip: IpAddr, session_id: u64) -> impl Future<Output = ()> + Send;
+}
+
+impl IpWarmup for Server {
+    async fn warmup_status(&self, ip: IpAddr) -> trc::Result<Option<WarmupStatus>> {
+        let Some(schedule) = self.core.smtp.queue.warmup.ips.get(&ip) else {
+            return Ok(None);
+        };
+        let day = schedule.day(now());
+        let limit = schedule.limit(day);
+        let sent = if limit.is_some() {
+            self.in_memory_store()
+                .counter_get(KeyValue::<()>::build_key(KV_WARMUP, warmup_key(ip, day)))
+                .await
+                .caused_by(trc::location!())?
+                .max(0) as u64
+        } else {
+            0
+        };
+
+        Ok(Some(WarmupStatus {
+            address: ip,
+            day,
+            days: schedule.limits.len() as u64,
+            limit,
+            sent,
+        }))
+    }
+
+    async fn warmup_allowed(&self, ip: IpAddr, session_id: u64) -> bool {
+        match self.warmup_status(ip).await {
+            Ok(Some(WarmupStatus {
+                limit: Some(limit),
+                sent,
+                ..
+            })) => sent < limit,
+            Ok(_) => true,
+            Err(err) => {
+                trc::error!(err.span_id(session_id));
+                true
+            }
+        }
+    }
+
+    async fn warmup_record(&self, ip: IpAddr, session_id: u64) {
+        let Some(schedule) = self.core.smtp.queue.warmup.ips.get(&ip) else {
+            return;
+        };
+        let day = schedule.day(now());
+        if schedule.limit(day).is_some()
+            && let Err(err) = self
+                .in_memory_store()
+                .counter_incr(
+                    KeyValue::with_prefix(KV_WARMUP, warmup_key(ip, day), 1).expires(2 * 86400),
+                    false,
+                )
+                .await
+        {
+            trc::error!(err.span_id(session_id));
+        }
+    }
+}
+
+fn warmup_key(ip: IpAddr, day: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(17 + std::mem::size_of::<u64>());
+    match ip {
+        IpAddr::V4(ip) => key.extend_from_slice(&ip.octets()),
+        IpAddr::V6(ip) => key.extend_from_slice(&ip.octets()),
+    }
+    key.extend_from_slice(&day.to_be_bytes());
+    key
+}
@@ -0,0 +1,131 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::atomic::Ordering, time::Instant};
+
+use common::Server;
+use store::write::now;
+use trc::{AddContext, QueueEvent};
+
+pub trait AdmissionControl: Sync + Send {
+    fn is_queue_admitting(&self, session_id: u64) -> impl Future<Output = bool> + Send;
+    fn queue_pressure(&self) -> impl Future<Output = Option<String>> + Send;
+}
+
+impl AdmissionControl for Server {
+    async fn is_queue_admitting(&self, session_id: u64) -> bool {
+        // Messages cannot be spooled while the blob store is failing
+        if !self.blob_store().is_available() {
+            return false;
+        }
+
+        let config = &self.core.smtp.queue.admission;
+        if !config.enable {
+            return true;
+        }
+
+        // Only one session refreshes the admission status on each interval
+        let status = &self.inner.data.queue_admission;
+        let last_check = status.last_check.load(Ordering::Relaxed);
+        let now = now();
+        if last_check + config.check_interval.as_secs() <= now
+            && status
+                .last_check
+                .compare_exchange(last_check, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            let pressure = self.queue_pressure().await;
+            let was_paused = status.is_paused.swap(pressure.is_some(), Ordering::Relaxed);
+
+            match pressure {
+                Some(reason) if !was_paused => {
+                    trc::event!(
+                        Queue(QueueEvent::AdmissionPaused),
+                        SpanId = session_id,
+                        Reason = reason,
+                    );
+                }
+                None if was_paused => {
+                    trc::event!(Queue(QueueEvent::AdmissionResumed), SpanId = session_id);
+                }
+                _ => (),
+            }
+        }
+
+        !status.is_paused.load(Ordering::Relaxed)
+    }
+
+    async fn queue_pressure(&self) -> Option<String> {
+        let config = &self.core.smtp.queue.admission;
+
+        // Count the queue backlog, which doubles as a data store probe
+        let time = Instant::now();
+        let total_messages = match self.total_queued_messages().await {
+            Ok(total_messages) => total_messages,
+            Err(err) => {
+                trc::error!(err.details("Failed to obtain queue backlog"));
+                return Some("Data store is unavailable".to_string());
+            }
+        };
+        let data_latency = time.elapsed();
+        if let Some(max_messages) = config.max_messages
+            && total_messages > max_messages
+        {
+            return Some(format!(
+                "Queue backlog of {total_messages} messages exceeds limit of {max_messages}"
+            ));
+        }
+
+        // Write, read back and delete a probe blob
+        let blob_key =
+            format!("queue-admission-{}-{}", self.core.network.node_id, now()).into_bytes();
+        let time = Instant::now();
+        let result = match self
+            .blob_store()
+            .put_blob(&blob_key, b"probe")
+            .await
+            .caused_by(trc::location!())
+        {
+            Ok(_) => self
+                .blob_store()
+                .get_blob(&blob_key, 0..usize::MAX)
+                .await
+                .caused_by(trc::location!()),
+            Err(err) => Err(err),
+        };
+        let blob_latency = time.elapsed();
+        match result {
+            Ok(Some(_)) => {
+                if let Err(err) = self.blob_store().delete_blob(&blob_key).await {
+                    trc::error!(err.details("Failed to delete queue admission probe"));
+                }
+            }
+            Ok(None) => {
+                return Some("Blob store did not return the probe blob".to_string());
+            }
+            Err(err) => {
+                trc::error!(err.details("Failed to probe blob store"));
+                return Some("Blob store is unavailable".to_string());
+            }
+        }
+
+        if let Some(max_latency) = config.max_latency {
+            if data_latency > max_latency {
+                return Some(format!(
+                    "Data store latency of {}ms exceeds limit",
+                    data_latency.as_millis()
+                ));
+            } else if blob_latency > max_latency {
+                return Some(format!(
+                    "Blob store latency of {}ms exceeds limit",
+                    blob_latency.as_millis()
+                ));
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,289 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::io::{self, Write};
+
+use common::Server;
+use mail_parser::DateTime;
+use store::{
+    Deserialize, IterateParams, ValueKey,
+    write::{QueueClass, ValueClass, now},
+};
+
+use super::{
+    Message, QueueId, Status,
+    format::{SpoolMigrate, SpooledMessage},
+    spool::SmtpSpool,
+};
+
+const HELP: &str = concat!(
+    "Stalwart Mail Server v",
+    env!("CARGO_PKG_VERSION"),
+    r#" Queue CLI
+
+Enter commands (type 'help' for available commands).
+"#
+);
+
+pub async fn queue_console(server: Server) {
+    print!("{HELP}");
+
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).unwrap_or_default() == 0 {
+            break;
+        }
+        let input = input.trim();
+
+        let parts: Vec<&str> = input.split_whitespace().collect();
+
+        if parts.is_empty() {
+            continue;
+        }
+
+        match parts[0] {
+            "list" => {
+                if parts.len() > 2 {
+                    println!("Usage: list [<filter>]");
+                } else {
+                    list_messages(&server, parts.get(1).copied()).await;
+                }
+            }
+            "show" => match parts.get(1).and_then(|id| id.parse::<QueueId>().ok()) {
+                Some(queue_id) => match server.read_message(queue_id).await {
+                    Some(message) => print_message(&message),
+                    None => println!("Message not found."),
+                },
+                None => println!("Usage: show <queue_id>"),
+            },
+            "retry" => match (
+                parts.get(1).and_then(|id| id.parse::<QueueId>().ok()),
+                parts.get(2),
+            ) {
+                (Some(queue_id), due) => {
+                    let due = match due {
+                        Some(due) => match DateTime::parse_rfc3339(due) {
+                            Some(due) => due.to_timestamp() as u64,
+                            None => {
+                                println!("Invalid RFC3339 timestamp: {due}");
+                                continue;
+                            }
+                        },
+                        None => now(),
+                    };
+                    retry_message(&server, queue_id, due, parts.get(3).copied()).await;
+                }
+                _ => println!("Usage: retry <queue_id> [<rfc3339_time> [<domain>]]"),
+            },
+            "cancel" => match parts.get(1).and_then(|id| id.parse::<QueueId>().ok()) {
+                Some(queue_id) => {
+                    cancel_message(&server, queue_id, parts.get(2).copied()).await;
+                }
+                None => println!("Usage: cancel <queue_id> [<recipient>]"),
+            },
+            "migrate" => {
+                migrate_spool(&server).await;
+            }
+            "help" => {
+                print_help();
+            }
+            "exit" | "quit" => {
+                println!("Exiting...");
+                break;
+            }
+            _ => {
+                println!("Unknown command. Type 'help' for available commands.");
+            }
+        }
+    }
+}
+
+async fn list_messages(server: &Server, filter: Option<&str>) {
+    let mut total = 0;
+    let result = server
+        .store()
+        .iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Queue(QueueClass::Message(0))),
+                ValueKey::from(ValueClass::Queue(QueueClass::Message(u64::MAX))),
+            )
+            .ascending(),
+            |_, value| {
+                let message = SpooledMessage::deserialize(value)?.inner;
+
+                if filter.is_none_or(|filter| {
+                    message.return_path_lcase.contains(filter)
+                        || message
+                            .recipients
+                            .iter()
+                            .any(|rcpt| rcpt.address_lcase.contains(filter))
+                }) {
+                    println!(
+                        "{:<20} {:<25} {:>10} {:>4} rcpt  next retry {}  from <{}>",
+                        message.queue_id,
+                        message_status(&message),
+                        message.size,
+                        message.recipients.len(),
+                        format_timestamp(message.next_delivery_event()),
+                        message.return_path,
+                    );
+                    total += 1;
+                }
+
+                Ok(true)
+            },
+        )
+        .await;
+
+    match result {
+        Ok(_) => println!("{total} message(s) found."),
+        Err(err) => println!("Failed to read queue: {err}"),
+    }
+}
+
+async fn retry_message(server: &Server, queue_id: QueueId, due: u64, domain: Option<&str>) {
+    if !server.try_lock_event(queue_id).await {
+        println!("Message is locked by a running queue manager, try again later.");
+        return;
+    }
+
+    match server.read_message(queue_id).await {
+        Some(mut message) => {
+            let prev_event = message.next_event().unwrap_or_default();
+
+            if message.reschedule(due, domain) {
+                let next_event = message.next_event().unwrap_or_default();
+                if message
+                    .save_changes(server, prev_event.into(), next_event.into())
+                    .await
+                {
+                    println!("Message rescheduled for {}.", format_timestamp(due));
+                } else {
+                    println!("Failed to update message.");
+                }
+            } else {
+                println!("No pending deliveries found.");
+            }
+        }
+        None => println!("Message not found."),
+    }
+
+    server.unlock_event(queue_id).await;
+}
+
+async fn cancel_message(server: &Server, queue_id: QueueId, rcpt: Option<&str>) {
+    if !server.try_lock_event(queue_id).await {
+        println!("Message is locked by a running queue manager, try again later.");
+        return;
+    }
+
+    match server.read_message(queue_id).await {
+        Some(mut message) => {
+            let prev_event = message.next_event().unwrap_or_default();
+
+            if let Some(rcpt) = rcpt {
+                if !message.cancel_recipients(rcpt) {
+                    println!("No matching recipients found.");
+                } else if message.has_pending_domains() {
+                    let next_event = message.next_event().unwrap_or_default();
+                    if message
+                        .save_changes(server, prev_event.into(), next_event.into())
+                        .await
+                    {
+                        println!("Delivery canceled for matching recipients.");
+                    } else {
+                        println!("Failed to update message.");
+                    }
+                } else if message.remove(server, prev_event).await {
+                    println!("Message removed from queue.");
+                } else {
+                    println!("Failed to remove message.");
+                }
+            } else if message.remove(server, prev_event).await {
+                println!("Message removed from queue.");
+            } else {
+                println!("Failed to remove message.");
+            }
+        }
+        None => println!("Message not found."),
+    }
+
+    server.unlock_event(queue_id).await;
+}
+
+async fn migrate_spool(server: &Server) {
+    match server.migrate_spool().await {
+        Ok(result) => {
+            println!(
+                "{} message(s) found, {} migrated, {} locked, {} failed.",
+                result.total, result.migrated, result.locked, result.failed
+            );
+            if result.locked > 0 {
+                println!("Locked messages were not migrated, run this command again later.");
+            }
+        }
+        Err(err) => println!("Failed to migrate queue: {err}"),
+    }
+}
+
+fn print_message(message: &Message) {
+    println!("Queue Id:    {}", message.queue_id);
+    println!("Return Path: <{}>", message.return_path);
+    println!("Created:     {}", format_timestamp(message.created));
+    println!("Size:        {}", message.size);
+    println!("Priority:    {}", message.priority);
+    if let Some(env_id) = &message.env_id {
+        println!("Envelope Id: {env_id}");
+    }
+
+    for (idx, domain) in message.domains.iter().enumerate() {
+        println!();
+        println!("Domain:      {}", domain.domain);
+        println!("  Status:      {}", domain.status);
+        println!("  Retry #:     {}", domain.retry.inner);
+        println!("  Next Retry:  {}", format_timestamp(domain.retry.due));
+        println!("  Next Notify: {}", format_timestamp(domain.notify.due));
+        println!("  Expires:     {}", format_timestamp(domain.expires));
+
+        for rcpt in message.recipients.iter().filter(|r| r.domain_idx == idx) {
+            println!("  Recipient:   <{}> {}", rcpt.address, rcpt.status);
+        }
+    }
+}
+
+fn message_status(message: &Message) -> &'static str {
+    if message.has_pending_domains() {
+        if message
+            .domains
+            .iter()
+            .any(|d| matches!(d.status, Status::TemporaryFailure(_)))
+        {
+            "deferred"
+        } else {
+            "scheduled"
+        }
+    } else {
+        "completed"
+    }
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64).to_rfc3339()
+}
+
+fn print_help() {
+    println!("Available commands:");
+    println!("  list [<filter>]");
+    println!("  show <queue_id>");
+    println!("  retry <queue_id> [<rfc3339_time> [<domain>]]");
+    println!("  cancel <queue_id> [<recipient>]");
+    println!("  migrate");
+    println!("  help");
+    println!("  exit/quit");
+}
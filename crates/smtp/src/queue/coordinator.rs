@@ -0,0 +1,175 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, time::Instant};
+
+use common::{Server, KV_QUEUE_MEMBER};
+use store::{
+    dispatch::lookup::KeyValue,
+    write::{key::DeserializeBigEndian, now, InMemoryClass, ValueClass},
+    xxhash_rust::xxh3::xxh3_64,
+    InMemoryStore, IterateParams, ValueKey, U64_LEN,
+};
+use trc::{AddContext, QueueEvent};
+
+use super::{spool::QUEUE_REFRESH, QueueId};
+
+/// Live nodes sharing the queue, each of which only delivers the messages
+/// that hash onto it.
+pub struct QueueMembers {
+    pub node_id: u64,
+    pub members: Vec<u64>,
+    pub next_heartbeat: Instant,
+}
+
+pub trait QueueCoordination: Sync + Send {
+    fn queue_heartbeat(&self) -> impl Future<Output = trc::Result<Vec<u64>>> + Send;
+    fn queue_leave(&self) -> impl Future<Output = ()> + Send;
+}
+
+impl QueueCoordination for Server {
+    /// Renews this node's membership and returns the ids of all live members.
+    async fn queue_heartbeat(&self) -> trc::Result<Vec<u64>> {
+        // Membership is kept in the data store, which all nodes share
+        let node_id = self.core.network.node_id;
+        let timeout = self.core.smtp.queue.coordinator.timeout.as_secs();
+        InMemoryStore::from(self.store().clone())
+            .key_set(
+                KeyValue::with_prefix(KV_QUEUE_MEMBER, node_id.to_be_bytes(), vec![])
+                    .expires(timeout),
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        let mut members = Vec::new();
+        let now = now();
+        self.store()
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::InMemory(InMemoryClass::Key(
+                        KeyValue::<()>::build_key(KV_QUEUE_MEMBER, 0u64.to_be_bytes()),
+                    ))),
+                    ValueKey::from(ValueClass::InMemory(InMemoryClass::Key(
+                        KeyValue::<()>::build_key(KV_QUEUE_MEMBER, u64::MAX.to_be_bytes()),
+                    ))),
+                )
+                .ascending(),
+                |key, value| {
+                    if key.len() == U64_LEN + 1 && value.deserialize_be_u64(0)? > now {
+                        members.push(key.deserialize_be_u64(1)?);
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(members)
+    }
+
+    /// Removes this node from the cluster so that other nodes take over its
+    /// messages without waiting for its membership to expire.
+    async fn queue_leave(&self) {
+        if let Err(err) = InMemoryStore::from(self.store().clone())
+            .key_delete(KeyValue::<()>::build_key(
+                KV_QUEUE_MEMBER,
+                self.core.network.node_id.to_be_bytes(),
+            ))
+            .await
+        {
+            trc::error!(err
+                .details("Failed to leave queue cluster")
+                .caused_by(trc::location!()));
+        }
+    }
+}
+
+impl QueueMembers {
+    pub fn new(node_id: u64) -> Self {
+        QueueMembers {
+            node_id,
+            members: Vec::new(),
+            next_heartbeat: Instant::now(),
+        }
+    }
+
+    /// Refreshes the member list, returning `true` when messages have to be
+    /// redistributed because a node joined or left.
+    pub async fn refresh(&mut self, server: &Server) -> bool {
+        let config = &server.core.smtp.queue.coordinator;
+        if !config.enable {
+            self.next_heartbeat = Instant::now() + std::time::Duration::from_secs(QUEUE_REFRESH);
+            return if !self.members.is_empty() {
+                self.members.clear();
+                server.queue_leave().await;
+                true
+            } else {
+                false
+            };
+        }
+        self.next_heartbeat = Instant::now() + config.heartbeat;
+
+        let members = match server.queue_heartbeat().await {
+            Ok(members) => members,
+            Err(err) => {
+                // Keep the last known members until the store is reachable again
+                trc::error!(err.details("Failed to renew queue cluster membership"));
+                return false;
+            }
+        };
+        if members == self.members {
+            return false;
+        }
+
+        for node_id in members.iter().filter(|id| !self.members.contains(id)) {
+            trc::event!(
+                Queue(QueueEvent::NodeJoined),
+                Id = *node_id,
+                Total = members.len(),
+            );
+        }
+        for node_id in self.members.iter().filter(|id| !members.contains(id)) {
+            trc::event!(
+                Queue(QueueEvent::NodeLeft),
+                Id = *node_id,
+                Total = members.len(),
+            );
+        }
+        self.members = members;
+
+        true
+    }
+
+    /// Removes this node from the cluster, for when it stops delivering.
+    pub async fn leave(&mut self, server: &Server) {
+        if !self.members.is_empty() {
+            self.members.clear();
+            server.queue_leave().await;
+        }
+        self.next_heartbeat = Instant::now();
+    }
+
+    pub fn is_heartbeat_due(&self) -> bool {
+        self.next_heartbeat <= Instant::now()
+    }
+
+    /// Returns whether this node is responsible for delivering a message,
+    /// which is always the case when the queue is not clustered.
+    pub fn is_owner(&self, queue_id: QueueId) -> bool {
+        queue_owner(&self.members, queue_id).is_none_or(|node_id| node_id == self.node_id)
+    }
+}
+
+/// Assigns a message to the member with the highest score (rendezvous hashing),
+/// so that when a node leaves only the messages it owned are reassigned.
+pub fn queue_owner(members: &[u64], queue_id: QueueId) -> Option<u64> {
+    members.iter().copied().max_by_key(|node_id| {
+        let mut bytes = [0u8; U64_LEN * 2];
+        bytes[..U64_LEN].copy_from_slice(&queue_id.to_be_bytes());
+        bytes[U64_LEN..].copy_from_slice(&node_id.to_be_bytes());
+        xxh3_64(&bytes)
+    })
+}
@@ -11,7 +11,8 @@ use mail_builder::mime::{make_boundary, BodyPart, MimePart};
 use mail_builder::MessageBuilder;
 use mail_parser::DateTime;
 use smtp_proto::{
-    Response, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
+    Response, MAIL_SMTPUTF8, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER,
+    RCPT_NOTIFY_SUCCESS,
 };
 use std::fmt::Write;
 use std::future::Future;
@@ -22,25 +23,39 @@ use crate::outbound::client::from_error_status;
 use crate::reporting::SmtpReporting;
 
 use super::spool::SmtpSpool;
+use super::suppression::SuppressionList;
 use super::{
-    Domain, Error, ErrorDetails, HostResponse, Message, MessageSource, QueueEnvelope, Recipient,
-    Status, RCPT_DSN_SENT, RCPT_STATUS_CHANGED,
+    trace_id, Domain, Error, ErrorDetails, HostResponse, Message, MessageSource, QueueEnvelope,
+    Recipient, Status, MAIL_DELAY_DSN_SHIFT, RCPT_DSN_SENT, RCPT_STATUS_CHANGED,
 };
 
 pub trait SendDsn: Sync + Send {
     fn send_dsn(&self, message: &mut Message) -> impl Future<Output = ()> + Send;
-    fn log_dsn(&self, message: &Message) -> impl Future<Output = ()> + Send;
+    fn log_dsn(&self, message: &Message, delayed: &[bool]) -> impl Future<Output = ()> + Send;
 }
 
 impl SendDsn for Server {
     async fn send_dsn(&self, message: &mut Message) {
+        // Apply the sender's delayed DSN policy
+        let delayed = message.delayed_dsn_domains(self).await;
+
         // Send DSN events
-        self.log_dsn(message).await;
+        self.log_dsn(message, &delayed).await;
+
+        // Suppress recipients that no longer exist
+        self.suppress_hard_bounces(message).await;
 
-        if !message.return_path.is_empty() {
+        if !message.return_path.is_empty()
+            && !self
+                .core
+                .smtp
+                .auto_responder
+                .is_dsn_suppressed(&message.return_path_lcase)
+        {
             // Build DSN
-            if let Some(dsn) = message.build_dsn(self).await {
+            if let Some(dsn) = message.build_dsn(self, &delayed).await {
                 let mut dsn_message = self.new_message("", "", "", message.span_id);
+                dsn_message.flags |= message.flags & MAIL_SMTPUTF8;
                 dsn_message
                     .add_recipient_parts(
                         &message.return_path,
@@ -67,14 +82,12 @@ impl SendDsn for Server {
                     .await;
             }
         } else {
-            // Handle double bounce
+            // Handle double bounce, automated senders are treated as a null return path
             message.handle_double_bounce();
         }
     }
 
-    async fn log_dsn(&self, message: &Message) {
-        let now = now();
-
+    async fn log_dsn(&self, message: &Message, delayed: &[bool]) {
         for rcpt in &message.recipients {
             if rcpt.has_flag(RCPT_DSN_SENT) {
                 continue;
@@ -92,7 +105,7 @@ impl SendDsn for Server {
                         Details = response.response.message.to_string(),
                     );
                 }
-                Status::TemporaryFailure(response) if domain.notify.due <= now => {
+                Status::TemporaryFailure(response) if delayed[rcpt.domain_idx] => {
                     trc::event!(
                         Delivery(trc::DeliveryEvent::DsnTempFail),
                         SpanId = message.span_id,
@@ -128,7 +141,7 @@ impl SendDsn for Server {
                                 Total = domain.retry.inner,
                             );
                         }
-                        Status::TemporaryFailure(_) if domain.notify.due <= now => {
+                        Status::TemporaryFailure(_) if delayed[rcpt.domain_idx] => {
                             trc::event!(
                                 Delivery(trc::DeliveryEvent::DsnTempFail),
                                 SpanId = message.span_id,
@@ -139,7 +152,7 @@ impl SendDsn for Server {
                                 Total = domain.retry.inner,
                             );
                         }
-                        Status::Scheduled if domain.notify.due <= now => {
+                        Status::Scheduled if delayed[rcpt.domain_idx] => {
                             trc::event!(
                                 Delivery(trc::DeliveryEvent::DsnTempFail),
                                 SpanId = message.span_id,
@@ -160,10 +173,57 @@ impl SendDsn for Server {
 }
 
 impl Message {
-    pub async fn build_dsn(&mut self, server: &Server) -> Option<Vec<u8>> {
+    /// Returns, for each domain, whether a delayed DSN is due after applying
+    /// the sender's delayed DSN policy.
+    pub async fn delayed_dsn_domains(&self, server: &Server) -> Vec<bool> {
+        let config = &server.core.smtp.queue.dsn.delay;
+        let now = now();
+        let mut delayed = vec![false; self.domains.len()];
+
+        if !self.domains.iter().any(|domain| domain.is_delay_due(now)) {
+            return delayed;
+        }
+
+        let is_allowed = server
+            .eval_if::<bool, _>(&config.enable, self, self.span_id)
+            .await
+            .unwrap_or(true)
+            && server
+                .eval_if::<u64, _>(&config.max_count, self, self.span_id)
+                .await
+                .is_none_or(|max_count| self.delay_dsn_count() < max_count);
+
+        if is_allowed {
+            // When coalescing, all domains with temporary failures are reported in a single DSN
+            let coalesce = server
+                .eval_if::<bool, _>(&config.coalesce, self, self.span_id)
+                .await
+                .unwrap_or(false);
+
+            for (delayed, domain) in delayed.iter_mut().zip(&self.domains) {
+                *delayed = domain.is_delay_due(now)
+                    || (coalesce && matches!(domain.status, Status::TemporaryFailure(_)));
+            }
+        }
+
+        delayed
+    }
+
+    pub fn delay_dsn_count(&self) -> u64 {
+        self.flags >> MAIL_DELAY_DSN_SHIFT
+    }
+
+    pub async fn build_dsn(&mut self, server: &Server, delayed: &[bool]) -> Option<Vec<u8>> {
         let config = &server.core.smtp.queue;
         let now = now();
 
+        // Postpone delay notifications suppressed by the sender's policy
+        for (domain, delayed) in self.domains.iter_mut().zip(delayed) {
+            if !delayed && domain.is_delay_due(now) {
+                domain.notify.due = domain.expires + 10;
+            }
+        }
+
         let mut txt_success = String::new();
         let mut txt_delay = String::new();
         let mut txt_failed = String::new();
@@ -185,7 +245,7 @@ impl Message {
                     response.write_dsn_text(&rcpt.address, &mut txt_success);
                 }
                 Status::TemporaryFailure(response)
-                    if domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
+                    if delayed[rcpt.domain_idx] && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
                 {
                     rcpt.write_dsn(&mut dsn);
                     rcpt.status.write_dsn(&mut dsn);
@@ -214,7 +274,7 @@ impl Message {
                             err.write_dsn_text(&rcpt.address, &domain.domain, &mut txt_failed);
                         }
                         Status::TemporaryFailure(err)
-                            if domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
+                            if delayed[rcpt.domain_idx] && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
                         {
                             rcpt.write_dsn(&mut dsn);
                             domain.status.write_dsn(&mut dsn);
@@ -222,7 +282,7 @@ impl Message {
                             err.write_dsn_text(&rcpt.address, &domain.domain, &mut txt_delay);
                         }
                         Status::Scheduled
-                            if domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
+                            if delayed[rcpt.domain_idx] && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
                         {
                             // This case should not happen under normal circumstances
                             rcpt.write_dsn(&mut dsn);
@@ -257,69 +317,52 @@ impl Message {
         let has_delay = !txt_delay.is_empty();
         let has_failure = !txt_failed.is_empty();
 
-        let mut txt = String::with_capacity(txt_len + 128);
-        let (subject, is_mixed) = if has_success && !has_delay && !has_failure {
-            txt.push_str(
-                "Your message has been successfully delivered to the following recipients:\r\n\r\n",
-            );
-            ("Successfully delivered message", false)
+        let language = server
+            .recipient_language(&self.return_path_lcase, self.span_id)
+            .await;
+        let i18n = &server.core.i18n;
+        let (kind, is_mixed) = if has_success && !has_delay && !has_failure {
+            ("success", false)
         } else if has_delay && !has_success && !has_failure {
-            txt.push_str("There was a temporary problem delivering your message to the following recipients:\r\n\r\n");
-            ("Warning: Delay in message delivery", false)
+            ("delay", false)
         } else if has_failure && !has_success && !has_delay {
-            txt.push_str(
-                "Your message could not be delivered to the following recipients:\r\n\r\n",
-            );
-            ("Failed to deliver message", false)
+            ("failure", false)
         } else if has_success {
-            txt.push_str("Your message has been partially delivered:\r\n\r\n");
-            ("Partially delivered message", true)
+            ("partial", true)
         } else {
-            txt.push_str("Your message could not be delivered to some recipients:\r\n\r\n");
-            (
-                "Warning: Temporary and permanent failures during message delivery",
-                true,
-            )
+            ("mixed", true)
         };
+        let subject = i18n
+            .text(&language, &format!("dsn.subject.{kind}"))
+            .to_string();
 
-        if has_success {
-            if is_mixed {
-                txt.push_str(
-                    "    ----- Delivery to the following addresses was successful -----\r\n",
-                );
-            }
-
-            txt.push_str(&txt_success);
-            txt.push_str("\r\n");
-        }
-
-        if has_delay {
-            if is_mixed {
-                txt.push_str(
-                    "    ----- There was a temporary problem delivering to these addresses -----\r\n",
-                );
-            }
-            txt.push_str(&txt_delay);
-            txt.push_str("\r\n");
-        }
-
-        if has_failure {
-            if is_mixed {
-                txt.push_str("    ----- Delivery to the following addresses failed -----\r\n");
+        let mut txt = String::with_capacity(txt_len + 128);
+        txt.push_str(i18n.text(&language, &format!("dsn.body.{kind}")));
+        txt.push_str("\r\n\r\n");
+
+        for (kind, section) in [
+            ("success", &txt_success),
+            ("delay", &txt_delay),
+            ("failure", &txt_failed),
+        ] {
+            if !section.is_empty() {
+                if is_mixed {
+                    let _ = write!(
+                        txt,
+                        "    ----- {} -----\r\n",
+                        i18n.text(&language, &format!("dsn.section.{kind}"))
+                    );
+                }
+                txt.push_str(section);
+                txt.push_str("\r\n");
             }
-            txt.push_str(&txt_failed);
-            txt.push_str("\r\n");
         }
 
         // Update next delay notification time
         if has_delay {
             let mut changes = Vec::new();
             for (domain_idx, domain) in self.domains.iter().enumerate() {
-                if matches!(
-                    &domain.status,
-                    Status::TemporaryFailure(_) | Status::Scheduled
-                ) && domain.notify.due <= now
-                {
+                if delayed[domain_idx] {
                     let envelope = QueueEnvelope::new(self, domain_idx);
 
                     if let Some(next_notify) = server
@@ -341,6 +384,10 @@ impl Message {
                 domain.notify.inner += inner;
                 domain.notify.due = due;
             }
+
+            if self.delay_dsn_count() < u8::MAX as u64 {
+                self.flags += 1 << MAIL_DELAY_DSN_SHIFT;
+            }
         }
 
         // Obtain hostname and sender addresses
@@ -413,23 +460,31 @@ impl Message {
             }
         };
 
+        // Internationalized messages are reported using RFC 6533 types
+        let (status_type, headers_type) = if (self.flags & MAIL_SMTPUTF8) != 0 {
+            ("message/global-delivery-status", "message/global-headers")
+        } else {
+            ("message/delivery-status", "message/rfc822")
+        };
+
         // Build message
         MessageBuilder::new()
             .from((from_name.as_str(), from_addr.as_str()))
             .header("To", HeaderType::Text(self.return_path.as_str().into()))
             .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+            .header(
+                "X-Trace-Id",
+                HeaderType::Text(trace_id(self.queue_id).into()),
+            )
             .message_id(format!("<{}@{}>", make_boundary("."), reporting_mta))
             .subject(subject)
             .body(MimePart::new(
                 ContentType::new("multipart/report").attribute("report-type", "delivery-status"),
                 BodyPart::Multipart(vec![
                     MimePart::new(ContentType::new("text/plain"), BodyPart::Text(txt.into())),
+                    MimePart::new(ContentType::new(status_type), BodyPart::Text(dsn.into())),
                     MimePart::new(
-                        ContentType::new("message/delivery-status"),
-                        BodyPart::Text(dsn.into()),
-                    ),
-                    MimePart::new(
-                        ContentType::new("message/rfc822"),
+                        ContentType::new(headers_type),
                         BodyPart::Text(headers.into()),
                     ),
                 ]),
@@ -578,7 +633,9 @@ impl Message {
         dsn.push_str(&DateTime::from_timestamp(self.created as i64).to_rfc822());
         dsn.push_str("\r\n");
         if let Some(env_id) = &self.env_id {
-            let _ = write!(dsn, "Original-Envelope-Id: {env_id}\r\n");
+            dsn.push_str("Original-Envelope-Id: ");
+            write_xtext(dsn, env_id);
+            dsn.push_str("\r\n");
         }
         dsn.push_str("\r\n");
     }
@@ -587,13 +644,29 @@ impl Message {
 impl Recipient {
     fn write_dsn(&self, dsn: &mut String) {
         if let Some(orcpt) = &self.orcpt {
-            let _ = write!(dsn, "Original-Recipient: rfc822;{orcpt}\r\n");
+            dsn.push_str("Original-Recipient: rfc822;");
+            write_xtext(dsn, orcpt);
+            dsn.push_str("\r\n");
         }
-        let _ = write!(dsn, "Final-Recipient: rfc822;{}\r\n", self.address);
+        let _ = write!(
+            dsn,
+            "Final-Recipient: {};{}\r\n",
+            if self.address.is_ascii() {
+                "rfc822"
+            } else {
+                "utf-8"
+            },
+            self.address
+        );
     }
 }
 
 impl Domain {
+    fn is_delay_due(&self, now: u64) -> bool {
+        matches!(self.status, Status::TemporaryFailure(_) | Status::Scheduled)
+            && self.notify.due <= now
+    }
+
     fn write_dsn_will_retry_until(&self, dsn: &mut String) {
         let now = now();
         if self.expires > now {
@@ -767,3 +840,14 @@ trait WriteDsn {
     fn write_dsn_diagnostic(&self, dsn: &mut String);
     fn write_response(&self, dsn: &mut String);
 }
+
+/// Encodes ENVID and ORCPT values as xtext (RFC 3461), as they are stored decoded.
+pub(crate) fn write_xtext(buf: &mut String, value: &str) {
+    for byte in value.bytes() {
+        if (b'!'..=b'~').contains(&byte) && byte != b'+' && byte != b'=' {
+            buf.push(char::from(byte));
+        } else {
+            let _ = write!(buf, "+{byte:02X}");
+        }
+    }
+}
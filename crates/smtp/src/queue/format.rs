@@ -0,0 +1,179 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use store::{
+    write::{BatchBuilder, Bincode, QueueClass, ValueClass},
+    Deserialize, IterateParams, Serialize, ValueKey,
+};
+use trc::AddContext;
+
+use super::{spool::SmtpSpool, Message, QueueId};
+
+// Messages spooled before the format was versioned
+pub const SPOOL_VERSION_LEGACY: u8 = 0;
+pub const SPOOL_VERSION: u8 = 1;
+
+// Legacy entries start with the little-endian size of the uncompressed
+// message, which can never reach u32::MAX, so this prefix is unambiguous.
+const SPOOL_MAGIC: [u8; 4] = [0xFF; 4];
+
+pub struct SpooledMessage {
+    pub version: u8,
+    pub inner: Message,
+}
+
+#[derive(Debug, Default)]
+pub struct SpoolMigration {
+    pub total: u64,
+    pub migrated: u64,
+    pub locked: u64,
+    pub failed: u64,
+}
+
+pub trait SpoolMigrate: Sync + Send {
+    fn migrate_spool(&self) -> impl Future<Output = trc::Result<SpoolMigration>> + Send;
+}
+
+impl SpooledMessage {
+    pub fn new(inner: Message) -> Self {
+        Self {
+            version: SPOOL_VERSION,
+            inner,
+        }
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.version == SPOOL_VERSION
+    }
+}
+
+impl Serialize for SpooledMessage {
+    fn serialize(self) -> Vec<u8> {
+        let payload = Bincode::new(self.inner).serialize();
+        let mut bytes = Vec::with_capacity(SPOOL_MAGIC.len() + 1 + payload.len());
+        bytes.extend_from_slice(&SPOOL_MAGIC);
+        bytes.push(SPOOL_VERSION);
+        bytes.extend_from_slice(&payload);
+        bytes
+    }
+}
+
+impl Deserialize for SpooledMessage {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        let (version, payload) = match bytes.strip_prefix(SPOOL_MAGIC.as_slice()) {
+            Some([version, payload @ ..]) => (*version, payload),
+            Some([]) => {
+                return Err(trc::StoreEvent::DataCorruption
+                    .ctx(trc::Key::Value, bytes)
+                    .caused_by(trc::location!())
+                    .details("Missing spool format version"));
+            }
+            None => (SPOOL_VERSION_LEGACY, bytes),
+        };
+
+        // Future format changes should decode older layouts here and
+        // convert them into the current message structure.
+        match version {
+            SPOOL_VERSION_LEGACY | SPOOL_VERSION => {
+                Bincode::<Message>::deserialize(payload).map(|message| SpooledMessage {
+                    version,
+                    inner: message.inner,
+                })
+            }
+            _ => Err(trc::StoreEvent::DataCorruption
+                .ctx(trc::Key::Value, bytes)
+                .ctx(trc::Key::Version, version as u64)
+                .caused_by(trc::location!())
+                .details("Unsupported spool format version")),
+        }
+    }
+}
+
+impl SpoolMigrate for Server {
+    async fn migrate_spool(&self) -> trc::Result<SpoolMigration> {
+        let mut result = SpoolMigration::default();
+        let mut pending: Vec<QueueId> = Vec::new();
+
+        self.store()
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Queue(QueueClass::Message(0))),
+                    ValueKey::from(ValueClass::Queue(QueueClass::Message(u64::MAX))),
+                )
+                .ascending(),
+                |key, value| {
+                    result.total += 1;
+                    match SpooledMessage::deserialize(value) {
+                        Ok(message) if !message.is_current() => {
+                            pending.push(message.inner.queue_id);
+                        }
+                        Ok(_) => (),
+                        Err(err) => {
+                            result.failed += 1;
+                            trc::error!(err
+                                .ctx(trc::Key::Key, key)
+                                .details("Failed to decode spooled message"));
+                        }
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        for queue_id in pending {
+            // Avoid racing with a queue manager that is delivering the message
+            if !self.try_lock_event(queue_id).await {
+                result.locked += 1;
+                continue;
+            }
+
+            match self
+                .store()
+                .get_value::<SpooledMessage>(ValueKey::from(ValueClass::Queue(
+                    QueueClass::Message(queue_id),
+                )))
+                .await
+            {
+                Ok(Some(message)) if !message.is_current() => {
+                    let mut batch = BatchBuilder::new();
+                    batch.set(
+                        ValueClass::Queue(QueueClass::Message(queue_id)),
+                        SpooledMessage::new(message.inner).serialize(),
+                    );
+                    match self.store().write(batch.build()).await {
+                        Ok(_) => {
+                            result.migrated += 1;
+                        }
+                        Err(err) => {
+                            result.failed += 1;
+                            trc::error!(err
+                                .span_id(queue_id)
+                                .details("Failed to migrate spooled message")
+                                .caused_by(trc::location!()));
+                        }
+                    }
+                }
+                Ok(_) => (),
+                Err(err) => {
+                    result.failed += 1;
+                    trc::error!(err
+                        .span_id(queue_id)
+                        .details("Failed to read spooled message")
+                        .caused_by(trc::location!()));
+                }
+            }
+
+            self.unlock_event(queue_id).await;
+        }
+
+        Ok(result)
+    }
+}
@@ -22,6 +22,7 @@ use tokio::sync::mpsc;
 
 use super::{
     Message, QueueId, Status,
+    coordinator::QueueMembers,
     spool::{QUEUE_REFRESH, SmtpSpool},
 };
 
@@ -29,6 +30,7 @@ pub struct Queue {
     pub core: Arc<Inner>,
     pub on_hold: AHashMap<QueueId, OnHold>,
     pub next_wake_up: Instant,
+    pub members: QueueMembers,
     pub rx: mpsc::Receiver<QueueEvent>,
 }
 
@@ -58,6 +60,7 @@ const BACK_PRESSURE_WARN_INTERVAL: Duration = Duration::from_secs(60);
 impl Queue {
     pub fn new(core: Arc<Inner>, rx: mpsc::Receiver<QueueEvent>) -> Self {
         Queue {
+            members: QueueMembers::new(core.shared_core.load().network.node_id),
             core,
             on_hold: AHashMap::with_capacity(128),
             next_wake_up: Instant::now(),
@@ -67,13 +70,18 @@ impl Queue {
 
     pub async fn start(&mut self) {
         let mut is_paused = false;
+        let mut is_stopping = false;
         let mut next_cleanup = Instant::now() + CLEANUP_INTERVAL;
         let mut last_backpressure_warning = Instant::now() - BACK_PRESSURE_WARN_INTERVAL;
         let mut in_flight_count = 0;
         let mut has_back_pressure = false;
 
         loop {
-            let refresh_queue = match tokio::time::timeout(
+            // Wake up in time to renew the cluster membership
+            if !is_paused && !is_stopping && self.members.next_heartbeat < self.next_wake_up {
+                self.next_wake_up = self.members.next_heartbeat;
+            }
+            let mut refresh_queue = match tokio::time::timeout(
                 self.next_wake_up.duration_since(Instant::now()),
                 self.rx.recv(),
             )
@@ -82,6 +90,11 @@ impl Queue {
                 Ok(Some(QueueEvent::WorkerDone { queue_id, status })) => {
                     in_flight_count -= 1;
 
+                    // All in-flight deliveries completed after a stop request
+                    if is_stopping && in_flight_count == 0 {
+                        break;
+                    }
+
                     match status {
                         QueueEventStatus::Completed => {
                             self.on_hold.remove(&queue_id);
@@ -109,15 +122,37 @@ impl Queue {
                         .queue_status
                         .store(!paused, Ordering::Relaxed);
                     is_paused = paused;
+
+                    // Paused nodes hand over their messages to the rest of the cluster
+                    if paused {
+                        self.members.leave(&self.core.build_server()).await;
+                    }
                     false
                 }
                 Err(_) => true,
-                Ok(Some(QueueEvent::Stop)) | Ok(None) => {
+                Ok(Some(QueueEvent::Stop)) => {
+                    self.members.leave(&self.core.build_server()).await;
+                    if in_flight_count == 0 {
+                        break;
+                    }
+
+                    // Stop scheduling deliveries and wait for the in-flight ones
+                    is_stopping = true;
+                    false
+                }
+                Ok(None) => {
                     break;
                 }
             };
 
-            if !is_paused {
+            if !is_paused && !is_stopping {
+                // Renew the cluster membership, rebalancing messages when nodes join or leave
+                if self.members.is_heartbeat_due()
+                    && self.members.refresh(&self.core.build_server()).await
+                {
+                    refresh_queue = true;
+                }
+
                 // Deliver scheduled messages
                 if refresh_queue || self.next_wake_up <= Instant::now() {
                     // If the number of in-flight messages is greater than the maximum allowed, skip the queue
@@ -165,6 +200,11 @@ impl Queue {
                     }
 
                     for queue_event in &queue_events {
+                        // Messages owned by other nodes are delivered by them
+                        if !self.members.is_owner(queue_event.queue_id) {
+                            continue;
+                        }
+
                         if queue_event.due <= now {
                             // Enforce global concurrency limits
                             if in_flight_count >= max_in_flight {
@@ -261,7 +301,7 @@ impl Queue {
                     self.next_wake_up = now + Duration::from_secs(next_wake_up);
                 }
             } else {
-                // Queue is paused
+                // Queue is paused or stopping
                 self.next_wake_up = Instant::now() + Duration::from_secs(86400);
             }
         }
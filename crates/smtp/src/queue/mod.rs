@@ -16,10 +16,17 @@ use smtp_proto::Response;
 use store::write::now;
 use utils::BlobHash;
 
+pub mod admission;
+pub mod console;
+pub mod coordinator;
 pub mod dsn;
+pub mod format;
 pub mod manager;
+pub mod moderation;
 pub mod quota;
+pub mod shaping;
 pub mod spool;
+pub mod suppression;
 pub mod throttle;
 
 pub type QueueId = u64;
@@ -96,6 +103,9 @@ pub struct Recipient {
 pub const RCPT_DSN_SENT: u64 = 1 << 32;
 pub const RCPT_STATUS_CHANGED: u64 = 2 << 32;
 
+// Number of delayed DSNs sent for a message, kept in the upper bits of its flags
+pub const MAIL_DELAY_DSN_SHIFT: u64 = 56;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status<T, E> {
     #[serde(rename = "scheduled")]
@@ -325,6 +335,13 @@ impl<'x> ResolveVariable for RecipientDomain<'x> {
     }
 }
 
+/// Formats the trace id of a message, which is the queue id assigned at intake
+/// as it appears in the Received header.
+#[inline(always)]
+pub fn trace_id(queue_id: QueueId) -> String {
+    format!("{queue_id:X}")
+}
+
 #[inline(always)]
 pub fn instant_to_timestamp(now: Instant, time: Instant) -> u64 {
     SystemTime::now()
@@ -0,0 +1,338 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, time::Duration};
+
+use common::{Server, KV_LOCK_OUTBOUND_MODERATION, KV_OUTBOUND_MODERATION};
+use mail_builder::MessageBuilder;
+use store::{
+    dispatch::lookup::KeyValue,
+    write::{now, BatchBuilder, Bincode, BlobOp},
+    Serialize,
+};
+use trc::{AddContext, QueueEvent};
+use utils::BlobHash;
+
+use crate::reporting::SmtpReporting;
+
+use super::{quota::HasQueueQuota, Message, MessageSource};
+
+const MODERATION_KEY: &[u8] = b"outbound";
+const LOCK_EXPIRY: u64 = 10;
+const LOCK_RETRIES: usize = 10;
+
+/// A message submitted by a moderated account, waiting for an administrator
+/// to approve or reject it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HeldMessage {
+    pub message: Message,
+    pub account: String,
+    pub subject: String,
+    pub received: u64,
+    pub expires: u64,
+}
+
+pub trait OutboundModeration: Sync + Send {
+    fn hold_outbound_message(
+        &self,
+        message: Message,
+        account: &str,
+        subject: &str,
+        raw_headers: &[u8],
+        raw_message: &[u8],
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn held_outbound_messages(&self) -> impl Future<Output = trc::Result<Vec<HeldMessage>>> + Send;
+
+    fn release_outbound_message(
+        &self,
+        queue_id: u64,
+        approve: bool,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl OutboundModeration for Server {
+    async fn hold_outbound_message(
+        &self,
+        mut message: Message,
+        account: &str,
+        subject: &str,
+        raw_headers: &[u8],
+        raw_message: &[u8],
+        session_id: u64,
+    ) -> trc::Result<()> {
+        // Store the message until an administrator reviews it
+        let config = &self.core.smtp.session.data.moderation;
+        let received = now();
+        let expires = received + config.expiry.as_secs();
+        let mut raw = Vec::with_capacity(raw_headers.len() + raw_message.len());
+        raw.extend_from_slice(raw_headers);
+        raw.extend_from_slice(raw_message);
+        message.blob_hash = BlobHash::from(raw.as_slice());
+        message.size = raw.len();
+
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            BlobOp::Reserve {
+                hash: message.blob_hash.clone(),
+                until: expires,
+            },
+            0u32.serialize(),
+        );
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+        self.blob_store()
+            .put_blob(message.blob_hash.as_slice(), &raw)
+            .await
+            .caused_by(trc::location!())?;
+
+        let queue_id = message.queue_id;
+        let return_path = message.return_path.clone();
+        let max_held = config.max_held;
+        update_held_messages(self, |messages| {
+            if messages.len() < max_held {
+                messages.push(HeldMessage {
+                    message,
+                    account: account.to_string(),
+                    subject: subject.to_string(),
+                    received,
+                    expires,
+                });
+                Ok(())
+            } else {
+                Err(trc::LimitEvent::Quota
+                    .into_err()
+                    .details("Too many messages held for moderation"))
+            }
+        })
+        .await?;
+
+        trc::event!(
+            Queue(QueueEvent::ModerationHeld),
+            SpanId = session_id,
+            QueueId = queue_id,
+            AccountName = account.to_string(),
+            From = return_path,
+            Expires = trc::Value::Timestamp(expires),
+        );
+
+        Ok(())
+    }
+
+    async fn held_outbound_messages(&self) -> trc::Result<Vec<HeldMessage>> {
+        let now = now();
+        self.in_memory_store()
+            .key_get::<Bincode<Vec<HeldMessage>>>(KeyValue::<()>::build_key(
+                KV_OUTBOUND_MODERATION,
+                MODERATION_KEY,
+            ))
+            .await
+            .map(|messages| {
+                messages
+                    .map(|messages| messages.inner)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|held| held.expires > now)
+                    .collect()
+            })
+    }
+
+    async fn release_outbound_message(&self, queue_id: u64, approve: bool) -> trc::Result<bool> {
+        let mut held_message = None;
+        update_held_messages(self, |messages| {
+            if let Some(idx) = messages
+                .iter()
+                .position(|held| held.message.queue_id == queue_id)
+            {
+                held_message = Some(messages.swap_remove(idx));
+            }
+            Ok(())
+        })
+        .await?;
+        let Some(HeldMessage {
+            mut message,
+            account,
+            subject,
+            received,
+            expires,
+        }) = held_message
+        else {
+            return Ok(false);
+        };
+        let blob_hash = message.blob_hash.clone();
+
+        if approve {
+            let raw_message = self
+                .blob_store()
+                .get_blob(blob_hash.as_slice(), 0..usize::MAX)
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| {
+                    trc::StoreEvent::NotFound
+                        .into_err()
+                        .details("Held message blob not found")
+                        .caused_by(trc::location!())
+                })?;
+
+            // Delivery schedules start counting from the time of approval
+            let delay = now().saturating_sub(received);
+            for domain in &mut message.domains {
+                domain.retry.due += delay;
+                domain.notify.due += delay;
+                domain.expires += delay;
+            }
+
+            if !self.has_quota(&mut message).await {
+                return Err(trc::LimitEvent::Quota
+                    .into_err()
+                    .details("Queue quota exceeded")
+                    .ctx(trc::Key::QueueId, queue_id));
+            }
+
+            trc::event!(
+                Queue(QueueEvent::ModerationApproved),
+                QueueId = queue_id,
+                AccountName = account,
+                From = message.return_path.clone(),
+            );
+
+            if !message
+                .queue(None, &raw_message, 0, self, MessageSource::Authenticated)
+                .await
+            {
+                return Err(trc::StoreEvent::UnexpectedError
+                    .into_err()
+                    .details("Failed to queue held message")
+                    .caused_by(trc::location!()));
+            }
+        } else {
+            trc::event!(
+                Queue(QueueEvent::ModerationRejected),
+                QueueId = queue_id,
+                AccountName = account,
+                From = message.return_path.clone(),
+            );
+
+            // Let the sender know that the message will not be delivered
+            if !message.return_path.is_empty() {
+                let config = &self.core.smtp.queue;
+                let from_name = self
+                    .eval_if(&config.dsn.name, &message, 0)
+                    .await
+                    .unwrap_or_else(|| String::from("Mail Delivery Subsystem"));
+                let from_addr = self
+                    .eval_if(&config.dsn.address, &message, 0)
+                    .await
+                    .unwrap_or_else(|| String::from("MAILER-DAEMON@localhost"));
+                let notice = MessageBuilder::new()
+                    .from((from_name.as_str(), from_addr.as_str()))
+                    .to(message.return_path.as_str())
+                    .header(
+                        "Auto-Submitted",
+                        mail_builder::headers::HeaderType::Text("auto-replied".into()),
+                    )
+                    .subject(format!("Message not delivered: {subject}"))
+                    .text_body(format!(
+                        concat!(
+                            "Your message \"{}\" was rejected by an administrator ",
+                            "and has not been delivered to any of its recipients.\r\n"
+                        ),
+                        subject
+                    ))
+                    .write_to_vec()
+                    .unwrap_or_default();
+                self.send_autogenerated(
+                    from_addr,
+                    [message.return_path.as_str()].into_iter(),
+                    notice,
+                    Some(&config.dsn.sign),
+                    0,
+                )
+                .await;
+            }
+        }
+
+        // Release the blob reservation
+        let mut batch = BatchBuilder::new();
+        batch.clear(BlobOp::Reserve {
+            hash: blob_hash,
+            until: expires,
+        });
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(true)
+    }
+}
+
+async fn update_held_messages(
+    server: &Server,
+    f: impl FnOnce(&mut Vec<HeldMessage>) -> trc::Result<()> + Send,
+) -> trc::Result<()> {
+    let store = server.in_memory_store();
+    let mut is_locked = false;
+    for _ in 0..LOCK_RETRIES {
+        if store
+            .try_lock(KV_LOCK_OUTBOUND_MODERATION, MODERATION_KEY, LOCK_EXPIRY)
+            .await?
+        {
+            is_locked = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    if !is_locked {
+        return Err(trc::StoreEvent::UnexpectedError
+            .into_err()
+            .details("Failed to lock moderation queue")
+            .caused_by(trc::location!()));
+    }
+
+    let result = match server.held_outbound_messages().await {
+        Ok(mut messages) => match f(&mut messages) {
+            Ok(_) if !messages.is_empty() => {
+                // The index expires along with the last held message
+                let expires = messages
+                    .iter()
+                    .map(|held| held.expires)
+                    .max()
+                    .unwrap_or_default()
+                    .saturating_sub(now());
+                store
+                    .key_set(
+                        KeyValue::with_prefix(
+                            KV_OUTBOUND_MODERATION,
+                            MODERATION_KEY,
+                            Bincode::new(messages).serialize(),
+                        )
+                        .expires(expires),
+                    )
+                    .await
+                    .caused_by(trc::location!())
+            }
+            Ok(_) => store
+                .key_delete(KeyValue::<()>::build_key(
+                    KV_OUTBOUND_MODERATION,
+                    MODERATION_KEY,
+                ))
+                .await
+                .caused_by(trc::location!()),
+            Err(err) => Err(err),
+        },
+        Err(err) => Err(err),
+    };
+
+    store
+        .remove_lock(KV_LOCK_OUTBOUND_MODERATION, MODERATION_KEY)
+        .await?;
+
+    result
+}
@@ -0,0 +1,227 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{
+    config::smtp::queue::{QueueShaper, ShapingTarget, TokenBucket},
+    Server, KV_QUEUE_SHAPING,
+};
+use store::{dispatch::lookup::KeyValue, write::now, Deserialize, Serialize};
+use trc::AddContext;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapingStatus {
+    pub id: String,
+    pub target: String,
+    pub hosts: Vec<String>,
+    pub requests: u64,
+    pub period: u64,
+    pub burst: u64,
+    pub remaining: u64,
+    pub next_refill: Option<u64>,
+}
+
+pub trait QueueRateShaping: Sync + Send {
+    fn shaping_status(
+        &self,
+        shaper: &QueueShaper,
+    ) -> impl Future<Output = trc::Result<ShapingStatus>> + Send;
+
+    fn shaping_take(
+        &self,
+        target: ShapingTarget,
+        host: &str,
+        session_id: u64,
+    ) -> impl Future<Output = Result<bool, u64>> + Send;
+}
+
+impl QueueRateShaping for Server {
+    async fn shaping_status(&self, shaper: &QueueShaper) -> trc::Result<ShapingStatus> {
+        load_bucket(self, shaper).await?;
+
+        let now = now();
+        let mut bucket = self
+            .inner
+            .data
+            .queue_shaping
+            .lock()
+            .get(&shaper.id)
+            .copied()
+            .unwrap_or_else(|| shaper.new_bucket(now));
+        shaper.refill(&mut bucket, now);
+        let remaining = shaper.remaining(&bucket);
+
+        Ok(ShapingStatus {
+            id: shaper.id.clone(),
+            target: shaper.target.as_str().to_string(),
+            hosts: shaper.hosts.clone(),
+            requests: shaper.rate.requests,
+            period: shaper.rate.period.as_secs(),
+            burst: shaper.burst,
+            remaining,
+            next_refill: (remaining < shaper.burst).then(|| now + shaper.next_token(&bucket)),
+        })
+    }
+
+    /// Takes a token from every bucket matching the recipient domain or MX host,
+    /// returning whether any matched or when to retry if one is exhausted.
+    async fn shaping_take(
+        &self,
+        target: ShapingTarget,
+        host: &str,
+        session_id: u64,
+    ) -> Result<bool, u64> {
+        let shapers = self
+            .core
+            .smtp
+            .queue
+            .shaping
+            .iter()
+            .filter(|shaper| shaper.matches(target, host))
+            .collect::<Vec<_>>();
+        if shapers.is_empty() {
+            return Ok(false);
+        }
+        for shaper in &shapers {
+            if let Err(err) = load_bucket(self, shaper).await {
+                trc::error!(err.span_id(session_id));
+            }
+        }
+
+        // Tokens are only taken if all buckets have one available
+        let now = now();
+        let mut exhausted = Vec::new();
+        let mut updated = Vec::with_capacity(shapers.len());
+        {
+            let mut buckets = self.inner.data.queue_shaping.lock();
+            for shaper in &shapers {
+                let bucket = buckets
+                    .entry(shaper.id.clone())
+                    .or_insert_with(|| shaper.new_bucket(now));
+                shaper.refill(bucket, now);
+                if shaper.remaining(bucket) == 0 {
+                    exhausted.push((*shaper, now + shaper.next_token(bucket)));
+                }
+            }
+            if exhausted.is_empty() {
+                for shaper in &shapers {
+                    if let Some(bucket) = buckets.get_mut(&shaper.id) {
+                        shaper.take(bucket);
+                        updated.push((*shaper, *bucket));
+                    }
+                }
+            }
+        }
+
+        if !exhausted.is_empty() {
+            for (shaper, _) in &exhausted {
+                trc::event!(
+                    Queue(trc::QueueEvent::RateLimitExceeded),
+                    SpanId = session_id,
+                    Id = shaper.id.clone(),
+                    Limit = vec![
+                        trc::Value::from(shaper.rate.requests),
+                        trc::Value::from(shaper.rate.period)
+                    ],
+                );
+            }
+
+            return Err(exhausted
+                .into_iter()
+                .map(|(_, retry_at)| retry_at)
+                .max()
+                .unwrap_or(now));
+        }
+
+        // Persist the buckets so that budgets survive restarts
+        for (shaper, bucket) in updated {
+            if let Err(err) = self
+                .in_memory_store()
+                .key_set(
+                    KeyValue::with_prefix(
+                        KV_QUEUE_SHAPING,
+                        shaper.id.as_bytes(),
+                        PersistedBucket(bucket).serialize(),
+                    )
+                    .expires(shaper.fill_time()),
+                )
+                .await
+            {
+                trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Loads the persisted state of a bucket the first time it is used.
+async fn load_bucket(server: &Server, shaper: &QueueShaper) -> trc::Result<()> {
+    if server
+        .inner
+        .data
+        .queue_shaping
+        .lock()
+        .contains_key(&shaper.id)
+    {
+        return Ok(());
+    }
+
+    if let Some(PersistedBucket(bucket)) = server
+        .in_memory_store()
+        .key_get::<PersistedBucket>(KeyValue::<()>::build_key(
+            KV_QUEUE_SHAPING,
+            shaper.id.as_bytes(),
+        ))
+        .await
+        .caused_by(trc::location!())?
+    {
+        server
+            .inner
+            .data
+            .queue_shaping
+            .lock()
+            .entry(shaper.id.clone())
+            .or_insert(bucket);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct PersistedBucket(TokenBucket);
+
+impl Serialize for PersistedBucket {
+    fn serialize(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(16);
+        buf.extend_from_slice(&self.0.tokens.to_be_bytes());
+        buf.extend_from_slice(&self.0.updated.to_be_bytes());
+        buf
+    }
+}
+
+impl Deserialize for PersistedBucket {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        if bytes.len() == 16 {
+            Ok(PersistedBucket(TokenBucket {
+                tokens: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+                updated: u64::from_be_bytes(bytes[8..16].try_into().unwrap()),
+            }))
+        } else {
+            Err(trc::StoreEvent::DataCorruption
+                .caused_by(trc::location!())
+                .ctx(trc::Key::Value, bytes))
+        }
+    }
+}
+
+impl From<store::Value<'_>> for PersistedBucket {
+    fn from(_: store::Value<'_>) -> Self {
+        unimplemented!()
+    }
+}
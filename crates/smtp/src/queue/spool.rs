@@ -5,20 +5,20 @@
  */
 
 use crate::queue::DomainPart;
-use common::ipc::QueueEvent;
+use common::ipc::{BackPressureSender, QueueEvent};
 use common::{Server, KV_LOCK_QUEUE_MESSAGE};
 use std::borrow::Cow;
 use std::future::Future;
 use std::time::{Duration, SystemTime};
 use store::write::key::DeserializeBigEndian;
-use store::write::{now, BatchBuilder, Bincode, BlobOp, QueueClass, ValueClass};
+use store::write::{now, BatchBuilder, BlobOp, QueueClass, ValueClass};
 use store::{IterateParams, Serialize, ValueKey, U64_LEN};
 use trc::ServerEvent;
 use utils::BlobHash;
 
 use super::{
-    Domain, Message, MessageSource, QueueEnvelope, QueueId, QueuedMessage, QuotaKey, Recipient,
-    Schedule, Status,
+    format::SpooledMessage, trace_id, Domain, ErrorDetails, HostResponse, Message, MessageSource,
+    QueueEnvelope, QueueId, QueuedMessage, QuotaKey, Recipient, Schedule, Status,
 };
 
 pub const LOCK_EXPIRY: u64 = 300;
@@ -148,9 +148,7 @@ impl SmtpSpool for Server {
     async fn read_message(&self, id: QueueId) -> Option<Message> {
         match self
             .store()
-            .get_value::<Bincode<Message>>(ValueKey::from(ValueClass::Queue(QueueClass::Message(
-                id,
-            ))))
+            .get_value::<SpooledMessage>(ValueKey::from(ValueClass::Queue(QueueClass::Message(id))))
             .await
         {
             Ok(Some(message)) => Some(message.inner),
@@ -232,6 +230,7 @@ impl Message {
             }),
             SpanId = session_id,
             QueueId = self.queue_id,
+            TraceId = trace_id(self.queue_id),
             From = if !self.return_path.is_empty() {
                 trc::Value::String(self.return_path.to_string())
             } else {
@@ -242,6 +241,7 @@ impl Message {
                 .iter()
                 .map(|r| trc::Value::String(r.address_lcase.clone()))
                 .collect::<Vec<_>>(),
+            EnvId = self.env_id.clone(),
             Size = self.size,
             NextRetry = trc::Value::Timestamp(self.next_delivery_event()),
             NextDsn = trc::Value::Timestamp(self.next_dsn()),
@@ -292,7 +292,7 @@ impl Message {
             )
             .set(
                 ValueClass::Queue(QueueClass::Message(self.queue_id)),
-                Bincode::new(self).serialize(),
+                SpooledMessage::new(self).serialize(),
             );
 
         if let Err(err) = server.store().write(batch.build()).await {
@@ -305,13 +305,12 @@ impl Message {
         }
 
         // Queue the message
-        if server
+        if !server
             .inner
             .ipc
             .queue_tx
-            .send(QueueEvent::Refresh)
+            .send_or_wait("queue", QueueEvent::Refresh)
             .await
-            .is_err()
         {
             trc::event!(
                 Server(ServerEvent::ThreadError),
@@ -413,7 +412,7 @@ impl Message {
         let span_id = self.span_id;
         batch.set(
             ValueClass::Queue(QueueClass::Message(self.queue_id)),
-            Bincode::new(self).serialize(),
+            SpooledMessage::new(self).serialize(),
         );
 
         if let Err(err) = server.store().write(batch.build()).await {
@@ -469,6 +468,85 @@ impl Message {
         }
     }
 
+    pub fn reschedule(&mut self, time: u64, domain_filter: Option<&str>) -> bool {
+        let mut found = false;
+
+        for domain in &mut self.domains {
+            if matches!(
+                domain.status,
+                Status::Scheduled | Status::TemporaryFailure(_)
+            ) && domain_filter.is_none_or(|filter| domain.domain.contains(filter))
+            {
+                domain.retry.due = time;
+                if domain.expires > time {
+                    domain.expires = time + 10;
+                }
+                found = true;
+            }
+        }
+
+        found
+    }
+
+    pub fn cancel_recipients(&mut self, rcpt_filter: &str) -> bool {
+        let mut found = false;
+
+        // Cancel delivery for all recipients that match
+        for rcpt in &mut self.recipients {
+            if rcpt.address_lcase.contains(rcpt_filter) {
+                rcpt.status = Status::PermanentFailure(HostResponse {
+                    hostname: ErrorDetails::default(),
+                    response: smtp_proto::Response {
+                        code: 0,
+                        esc: [0, 0, 0],
+                        message: "Delivery canceled.".to_string(),
+                    },
+                });
+                found = true;
+            }
+        }
+
+        if found {
+            // Mark as completed domains without any pending deliveries
+            for (domain_idx, domain) in self.domains.iter_mut().enumerate() {
+                if matches!(
+                    domain.status,
+                    Status::TemporaryFailure(_) | Status::Scheduled
+                ) {
+                    let mut total_rcpt = 0;
+                    let mut total_completed = 0;
+
+                    for rcpt in &self.recipients {
+                        if rcpt.domain_idx == domain_idx {
+                            total_rcpt += 1;
+                            if matches!(
+                                rcpt.status,
+                                Status::PermanentFailure(_) | Status::Completed(_)
+                            ) {
+                                total_completed += 1;
+                            }
+                        }
+                    }
+
+                    if total_rcpt == total_completed {
+                        domain.status = Status::Completed(());
+                    }
+                }
+            }
+        }
+
+        found
+    }
+
+    pub fn has_pending_domains(&self) -> bool {
+        self.domains.iter().any(|domain| {
+            matches!(
+                domain.status,
+                Status::TemporaryFailure(_) | Status::Scheduled
+            )
+        })
+    }
+
     pub fn has_domain(&self, domains: &[String]) -> bool {
         self.domains.iter().any(|d| domains.contains(&d.domain))
             || self
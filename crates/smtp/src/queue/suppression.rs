@@ -0,0 +1,177 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{Server, KV_SUPPRESSION};
+use smtp_proto::Response;
+use store::dispatch::lookup::KeyValue;
+use trc::AddContext;
+
+use super::{Message, Status, RCPT_DSN_SENT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SuppressionReason {
+    Bounce,
+    Complaint,
+    Manual,
+}
+
+pub trait SuppressionList: Sync + Send {
+    fn is_suppressed(
+        &self,
+        sender: &str,
+        rcpt: &str,
+    ) -> impl Future<Output = trc::Result<Option<SuppressionReason>>> + Send;
+
+    fn suppress(
+        &self,
+        sender: &str,
+        rcpt: &str,
+        reason: SuppressionReason,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn unsuppress(&self, sender: &str, rcpt: &str) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn suppress_hard_bounces(&self, message: &Message) -> impl Future<Output = ()> + Send;
+}
+
+impl SuppressionList for Server {
+    async fn is_suppressed(
+        &self,
+        sender: &str,
+        rcpt: &str,
+    ) -> trc::Result<Option<SuppressionReason>> {
+        self.in_memory_store()
+            .key_get::<i64>(KeyValue::<()>::build_key(
+                KV_SUPPRESSION,
+                suppression_key(sender, rcpt),
+            ))
+            .await
+            .caused_by(trc::location!())
+            .map(|reason| reason.map(SuppressionReason::from_id))
+    }
+
+    async fn suppress(
+        &self,
+        sender: &str,
+        rcpt: &str,
+        reason: SuppressionReason,
+    ) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_SUPPRESSION,
+                    suppression_key(sender, rcpt),
+                    reason.id().to_be_bytes().to_vec(),
+                )
+                .expires_opt(
+                    self.core
+                        .smtp
+                        .queue
+                        .suppression
+                        .expiry
+                        .map(|expiry| expiry.as_secs()),
+                ),
+            )
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn unsuppress(&self, sender: &str, rcpt: &str) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_delete(KeyValue::<()>::build_key(
+                KV_SUPPRESSION,
+                suppression_key(sender, rcpt),
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn suppress_hard_bounces(&self, message: &Message) {
+        if !self.core.smtp.queue.suppression.enable || message.return_path.is_empty() {
+            return;
+        }
+
+        for rcpt in &message.recipients {
+            if rcpt.has_flag(RCPT_DSN_SENT) {
+                continue;
+            }
+
+            if let Status::PermanentFailure(err) = &rcpt.status
+                && is_hard_bounce(&err.response)
+            {
+                if let Err(err) = self
+                    .suppress(
+                        &message.return_path_lcase,
+                        &rcpt.address_lcase,
+                        SuppressionReason::Bounce,
+                    )
+                    .await
+                {
+                    trc::error!(err.span_id(message.span_id));
+                    continue;
+                }
+
+                trc::event!(
+                    Queue(trc::QueueEvent::RecipientSuppressed),
+                    SpanId = message.span_id,
+                    From = message.return_path_lcase.clone(),
+                    To = rcpt.address_lcase.clone(),
+                    Code = err.response.code,
+                    Details = err.response.message.clone(),
+                );
+            }
+        }
+    }
+}
+
+impl SuppressionReason {
+    fn id(&self) -> i64 {
+        match self {
+            SuppressionReason::Bounce => 0,
+            SuppressionReason::Complaint => 1,
+            SuppressionReason::Manual => 2,
+        }
+    }
+
+    fn from_id(id: i64) -> Self {
+        match id {
+            0 => SuppressionReason::Bounce,
+            1 => SuppressionReason::Complaint,
+            _ => SuppressionReason::Manual,
+        }
+    }
+}
+
+fn suppression_key(sender: &str, rcpt: &str) -> Vec<u8> {
+    // VERP and subaddressed senders share the list of the base address
+    let sender = sender.to_lowercase();
+    let sender = match sender
+        .rsplit_once('@')
+        .filter(|(local, _)| !local.starts_with("srs"))
+        .and_then(|(local, domain)| Some((local.split_once('+')?.0, domain)))
+    {
+        Some((local, domain)) => format!("{local}@{domain}"),
+        None => sender,
+    };
+    let mut key = Vec::with_capacity(sender.len() + rcpt.len() + 1);
+    key.extend_from_slice(sender.as_bytes());
+    key.push(0);
+    key.extend_from_slice(rcpt.to_lowercase().as_bytes());
+    key
+}
+
+/// Returns true when the remote server rejected the recipient address itself,
+/// as opposed to policy or content related failures.
+pub fn is_hard_bounce(response: &Response<String>) -> bool {
+    match response.esc {
+        [5, 1, _] | [5, 2, 1] => true,
+        [0, 0, 0] => matches!(response.code, 550 | 551 | 553),
+        _ => false,
+    }
+}
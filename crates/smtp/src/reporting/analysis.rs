@@ -7,6 +7,7 @@
 use std::{
     borrow::Cow,
     collections::hash_map::Entry,
+    future::Future,
     io::{Cursor, Read},
 };
 
@@ -17,7 +18,7 @@ use mail_auth::{
     report::{tlsrpt::TlsReport, ActionDisposition, DmarcResult, Feedback, Report},
     zip,
 };
-use mail_parser::{Message, MimeHeaders, PartType};
+use mail_parser::{Message, MessageParser, MimeHeaders, PartType};
 
 use store::{
     write::{now, BatchBuilder, Bincode, ReportClass, ValueClass},
@@ -25,6 +26,8 @@ use store::{
 };
 use trc::IncomingReportEvent;
 
+use super::feedback::FeedbackLoop;
+
 enum Compression {
     None,
     Gzip,
@@ -52,13 +55,39 @@ pub struct IncomingReport<T> {
 }
 
 pub trait AnalyzeReport: Sync + Send {
-    fn analyze_report(&self, message: Message<'static>, session_id: u64);
+    fn analyze_report(
+        &self,
+        message: Message<'static>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
 }
 
 impl AnalyzeReport for Server {
-    fn analyze_report(&self, message: Message<'static>, session_id: u64) {
+    async fn analyze_report(&self, message: Message<'static>, session_id: u64) {
+        // Wait for an analysis worker to become available
+        let permit = match self
+            .inner
+            .ipc
+            .report_analysis_sm
+            .clone()
+            .acquire_owned()
+            .await
+        {
+            Ok(permit) => permit,
+            Err(_) => {
+                trc::error!(
+                    trc::Error::new(trc::EventType::Server(trc::ServerEvent::ThreadError))
+                        .details("Failed to obtain semaphore permit.")
+                        .span_id(session_id)
+                        .caused_by(trc::location!())
+                );
+                return;
+            }
+        };
+
         let core = self.clone();
         tokio::spawn(async move {
+            let _permit = permit;
             let from = message
                 .from()
                 .and_then(|a| a.last())
@@ -150,6 +179,17 @@ impl AnalyzeReport for Server {
                 }
             }
 
+            // Obtain the original message included in feedback reports
+            let original = message.parts.iter().find_map(|part| match &part.body {
+                PartType::Message(message) => Some(Cow::Borrowed(message)),
+                PartType::Text(headers) if part.is_content_type("text", "rfc822-headers") => {
+                    MessageParser::new()
+                        .parse_headers(headers.as_bytes())
+                        .map(Cow::Owned)
+                }
+                _ => None,
+            });
+
             for report in reports {
                 let data = match report.compression {
                     Compression::None => Cow::Borrowed(report.data),
@@ -256,7 +296,16 @@ impl AnalyzeReport for Server {
                         Some(report) => {
                             // Log
                             report.log();
-                            Format::Arf(report.into_owned())
+
+                            // Correlate complaints with outbound messages
+                            Format::Arf(
+                                core.handle_complaint(
+                                    report.into_owned(),
+                                    original.as_deref(),
+                                    session_id,
+                                )
+                                .await,
+                            )
                         }
                         None => {
                             trc::event!(
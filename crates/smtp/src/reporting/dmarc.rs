@@ -33,7 +33,10 @@ use crate::{
     reporting::SmtpReporting,
 };
 
-use super::{AggregateTimestamp, SerializedSize};
+use super::{
+    scheduler::{AggregateReportType, ReportSchedule},
+    AggregateTimestamp, SerializedSize,
+};
 
 #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct DmarcFormat {
@@ -256,17 +259,27 @@ impl<T: SessionStream> Session<T> {
         }
 
         // Send aggregate reports
-        let interval = self
+        if dmarc_record.rua().is_empty() {
+            return;
+        }
+        let interval = match self
             .server
-            .eval_if(
-                &self.server.core.smtp.report.dmarc_aggregate.send,
-                self,
-                self.data.session_id,
-            )
+            .report_interval(AggregateReportType::Dmarc, dmarc_output.domain())
             .await
-            .unwrap_or(AggregateFrequency::Never);
+        {
+            Some(interval) => interval,
+            None => self
+                .server
+                .eval_if(
+                    &self.server.core.smtp.report.dmarc_aggregate.send,
+                    self,
+                    self.data.session_id,
+                )
+                .await
+                .unwrap_or(AggregateFrequency::Never),
+        };
 
-        if matches!(interval, AggregateFrequency::Never) || dmarc_record.rua().is_empty() {
+        if matches!(interval, AggregateFrequency::Never) {
             return;
         }
 
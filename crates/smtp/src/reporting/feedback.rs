@@ -0,0 +1,175 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use mail_auth::report::{Feedback, FeedbackType};
+use mail_parser::Message;
+use utils::sanitize_email;
+
+use crate::queue::{
+    spool::SmtpSpool,
+    suppression::{SuppressionList, SuppressionReason},
+    QueueId,
+};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ComplaintSource {
+    pub sender: Option<String>,
+    pub rcpt: Option<String>,
+    pub queue_id: Option<QueueId>,
+}
+
+pub trait FeedbackLoop: Sync + Send {
+    fn handle_complaint(
+        &self,
+        feedback: Feedback<'static>,
+        original: Option<&Message<'_>>,
+        session_id: u64,
+    ) -> impl Future<Output = Feedback<'static>> + Send;
+}
+
+impl FeedbackLoop for Server {
+    async fn handle_complaint(
+        &self,
+        mut feedback: Feedback<'static>,
+        original: Option<&Message<'_>>,
+        session_id: u64,
+    ) -> Feedback<'static> {
+        let mut source = correlate_complaint(&feedback, original);
+
+        // Obtain the envelope from the queue if the message is still there
+        if let Some(queue_id) = source.queue_id
+            && (source.sender.is_none() || source.rcpt.is_none())
+            && let Some(message) = self.read_message(queue_id).await
+        {
+            if source.sender.is_none() && !message.return_path_lcase.is_empty() {
+                source.sender = Some(message.return_path_lcase);
+            }
+            if source.rcpt.is_none()
+                && let [rcpt] = message.recipients.as_slice()
+            {
+                source.rcpt = Some(rcpt.address_lcase.clone());
+            }
+        }
+
+        // Keep the correlated envelope with the stored report
+        if feedback.original_mail_from().is_none()
+            && let Some(sender) = &source.sender
+        {
+            feedback = feedback.with_original_mail_from(sender.clone());
+        }
+        if feedback.original_rcpt_to().is_none()
+            && let Some(rcpt) = &source.rcpt
+        {
+            feedback = feedback.with_original_rcpt_to(rcpt.clone());
+        }
+
+        // Stop sending messages to recipients that complained
+        if self.core.smtp.queue.suppression.enable
+            && matches!(feedback.feedback_type(), FeedbackType::Abuse)
+            && let (Some(sender), Some(rcpt)) = (&source.sender, &source.rcpt)
+        {
+            match self
+                .suppress(sender, rcpt, SuppressionReason::Complaint)
+                .await
+            {
+                Ok(_) => {
+                    trc::event!(
+                        Queue(trc::QueueEvent::RecipientSuppressed),
+                        SpanId = session_id,
+                        QueueId = source.queue_id,
+                        From = sender.to_string(),
+                        To = rcpt.to_string(),
+                        Details = "Abuse report",
+                    );
+                }
+                Err(err) => {
+                    trc::error!(err.span_id(session_id));
+                }
+            }
+        }
+
+        feedback
+    }
+}
+
+/// Obtains the envelope of the message a complaint refers to, using the report
+/// fields, VERP encoded senders and the headers of the original message.
+pub fn correlate_complaint(
+    feedback: &Feedback<'_>,
+    original: Option<&Message<'_>>,
+) -> ComplaintSource {
+    let mut source = ComplaintSource {
+        sender: feedback.original_mail_from().and_then(parse_address),
+        rcpt: feedback.original_rcpt_to().and_then(parse_address),
+        queue_id: None,
+    };
+
+    if let Some(original) = original
+        && source.sender.is_none()
+    {
+        source.sender = original.return_path().as_text().and_then(parse_address);
+    }
+
+    // Decode VERP senders
+    if let Some((sender, rcpt)) = source.sender.as_deref().and_then(decode_verp) {
+        if source.rcpt.is_none() {
+            source.rcpt = Some(rcpt);
+        }
+        source.sender = Some(sender);
+    }
+
+    if let Some(original) = original {
+        if source.rcpt.is_none()
+            && let Some(to) = original.to()
+            && to.iter().count() == 1
+        {
+            source.rcpt = to
+                .first()
+                .and_then(|addr| addr.address())
+                .and_then(parse_address);
+        }
+
+        // Find the queue id in the Received header added by this server
+        source.queue_id = original
+            .headers_raw()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Received"))
+            .find_map(|(_, value)| parse_received_id(value));
+    }
+
+    source
+}
+
+/// Decodes a VERP address such as `bounces+jane=example.org@sender.org`,
+/// returning the sender and the recipient addresses.
+pub fn decode_verp(address: &str) -> Option<(String, String)> {
+    let (local, domain) = address.rsplit_once('@')?;
+    let (local, verp) = local.split_once('+')?;
+    let (rcpt_local, rcpt_domain) = verp.rsplit_once('=')?;
+    if local.is_empty() || rcpt_local.is_empty() || !rcpt_domain.contains('.') {
+        return None;
+    }
+
+    Some((
+        format!("{local}@{domain}"),
+        sanitize_email(&format!("{rcpt_local}@{rcpt_domain}"))?,
+    ))
+}
+
+fn parse_received_id(value: &str) -> Option<QueueId> {
+    if !value.contains("(Stalwart SMTP)") {
+        return None;
+    }
+    let (_, id) = value.rsplit_once(" id ")?;
+    let id = id.split([';', ' ', '\r', '\n', '\t']).next()?;
+    QueueId::from_str_radix(id, 16).ok()
+}
+
+fn parse_address(address: &str) -> Option<String> {
+    sanitize_email(address.trim().trim_start_matches('<').trim_end_matches('>'))
+}
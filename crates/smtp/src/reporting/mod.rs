@@ -9,7 +9,7 @@ use std::{future::Future, io, time::SystemTime};
 use common::{
     config::smtp::report::{AddressMatch, AggregateFrequency},
     expr::if_block::IfBlock,
-    ipc::ReportingEvent,
+    ipc::{BackPressureSender, ReportingEvent},
     Server, USER_AGENT,
 };
 use mail_auth::{
@@ -30,6 +30,7 @@ use crate::{
 pub mod analysis;
 pub mod dkim;
 pub mod dmarc;
+pub mod feedback;
 pub mod scheduler;
 pub mod spf;
 pub mod tls;
@@ -54,7 +55,8 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
     }
 
     pub fn is_report(&self) -> bool {
-        for addr_match in &self.server.core.smtp.report.analysis.addresses {
+        let analysis = &self.server.core.smtp.report.analysis;
+        for addr_match in analysis.addresses.iter().chain(&analysis.feedback_loop) {
             for addr in &self.data.rcpt_to {
                 match addr_match {
                     AddressMatch::StartsWith(prefix) if addr.address_lcase.starts_with(prefix) => {
@@ -202,7 +204,13 @@ impl SmtpReporting for Server {
     }
 
     async fn schedule_report(&self, report: impl Into<ReportingEvent> + Sync + Send) {
-        if self.inner.ipc.report_tx.send(report.into()).await.is_err() {
+        if !self
+            .inner
+            .ipc
+            .report_tx
+            .send_or_wait("report", report.into())
+            .await
+        {
             trc::event!(
                 Server(trc::ServerEvent::ThreadError),
                 CausedBy = trc::location!(),
@@ -5,7 +5,10 @@
  */
 
 use ahash::AHashMap;
-use common::{core::BuildServer, ipc::ReportingEvent, Inner, Server, KV_LOCK_QUEUE_REPORT};
+use common::{
+    config::smtp::report::AggregateFrequency, core::BuildServer, ipc::ReportingEvent, Inner,
+    Server, KV_LOCK_QUEUE_REPORT, KV_REPORT_INTERVAL,
+};
 
 use std::{
     future::Future,
@@ -13,10 +16,12 @@ use std::{
     time::{Duration, SystemTime},
 };
 use store::{
+    dispatch::lookup::KeyValue,
     write::{now, BatchBuilder, QueueClass, ReportEvent, ValueClass},
     Deserialize, IterateParams, Store, ValueKey,
 };
 use tokio::sync::mpsc;
+use trc::AddContext;
 
 use crate::queue::spool::LOCK_EXPIRY;
 
@@ -53,33 +58,14 @@ impl SpawnReport for mpsc::Receiver<ReportingEvent> {
                     {
                         let server_ = server.clone();
                         tokio::spawn(async move {
-                            let mut tls_reports = AHashMap::new();
-                            for report_event in events {
-                                match report_event {
-                                    QueueClass::DmarcReportHeader(event) if event.due <= now => {
-                                        let lock_name = event.dmarc_lock();
-                                        if server_.try_lock_report(&lock_name).await {
-                                            server_.send_dmarc_aggregate_report(event).await;
-                                            server_.unlock_report(&lock_name).await;
-                                        }
-                                    }
-                                    QueueClass::TlsReportHeader(event) if event.due <= now => {
-                                        tls_reports
-                                            .entry(event.domain.clone())
-                                            .or_insert_with(Vec::new)
-                                            .push(event);
-                                    }
-                                    _ => (),
-                                }
-                            }
-
-                            for (_, tls_report) in tls_reports {
-                                let lock_name = tls_report.first().unwrap().tls_lock();
-                                if server_.try_lock_report(&lock_name).await {
-                                    server_.send_tls_aggregate_report(tls_report).await;
-                                    server_.unlock_report(&lock_name).await;
-                                }
-                            }
+                            server_
+                                .send_queued_reports(
+                                    events
+                                        .into_iter()
+                                        .filter(|e| e.due().is_some_and(|due| due <= now))
+                                        .collect(),
+                                )
+                                .await;
                         });
                     }
                 }
@@ -186,6 +172,130 @@ async fn next_report_event(store: &Store) -> Vec<QueueClass> {
     events
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateReportType {
+    Dmarc,
+    Tls,
+}
+
+pub trait ReportSchedule: Sync + Send {
+    fn send_queued_reports(&self, events: Vec<QueueClass>) -> impl Future<Output = ()> + Send;
+
+    fn report_interval(
+        &self,
+        report_type: AggregateReportType,
+        domain: &str,
+    ) -> impl Future<Output = Option<AggregateFrequency>> + Send;
+
+    fn set_report_interval(
+        &self,
+        report_type: AggregateReportType,
+        domain: &str,
+        interval: Option<AggregateFrequency>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl ReportSchedule for Server {
+    async fn send_queued_reports(&self, events: Vec<QueueClass>) {
+        let mut tls_reports = AHashMap::new();
+        for report_event in events {
+            match report_event {
+                QueueClass::DmarcReportHeader(event) => {
+                    let lock_name = event.dmarc_lock();
+                    if self.try_lock_report(&lock_name).await {
+                        self.send_dmarc_aggregate_report(event).await;
+                        self.unlock_report(&lock_name).await;
+                    }
+                }
+                QueueClass::TlsReportHeader(event) => {
+                    tls_reports
+                        .entry(event.domain.clone())
+                        .or_insert_with(Vec::new)
+                        .push(event);
+                }
+                _ => (),
+            }
+        }
+
+        for (_, tls_report) in tls_reports {
+            let lock_name = tls_report.first().unwrap().tls_lock();
+            if self.try_lock_report(&lock_name).await {
+                self.send_tls_aggregate_report(tls_report).await;
+                self.unlock_report(&lock_name).await;
+            }
+        }
+    }
+
+    async fn report_interval(
+        &self,
+        report_type: AggregateReportType,
+        domain: &str,
+    ) -> Option<AggregateFrequency> {
+        match self
+            .in_memory_store()
+            .key_get::<i64>(KeyValue::<()>::build_key(
+                KV_REPORT_INTERVAL,
+                report_type.interval_key(domain),
+            ))
+            .await
+        {
+            Ok(interval) => interval.map(|id| match id {
+                1 => AggregateFrequency::Hourly,
+                2 => AggregateFrequency::Daily,
+                3 => AggregateFrequency::Weekly,
+                _ => AggregateFrequency::Never,
+            }),
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to obtain report interval.")
+                    .caused_by(trc::location!()));
+                None
+            }
+        }
+    }
+
+    async fn set_report_interval(
+        &self,
+        report_type: AggregateReportType,
+        domain: &str,
+        interval: Option<AggregateFrequency>,
+    ) -> trc::Result<()> {
+        let key = report_type.interval_key(domain);
+        if let Some(interval) = interval {
+            let id: i64 = match interval {
+                AggregateFrequency::Never => 0,
+                AggregateFrequency::Hourly => 1,
+                AggregateFrequency::Daily => 2,
+                AggregateFrequency::Weekly => 3,
+            };
+            self.in_memory_store()
+                .key_set(KeyValue::with_prefix(
+                    KV_REPORT_INTERVAL,
+                    key,
+                    id.to_be_bytes().to_vec(),
+                ))
+                .await
+        } else {
+            self.in_memory_store()
+                .key_delete(KeyValue::<()>::build_key(KV_REPORT_INTERVAL, key))
+                .await
+        }
+        .caused_by(trc::location!())
+    }
+}
+
+impl AggregateReportType {
+    fn interval_key(&self, domain: &str) -> Vec<u8> {
+        let mut key = Vec::with_capacity(domain.len() + 1);
+        key.push(match self {
+            AggregateReportType::Dmarc => 0,
+            AggregateReportType::Tls => 1,
+        });
+        key.extend_from_slice(domain.to_lowercase().as_bytes());
+        key
+    }
+}
+
 pub trait LockReport: Sync + Send {
     fn try_lock_report(&self, lock: &[u8]) -> impl Future<Output = bool> + Send;
 
@@ -6,11 +6,12 @@
 
 use std::{borrow::Cow, future::Future, sync::Arc, time::Instant};
 
-use common::{scripts::plugins::PluginContext, Server};
+use common::{scripts::plugins::PluginContext, SandboxId, Server};
 use mail_auth::common::headers::HeaderWriter;
 use mail_parser::{Encoding, Message, MessagePart, PartType};
 use sieve::{
     compiler::grammar::actions::action_redirect::{ByMode, ByTime, Notify, NotifyItem, Ret},
+    runtime::RuntimeError,
     Event, Input, MatchAs, Recipient, Sieve,
 };
 use smtp_proto::{
@@ -24,7 +25,7 @@ use crate::{
     queue::{quota::HasQueueQuota, spool::SmtpSpool, DomainPart, MessageSource},
 };
 
-use super::{ScriptModification, ScriptParameters, ScriptResult};
+use super::{sandbox::ScriptSandbox, ScriptModification, ScriptParameters, ScriptResult};
 
 pub trait RunScript: Sync + Send {
     fn run_script(
@@ -42,8 +43,24 @@ impl RunScript for Server {
         script: Arc<Sieve>,
         params: ScriptParameters<'_>,
     ) -> ScriptResult {
+        // Skip scripts that were disabled for exceeding their limits
+        let session_id = params.session_id;
+        let sandbox_id = SandboxId::Sieve(script_id.clone());
+        if self.sandbox_is_disabled(&sandbox_id) {
+            trc::event!(
+                Sieve(SieveEvent::ScriptDisabled),
+                Id = script_id,
+                SpanId = session_id,
+            );
+
+            return ScriptResult::Accept {
+                modifications: vec![],
+            };
+        }
+
         // Create filter instance
         let time = Instant::now();
+        let limits = &self.core.sieve.trusted_limits;
         let mut instance = self
             .core
             .sieve
@@ -67,14 +84,22 @@ impl RunScript for Server {
             .with_user_full_name(&params.from_name);
         let mut input = Input::script("__script", script);
         let mut messages: Vec<Vec<u8>> = Vec::new();
-        let session_id = params.session_id;
 
         let mut reject_reason = None;
         let mut modifications = vec![];
         let mut keep_id = usize::MAX;
 
+        let mut memory_used = 0;
+        let mut external_calls = 0;
+        let mut violation = None;
+
         // Start event loop
         while let Some(result) = instance.run(input) {
+            if let Some(reason) = limits.exceeded(time.elapsed(), memory_used, external_calls) {
+                violation = reason.into();
+                break;
+            }
+
             match result {
                 Ok(event) => match event {
                     Event::IncludeScript { name, optional } => {
@@ -100,6 +125,7 @@ impl RunScript for Server {
                     } => {
                         input = false.into();
                         'outer: for list in lists {
+                            external_calls += 1;
                             if let Some(store) = self.core.storage.lookups.get(&list) {
                                 for value in &values {
                                     if let Ok(true) = store
@@ -125,6 +151,7 @@ impl RunScript for Server {
                         }
                     }
                     Event::Function { id, arguments } => {
+                        external_calls += 1;
                         input = self
                             .core
                             .run_plugin(
@@ -337,6 +364,7 @@ impl RunScript for Server {
                         input = true.into();
                     }
                     Event::CreatedMessage { message, .. } => {
+                        memory_used += message.len();
                         messages.push(message);
                         input = true.into();
                     }
@@ -359,6 +387,9 @@ impl RunScript for Server {
                     }
                 },
                 Err(err) => {
+                    if matches!(err, RuntimeError::CPULimitReached) {
+                        violation = "CPU limit exceeded".into();
+                    }
                     trc::event!(
                         Sieve(SieveEvent::RuntimeError),
                         Id = script_id.clone(),
@@ -370,6 +401,30 @@ impl RunScript for Server {
             }
         }
 
+        // Update script statistics
+        if let Some(reason) = violation {
+            trc::event!(
+                Sieve(SieveEvent::LimitExceeded),
+                Id = script_id.clone(),
+                SpanId = session_id,
+                Reason = reason,
+                Elapsed = time.elapsed(),
+            );
+        }
+        if let Some(disabled_until) = self.sandbox_record(
+            sandbox_id,
+            &limits.policy,
+            time.elapsed(),
+            violation.is_some(),
+        ) {
+            trc::event!(
+                Sieve(SieveEvent::ScriptDisabled),
+                Id = script_id.clone(),
+                SpanId = session_id,
+                Expires = trc::Value::Timestamp(disabled_until),
+            );
+        }
+
         // Keep id
         // 0 = use original message
         // MAX = implicit keep
@@ -16,6 +16,7 @@ use sieve::{runtime::Variable, Envelope};
 pub mod envelope;
 pub mod event_loop;
 pub mod exec;
+pub mod sandbox;
 
 #[derive(Debug, serde::Serialize)]
 pub enum ScriptResult {
@@ -0,0 +1,75 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::{config::scripts::SandboxPolicy, SandboxId, SandboxStats, Server};
+use store::write::now;
+
+pub trait ScriptSandbox: Sync + Send {
+    fn sandbox_is_disabled(&self, id: &SandboxId) -> bool;
+
+    fn sandbox_record(
+        &self,
+        id: SandboxId,
+        policy: &SandboxPolicy,
+        elapsed: Duration,
+        is_violation: bool,
+    ) -> Option<u64>;
+
+    fn sandbox_stats(&self) -> Vec<(SandboxId, SandboxStats)>;
+}
+
+impl ScriptSandbox for Server {
+    fn sandbox_is_disabled(&self, id: &SandboxId) -> bool {
+        self.inner
+            .data
+            .sandbox_stats
+            .lock()
+            .get(id)
+            .is_some_and(|stats| stats.disabled_until > now())
+    }
+
+    fn sandbox_record(
+        &self,
+        id: SandboxId,
+        policy: &SandboxPolicy,
+        elapsed: Duration,
+        is_violation: bool,
+    ) -> Option<u64> {
+        let mut sandbox_stats = self.inner.data.sandbox_stats.lock();
+        let stats = sandbox_stats.entry(id).or_default();
+        stats.runs += 1;
+        stats.elapsed += elapsed;
+
+        if !is_violation {
+            stats.consecutive_violations = 0;
+            return None;
+        }
+
+        // Disable the script after too many consecutive violations
+        stats.violations += 1;
+        stats.consecutive_violations += 1;
+        match policy.max_violations {
+            Some(max_violations) if stats.consecutive_violations >= max_violations => {
+                stats.consecutive_violations = 0;
+                stats.disabled_until = now() + policy.disable_for.as_secs();
+                Some(stats.disabled_until)
+            }
+            _ => None,
+        }
+    }
+
+    fn sandbox_stats(&self) -> Vec<(SandboxId, SandboxStats)> {
+        self.inner
+            .data
+            .sandbox_stats
+            .lock()
+            .iter()
+            .map(|(id, stats)| (id.clone(), stats.clone()))
+            .collect()
+    }
+}
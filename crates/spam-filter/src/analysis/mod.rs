@@ -29,6 +29,7 @@ pub mod ip;
 pub mod messageid;
 pub mod mime;
 pub mod pyzor;
+pub mod rspamd;
 pub mod received;
 pub mod recipient;
 pub mod replyto;
@@ -0,0 +1,93 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fmt::Write, future::Future, time::Instant};
+
+use common::{config::spamfilter::SpamFilterAction, Server};
+
+use crate::{modules::rspamd::rspamd_check, SpamFilterContext};
+
+pub trait SpamFilterAnalyzeRspamd: Sync + Send {
+    /// Classifies the message using Rspamd, returns `None` if Rspamd is not
+    /// configured or could not be reached.
+    fn spam_filter_classify_rspamd(
+        &self,
+        ctx: &mut SpamFilterContext<'_>,
+    ) -> impl Future<Output = Option<SpamFilterAction<String>>> + Send;
+}
+
+impl SpamFilterAnalyzeRspamd for Server {
+    async fn spam_filter_classify_rspamd(
+        &self,
+        ctx: &mut SpamFilterContext<'_>,
+    ) -> Option<SpamFilterAction<String>> {
+        let config = self.core.spam.rspamd.as_ref()?;
+        let time = Instant::now();
+        let response = match rspamd_check(config, &ctx.input).await {
+            Ok(response) => response,
+            Err(err) => {
+                trc::error!(err
+                    .span_id(ctx.input.span_id)
+                    .ctx(trc::Key::Elapsed, time.elapsed()));
+                return None;
+            }
+        };
+
+        trc::event!(
+            Spam(trc::SpamEvent::Rspamd),
+            Result = response.action.clone(),
+            Value = response.score,
+            SpanId = ctx.input.span_id,
+            Elapsed = time.elapsed()
+        );
+
+        match response.action.as_str() {
+            "reject" => return Some(SpamFilterAction::Reject),
+            "discard" => return Some(SpamFilterAction::Discard),
+            _ => (),
+        }
+
+        let mut symbols = response
+            .symbols
+            .iter()
+            .map(|(name, symbol)| (name.as_str(), symbol.score))
+            .collect::<Vec<_>>();
+        symbols.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        ctx.result.score = response.score;
+        ctx.result
+            .tags
+            .extend(symbols.iter().map(|(name, _)| name.to_string()));
+
+        // Write results and status headers
+        let mut header = String::with_capacity(64 + symbols.len() * 32);
+        if let Some(header_name) = &self.core.spam.headers.result {
+            header.push_str(header_name);
+            header.push_str(": ");
+            for (idx, (name, score)) in symbols.iter().enumerate() {
+                if idx > 0 {
+                    header.push_str(",\r\n\t");
+                }
+                let _ = write!(&mut header, "{} ({:.2})", name, score);
+            }
+            header.push_str("\r\n");
+        }
+        if let Some(header_name) = &self.core.spam.headers.status {
+            let _ = write!(
+                &mut header,
+                "{}: {}, score={:.2}\r\n",
+                header_name,
+                if response.is_spam() || ctx.result.score >= self.core.spam.scores.spam_threshold {
+                    "Yes"
+                } else {
+                    "No"
+                },
+                ctx.result.score
+            );
+        }
+
+        Some(SpamFilterAction::Allow(header))
+    }
+}
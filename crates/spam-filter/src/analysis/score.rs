@@ -15,11 +15,11 @@ use crate::{
         messageid::SpamFilterAnalyzeMid, mime::SpamFilterAnalyzeMime,
         pyzor::SpamFilterAnalyzePyzor, received::SpamFilterAnalyzeReceived,
         recipient::SpamFilterAnalyzeRecipient, replyto::SpamFilterAnalyzeReplyTo,
-        reputation::SpamFilterAnalyzeReputation, rules::SpamFilterAnalyzeRules,
-        subject::SpamFilterAnalyzeSubject, trusted_reply::SpamFilterAnalyzeTrustedReply,
-        url::SpamFilterAnalyzeUrl,
+        reputation::SpamFilterAnalyzeReputation, rspamd::SpamFilterAnalyzeRspamd,
+        rules::SpamFilterAnalyzeRules, subject::SpamFilterAnalyzeSubject,
+        trusted_reply::SpamFilterAnalyzeTrustedReply, url::SpamFilterAnalyzeUrl,
     },
-    modules::bayes::BayesClassifier,
+    modules::{bayes::BayesClassifier, stats::SpamFilterStats},
     SpamFilterContext,
 };
 
@@ -111,6 +111,11 @@ impl SpamFilterAnalyzeScore for Server {
             }
         }
 
+        // Track which tags fired
+        if !ctx.input.is_test {
+            self.spam_filter_record_tags(&ctx.result.tags);
+        }
+
         if self.core.spam.scores.reject_threshold > 0.0
             && ctx.result.score >= self.core.spam.scores.reject_threshold
         {
@@ -142,6 +147,11 @@ impl SpamFilterAnalyzeScore for Server {
         &self,
         ctx: &mut SpamFilterContext<'_>,
     ) -> SpamFilterAction<String> {
+        // Delegate classification to Rspamd when configured
+        if let Some(result) = self.spam_filter_classify_rspamd(ctx).await {
+            return result;
+        }
+
         // IP address analysis
         self.spam_filter_analyze_ip(ctx).await;
 
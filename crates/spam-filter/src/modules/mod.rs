@@ -15,7 +15,9 @@ pub mod dnsbl;
 pub mod expression;
 pub mod html;
 pub mod pyzor;
+pub mod rspamd;
 pub mod sanitize;
+pub mod stats;
 
 pub(crate) async fn key_get<T: Deserialize + From<Value<'static>> + std::fmt::Debug + 'static>(
     server: &Server,
@@ -0,0 +1,130 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::HashMap;
+
+use common::config::spamfilter::RspamdConfig;
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+
+use crate::SpamFilterInput;
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct RspamdResponse {
+    #[serde(default)]
+    pub score: f64,
+    #[serde(default)]
+    pub action: String,
+    #[serde(default)]
+    pub symbols: HashMap<String, RspamdSymbol>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct RspamdSymbol {
+    #[serde(default)]
+    pub score: f64,
+}
+
+impl RspamdResponse {
+    pub fn is_spam(&self) -> bool {
+        matches!(
+            self.action.as_str(),
+            "reject" | "add header" | "rewrite subject"
+        )
+    }
+}
+
+pub(crate) async fn rspamd_check(
+    config: &RspamdConfig,
+    input: &SpamFilterInput<'_>,
+) -> trc::Result<RspamdResponse> {
+    let mut request = client(config)?
+        .post(format!("{}/checkv2", config.url))
+        .header("IP", input.remote_ip.to_string())
+        .header("From", input.env_from);
+    if let Some(helo) = input.ehlo_domain {
+        request = request.header("Helo", helo);
+    }
+    if let Some(user) = input.authenticated_as {
+        request = request.header("User", user);
+    }
+    for rcpt in &input.env_rcpt_to {
+        request = request.header("Rcpt", *rcpt);
+    }
+    if let Some(ptr) = input
+        .iprev_result
+        .and_then(|iprev| iprev.ptr.as_ref())
+        .and_then(|ptr| ptr.first())
+    {
+        request = request.header("Hostname", ptr.trim_end_matches('.'));
+    }
+
+    let response = send(request.body(input.message.raw_message().to_vec())).await?;
+    serde_json::from_slice(&response).map_err(|err| {
+        trc::SpamEvent::RspamdError
+            .into_err()
+            .reason(err)
+            .ctx(trc::Key::Url, config.url.clone())
+            .details("Failed to parse Rspamd response")
+    })
+}
+
+pub async fn rspamd_learn(
+    config: &RspamdConfig,
+    raw_message: &[u8],
+    learn_spam: bool,
+) -> trc::Result<()> {
+    let mut request = client(config)?.post(format!(
+        "{}/{}",
+        config.controller_url,
+        if learn_spam { "learnspam" } else { "learnham" }
+    ));
+    if let Some(password) = &config.password {
+        request = request.header("Password", password);
+    }
+
+    send(request.body(raw_message.to_vec())).await.map(|_| ())
+}
+
+fn client(config: &RspamdConfig) -> trc::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(config.timeout)
+        .build()
+        .map_err(|err| {
+            trc::SpamEvent::RspamdError
+                .into_err()
+                .reason(err)
+                .details("Failed to build request")
+        })
+}
+
+async fn send(request: RequestBuilder) -> trc::Result<Vec<u8>> {
+    let response = request.send().await.map_err(|err| {
+        trc::SpamEvent::RspamdError
+            .into_err()
+            .reason(err)
+            .details("Failed to send request")
+    })?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(trc::SpamEvent::RspamdError
+            .into_err()
+            .ctx(trc::Key::Code, status.as_u16())
+            .details("Rspamd returned an error"));
+    }
+
+    response
+        .bytes()
+        .await
+        .map(|bytes| bytes.to_vec())
+        .map_err(|err| {
+            trc::SpamEvent::RspamdError
+                .into_err()
+                .reason(err)
+                .details("Failed to read response")
+        })
+}
@@ -0,0 +1,60 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, SpamTagHits};
+use store::{
+    ahash::{AHashMap, AHashSet},
+    write::now,
+};
+
+// Hourly buckets are kept for one week
+const MAX_BUCKETS: usize = 24 * 7;
+const HOUR: u64 = 3600;
+
+pub trait SpamFilterStats: Sync + Send {
+    fn spam_filter_record_tags(&self, tags: &AHashSet<String>);
+
+    fn spam_filter_top_tags(&self, hours: u64, limit: usize) -> Vec<(String, u64)>;
+}
+
+impl SpamFilterStats for Server {
+    fn spam_filter_record_tags(&self, tags: &AHashSet<String>) {
+        let hour = now() / HOUR;
+        let mut buckets = self.inner.data.spam_tag_hits.lock();
+        if buckets.back().is_none_or(|bucket| bucket.hour != hour) {
+            if buckets.len() >= MAX_BUCKETS {
+                buckets.pop_front();
+            }
+            buckets.push_back(SpamTagHits {
+                hour,
+                tags: AHashMap::new(),
+            });
+        }
+        let bucket = buckets.back_mut().unwrap();
+        for tag in tags {
+            *bucket.tags.entry(tag.clone()).or_default() += 1;
+        }
+    }
+
+    fn spam_filter_top_tags(&self, hours: u64, limit: usize) -> Vec<(String, u64)> {
+        let since = (now() / HOUR).saturating_sub(hours.saturating_sub(1));
+        let mut totals: AHashMap<&str, u64> = AHashMap::new();
+        let buckets = self.inner.data.spam_tag_hits.lock();
+        for bucket in buckets.iter().filter(|bucket| bucket.hour >= since) {
+            for (tag, hits) in &bucket.tags {
+                *totals.entry(tag.as_str()).or_default() += hits;
+            }
+        }
+
+        let mut totals = totals
+            .into_iter()
+            .map(|(tag, hits)| (tag.to_string(), hits))
+            .collect::<Vec<_>>();
+        totals.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        totals.truncate(limit);
+        totals
+    }
+}
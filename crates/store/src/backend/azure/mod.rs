@@ -170,6 +170,22 @@ impl AzureStore {
         Ok(())
     }
 
+    pub(crate) async fn put_named_blob(&self, name: &str, data: &[u8]) -> trc::Result<()> {
+        let name = match &self.prefix {
+            Some(prefix) => format!("{prefix}{name}"),
+            None => name.to_string(),
+        };
+
+        self.client
+            .blob_client(name)
+            .put_block_blob(data.to_vec())
+            .into_future()
+            .await
+            .map_err(into_error)?;
+
+        Ok(())
+    }
+
     pub(crate) async fn delete_blob(&self, key: &[u8]) -> trc::Result<bool> {
         let blob_client = self.client.blob_client(self.build_key(key));
 
@@ -4,10 +4,14 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{io::SeekFrom, ops::Range, path::PathBuf};
+use std::{
+    io::{ErrorKind, SeekFrom},
+    ops::Range,
+    path::PathBuf,
+};
 
 use tokio::{
-    fs::{self, File},
+    fs::{self, File, OpenOptions},
     io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
 };
 use utils::{
@@ -99,6 +103,37 @@ impl FsStore {
         Ok(())
     }
 
+    pub(crate) async fn put_named_blob(&self, name: &str, data: &[u8]) -> trc::Result<()> {
+        let mut blob_path = self.path.clone();
+        for part in name.split('/') {
+            if part.is_empty() || part == "." || part == ".." {
+                return Err(trc::StoreEvent::FilesystemError
+                    .reason("Invalid object name")
+                    .ctx(trc::Key::Key, name.to_string()));
+            }
+            blob_path.push(part);
+        }
+
+        fs::create_dir_all(blob_path.parent().unwrap())
+            .await
+            .map_err(into_error)?;
+
+        // Named objects are written once and never replaced
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&blob_path)
+            .await
+        {
+            Ok(mut blob_file) => {
+                blob_file.write_all(data).await.map_err(into_error)?;
+                blob_file.flush().await.map_err(into_error)
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(()),
+            Err(err) => Err(into_error(err)),
+        }
+    }
+
     pub(crate) async fn delete_blob(&self, key: &[u8]) -> trc::Result<bool> {
         let blob_path = self.build_path(key);
         if fs::metadata(&blob_path).await.is_ok() {
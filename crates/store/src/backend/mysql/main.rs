@@ -9,7 +9,7 @@ use std::time::Duration;
 use mysql_async::{prelude::Queryable, OptsBuilder, Pool, PoolConstraints, PoolOpts, SslOpts};
 use utils::config::{utils::AsKey, Config};
 
-use crate::*;
+use crate::{dispatch::cache::ValueCache, *};
 
 use super::{into_error, MysqlStore};
 
@@ -76,6 +76,7 @@ impl MysqlStore {
 
         let db = Self {
             conn_pool: Pool::new(opts),
+            cache: ValueCache::parse(config, prefix.as_str()),
         };
 
         if create_tables {
@@ -8,6 +8,8 @@ use std::fmt::Display;
 
 use mysql_async::Pool;
 
+use crate::dispatch::cache::ValueCache;
+
 pub mod blob;
 pub mod lookup;
 pub mod main;
@@ -16,6 +18,7 @@ pub mod write;
 
 pub struct MysqlStore {
     pub(crate) conn_pool: Pool,
+    pub(crate) cache: Option<ValueCache>,
 }
 
 #[inline(always)]
@@ -6,7 +6,7 @@
 
 use std::time::Duration;
 
-use crate::{backend::postgres::tls::MakeRustlsConnect, *};
+use crate::{backend::postgres::tls::MakeRustlsConnect, dispatch::cache::ValueCache, *};
 
 use super::{into_error, PostgresStore};
 
@@ -63,6 +63,7 @@ impl PostgresStore {
                 )
             })
             .ok()?,
+            cache: ValueCache::parse(config, prefix.as_str()),
         };
 
         if create_tables {
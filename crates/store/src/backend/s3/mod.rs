@@ -145,6 +145,45 @@ impl S3Store {
         }
     }
 
+    pub(crate) async fn put_named_blob(
+        &self,
+        name: &str,
+        data: &[u8],
+        content_type: &str,
+    ) -> trc::Result<()> {
+        let key = match &self.prefix {
+            Some(prefix) => format!("{prefix}{name}"),
+            None => name.to_string(),
+        };
+        let mut retries_left = self.max_retries;
+
+        loop {
+            let response = self
+                .bucket
+                .put_object_with_content_type(&key, data, content_type)
+                .await
+                .map_err(into_error)?;
+
+            match response.status_code() {
+                200..=299 => return Ok(()),
+                500..=599 if retries_left > 0 => {
+                    // wait backoff
+                    tokio::time::sleep(Duration::from_secs(
+                        1 << (self.max_retries - retries_left).min(6),
+                    ))
+                    .await;
+
+                    retries_left -= 1;
+                }
+                code => {
+                    return Err(trc::StoreEvent::S3Error
+                        .reason(String::from_utf8_lossy(response.as_slice()))
+                        .ctx(trc::Key::Code, code))
+                }
+            }
+        }
+    }
+
     pub(crate) async fn delete_blob(&self, key: &[u8]) -> trc::Result<bool> {
         let mut retries_left = self.max_retries;
 
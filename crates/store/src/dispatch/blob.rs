@@ -4,15 +4,32 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{borrow::Cow, ops::Range, time::Instant};
+use std::{
+    borrow::Cow,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
 
 use trc::{AddContext, StoreEvent};
 use utils::config::utils::ParseValue;
 
-use crate::{BlobBackend, BlobStore, CompressionAlgo, Store};
+use crate::{write::now, BlobBackend, BlobStore, CompressionAlgo, Store};
+
+// Seconds to wait after a failure before sending requests to the backend again
+const RETRY_INTERVAL: u64 = 10;
+
+/// Tracks whether the blob backend is reachable, failing requests early
+/// while it is down instead of waiting for each one to time out.
+#[derive(Debug, Default)]
+pub struct BlobStoreHealth {
+    unavailable_since: AtomicU64,
+    last_failure: AtomicU64,
+}
 
 impl BlobStore {
     pub async fn get_blob(&self, key: &[u8], range: Range<usize>) -> trc::Result<Option<Vec<u8>>> {
+        self.health.check()?;
         let read_range = match self.compression {
             CompressionAlgo::None => range.clone(),
             CompressionAlgo::Lz4 => 0..usize::MAX,
@@ -38,6 +55,7 @@ impl BlobStore {
             #[cfg(feature = "azure")]
             BlobBackend::Azure(store) => store.get_blob(key, read_range).await,
         };
+        let result = self.health.track(result);
 
         trc::event!(
             Store(StoreEvent::BlobRead),
@@ -86,6 +104,7 @@ impl BlobStore {
     }
 
     pub async fn put_blob(&self, key: &[u8], data: &[u8]) -> trc::Result<()> {
+        self.health.check()?;
         let data: Cow<[u8]> = match self.compression {
             CompressionAlgo::None => data.into(),
             CompressionAlgo::Lz4 => {
@@ -115,8 +134,8 @@ impl BlobStore {
             BlobBackend::S3(store) => store.put_blob(key, data.as_ref()).await,
             #[cfg(feature = "azure")]
             BlobBackend::Azure(store) => store.put_blob(key, data.as_ref()).await,
-        }
-        .caused_by(trc::location!());
+        };
+        let result = self.health.track(result).caused_by(trc::location!());
 
         trc::event!(
             Store(StoreEvent::BlobWrite),
@@ -128,7 +147,51 @@ impl BlobStore {
         result
     }
 
+    /// Writes an uncompressed object under a verbatim name, for copies that
+    /// are read by external tools rather than by the server.
+    #[allow(unused_variables)]
+    pub async fn put_named_blob(
+        &self,
+        name: &str,
+        data: &[u8],
+        content_type: &str,
+    ) -> trc::Result<()> {
+        self.health.check()?;
+        let start_time = Instant::now();
+        let result = match &self.backend {
+            BlobBackend::Store(store) => match store {
+                #[cfg(feature = "sqlite")]
+                Store::SQLite(store) => store.put_blob(name.as_bytes(), data).await,
+                #[cfg(feature = "foundation")]
+                Store::FoundationDb(store) => store.put_blob(name.as_bytes(), data).await,
+                #[cfg(feature = "postgres")]
+                Store::PostgreSQL(store) => store.put_blob(name.as_bytes(), data).await,
+                #[cfg(feature = "mysql")]
+                Store::MySQL(store) => store.put_blob(name.as_bytes(), data).await,
+                #[cfg(feature = "rocks")]
+                Store::RocksDb(store) => store.put_blob(name.as_bytes(), data).await,
+                Store::None => Err(trc::StoreEvent::NotConfigured.into()),
+            },
+            BlobBackend::Fs(store) => store.put_named_blob(name, data).await,
+            #[cfg(feature = "s3")]
+            BlobBackend::S3(store) => store.put_named_blob(name, data, content_type).await,
+            #[cfg(feature = "azure")]
+            BlobBackend::Azure(store) => store.put_named_blob(name, data).await,
+        };
+        let result = self.health.track(result).caused_by(trc::location!());
+
+        trc::event!(
+            Store(StoreEvent::BlobWrite),
+            Key = name.to_string(),
+            Elapsed = start_time.elapsed(),
+            Size = data.len(),
+        );
+
+        result
+    }
+
     pub async fn delete_blob(&self, key: &[u8]) -> trc::Result<bool> {
+        self.health.check()?;
         let start_time = Instant::now();
         let result = match &self.backend {
             BlobBackend::Store(store) => match store {
@@ -149,8 +212,8 @@ impl BlobStore {
             BlobBackend::S3(store) => store.delete_blob(key).await,
             #[cfg(feature = "azure")]
             BlobBackend::Azure(store) => store.delete_blob(key).await,
-        }
-        .caused_by(trc::location!());
+        };
+        let result = self.health.track(result).caused_by(trc::location!());
 
         trc::event!(
             Store(StoreEvent::BlobWrite),
@@ -165,8 +228,86 @@ impl BlobStore {
         Self {
             backend: self.backend,
             compression,
+            health: self.health,
         }
     }
+
+    /// Returns `false` while the backend is failing and no retry is due yet.
+    pub fn is_available(&self) -> bool {
+        self.health.is_available()
+    }
+
+    /// Returns `true` from the first backend failure until a request succeeds.
+    pub fn is_degraded(&self) -> bool {
+        self.health.unavailable_since.load(Ordering::Relaxed) != 0
+    }
+}
+
+impl BlobStoreHealth {
+    fn is_available(&self) -> bool {
+        self.unavailable_since.load(Ordering::Relaxed) == 0
+            || now() >= self.last_failure.load(Ordering::Relaxed) + RETRY_INTERVAL
+    }
+
+    fn check(&self) -> trc::Result<()> {
+        if self.is_available() {
+            Ok(())
+        } else {
+            Err(trc::StoreEvent::BlobStoreUnavailable
+                .ctx(
+                    trc::Key::NextRetry,
+                    trc::Value::Timestamp(
+                        self.last_failure.load(Ordering::Relaxed) + RETRY_INTERVAL,
+                    ),
+                )
+                .caused_by(trc::location!()))
+        }
+    }
+
+    fn track<T>(&self, result: trc::Result<T>) -> trc::Result<T> {
+        match result {
+            Ok(value) => {
+                let since = self.unavailable_since.swap(0, Ordering::Relaxed);
+                if since != 0 {
+                    trc::event!(
+                        Store(StoreEvent::BlobStoreRecovered),
+                        Elapsed = Duration::from_secs(now().saturating_sub(since)),
+                    );
+                }
+                Ok(value)
+            }
+            Err(err) if is_backend_error(&err) => {
+                let now = now();
+                self.last_failure.store(now, Ordering::Relaxed);
+                if self
+                    .unavailable_since
+                    .compare_exchange(0, now, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    trc::event!(Store(StoreEvent::BlobStoreDegraded), CausedBy = err.clone(),);
+                }
+                Err(err.wrap(trc::EventType::Store(StoreEvent::BlobStoreUnavailable)))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn is_backend_error(err: &trc::Error) -> bool {
+    matches!(
+        err.event_type(),
+        trc::EventType::Store(
+            StoreEvent::FoundationdbError
+                | StoreEvent::MysqlError
+                | StoreEvent::PostgresqlError
+                | StoreEvent::RocksdbError
+                | StoreEvent::SqliteError
+                | StoreEvent::S3Error
+                | StoreEvent::AzureError
+                | StoreEvent::FilesystemError
+                | StoreEvent::PoolError
+        )
+    )
 }
 
 const MAGIC_MARKER: u8 = 0xa0;
@@ -0,0 +1,193 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use utils::{
+    cache::Cache,
+    config::{utils::AsKey, Config},
+};
+
+use crate::{
+    write::{Batch, Operation},
+    WITH_SUBSPACE,
+};
+
+// In-process cache for frequently read values (mailbox and identity properties, ACLs)
+pub struct ValueCache {
+    entries: Cache<Vec<u8>, Arc<Vec<u8>>>,
+    // Bumped on every invalidation, used to discard reads that raced with a write
+    version: AtomicU64,
+    // Bumped on local writes only, advertised to cluster peers
+    generation: AtomicU64,
+}
+
+impl ValueCache {
+    pub fn parse(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+        let prefix = prefix.as_key();
+        if !config
+            .property_or_default::<bool>((&prefix, "cache.enable"), "false")
+            .unwrap_or_default()
+        {
+            return None;
+        }
+
+        let weight_capacity = config
+            .property((&prefix, "cache.size"))
+            .unwrap_or(32 * 1024 * 1024);
+        let estimated_items_capacity = config
+            .property((&prefix, "cache.capacity"))
+            .unwrap_or(weight_capacity as usize / 256);
+
+        Some(ValueCache {
+            entries: Cache::new(estimated_items_capacity, weight_capacity),
+            version: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+        })
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &[u8]) -> Option<Arc<Vec<u8>>> {
+        self.entries.get(key)
+    }
+
+    pub fn insert(&self, key: Vec<u8>, value: Arc<Vec<u8>>, version: u64) {
+        // Skip values that might have been modified while they were being fetched
+        if self.version() == version {
+            self.entries.insert(key.clone(), value);
+            if self.version() != version {
+                self.entries.remove(key.as_slice());
+            }
+        }
+    }
+
+    pub fn cached_keys(batch: &Batch) -> Vec<Vec<u8>> {
+        let mut account_id = u32::MAX;
+        let mut collection = u8::MAX;
+        let mut document_id = u32::MAX;
+        let mut keys = Vec::new();
+
+        for op in &batch.ops {
+            match op {
+                Operation::AccountId {
+                    account_id: account_id_,
+                } => {
+                    account_id = *account_id_;
+                }
+                Operation::Collection {
+                    collection: collection_,
+                } => {
+                    collection = *collection_;
+                }
+                Operation::DocumentId {
+                    document_id: document_id_,
+                } => {
+                    document_id = *document_id_;
+                }
+                Operation::Value { class, .. } if class.is_cacheable(collection) => {
+                    keys.push(class.serialize(
+                        account_id,
+                        collection,
+                        document_id,
+                        WITH_SUBSPACE,
+                        None,
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        keys
+    }
+
+    pub fn invalidate(&self, keys: Vec<Vec<u8>>) {
+        if !keys.is_empty() {
+            // Bump the version first so in-flight reads are not cached
+            self.version.fetch_add(1, Ordering::Relaxed);
+            for key in keys {
+                self.entries.remove(key.as_slice());
+            }
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.version.fetch_add(1, Ordering::Relaxed);
+        self.entries.clear();
+    }
+
+    #[inline(always)]
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Relaxed)
+    }
+
+    #[inline(always)]
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicU64, Arc};
+
+    use utils::cache::Cache;
+
+    use crate::{
+        write::{BatchBuilder, ValueClass},
+        Key, ValueKey, WITH_SUBSPACE,
+    };
+
+    use super::ValueCache;
+
+    #[test]
+    fn value_cache_invalidation() {
+        let cache = ValueCache {
+            entries: Cache::new(16, 1024 * 1024),
+            version: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+        };
+        let key = ValueKey {
+            account_id: 1,
+            collection: 1,
+            document_id: 5,
+            class: ValueClass::Property(2),
+        };
+        assert!(key.is_cacheable());
+        let cache_key = key.serialize(WITH_SUBSPACE);
+
+        // Cache value
+        cache.insert(cache_key.clone(), Arc::new(vec![1]), cache.version());
+        assert_eq!(cache.get(&cache_key).unwrap().as_slice(), &[1]);
+
+        // Values fetched before a write are not cached
+        let version = cache.version();
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(1)
+            .with_collection(1u8)
+            .update_document(5)
+            .set(ValueClass::Property(2), vec![2]);
+        let keys = ValueCache::cached_keys(&batch.build_batch());
+        assert_eq!(keys, vec![cache_key.clone()]);
+        cache.invalidate(keys);
+        assert!(cache.get(&cache_key).is_none());
+        assert_eq!(cache.generation(), 1);
+        cache.insert(cache_key.clone(), Arc::new(vec![1]), version);
+        assert!(cache.get(&cache_key).is_none());
+
+        // Writes to other collections are ignored
+        batch
+            .with_account_id(1)
+            .with_collection(0u8)
+            .update_document(5)
+            .set(ValueClass::Property(2), vec![2]);
+        assert!(ValueCache::cached_keys(&batch.build_batch()).is_empty());
+    }
+}
@@ -241,6 +241,18 @@ impl InMemoryStore {
         key: &[u8],
         rate: &Rate,
         soft_check: bool,
+    ) -> trc::Result<Option<u64>> {
+        self.is_rate_allowed_by(prefix, key, rate, 1, soft_check)
+            .await
+    }
+
+    pub async fn is_rate_allowed_by(
+        &self,
+        prefix: u8,
+        key: &[u8],
+        rate: &Rate,
+        amount: u64,
+        soft_check: bool,
     ) -> trc::Result<Option<u64>> {
         let now = now();
         let range_start = now / rate.period.as_secs();
@@ -253,11 +265,14 @@ impl InMemoryStore {
         bucket.extend_from_slice(range_start.to_be_bytes().as_slice());
 
         let requests = if !soft_check {
-            self.counter_incr(KeyValue::new(bucket, 1).expires(expires_in), true)
-                .await
-                .caused_by(trc::location!())?
+            self.counter_incr(
+                KeyValue::new(bucket, amount as i64).expires(expires_in),
+                true,
+            )
+            .await
+            .caused_by(trc::location!())?
         } else {
-            self.counter_get(bucket).await.caused_by(trc::location!())? + 1
+            self.counter_get(bucket).await.caused_by(trc::location!())? + amount as i64
         };
 
         if requests <= rate.requests as i64 {
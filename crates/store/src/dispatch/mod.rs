@@ -8,7 +8,10 @@ use roaring::RoaringBitmap;
 
 use crate::Store;
 
+use self::cache::ValueCache;
+
 pub mod blob;
+pub mod cache;
 pub mod fts;
 pub mod lookup;
 pub mod store;
@@ -29,6 +32,16 @@ impl Store {
             Self::None => "none",
         }
     }
+
+    pub fn value_cache(&self) -> Option<&ValueCache> {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::PostgreSQL(store) => store.cache.as_ref(),
+            #[cfg(feature = "mysql")]
+            Self::MySQL(store) => store.cache.as_ref(),
+            _ => None,
+        }
+    }
 }
 
 #[allow(clippy::len_without_is_empty)]
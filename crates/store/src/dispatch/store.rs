@@ -6,6 +6,7 @@
 
 use std::{
     ops::{BitAndAssign, Range},
+    sync::Arc,
     time::Instant,
 };
 
@@ -15,7 +16,7 @@ use trc::{AddContext, StoreEvent};
 use crate::{
     BitmapKey, Deserialize, IterateParams, Key, QueryResult, SUBSPACE_BITMAP_ID,
     SUBSPACE_BITMAP_TAG, SUBSPACE_BITMAP_TEXT, SUBSPACE_INDEXES, SUBSPACE_LOGS, Store, U32_LEN,
-    Value, ValueKey,
+    Value, ValueKey, WITH_SUBSPACE,
     write::{
         AnyClass, AnyKey, AssignedIds, Batch, BatchBuilder, BitmapClass, BitmapHash, Operation,
         ReportClass, ValueClass, ValueOp,
@@ -24,7 +25,7 @@ use crate::{
     },
 };
 
-use super::DocumentSet;
+use super::{DocumentSet, cache::ValueCache};
 
 #[cfg(feature = "test_mode")]
 #[allow(clippy::type_complexity)]
@@ -38,6 +39,30 @@ static BITMAPS: std::sync::LazyLock<
 
 impl Store {
     pub async fn get_value<U>(&self, key: impl Key) -> trc::Result<Option<U>>
+    where
+        U: Deserialize + 'static,
+    {
+        if let Some(cache) = self.value_cache().filter(|_| key.is_cacheable()) {
+            let cache_key = key.serialize(WITH_SUBSPACE);
+            if let Some(value) = cache.get(&cache_key) {
+                return U::deserialize(&value).map(Some);
+            }
+
+            let version = cache.version();
+            return match self.get_value_uncached::<Vec<u8>>(key).await? {
+                Some(value) => {
+                    let result = U::deserialize(&value).map(Some);
+                    cache.insert(cache_key, Arc::new(value), version);
+                    result
+                }
+                None => Ok(None),
+            };
+        }
+
+        self.get_value_uncached(key).await
+    }
+
+    async fn get_value_uncached<U>(&self, key: impl Key) -> trc::Result<Option<U>>
     where
         U: Deserialize + 'static,
     {
@@ -268,6 +293,11 @@ impl Store {
         let start_time = Instant::now();
         let ops = batch.ops.len();
 
+        let cached_keys = self
+            .value_cache()
+            .map(|_| ValueCache::cached_keys(&batch))
+            .unwrap_or_default();
+
         let result = match self {
             #[cfg(feature = "sqlite")]
             Self::SQLite(store) => store.write(batch).await,
@@ -282,6 +312,11 @@ impl Store {
             Self::None => Err(trc::StoreEvent::NotConfigured.into()),
         };
 
+        // Invalidate cached values, including on failure as the commit status might be unknown
+        if let Some(cache) = self.value_cache() {
+            cache.invalidate(cached_keys);
+        }
+
         trc::event!(
             Store(StoreEvent::DataWrite),
             Elapsed = start_time.elapsed(),
@@ -346,6 +381,10 @@ impl Store {
     }
 
     pub async fn delete_range(&self, from: impl Key, to: impl Key) -> trc::Result<()> {
+        if let Some(cache) = self.value_cache() {
+            cache.clear();
+        }
+
         match self {
             #[cfg(feature = "sqlite")]
             Self::SQLite(store) => store.delete_range(from, to).await,
@@ -17,6 +17,7 @@ pub use ahash;
 use ahash::AHashMap;
 use backend::{fs::FsStore, http::HttpStore, memory::StaticMemoryStore};
 pub use blake3;
+use dispatch::blob::BlobStoreHealth;
 pub use parking_lot;
 pub use rand;
 pub use roaring;
@@ -65,6 +66,9 @@ pub(crate) const WITH_SUBSPACE: u32 = 1;
 pub trait Key: Sync + Send + Clone {
     fn serialize(&self, flags: u32) -> Vec<u8>;
     fn subspace(&self) -> u8;
+    fn is_cacheable(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -197,6 +201,7 @@ pub enum Store {
 pub struct BlobStore {
     pub backend: BlobBackend,
     pub compression: CompressionAlgo,
+    pub health: Arc<BlobStoreHealth>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -271,6 +276,7 @@ impl From<FsStore> for BlobStore {
         BlobStore {
             backend: BlobBackend::Fs(Arc::new(store)),
             compression: CompressionAlgo::None,
+            health: Default::default(),
         }
     }
 }
@@ -281,6 +287,7 @@ impl From<S3Store> for BlobStore {
         BlobStore {
             backend: BlobBackend::S3(Arc::new(store)),
             compression: CompressionAlgo::None,
+            health: Default::default(),
         }
     }
 }
@@ -291,6 +298,7 @@ impl From<AzureStore> for BlobStore {
         BlobStore {
             backend: BlobBackend::Azure(Arc::new(store)),
             compression: CompressionAlgo::None,
+            health: Default::default(),
         }
     }
 }
@@ -320,6 +328,7 @@ impl From<Store> for BlobStore {
         BlobStore {
             backend: BlobBackend::Store(store),
             compression: CompressionAlgo::None,
+            health: Default::default(),
         }
     }
 }
@@ -335,6 +344,7 @@ impl Default for BlobStore {
         Self {
             backend: BlobBackend::Store(Store::None),
             compression: CompressionAlgo::None,
+            health: Default::default(),
         }
     }
 }
@@ -215,6 +215,10 @@ impl<T: AsRef<ValueClass<u32>> + Sync + Send + Clone> Key for ValueKey<T> {
         self.class.as_ref().subspace(self.collection)
     }
 
+    fn is_cacheable(&self) -> bool {
+        self.class.as_ref().is_cacheable(self.collection)
+    }
+
     fn serialize(&self, flags: u32) -> Vec<u8> {
         self.class.as_ref().serialize(
             self.account_id,
@@ -580,7 +584,7 @@ impl<T> ValueClass<T> {
     pub fn subspace(&self, collection: u8) -> u8 {
         match self {
             ValueClass::Property(field) => {
-                if *field == 84 && collection == 1 {
+                if is_mailbox_counter(*field, collection) {
                     SUBSPACE_COUNTER
                 } else {
                     SUBSPACE_PROPERTY
@@ -623,17 +627,32 @@ impl<T> ValueClass<T> {
         }
     }
 
+    pub fn is_cacheable(&self, collection: u8) -> bool {
+        match self {
+            ValueClass::Acl(_) => true,
+            // Mailbox and Identity properties, excluding counters
+            ValueClass::Property(_) => matches!(collection, 1 | 3) && !self.is_counter(collection),
+            _ => false,
+        }
+    }
+
     pub fn is_counter(&self, collection: u8) -> bool {
         match self {
             ValueClass::Directory(DirectoryClass::UsedQuota(_))
             | ValueClass::InMemory(InMemoryClass::Counter(_))
             | ValueClass::Queue(QueueClass::QuotaCount(_) | QueueClass::QuotaSize(_)) => true,
-            ValueClass::Property(84) if collection == 1 => true, // TODO: Find a more elegant way to do this
+            ValueClass::Property(field) => is_mailbox_counter(*field, collection),
             _ => false,
         }
     }
 }
 
+// Mailbox size (27), totalEmails (37), unreadEmails (42) and UID (84) counters
+#[inline(always)]
+fn is_mailbox_counter(field: u8, collection: u8) -> bool {
+    collection == 1 && matches!(field, 27 | 37 | 42 | 84)
+}
+
 impl From<ValueClass<u32>> for ValueKey<ValueClass<u32>> {
     fn from(class: ValueClass<u32>) -> Self {
         ValueKey {
@@ -365,6 +365,12 @@ impl Deserialize for String {
     }
 }
 
+impl Deserialize for Vec<u8> {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        Ok(bytes.to_vec())
+    }
+}
+
 impl Deserialize for u64 {
     fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
         Ok(u64::from_be_bytes(bytes.try_into().map_err(|_| {
@@ -195,7 +195,10 @@ impl TaskQueueEvent {
             TaskQueueEvent::Locked => "Task is locked by another process",
             TaskQueueEvent::BlobNotFound => "Blob not found for task",
             TaskQueueEvent::MetadataNotFound => "Metadata not found for task",
+            TaskQueueEvent::Deferred => "Task deferred",
             TaskQueueEvent::BayesTrain => "Bayesian training completed",
+            TaskQueueEvent::TextExtract => "Text extracted from attachment",
+            TaskQueueEvent::TextExtractError => "Failed to extract text from attachment",
         }
     }
 
@@ -205,7 +208,16 @@ impl TaskQueueEvent {
             TaskQueueEvent::Locked => "The task id is locked by another process",
             TaskQueueEvent::BlobNotFound => "The requested blob was not found for task",
             TaskQueueEvent::MetadataNotFound => "The metadata was not found for task",
+            TaskQueueEvent::Deferred => {
+                "The task was postponed until the blob store is available again"
+            }
             TaskQueueEvent::BayesTrain => "Bayesian training has been completed",
+            TaskQueueEvent::TextExtract => {
+                "Text was extracted from an attachment by the external extractor"
+            }
+            TaskQueueEvent::TextExtractError => {
+                "The external extractor failed to extract text from an attachment"
+            }
         }
     }
 }
@@ -441,6 +453,7 @@ impl SmtpEvent {
             SmtpEvent::RcptToGreylisted => "RCPT TO greylisted",
             SmtpEvent::TooManyRecipients => "Too many recipients",
             SmtpEvent::TooManyInvalidRcpt => "Too many invalid recipients",
+            SmtpEvent::HarvestAttempt => "Directory harvest attempt",
             SmtpEvent::RawInput => "Raw SMTP input received",
             SmtpEvent::RawOutput => "Raw SMTP output sent",
             SmtpEvent::MissingLocalHostname => "Missing local hostname",
@@ -459,6 +472,7 @@ impl SmtpEvent {
             SmtpEvent::MtPriorityInvalid => "Invalid MT-PRIORITY parameter",
             SmtpEvent::DsnDisabled => "DSN extension disabled",
             SmtpEvent::AuthNotAllowed => "Authentication not allowed",
+            SmtpEvent::AuthAttemptAlert => "Unexpected authentication attempt",
             SmtpEvent::AuthMechanismNotSupported => "Auth mechanism not supported",
             SmtpEvent::AuthExchangeTooLong => "Auth exchange too long",
             SmtpEvent::AlreadyAuthenticated => "Already authenticated",
@@ -477,6 +491,22 @@ impl SmtpEvent {
             SmtpEvent::UnsupportedParameter => "Unsupported parameter",
             SmtpEvent::SyntaxError => "Syntax error",
             SmtpEvent::RequestTooLarge => "Request too large",
+            SmtpEvent::ListPost => "Mailing list post distributed",
+            SmtpEvent::ListPostHeld => "Mailing list post held for moderation",
+            SmtpEvent::ListPostRejected => "Mailing list post rejected",
+            SmtpEvent::ListSubscribe => "Mailing list subscription",
+            SmtpEvent::ListUnsubscribe => "Mailing list unsubscription",
+            SmtpEvent::SmtpUtf8Required => "SMTPUTF8 required",
+            SmtpEvent::SmimeSigned => "Message signed using S/MIME",
+            SmtpEvent::SmimeEncrypted => "Message encrypted using S/MIME",
+            SmtpEvent::SmimeError => "S/MIME processing failed",
+            SmtpEvent::SmimeCertificateHarvested => "S/MIME certificate harvested",
+            SmtpEvent::JournalQueued => "Journal copy queued",
+            SmtpEvent::JournalDecryptFailed => "Journal copy decryption failed",
+            SmtpEvent::Archived => "Outbound message archived",
+            SmtpEvent::ArchiveFailed => "Failed to archive outbound message",
+            SmtpEvent::ExpnWithheld => "EXPN expansion withheld",
+            SmtpEvent::PipeliningViolation => "Pipelining protocol violation",
             SmtpEvent::ConnectionStart => "SMTP connection started",
             SmtpEvent::ConnectionEnd => "SMTP connection ended",
         }
@@ -554,6 +584,9 @@ impl SmtpEvent {
             SmtpEvent::TooManyInvalidRcpt => {
                 "The remote client exceeded the number of invalid RCPT TO commands allowed"
             }
+            SmtpEvent::HarvestAttempt => {
+                "The remote client probed too many unknown recipients and was disconnected"
+            }
             SmtpEvent::RawInput => "Raw SMTP input received",
             SmtpEvent::RawOutput => "Raw SMTP output sent",
             SmtpEvent::MissingLocalHostname => "The local hostname is missing in the configuration",
@@ -576,6 +609,9 @@ impl SmtpEvent {
             SmtpEvent::MtPriorityInvalid => "The MT-PRIORITY parameter is invalid",
             SmtpEvent::DsnDisabled => "The DSN extension is disabled",
             SmtpEvent::AuthNotAllowed => "Authentication is not allowed on this listener",
+            SmtpEvent::AuthAttemptAlert => {
+                "The remote client attempted to authenticate where it is not expected"
+            }
             SmtpEvent::AuthMechanismNotSupported => {
                 "The requested authentication mechanism is not supported"
             }
@@ -599,6 +635,46 @@ impl SmtpEvent {
             SmtpEvent::UnsupportedParameter => "The command contained an unsupported parameter",
             SmtpEvent::SyntaxError => "The command contained a syntax error",
             SmtpEvent::RequestTooLarge => "The request was too large",
+            SmtpEvent::ListPost => "A message was distributed to the members of a mailing list",
+            SmtpEvent::ListPostHeld => {
+                "A message posted to a moderated mailing list was held for approval"
+            }
+            SmtpEvent::ListPostRejected => {
+                "The sender is not allowed to post to the mailing list"
+            }
+            SmtpEvent::ListSubscribe => "An address was subscribed to a mailing list",
+            SmtpEvent::ListUnsubscribe => "An address was unsubscribed from a mailing list",
+            SmtpEvent::SmtpUtf8Required => {
+                "An internationalized address was used without the SMTPUTF8 parameter"
+            }
+            SmtpEvent::SmimeSigned => {
+                "An outbound message was signed with the domain's S/MIME certificate"
+            }
+            SmtpEvent::SmimeEncrypted => {
+                "An outbound message was encrypted using the S/MIME certificates of its recipients"
+            }
+            SmtpEvent::SmimeError => "An error occurred while signing or encrypting a message",
+            SmtpEvent::SmimeCertificateHarvested => {
+                "An S/MIME certificate was collected from a signed message of a verified sender"
+            }
+            SmtpEvent::JournalQueued => {
+                "A copy of an inbound message was queued for delivery to the journal address"
+            }
+            SmtpEvent::JournalDecryptFailed => {
+                "An encrypted message could not be decrypted with the escrowed keys, the journal copy was sent encrypted"
+            }
+            SmtpEvent::Archived => {
+                "An immutable copy of an outbound message was written to the domain's archive store"
+            }
+            SmtpEvent::ArchiveFailed => {
+                "An outbound message was queued but its copy could not be written to the archive store"
+            }
+            SmtpEvent::ExpnWithheld => {
+                "The mailing list exceeds the disclosure threshold, its members were not returned"
+            }
+            SmtpEvent::PipeliningViolation => {
+                "The remote client sent commands without waiting for a response it was required to wait for"
+            }
             SmtpEvent::ConnectionStart => "A new SMTP connection was started",
             SmtpEvent::ConnectionEnd => "The SMTP connection was ended",
             SmtpEvent::StartTlsAlready => "TLS is already active",
@@ -638,9 +714,11 @@ impl DeliveryEvent {
             DeliveryEvent::StartTlsUnavailable => "STARTTLS unavailable",
             DeliveryEvent::StartTlsError => "STARTTLS error",
             DeliveryEvent::StartTlsDisabled => "STARTTLS disabled",
+            DeliveryEvent::SmtpUtf8Unavailable => "SMTPUTF8 unavailable",
             DeliveryEvent::ImplicitTlsError => "Implicit TLS error",
             DeliveryEvent::ConcurrencyLimitExceeded => "Concurrency limit exceeded",
             DeliveryEvent::RateLimitExceeded => "Rate limit exceeded",
+            DeliveryEvent::WarmupLimitReached => "IP warm-up limit reached",
             DeliveryEvent::DoubleBounce => "Discarding message after double bounce",
             DeliveryEvent::DsnSuccess => "DSN success notification",
             DeliveryEvent::DsnTempFail => "DSN temporary failure notification",
@@ -690,10 +768,16 @@ impl DeliveryEvent {
                 "STARTTLS has been disabled in the configuration for this host"
             }
             DeliveryEvent::ImplicitTlsError => "Error starting implicit TLS",
+            DeliveryEvent::SmtpUtf8Unavailable => {
+                "The remote server does not support SMTPUTF8 and the message could not be downgraded"
+            }
             DeliveryEvent::ConcurrencyLimitExceeded => {
                 "The concurrency limit was exceeded for the remote host"
             }
             DeliveryEvent::RateLimitExceeded => "The rate limit was exceeded for the remote host",
+            DeliveryEvent::WarmupLimitReached => {
+                "The daily warm-up limit was reached for the source IP"
+            }
             DeliveryEvent::DoubleBounce => "The message was discarded after a double bounce",
             DeliveryEvent::DsnSuccess => "A success delivery status notification was created",
             DeliveryEvent::DsnTempFail => {
@@ -723,6 +807,15 @@ impl QueueEvent {
             QueueEvent::QueueDsn => "Queued DSN for delivery",
             QueueEvent::QueueAutogenerated => "Queued autogenerated message for delivery",
             QueueEvent::BackPressure => "Queue backpressure detected",
+            QueueEvent::RecipientSuppressed => "Recipient added to suppression list",
+            QueueEvent::ModerationHeld => "Message held for moderation",
+            QueueEvent::ModerationApproved => "Held message approved",
+            QueueEvent::ModerationRejected => "Held message rejected",
+            QueueEvent::AdmissionPaused => "Queue admission paused",
+            QueueEvent::AdmissionResumed => "Queue admission resumed",
+            QueueEvent::AdmissionRejected => "Message rejected by queue admission control",
+            QueueEvent::NodeJoined => "Node joined the queue cluster",
+            QueueEvent::NodeLeft => "Node left the queue cluster",
         }
     }
 
@@ -744,6 +837,33 @@ impl QueueEvent {
             QueueEvent::BackPressure => {
                 "Queue congested, processing can't keep up with incoming message rate"
             }
+            QueueEvent::RecipientSuppressed => {
+                "The recipient was suppressed for the sender after a hard bounce or complaint"
+            }
+            QueueEvent::ModerationHeld => {
+                "A message submitted by a moderated account is awaiting administrator approval"
+            }
+            QueueEvent::ModerationApproved => {
+                "A message held for moderation was approved and queued for delivery"
+            }
+            QueueEvent::ModerationRejected => {
+                "A message held for moderation was rejected and discarded"
+            }
+            QueueEvent::AdmissionPaused => {
+                "New messages are being temporarily rejected due to store pressure or queue backlog"
+            }
+            QueueEvent::AdmissionResumed => {
+                "The stores and queue recovered and new messages are being accepted again"
+            }
+            QueueEvent::AdmissionRejected => {
+                "A new message was temporarily rejected because the queue is not accepting messages"
+            }
+            QueueEvent::NodeJoined => {
+                "A node started processing the queue, messages were rebalanced across nodes"
+            }
+            QueueEvent::NodeLeft => {
+                "A node stopped processing the queue, its messages were taken over by other nodes"
+            }
         }
     }
 }
@@ -976,6 +1096,7 @@ impl MtaHookEvent {
             MtaHookEvent::ActionReject => "MTA hook action: Reject",
             MtaHookEvent::ActionQuarantine => "MTA hook action: Quarantine",
             MtaHookEvent::Error => "MTA hook error",
+            MtaHookEvent::Disabled => "MTA hook disabled",
         }
     }
 
@@ -986,6 +1107,9 @@ impl MtaHookEvent {
             MtaHookEvent::ActionReject => "The MTA hook requested to reject the message",
             MtaHookEvent::ActionQuarantine => "The MTA hook requested to quarantine the message",
             MtaHookEvent::Error => "An error occurred with the MTA hook",
+            MtaHookEvent::Disabled => {
+                "The MTA hook was temporarily disabled after failing repeatedly"
+            }
         }
     }
 }
@@ -1013,11 +1137,14 @@ impl SpamEvent {
         match self {
             SpamEvent::Pyzor => "Pyzor success",
             SpamEvent::PyzorError => "Pyzor error",
+            SpamEvent::Rspamd => "Rspamd success",
+            SpamEvent::RspamdError => "Rspamd error",
             SpamEvent::Train => "Training spam filter",
             SpamEvent::TrainBalance => "Spam filter model balance verify",
             SpamEvent::TrainError => "Error training spam filter",
             SpamEvent::Classify => "Classifying message for spam",
             SpamEvent::ClassifyError => "Not enough training data for spam filter",
+            SpamEvent::Result => "Spam filter result",
             SpamEvent::Dnsbl => "DNSBL query",
             SpamEvent::DnsblError => "Error querying DNSBL",
         }
@@ -1026,11 +1153,14 @@ impl SpamEvent {
     pub fn explain(&self) -> &'static str {
         match self {
             SpamEvent::PyzorError => "An error occurred with Pyzor",
+            SpamEvent::Rspamd => "Rspamd request successful",
+            SpamEvent::RspamdError => "An error occurred with Rspamd",
             SpamEvent::Train => "The spam filter is being trained with the message",
             SpamEvent::TrainBalance => "The spam filter training data is verified for balance",
             SpamEvent::TrainError => "An error occurred while training the spam filter",
             SpamEvent::Classify => "The message is being classified for spam",
             SpamEvent::ClassifyError => "There is not enough training data for the spam filter",
+            SpamEvent::Result => "The spam filter classified the message",
             SpamEvent::Pyzor => "Pyzor query successful",
             SpamEvent::Dnsbl => "The DNSBL query was successful",
             SpamEvent::DnsblError => "An error occurred while querying the DNSBL",
@@ -1053,6 +1183,11 @@ impl SieveEvent {
             SieveEvent::UnexpectedError => "Unexpected Sieve error",
             SieveEvent::NotSupported => "Sieve action not supported",
             SieveEvent::QuotaExceeded => "Sieve quota exceeded",
+            SieveEvent::AutoResponseSuppressed => "Sieve auto-response suppressed",
+            SieveEvent::LimitExceeded => "Sieve limit exceeded",
+            SieveEvent::ScriptDisabled => "Sieve script disabled",
+            SieveEvent::Notify => "Sieve notification sent",
+            SieveEvent::NotifyFailed => "Sieve notification failed",
         }
     }
 
@@ -1072,6 +1207,21 @@ impl SieveEvent {
             SieveEvent::UnexpectedError => "An unexpected error occurred with the Sieve script",
             SieveEvent::NotSupported => "The Sieve action is not supported",
             SieveEvent::QuotaExceeded => "The Sieve quota was exceeded",
+            SieveEvent::AutoResponseSuppressed => {
+                "An auto-response to an automatically generated message was suppressed"
+            }
+            SieveEvent::LimitExceeded => {
+                "The Sieve script exceeded its execution limits and was aborted"
+            }
+            SieveEvent::ScriptDisabled => {
+                "The Sieve script was temporarily disabled after repeatedly exceeding its limits"
+            }
+            SieveEvent::Notify => {
+                "A notification requested by a Sieve script was delivered through an external service"
+            }
+            SieveEvent::NotifyFailed => {
+                "The external service failed to deliver a notification requested by a Sieve script"
+            }
         }
     }
 }
@@ -1151,6 +1301,11 @@ impl ServerEvent {
             ServerEvent::StartupError => "Server startup error",
             ServerEvent::ThreadError => "Server thread error",
             ServerEvent::Licensing => "Server licensing event",
+            ServerEvent::ChannelFull => "Internal channel full",
+            ServerEvent::Restart => concat!(
+                "Restarting Stalwart Mail Server v",
+                env!("CARGO_PKG_VERSION")
+            ),
         }
     }
 
@@ -1161,6 +1316,12 @@ impl ServerEvent {
             ServerEvent::StartupError => "An error occurred while starting the server",
             ServerEvent::ThreadError => "An error occurred with a server thread",
             ServerEvent::Licensing => "A licensing event occurred",
+            ServerEvent::ChannelFull => {
+                "An internal channel reached its capacity, senders are waiting for it to drain"
+            }
+            ServerEvent::Restart => {
+                "A restart was requested, the server is draining sessions before restarting"
+            }
         }
     }
 }
@@ -1172,6 +1333,7 @@ impl TelemetryEvent {
             TelemetryEvent::LogError => "Log collector error",
             TelemetryEvent::WebhookError => "Webhook collector error",
             TelemetryEvent::JournalError => "Journal collector error",
+            TelemetryEvent::EventLogError => "Event log collector error",
             TelemetryEvent::OtelExporterError => "OpenTelemetry exporter error",
             TelemetryEvent::OtelMetricsExporterError => "OpenTelemetry metrics exporter error",
             TelemetryEvent::PrometheusExporterError => "Prometheus exporter error",
@@ -1184,6 +1346,9 @@ impl TelemetryEvent {
             TelemetryEvent::LogError => "An error occurred with the log collector",
             TelemetryEvent::WebhookError => "An error occurred with the webhook collector",
             TelemetryEvent::JournalError => "An error occurred with the journal collector",
+            TelemetryEvent::EventLogError => {
+                "An error occurred with the Windows event log collector"
+            }
             TelemetryEvent::OtelExporterError => {
                 "An error occurred with the OpenTelemetry exporter"
             }
@@ -1273,6 +1438,8 @@ impl PurgeEvent {
             PurgeEvent::InProgress => "Active purge in progress",
             PurgeEvent::AutoExpunge => "Auto-expunge executed",
             PurgeEvent::TombstoneCleanup => "Tombstone cleanup executed",
+            PurgeEvent::MessageExpiry => "Expired messages deleted",
+            PurgeEvent::LegalHold => "Purge skipped for account on legal hold",
         }
     }
 
@@ -1285,6 +1452,10 @@ impl PurgeEvent {
             PurgeEvent::InProgress => "An active purge is in progress",
             PurgeEvent::AutoExpunge => "Auto-expunge has been executed",
             PurgeEvent::TombstoneCleanup => "Tombstone cleanup has been executed",
+            PurgeEvent::MessageExpiry => "Messages past their expiry time have been deleted",
+            PurgeEvent::LegalHold => {
+                "Deleted messages were retained because the account is on legal hold"
+            }
         }
     }
 }
@@ -1546,6 +1717,10 @@ impl StoreEvent {
             StoreEvent::UnexpectedError => "Unexpected store error",
             StoreEvent::CryptoError => "Store crypto error",
             StoreEvent::BlobMissingMarker => "Blob missing marker",
+            StoreEvent::CapacityForecast => "Storage capacity forecast",
+            StoreEvent::BlobStoreUnavailable => "Blob store unavailable",
+            StoreEvent::BlobStoreDegraded => "Blob store degraded",
+            StoreEvent::BlobStoreRecovered => "Blob store recovered",
             StoreEvent::SqlQuery => "SQL query executed",
             StoreEvent::LdapQuery => "LDAP query executed",
             StoreEvent::LdapBind => "LDAP bind operation",
@@ -1583,6 +1758,16 @@ impl StoreEvent {
             StoreEvent::UnexpectedError => "An unexpected store error occurred",
             StoreEvent::CryptoError => "A store crypto error occurred",
             StoreEvent::BlobMissingMarker => "The blob is missing a marker",
+            StoreEvent::CapacityForecast => {
+                "Storage usage is projected to reach its limit within the alert threshold"
+            }
+            StoreEvent::BlobStoreUnavailable => {
+                "The blob store is temporarily unavailable, try again later"
+            }
+            StoreEvent::BlobStoreDegraded => {
+                "The blob store is failing, message contents are unavailable until it recovers"
+            }
+            StoreEvent::BlobStoreRecovered => "The blob store is available again",
             StoreEvent::SqlQuery => "An SQL query was executed",
             StoreEvent::LdapQuery => "An LDAP query was executed",
             StoreEvent::LdapBind => "An LDAP bind operation was executed",
@@ -1605,6 +1790,13 @@ impl MessageIngestEvent {
             MessageIngestEvent::ImapAppend => "Message appended via IMAP",
             MessageIngestEvent::JmapAppend => "Message appended via JMAP",
             MessageIngestEvent::Duplicate => "Skipping duplicate message",
+            MessageIngestEvent::Forward => "Message forwarded",
+            MessageIngestEvent::ForwardLoop => "Forwarding loop detected",
+            MessageIngestEvent::Fetch => "Messages fetched from external account",
+            MessageIngestEvent::FetchError => "Failed to fetch from external account",
+            MessageIngestEvent::Recall => "Message recalled",
+            MessageIngestEvent::KeywordAdded => "Message keyword added",
+            MessageIngestEvent::KeywordRemoved => "Message keyword removed",
             MessageIngestEvent::Error => "Message ingestion error",
         }
     }
@@ -1616,6 +1808,25 @@ impl MessageIngestEvent {
             MessageIngestEvent::ImapAppend => "The message has been appended via IMAP",
             MessageIngestEvent::JmapAppend => "The message has been appended via JMAP",
             MessageIngestEvent::Duplicate => "The message is a duplicate and has been skipped",
+            MessageIngestEvent::Forward => {
+                "The message has been forwarded to the account's forwarding addresses"
+            }
+            MessageIngestEvent::ForwardLoop => {
+                "The message was not forwarded as it has already been delivered to this account"
+            }
+            MessageIngestEvent::Fetch => {
+                "Messages have been downloaded from an external POP3 or IMAP account"
+            }
+            MessageIngestEvent::FetchError => {
+                "An error occurred while downloading messages from an external account"
+            }
+            MessageIngestEvent::Recall => {
+                "A message has been recalled by its sender from a local recipient's mailbox"
+            }
+            MessageIngestEvent::KeywordAdded => "A monitored keyword has been added to a message",
+            MessageIngestEvent::KeywordRemoved => {
+                "A monitored keyword has been removed from a message"
+            }
             MessageIngestEvent::Error => "An error occurred while ingesting the message",
         }
     }
@@ -1745,6 +1956,7 @@ impl AuthEvent {
             AuthEvent::Error => "Authentication error",
             AuthEvent::TokenExpired => "OAuth token expired",
             AuthEvent::ClientRegistration => "OAuth Client registration",
+            AuthEvent::Impersonation => "Account impersonation",
         }
     }
 
@@ -1757,6 +1969,9 @@ impl AuthEvent {
             AuthEvent::Error => "An error occurred with authentication",
             AuthEvent::TokenExpired => "OAuth authentication token has expired",
             AuthEvent::ClientRegistration => "OAuth client successfully registered",
+            AuthEvent::Impersonation => {
+                "An administrator opened another user's account in read-only mode"
+            }
         }
     }
 }
@@ -1792,6 +2007,13 @@ impl SecurityEvent {
             SecurityEvent::IpBlocked => "Blocked IP address",
             SecurityEvent::ScanBan => "Banned due to scan",
             SecurityEvent::Unauthorized => "Unauthorized access",
+            SecurityEvent::DataSearch => "Cross-account message search",
+            SecurityEvent::DataExport => "Messages exported",
+            SecurityEvent::EscrowDecryption => "Message decrypted with escrow key",
+            SecurityEvent::SessionTerminated => "Session terminated",
+            SecurityEvent::IpListUpdated => "IP list updated",
+            SecurityEvent::BootstrapEnabled => "Bootstrap mode enabled",
+            SecurityEvent::BootstrapCompleted => "Bootstrap completed",
         }
     }
 
@@ -1807,6 +2029,21 @@ impl SecurityEvent {
             SecurityEvent::LoiterBan => "IP address was banned due to multiple loitering events",
             SecurityEvent::IpBlocked => "Rejected connection from blocked IP address",
             SecurityEvent::Unauthorized => "Account does not have permission to access resource",
+            SecurityEvent::DataSearch => "An administrator searched messages across accounts",
+            SecurityEvent::DataExport => {
+                "An administrator exported messages from one or more accounts"
+            }
+            SecurityEvent::EscrowDecryption => {
+                "An encrypted message was decrypted with an escrowed key for its journal copy"
+            }
+            SecurityEvent::SessionTerminated => "An administrator terminated an active session",
+            SecurityEvent::IpListUpdated => "An administrator modified the IP allow or block list",
+            SecurityEvent::BootstrapEnabled => {
+                "The server can be provisioned using the one-time bootstrap token"
+            }
+            SecurityEvent::BootstrapCompleted => {
+                "Bootstrap was completed and the bootstrap token was revoked"
+            }
         }
     }
 }
@@ -40,8 +40,13 @@ impl EventType {
                 | StoreEvent::NotConfigured
                 | StoreEvent::NotSupported
                 | StoreEvent::UnexpectedError
-                | StoreEvent::CryptoError => Level::Error,
-                StoreEvent::BlobMissingMarker | StoreEvent::HttpStoreError => Level::Warn,
+                | StoreEvent::CryptoError
+                | StoreEvent::BlobStoreUnavailable => Level::Error,
+                StoreEvent::BlobMissingMarker
+                | StoreEvent::HttpStoreError
+                | StoreEvent::CapacityForecast
+                | StoreEvent::BlobStoreDegraded => Level::Warn,
+                StoreEvent::BlobStoreRecovered => Level::Info,
             },
             EventType::Jmap(_) => Level::Debug,
             EventType::Imap(event) => match event {
@@ -133,6 +138,7 @@ impl EventType {
                 | SmtpEvent::RcptToRewritten
                 | SmtpEvent::RcptToMissing
                 | SmtpEvent::RequireTlsDisabled
+                | SmtpEvent::SmtpUtf8Required
                 | SmtpEvent::DeliverByDisabled
                 | SmtpEvent::DeliverByInvalid
                 | SmtpEvent::FutureReleaseDisabled
@@ -157,7 +163,13 @@ impl EventType {
                 | SmtpEvent::UnsupportedParameter
                 | SmtpEvent::SyntaxError
                 | SmtpEvent::Error => Level::Debug,
-                SmtpEvent::MissingLocalHostname | SmtpEvent::RemoteIdNotFound => Level::Warn,
+                SmtpEvent::MissingLocalHostname
+                | SmtpEvent::RemoteIdNotFound
+                | SmtpEvent::AuthAttemptAlert
+                | SmtpEvent::SmimeError
+                | SmtpEvent::JournalDecryptFailed
+                | SmtpEvent::ArchiveFailed
+                | SmtpEvent::HarvestAttempt => Level::Warn,
                 SmtpEvent::ConcurrencyLimitExceeded
                 | SmtpEvent::TransferLimitExceeded
                 | SmtpEvent::RateLimitExceeded
@@ -196,6 +208,18 @@ impl EventType {
                 | SmtpEvent::AuthMechanismNotSupported
                 | SmtpEvent::ExpnDisabled
                 | SmtpEvent::RequestTooLarge
+                | SmtpEvent::ListPost
+                | SmtpEvent::ListPostHeld
+                | SmtpEvent::ListPostRejected
+                | SmtpEvent::ListSubscribe
+                | SmtpEvent::ListUnsubscribe
+                | SmtpEvent::SmimeSigned
+                | SmtpEvent::SmimeEncrypted
+                | SmtpEvent::SmimeCertificateHarvested
+                | SmtpEvent::JournalQueued
+                | SmtpEvent::Archived
+                | SmtpEvent::ExpnWithheld
+                | SmtpEvent::PipeliningViolation
                 | SmtpEvent::TooManyRecipients => Level::Info,
                 SmtpEvent::RawInput | SmtpEvent::RawOutput => Level::Trace,
             },
@@ -228,7 +252,7 @@ impl EventType {
             EventType::Auth(cause) => match cause {
                 AuthEvent::Failed | AuthEvent::TokenExpired => Level::Debug,
                 AuthEvent::MissingTotp => Level::Trace,
-                AuthEvent::TooManyAttempts => Level::Warn,
+                AuthEvent::TooManyAttempts | AuthEvent::Impersonation => Level::Warn,
                 AuthEvent::Error => Level::Error,
                 AuthEvent::Success | AuthEvent::ClientRegistration => Level::Info,
             },
@@ -268,9 +292,11 @@ impl EventType {
                 PurgeEvent::Finished => Level::Debug,
                 PurgeEvent::Running => Level::Info,
                 PurgeEvent::Error => Level::Error,
-                PurgeEvent::InProgress | PurgeEvent::AutoExpunge | PurgeEvent::TombstoneCleanup => {
-                    Level::Debug
-                }
+                PurgeEvent::InProgress
+                | PurgeEvent::AutoExpunge
+                | PurgeEvent::TombstoneCleanup
+                | PurgeEvent::MessageExpiry
+                | PurgeEvent::LegalHold => Level::Debug,
             },
             EventType::Eval(event) => match event {
                 EvalEvent::Error | EvalEvent::StoreNotFound => Level::Debug,
@@ -278,10 +304,12 @@ impl EventType {
                 EvalEvent::DirectoryNotFound => Level::Warn,
             },
             EventType::Server(event) => match event {
-                ServerEvent::Startup | ServerEvent::Shutdown | ServerEvent::Licensing => {
-                    Level::Info
-                }
+                ServerEvent::Startup
+                | ServerEvent::Shutdown
+                | ServerEvent::Licensing
+                | ServerEvent::Restart => Level::Info,
                 ServerEvent::StartupError | ServerEvent::ThreadError => Level::Error,
+                ServerEvent::ChannelFull => Level::Warn,
             },
             EventType::Acme(event) => match event {
                 AcmeEvent::DnsRecordCreated
@@ -325,8 +353,13 @@ impl EventType {
                 | SieveEvent::QuotaExceeded
                 | SieveEvent::ListNotFound
                 | SieveEvent::ScriptNotFound
-                | SieveEvent::MessageTooLarge => Level::Warn,
-                SieveEvent::SendMessage => Level::Info,
+                | SieveEvent::MessageTooLarge
+                | SieveEvent::LimitExceeded
+                | SieveEvent::ScriptDisabled
+                | SieveEvent::NotifyFailed => Level::Warn,
+                SieveEvent::SendMessage
+                | SieveEvent::AutoResponseSuppressed
+                | SieveEvent::Notify => Level::Info,
                 SieveEvent::UnexpectedError => Level::Error,
                 SieveEvent::ActionAccept
                 | SieveEvent::RuntimeError
@@ -336,6 +369,8 @@ impl EventType {
             },
             EventType::Spam(event) => match event {
                 SpamEvent::PyzorError
+                | SpamEvent::RspamdError
+                | SpamEvent::Rspamd
                 | SpamEvent::TrainError
                 | SpamEvent::DnsblError
                 | SpamEvent::Pyzor
@@ -344,6 +379,7 @@ impl EventType {
                 | SpamEvent::ClassifyError
                 | SpamEvent::TrainBalance
                 | SpamEvent::Dnsbl => Level::Debug,
+                SpamEvent::Result => Level::Info,
             },
             EventType::Http(event) => match event {
                 HttpEvent::ConnectionStart | HttpEvent::ConnectionEnd => Level::Debug,
@@ -378,7 +414,10 @@ impl EventType {
                 TaskQueueEvent::BlobNotFound
                 | TaskQueueEvent::Locked
                 | TaskQueueEvent::BayesTrain
-                | TaskQueueEvent::MetadataNotFound => Level::Debug,
+                | TaskQueueEvent::MetadataNotFound
+                | TaskQueueEvent::Deferred
+                | TaskQueueEvent::TextExtract => Level::Debug,
+                TaskQueueEvent::TextExtractError => Level::Warn,
             },
             EventType::Dmarc(_) => Level::Debug,
             EventType::Spf(_) => Level::Debug,
@@ -406,7 +445,7 @@ impl EventType {
                 | MtaHookEvent::ActionDiscard
                 | MtaHookEvent::ActionReject
                 | MtaHookEvent::ActionQuarantine => Level::Info,
-                MtaHookEvent::Error => Level::Warn,
+                MtaHookEvent::Error | MtaHookEvent::Disabled => Level::Warn,
             },
             EventType::Dane(event) => match event {
                 DaneEvent::AuthenticationSuccess
@@ -443,7 +482,9 @@ impl EventType {
                 | DeliveryEvent::StartTlsUnavailable
                 | DeliveryEvent::StartTlsError
                 | DeliveryEvent::StartTlsDisabled
+                | DeliveryEvent::SmtpUtf8Unavailable
                 | DeliveryEvent::ImplicitTlsError
+                | DeliveryEvent::WarmupLimitReached
                 | DeliveryEvent::DoubleBounce => Level::Info,
                 DeliveryEvent::ConcurrencyLimitExceeded
                 | DeliveryEvent::RateLimitExceeded
@@ -460,7 +501,7 @@ impl EventType {
                 DeliveryEvent::RawInput | DeliveryEvent::RawOutput => Level::Trace,
             },
             EventType::Queue(event) => match event {
-                QueueEvent::BackPressure => Level::Warn,
+                QueueEvent::BackPressure | QueueEvent::AdmissionPaused => Level::Warn,
                 QueueEvent::QueueMessage
                 | QueueEvent::QueueMessageAuthenticated
                 | QueueEvent::QueueReport
@@ -469,7 +510,15 @@ impl EventType {
                 | QueueEvent::RateLimitExceeded
                 | QueueEvent::ConcurrencyLimitExceeded
                 | QueueEvent::Rescheduled
-                | QueueEvent::QuotaExceeded => Level::Info,
+                | QueueEvent::QuotaExceeded
+                | QueueEvent::RecipientSuppressed
+                | QueueEvent::ModerationHeld
+                | QueueEvent::ModerationApproved
+                | QueueEvent::ModerationRejected
+                | QueueEvent::AdmissionResumed
+                | QueueEvent::AdmissionRejected
+                | QueueEvent::NodeJoined
+                | QueueEvent::NodeLeft => Level::Info,
                 QueueEvent::Locked | QueueEvent::BlobNotFound => Level::Debug,
             },
             EventType::TlsRpt(event) => match event {
@@ -524,7 +573,13 @@ impl EventType {
                 | MessageIngestEvent::Spam
                 | MessageIngestEvent::ImapAppend
                 | MessageIngestEvent::JmapAppend
-                | MessageIngestEvent::Duplicate => Level::Info,
+                | MessageIngestEvent::Duplicate
+                | MessageIngestEvent::Forward
+                | MessageIngestEvent::Fetch
+                | MessageIngestEvent::Recall
+                | MessageIngestEvent::KeywordAdded
+                | MessageIngestEvent::KeywordRemoved => Level::Info,
+                MessageIngestEvent::ForwardLoop | MessageIngestEvent::FetchError => Level::Warn,
                 MessageIngestEvent::Error => Level::Error,
             },
             EventType::Security(_) => Level::Info,
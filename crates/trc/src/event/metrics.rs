@@ -36,6 +36,10 @@ impl MetricType {
             Self::QueueCount => "queue.count",
             Self::UserCount => "user.count",
             Self::DomainCount => "domain.count",
+            Self::StateChannelUsage => "ipc.state.queued",
+            Self::HousekeeperChannelUsage => "ipc.housekeeper.queued",
+            Self::QueueChannelUsage => "ipc.queue.queued",
+            Self::ReportChannelUsage => "ipc.report.queued",
         }
     }
 
@@ -68,6 +72,10 @@ impl MetricType {
             Self::QueueCount => "Total number of messages in the queue",
             Self::UserCount => "Total number of users",
             Self::DomainCount => "Total number of domains",
+            Self::StateChannelUsage => "Events waiting in the state change channel",
+            Self::HousekeeperChannelUsage => "Events waiting in the housekeeper channel",
+            Self::QueueChannelUsage => "Events waiting in the queue manager channel",
+            Self::ReportChannelUsage => "Events waiting in the report scheduler channel",
         }
     }
 
@@ -100,6 +108,10 @@ impl MetricType {
             Self::QueueCount => "messages",
             Self::UserCount => "users",
             Self::DomainCount => "domains",
+            Self::StateChannelUsage
+            | Self::HousekeeperChannelUsage
+            | Self::QueueChannelUsage
+            | Self::ReportChannelUsage => "events",
         }
     }
 
@@ -132,6 +144,10 @@ impl MetricType {
             Self::QueueCount => 24,
             Self::UserCount => 25,
             Self::DomainCount => 26,
+            Self::StateChannelUsage => 27,
+            Self::HousekeeperChannelUsage => 28,
+            Self::QueueChannelUsage => 29,
+            Self::ReportChannelUsage => 30,
         }
     }
 
@@ -164,6 +180,10 @@ impl MetricType {
             24 => Some(Self::QueueCount),
             25 => Some(Self::UserCount),
             26 => Some(Self::DomainCount),
+            27 => Some(Self::StateChannelUsage),
+            28 => Some(Self::HousekeeperChannelUsage),
+            29 => Some(Self::QueueChannelUsage),
+            30 => Some(Self::ReportChannelUsage),
             _ => None,
         }
     }
@@ -197,6 +217,10 @@ impl MetricType {
             "queue.count" => Some(Self::QueueCount),
             "user.count" => Some(Self::UserCount),
             "domain.count" => Some(Self::DomainCount),
+            "ipc.state.queued" => Some(Self::StateChannelUsage),
+            "ipc.housekeeper.queued" => Some(Self::HousekeeperChannelUsage),
+            "ipc.queue.queued" => Some(Self::QueueChannelUsage),
+            "ipc.report.queued" => Some(Self::ReportChannelUsage),
             _ => None,
         }
     }
@@ -230,6 +254,10 @@ impl MetricType {
             Self::QueueCount,
             Self::UserCount,
             Self::DomainCount,
+            Self::StateChannelUsage,
+            Self::HousekeeperChannelUsage,
+            Self::QueueChannelUsage,
+            Self::ReportChannelUsage,
         ]
     }
 }
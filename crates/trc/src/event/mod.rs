@@ -184,6 +184,11 @@ impl Error {
         self.0.inner == EventType::Store(StoreEvent::AssertValueFailed)
     }
 
+    #[inline(always)]
+    pub fn is_unavailable(&self) -> bool {
+        self.0.inner == EventType::Store(StoreEvent::BlobStoreUnavailable)
+    }
+
     pub fn key(&self, key: Key) -> Option<&Value> {
         self.0
             .keys
@@ -363,6 +368,7 @@ impl StoreEvent {
             Self::NotSupported => "Operation not supported",
             Self::UnexpectedError => "Unexpected error",
             Self::CryptoError => "Crypto error",
+            Self::BlobStoreUnavailable => "Blob store temporarily unavailable",
             _ => "Store error",
         }
     }
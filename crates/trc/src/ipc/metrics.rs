@@ -49,6 +49,11 @@ static SERVER_MEMORY: AtomicGauge = AtomicGauge::new(MetricType::ServerMemory);
 static QUEUE_COUNT: AtomicGauge = AtomicGauge::new(MetricType::QueueCount);
 static USER_COUNT: AtomicGauge = AtomicGauge::new(MetricType::UserCount);
 static DOMAIN_COUNT: AtomicGauge = AtomicGauge::new(MetricType::DomainCount);
+static STATE_CHANNEL_USAGE: AtomicGauge = AtomicGauge::new(MetricType::StateChannelUsage);
+static HOUSEKEEPER_CHANNEL_USAGE: AtomicGauge =
+    AtomicGauge::new(MetricType::HousekeeperChannelUsage);
+static QUEUE_CHANNEL_USAGE: AtomicGauge = AtomicGauge::new(MetricType::QueueChannelUsage);
+static REPORT_CHANNEL_USAGE: AtomicGauge = AtomicGauge::new(MetricType::ReportChannelUsage);
 
 const CONN_SMTP_IN: usize = 0;
 const CONN_SMTP_OUT: usize = 1;
@@ -226,9 +231,25 @@ impl Collector {
     }
 
     pub fn collect_gauges(is_enterprise: bool) -> impl Iterator<Item = &'static AtomicGauge> {
-        static E_GAUGES: &[&AtomicGauge] =
-            &[&SERVER_MEMORY, &QUEUE_COUNT, &USER_COUNT, &DOMAIN_COUNT];
-        static C_GAUGES: &[&AtomicGauge] = &[&SERVER_MEMORY, &USER_COUNT, &DOMAIN_COUNT];
+        static E_GAUGES: &[&AtomicGauge] = &[
+            &SERVER_MEMORY,
+            &QUEUE_COUNT,
+            &USER_COUNT,
+            &DOMAIN_COUNT,
+            &STATE_CHANNEL_USAGE,
+            &HOUSEKEEPER_CHANNEL_USAGE,
+            &QUEUE_CHANNEL_USAGE,
+            &REPORT_CHANNEL_USAGE,
+        ];
+        static C_GAUGES: &[&AtomicGauge] = &[
+            &SERVER_MEMORY,
+            &USER_COUNT,
+            &DOMAIN_COUNT,
+            &STATE_CHANNEL_USAGE,
+            &HOUSEKEEPER_CHANNEL_USAGE,
+            &QUEUE_CHANNEL_USAGE,
+            &REPORT_CHANNEL_USAGE,
+        ];
 
         if is_enterprise { E_GAUGES } else { C_GAUGES }
             .iter()
@@ -315,6 +336,10 @@ impl Collector {
             MetricType::SieveRequestTime => CONNECTION_METRICS[CONN_SIEVE].elapsed.average(),
             MetricType::UserCount => USER_COUNT.get() as f64,
             MetricType::DomainCount => DOMAIN_COUNT.get() as f64,
+            MetricType::StateChannelUsage => STATE_CHANNEL_USAGE.get() as f64,
+            MetricType::HousekeeperChannelUsage => HOUSEKEEPER_CHANNEL_USAGE.get() as f64,
+            MetricType::QueueChannelUsage => QUEUE_CHANNEL_USAGE.get() as f64,
+            MetricType::ReportChannelUsage => REPORT_CHANNEL_USAGE.get() as f64,
         }
     }
 
@@ -324,6 +349,10 @@ impl Collector {
             MetricType::QueueCount => QUEUE_COUNT.set(value),
             MetricType::UserCount => USER_COUNT.set(value),
             MetricType::DomainCount => DOMAIN_COUNT.set(value),
+            MetricType::StateChannelUsage => STATE_CHANNEL_USAGE.set(value),
+            MetricType::HousekeeperChannelUsage => HOUSEKEEPER_CHANNEL_USAGE.set(value),
+            MetricType::QueueChannelUsage => QUEUE_CHANNEL_USAGE.set(value),
+            MetricType::ReportChannelUsage => REPORT_CHANNEL_USAGE.set(value),
             _ => {}
         }
     }
@@ -514,7 +543,9 @@ impl EventType {
                 | SmtpEvent::RcptToMissing
                 | SmtpEvent::TooManyRecipients
                 | SmtpEvent::TooManyInvalidRcpt
+                | SmtpEvent::HarvestAttempt
                 | SmtpEvent::AuthMechanismNotSupported
+                | SmtpEvent::AuthAttemptAlert
                 | SmtpEvent::AuthExchangeTooLong
                 | SmtpEvent::CommandNotImplemented
                 | SmtpEvent::InvalidCommand
@@ -535,6 +566,7 @@ impl EventType {
                 AuthEvent::Success
                 | AuthEvent::Failed
                 | AuthEvent::TooManyAttempts
+                | AuthEvent::Impersonation
                 | AuthEvent::Error,
             ) => true,
             EventType::Config(_) => false,
@@ -574,14 +606,19 @@ impl EventType {
                 | SieveEvent::RuntimeError
                 | SieveEvent::UnexpectedError
                 | SieveEvent::NotSupported
-                | SieveEvent::QuotaExceeded,
+                | SieveEvent::QuotaExceeded
+                | SieveEvent::AutoResponseSuppressed
+                | SieveEvent::LimitExceeded
+                | SieveEvent::ScriptDisabled,
             ) => true,
             EventType::Spam(
                 SpamEvent::PyzorError
+                | SpamEvent::RspamdError
                 | SpamEvent::Train
                 | SpamEvent::TrainError
                 | SpamEvent::Classify
                 | SpamEvent::ClassifyError
+                | SpamEvent::Result
                 | SpamEvent::DnsblError,
             ) => true,
             EventType::PushSubscription(_) => true,
@@ -598,7 +635,9 @@ impl EventType {
             EventType::TaskQueue(
                 TaskQueueEvent::Index
                 | TaskQueueEvent::BlobNotFound
-                | TaskQueueEvent::MetadataNotFound,
+                | TaskQueueEvent::MetadataNotFound
+                | TaskQueueEvent::TextExtract
+                | TaskQueueEvent::TextExtractError,
             ) => true,
             EventType::Milter(
                 MilterEvent::ActionAccept
@@ -631,6 +670,7 @@ impl EventType {
                 | DeliveryEvent::ImplicitTlsError
                 | DeliveryEvent::ConcurrencyLimitExceeded
                 | DeliveryEvent::RateLimitExceeded
+                | DeliveryEvent::WarmupLimitReached
                 | DeliveryEvent::DoubleBounce
                 | DeliveryEvent::DsnSuccess
                 | DeliveryEvent::DsnTempFail
@@ -646,7 +686,14 @@ impl EventType {
                 | QueueEvent::BlobNotFound
                 | QueueEvent::RateLimitExceeded
                 | QueueEvent::ConcurrencyLimitExceeded
-                | QueueEvent::QuotaExceeded,
+                | QueueEvent::QuotaExceeded
+                | QueueEvent::RecipientSuppressed
+                | QueueEvent::ModerationHeld
+                | QueueEvent::ModerationApproved
+                | QueueEvent::ModerationRejected
+                | QueueEvent::AdmissionPaused
+                | QueueEvent::AdmissionResumed
+                | QueueEvent::AdmissionRejected,
             ) => true,
             EventType::TlsRpt(_) => false,
             EventType::MtaSts(
@@ -675,7 +722,8 @@ impl EventType {
                 | TelemetryEvent::OtelExporterError
                 | TelemetryEvent::OtelMetricsExporterError
                 | TelemetryEvent::PrometheusExporterError
-                | TelemetryEvent::JournalError,
+                | TelemetryEvent::JournalError
+                | TelemetryEvent::EventLogError,
             ) => true,
             _ => false,
         }
@@ -75,6 +75,7 @@ pub enum Value {
 pub enum Key {
     AccountName,
     AccountId,
+    Asn,
     BlobId,
     #[default]
     CausedBy,
@@ -82,6 +83,7 @@ pub enum Key {
     Code,
     Collection,
     Contents,
+    Country,
     Details,
     DkimFail,
     DkimNone,
@@ -94,6 +96,7 @@ pub enum Key {
     Domain,
     Due,
     Elapsed,
+    EnvId,
     Expires,
     From,
     Hostname,
@@ -130,6 +133,7 @@ pub enum Key {
     Total,
     TotalFailures,
     TotalSuccesses,
+    TraceId,
     Type,
     Uid,
     UidNext,
@@ -208,6 +212,13 @@ pub enum SecurityEvent {
     LoiterBan,
     IpBlocked,
     Unauthorized,
+    DataSearch,
+    DataExport,
+    EscrowDecryption,
+    SessionTerminated,
+    IpListUpdated,
+    BootstrapEnabled,
+    BootstrapCompleted,
 }
 
 #[event_type]
@@ -242,6 +253,9 @@ pub enum TaskQueueEvent {
     Locked,
     BlobNotFound,
     MetadataNotFound,
+    Deferred,
+    TextExtract,
+    TextExtractError,
 }
 
 #[event_type]
@@ -396,6 +410,7 @@ pub enum SmtpEvent {
     RcptToGreylisted,
     TooManyRecipients,
     TooManyInvalidRcpt,
+    HarvestAttempt,
     RawInput,
     RawOutput,
     MissingLocalHostname,
@@ -414,6 +429,7 @@ pub enum SmtpEvent {
     MtPriorityInvalid,
     DsnDisabled,
     AuthNotAllowed,
+    AuthAttemptAlert,
     AuthMechanismNotSupported,
     AuthExchangeTooLong,
     AlreadyAuthenticated,
@@ -432,6 +448,22 @@ pub enum SmtpEvent {
     UnsupportedParameter,
     SyntaxError,
     RequestTooLarge,
+    ListPost,
+    ListPostHeld,
+    ListPostRejected,
+    ListSubscribe,
+    ListUnsubscribe,
+    SmtpUtf8Required,
+    SmimeSigned,
+    SmimeEncrypted,
+    SmimeError,
+    SmimeCertificateHarvested,
+    JournalQueued,
+    JournalDecryptFailed,
+    Archived,
+    ArchiveFailed,
+    ExpnWithheld,
+    PipeliningViolation,
 }
 
 #[event_type]
@@ -468,12 +500,14 @@ pub enum DeliveryEvent {
     ImplicitTlsError,
     ConcurrencyLimitExceeded,
     RateLimitExceeded,
+    WarmupLimitReached,
     DoubleBounce,
     DsnSuccess,
     DsnTempFail,
     DsnPermFail,
     RawInput,
     RawOutput,
+    SmtpUtf8Unavailable,
 }
 
 #[event_type]
@@ -490,6 +524,15 @@ pub enum QueueEvent {
     ConcurrencyLimitExceeded,
     QuotaExceeded,
     BackPressure,
+    RecipientSuppressed,
+    ModerationHeld,
+    ModerationApproved,
+    ModerationRejected,
+    AdmissionPaused,
+    AdmissionResumed,
+    AdmissionRejected,
+    NodeJoined,
+    NodeLeft,
 }
 
 #[event_type]
@@ -589,6 +632,7 @@ pub enum MtaHookEvent {
     ActionReject,
     ActionQuarantine,
     Error,
+    Disabled,
 }
 
 #[event_type]
@@ -602,6 +646,8 @@ pub enum PushSubscriptionEvent {
 pub enum SpamEvent {
     Pyzor,
     PyzorError,
+    Rspamd,
+    RspamdError,
     Dnsbl,
     DnsblError,
     Train,
@@ -609,6 +655,7 @@ pub enum SpamEvent {
     TrainError,
     Classify,
     ClassifyError,
+    Result,
 }
 
 #[event_type]
@@ -625,6 +672,11 @@ pub enum SieveEvent {
     UnexpectedError,
     NotSupported,
     QuotaExceeded,
+    AutoResponseSuppressed,
+    LimitExceeded,
+    ScriptDisabled,
+    Notify,
+    NotifyFailed,
 }
 
 #[event_type]
@@ -661,6 +713,8 @@ pub enum ServerEvent {
     StartupError,
     ThreadError,
     Licensing,
+    ChannelFull,
+    Restart,
 }
 
 #[event_type]
@@ -672,6 +726,7 @@ pub enum TelemetryEvent {
     OtelMetricsExporterError,
     PrometheusExporterError,
     JournalError,
+    EventLogError,
 }
 
 #[event_type]
@@ -714,6 +769,8 @@ pub enum PurgeEvent {
     InProgress,
     AutoExpunge,
     TombstoneCleanup,
+    MessageExpiry,
+    LegalHold,
 }
 
 #[event_type]
@@ -840,9 +897,15 @@ pub enum StoreEvent {
     UnexpectedError,
     CryptoError,
     HttpStoreError,
+    BlobStoreUnavailable,
 
     // Warnings
     BlobMissingMarker,
+    CapacityForecast,
+    BlobStoreDegraded,
+
+    // Events
+    BlobStoreRecovered,
 
     // Traces
     DataWrite,
@@ -864,6 +927,13 @@ pub enum MessageIngestEvent {
     ImapAppend,
     JmapAppend,
     Duplicate,
+    Forward,
+    ForwardLoop,
+    Fetch,
+    FetchError,
+    Recall,
+    KeywordAdded,
+    KeywordRemoved,
     Error,
 }
 
@@ -932,6 +1002,7 @@ pub enum AuthEvent {
     MissingTotp,
     TooManyAttempts,
     ClientRegistration,
+    Impersonation,
     Error,
 }
 
@@ -979,6 +1050,10 @@ pub enum MetricType {
     SieveRequestTime,
     UserCount,
     DomainCount,
+    StateChannelUsage,
+    HousekeeperChannelUsage,
+    QueueChannelUsage,
+    ReportChannelUsage,
 }
 
 pub const TOTAL_EVENT_COUNT: usize = total_event_count!();
@@ -282,6 +282,12 @@ impl CacheItemWeight for Vec<MX> {
     }
 }
 
+impl CacheItemWeight for Vec<u8> {
+    fn weight(&self) -> u64 {
+        self.len() as u64 + std::mem::size_of::<Vec<u8>>() as u64
+    }
+}
+
 impl CacheItemWeight for Vec<String> {
     fn weight(&self) -> u64 {
         self.iter().map(|s| s.len()).sum::<usize>() as u64
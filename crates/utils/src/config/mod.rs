@@ -20,6 +20,8 @@ pub struct Config {
     pub keys: BTreeMap<String, String>,
     pub warnings: AHashMap<String, ConfigWarning>,
     pub errors: AHashMap<String, ConfigError>,
+    #[serde(skip)]
+    pub defaults: BTreeMap<String, String>,
     #[cfg(debug_assertions)]
     #[serde(skip)]
     pub keys_read: parking_lot::Mutex<ahash::AHashSet<String>>,
@@ -59,7 +61,21 @@ pub struct Rate {
 
 pub type Result<T> = std::result::Result<T, String>;
 
+pub const ENV_OVERRIDE_PREFIX: &str = "STALWART__";
+
 impl Config {
+    pub fn apply_env_overrides(&mut self) {
+        self.apply_overrides(std::env::vars());
+    }
+
+    pub fn apply_overrides(&mut self, vars: impl IntoIterator<Item = (String, String)>) {
+        for (name, value) in vars {
+            if let Some(key) = env_override_key(&name) {
+                self.keys.insert(key, value);
+            }
+        }
+    }
+
     pub async fn resolve_macros(&mut self, classes: &[&str]) {
         for macro_class in classes {
             self.resolve_macro_type(macro_class).await;
@@ -227,12 +243,38 @@ impl Config {
     }
 }
 
+// Maps an environment variable such as STALWART__SERVER__LISTENER__SMTP__BIND
+// to its configuration key. Key segments are separated by a double underscore
+// and single underscores are translated into dashes.
+pub fn env_override_key(name: &str) -> Option<String> {
+    let name = name.strip_prefix(ENV_OVERRIDE_PREFIX)?;
+    let mut key = String::with_capacity(name.len());
+
+    for (pos, segment) in name.split("__").enumerate() {
+        if segment.is_empty() {
+            return None;
+        }
+        if pos > 0 {
+            key.push('.');
+        }
+        for ch in segment.chars() {
+            match ch {
+                '_' => key.push('-'),
+                _ => key.push(ch.to_ascii_lowercase()),
+            }
+        }
+    }
+
+    (!key.is_empty()).then_some(key)
+}
+
 impl Clone for Config {
     fn clone(&self) -> Self {
         Self {
             keys: self.keys.clone(),
             warnings: self.warnings.clone(),
             errors: self.errors.clone(),
+            defaults: self.defaults.clone(),
             #[cfg(debug_assertions)]
             keys_read: Default::default(),
         }
@@ -279,3 +321,40 @@ impl From<(String, &str)> for ConfigKey {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, env_override_key};
+
+    #[test]
+    fn env_overrides() {
+        for (name, expected) in [
+            ("STALWART__server__hostname", Some("server.hostname")),
+            (
+                "STALWART__SERVER__LISTENER__SMTP__BIND",
+                Some("server.listener.smtp.bind"),
+            ),
+            ("STALWART__SPAM_FILTER__ENABLE", Some("spam-filter.enable")),
+            ("STALWART__", None),
+            ("STALWART__server____hostname", None),
+            ("STALWART_SERVER__HOSTNAME", None),
+            ("PATH", None),
+        ] {
+            assert_eq!(env_override_key(name).as_deref(), expected, "{name}");
+        }
+
+        let mut config = Config::default();
+        config
+            .parse("[server]\nhostname = \"mx.example.org\"\n")
+            .unwrap();
+        config.apply_overrides([
+            (
+                "STALWART__SERVER__HOSTNAME".to_string(),
+                "mx.example.com".to_string(),
+            ),
+            ("HOME".to_string(), "/root".to_string()),
+        ]);
+        assert_eq!(config.value("server.hostname"), Some("mx.example.com"));
+        assert_eq!(config.keys.len(), 1);
+    }
+}
@@ -52,7 +52,10 @@ impl Config {
 
         let value = match self.keys.get(&key) {
             Some(value) => value.as_str(),
-            None => default,
+            None => {
+                self.defaults.insert(key.clone(), default.to_string());
+                default
+            }
         };
         match T::parse_value(value) {
             Ok(value) => Some(value),
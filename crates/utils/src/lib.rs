@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{fmt::Display, sync::Arc};
+use std::{borrow::Cow, fmt::Display, sync::Arc};
 
 pub mod cache;
 pub mod codec;
@@ -352,6 +352,76 @@ impl ServerCertVerifier for DummyVerifier {
 }
 
 // Basic email sanitizer
+/// Returns the alternate IDNA form of an address or domain name, A-labels
+/// are converted to U-labels and vice versa.
+pub fn idna_alternate(value: &str) -> Option<String> {
+    let (local, domain) = match value.rsplit_once('@') {
+        Some((local, domain)) => (Some(local), domain),
+        None => (None, value),
+    };
+    let alternate = if domain.is_ascii() {
+        if !domain
+            .split('.')
+            .any(|label| label.len() > 4 && label[..4].eq_ignore_ascii_case("xn--"))
+        {
+            return None;
+        }
+        let (domain, result) = idna::domain_to_unicode(domain);
+        result.ok()?;
+        domain
+    } else {
+        idna::domain_to_ascii(domain).ok()?
+    };
+
+    if alternate != domain {
+        Some(match local {
+            Some(local) => format!("{local}@{alternate}"),
+            None => alternate,
+        })
+    } else {
+        None
+    }
+}
+
+/// Converts the domain of an internationalized address to its A-label form,
+/// returns `None` if the local part is not ASCII.
+pub fn ascii_address(address: &str) -> Option<Cow<'_, str>> {
+    if address.is_ascii() {
+        Some(Cow::Borrowed(address))
+    } else {
+        let (local, domain) = address.rsplit_once('@')?;
+        if local.is_ascii() {
+            Some(Cow::Owned(format!(
+                "{local}@{}",
+                idna::domain_to_ascii(domain).ok()?
+            )))
+        } else {
+            None
+        }
+    }
+}
+
+/// Normalizes a BCP 47 language tag such as "pt_BR" into its lowercase,
+/// hyphen separated form, rejecting syntactically invalid tags.
+pub fn sanitize_language(tag: &str) -> Option<String> {
+    let mut parts = tag.trim().split(['-', '_']);
+    let primary = parts.next()?;
+    if !(2..=3).contains(&primary.len()) || !primary.chars().all(|ch| ch.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let mut result = primary.to_ascii_lowercase();
+    for part in parts {
+        if part.is_empty() || part.len() > 8 || !part.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+            return None;
+        }
+        result.push('-');
+        result.push_str(&part.to_ascii_lowercase());
+    }
+
+    Some(result)
+}
+
 pub fn sanitize_email(email: &str) -> Option<String> {
     let mut result = String::with_capacity(email.len());
     let mut found_local = false;
@@ -0,0 +1,71 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, IMAPTest, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, handle: &IMAPTest) {
+    println!("Running IMAPSIEVE tests...");
+
+    // Create the mailbox the script is attached to and a source mailbox
+    for mailbox in ["ToDo", "Someday"] {
+        imap.send(&format!("CREATE {mailbox}")).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+    imap.send("APPEND Someday {43+}\r\nSubject: Buy milk\r\n\r\nDon't forget the milk!")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Copying a message into ToDo should trigger the script
+    imap.send("SELECT Someday").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("COPY 1 ToDo").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COPYUID");
+    assert_script_ran(handle, "imapsieve:COPY:ToDo:", true).await;
+
+    // Flag changes in other mailboxes should not trigger the script
+    imap.send("STORE 1 +FLAGS.SILENT (\\Answered)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert_script_ran(handle, "imapsieve:FLAG:ToDo:\\Answered", false).await;
+
+    // Flag changes in ToDo should trigger the script
+    imap.send("SELECT ToDo").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("STORE 1 +FLAGS.SILENT (\\Flagged)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert_script_ran(handle, "imapsieve:FLAG:ToDo:\\Flagged", true).await;
+
+    // APPEND is not listed in the rule causes
+    imap.send("APPEND ToDo {13+}\r\nSubject: Test").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert_script_ran(handle, "imapsieve:APPEND:ToDo:", false).await;
+
+    // Clean up
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for mailbox in ["ToDo", "Someday"] {
+        imap.send(&format!("DELETE {mailbox}")).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+}
+
+async fn assert_script_ran(handle: &IMAPTest, key: &str, expected: bool) {
+    assert_eq!(
+        handle
+            .server
+            .core
+            .storage
+            .lookup
+            .key_exists(key.to_string())
+            .await
+            .unwrap(),
+        expected,
+        "{key}"
+    );
+}
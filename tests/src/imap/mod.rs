@@ -13,9 +13,11 @@ pub mod condstore;
 pub mod copy_move;
 pub mod fetch;
 pub mod idle;
+pub mod imapsieve;
 pub mod mailbox;
 pub mod managesieve;
 pub mod pop;
+pub mod quota;
 pub mod search;
 pub mod store;
 pub mod thread;
@@ -202,6 +204,25 @@ private-key = "%{file:{PK}}%"
 [imap.protocol]
 uidplus = true
 
+[sieve.trusted.scripts.imapsieve]
+contents = '''
+require ["variables", "environment", "imapsieve"];
+
+global "key";
+
+if anyof(environment :is "imap.cause" "COPY", environment :is "imap.cause" "FLAG") {
+    set "key" "imapsieve:${env.imap.cause}:${env.imap.mailbox}:${env.imap.changedflags}";
+    if eval "!key_set('', key, 'ok', 3600)" {
+        discard;
+    }
+}
+'''
+
+[sieve.trusted.imapsieve.todo]
+mailbox = "ToDo"
+causes = ["COPY", "FLAG"]
+script = "imapsieve"
+
 [storage]
 data = "{STORE}"
 fts = "{STORE}"
@@ -212,6 +233,9 @@ directory = "{STORE}"
 [jmap.protocol]
 set.max-objects = 100000
 
+[jmap.email]
+max-messages = 10000
+
 [jmap.protocol.request]
 max-concurrent = 8
 
@@ -421,6 +445,9 @@ async fn init_imap_tests(store_id: &str, delete_if_exists: bool) -> IMAPTest {
             &["foobar@example.com"],
         )
         .await;
+    store
+        .set_test_quota("foobar@example.com", 1024 * 1024 * 1024)
+        .await;
     store
         .create_test_user(
             "popper@example.com",
@@ -500,6 +527,8 @@ pub async fn imap_tests() {
     idle::test(&mut imap, &mut imap_check).await;
     condstore::test(&mut imap, &mut imap_check).await;
     acl::test(&mut imap, &mut imap_check).await;
+    imapsieve::test(&mut imap, &handle).await;
+    quota::test().await;
 
     // Logout
     for imap in [&mut imap, &mut imap_check] {
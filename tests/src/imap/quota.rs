@@ -0,0 +1,87 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test() {
+    println!("Running QUOTA tests...");
+
+    // Bill has a storage quota of 1GB and the server limits accounts to 10000 messages
+    let mut imap = ImapConnection::connect(b"_q ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.send("AUTHENTICATE PLAIN {36+}\r\nAGZvb2JhckBleGFtcGxlLmNvbQBzZWNyZXQ=")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("CAPABILITY").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("QUOTA=RES-STORAGE")
+        .assert_contains("QUOTA=RES-MESSAGE");
+
+    // Obtain the quota root of the Inbox
+    imap.send("GETQUOTAROOT INBOX").await;
+    let (quota_root, total_messages) =
+        parse_quota_root(imap.assert_read(Type::Tagged, ResponseType::Ok).await);
+
+    // Appending a message increases the message count
+    imap.send("APPEND INBOX {35+}\r\nSubject: Quota test\r\n\r\nHello there!")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send(&format!("GETQUOTA {quota_root}")).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains(&format!(
+            "* QUOTA {quota_root} (STORAGE 0 1048576 MESSAGE {} 10000)",
+            total_messages + 1
+        ));
+
+    // Quota roots of other accounts are not accessible
+    imap.send("GETQUOTA \"#99999\"").await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+
+    // Expunging the message decreases the message count
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("UID SEARCH SUBJECT \"Quota test\"").await;
+    let uid = imap
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .iter()
+        .find_map(|line| line.strip_prefix("* SEARCH "))
+        .expect("Missing SEARCH response")
+        .to_string();
+    imap.send(&format!("UID STORE {uid} +FLAGS.SILENT (\\Deleted)"))
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send(&format!("UID EXPUNGE {uid}")).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("GETQUOTAROOT INBOX").await;
+    assert_eq!(
+        parse_quota_root(imap.assert_read(Type::Tagged, ResponseType::Ok).await),
+        (quota_root, total_messages)
+    );
+}
+
+fn parse_quota_root(response: Vec<String>) -> (String, u64) {
+    let quota_root = response
+        .iter()
+        .find_map(|line| line.strip_prefix("* QUOTAROOT \"INBOX\" "))
+        .expect("Missing QUOTAROOT response")
+        .to_string();
+    let total_messages = response
+        .iter()
+        .find_map(|line| {
+            line.strip_prefix(&format!("* QUOTA {quota_root} (STORAGE 0 1048576 MESSAGE "))
+        })
+        .and_then(|line| line.strip_suffix(" 10000)"))
+        .expect("Missing QUOTA response")
+        .parse()
+        .unwrap();
+
+    (quota_root, total_messages)
+}
@@ -10,7 +10,7 @@ use std::{
     time::Duration,
 };
 
-use common::listener::blocked::BLOCKED_IP_KEY;
+use common::listener::blocked::{IpListEntry, BLOCKED_IP_KEY};
 use imap_proto::ResponseType;
 use jmap_client::{
     client::{Client, Credentials},
@@ -19,6 +19,7 @@ use jmap_client::{
 };
 use jmap_proto::types::id::Id;
 use store::write::now;
+use utils::config::ipmask::IpAddrOrMask;
 
 use crate::{
     directory::internal::TestInternalDirectory,
@@ -117,8 +118,9 @@ pub async fn test(params: &mut JMAPTest) {
             .config
             .get(format!("{BLOCKED_IP_KEY}.127.0.0.1"))
             .await
-            .unwrap(),
-        Some(String::new())
+            .unwrap()
+            .map(|value| IpListEntry::parse(&value).source),
+        Some(Some("auto-ban.auth".to_string()))
     );
     ImapConnection::connect(b"_y ")
         .await
@@ -138,7 +140,7 @@ pub async fn test(params: &mut JMAPTest) {
         .data
         .blocked_ips
         .write()
-        .remove(&IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+        .remove(&IpAddrOrMask::Ip(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))));
 
     // Valid authentication requests should not be rate limited
     for _ in 0..110 {
@@ -71,6 +71,7 @@ pub mod sieve_script;
 pub mod stress_test;
 pub mod thread_get;
 pub mod thread_merge;
+pub mod troubleshoot;
 pub mod vacation_response;
 pub mod webhooks;
 pub mod websocket;
@@ -349,6 +350,9 @@ events = ["auth.*", "delivery.dsn*", "message-ingest.*", "security.authenticatio
 signature-key = "ovos-moles"
 throttle = "100ms"
 
+[sieve.untrusted.extensions.virustest]
+enable = false
+
 [sieve.untrusted.scripts."common"]
 contents = '''
 require "reject";
@@ -392,6 +396,7 @@ pub async fn jmap_tests() {
     websocket::test(&mut params).await;
     quota::test(&mut params).await;
     crypto::test(&mut params).await;
+    troubleshoot::test().await;
     blob::test(&mut params).await;
     purge::test(&mut params).await;
 
@@ -876,6 +881,23 @@ impl ManagementApi {
                     .unwrap_or_else(|err| panic!("{err}: {result}"))
             })
     }
+    pub async fn put_raw<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        body: impl Into<String>,
+    ) -> Result<Response<T>, String> {
+        self.request_raw(Method::PUT, query, Some(body.into()))
+            .await
+            .map(|result| {
+                serde_json::from_str::<Response<T>>(&result)
+                    .unwrap_or_else(|err| panic!("{err}: {result}"))
+            })
+    }
+
+    pub async fn get_raw(&self, query: &str) -> Result<String, String> {
+        self.request_raw(Method::GET, query, None).await
+    }
+
     pub async fn request<T: DeserializeOwned>(
         &self,
         method: Method,
@@ -6,7 +6,13 @@
 
 use ahash::AHashSet;
 use common::Server;
-use directory::{backend::internal::manage::ManageDirectory, QueryBy};
+use directory::{
+    backend::internal::{
+        manage::{ManageDirectory, UpdatePrincipal},
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+    },
+    QueryBy,
+};
 use email::mailbox::{INBOX_ID, JUNK_ID, TRASH_ID};
 use imap_proto::ResponseType;
 use jmap::email::delete::EmailDeletion;
@@ -193,6 +199,67 @@ pub async fn test(params: &mut JMAPTest) {
         );
     }
 
+    // Place the account on legal hold
+    server
+        .increment_token_revision(
+            server
+                .core
+                .storage
+                .data
+                .update_principal(UpdatePrincipal::by_id(account_id).with_updates(vec![
+                    PrincipalUpdate::set(PrincipalField::LegalHold, PrincipalValue::Integer(1)),
+                ]))
+                .await
+                .unwrap(),
+        )
+        .await;
+
+    // Deleted and expunged messages must be retained while the hold is active
+    client.email_destroy(&message_ids[0]).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    server.purge_account(account_id).await;
+    assert_eq!(
+        server
+            .get_document_ids(account_id, Collection::Email)
+            .await
+            .unwrap()
+            .unwrap()
+            .len(),
+        4
+    );
+    assert!(server
+        .core
+        .storage
+        .data
+        .delete_principal(QueryBy::Id(account_id))
+        .await
+        .is_err());
+
+    // Lift the hold and purge again
+    server
+        .increment_token_revision(
+            server
+                .core
+                .storage
+                .data
+                .update_principal(UpdatePrincipal::by_id(account_id).with_updates(vec![
+                    PrincipalUpdate::set(PrincipalField::LegalHold, PrincipalValue::Integer(0)),
+                ]))
+                .await
+                .unwrap(),
+        )
+        .await;
+    server.purge_account(account_id).await;
+    assert_eq!(
+        server
+            .get_document_ids(account_id, Collection::Email)
+            .await
+            .unwrap()
+            .unwrap()
+            .len(),
+        1
+    );
+
     // Delete account
     server
         .core
@@ -4,18 +4,21 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use jmap::email::delete::EmailDeletion;
 use jmap_client::{
     core::set::{SetError, SetErrorType},
     email, mailbox,
     sieve::query::{Comparator, Filter},
     Error,
 };
-use jmap_proto::types::id::Id;
+use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+use serde_json::{json, Value};
 use std::{
     fs,
     path::PathBuf,
     time::{Duration, Instant},
 };
+use store::write::{now, BatchBuilder, F_VALUE};
 
 use crate::{
     directory::internal::TestInternalDirectory,
@@ -24,6 +27,7 @@ use crate::{
         delivery::SmtpConnection,
         email_submission::{assert_message_delivery, spawn_mock_smtp_server, MockMessage},
         mailbox::destroy_all_mailboxes,
+        ManagementApi,
     },
     smtp::DnsCache,
 };
@@ -67,6 +71,25 @@ pub async fn test(params: &mut JMAPTest) {
         }))
     ));
 
+    // Extensions disabled by the administrator should be rejected
+    client
+        .sieve_script_validate(
+            "require [\"spamtest\", \"relational\", \"comparator-i;ascii-numeric\"];\r\nif spamtest :value \"ge\" :comparator \"i;ascii-numeric\" \"5\" { discard; }",
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        client
+            .sieve_script_validate(
+                "# Virus check\r\nrequire [\"fileinto\", \"virustest\"];\r\nif virustest :value \"eq\" :comparator \"i;ascii-numeric\" \"5\" { discard; }",
+            )
+            .await,
+        Err(Error::Set(SetError {
+            type_: SetErrorType::InvalidScript,
+            ..
+        }))
+    ));
+
     // Create 5 Sieve scripts, all deactivated.
     let mut script_ids = Vec::new();
     for i in 0..5 {
@@ -384,6 +407,151 @@ pub async fn test(params: &mut JMAPTest) {
     )
     .await;
 
+    // Publish two versions of a library script and roll back to the first one
+    let api = ManagementApi::new(8899, "admin", "secret");
+    for version in 1..=2 {
+        assert_eq!(
+            api.post::<u32>(
+                "/api/sieve/library/shared-rules",
+                &json!({
+                    "contents": format!(
+                        "require \"reject\";\r\nreject \"Rejected from library version {version}.\";\r\nstop;"
+                    ),
+                    "description": "Shared rules",
+                }),
+            )
+            .await
+            .unwrap()
+            .unwrap_data(),
+            version
+        );
+    }
+    api.post::<u32>(
+        "/api/sieve/library/shared-rules",
+        &json!({"contents": "require \"virustest\";"}),
+    )
+    .await
+    .unwrap()
+    .expect_error("not enabled");
+    api.patch::<()>("/api/sieve/library/shared-rules", &json!({"version": 1}))
+        .await
+        .unwrap()
+        .unwrap_data();
+    let list = api
+        .get::<Value>("/api/sieve/library")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(list["total"], 1);
+    assert_eq!(list["items"][0]["version"], 1);
+    assert_eq!(list["items"][0]["versions"].as_array().unwrap().len(), 2);
+    assert!(api
+        .get::<Value>("/api/sieve/library/shared-rules/2")
+        .await
+        .unwrap()
+        .unwrap_data()["contents"]
+        .as_str()
+        .unwrap()
+        .contains("library version 2"));
+
+    // Include the published library script
+    client
+        .sieve_script_create(
+            "test_include_library",
+            b"require [\"include\"];\r\ninclude :global \"shared-rules\";".to_vec(),
+            true,
+        )
+        .await
+        .unwrap();
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "Bcc: Undisclosed recipients;\r\n",
+            "Message-ID: <1234@example.com>\r\n",
+            "Subject: Holidays\r\n",
+            "\r\n",
+            "Remember to file your T.P.S. reports before ",
+            "going on holidays."
+        ),
+    )
+    .await;
+
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new(
+            "<>",
+            ["<bill@remote.org>"],
+            "@Rejected from library version 1",
+        ),
+    )
+    .await;
+    api.delete::<()>("/api/sieve/library/shared-rules")
+        .await
+        .unwrap()
+        .unwrap_data();
+
+    // Import, activate and export scripts on behalf of an account
+    let script = "require \"fileinto\";\r\nfileinto \"INBOX\";\r\n";
+    api.put_raw::<()>(
+        "/api/sieve/scripts/jdoe@example.com/imported?activate=true",
+        script,
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    api.put_raw::<()>(
+        "/api/sieve/scripts/jdoe@example.com/broken",
+        "require \"virustest\";",
+    )
+    .await
+    .unwrap()
+    .expect_error("not enabled");
+    api.put_raw::<()>("/api/sieve/scripts/jdoe@example.com/vacation", script)
+        .await
+        .unwrap()
+        .expect_error("reserved");
+    let list = api
+        .get::<Value>("/api/sieve/scripts/jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    let imported = list["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["name"] == "imported")
+        .unwrap();
+    assert_eq!(imported["isActive"], true);
+    assert_eq!(imported["size"], script.len());
+    assert_eq!(
+        api.get_raw("/api/sieve/scripts/jdoe@example.com/imported")
+            .await
+            .unwrap(),
+        script
+    );
+    api.post::<()>(
+        "/api/sieve/scripts/jdoe@example.com/imported/deactivate",
+        &(),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    assert!(api
+        .get::<Value>("/api/sieve/scripts/jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap_data()["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|item| item["isActive"] == false));
+    api.get::<()>("/api/sieve/scripts/unknown@example.com")
+        .await
+        .unwrap()
+        .expect_error("notFound");
+
     // Run enclose + redirect tests
     client
         .sieve_script_create(
@@ -509,6 +677,89 @@ pub async fn test(params: &mut JMAPTest) {
         panic!("Email {:?} not found in: {:#?}", subject, emails);
     }
 
+    // Run expiry tests
+    client
+        .sieve_script_create(
+            "test_expire",
+            concat!(
+                "require [\"variables\", \"include\"];\r\n",
+                "global \"expire\";\r\n",
+                "set \"expire\" \"90\";\r\n",
+            )
+            .as_bytes()
+            .to_vec(),
+            true,
+        )
+        .await
+        .unwrap();
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Limited time offer\r\n",
+            "\r\n",
+            "Buy now!"
+        ),
+    )
+    .await;
+    let message_id = client
+        .email_query(
+            email::query::Filter::subject("Limited time offer").into(),
+            None::<Vec<_>>,
+        )
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .expect("Expiring message was not delivered.");
+    let blob_id = client
+        .email_get(&message_id, [email::Property::BlobId].into())
+        .await
+        .unwrap()
+        .unwrap()
+        .take_blob_id();
+    let raw_message = String::from_utf8(client.download(&blob_id).await.unwrap()).unwrap();
+    assert!(
+        raw_message.contains("Expires: "),
+        "Expires header not found: {raw_message}"
+    );
+
+    // Messages are deleted by the housekeeper once they expire
+    let account_id = Id::from_bytes(account_id.as_bytes()).unwrap().document_id();
+    let document_id = Id::from_bytes(message_id.as_bytes()).unwrap().document_id();
+    let expires_at = server
+        .get_property::<u64>(
+            account_id,
+            Collection::Email,
+            document_id,
+            Property::Expires,
+        )
+        .await
+        .unwrap()
+        .expect("Expiry time was not stored.");
+    assert!(expires_at > now() + 89 * 86400 && expires_at <= now() + 90 * 86400);
+    server.emails_expire(account_id).await.unwrap();
+    assert!(client
+        .email_get(&message_id, None::<Vec<_>>)
+        .await
+        .unwrap()
+        .is_some());
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::Email)
+        .update_document(document_id)
+        .value(Property::Expires, now() - 1, F_VALUE);
+    server.core.storage.data.write(batch.build()).await.unwrap();
+    server.emails_expire(account_id).await.unwrap();
+    assert!(client
+        .email_get(&message_id, None::<Vec<_>>)
+        .await
+        .unwrap()
+        .is_none());
+
     // Remove test data
     client.sieve_script_deactivate().await.unwrap();
     let mut request = client.build();
@@ -253,6 +253,7 @@ async fn test_multi_thread(params: &mut JMAPTest) {
                         },
                         spam_classify: false,
                         spam_train: false,
+                        expires_at: None,
                         session_id: 0,
                     })
                     .await
@@ -0,0 +1,35 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use serde_json::Value;
+
+use crate::jmap::ManagementApi;
+
+pub async fn test() {
+    println!("Running configuration audit tests...");
+
+    // The test configuration allows plain text authentication but does not relay
+    let findings = ManagementApi::new(8899, "admin", "secret")
+        .get::<Vec<Value>>("/api/troubleshoot/audit")
+        .await
+        .unwrap()
+        .unwrap_data();
+    let ids = findings
+        .iter()
+        .filter_map(|finding| finding["id"].as_str())
+        .collect::<Vec<_>>();
+    for id in ["smtp-plain-text-auth", "imap-plain-text-auth"] {
+        assert!(ids.contains(&id), "{findings:?}");
+    }
+    assert!(!ids.contains(&"open-relay"), "{findings:?}");
+    for finding in &findings {
+        assert!(
+            ["critical", "warning", "info"].contains(&finding["severity"].as_str().unwrap()),
+            "{finding}"
+        );
+        assert!(!finding["remediation"].as_str().unwrap().is_empty());
+    }
+}
@@ -6,8 +6,10 @@
 
 use chrono::{TimeDelta, Utc};
 
+use common::KV_AUTORESPONDER_SUPPRESSED;
 use jmap_proto::types::id::Id;
 use std::time::Instant;
+use store::dispatch::lookup::KeyValue;
 
 use crate::{
     directory::internal::TestInternalDirectory,
@@ -18,6 +20,7 @@ use crate::{
             assert_message_delivery, expect_nothing, spawn_mock_smtp_server, MockMessage,
         },
         mailbox::destroy_all_mailboxes,
+        ManagementApi,
     },
     smtp::DnsCache,
 };
@@ -30,20 +33,18 @@ pub async fn test(params: &mut JMAPTest) {
     // Create test account
     let server = params.server.clone();
     let client = &mut params.client;
-    let account_id = Id::from(
-        server
-            .core
-            .storage
-            .data
-            .create_test_user(
-                "jdoe@example.com",
-                "12345",
-                "John Doe",
-                &["jdoe@example.com"],
-            )
-            .await,
-    )
-    .to_string();
+    let document_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    let account_id = Id::from(document_id).to_string();
     client.set_default_account_id(&account_id);
 
     // Start mock SMTP server
@@ -124,6 +125,49 @@ pub async fn test(params: &mut JMAPTest) {
 
     expect_nothing(&mut smtp_rx).await;
 
+    // VERP return paths and list precedence should not
+    // trigger a vacation response either
+    lmtp.ingest(
+        "bounce-1234-jdoe=example.com@mailer.remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: newsletter@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Weekly digest\r\n",
+            "\r\n",
+            "All the news that's fit to print.",
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+
+    lmtp.ingest(
+        "calendar@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: calendar@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Precedence: list\r\n",
+            "Subject: Meeting reminder\r\n",
+            "\r\n",
+            "Don't forget the meeting.",
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+
+    // Suppressed replies are counted per account
+    let vacation = ManagementApi::new(8899, "jdoe@example.com", "12345")
+        .get::<serde_json::Value>("/api/account/vacation")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(
+        vacation["suppressedReplies"].as_u64(),
+        Some(2),
+        "{vacation}"
+    );
+
     // Vacation responses should honor the configured date ranges
     client
         .vacation_response_set_dates(
@@ -181,6 +225,14 @@ pub async fn test(params: &mut JMAPTest) {
 
     // Remove test data
     client.vacation_response_destroy().await.unwrap();
+    server
+        .in_memory_store()
+        .counter_delete(KeyValue::<()>::build_key(
+            KV_AUTORESPONDER_SUPPRESSED,
+            document_id.to_be_bytes(),
+        ))
+        .await
+        .unwrap();
     destroy_all_mailboxes(params).await;
     assert_is_empty(server).await;
 }
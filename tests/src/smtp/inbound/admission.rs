@@ -0,0 +1,74 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::atomic::Ordering;
+
+use crate::smtp::{TempDir, TestSMTP};
+use common::Core;
+use smtp::queue::admission::AdmissionControl;
+use store::{
+    write::{BatchBuilder, QueueClass, ValueClass},
+    Stores,
+};
+use utils::config::Config;
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/data.db"
+
+[queue.admission]
+enable = true
+max-messages = 1
+max-latency = "10s"
+check-interval = "1h"
+"#;
+
+#[tokio::test]
+async fn queue_admission() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_inbound_admission", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    let server = TestSMTP::from_core(core).server;
+    let status = &server.inner.data.queue_admission;
+
+    // Empty queue should be admitting
+    assert!(server.queue_pressure().await.is_none());
+    assert!(server.is_queue_admitting(0).await);
+
+    // Exceed the backlog limit
+    let mut batch = BatchBuilder::new();
+    for queue_id in [1, 2] {
+        batch.set(ValueClass::Queue(QueueClass::Message(queue_id)), vec![]);
+    }
+    server.store().write(batch.build_batch()).await.unwrap();
+    assert!(server.queue_pressure().await.is_some());
+
+    // Status is cached until the next check interval
+    assert!(server.is_queue_admitting(0).await);
+    status.last_check.store(0, Ordering::Relaxed);
+    assert!(!server.is_queue_admitting(0).await);
+    assert!(!server.is_queue_admitting(0).await);
+
+    // Drain the backlog and resume admission
+    let mut batch = BatchBuilder::new();
+    for queue_id in [1, 2] {
+        batch.clear(ValueClass::Queue(QueueClass::Message(queue_id)));
+    }
+    server.store().write(batch.build_batch()).await.unwrap();
+    status.last_check.store(0, Ordering::Relaxed);
+    assert!(server.is_queue_admitting(0).await);
+}
@@ -487,6 +487,7 @@ async fn antispam() {
                         arc_result.as_ref(),
                         dmarc_result.as_ref(),
                         dmarc_policy.as_ref(),
+                        0,
                     )
                     .await
                 {
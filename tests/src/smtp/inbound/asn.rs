@@ -8,7 +8,10 @@
 mod tests {
     use std::time::{Duration, Instant};
 
-    use common::{config::network::AsnGeoLookupConfig, Core, Server};
+    use common::{
+        config::network::{AsnGeoFormat, AsnGeoLookupConfig},
+        Core, Server,
+    };
 
     #[tokio::test]
     #[ignore]
@@ -75,6 +78,7 @@ mod tests {
                 )
                 .to_string(),
             ],
+            format: AsnGeoFormat::Csv,
         };
         let server = Server {
             core: core.into(),
@@ -107,4 +111,72 @@ mod tests {
             assert_eq!(result.country.as_ref().map(|s| s.as_str()), Some(country));
         }
     }
+
+    #[tokio::test]
+    async fn lookup_asn_country_ip2location() {
+        let dir = std::env::temp_dir();
+        let asn_path = dir.join("stalwart-ip2location-asn.csv");
+        let geo_path = dir.join("stalwart-ip2location-country.csv");
+        std::fs::write(
+            &asn_path,
+            concat!(
+                "\"0\",\"16777215\",\"-\",\"-\",\"-\"\n",
+                "\"16777216\",\"16777471\",\"1.0.0.0/24\",\"13335\",\"CloudFlare Inc\"\n",
+                "\"134744064\",\"134744319\",\"8.8.8.0/24\",\"15169\",\"Google LLC\"\n",
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            &geo_path,
+            concat!(
+                "\"0\",\"16777215\",\"-\",\"-\"\n",
+                "\"16777216\",\"16777471\",\"US\",\"United States of America\"\n",
+                "\"134744064\",\"134744319\",\"US\",\"United States of America\"\n",
+                "\"281470698520832\",\"281470698521087\",\"AU\",\"Australia\"\n",
+            ),
+        )
+        .unwrap();
+
+        let mut core = Core::default();
+        core.network.asn_geo_lookup = AsnGeoLookupConfig::Resource {
+            expires: Duration::from_secs(86400),
+            timeout: Duration::from_secs(100),
+            max_size: 100 * 1024 * 1024,
+            headers: Default::default(),
+            asn_resources: vec![format!("file://{}", asn_path.display())],
+            geo_resources: vec![format!("file://{}", geo_path.display())],
+            format: AsnGeoFormat::Ip2Location,
+        };
+        let server = Server {
+            core: core.into(),
+            inner: Default::default(),
+        };
+
+        server.lookup_asn_country("8.8.8.8".parse().unwrap()).await;
+        loop {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            if server.inner.data.asn_geo_data.lock.available_permits() > 0 {
+                break;
+            }
+        }
+
+        for (ip, asn, asn_name, country) in [
+            ("8.8.8.8", Some(15169), Some("Google LLC"), Some("US")),
+            ("1.0.0.1", Some(13335), Some("CloudFlare Inc"), Some("US")),
+            ("1.0.1.1", None, None, Some("AU")),
+            ("9.9.9.9", None, None, None),
+        ] {
+            let result = server.lookup_asn_country(ip.parse().unwrap()).await;
+            assert_eq!(result.asn.as_ref().map(|r| r.id), asn, "{ip}");
+            assert_eq!(
+                result.asn.as_ref().and_then(|r| r.name.as_deref()),
+                asn_name,
+                "{ip}"
+            );
+            assert_eq!(result.country.as_ref().map(|s| s.as_str()), country, "{ip}");
+        }
+
+        std::fs::remove_file(asn_path).unwrap();
+        std::fs::remove_file(geo_path).unwrap();
+    }
 }
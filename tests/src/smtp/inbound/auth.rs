@@ -56,6 +56,8 @@ mechanisms = [{if = "remote_ip = '10.0.0.1' && is_tls", then = "[plain, login]"}
               {else = 0}]
 directory = [{if = "remote_ip = '10.0.0.1'", then = "'local'"},
              {else = false}]
+allow = [{if = "local_port == 25", then = false},
+         {else = true}]
 must-match-sender = true
 
 [session.auth.errors]
@@ -160,4 +162,18 @@ async fn auth() {
     session
         .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "503 5.5.1")
         .await;
+
+    // AUTH should not be advertised or accepted on port 25
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.data.local_port = 25;
+    session.data.authenticated_as.take();
+    session.eval_session_params().await;
+    session
+        .ehlo("mx.foobar.org")
+        .await
+        .assert_not_contains("AUTH ")
+        .assert_not_contains(" PLAIN");
+    session
+        .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "503 5.5.1")
+        .await;
 }
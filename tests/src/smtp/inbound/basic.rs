@@ -52,3 +52,34 @@ async fn basic_commands() {
     session.ingest(b"QUIT\r\n").await.unwrap_err();
     session.response().assert_code("221");
 }
+
+#[tokio::test]
+async fn pipelining() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut session = Session::test(TestSMTP::from_core(Core::default()).server);
+    session.ehlo("mx.foobar.org").await;
+
+    // Responses to a pipelined group should all be sent
+    session.ingest(b"RSET\r\nRSET\r\nNOOP\r\n").await.unwrap();
+    session.response().assert_count("250 2.0.0", 3);
+
+    // Violations should be tolerated when strict mode is disabled
+    session.ingest(b"NOOP\r\nRSET\r\n").await.unwrap();
+    session.response().assert_count("250 2.0.0", 2);
+
+    // Violations should disconnect the client in strict mode
+    session.params.pipelining_strict = true;
+    session.ingest(b"NOOP\r\nRSET\r\n").await.unwrap_err();
+    session.response().assert_contains("554 5.5.0");
+
+    // Pipelining should not be allowed before it is negotiated
+    let mut session = Session::test(TestSMTP::from_core(Core::default()).server);
+    session.params.pipelining_strict = true;
+    session
+        .ingest(b"HELO mx.foobar.org\r\nRSET\r\n")
+        .await
+        .unwrap_err();
+    session.response().assert_contains("554 5.5.0");
+}
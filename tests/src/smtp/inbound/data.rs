@@ -68,6 +68,9 @@ messages = [{if = "remote_ip = '10.0.0.1'", then = 1},
             {else = 100}]
 received-headers = 3
 
+[session.data.spool]
+chunk-size = 1024
+
 [session.data.add-headers]
 received = [{if = "remote_ip = '10.0.0.3'", then = true},
             {else = false}]
@@ -81,6 +84,8 @@ date = [{if = "remote_ip = '10.0.0.3'", then = true},
         {else = false}]
 return-path =  [{if = "remote_ip = '10.0.0.3'", then = true},
             {else = false}]
+trace-id = [{if = "remote_ip = '10.0.0.3'", then = true},
+            {else = false}]
 
 [[queue.quota]]
 match = "sender = 'john@doe.org'"
@@ -163,8 +168,9 @@ async fn data() {
     session
         .send_message("bill@doe.org", &["mike@test.com"], "test:no_msgid", "250")
         .await;
-    qr.expect_message()
-        .await
+    let message = qr.expect_message().await;
+    let trace_id = format!("X-Trace-Id: {:X}", message.queue_id);
+    message
         .read_lines(&qr)
         .await
         .assert_contains("From: ")
@@ -175,7 +181,8 @@ async fn data() {
         .assert_contains("Return-Path: ")
         .assert_contains("Received: ")
         .assert_contains("Authentication-Results: ")
-        .assert_contains("Received-SPF: ");
+        .assert_contains("Received-SPF: ")
+        .assert_contains(&trace_id);
 
     // Only one message is allowed in the queue from john@doe.org
     session.data.remote_ip_str = "10.0.0.2".to_string();
@@ -231,6 +238,50 @@ async fn data() {
         )
         .await;
 
+    // Large messages are spooled to the blob store while being received
+    let large_message = format!(
+        "From: alice@doe.org\r\nTo: mike@test.com\r\nSubject: Large message\r\n\r\n{}",
+        "Lorem ipsum dolor sit amet, consectetur adipiscing elit.\r\n".repeat(100)
+    );
+    session
+        .send_message("alice@doe.org", &["mike@test.com"], &large_message, "250")
+        .await;
+    assert_eq!(
+        qr.expect_message().await.read_message(&qr).await,
+        large_message
+    );
+
+    // Spooled BDAT chunks are reassembled in order
+    let (first_chunk, last_chunk) = large_message.split_at(3000);
+    session.mail_from("alice@doe.org", "250").await;
+    session.rcpt_to("mike@test.com", "250").await;
+    session
+        .ingest(format!("BDAT {}\r\n{first_chunk}", first_chunk.len()).as_bytes())
+        .await
+        .unwrap();
+    session.response().assert_code("250 2.6.0");
+    session
+        .ingest(format!("BDAT {} LAST\r\n{last_chunk}", last_chunk.len()).as_bytes())
+        .await
+        .unwrap();
+    session.response().assert_code("250");
+    assert_eq!(
+        qr.expect_message().await.read_message(&qr).await,
+        large_message
+    );
+
+    // Spooled chunks are discarded when the transaction is reset
+    session.mail_from("alice@doe.org", "250").await;
+    session.rcpt_to("mike@test.com", "250").await;
+    session
+        .ingest(format!("BDAT {}\r\n{first_chunk}", first_chunk.len()).as_bytes())
+        .await
+        .unwrap();
+    session.response().assert_code("250 2.6.0");
+    assert!(!session.data.spool.chunks.is_empty());
+    session.rset().await;
+    assert!(session.data.spool.chunks.is_empty());
+
     // Make sure store is empty
     qr.clear_queue(&test.server).await;
     test.server
@@ -11,15 +11,16 @@ use common::{
     Server,
 };
 use store::{
-    write::{key::DeserializeBigEndian, Bincode, QueueClass, ReportEvent, ValueClass},
+    write::{key::DeserializeBigEndian, QueueClass, ReportEvent, ValueClass},
     Deserialize, IterateParams, ValueKey, U64_LEN,
 };
 use tokio::sync::mpsc::error::TryRecvError;
 
-use smtp::queue::{Message, QueueId, QueuedMessage};
+use smtp::queue::{format::SpooledMessage, Message, QueueId, QueuedMessage};
 
 use super::{QueueReceiver, ReportReceiver};
 
+pub mod admission;
 pub mod antispam;
 pub mod asn;
 pub mod auth;
@@ -31,6 +32,7 @@ pub mod limits;
 pub mod mail;
 pub mod milter;
 pub mod rcpt;
+pub mod reputation;
 pub mod rewrite;
 pub mod scripts;
 pub mod sign;
@@ -186,7 +188,7 @@ impl QueueReceiver {
             .iterate(
                 IterateParams::new(from_key, to_key).descending(),
                 |key, value| {
-                    let value = Bincode::<Message>::deserialize(value)?;
+                    let value = SpooledMessage::deserialize(value)?;
                     assert_eq!(key.deserialize_be_u64(0)?, value.inner.queue_id);
                     messages.push(value.inner);
                     Ok(true)
@@ -67,9 +67,17 @@ relay = [{if = "remote_ip = '10.0.0.1'", then = false},
 [session.rcpt.errors]
 total = [{if = "remote_ip = '10.0.0.1'", then = 3},
          {else = 100}]
-wait = [{if = "remote_ip = '10.0.0.1'", then = '5ms'},
+wait = [{if = "remote_ip = '10.0.0.1' || remote_ip = '10.0.0.3'", then = '5ms'},
         {else = '1s'}]
 
+[session.rcpt.harvest]
+enable = [{if = "remote_ip = '10.0.0.3'", then = true},
+          {else = false}]
+delay = "10ms"
+max-delay = "20ms"
+disconnect = 3
+block = true
+
 [session.extensions]
 dsn = [{if = "remote_ip = '10.0.0.1'", then = false},
        {else = true}]
@@ -162,4 +170,20 @@ async fn rcpt() {
     let rcpt = session.data.rcpt_to.last().unwrap();
     assert!((rcpt.flags & (RCPT_NOTIFY_DELAY | RCPT_NOTIFY_SUCCESS | RCPT_NOTIFY_FAILURE)) != 0);
     assert_eq!(rcpt.dsn_info.as_ref().unwrap(), "Jane.Doe@Foobar.org");
+
+    // Directory harvesting is tarpitted and eventually blocked for 10.0.0.3
+    session.data.remote_ip_str = "10.0.0.3".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.rset().await;
+    session.mail_from("john@example.net", "250").await;
+    session.rcpt_to("tom@foobar.org", "550 5.1.2").await;
+    session.rcpt_to("sam@foobar.org", "550 5.1.2").await;
+    assert_eq!(session.data.rcpt_unknown, 2);
+    session
+        .ingest(b"RCPT TO:<ann@foobar.org>\r\n")
+        .await
+        .unwrap_err();
+    session.response().assert_code("421 4.7.0");
+    assert!(session.server.is_ip_blocked(&session.data.remote_ip));
 }
@@ -0,0 +1,125 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Core;
+
+use smtp::{
+    core::Session,
+    inbound::reputation::{ConnectionReputation, ReputationType},
+};
+use store::Stores;
+use utils::config::Config;
+
+use crate::smtp::{session::TestSession, TempDir, TestSMTP};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+
+[session.reputation]
+enable = true
+penalty.invalid-rcpt = 1.0
+
+[session.rcpt]
+directory = "'local'"
+relay = [{if = "reputation > 2", then = false},
+         {else = true}]
+
+[session.rcpt.errors]
+total = 100
+wait = "1ms"
+"#;
+
+#[tokio::test]
+async fn reputation() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_reputation_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    let server = TestSMTP::from_core(core).server;
+
+    // Unknown senders start with a neutral score
+    let mut session = Session::test(server.clone());
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.spammer.org").await;
+    assert_eq!(session.data.reputation, 0.0);
+    session.mail_from("bill@spammer.org", "250").await;
+    session.rcpt_to("external@domain.com", "250").await;
+
+    // Invalid recipients increase the score of the IP and HELO domain
+    for rcpt in ["tom@foobar.org", "sam@foobar.org", "ann@foobar.org"] {
+        session.rcpt_to(rcpt, "550 5.1.2").await;
+    }
+    for (rep_type, key) in [
+        (ReputationType::Ip, "10.0.0.1"),
+        (ReputationType::Helo, "mx.spammer.org"),
+    ] {
+        let reputation = server.reputation(rep_type, key).await.unwrap().unwrap();
+        assert_eq!(reputation.invalid_rcpts, 3);
+        assert!(reputation.score > 2.9 && reputation.score <= 3.0);
+    }
+
+    // New connections from the same IP inherit the score
+    let mut session = Session::test(server.clone());
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.load_reputation().await;
+    assert!(session.data.reputation > 2.9);
+    session.ehlo("mx.example.org").await;
+    session.mail_from("bill@example.org", "250").await;
+    session.rcpt_to("external@domain.com", "550 5.1.2").await;
+
+    // The HELO domain also carries the score
+    let mut session = Session::test(server.clone());
+    session.data.remote_ip_str = "10.0.0.2".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("MX.SPAMMER.ORG").await;
+    assert!(session.data.reputation > 2.9);
+
+    // Resetting the score allows relaying again
+    server
+        .reset_reputation(ReputationType::Ip, "10.0.0.1")
+        .await
+        .unwrap();
+    assert_eq!(
+        server
+            .reputation(ReputationType::Ip, "10.0.0.1")
+            .await
+            .unwrap(),
+        None
+    );
+    let mut session = Session::test(server);
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.example.org").await;
+    assert_eq!(session.data.reputation, 0.0);
+    session.mail_from("bill@example.org", "250").await;
+    session.rcpt_to("external@domain.com", "250").await;
+}
@@ -5,7 +5,7 @@
  */
 
 use core::panic;
-use std::{fmt::Write, fs, path::PathBuf};
+use std::{fmt::Write, fs, path::PathBuf, time::Duration};
 
 use crate::{
     enable_logging,
@@ -16,11 +16,11 @@ use crate::{
     },
     AssertConfig,
 };
-use common::Core;
+use common::{config::scripts::SandboxPolicy, Core, SandboxId};
 
 use smtp::{
     core::Session,
-    scripts::{event_loop::RunScript, ScriptResult},
+    scripts::{event_loop::RunScript, sandbox::ScriptSandbox, ScriptResult},
 };
 use store::Stores;
 use utils::config::Config;
@@ -374,3 +374,41 @@ async fn sieve_scripts() {
         .assert_contains("Authentication-Results: ");
     qr.assert_no_events();
 }
+
+#[test]
+fn sandbox_policy() {
+    let server = TestSMTP::from_core(Core::default()).server;
+    let policy = SandboxPolicy {
+        max_violations: Some(2),
+        disable_for: Duration::from_secs(3600),
+    };
+    let id = SandboxId::Sieve("slow".to_string());
+    let elapsed = Duration::from_millis(10);
+
+    // Successful runs reset the consecutive violation count
+    assert_eq!(
+        server.sandbox_record(id.clone(), &policy, elapsed, true),
+        None
+    );
+    assert_eq!(
+        server.sandbox_record(id.clone(), &policy, elapsed, false),
+        None
+    );
+    assert_eq!(
+        server.sandbox_record(id.clone(), &policy, elapsed, true),
+        None
+    );
+    assert!(!server.sandbox_is_disabled(&id));
+
+    // Consecutive violations disable the script
+    assert!(server
+        .sandbox_record(id.clone(), &policy, elapsed, true)
+        .is_some());
+    assert!(server.sandbox_is_disabled(&id));
+    assert!(!server.sandbox_is_disabled(&SandboxId::Hook("slow".to_string())));
+
+    let (_, stats) = server.sandbox_stats().into_iter().next().unwrap();
+    assert_eq!(stats.runs, 4);
+    assert_eq!(stats.violations, 3);
+    assert_eq!(stats.elapsed, elapsed * 4);
+}
@@ -16,9 +16,9 @@ use store::Stores;
 use utils::config::Config;
 
 use crate::smtp::{
-    DnsCache, TempDir, TestSMTP,
     inbound::TestMessage,
     session::{TestSession, VerifyResponse},
+    DnsCache, TempDir, TestSMTP,
 };
 use smtp::core::Session;
 
@@ -93,9 +93,25 @@ description = "John Doe"
 secret = "secret"
 email = ["jdoe@example.com"]
 
+[[directory."local".principals]]
+name = "jane"
+description = "Jane Doe"
+secret = "secret"
+email = ["jane@example.com"]
+
 [session.rcpt]
 directory = "'local'"
 
+[session.data]
+script = [{if = "rcpt == 'jane@example.com'", then = "'tag'"},
+          {else = false}]
+
+[sieve.trusted.scripts.tag]
+contents = """
+require "editheader";
+addheader "X-Tagged" "yes";
+"""
+
 [session.data.add-headers]
 received = true
 received-spf = true
@@ -119,6 +135,10 @@ seal = "'ed'"
 [auth.dmarc]
 verify = "relaxed"
 
+[auth.resign.script]
+dkim = "['ed']"
+arc = "'rsa'"
+
 "#;
 
 #[tokio::test]
@@ -218,4 +238,18 @@ async fn sign_and_seal() {
         .assert_contains(
             "ARC-Message-Signature: i=1; a=ed25519-sha256; s=ed; d=example.com; c=relaxed/simple;",
         );
+
+    // Messages modified by scripts are re-sealed and re-signed
+    session
+        .send_message("bill@foobar.org", &["jane@example.com"], "test:dkim", "250")
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("X-Tagged: yes")
+        .assert_contains("ARC-Seal: i=1; a=rsa-sha256; s=rsa; d=example.com; cv=none;")
+        .assert_not_contains("ARC-Seal: i=1; a=ed25519-sha256;")
+        .assert_contains("DKIM-Signature: v=1; a=rsa-sha256; s=rsa; d=example.com;")
+        .assert_contains("DKIM-Signature: v=1; a=ed25519-sha256; s=ed; d=example.com;");
 }
@@ -61,8 +61,14 @@ directory = "'local'"
 [session.extensions]
 vrfy = [{if = "remote_ip = '10.0.0.1'", then = true},
         {else = false}]
-expn = [{if = "remote_ip = '10.0.0.1'", then = true},
+expn = [{if = "remote_ip != '10.0.0.2'", then = true},
         {else = false}]
+vrfy-rate = "[3, 1m]"
+expn-rate = "[3, 1m]"
+expn-max-size = [{if = "remote_ip = '10.0.0.4'", then = 2},
+                 {else = 0}]
+expn-opaque-size = [{if = "remote_ip = '10.0.0.3'", then = 2},
+                    {else = 0}]
 
 "#;
 
@@ -114,4 +120,26 @@ async fn vrfy_expn() {
 
     // Non-existent EXPN
     session.cmd("EXPN procurement", "550 5.1.2").await;
+
+    // VRFY and EXPN should be rate limited
+    session.cmd("VRFY jane", "250 jane@foobar.org").await;
+    session.cmd("VRFY john", "452 4.4.5").await;
+    session.cmd("EXPN sales@foobar.org", "250").await;
+    session.cmd("EXPN sales@foobar.org", "452 4.4.5").await;
+
+    // Lists above the disclosure threshold should not be expanded
+    session.data.remote_ip_str = "10.0.0.3".to_string();
+    session.eval_session_params().await;
+    session
+        .cmd("EXPN sales@foobar.org", "252 2.1.5")
+        .await
+        .assert_not_contains("@foobar.org");
+
+    // Expansions should be truncated to the maximum size
+    session.data.remote_ip_str = "10.0.0.4".to_string();
+    session.eval_session_params().await;
+    session
+        .cmd("EXPN sales@foobar.org", "250")
+        .await
+        .assert_count("@foobar.org", 2);
 }
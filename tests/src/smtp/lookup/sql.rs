@@ -81,6 +81,15 @@ vrfy = true
 expr = "sql_query('sql', 'SELECT description FROM domains WHERE name = ?', 'foobar.org')"
 expect = "Main domain"
 
+[lookup.query."domain-description"]
+store = "sql"
+query = "SELECT description FROM domains WHERE name = ?"
+cache.ttl = "1h"
+
+[test."lookup_query"]
+expr = "lookup_query('domain-description', 'foobar.net')"
+expect = "Secondary domain"
+
 [test."dns"]
 expr = "dns_query(rcpt_domain, 'mx')[0]"
 expect = "mx.foobar.org"
@@ -214,7 +223,7 @@ async fn lookup_sql() {
         V_LOCAL_IP,
         V_PRIORITY,
     ]);
-    for test_name in ["sql", "dns", "key_get", "counter_get"] {
+    for test_name in ["sql", "lookup_query", "dns", "key_get", "counter_get"] {
         let e =
             Expression::try_parse(&mut config, ("test", test_name, "expr"), &token_map).unwrap();
         assert_eq!(
@@ -228,6 +237,25 @@ async fn lookup_sql() {
         );
     }
 
+    // Lookup query results are served from the cache
+    handle
+        .store
+        .sql_query::<usize>(
+            "UPDATE domains SET description = 'Changed' WHERE name = 'foobar.net'",
+            Vec::new(),
+        )
+        .await
+        .unwrap();
+    let e =
+        Expression::try_parse(&mut config, ("test", "lookup_query", "expr"), &token_map).unwrap();
+    assert_eq!(
+        test.server
+            .eval_expr::<String, _>(&e, &RecipientDomain::new("test.org"), "text", 0)
+            .await
+            .unwrap(),
+        "Secondary domain"
+    );
+
     let mut session = Session::test(test.server);
     session.data.remote_ip_str = "10.0.0.50".parse().unwrap();
     session.eval_session_params().await;
@@ -272,6 +272,7 @@ async fn manage_queue() {
             "/api/queue/messages?from=bill3@foobar.net&to=rcpt5@example1.com".to_string(),
             vec!["c"],
         ),
+        ("/api/queue/messages?envid=e".to_string(), vec!["e"]),
         (
             format!("/api/queue/messages?before={test_search}"),
             vec!["a", "b"],
@@ -12,7 +12,7 @@ use common::{
     ipc::{DmarcEvent, PolicyType, TlsEvent},
 };
 
-use jmap::api::management::queue::Report;
+use jmap::api::management::queue::{DomainReportSchedule, Report};
 use mail_auth::{
     common::parse::TxtRecordParser,
     dmarc::Dmarc,
@@ -188,6 +188,68 @@ async fn manage_reports() {
         assert_eq!(ids, expected_ids, "failed for {query}");
     }
 
+    // Test report schedule
+    assert!(api
+        .request::<bool>(
+            Method::PATCH,
+            "/api/queue/schedule/foobar.net?dmarc=hourly&tls=never"
+        )
+        .await
+        .unwrap()
+        .unwrap_data());
+    api.request::<bool>(
+        Method::PATCH,
+        "/api/queue/schedule/foobar.net?dmarc=fortnightly",
+    )
+    .await
+    .unwrap()
+    .expect_request_error("Invalid aggregate frequency");
+    let schedules = api
+        .request::<List<DomainReportSchedule>>(Method::GET, "/api/queue/schedule")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items;
+    assert_eq!(
+        schedules
+            .iter()
+            .map(|s| (
+                s.domain.as_str(),
+                s.dmarc.pending,
+                s.tls.pending,
+                s.dmarc.interval,
+                s.tls.interval
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "foobar.net",
+                1,
+                1,
+                Some(AggregateFrequency::Hourly),
+                Some(AggregateFrequency::Never)
+            ),
+            ("foobar.org", 1, 1, None, None)
+        ]
+    );
+    assert!(schedules.iter().all(|s| s.dmarc.next_due.is_some()));
+    assert!(api
+        .request::<bool>(
+            Method::PATCH,
+            "/api/queue/schedule/foobar.net?dmarc=default&tls=default"
+        )
+        .await
+        .unwrap()
+        .unwrap_data());
+    assert!(api
+        .request::<List<DomainReportSchedule>>(Method::GET, "/api/queue/schedule?domain=foobar.net")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items
+        .iter()
+        .all(|s| s.dmarc.interval.is_none() && s.tls.interval.is_none()));
+
     // Cancel reports
     for id in ["a", "b"] {
         assert!(
@@ -224,6 +286,24 @@ async fn manage_reports() {
     assert!(ids.next().unwrap().is_some());
     assert!(ids.next().unwrap().is_some());
 
+    // Force sending the pending reports of a domain
+    assert_eq!(
+        api.request::<usize>(Method::POST, "/api/queue/schedule/foobar.org")
+            .await
+            .unwrap()
+            .unwrap_data(),
+        1
+    );
+    let mut ids = api
+        .get_reports(&[
+            id_map.get("c").unwrap().clone(),
+            id_map.get("d").unwrap().clone(),
+        ])
+        .await
+        .into_iter();
+    assert!(ids.next().unwrap().is_none());
+    assert!(ids.next().unwrap().is_some());
+
     // Cancel all reports
     assert!(api
         .request::<bool>(Method::DELETE, "/api/queue/reports")
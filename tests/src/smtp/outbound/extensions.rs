@@ -138,7 +138,7 @@ async fn extensions() {
     session
         .send_message(
             "<john@test.org> ENVID=abc123 RET=HDRS REQUIRETLS SMTPUTF8",
-            &["<bill@foobar.org> NOTIFY=NEVER"],
+            &["<bill@foobar.org> NOTIFY=NEVER ORCPT=rfc822;Bill+2BOrig@foobar.org"],
             "test:no_dkim",
             "250",
         )
@@ -155,4 +155,8 @@ async fn extensions() {
     assert!((message.flags & MAIL_REQUIRETLS) != 0);
     assert!((message.flags & MAIL_SMTPUTF8) != 0);
     assert!((message.recipients.last().unwrap().flags & RCPT_NOTIFY_NEVER) != 0);
+    assert_eq!(
+        message.recipients.last().unwrap().orcpt.as_deref(),
+        Some("Bill+Orig@foobar.org")
+    );
 }
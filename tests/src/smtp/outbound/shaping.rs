@@ -0,0 +1,113 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::config::smtp::queue::{ShapingTarget, TokenBucket};
+use smtp::queue::shaping::QueueRateShaping;
+use store::write::now;
+
+use crate::smtp::TestSMTP;
+
+const CONFIG: &str = r#"
+[queue.shaping.gmail]
+domain = ["gmail.com", "googlemail.com"]
+rate = "2/1h"
+
+[queue.shaping.outlook]
+mx = ["outlook.com"]
+rate = "10/1d"
+burst = 1
+"#;
+
+#[tokio::test]
+async fn queue_shaping() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_queue_shaping", CONFIG).await;
+    let core = local.build_smtp();
+    let gmail = core
+        .core
+        .smtp
+        .queue
+        .shaping
+        .iter()
+        .find(|shaper| shaper.id == "gmail")
+        .unwrap();
+
+    // Tokens are refilled at the configured rate, up to the burst size
+    let mut bucket = TokenBucket {
+        tokens: 0,
+        updated: 0,
+    };
+    gmail.refill(&mut bucket, 1800);
+    assert_eq!(gmail.remaining(&bucket), 1);
+    assert_eq!(gmail.next_token(&bucket), 1800);
+    gmail.refill(&mut bucket, 100_000);
+    assert_eq!(gmail.remaining(&bucket), 2);
+    assert_eq!(gmail.fill_time(), 3600);
+
+    // Recipient domains in the same bucket share its budget
+    assert_eq!(
+        core.shaping_take(ShapingTarget::Domain, "gmail.com", 0)
+            .await,
+        Ok(true)
+    );
+    assert_eq!(
+        core.shaping_take(ShapingTarget::Domain, "GoogleMail.com", 0)
+            .await,
+        Ok(true)
+    );
+    let retry_at = core
+        .shaping_take(ShapingTarget::Domain, "gmail.com", 0)
+        .await
+        .unwrap_err();
+    assert!(
+        retry_at > now() && retry_at <= now() + 1800,
+        "Retry at: {retry_at}"
+    );
+
+    // Other destinations are not shaped
+    assert_eq!(
+        core.shaping_take(ShapingTarget::Domain, "example.org", 0)
+            .await,
+        Ok(false)
+    );
+    assert_eq!(
+        core.shaping_take(ShapingTarget::Mx, "gmail.com", 0).await,
+        Ok(false)
+    );
+
+    // MX hosts match on their suffix
+    assert_eq!(
+        core.shaping_take(ShapingTarget::Mx, "mx1.mail.protection.outlook.com.", 0)
+            .await,
+        Ok(true)
+    );
+    assert!(core
+        .shaping_take(ShapingTarget::Mx, "mx2.outlook.com", 0)
+        .await
+        .is_err());
+    assert_eq!(
+        core.shaping_take(ShapingTarget::Mx, "notoutlook.com", 0)
+            .await,
+        Ok(false)
+    );
+
+    // Remaining budgets are restored from the store after a restart
+    core.inner.data.queue_shaping.lock().clear();
+    let status = core.shaping_status(gmail).await.unwrap();
+    assert_eq!(status.remaining, 0);
+    assert_eq!(status.burst, 2);
+    assert_eq!(status.requests, 2);
+    assert_eq!(status.period, 3600);
+    assert!(status
+        .next_refill
+        .is_some_and(|next_refill| next_refill <= now() + 1800));
+    assert!(core
+        .shaping_take(ShapingTarget::Domain, "gmail.com", 0)
+        .await
+        .is_err());
+}
@@ -0,0 +1,142 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+use mail_parser::DateTime;
+use smtp::outbound::warmup::{IpWarmup, WarmupStatus};
+use store::write::now;
+
+use crate::smtp::{session::TestSession, DnsCache, TestSMTP};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[queue.outbound.source-ip]
+v4 = [{if = "sender_domain == 'spill.org'", then = "['127.0.0.1', '127.0.0.2']"},
+      {else = "['127.0.0.1']"}]
+
+[queue.warmup]
+providers = ["foobar.org"]
+
+[queue.warmup."new-ip"]
+address = "127.0.0.1"
+start = "{TODAY}"
+schedule = [1, 100]
+"#;
+
+const REMOTE: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn ip_warmup() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_warmup_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+
+    // Add mock DNS entries
+    let today = DateTime::from_timestamp(now() as i64);
+    let mut local = TestSMTP::new(
+        "smtp_warmup_local",
+        LOCAL.replace(
+            "{TODAY}",
+            &format!("{:04}-{:02}-{:02}", today.year, today.month, today.day),
+        ),
+    )
+    .await;
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    // The first message is sent from the warm-up IP
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    remote.queue_receiver.expect_message().await;
+    assert_eq!(
+        core.warmup_status("127.0.0.1".parse().unwrap())
+            .await
+            .unwrap(),
+        Some(WarmupStatus {
+            address: "127.0.0.1".parse().unwrap(),
+            day: 0,
+            days: 2,
+            limit: Some(1),
+            sent: 1,
+        })
+    );
+
+    // The daily limit was reached and there are no other IPs available
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let message = local.queue_receiver.last_queued_message().await;
+    let status = message.domains[0].status.to_string();
+    assert!(status.contains("warm-up limit"), "Message: {:?}", message);
+    remote.queue_receiver.assert_no_events();
+
+    // Excess volume spills over to established IPs
+    session
+        .send_message(
+            "jane@spill.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    remote.queue_receiver.expect_message().await;
+    assert_eq!(
+        core.warmup_status("127.0.0.1".parse().unwrap())
+            .await
+            .unwrap()
+            .unwrap()
+            .sent,
+        1
+    );
+}
@@ -4,7 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{fs, path::PathBuf, time::SystemTime};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 
 use smtp_proto::{Response, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_SUCCESS};
 use store::write::now;
@@ -160,6 +164,122 @@ async fn generate_dsn() {
     assert_eq!(queue.len(), 4);
 }
 
+const CONFIG_DELAY: &str = r#"
+[report]
+submitter = "'mx.example.org'"
+
+[report.dsn]
+from-name = "'Mail Delivery Subsystem'"
+from-address = "'MAILER-DAEMON@example.org'"
+sign = "['rsa']"
+
+[report.dsn.delay]
+max-count = 1
+coalesce = [{if = "sender_domain == 'foobar.org'", then = true},
+            {else = false}]
+
+"#;
+
+#[tokio::test]
+async fn delayed_dsn_policy() {
+    // Enable logging
+    crate::enable_logging();
+
+    let flags = RCPT_NOTIFY_FAILURE | RCPT_NOTIFY_DELAY;
+    let temp_fail = Status::TemporaryFailure(Error::ConnectionError(ErrorDetails {
+        entity: "mx.domain.org".to_string(),
+        details: "Connection timeout".to_string(),
+    }));
+    let mut message = Message {
+        size: 0,
+        queue_id: 0,
+        span_id: 0,
+        created: now(),
+        return_path: "sender@foobar.org".to_string(),
+        return_path_lcase: "sender@foobar.org".to_string(),
+        return_path_domain: "foobar.org".to_string(),
+        recipients: vec![
+            Recipient {
+                domain_idx: 0,
+                address: "jane@example.org".to_string(),
+                address_lcase: "jane@example.org".to_string(),
+                status: Status::Scheduled,
+                flags,
+                orcpt: None,
+            },
+            Recipient {
+                domain_idx: 1,
+                address: "john@example.net".to_string(),
+                address_lcase: "john@example.net".to_string(),
+                status: Status::Scheduled,
+                flags,
+                orcpt: None,
+            },
+        ],
+        domains: vec![
+            Domain {
+                domain: "example.org".to_string(),
+                retry: Schedule::now(),
+                notify: Schedule::now(),
+                expires: now() + 86400,
+                status: temp_fail.clone(),
+            },
+            Domain {
+                domain: "example.net".to_string(),
+                retry: Schedule::now(),
+                notify: Schedule::later(Duration::from_secs(3600)),
+                expires: now() + 86400,
+                status: temp_fail,
+            },
+        ],
+        flags: 0,
+        env_id: None,
+        priority: 0,
+        blob_hash: BlobHash::from("Subject: test\r\n\r\ntest\r\n".as_bytes()),
+        quota_keys: vec![],
+    };
+
+    // Load config
+    let mut local =
+        TestSMTP::new("smtp_dsn_delay_test", CONFIG_DELAY.to_string() + SIGNATURES).await;
+    let core = local.build_smtp();
+    let qr = &mut local.queue_receiver;
+    qr.blob_store
+        .put_blob(
+            message.blob_hash.as_slice(),
+            "Subject: test\r\n\r\ntest\r\n".as_bytes(),
+        )
+        .await
+        .unwrap();
+
+    // Both domains should be reported in a single delay DSN
+    core.send_dsn(&mut message).await;
+    let dsn_message = qr.expect_message().await;
+    let dsn = String::from_utf8(
+        qr.blob_store
+            .get_blob(dsn_message.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(dsn.contains("<jane@example.org>"), "{dsn}");
+    assert!(dsn.contains("<john@example.net>"), "{dsn}");
+    assert_eq!(message.delay_dsn_count(), 1);
+    assert_eq!(message.domains[0].notify.due, message.domains[1].notify.due);
+    qr.assert_no_events();
+
+    // The maximum number of delay DSNs was reached
+    for domain in &mut message.domains {
+        domain.notify.due = now();
+    }
+    core.send_dsn(&mut message).await;
+    qr.assert_no_events();
+    for domain in &message.domains {
+        assert_eq!(domain.notify.due, domain.expires + 10);
+    }
+}
+
 impl QueueReceiver {
     async fn compare_dsn(&self, message: Message, test: &str) {
         let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -8,8 +8,16 @@ use std::time::Duration;
 
 use mail_auth::hickory_resolver::proto::op::ResponseCode;
 
-use smtp::queue::{spool::SmtpSpool, Domain, Message, Schedule, Status};
-use store::write::now;
+use smtp::queue::{
+    coordinator::{queue_owner, QueueCoordination, QueueMembers},
+    format::{SpoolMigrate, SpooledMessage},
+    spool::SmtpSpool,
+    Domain, Message, Schedule, Status,
+};
+use store::{
+    write::{now, BatchBuilder, Bincode, QueueClass, ValueClass},
+    Serialize, ValueKey,
+};
 
 use crate::smtp::TestSMTP;
 
@@ -65,6 +73,107 @@ async fn queue_due() {
     qr.assert_queue_is_empty().await;
 }
 
+#[tokio::test]
+async fn spool_migration() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_spool_migration_test", CONFIG).await;
+    let core = local.build_smtp();
+
+    // Write a message using the unversioned spool format
+    let mut message = new_message(0);
+    message.domains.push(domain("a", 1, 2, 3));
+    let mut batch = BatchBuilder::new();
+    batch.set(
+        ValueClass::Queue(QueueClass::Message(0)),
+        Bincode::new(message).serialize(),
+    );
+    core.store().write(batch.build()).await.unwrap();
+    let key = ValueKey::from(ValueClass::Queue(QueueClass::Message(0)));
+    assert!(!core
+        .store()
+        .get_value::<SpooledMessage>(key.clone())
+        .await
+        .unwrap()
+        .unwrap()
+        .is_current());
+
+    // Legacy messages are readable before and after migration
+    assert_eq!(core.read_message(0).await.unwrap().domain("a").retry.due, 1);
+    let result = core.migrate_spool().await.unwrap();
+    assert_eq!((result.total, result.migrated, result.failed), (1, 1, 0));
+    assert!(core
+        .store()
+        .get_value::<SpooledMessage>(key)
+        .await
+        .unwrap()
+        .unwrap()
+        .is_current());
+    assert_eq!(core.read_message(0).await.unwrap().domain("a").retry.due, 1);
+
+    // Running it again is a no-op
+    let result = core.migrate_spool().await.unwrap();
+    assert_eq!((result.total, result.migrated, result.failed), (1, 0, 0));
+}
+
+#[tokio::test]
+async fn queue_coordinator() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new(
+        "smtp_queue_coordinator_test",
+        CONFIG.to_string() + "\n[queue.coordinator]\nenable = true\n",
+    )
+    .await;
+    let core = local.build_smtp();
+    let node_id = core.core.network.node_id;
+
+    // Joining the cluster makes this node the owner of every message
+    let mut members = QueueMembers::new(node_id);
+    assert!(members.refresh(&core).await);
+    assert_eq!(members.members, vec![node_id]);
+    assert!(!members.is_heartbeat_due());
+    assert!((0..100).all(|queue_id| members.is_owner(queue_id)));
+    assert!(!members.refresh(&core).await);
+
+    // Leaving clears the member list until the next heartbeat
+    members.leave(&core).await;
+    assert!(members.members.is_empty());
+    assert!(members.is_heartbeat_due());
+
+    // Heartbeats register the node again
+    assert_eq!(core.queue_heartbeat().await.unwrap(), vec![node_id]);
+    core.queue_leave().await;
+}
+
+#[test]
+fn queue_partitioning() {
+    assert_eq!(queue_owner(&[], 1), None);
+    assert_eq!(queue_owner(&[7], 1), Some(7));
+
+    // Messages are spread across all members
+    let members = [1, 2, 3];
+    let mut counts = [0usize; 3];
+    for queue_id in 0..3000 {
+        let owner = queue_owner(&members, queue_id).unwrap();
+        counts[members.iter().position(|id| *id == owner).unwrap()] += 1;
+    }
+    assert!(counts.iter().all(|count| *count > 800), "{counts:?}");
+
+    // Only the messages owned by a departing node are reassigned
+    for queue_id in 0..3000 {
+        let owner = queue_owner(&members, queue_id).unwrap();
+        let new_owner = queue_owner(&[1, 3], queue_id).unwrap();
+        if owner != 2 {
+            assert_eq!(owner, new_owner);
+        } else {
+            assert_ne!(new_owner, 2);
+        }
+    }
+}
+
 #[test]
 fn delivery_events() {
     let mut message = new_message(0);
@@ -8,6 +8,8 @@ use std::time::Duration;
 
 use crate::smtp::{inbound::TestQueueEvent, session::TestSession, TestSMTP};
 
+use smtp::queue::suppression::{SuppressionList, SuppressionReason};
+
 use store::{
     write::{ReportClass, ValueClass},
     IterateParams, ValueKey,
@@ -22,6 +24,7 @@ messages = 100
 
 [report.analysis]
 addresses = ["reports@*", "*@dmarc.foobar.org", "feedback@foobar.org"]
+feedback-loop = ["fbl@foobar.org"]
 forward = false
 store = "1s"
 "#;
@@ -63,8 +66,48 @@ async fn report_analyze() {
             ac += 1;
         }
     }
+    session
+        .send_message(
+            "bounces@foobar.org",
+            &["fbl@foobar.org"],
+            "report:fbl1",
+            "250",
+        )
+        .await;
+    qr.assert_no_events();
+    total_reports_received += 1;
     tokio::time::sleep(Duration::from_millis(200)).await;
 
+    // Complaints should add the recipients to the sender's suppression list
+    assert_eq!(
+        local
+            .server
+            .is_suppressed("somespammer@example.net", "user@example.com")
+            .await
+            .unwrap(),
+        Some(SuppressionReason::Complaint)
+    );
+
+    // Redacted complaints are correlated using VERP
+    for sender in ["bounces@foobar.org", "bounces+jane=example.org@foobar.org"] {
+        assert_eq!(
+            local
+                .server
+                .is_suppressed(sender, "jane@example.org")
+                .await
+                .unwrap(),
+            Some(SuppressionReason::Complaint)
+        );
+    }
+    assert_eq!(
+        local
+            .server
+            .is_suppressed("bounces@foobar.org", "user@example.com")
+            .await
+            .unwrap(),
+        None
+    );
+
     //let c = tokio::time::sleep(Duration::from_secs(86400)).await;
 
     // Purging the database shouldn't remove the reports
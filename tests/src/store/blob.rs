@@ -6,6 +6,7 @@
 
 use ahash::AHashMap;
 use store::{
+    backend::fs::FsStore,
     write::{blob::BlobQuota, now, BatchBuilder, BlobOp},
     BlobClass, BlobStore, Serialize, Stores,
 };
@@ -25,6 +26,9 @@ pub async fn blob_tests() {
         test_store(blob_store.clone()).await;
     }
 
+    println!("Testing degraded blob store...");
+    test_degraded_store(&temp_dir).await;
+
     for (store_id, store) in stores.stores {
         println!("Testing blob management on store {}...", store_id);
 
@@ -403,6 +407,37 @@ pub async fn blob_tests() {
     temp_dir.delete();
 }
 
+async fn test_degraded_store(temp_dir: &TempDir) {
+    let path = temp_dir.path.join("degraded");
+    let mut config = Config::new(format!(
+        "[store.degraded]\ntype = \"fs\"\npath = \"{}\"\n",
+        path.to_str().unwrap()
+    ))
+    .unwrap();
+    let store: BlobStore = FsStore::open(&mut config, ("store", "degraded"))
+        .await
+        .unwrap()
+        .into();
+    let hash = BlobHash::from(b"degraded".as_slice());
+    store.put_blob(hash.as_slice(), b"degraded").await.unwrap();
+    assert!(!store.is_degraded());
+
+    // Replace the store directory with a file so that writes fail
+    std::fs::remove_dir_all(&path).unwrap();
+    std::fs::write(&path, b"").unwrap();
+    let err = store.put_blob(b"abc", b"abc").await.unwrap_err();
+    assert!(err.is_unavailable());
+    assert!(store.is_degraded());
+    assert!(!store.is_available());
+
+    // Requests fail early while the backend is down
+    assert!(store
+        .get_blob(hash.as_slice(), 0..usize::MAX)
+        .await
+        .unwrap_err()
+        .is_unavailable());
+}
+
 async fn test_store(store: BlobStore) {
     // Test small blob
     const DATA: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit. Fusce erat nisl, dignissim a porttitor id, varius nec arcu. Sed mauris.";